@@ -0,0 +1,130 @@
+use cargo::core::Workspace;
+use cargo::core::dependency::Kind;
+use cargo::ops::{self, DepSource};
+use cargo::util::{CargoError, CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_dep: Vec<String>,
+    flag_package: Option<String>,
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_optional: bool,
+    flag_dev: bool,
+    flag_build: bool,
+    flag_target: Option<String>,
+    flag_path: Option<String>,
+    flag_git: Option<String>,
+    flag_branch: Option<String>,
+    flag_tag: Option<String>,
+    flag_rev: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Add dependencies to a Cargo.toml manifest file
+
+Usage:
+    cargo add [options] <dep>...
+    cargo add -h | --help
+
+Options:
+    -h, --help               Print this message
+    -p SPEC, --package SPEC  Package to add the dependencies to
+    --features FEATURES      Space or comma separated list of features to enable
+    --no-default-features    Disable the default features of the dependencies
+    --optional               Mark the dependencies as optional
+    --dev                    Add them to dev-dependencies
+    --build                  Add them to build-dependencies
+    --target TARGET          Add them for the given target triple or cfg() only
+    --path PATH              Add the package at PATH
+    --git URL                Add the package from the git repository at URL
+    --branch BRANCH          Branch to use with --git
+    --tag TAG                Tag to use with --git
+    --rev SHA                Specific commit to use with --git
+    --manifest-path PATH     Path to the manifest to edit
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+Each <dep> is the name of a package, optionally followed by `@` and a version
+requirement, like `serde@1.0`. Without one, the newest version in the registry
+is added, unless the dependency comes from --path or --git.
+
+Adding a dependency which is already there updates it: a new source or version
+replaces the old one, features are added to those already enabled, and its
+other settings are kept. The rest of the manifest, comments and formatting
+included, is left as it was.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+
+    let kind = match (options.flag_dev, options.flag_build) {
+        (true, true) => {
+            let err = CargoError::from("`--dev` and `--build` can't be used together");
+            return Err(err.into())
+        }
+        (true, false) => Kind::Development,
+        (false, true) => Kind::Build,
+        (false, false) => Kind::Normal,
+    };
+    let reference = [&options.flag_branch, &options.flag_tag, &options.flag_rev];
+    let references = reference.iter().filter(|r| r.is_some()).count();
+    let source = match (&options.flag_path, &options.flag_git) {
+        (&Some(_), &Some(_)) => {
+            let err = CargoError::from("`--path` and `--git` can't be used together");
+            return Err(err.into())
+        }
+        (&Some(ref path), &None) => DepSource::Path(path),
+        (&None, &Some(ref url)) => {
+            if references > 1 {
+                let err = "only one of `--branch`, `--tag` and `--rev` can be used";
+                return Err(CargoError::from(err).into())
+            }
+            DepSource::Git {
+                url: url,
+                branch: options.flag_branch.as_ref().map(|s| &s[..]),
+                tag: options.flag_tag.as_ref().map(|s| &s[..]),
+                rev: options.flag_rev.as_ref().map(|s| &s[..]),
+            }
+        }
+        (&None, &None) => {
+            if references > 0 {
+                let err = "`--branch`, `--tag` and `--rev` can only be used with `--git`";
+                return Err(CargoError::from(err).into())
+            }
+            DepSource::Registry
+        }
+    };
+
+    let root = find_root_manifest_for_wd(options.flag_manifest_path.clone(), config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::add(&ws, &ops::AddOptions {
+        package: options.flag_package.as_ref().map(|s| &s[..]),
+        deps: &options.arg_dep,
+        source: source,
+        kind: kind,
+        target: options.flag_target.as_ref().map(|s| &s[..]),
+        features: &options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        optional: options.flag_optional,
+    })?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_manifest_path: Option<String>,
+    flag_database: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Check locked dependencies against a database of known vulnerabilities
+
+Usage:
+    cargo audit [options]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest to audit dependencies for
+    --database URL           URL of the advisory database to use
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+This command requires that a `Cargo.lock` already exists. The advisory
+database is fetched from the URL given with --database or the `audit.database`
+configuration key; with --frozen the copy fetched last is used instead.
+
+The database is a JSON object with a list of `advisories`. Each advisory has
+an `id`, the name of the affected `package`, a `title`, and optionally a
+`severity` and a `url`. Registry packages are affected unless their version
+matches one of the requirements listed in `patched` or `unaffected`, and git
+packages are affected if their locked revision is listed in `revisions`, as at
+least 7 hex digits of the commit id.
+
+Every locked package an advisory applies to is reported with the advisory's
+severity and the chain of dependencies pulling it in, and the command fails if
+there are any.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::audit(&ws, &ops::AuditOptions {
+        database: options.flag_database.as_ref().map(|s| &s[..]),
+    })?;
+    Ok(())
+}
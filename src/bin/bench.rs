@@ -32,6 +32,7 @@ pub struct Options {
     flag_no_fail_fast: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_refresh: bool,
     arg_args: Vec<String>,
     flag_all: bool,
     flag_exclude: Vec<String>,
@@ -57,7 +58,7 @@ Options:
     --bench NAME ...             Benchmark only the specified bench target
     --benches                    Benchmark all benches
     --all-targets                Benchmark all targets (default)
-    --no-run                     Compile, but don't run benchmarks
+    --no-run                     Compile, but don't run benchmarks, printing their paths
     -p SPEC, --package SPEC ...  Package to run benchmarks for
     --all                        Benchmark all packages in the workspace
     --exclude SPEC ...           Exclude packages from the benchmark
@@ -74,6 +75,7 @@ Options:
     --no-fail-fast               Run all benchmarks regardless of failure
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --refresh                    Update registry indexes even if recently updated
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 All of the trailing arguments are passed to the benchmark binaries generated
@@ -105,6 +107,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
@@ -118,6 +121,8 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         no_run: options.flag_no_run,
         no_fail_fast: options.flag_no_fail_fast,
         only_doc: false,
+        // Benchmarks running side by side would skew each other's timings.
+        test_jobs: 1,
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
@@ -137,6 +142,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
             message_format: options.flag_message_format,
             target_rustdoc_args: None,
             target_rustc_args: None,
+            dry_run: false,
+            timings: false,
+            all_warnings: false,
+            deny_warnings: false,
+            size_report: false,
+            reproducible: false,
         },
     };
 
@@ -4,6 +4,7 @@ use cargo::core::Workspace;
 use cargo::ops::{self, CompileOptions, MessageFormat, Packages};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
 use cargo::util::{CliResult, Config};
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -29,7 +30,15 @@ pub struct Options {
     flag_bench: Vec<String>,
     flag_benches: bool,
     flag_all_targets: bool,
+    flag_dry_run: bool,
+    flag_timings: bool,
+    flag_all_warnings: bool,
+    flag_deny_warnings: bool,
+    flag_size_report: bool,
+    flag_reproducible: bool,
     flag_locked: bool,
+    flag_refresh: bool,
+    flag_require_signatures: bool,
     flag_frozen: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
@@ -64,6 +73,12 @@ Options:
     --all-features               Build all available features
     --no-default-features        Do not build the `default` feature
     --target TRIPLE              Build for the target triple
+    --dry-run                    List what would be rebuilt without building it
+    --timings                    Report how long each unit took to build
+    --size-report                Report the size of the artifacts of a release build
+    --all-warnings               Show warnings repeated by several targets each time
+    --deny-warnings              Fail if a workspace member has warnings
+    --reproducible               Build independently of paths and time, and verify it
     --manifest-path PATH         Path to the manifest to compile
     -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet                  No output printed to stdout
@@ -71,6 +86,8 @@ Options:
     --message-format FMT         Error format: human, json [default: human]
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --refresh                    Update registry indexes even if recently updated
+    --require-signatures         Require downloaded packages to be signed by a trusted key
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 If the --package argument is given, then SPEC is a package id specification
@@ -85,8 +102,59 @@ Note that `--exclude` has to be specified in conjunction with the `--all` flag.
 Compilation can be configured via the use of profiles which are configured in
 the manifest. The default profile for this command is `dev`, but passing
 the --release flag will use the `release` profile instead.
+
+With --dry-run every unit of work that would be rebuilt is listed along with
+why its fingerprint is stale, and nothing is compiled. Dependencies still need
+to be resolved, so a missing lock file or source may still be fetched.
+
+With --timings a summary of the slowest units and the critical path through the
+build is printed once it finishes, and a full report is written to
+`target/cargo-timings/cargo-timing.html` (and `.json`).
+
+With --size-report (or `build.size-report = true`) a release build prints the
+size of each binary and library it produced, and how it changed since the last
+report for the same target. Other builds don't print it.
+
+A warning is only shown the first time it's emitted, so a dependency built for
+both the host and the target, or a module shared by several targets, doesn't
+repeat it. The number of warnings and of duplicates suppressed is printed once
+the build finishes, --all-warnings shows every one of them instead. With
+--deny-warnings (or `build.warnings = "deny"`) a package of the workspace with
+warnings fails to build, while dependencies are free to have them.
+
+With --reproducible the paths rustc embeds are remapped to be relative to each
+package and the target directory, `SOURCE_DATE_EPOCH` is set to 0 (unless it's
+already set) for rustc and build scripts, and dependencies are passed in a
+fixed order. Everything is then built a second time in
+`target/reproducible-check`, and the build fails if an artifact differs between
+the two, naming the first artifact and byte that do.
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo build --release",
+         "Build the current package with optimizations."),
+        ("cargo build --all --exclude foo",
+         "Build every package in the workspace except `foo`."),
+        ("cargo build --features \"serde json\"",
+         "Build with the `serde` and `json` features enabled."),
+        ("cargo build --target x86_64-unknown-linux-musl",
+         "Cross-compile for another target."),
+    ],
+    config: &[
+        ("build.jobs", "Number of parallel jobs, like `--jobs`"),
+        ("build.target", "Target triple to build for, like `--target`"),
+        ("build.target-dir", "Directory where all artifacts are placed"),
+        ("build.rustflags", "Extra flags passed to every rustc invocation"),
+        ("build.unify-dev-features", "Whether dev-dependency features are enabled"),
+        ("build.shared-cache", "Whether dependencies are compiled once for all projects"),
+        ("build.warnings", "`deny` to fail on warnings, like `--deny-warnings`"),
+        ("build.size-report", "Whether release builds report sizes, like `--size-report`"),
+        ("signatures.require", "Require signed dependencies, like `--require-signatures`"),
+        ("target.<triple>.linker", "Linker to use for <triple>"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     debug!("executing; cmd=cargo-build; args={:?}",
            env::args().collect::<Vec<_>>());
@@ -96,6 +164,8 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
+    config.set_require_signatures(options.flag_require_signatures);
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
@@ -124,6 +194,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        dry_run: options.flag_dry_run,
+        timings: options.flag_timings,
+        all_warnings: options.flag_all_warnings,
+        deny_warnings: options.flag_deny_warnings,
+        size_report: options.flag_size_report,
+        reproducible: options.flag_reproducible,
     };
 
     ops::compile(&ws, &opts)?;
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
+
+#[derive(Deserialize)]
+pub struct Options {
+    cmd_gc: bool,
+    cmd_clean: bool,
+    flag_unused_for: Option<u64>,
+    flag_max_size: Option<String>,
+    flag_older_than: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Inspect Cargo's global cache
+
+Usage:
+    cargo cache stats [options]
+    cargo cache gc [options]
+    cargo cache clean [options]
+
+Options:
+    -h, --help               Print this message
+    --unused-for DAYS        Only remove what hasn't been used for this long
+    --max-size SIZE          Remove the least recently used entries until the
+                             cache takes up no more than SIZE, e.g. `10GB`
+    --older-than AGE         Remove the entries which haven't been used for AGE,
+                             e.g. `30d`
+    --manifest-path PATH     Path to the manifest of the workspace to include
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+The `stats` subcommand prints how many git databases, git checkouts, registry
+indices, downloaded crates and unpacked crate sources are in the cache along
+with how much space each of them takes up.
+
+With `--verbose` inside a workspace, the hit rate of its fingerprint cache is
+printed as well, that is how many units have been found fresh by builds in its
+target directory as opposed to having to be rebuilt. Builds only keep count of
+that with `build.fingerprint-stats = true` in the configuration.
+
+The `gc` subcommand frees up space by removing git checkouts and unpacked
+crate sources. Both are recreated from the git databases and downloaded crates
+the next time they're needed, without having to access the network. It waits
+for any builds which are reading from the cache to finish first. With
+`--unused-for`, only the checkouts and sources which haven't been used for the
+given number of days are removed.
+
+The `clean` subcommand removes the least recently used entries of the cache,
+including git databases and downloaded crates, which have to be downloaded
+again the next time they're needed. With `--older-than`, every entry which
+hasn't been used for the given age is removed; ages are a number followed by
+`s`, `m`, `h`, `d` or `w`. With `--max-size`, entries are removed oldest first
+until the cache fits within the given size, a number followed by a unit like
+`MB`, `GB` or `GiB`. At least one of the two has to be given. Like `gc`, it
+waits for any builds which are reading from the cache to finish first.
+
+Cargo's home directory has a `cache-manifest.json` recording where each entry
+of the cache came from and when it was last used, along with a `CACHE-README`
+describing the layout of the cache. The `stats` subcommand also prints how
+much hasn't been used for 30 days according to the manifest.
+";
+
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo cache stats",
+         "Show how much space the global cache takes up."),
+        ("cargo cache gc",
+         "Remove git checkouts and unpacked crate sources, which Cargo can \
+          recreate without the network."),
+        ("cargo cache clean --max-size 10GB",
+         "Remove the least recently used entries until the cache takes up no \
+          more than 10GB."),
+        ("cargo cache clean --older-than 30d",
+         "Remove everything which hasn't been used for 30 days."),
+    ],
+    config: &[
+        ("git.checkout-strategy", "How git dependencies are checked out"),
+        ("build.fingerprint-stats", "Whether builds keep count of fresh and rebuilt units"),
+    ],
+};
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    if options.cmd_gc {
+        let unused_for = options.flag_unused_for.map(|days| {
+            Duration::from_secs(days * 24 * 60 * 60)
+        });
+        ops::cache_gc(config, unused_for)?;
+        return Ok(())
+    }
+    if options.cmd_clean {
+        ops::cache_clean(config, &ops::CacheCleanOptions {
+            max_size: options.flag_max_size.as_ref().map(|s| &s[..]),
+            older_than: options.flag_older_than.as_ref().map(|s| &s[..]),
+        })?;
+        return Ok(())
+    }
+    let explicit = options.flag_manifest_path.is_some();
+    let ws = match find_root_manifest_for_wd(options.flag_manifest_path, config.cwd()) {
+        Ok(root) => Some(Workspace::new(&root, config)?),
+        Err(..) if !explicit => None,
+        Err(e) => return Err(e.into()),
+    };
+    ops::cache_stats(config, ws.as_ref())?;
+    Ok(())
+}
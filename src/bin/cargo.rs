@@ -9,11 +9,11 @@ extern crate serde_derive;
 extern crate serde_json;
 
 use std::collections::BTreeSet;
-use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use cargo::cli::GlobalArgs;
 use cargo::core::shell::{Shell, Verbosity};
 use cargo::util::{self, CliResult, lev_distance, Config, CargoResult, CargoError, CargoErrorKind};
 use cargo::util::CliError;
@@ -34,7 +34,8 @@ pub struct Flags {
     flag_z: Vec<String>,
 }
 
-const USAGE: &'static str = "
+fn usage() -> String {
+    format!("
 Rust's package manager
 
 Usage:
@@ -42,16 +43,12 @@ Usage:
     cargo [options]
 
 Options:
-    -h, --help          Display this message
-    -V, --version       Print version info and exit
-    --list              List installed commands
-    --explain CODE      Run `rustc --explain CODE`
-    -v, --verbose ...   Use verbose output (-vv very verbose/build.rs output)
-    -q, --quiet         No output printed to stdout
-    --color WHEN        Coloring: auto, always, never
-    --frozen            Require Cargo.lock and cache are up to date
-    --locked            Require Cargo.lock is up to date
-    -Z FLAG ...         Unstable (nightly-only) flags to Cargo
+    -h, --help                Display this message
+    -V, --version             Print version info and exit
+    --list                    List installed commands
+    --explain CODE            Run `rustc --explain CODE`
+{}
+The options from `-v` on may also be given after the command.
 
 Some common cargo commands are (see all commands with --list):
     build       Compile the current project
@@ -70,66 +67,82 @@ Some common cargo commands are (see all commands with --list):
     uninstall   Uninstall a Rust binary
 
 See 'cargo help <command>' for more information on a specific command.
-";
+
+Exit status:
+    0           Success
+    101         Compilation failed, tests failed or any other error
+    102         Dependencies could not be resolved
+    103         A network operation failed
+    104         Internal error in Cargo
+    130         Interrupted
+", cargo::cli::global_flags_usage(24))
+}
 
 fn main() {
-    env_logger::init().unwrap();
+    init_logging();
 
     let mut config = match Config::default() {
         Ok(cfg) => cfg,
         Err(e) => {
             let mut shell = Shell::new();
-            cargo::exit_with_error(e.into(), &mut shell)
+            cargo::cli::exit_with_error(e.into(), &mut shell)
         }
     };
 
     let result = (|| {
-        let args: Vec<_> = try!(env::args_os()
-            .map(|s| {
-                s.into_string().map_err(|s| {
-                    CargoError::from(format!("invalid unicode in argument: {:?}", s))
-                })
-            })
-            .collect());
-        let rest = &args;
-        cargo::call_main_without_stdin(execute, &mut config, USAGE, rest, true)
+        let global = command_line()?;
+        config.set_cli_config(&global.config)?;
+        cargo::cli::call_main_without_stdin(execute, &mut config, &usage(), &global.args, true)
     })();
 
     match result {
-        Err(e) => cargo::exit_with_error(e, &mut *config.shell()),
+        Err(e) => cargo::cli::exit_with_error(e, &mut *config.shell()),
         Ok(()) => {}
     }
 }
 
 macro_rules! each_subcommand{
     ($mac:ident) => {
+        $mac!(add);
+        $mac!(audit);
         $mac!(bench);
         $mac!(build);
+        $mac!(cache);
         $mac!(check);
         $mac!(clean);
+        $mac!(completions);
         $mac!(doc);
+        $mac!(features);
         $mac!(fetch);
         $mac!(generate_lockfile);
         $mac!(git_checkout);
+        $mac!(graph);
         $mac!(help);
+        $mac!(info);
         $mac!(init);
         $mac!(install);
+        $mac!(license);
         $mac!(locate_project);
         $mac!(login);
         $mac!(metadata);
         $mac!(new);
+        $mac!(outdated);
         $mac!(owner);
         $mac!(package);
         $mac!(pkgid);
         $mac!(publish);
         $mac!(read_manifest);
+        $mac!(remove);
         $mac!(run);
         $mac!(rustc);
         $mac!(rustdoc);
+        $mac!(sbom);
         $mac!(search);
         $mac!(test);
+        $mac!(tree);
         $mac!(uninstall);
         $mac!(update);
+        $mac!(verify);
         $mac!(verify_project);
         $mac!(version);
         $mac!(yank);
@@ -196,7 +209,7 @@ fn execute(flags: Flags, config: &mut Config) -> CliResult {
         "" | "help" if flags.arg_args.is_empty() => {
             config.shell().set_verbosity(Verbosity::Verbose);
             let args = &["cargo".to_string(), "-h".to_string()];
-            return cargo::call_main_without_stdin(execute, config, USAGE, args, false);
+            return cargo::cli::call_main_without_stdin(execute, config, &usage(), args, false);
         }
 
         // For `cargo help -h` and `cargo help --help`, print out the help
@@ -206,20 +219,28 @@ fn execute(flags: Flags, config: &mut Config) -> CliResult {
         }
 
         // For `cargo help foo`, print out the usage message for the specified
-        // subcommand by executing the command with the `-h` flag.
-        "help" => vec!["cargo".to_string(), flags.arg_args[0].clone(), "-h".to_string()],
+        // subcommand by executing the command with the `-h` flag, unless it
+        // has some extended help to print along with it.
+        "help" => {
+            let command = &flags.arg_args[0];
+            if let Some(extended) = help::extended_help(command) {
+                let usage = builtin_usages().into_iter()
+                    .find(|&(ref name, _)| name == command)
+                    .map(|(_, usage)| usage)
+                    .unwrap();
+                help::print_extended_help(usage, extended);
+                return Ok(())
+            }
+            vec!["cargo".to_string(), command.clone(), "-h".to_string()]
+        }
 
         // For all other invocations, we're of the form `cargo foo args...`. We
         // use the exact environment arguments to preserve tokens like `--` for
-        // example.
+        // example, with the global flags moved after the command.
         _ => {
-            let mut default_alias = HashMap::new();
-            default_alias.insert("b", "build".to_string());
-            default_alias.insert("t", "test".to_string());
-            default_alias.insert("r", "run".to_string());
-            let mut args: Vec<String> = env::args().collect();
-            if let Some(new_command) = default_alias.get(&args[1][..]) {
-                args[1] = new_command.clone();
+            let mut args = command_line()?.args;
+            if let Some(new_command) = default_alias(&args[1]) {
+                args[1] = new_command.to_string();
             }
             args
         }
@@ -229,23 +250,10 @@ fn execute(flags: Flags, config: &mut Config) -> CliResult {
         return r;
     }
 
-    let alias_list = aliased_command(config, &args[1])?;
-    let args = match alias_list {
-        Some(alias_command) => {
-            let chain = args.iter()
-                .take(1)
-                .chain(alias_command.iter())
-                .chain(args.iter().skip(2))
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>();
-            if let Some(r) = try_execute_builtin_command(config, &chain) {
-                return r;
-            } else {
-                chain
-            }
-        }
-        None => args,
-    };
+    let args = expand_aliases(config, args)?;
+    if let Some(r) = try_execute_builtin_command(config, &args) {
+        return r;
+    }
 
     execute_external_subcommand(config, &args[1], &args)
 }
@@ -254,7 +262,7 @@ fn try_execute_builtin_command(config: &mut Config, args: &[String]) -> Option<C
     macro_rules! cmd {
         ($name:ident) => (if args[1] == stringify!($name).replace("_", "-") {
             config.shell().set_verbosity(Verbosity::Verbose);
-            let r = cargo::call_main_without_stdin($name::execute,
+            let r = cargo::cli::call_main_without_stdin($name::execute,
                                                    config,
                                                    $name::USAGE,
                                                    &args,
@@ -267,6 +275,78 @@ fn try_execute_builtin_command(config: &mut Config, args: &[String]) -> Option<C
     None
 }
 
+/// The arguments cargo was invoked with, see `GlobalArgs::parse`.
+fn command_line() -> CargoResult<GlobalArgs> {
+    let args: Vec<_> = env::args_os()
+        .map(|s| {
+            s.into_string().map_err(|s| {
+                CargoError::from(format!("invalid unicode in argument: {:?}", s))
+            })
+        })
+        .collect::<CargoResult<_>>()?;
+    let usages = builtin_usages();
+    GlobalArgs::parse(&args, &|command| {
+        let command = default_alias(command).unwrap_or(command);
+        usages.iter().find(|&&(ref name, _)| name == command).map(|&(_, usage)| usage)
+    })
+}
+
+/// The name and usage message of every built-in command.
+fn builtin_usages() -> Vec<(String, &'static str)> {
+    let mut usages = Vec::new();
+    macro_rules! usage {
+        ($name:ident) => (
+            usages.push((stringify!($name).replace("_", "-"), $name::USAGE))
+        )
+    }
+    each_subcommand!(usage);
+    usages
+}
+
+/// The built-in alias for `command`, which can't be overridden.
+fn default_alias(command: &str) -> Option<&'static str> {
+    match command {
+        "b" => Some("build"),
+        "t" => Some("test"),
+        "r" => Some("run"),
+        _ => None,
+    }
+}
+
+/// Expands the command in `args` as long as it's a user-defined alias, as
+/// aliases may be defined in terms of other aliases. An alias which ends up
+/// expanding to itself is an error.
+fn expand_aliases(config: &Config, mut args: Vec<String>) -> CargoResult<Vec<String>> {
+    let builtins = builtin_usages().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    let mut expanded: Vec<String> = Vec::new();
+    while !builtins.contains(&args[1]) {
+        let alias = match aliased_command(config, &args[1])? {
+            Some(alias) => alias,
+            None => break,
+        };
+        if expanded.contains(&args[1]) {
+            expanded.push(args[1].clone());
+            return Err(CargoError::from(format!("alias `{}` expands to itself: {}",
+                                                args[1], expanded.join(" -> "))));
+        }
+        if alias.is_empty() {
+            return Err(CargoError::from(format!("alias `{}` doesn't name a command",
+                                                args[1])));
+        }
+        expanded.push(args[1].clone());
+        args = args.iter()
+                   .take(1)
+                   .chain(alias.iter())
+                   .chain(args.iter().skip(2))
+                   .cloned()
+                   .collect();
+        if let Some(new_command) = default_alias(&args[1]) {
+            args[1] = new_command.to_string();
+        }
+    }
+    Ok(args)
+}
+
 fn aliased_command(config: &Config, command: &str) -> CargoResult<Option<Vec<String>>> {
     let alias_name = format!("alias.{}", command);
     let mut result = Ok(None);
@@ -397,7 +477,27 @@ fn search_directories(config: &Config) -> Vec<PathBuf> {
     dirs
 }
 
+/// Sets up logging from `RUST_LOG` and then `CARGO_LOG`, the latter of which
+/// accepts short names like `git` or `resolver` in place of module paths.
+fn init_logging() {
+    let mut builder = env_logger::LogBuilder::new();
+    if let Ok(filters) = env::var("RUST_LOG") {
+        builder.parse(&filters);
+    }
+    if let Ok(filters) = env::var("CARGO_LOG") {
+        builder.parse(&util::logging::expand_filters(&filters));
+    }
+    builder.init().unwrap();
+}
+
 fn init_git_transports(config: &Config) {
+    // Headers configured for git hosts are sent by a transport of our own,
+    // which is only used for the URLs that need them. Its registration is
+    // unsafe for the same reasons as the one below.
+    if let Err(e) = unsafe { cargo::sources::git::register_transport(config) } {
+        debug!("failed to register the git transport for custom headers: {}", e);
+    }
+
     // Only use a custom transport if a proxy is configured, right now libgit2
     // doesn't support proxies and we have to use a custom transport in this
     // case. The custom transport, however, is not as well battle-tested.
@@ -4,6 +4,7 @@ use cargo::core::Workspace;
 use cargo::ops::{self, CompileOptions, MessageFormat, Packages};
 use cargo::util::{CliResult, CliError, Config};
 use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
 
 pub const USAGE: &'static str = "
 Check a local package and all of its dependencies for errors
@@ -33,6 +34,8 @@ Options:
     --all-features               Check all available features
     --no-default-features        Do not check the `default` feature
     --target TRIPLE              Check for the target triple
+    --all-warnings               Show warnings repeated by several targets each time
+    --deny-warnings              Fail if a workspace member has warnings
     --manifest-path PATH         Path to the manifest to compile
     -v, --verbose ...            Use verbose output
     -q, --quiet                  No output printed to stdout
@@ -40,6 +43,7 @@ Options:
     --message-format FMT         Error format: human, json [default: human]
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --refresh                    Update registry indexes even if recently updated
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 If the --package argument is given, then SPEC is a package id specification
@@ -59,6 +63,24 @@ The `--profile test` flag can be used to check unit tests with the
 `#[cfg(test)]` attribute.
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo check",
+         "Check the current package for errors without producing binaries."),
+        ("cargo check --all-targets",
+         "Also check tests, benchmarks and examples."),
+        ("cargo check --profile test",
+         "Check the library and binaries as they're compiled for tests."),
+    ],
+    config: &[
+        ("build.jobs", "Number of parallel jobs, like `--jobs`"),
+        ("build.target", "Target triple to check for, like `--target`"),
+        ("build.target-dir", "Directory where all artifacts are placed"),
+        ("build.rustflags", "Extra flags passed to every rustc invocation"),
+        ("build.warnings", "`deny` to fail on warnings, like `--deny-warnings`"),
+    ],
+};
+
 #[derive(Deserialize)]
 pub struct Options {
     flag_package: Vec<String>,
@@ -83,7 +105,10 @@ pub struct Options {
     flag_bench: Vec<String>,
     flag_benches: bool,
     flag_all_targets: bool,
+    flag_all_warnings: bool,
+    flag_deny_warnings: bool,
     flag_locked: bool,
+    flag_refresh: bool,
     flag_frozen: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
@@ -102,6 +127,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
@@ -140,6 +166,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        dry_run: false,
+        timings: false,
+        all_warnings: options.flag_all_warnings,
+        deny_warnings: options.flag_deny_warnings,
+        size_report: false,
+        reproducible: false,
     };
 
     ops::compile(&ws, &opts)?;
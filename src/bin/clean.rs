@@ -4,6 +4,7 @@ use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, Config};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -45,6 +46,19 @@ given, then all packages' artifacts are removed. For more information on SPEC
 and its format, see the `cargo help pkgid` command.
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo clean",
+         "Remove the whole target directory."),
+        ("cargo clean -p foo --release",
+         "Only remove the release artifacts of the package `foo`."),
+    ],
+    config: &[
+        ("build.target", "Target triple to clean for with `--package`, like `--target`"),
+        ("build.target-dir", "Directory which is cleaned"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     debug!("executing; cmd=cargo-clean; args={:?}", env::args().collect::<Vec<_>>());
     config.configure(options.flag_verbose,
@@ -0,0 +1,274 @@
+use std::fmt::Write;
+
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_shell: String,
+}
+
+pub const USAGE: &'static str = "
+Generate shell completions for Cargo
+
+Usage:
+    cargo completions <shell>
+    cargo completions -h | --help
+
+Options:
+    -h, --help               Print this message
+
+Prints a completion script for <shell>, which is one of `bash`, `zsh` or
+`fish`. The script is generated from the usage messages of Cargo's built-in
+commands, so it always matches the options of the cargo it came from.
+
+For example, to load completions into the current bash session:
+
+    source <(cargo completions bash)
+
+zsh finds completions on its `fpath` in files named after the function they
+define, e.g. `cargo completions zsh > ~/.zfunc/_cargo`, and fish looks for them
+in `~/.config/fish/completions/cargo.fish`.
+";
+
+/// A built-in command, as described by its usage message.
+struct Command {
+    name: String,
+    about: String,
+    subcommands: Vec<String>,
+    options: Vec<Opt>,
+}
+
+/// An option of a command, e.g. `-p SPEC, --package SPEC ...`.
+struct Opt {
+    short: Option<String>,
+    long: Option<String>,
+    takes_value: bool,
+    about: String,
+}
+
+pub fn execute(options: Options, _: &mut Config) -> CliResult {
+    let cargo = parse_usage("", ::USAGE);
+    let commands = ::builtin_usages().into_iter()
+        .map(|(name, usage)| parse_usage(&name, usage))
+        .collect::<Vec<_>>();
+    let script = match &options.arg_shell[..] {
+        "bash" => bash(&cargo, &commands),
+        "zsh" => zsh(&cargo, &commands),
+        "fish" => fish(&cargo, &commands),
+        shell => {
+            let err = format!("unsupported shell `{}`, expected one of `bash`, \
+                               `zsh` or `fish`", shell).into();
+            return Err(CliError::new(err, 101))
+        }
+    };
+    print!("{}", script);
+    Ok(())
+}
+
+/// Extracts everything worth completing from the docopt usage message of the
+/// command `name`.
+fn parse_usage(name: &str, usage: &str) -> Command {
+    let mut command = Command {
+        name: name.to_string(),
+        about: usage.lines().map(|l| l.trim()).find(|l| !l.is_empty())
+                    .unwrap_or("").to_string(),
+        subcommands: Vec::new(),
+        options: Vec::new(),
+    };
+
+    let invocation = format!("cargo {} ", name);
+    let mut in_options = false;
+    for line in usage.lines() {
+        let trimmed = line.trim();
+        if trimmed == "Options:" {
+            in_options = true;
+            continue
+        }
+        if trimmed.is_empty() {
+            in_options = false;
+            continue
+        }
+
+        // Literal words in the usage patterns, e.g. `gc` in
+        // `cargo cache gc [options]`.
+        if !name.is_empty() && trimmed.starts_with(&invocation) {
+            for word in trimmed[invocation.len()..].split_whitespace() {
+                let literal = word.chars().all(|c| c.is_lowercase() || c == '-') &&
+                              !word.starts_with('-');
+                if literal && !command.subcommands.iter().any(|s| s == word) {
+                    command.subcommands.push(word.to_string());
+                }
+            }
+        }
+
+        if !in_options || !trimmed.starts_with('-') {
+            continue
+        }
+        let (spec, about) = match trimmed.find("  ") {
+            Some(i) => (&trimmed[..i], trimmed[i..].trim()),
+            None => (trimmed, ""),
+        };
+        let mut opt = Opt {
+            short: None,
+            long: None,
+            takes_value: false,
+            about: about.to_string(),
+        };
+        for part in spec.split(", ") {
+            let mut words = part.split_whitespace();
+            let flag = match words.next() {
+                Some(flag) => flag,
+                None => continue,
+            };
+            if flag.starts_with("--") {
+                opt.long = Some(flag[2..].to_string());
+            } else {
+                opt.short = Some(flag[1..].to_string());
+            }
+            if words.next().map(|w| w != "...").unwrap_or(false) {
+                opt.takes_value = true;
+            }
+        }
+        command.options.push(opt);
+    }
+    command
+}
+
+fn flags(opt: &Opt) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(ref short) = opt.short {
+        flags.push(format!("-{}", short));
+    }
+    if let Some(ref long) = opt.long {
+        flags.push(format!("--{}", long));
+    }
+    flags
+}
+
+fn words(command: &Command) -> String {
+    let mut words = command.subcommands.clone();
+    words.extend(command.options.iter().flat_map(flags));
+    words.join(" ")
+}
+
+fn bash(cargo: &Command, commands: &[Command]) -> String {
+    let names = commands.iter().map(|c| &c.name[..]).collect::<Vec<_>>().join(" ");
+    let mut out = String::new();
+    out.push_str("_cargo()\n{\n");
+    out.push_str("\tlocal cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    out.push_str("\tlocal cmd=\"${COMP_WORDS[1]}\"\n");
+    out.push_str("\tlocal opts\n\n");
+    out.push_str("\tif [[ $COMP_CWORD -eq 1 ]]; then\n");
+    writeln!(out, "\t\topts=\"{} {}\"", words(cargo), names).unwrap();
+    out.push_str("\telse\n\t\tcase \"$cmd\" in\n");
+    for command in commands {
+        let words = if command.name == "help" {
+            names.clone()
+        } else {
+            words(command)
+        };
+        writeln!(out, "\t\t\t{}) opts=\"{}\" ;;", command.name, words).unwrap();
+    }
+    out.push_str("\t\t\t*) opts=\"\" ;;\n\t\tesac\n\tfi\n\n");
+    out.push_str("\tCOMPREPLY=( $(compgen -W \"$opts\" -- \"$cur\") )\n");
+    out.push_str("\treturn 0\n}\n");
+    out.push_str("complete -F _cargo -o default cargo\n");
+    out
+}
+
+fn zsh(cargo: &Command, commands: &[Command]) -> String {
+    fn quote(s: &str) -> String {
+        s.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]")
+    }
+    fn arguments(out: &mut String, indent: &str, command: &Command) {
+        for opt in command.options.iter() {
+            let flags = flags(opt);
+            let value = if opt.takes_value { ":value:" } else { "" };
+            for flag in flags.iter() {
+                let exclusive = if flags.len() > 1 {
+                    format!("({})", flags.join(" "))
+                } else {
+                    String::new()
+                };
+                writeln!(out, "{}'{}{}[{}]{}' \\", indent, exclusive, flag,
+                         quote(&opt.about), value).unwrap();
+            }
+        }
+        if !command.subcommands.is_empty() {
+            writeln!(out, "{}'1:subcommand:({})' \\", indent,
+                     command.subcommands.join(" ")).unwrap();
+        }
+        writeln!(out, "{}'*::args:_files'", indent).unwrap();
+    }
+
+    let mut out = String::new();
+    out.push_str("#compdef cargo\n\n_cargo() {\n");
+    out.push_str("\tlocal -a commands\n\tcommands=(\n");
+    for command in commands {
+        writeln!(out, "\t\t'{}:{}'", command.name, quote(&command.about)).unwrap();
+    }
+    out.push_str("\t)\n\n");
+    out.push_str("\tif (( CURRENT == 2 )); then\n");
+    out.push_str("\t\t_describe 'command' commands\n");
+    out.push_str("\t\t_arguments \\\n");
+    arguments(&mut out, "\t\t\t", cargo);
+    out.push_str("\t\treturn\n\tfi\n\n");
+    out.push_str("\tshift words\n\t(( CURRENT-- ))\n");
+    out.push_str("\tcase $words[1] in\n");
+    for command in commands {
+        writeln!(out, "\t\t{})", command.name).unwrap();
+        if command.name == "help" {
+            out.push_str("\t\t\t_describe 'command' commands\n");
+        } else {
+            out.push_str("\t\t\t_arguments \\\n");
+            arguments(&mut out, "\t\t\t\t", command);
+        }
+        out.push_str("\t\t\t;;\n");
+    }
+    out.push_str("\tesac\n}\n\n_cargo \"$@\"\n");
+    out
+}
+
+fn fish(cargo: &Command, commands: &[Command]) -> String {
+    fn quote(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('\'', "\\'")
+    }
+    fn options(out: &mut String, condition: &str, command: &Command) {
+        for opt in command.options.iter() {
+            write!(out, "complete -c cargo -n '{}'", condition).unwrap();
+            if let Some(ref short) = opt.short {
+                write!(out, " -s {}", short).unwrap();
+            }
+            if let Some(ref long) = opt.long {
+                write!(out, " -l {}", long).unwrap();
+            }
+            if opt.takes_value {
+                out.push_str(" -r");
+            }
+            writeln!(out, " -d '{}'", quote(&opt.about)).unwrap();
+        }
+        for subcommand in command.subcommands.iter() {
+            writeln!(out, "complete -c cargo -n '{}' -f -a {}",
+                     condition, subcommand).unwrap();
+        }
+    }
+
+    let mut out = String::new();
+    options(&mut out, "__fish_use_subcommand", cargo);
+    for command in commands {
+        writeln!(out, "complete -c cargo -n '__fish_use_subcommand' -f -a {} -d '{}'",
+                 command.name, quote(&command.about)).unwrap();
+    }
+    for command in commands {
+        let condition = format!("__fish_seen_subcommand_from {}", command.name);
+        if command.name == "help" {
+            for other in commands {
+                writeln!(out, "complete -c cargo -n '{}' -f -a {}",
+                         condition, other.name).unwrap();
+            }
+        } else {
+            options(&mut out, &condition, command);
+        }
+    }
+    out
+}
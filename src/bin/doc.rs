@@ -4,6 +4,7 @@ use cargo::core::Workspace;
 use cargo::ops::{self, MessageFormat, Packages};
 use cargo::util::{CliResult, Config};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -26,6 +27,7 @@ pub struct Options {
     flag_bins: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_refresh: bool,
     flag_all: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
@@ -59,6 +61,7 @@ Options:
     --message-format FMT         Error format: human, json [default: human]
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --refresh                    Update registry indexes even if recently updated
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 By default the documentation for the local package and all dependencies is
@@ -74,6 +77,22 @@ current package is documented. For more information on SPEC and its format, see
 the `cargo help pkgid` command.
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo doc --open",
+         "Document the current package and its dependencies, then open it."),
+        ("cargo doc --no-deps",
+         "Only document the current package."),
+        ("cargo doc --all",
+         "Document every package in the workspace."),
+    ],
+    config: &[
+        ("build.rustdoc", "The rustdoc program to use"),
+        ("build.target", "Target triple to document for, like `--target`"),
+        ("build.target-dir", "Directory where the documentation is placed"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     debug!("executing; cmd=cargo-check; args={:?}",
            env::args().collect::<Vec<_>>());
@@ -84,6 +103,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
@@ -117,6 +137,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                 deps: !options.flag_no_deps,
             },
             target_rustc_args: None,
+            dry_run: false,
+            timings: false,
+            all_warnings: false,
+            deny_warnings: false,
+            size_report: false,
+            reproducible: false,
             target_rustdoc_args: None,
         },
     };
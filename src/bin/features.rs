@@ -0,0 +1,89 @@
+use cargo::core::Workspace;
+use cargo::ops::{self, Packages};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Show the features each package is built with
+
+Usage:
+    cargo features [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package(s) to show the features for
+    --all                        Show the features for all packages in the workspace
+    --exclude SPEC ...           Exclude packages from the report
+    --features FEATURES          Space-separated list of features to also enable
+    --all-features               Enable all available features
+    --no-default-features        Do not enable the `default` feature
+    --manifest-path PATH         Path to the manifest of the package
+    -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+Features are unified across the whole dependency graph: a package is compiled
+with every feature enabled by anything that depends on it. This command prints
+each package in the graph along with the features it ends up with.
+
+Features which are only enabled by dev-dependencies are pointed out, as are
+packages which are only used by dev-dependencies. These are included in
+`cargo build` unless `build.unify-dev-features` is set to false in a Cargo
+config file, in which case they're only used when building tests, benchmarks
+and examples.
+";
+
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo features",
+         "Show the features each package of the current package's graph is built with."),
+        ("cargo features --all --no-default-features",
+         "Show the features of the whole workspace without default features."),
+    ],
+    config: &[
+        ("build.unify-dev-features", "Whether dev-dependency features are enabled"),
+    ],
+};
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    let spec = Packages::from_flags(ws.is_virtual(),
+                                    options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    ops::report_features(&ws,
+                         &options.flag_features,
+                         options.flag_all_features,
+                         options.flag_no_default_features,
+                         spec)?;
+    Ok(())
+}
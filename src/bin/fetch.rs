@@ -11,6 +11,8 @@ pub struct Options {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_refresh: bool,
+    flag_require_signatures: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -29,6 +31,8 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --refresh                Update registry indexes even if recently updated
+    --require-signatures     Require downloaded packages to be signed by a trusted key
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 
 If a lockfile is available, this command will ensure that all of the git
@@ -48,6 +52,8 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
+    config.set_require_signatures(options.flag_require_signatures);
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
     ops::fetch(&ws)?;
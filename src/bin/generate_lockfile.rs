@@ -13,6 +13,7 @@ pub struct Options {
     flag_color: Option<String>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_refresh: bool,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
 }
@@ -31,6 +32,7 @@ Options:
     --color WHEN             Coloring: auto, always, never
     --frozen                 Require Cargo.lock and cache are up to date
     --locked                 Require Cargo.lock is up to date
+    --refresh                Update registry indexes even if recently updated
     -Z FLAG ...              Unstable (nightly-only) flags to Cargo
 ";
 
@@ -42,6 +44,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
     let ws = Workspace::new(&root, config)?;
@@ -1,6 +1,6 @@
 use cargo::core::source::{Source, SourceId, GitReference};
-use cargo::sources::git::{GitSource};
-use cargo::util::{Config, CliResult, ToUrl};
+use cargo::sources::git::{self, GitSource};
+use cargo::util::{Config, CliResult};
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -41,7 +41,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      &options.flag_z)?;
     let Options { flag_url: url, flag_reference: reference, .. } = options;
 
-    let url = url.to_url()?;
+    let url = git::to_url(&url)?;
 
     let reference = GitReference::Branch(reference.clone());
     let source_id = SourceId::for_git(&url, reference)?;
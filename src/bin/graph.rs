@@ -0,0 +1,105 @@
+use cargo::core::Workspace;
+use cargo::ops::{self, GraphFormat, GraphOptions, Packages};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_format: GraphFormat,
+    flag_no_dev_dependencies: bool,
+    flag_no_build_dependencies: bool,
+    flag_depth: Option<u32>,
+    flag_focus: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Export the dependency graph of a package
+
+Usage:
+    cargo graph [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package(s) to export the dependency graph of
+    --all                        Export the dependency graph of the whole workspace
+    --exclude SPEC ...           Exclude packages from the graph
+    --features FEATURES          Space-separated list of features to also enable
+    --all-features               Enable all available features
+    --no-default-features        Do not enable the `default` feature
+    --format FMT                 Output format: dot, json [default: dot]
+    --no-dev-dependencies        Leave out dev-dependencies
+    --no-build-dependencies      Leave out build-dependencies
+    --depth N                    Only include packages up to N dependencies away
+    --focus SPEC                 Only include what leads to and comes from SPEC
+    --manifest-path PATH         Path to the manifest of the package
+    -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+The graph is printed in the GraphViz `dot` language, which can be rendered with
+e.g. `cargo graph | dot -Tsvg > graph.svg`, or as JSON with `--format json`.
+Every package is labeled with the features it's built with, and every edge with
+the version requirement and features of the dependency it comes from.
+Dev-dependencies are drawn dashed and build-dependencies dotted.
+
+With --focus, only the packages which depend on SPEC, directly or not, and the
+packages SPEC depends on are kept, which is handy to find out why a package
+ends up in a build.
+";
+
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo graph | dot -Tsvg > graph.svg",
+         "Render the dependency graph of the current package to an SVG image."),
+        ("cargo graph --focus libc --no-dev-dependencies",
+         "Show which packages pull in `libc` when building the current package."),
+        ("cargo graph --format json --depth 1",
+         "Print the direct dependencies of the current package as JSON."),
+    ],
+    config: &[],
+};
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    let spec = Packages::from_flags(ws.is_virtual(),
+                                    options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    ops::graph(&ws, &GraphOptions {
+        spec: spec,
+        features: &options.flag_features,
+        all_features: options.flag_all_features,
+        no_default_features: options.flag_no_default_features,
+        format: options.flag_format,
+        dev_deps: !options.flag_no_dev_dependencies,
+        build_deps: !options.flag_no_build_dependencies,
+        depth: options.flag_depth,
+        focus: options.flag_focus.as_ref().map(|s| &s[..]),
+    })?;
+    Ok(())
+}
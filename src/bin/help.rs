@@ -12,8 +12,58 @@ Usage:
 
 Options:
     -h, --help          Print this message
+
+Prints the usage message of <command>. For the most commonly used commands
+this is followed by some examples and the configuration keys which affect the
+command, see http://doc.crates.io/config.html for how to set them.
 ";
 
+/// Long-form help for a command, printed by `cargo help <command>` after its
+/// usage message.
+pub struct ExtendedHelp {
+    /// Example invocations along with what they do.
+    pub examples: &'static [(&'static str, &'static str)],
+    /// Configuration keys which affect the command, and how.
+    pub config: &'static [(&'static str, &'static str)],
+}
+
+/// Returns the extended help registered by `command`, if any.
+pub fn extended_help(command: &str) -> Option<&'static ExtendedHelp> {
+    match command {
+        "build" => Some(&::build::HELP),
+        "cache" => Some(&::cache::HELP),
+        "check" => Some(&::check::HELP),
+        "clean" => Some(&::clean::HELP),
+        "doc" => Some(&::doc::HELP),
+        "features" => Some(&::features::HELP),
+        "graph" => Some(&::graph::HELP),
+        "publish" => Some(&::publish::HELP),
+        "run" => Some(&::run::HELP),
+        "test" => Some(&::test::HELP),
+        "tree" => Some(&::tree::HELP),
+        "update" => Some(&::update::HELP),
+        _ => None,
+    }
+}
+
+/// Prints the usage message of a command followed by its extended help.
+pub fn print_extended_help(usage: &str, help: &ExtendedHelp) {
+    println!("{}", usage.trim());
+    if !help.examples.is_empty() {
+        println!("\nExamples:");
+        for &(command, about) in help.examples {
+            println!("\n    {}\n        {}", command, about);
+        }
+    }
+    if !help.config.is_empty() {
+        println!("\nConfiguration:\n");
+        let width = help.config.iter().map(|&(key, _)| key.len()).max().unwrap_or(0);
+        for &(key, about) in help.config {
+            println!("    {:<width$}  {}", key, about, width = width);
+        }
+    }
+}
+
 pub fn execute(_: Options, _: &mut Config) -> CliResult {
     // This is a dummy command just so that `cargo help help` works.
     // The actual delegation of help flag to subcommands is handled by the
@@ -0,0 +1,62 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_spec: String,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Show information about a package
+
+Usage:
+    cargo info [options] <spec>
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest of the package to look in
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+The <spec> is the name of a package, optionally followed by `@` and a version
+requirement, for example `serde@1.0`. Its description, license, links, features
+and dependencies are printed, along with the versions it's available in.
+
+If the package is a dependency of the current package, the version that's
+used is shown. Otherwise the newest version in the registry is, or the newest
+one which matches the version requirement.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    // Outside of a package there's only the registry to look in.
+    let root = match options.flag_manifest_path {
+        Some(path) => Some(find_root_manifest_for_wd(Some(path), config.cwd())?),
+        None => find_root_manifest_for_wd(None, config.cwd()).ok(),
+    };
+    let ws = match root {
+        Some(root) => Some(Workspace::new(&root, config)?),
+        None => None,
+    };
+    ops::info(config, ws.as_ref(), &options.arg_spec)?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use cargo::ops;
 use cargo::core::{SourceId, GitReference};
-use cargo::util::{CargoError, CliResult, Config, ToUrl};
+use cargo::sources::git;
+use cargo::util::{CargoError, CliResult, Config};
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -21,6 +22,7 @@ pub struct Options {
     flag_force: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_require_signatures: bool,
 
     arg_crate: Vec<String>,
     flag_vers: Option<String>,
@@ -70,6 +72,7 @@ Build and install options:
     --color WHEN              Coloring: auto, always, never
     --frozen                  Require Cargo.lock and cache are up to date
     --locked                  Require Cargo.lock is up to date
+    --require-signatures      Require downloaded packages to be signed by a trusted key
     -Z FLAG ...               Unstable (nightly-only) flags to Cargo
 
 This command manages Cargo's local set of installed binary crates. Only packages
@@ -110,6 +113,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_require_signatures(options.flag_require_signatures);
 
     let compile_opts = ops::CompileOptions {
         config: config,
@@ -129,11 +133,17 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                                         false),
         message_format: ops::MessageFormat::Human,
         target_rustc_args: None,
+        dry_run: false,
+        timings: false,
+        all_warnings: false,
+        deny_warnings: false,
+        size_report: false,
+        reproducible: false,
         target_rustdoc_args: None,
     };
 
     let source = if let Some(url) = options.flag_git {
-        let url = url.to_url()?;
+        let url = git::to_url(&url)?;
         let gitref = if let Some(branch) = options.flag_branch {
             GitReference::Branch(branch)
         } else if let Some(tag) = options.flag_tag {
@@ -0,0 +1,71 @@
+use cargo::core::Workspace;
+use cargo::ops::{self, LicenseFormat, Packages};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_format: LicenseFormat,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Report the licenses of a package and all of its dependencies
+
+Usage:
+    cargo license [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package(s) to report the dependencies of
+    --all                        Report the dependencies of the whole workspace
+    --exclude SPEC ...           Exclude packages from the report
+    --format FMT                 Output format: text, json, csv [default: text]
+    --manifest-path PATH         Path to the manifest of the package
+    -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+Every package in the dependency graph is listed along with the `license`,
+`license-file`, `authors` and `repository` from its manifest. Packages which
+are only used by dev-dependencies are left out, as they don't end up in what's
+built for others.
+
+If `license.allowed` is set to a list of SPDX license identifiers in a Cargo
+config file, the command fails if a dependency isn't licensed under them. A
+license expression like `MIT OR Apache-2.0` is allowed if either license is.
+The workspace members themselves aren't checked.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    let spec = Packages::from_flags(ws.is_virtual(),
+                                    options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    ops::license(&ws, &ops::LicenseOptions {
+        spec: spec,
+        format: options.flag_format,
+    })?;
+    Ok(())
+}
@@ -33,6 +33,6 @@ pub fn execute(flags: LocateProjectFlags, config: &mut Config) -> CliResult {
                       .map_err(|e| CliError::new(e, 1))?;
 
     let location = ProjectLocation { root: string.to_string() };
-    cargo::print_json(&location);
+    cargo::cli::print_json(&location);
     Ok(())
 }
@@ -4,11 +4,12 @@ use std::io;
 use cargo::ops;
 use cargo::core::{SourceId, Source};
 use cargo::sources::RegistrySource;
-use cargo::util::{CliResult, CargoResultExt, Config};
+use cargo::util::{CliResult, CargoError, CargoResultExt, Config};
 
 #[derive(Deserialize)]
 pub struct Options {
     flag_host: Option<String>,
+    flag_registry: Option<String>,
     arg_token: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
@@ -28,6 +29,7 @@ Usage:
 Options:
     -h, --help               Print this message
     --host HOST              Host to set the token for
+    --registry NAME          Alternative registry to save the token for (unstable)
     -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
@@ -44,15 +46,25 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    if options.flag_registry.is_some() {
+        config.cli_unstable().fail_if_stable_opt("--registry")?;
+    }
     let token = match options.arg_token.clone() {
         Some(token) => token,
         None => {
-            let src = SourceId::crates_io(config)?;
+            let src = match options.flag_registry {
+                Some(ref registry) => SourceId::alt_registry(config, registry)?,
+                None => SourceId::crates_io(config)?,
+            };
             let mut src = RegistrySource::remote(&src, config);
             src.update()?;
-            let config = src.config()?.unwrap();
-            let host = options.flag_host.clone().unwrap_or(config.api.unwrap());
-            println!("please visit {}me and paste the API Token below", host);
+            let api = src.config()?.unwrap().api;
+            let host = options.flag_host.clone().unwrap_or(api.unwrap());
+            // The prompt goes to stderr like everything else that's meant
+            // for people rather than programs.
+            writeln!(config.shell().err(),
+                     "please visit {}me and paste the API Token below", host)
+                .map_err(CargoError::from)?;
             let mut line = String::new();
             let input = io::stdin();
             input.lock().read_line(&mut line).chain_err(|| {
@@ -63,7 +75,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
     };
 
     let token = token.trim().to_string();
-    ops::registry_login(config, token)?;
+    ops::registry_login(config, token, options.flag_registry)?;
     Ok(())
 }
 
@@ -70,6 +70,6 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
 
     let ws = Workspace::new(&manifest, config)?;
     let result = output_metadata(&ws, &options)?;
-    cargo::print_json(&result);
+    cargo::cli::print_json(&result);
     Ok(())
 }
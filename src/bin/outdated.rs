@@ -0,0 +1,59 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Show dependencies which have newer versions available
+
+Usage:
+    cargo outdated [options]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest to check dependencies for
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+This command requires that a `Cargo.lock` already exists. Each locked
+dependency is compared against what's currently available:
+
+* Registry dependencies are compared against the newest semver compatible
+  version, which is what `cargo update` would select, as well as the newest
+  version published overall.
+
+* Git dependencies are compared against the current head of the branch or tag
+  they track on the remote. Dependencies pinned to a `rev` are skipped.
+
+A table of the dependencies that `cargo update` would change is printed, and
+nothing is modified.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::outdated(&ws)?;
+    Ok(())
+}
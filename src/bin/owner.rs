@@ -8,6 +8,7 @@ pub struct Options {
     flag_add: Option<Vec<String>>,
     flag_remove: Option<Vec<String>>,
     flag_index: Option<String>,
+    flag_registry: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
@@ -30,6 +31,7 @@ Options:
     -r, --remove LOGIN       Name of a user or team to remove as an owner
     -l, --list               List owners of a crate
     --index INDEX            Registry index to modify owners for
+    --registry NAME          Alternative registry to modify owners for (unstable)
     --token TOKEN            API token to use when authenticating
     -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet              No output printed to stdout
@@ -54,6 +56,9 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    if options.flag_registry.is_some() {
+        config.cli_unstable().fail_if_stable_opt("--registry")?;
+    }
     let opts = ops::OwnersOptions {
         krate: options.arg_crate,
         token: options.flag_token,
@@ -61,6 +66,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         to_add: options.flag_add,
         to_remove: options.flag_remove,
         list: options.flag_list,
+        registry: options.flag_registry,
     };
     ops::modify_owners(config, &opts)?;
     Ok(())
@@ -1,11 +1,13 @@
 use cargo::core::Workspace;
-use cargo::ops;
+use cargo::ops::{self, MessageFormat};
 use cargo::util::{CliResult, Config};
 use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
     flag_index: Option<String>,
+    flag_registry: Option<String>,
     flag_host: Option<String>,  // TODO: Deprecated, remove
     flag_token: Option<String>,
     flag_target: Option<String>,
@@ -17,6 +19,8 @@ pub struct Options {
     flag_allow_dirty: bool,
     flag_jobs: Option<u32>,
     flag_dry_run: bool,
+    flag_check: bool,
+    flag_message_format: MessageFormat,
     flag_frozen: bool,
     flag_locked: bool,
     #[serde(rename = "flag_Z")]
@@ -32,6 +36,7 @@ Usage:
 Options:
     -h, --help               Print this message
     --index INDEX            Registry index to upload the package to
+    --registry NAME          Alternative registry to upload the package to (unstable)
     --host HOST              DEPRECATED, renamed to '--index'
     --token TOKEN            Token to use when uploading
     --no-verify              Don't verify package tarball before publish
@@ -40,6 +45,8 @@ Options:
     --manifest-path PATH     Path to the manifest of the package to publish
     -j N, --jobs N           Number of parallel jobs, defaults to # of CPUs
     --dry-run                Perform all checks without uploading
+    --check                  Only check that the package can be published
+    --message-format FMT     Output format of --check: human, json [default: human]
     -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet              No output printed to stdout
     --color WHEN             Coloring: auto, always, never
@@ -49,6 +56,24 @@ Options:
 
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo publish --dry-run",
+         "Package and verify the current package without uploading it."),
+        ("cargo publish --check --message-format json",
+         "Report missing metadata, unversioned path dependencies, wildcard \
+          requirements and an oversized package as JSON, failing if the \
+          registry would reject it."),
+        ("cargo publish",
+         "Package the current package and upload it to crates.io."),
+    ],
+    config: &[
+        ("registry.index", "The registry to upload to, like `--index`"),
+        ("registry.token", "The API token, see `cargo login`"),
+        ("http.proxy", "HTTP proxy to use for the upload"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     config.configure(options.flag_verbose,
                      options.flag_quiet,
@@ -60,16 +85,23 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
     let Options {
         flag_token: token,
         flag_index: index,
+        flag_registry: registry,
         flag_host: host,    // TODO: Deprecated, remove
         flag_manifest_path,
         flag_no_verify: no_verify,
         flag_allow_dirty: allow_dirty,
         flag_jobs: jobs,
         flag_dry_run: dry_run,
+        flag_check: check,
+        flag_message_format: message_format,
         flag_target: target,
         ..
     } = options;
 
+    if registry.is_some() {
+        config.cli_unstable().fail_if_stable_opt("--registry")?;
+    }
+
 
     // TODO: Deprecated
     // remove once it has been decided --host can be removed
@@ -100,6 +132,9 @@ about this warning.";
         target: target.as_ref().map(|t| &t[..]),
         jobs: jobs,
         dry_run: dry_run,
+        registry: registry,
+        check: check,
+        message_format: message_format,
     })?;
     Ok(())
 }
@@ -34,6 +34,6 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
     let pkg = Package::for_path(&root, config)?;
-    cargo::print_json(&pkg);
+    cargo::cli::print_json(&pkg);
     Ok(())
 }
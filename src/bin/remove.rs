@@ -0,0 +1,74 @@
+use cargo::core::Workspace;
+use cargo::core::dependency::Kind;
+use cargo::ops;
+use cargo::util::{CargoError, CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    arg_dep: Vec<String>,
+    flag_package: Option<String>,
+    flag_dev: bool,
+    flag_build: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Remove dependencies from a Cargo.toml manifest file
+
+Usage:
+    cargo remove [options] <dep>...
+    cargo remove -h | --help
+
+Options:
+    -h, --help               Print this message
+    -p SPEC, --package SPEC  Package to remove the dependencies from
+    --dev                    Remove them from dev-dependencies
+    --build                  Remove them from build-dependencies
+    --target TARGET          Remove them from the dependencies for the given target
+    --manifest-path PATH     Path to the manifest to edit
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+The rest of the manifest, comments and formatting included, is left as it was.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+
+    let kind = match (options.flag_dev, options.flag_build) {
+        (true, true) => {
+            let err = CargoError::from("`--dev` and `--build` can't be used together");
+            return Err(err.into())
+        }
+        (true, false) => Kind::Development,
+        (false, true) => Kind::Build,
+        (false, false) => Kind::Normal,
+    };
+    let root = find_root_manifest_for_wd(options.flag_manifest_path.clone(), config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::remove(&ws, &ops::RemoveOptions {
+        package: options.flag_package.as_ref().map(|s| &s[..]),
+        deps: &options.arg_dep,
+        kind: kind,
+        target: options.flag_target.as_ref().map(|s| &s[..]),
+    })?;
+    Ok(())
+}
@@ -4,6 +4,7 @@ use cargo::core::Workspace;
 use cargo::ops::{self, MessageFormat, Packages};
 use cargo::util::{CliResult, CliError, Config, CargoErrorKind};
 use cargo::util::important_paths::{find_root_manifest_for_wd};
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -23,6 +24,7 @@ pub struct Options {
     flag_release: bool,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_refresh: bool,
     arg_args: Vec<String>,
     #[serde(rename = "flag_Z")]
     flag_z: Vec<String>,
@@ -52,10 +54,12 @@ Options:
     --message-format FMT         Error format: human, json [default: human]
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --refresh                    Update registry indexes even if recently updated
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 If neither `--bin` nor `--example` are given, then if the project only has one
-bin target it will be run. Otherwise `--bin` specifies the bin target to run,
+bin target it will be run, or else the one named by the `default-run` key of the
+manifest. Otherwise `--bin` specifies the bin target to run,
 and `--example` specifies the example target to run. At most one of `--bin` or
 `--example` can be provided.
 
@@ -64,6 +68,23 @@ arguments to both Cargo and the binary, the ones after `--` go to the binary,
 the ones before go to Cargo.
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo run",
+         "Build and run the current package's binary."),
+        ("cargo run --bin server -- --port 8080",
+         "Run the `server` binary, passing it `--port 8080`."),
+        ("cargo run --example demo --release",
+         "Run the `demo` example with optimizations."),
+    ],
+    config: &[
+        ("build.jobs", "Number of parallel jobs, like `--jobs`"),
+        ("build.target", "Target triple to build for, like `--target`"),
+        ("build.target-dir", "Directory where all artifacts are placed"),
+        ("target.<triple>.runner", "Program used to run the binary"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     config.configure(options.flag_verbose,
                      options.flag_quiet,
@@ -71,6 +92,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
@@ -108,6 +130,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        dry_run: false,
+        timings: false,
+        all_warnings: false,
+        deny_warnings: false,
+        size_report: false,
+        reproducible: false,
     };
 
     let ws = Workspace::new(&root, config)?;
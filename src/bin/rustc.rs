@@ -131,6 +131,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         message_format: options.flag_message_format,
         target_rustdoc_args: None,
         target_rustc_args: options.arg_opts.as_ref().map(|a| &a[..]),
+        dry_run: false,
+        timings: false,
+        all_warnings: false,
+        deny_warnings: false,
+        size_report: false,
+        reproducible: false,
     };
 
     let ws = Workspace::new(&root, config)?;
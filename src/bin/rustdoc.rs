@@ -117,6 +117,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
             mode: ops::CompileMode::Doc { deps: false },
             target_rustdoc_args: Some(&options.arg_opts),
             target_rustc_args: None,
+            dry_run: false,
+            timings: false,
+            all_warnings: false,
+            deny_warnings: false,
+            size_report: false,
+            reproducible: false,
         },
     };
 
@@ -0,0 +1,71 @@
+use cargo::core::Workspace;
+use cargo::ops::{self, Packages, SbomFormat};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_format: SbomFormat,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Print a software bill of materials for a package and its dependencies
+
+Usage:
+    cargo sbom [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package(s) to describe
+    --all                        Describe the whole workspace
+    --exclude SPEC ...           Exclude packages from the document
+    --format FMT                 Document format: cyclonedx, spdx [default: cyclonedx]
+    --manifest-path PATH         Path to the manifest of the package
+    -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+A CycloneDX 1.4 or SPDX 2.3 JSON document describing every package in the
+dependency graph is printed to stdout. Packages are identified by package URLs
+(`pkg:cargo/<name>@<version>`) which include the locked revision of git
+dependencies, registry packages carry the SHA-256 checksum from Cargo.lock, and
+the dependencies between packages are recorded as well.
+
+Packages only used by dev-dependencies are left out. The creation time of the
+document is taken from `SOURCE_DATE_EPOCH` if it's set, so that documents can
+be reproduced.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    let spec = Packages::from_flags(ws.is_virtual(),
+                                    options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    ops::sbom(&ws, &ops::SbomOptions {
+        spec: spec,
+        format: options.flag_format,
+    })?;
+    Ok(())
+}
@@ -19,7 +19,7 @@ pub struct Options {
 }
 
 pub const USAGE: &'static str = "
-Search packages in crates.io
+Search packages in crates.io, or whichever registry replaces it
 
 Usage:
     cargo search [options] <query>...
@@ -4,6 +4,7 @@ use cargo::core::Workspace;
 use cargo::ops::{self, MessageFormat, Packages};
 use cargo::util::{CliResult, CliError, Config, CargoErrorKind};
 use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
@@ -33,8 +34,10 @@ pub struct Options {
     flag_message_format: MessageFormat,
     flag_release: bool,
     flag_no_fail_fast: bool,
+    flag_test_jobs: Option<u32>,
     flag_frozen: bool,
     flag_locked: bool,
+    flag_refresh: bool,
     flag_all: bool,
     flag_exclude: Vec<String>,
     #[serde(rename = "flag_Z")]
@@ -60,7 +63,7 @@ Options:
     --bench NAME ...             Test only the specified bench target
     --benches                    Test all benches
     --all-targets                Test all targets (default)
-    --no-run                     Compile, but don't run tests
+    --no-run                     Compile, but don't run tests, printing their paths
     -p SPEC, --package SPEC ...  Package to run tests for
     --all                        Test all packages in the workspace
     --exclude SPEC ...           Exclude packages from the test
@@ -76,8 +79,10 @@ Options:
     --color WHEN                 Coloring: auto, always, never
     --message-format FMT         Error format: human, json [default: human]
     --no-fail-fast               Run all tests regardless of failure
+    --test-jobs N                Number of test binaries to run at the same time (default 1)
     --frozen                     Require Cargo.lock and cache are up to date
     --locked                     Require Cargo.lock is up to date
+    --refresh                    Update registry indexes even if recently updated
     -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
 
 All of the trailing arguments are passed to the test binaries generated for
@@ -103,6 +108,10 @@ to the test binaries:
 
     cargo test -- --test-threads=1
 
+Test binaries themselves are run one after the other, unless --test-jobs is
+given. Their output is then held back until they've finished, so that it isn't
+interleaved, and the results of all of them are added up at the end.
+
 Compilation can be configured via the `test` profile in the manifest.
 
 By default the rust test harness hides output from test execution to
@@ -116,6 +125,28 @@ To get the list of all options available for the test binaries use this:
     cargo test -- --help
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo test",
+         "Build and run all of the current package's tests."),
+        ("cargo test parse",
+         "Only run tests whose name contains `parse`."),
+        ("cargo test --test integration -- --nocapture",
+         "Run the `integration` test target and show its output."),
+        ("cargo test --all --no-fail-fast",
+         "Test the whole workspace, carrying on after a package fails."),
+        ("cargo test --doc",
+         "Only run the library's documentation tests."),
+    ],
+    config: &[
+        ("build.jobs", "Number of parallel jobs, like `--jobs`"),
+        ("build.target", "Target triple to build for, like `--target`"),
+        ("build.target-dir", "Directory where all artifacts are placed"),
+        ("build.unify-dev-features", "Whether dev-dependency features are enabled"),
+        ("target.<triple>.runner", "Program used to run the test executables"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     debug!("executing; cmd=cargo-test; args={:?}",
            env::args().collect::<Vec<_>>());
@@ -126,6 +157,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    config.set_refresh_index(options.flag_refresh);
 
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
     let ws = Workspace::new(&root, config)?;
@@ -133,6 +165,17 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
     let empty = Vec::new();
     let (mode, filter);
     if options.flag_doc {
+        // Only the library's documentation is tested, so picking any other
+        // targets would silently do nothing.
+        if options.flag_lib || !options.flag_bin.is_empty() || options.flag_bins ||
+           !options.flag_test.is_empty() || options.flag_tests ||
+           !options.flag_example.is_empty() || options.flag_examples ||
+           !options.flag_bench.is_empty() || options.flag_benches ||
+           options.flag_all_targets {
+            let err = "`--doc` can't be combined with other options selecting \
+                       targets".into();
+            return Err(CliError::new(err, 101))
+        }
         mode = ops::CompileMode::Doctest;
         filter = ops::CompileFilter::new(true, &empty, false, &empty, false,
                                                &empty, false, &empty, false,
@@ -156,6 +199,7 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
         no_run: options.flag_no_run,
         no_fail_fast: options.flag_no_fail_fast,
         only_doc: options.flag_doc,
+        test_jobs: options.flag_test_jobs.unwrap_or(1),
         compile_opts: ops::CompileOptions {
             config: config,
             jobs: options.flag_jobs,
@@ -170,6 +214,12 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
             message_format: options.flag_message_format,
             target_rustdoc_args: None,
             target_rustc_args: None,
+            dry_run: false,
+            timings: false,
+            all_warnings: false,
+            deny_warnings: false,
+            size_report: false,
+            reproducible: false,
         },
     };
 
@@ -0,0 +1,101 @@
+use cargo::core::Workspace;
+use cargo::ops::{self, Packages, TreeOptions};
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_package: Vec<String>,
+    flag_all: bool,
+    flag_exclude: Vec<String>,
+    flag_features: Vec<String>,
+    flag_all_features: bool,
+    flag_no_default_features: bool,
+    flag_duplicates: bool,
+    flag_invert: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Display the dependency graph of a package as a tree
+
+Usage:
+    cargo tree [options]
+
+Options:
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package(s) to show the dependencies of
+    --all                        Show the dependencies of the whole workspace
+    --exclude SPEC ...           Exclude packages from the tree
+    --features FEATURES          Space-separated list of features to also enable
+    --all-features               Enable all available features
+    --no-default-features        Do not enable the `default` feature
+    -d, --duplicates             Only show packages which appear more than once
+    -i SPEC, --invert SPEC       Show what depends on SPEC instead
+    --manifest-path PATH         Path to the manifest of the package
+    -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet                  No output printed to stdout
+    --color WHEN                 Coloring: auto, always, never
+    --frozen                     Require Cargo.lock and cache are up to date
+    --locked                     Require Cargo.lock is up to date
+    -Z FLAG ...                  Unstable (nightly-only) flags to Cargo
+
+Every package is printed along with the packages it depends on, including
+dev-dependencies. The dependencies of a package which has already been printed
+aren't repeated, the package is marked with `(*)` instead.
+
+With --duplicates, only packages of which several versions or sources are in
+the dependency graph are shown. Each copy is printed with the packages which
+depend on it, all the way up to the workspace members, to help with finding out
+which dependencies need to be updated to only build a single copy.
+
+With --invert, the tree is turned upside down to answer why SPEC is part of the
+build: SPEC is printed first, followed by every package which depends on it, up
+to the workspace members. Each of them is annotated with how it depends on the
+package above it: as a normal, dev- or build-dependency, and on which platform
+if it's target-specific.
+";
+
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo tree",
+         "Show the dependency graph of the current package."),
+        ("cargo tree --all --duplicates",
+         "Show which packages of the workspace pull in several copies of a crate."),
+        ("cargo tree -i libc",
+         "Show why `libc` is a dependency of the current package."),
+    ],
+    config: &[],
+};
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    let spec = Packages::from_flags(ws.is_virtual(),
+                                    options.flag_all,
+                                    &options.flag_exclude,
+                                    &options.flag_package)?;
+    ops::tree(&ws, &TreeOptions {
+        spec: spec,
+        features: &options.flag_features,
+        all_features: options.flag_all_features,
+        no_default_features: options.flag_no_default_features,
+        duplicates: options.flag_duplicates,
+        invert: options.flag_invert.as_ref().map(|s| &s[..]),
+    })?;
+    Ok(())
+}
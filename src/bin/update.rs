@@ -4,12 +4,14 @@ use cargo::core::Workspace;
 use cargo::ops;
 use cargo::util::{CliResult, Config};
 use cargo::util::important_paths::find_root_manifest_for_wd;
+use help::ExtendedHelp;
 
 #[derive(Deserialize)]
 pub struct Options {
     flag_package: Vec<String>,
     flag_aggressive: bool,
     flag_precise: Option<String>,
+    flag_dry_run: bool,
     flag_manifest_path: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
@@ -31,6 +33,7 @@ Options:
     -p SPEC, --package SPEC ...  Package to update
     --aggressive                 Force updating all dependencies of <name> as well
     --precise PRECISE            Update a single dependency to exactly PRECISE
+    --dry-run                    Show what would change without writing Cargo.lock
     --manifest-path PATH         Path to the crate's manifest
     -v, --verbose ...            Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet                  No output printed to stdout
@@ -57,9 +60,31 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+With --dry-run the changes to each pin are printed as usual, old -> new, but
+Cargo.lock is left untouched. Sources still need to be queried for their newest
+versions, but no packages are downloaded.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ";
 
+pub static HELP: ExtendedHelp = ExtendedHelp {
+    examples: &[
+        ("cargo update",
+         "Update every dependency in Cargo.lock to its newest compatible version."),
+        ("cargo update -p serde",
+         "Only update `serde`, leaving everything else locked."),
+        ("cargo update -p serde --precise 1.0.10",
+         "Lock `serde` to exactly version 1.0.10."),
+        ("cargo update --dry-run",
+         "Show what would change without writing Cargo.lock."),
+    ],
+    config: &[
+        ("registry.index", "The registry to update from"),
+        ("net.retry", "Number of times network operations are retried"),
+        ("git.checkout-strategy", "How git dependencies are checked out"),
+    ],
+};
+
 pub fn execute(options: Options, config: &mut Config) -> CliResult {
     debug!("executing; cmd=cargo-update; args={:?}", env::args().collect::<Vec<_>>());
     config.configure(options.flag_verbose,
@@ -68,12 +93,15 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    // Updating is the whole point, however recently the indexes were updated.
+    config.set_refresh_index(true);
     let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
 
     let update_opts = ops::UpdateOptions {
         aggressive: options.flag_aggressive,
         precise: options.flag_precise.as_ref().map(|s| &s[..]),
         to_update: &options.flag_package,
+        dry_run: options.flag_dry_run,
         config: config,
     };
 
@@ -0,0 +1,58 @@
+use cargo::core::Workspace;
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_wd;
+
+#[derive(Deserialize)]
+pub struct Options {
+    flag_manifest_path: Option<String>,
+    flag_repair: bool,
+    flag_verbose: u32,
+    flag_quiet: Option<bool>,
+    flag_color: Option<String>,
+    flag_frozen: bool,
+    flag_locked: bool,
+    #[serde(rename = "flag_Z")]
+    flag_z: Vec<String>,
+}
+
+pub const USAGE: &'static str = "
+Check the local cache against the versions pinned in Cargo.lock
+
+Usage:
+    cargo verify [options]
+
+Options:
+    -h, --help               Print this message
+    --manifest-path PATH     Path to the manifest to verify dependencies for
+    --repair                 Execute the repair plan for any problems found
+    -v, --verbose ...        Use verbose output (-vv very verbose/build.rs output)
+    -q, --quiet              No output printed to stdout
+    --color WHEN             Coloring: auto, always, never
+    --frozen                 Require Cargo.lock and cache are up to date
+    --locked                 Require Cargo.lock is up to date
+    -Z FLAG ...              Unstable (nightly-only) flags to Cargo
+
+Every dependency recorded in the lockfile is checked against the local cache:
+git databases must contain the locked revision, git checkouts must be
+unmodified checkouts of that revision, and downloaded registry archives must
+match the checksum recorded in the lockfile.
+
+Any problems found are printed along with a plan to repair them. Passing
+`--repair` removes the broken cache entries and fetches them again.
+";
+
+pub fn execute(options: Options, config: &mut Config) -> CliResult {
+    config.configure(options.flag_verbose,
+                     options.flag_quiet,
+                     &options.flag_color,
+                     options.flag_frozen,
+                     options.flag_locked,
+                     &options.flag_z)?;
+    let root = find_root_manifest_for_wd(options.flag_manifest_path, config.cwd())?;
+    let ws = Workspace::new(&root, config)?;
+    ops::verify(&ws, &ops::VerifyOptions {
+        repair: options.flag_repair,
+    })?;
+    Ok(())
+}
@@ -65,7 +65,7 @@ pub fn execute(args: Flags, config: &mut Config) -> CliResult {
 
     let mut h = HashMap::new();
     h.insert("success".to_string(), "true".to_string());
-    cargo::print_json(&h);
+    cargo::cli::print_json(&h);
     Ok(())
 }
 
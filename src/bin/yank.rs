@@ -7,6 +7,7 @@ pub struct Options {
     flag_token: Option<String>,
     flag_vers: Option<String>,
     flag_index: Option<String>,
+    flag_registry: Option<String>,
     flag_verbose: u32,
     flag_quiet: Option<bool>,
     flag_color: Option<String>,
@@ -28,6 +29,7 @@ Options:
     --vers VERSION      The version to yank or un-yank
     --undo              Undo a yank, putting a version back into the index
     --index INDEX       Registry index to yank from
+    --registry NAME     Alternative registry to yank from (unstable)
     --token TOKEN       API token to use when authenticating
     -v, --verbose ...   Use verbose output (-vv very verbose/build.rs output)
     -q, --quiet         No output printed to stdout
@@ -52,11 +54,15 @@ pub fn execute(options: Options, config: &mut Config) -> CliResult {
                      options.flag_frozen,
                      options.flag_locked,
                      &options.flag_z)?;
+    if options.flag_registry.is_some() {
+        config.cli_unstable().fail_if_stable_opt("--registry")?;
+    }
     ops::yank(config,
               options.arg_crate,
               options.flag_vers,
               options.flag_token,
               options.flag_index,
+              options.flag_registry,
               options.flag_undo)?;
     Ok(())
 }
@@ -0,0 +1,329 @@
+//! Helpers for the `cargo` binary and for external subcommands which want to
+//! behave like the built-in ones: parsing arguments with docopt, printing JSON
+//! to stdout and reporting errors before exiting.
+//!
+//! The flags in `GLOBAL_FLAGS` are accepted before the command as well as
+//! after it, `GlobalArgs::parse` moves them after the command so that each
+//! command's docopt usage only has to deal with its own arguments.
+//!
+//! Nothing else in the library depends on this module, tools which only drive
+//! Cargo programmatically don't need it.
+
+use std::process;
+
+use docopt::Docopt;
+use serde::Deserialize;
+use serde::ser;
+use serde_json;
+
+use core::Shell;
+use core::shell::Verbosity::Verbose;
+use util::{CargoResult, CliError, CliResult, Config};
+
+/// A flag which is accepted before the command as well as after it.
+pub struct GlobalFlag {
+    pub short: Option<&'static str>,
+    pub long: Option<&'static str>,
+    /// The name of its value, if it takes one.
+    pub value: Option<&'static str>,
+    /// Whether it may be given more than once.
+    pub multiple: bool,
+    pub about: &'static str,
+}
+
+impl GlobalFlag {
+    /// How the flag is spelled in usage messages.
+    fn name(&self) -> &'static str {
+        self.long.or(self.short).unwrap()
+    }
+
+    /// What a command's usage has to mention for the flag to be accepted.
+    fn usage_name(&self) -> String {
+        match (self.long, self.value) {
+            (Some(long), _) => long.to_string(),
+            (None, Some(value)) => format!("{} {}", self.name(), value),
+            (None, None) => self.name().to_string(),
+        }
+    }
+}
+
+pub static GLOBAL_FLAGS: &'static [GlobalFlag] = &[
+    GlobalFlag {
+        short: Some("-v"), long: Some("--verbose"), value: None, multiple: true,
+        about: "Use verbose output (-vv very verbose/build.rs output)",
+    },
+    GlobalFlag {
+        short: Some("-q"), long: Some("--quiet"), value: None, multiple: false,
+        about: "No output printed to stdout",
+    },
+    GlobalFlag {
+        short: None, long: Some("--color"), value: Some("WHEN"), multiple: false,
+        about: "Coloring: auto, always, never",
+    },
+    GlobalFlag {
+        short: None, long: Some("--frozen"), value: None, multiple: false,
+        about: "Require Cargo.lock and cache are up to date",
+    },
+    GlobalFlag {
+        short: None, long: Some("--locked"), value: None, multiple: false,
+        about: "Require Cargo.lock is up to date",
+    },
+    GlobalFlag {
+        short: Some("-Z"), long: None, value: Some("FLAG"), multiple: true,
+        about: "Unstable (nightly-only) flags to Cargo",
+    },
+    GlobalFlag {
+        short: None, long: Some("--manifest-path"), value: Some("PATH"), multiple: false,
+        about: "Path to the manifest of the package to work on",
+    },
+    GlobalFlag {
+        short: None, long: Some("--config"), value: Some("KEY=VALUE"), multiple: true,
+        about: "Override a configuration value, like `term.verbose=true`",
+    },
+];
+
+/// The lines describing `GLOBAL_FLAGS` in a docopt usage message, with the
+/// flags padded to `width` columns.
+pub fn global_flags_usage(width: usize) -> String {
+    let mut usage = String::new();
+    for flag in GLOBAL_FLAGS {
+        let mut spec = match (flag.short, flag.long) {
+            (Some(short), Some(long)) => format!("{}, {}", short, long),
+            (short, long) => short.or(long).unwrap().to_string(),
+        };
+        if let Some(value) = flag.value {
+            // docopt only takes names like `VALUE` or `<key=value>`.
+            if value.chars().all(|c| c.is_uppercase()) {
+                spec.push_str(&format!(" {}", value));
+            } else {
+                spec.push_str(&format!(" <{}>", value));
+            }
+        }
+        if flag.multiple {
+            spec.push_str(" ...");
+        }
+        usage.push_str(&format!("    {:<w$}  {}\n", spec, flag.about, w = width));
+    }
+    usage
+}
+
+/// The command line with the global flags given before the command moved
+/// after it, and the `--config` overrides taken out.
+pub struct GlobalArgs {
+    pub args: Vec<String>,
+    /// The `KEY=VALUE` overrides, which are meant for
+    /// `Config::set_cli_config`.
+    pub config: Vec<String>,
+}
+
+impl GlobalArgs {
+    /// Parses the command line `args`, including the program name.
+    ///
+    /// `usage` returns the usage message of the built-in command it's given.
+    /// Global flags moved after a built-in command have to be mentioned in its
+    /// usage, and `--config` is taken out from anywhere before a `--` in its
+    /// arguments. The arguments of other commands are left as they are.
+    pub fn parse(args: &[String], usage: &Fn(&str) -> Option<&'static str>)
+                 -> CargoResult<GlobalArgs> {
+        let mut moved = Vec::new();
+        let mut config = Vec::new();
+        let mut i = 1;
+        while i < args.len() {
+            let arg = &args[i];
+            if arg == "--" || arg == "-" || !arg.starts_with('-') {
+                break
+            }
+            let flags = match parse_flag(args, &mut i)? {
+                Some(flags) => flags,
+                None => break,
+            };
+            for (flag, value) in flags {
+                match value {
+                    Some(value) => {
+                        if flag.long == Some("--config") {
+                            config.push(value);
+                        } else {
+                            moved.push((flag, Some(value)));
+                        }
+                    }
+                    None => moved.push((flag, None)),
+                }
+            }
+        }
+
+        let mut out = vec![args[0].clone()];
+        let command = match args.get(i) {
+            Some(command) if !command.starts_with('-') => command,
+            _ => {
+                // `cargo` itself takes the global flags.
+                out.extend(flag_args(&moved));
+                out.extend(args[i..].iter().cloned());
+                return Ok(GlobalArgs { args: out, config: config })
+            }
+        };
+        out.push(command.clone());
+
+        let usage = usage(command);
+        if let Some(usage) = usage {
+            for &(flag, _) in moved.iter() {
+                if !usage.contains(&flag.usage_name()[..]) {
+                    bail!("`{}` isn't accepted by `cargo {}`", flag.name(), command)
+                }
+            }
+        }
+        out.extend(flag_args(&moved));
+
+        let mut rest = args[i + 1..].iter();
+        while let Some(arg) = rest.next() {
+            if usage.is_none() || arg == "--" {
+                out.push(arg.clone());
+                out.extend(rest.by_ref().cloned());
+                break
+            }
+            if arg == "--config" {
+                match rest.next() {
+                    Some(value) => config.push(value.clone()),
+                    None => bail!("`--config` needs a value, like `--config KEY=VALUE`"),
+                }
+            } else if arg.starts_with("--config=") {
+                config.push(arg["--config=".len()..].to_string());
+            } else {
+                out.push(arg.clone());
+            }
+        }
+        Ok(GlobalArgs { args: out, config: config })
+    }
+}
+
+/// Parses the global flags in `args[*i]`, advancing `i` past them and their
+/// values. Returns `None`, leaving `i` alone, if it isn't one.
+fn parse_flag(args: &[String], i: &mut usize)
+              -> CargoResult<Option<Vec<(&'static GlobalFlag, Option<String>)>>> {
+    let arg = &args[*i];
+    let mut flags = Vec::new();
+    let mut next = *i + 1;
+    if arg.starts_with("--") {
+        let (name, value) = match arg.find('=') {
+            Some(eq) => (&arg[..eq], Some(arg[eq + 1..].to_string())),
+            None => (&arg[..], None),
+        };
+        let flag = match GLOBAL_FLAGS.iter().find(|f| f.long == Some(name)) {
+            Some(flag) => flag,
+            None => return Ok(None),
+        };
+        let value = match (flag.value, value) {
+            (Some(_), Some(value)) => Some(value),
+            (Some(_), None) => {
+                next += 1;
+                match args.get(next - 1) {
+                    Some(value) => Some(value.clone()),
+                    None => bail!("`{}` needs a value", name),
+                }
+            }
+            (None, Some(_)) => bail!("`{}` doesn't take a value", name),
+            (None, None) => None,
+        };
+        flags.push((flag, value));
+    } else {
+        // A cluster of short flags like `-vv`, the last of which may take a
+        // value, as in `-Zflag` or `-Z flag`.
+        for (pos, c) in arg.char_indices().skip(1) {
+            let short = format!("-{}", c);
+            let flag = match GLOBAL_FLAGS.iter().find(|f| f.short == Some(&short[..])) {
+                Some(flag) => flag,
+                None => return Ok(None),
+            };
+            if flag.value.is_none() {
+                flags.push((flag, None));
+                continue
+            }
+            let rest = &arg[pos + c.len_utf8()..];
+            let value = if !rest.is_empty() {
+                rest.to_string()
+            } else {
+                next += 1;
+                match args.get(next - 1) {
+                    Some(value) => value.clone(),
+                    None => bail!("`{}` needs a value", short),
+                }
+            };
+            flags.push((flag, Some(value)));
+            break
+        }
+    }
+
+    for &(flag, ref value) in flags.iter() {
+        if flag.long == Some("--color") {
+            let value = value.as_ref().unwrap();
+            if !["auto", "always", "never"].contains(&&value[..]) {
+                bail!("argument for --color must be auto, always, or never, but found `{}`",
+                      value)
+            }
+        }
+    }
+    *i = next;
+    Ok(Some(flags))
+}
+
+fn flag_args(flags: &[(&'static GlobalFlag, Option<String>)]) -> Vec<String> {
+    let mut args = Vec::new();
+    for &(flag, ref value) in flags {
+        args.push(flag.name().to_string());
+        if let Some(ref value) = *value {
+            args.push(value.clone());
+        }
+    }
+    args
+}
+
+pub fn call_main_without_stdin<'de, Flags: Deserialize<'de>>(
+            exec: fn(Flags, &mut Config) -> CliResult,
+            config: &mut Config,
+            usage: &str,
+            args: &[String],
+            options_first: bool) -> CliResult
+{
+    let docopt = Docopt::new(usage).unwrap()
+        .options_first(options_first)
+        .argv(args.iter().map(|s| &s[..]))
+        .help(true);
+
+    let flags = docopt.deserialize().map_err(|e| {
+        let code = if e.fatal() {1} else {0};
+        CliError::new(e.to_string().into(), code)
+    })?;
+
+    exec(flags, config)
+}
+
+pub fn print_json<T: ser::Serialize>(obj: &T) {
+    let encoded = serde_json::to_string(&obj).unwrap();
+    println!("{}", encoded);
+}
+
+pub fn exit_with_error(err: CliError, shell: &mut Shell) -> ! {
+    debug!("exit_with_error; err={:?}", err);
+
+    let CliError { error, exit_code, unknown } = err;
+    // exit_code == 0 is non-fatal error, e.g. docopt version info
+    let fatal = exit_code != 0;
+
+    let hide = unknown && shell.verbosity() != Verbose;
+
+    if let Some(error) = error {
+        if hide {
+            drop(shell.error("An unknown error occurred"))
+        } else if fatal {
+            drop(shell.error(&error))
+        } else {
+            drop(writeln!(shell.err(), "{}", error))
+        }
+
+        if !::handle_cause(error, shell) || hide {
+            drop(writeln!(shell.err(), "\nTo learn more, run the command again \
+                                        with --verbose."));
+        }
+    }
+
+    process::exit(exit_code)
+}
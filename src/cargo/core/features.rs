@@ -231,12 +231,21 @@ impl Features {
 #[derive(Default, Debug)]
 pub struct CliUnstable {
     pub print_im_a_teapot: bool,
+    pub minimal_versions: bool,
+    pub unused_dependencies: bool,
+    pub unstable_options: bool,
 }
 
 impl CliUnstable {
     pub fn parse(&mut self, flags: &[String]) -> CargoResult<()> {
-        if !flags.is_empty() && !nightly_features_allowed() {
-            bail!("the `-Z` flag is only accepted on the nightly channel of Cargo")
+        if let Some(flag) = flags.first() {
+            if !nightly_features_allowed() {
+                let name = flag.splitn(2, '=').next().unwrap();
+                bail!("the `-Z {}` flag is only accepted on the nightly channel \
+                       of Cargo, but this is the `{}` channel",
+                      name,
+                      channel())
+            }
         }
         for flag in flags {
             self.add(flag)?;
@@ -260,11 +269,24 @@ impl CliUnstable {
 
         match k {
             "print-im-a-teapot" => self.print_im_a_teapot = parse_bool(v)?,
+            "minimal-versions" => self.minimal_versions = parse_bool(v)?,
+            "unused-dependencies" => self.unused_dependencies = parse_bool(v)?,
+            "unstable-options" => self.unstable_options = parse_bool(v)?,
             _ => bail!("unknown `-Z` flag specified: {}", k),
         }
 
         Ok(())
     }
+
+    /// Fails unless `-Z unstable-options` was passed, for command line
+    /// options which aren't stable yet.
+    pub fn fail_if_stable_opt(&self, flag: &str) -> CargoResult<()> {
+        if !self.unstable_options {
+            bail!("the `{}` flag is unstable, pass `-Z unstable-options` \
+                   to enable it", flag)
+        }
+        Ok(())
+    }
 }
 
 fn channel() -> String {
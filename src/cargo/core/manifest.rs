@@ -24,6 +24,7 @@ pub struct Manifest {
     summary: Summary,
     targets: Vec<Target>,
     links: Option<String>,
+    default_run: Option<String>,
     warnings: Vec<DelayedWarning>,
     exclude: Vec<String>,
     include: Vec<String>,
@@ -155,7 +156,7 @@ impl ser::Serialize for TargetKind {
 pub struct Profile {
     pub opt_level: String,
     #[serde(skip_serializing)]
-    pub lto: bool,
+    pub lto: Lto,
     #[serde(skip_serializing)]
     pub codegen_units: Option<u32>,    // None = use rustc default
     #[serde(skip_serializing)]
@@ -176,6 +177,38 @@ pub struct Profile {
     pub check: bool,
     #[serde(skip_serializing)]
     pub panic: Option<String>,
+    /// Whether the C runtime is linked statically, `None` for the default of
+    /// the target.
+    #[serde(skip_serializing)]
+    pub crt_static: Option<bool>,
+    /// What's stripped from linked artifacts, `debuginfo` or `symbols`.
+    #[serde(skip_serializing)]
+    pub strip: Option<String>,
+    #[serde(skip_serializing)]
+    pub split_debuginfo: Option<String>,
+    /// Whether rustc compiles incrementally, `None` unless the profile or its
+    /// default says, see `Context::incremental_args`.
+    #[serde(skip_serializing)]
+    pub incremental: Option<bool>,
+}
+
+/// The `lto` setting of a profile, either `true` or `false`, or the kind of
+/// LTO to pass to rustc, like `"thin"`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Lto {
+    Bool(bool),
+    Named(String),
+}
+
+impl Lto {
+    /// Whether the whole crate graph is optimized as a single unit, which
+    /// codegen units don't apply to.
+    pub fn is_fat(&self) -> bool {
+        match *self {
+            Lto::Bool(b) => b,
+            Lto::Named(ref s) => s == "fat",
+        }
+    }
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
@@ -191,6 +224,33 @@ pub struct Profiles {
     pub check: Profile,
     pub check_test: Profile,
     pub doctest: Profile,
+    /// The `[profile.dev.package]` tables.
+    pub dev_overrides: ProfileOverrides,
+    /// The `[profile.release.package]` tables.
+    pub release_overrides: ProfileOverrides,
+}
+
+/// Settings which apply to some of the packages in the graph only, from the
+/// `package` table of a profile.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ProfileOverrides {
+    /// The settings for `"*"`, every package which isn't a member of the
+    /// workspace.
+    pub all: Option<ProfileOverride>,
+    /// The settings for specific packages, which take precedence over `all`.
+    pub packages: Vec<(PackageIdSpec, ProfileOverride)>,
+}
+
+/// The subset of a profile's settings which can be overridden for some of the
+/// packages. Settings which have to agree across the crate graph, like `lto`
+/// and `panic`, can't be.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ProfileOverride {
+    pub opt_level: Option<String>,
+    pub codegen_units: Option<u32>,
+    pub debuginfo: Option<Option<u32>>,
+    pub debug_assertions: Option<bool>,
+    pub overflow_checks: Option<bool>,
 }
 
 /// Information about a binary, a library, an example, etc. that is part of the
@@ -238,6 +298,7 @@ impl Manifest {
                exclude: Vec<String>,
                include: Vec<String>,
                links: Option<String>,
+               default_run: Option<String>,
                metadata: ManifestMetadata,
                profiles: Profiles,
                publish: bool,
@@ -254,6 +315,7 @@ impl Manifest {
             exclude: exclude,
             include: include,
             links: links,
+            default_run: default_run,
             metadata: metadata,
             profiles: profiles,
             publish: publish,
@@ -284,6 +346,11 @@ impl Manifest {
     pub fn links(&self) -> Option<&str> {
         self.links.as_ref().map(|s| &s[..])
     }
+    /// The binary `cargo run` runs when the package has several of them and
+    /// none is picked on the command line.
+    pub fn default_run(&self) -> Option<&str> {
+        self.default_run.as_ref().map(|s| &s[..])
+    }
 
     pub fn workspace_config(&self) -> &WorkspaceConfig {
         &self.workspace
@@ -623,6 +690,7 @@ impl Profile {
         Profile {
             opt_level: "3".to_string(),
             debuginfo: None,
+            incremental: Some(false),
             ..Profile::default()
         }
     }
@@ -679,11 +747,43 @@ impl Profile {
     }
 }
 
+impl ProfileOverrides {
+    /// The settings overridden for `pkg`, which is a workspace member if
+    /// `member` is set.
+    pub fn get(&self, pkg: &PackageId, member: bool) -> Option<&ProfileOverride> {
+        self.packages.iter()
+            .find(|&&(ref spec, _)| spec.matches(pkg))
+            .map(|&(_, ref o)| o)
+            .or_else(|| if member { None } else { self.all.as_ref() })
+    }
+}
+
+impl ProfileOverride {
+    /// Applies these settings on top of `profile`.
+    pub fn apply(&self, profile: &mut Profile) {
+        if let Some(ref opt_level) = self.opt_level {
+            profile.opt_level = opt_level.clone();
+        }
+        if let Some(codegen_units) = self.codegen_units {
+            profile.codegen_units = Some(codegen_units);
+        }
+        if let Some(debuginfo) = self.debuginfo {
+            profile.debuginfo = debuginfo;
+        }
+        if let Some(debug_assertions) = self.debug_assertions {
+            profile.debug_assertions = debug_assertions;
+        }
+        if let Some(overflow_checks) = self.overflow_checks {
+            profile.overflow_checks = overflow_checks;
+        }
+    }
+}
+
 impl Default for Profile {
     fn default() -> Profile {
         Profile {
             opt_level: "0".to_string(),
-            lto: false,
+            lto: Lto::Bool(false),
             codegen_units: None,
             rustc_args: None,
             rustdoc_args: None,
@@ -696,6 +796,10 @@ impl Default for Profile {
             run_custom_build: false,
             check: false,
             panic: None,
+            crt_static: None,
+            strip: None,
+            split_debuginfo: None,
+            incremental: None,
         }
     }
 }
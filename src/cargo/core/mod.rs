@@ -1,7 +1,8 @@
 pub use self::dependency::Dependency;
 pub use self::features::{Features, Feature, CliUnstable};
 pub use self::manifest::{EitherManifest, VirtualManifest};
-pub use self::manifest::{Manifest, Target, TargetKind, Profile, LibKind, Profiles};
+pub use self::manifest::{Manifest, Target, TargetKind, Profile, LibKind, Profiles, Lto};
+pub use self::manifest::{ProfileOverride, ProfileOverrides};
 pub use self::package::{Package, PackageSet};
 pub use self::package_id::PackageId;
 pub use self::package_id_spec::PackageIdSpec;
@@ -178,6 +178,8 @@ impl hash::Hash for Package {
 pub struct PackageSet<'cfg> {
     packages: HashMap<PackageId, LazyCell<Package>>,
     sources: RefCell<SourceMap<'cfg>>,
+    /// The checksums the packages were resolved with, see `set_checksums`
+    checksums: HashMap<PackageId, String>,
 }
 
 impl<'cfg> PackageSet<'cfg> {
@@ -188,9 +190,19 @@ impl<'cfg> PackageSet<'cfg> {
                 (id.clone(), LazyCell::new())
             }).collect(),
             sources: RefCell::new(sources),
+            checksums: HashMap::new(),
         }
     }
 
+    /// Records the checksums from the resolve these packages came out of, and
+    /// so from the lock file, which the index entries of packages are checked
+    /// against as they're downloaded.
+    pub fn set_checksums(&mut self, checksums: &HashMap<PackageId, Option<String>>) {
+        self.checksums = checksums.iter().filter_map(|(id, cksum)| {
+            cksum.as_ref().map(|c| (id.clone(), c.clone()))
+        }).collect();
+    }
+
     pub fn package_ids<'a>(&'a self) -> Box<Iterator<Item=&'a PackageId> + 'a> {
         Box::new(self.packages.keys())
     }
@@ -209,11 +221,78 @@ impl<'cfg> PackageSet<'cfg> {
         let pkg = source.download(id).chain_err(|| {
             "unable to get packages from source"
         })?;
+        if let Some(expected) = self.checksums.get(id) {
+            verify_checksum(&pkg, expected)?;
+        }
         assert!(slot.fill(pkg).is_ok());
         Ok(slot.borrow().unwrap())
     }
 
+    /// Gets the sources of `ids` ready to hand them out with `get`, letting
+    /// each fetch all of its packages at once, see `Source::prefetch`.
+    pub fn prefetch(&self, ids: &[&PackageId]) -> CargoResult<()> {
+        let mut by_source: Vec<(&SourceId, Vec<PackageId>)> = Vec::new();
+        for id in ids {
+            match self.packages.get(*id) {
+                Some(slot) if slot.borrow().is_none() => {}
+                _ => continue,
+            }
+            let source_id = id.source_id();
+            match by_source.iter().position(|&(s, _)| s == source_id) {
+                Some(i) => by_source[i].1.push((*id).clone()),
+                None => by_source.push((source_id, vec![(*id).clone()])),
+            }
+        }
+        let mut sources = self.sources.borrow_mut();
+        for (source_id, ids) in by_source {
+            let source = sources.get_mut(source_id).ok_or_else(|| {
+                internal(format!("couldn't find source `{}`", source_id))
+            })?;
+            source.prefetch(&ids).chain_err(|| {
+                "unable to get packages from source"
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Whether `get` can hand out `id` without fetching it over the network.
+    pub fn is_downloaded(&self, id: &PackageId) -> bool {
+        match self.packages.get(id) {
+            Some(slot) if slot.borrow().is_some() => return true,
+            _ => {}
+        }
+        self.sources.borrow().get(id.source_id()).map(|source| {
+            source.is_downloaded(id)
+        }).unwrap_or(false)
+    }
+
     pub fn sources(&self) -> Ref<SourceMap<'cfg>> {
         self.sources.borrow()
     }
 }
+
+/// Checks that the index entry `pkg` was downloaded with has the checksum it
+/// was resolved with. Sources check what they download against their index,
+/// so this ties the downloaded package to the lock file as well.
+fn verify_checksum(pkg: &Package, expected: &str) -> CargoResult<()> {
+    let actual = pkg.summary().checksum();
+    if actual == Some(expected) {
+        return Ok(())
+    }
+    bail!("\
+checksum for `{}` in the registry index doesn't match the one in the lock file
+
+expected: {}
+actual:   {}
+
+the package's index entry changed since the lock file was generated, and the \
+package that was downloaded is the one the index now describes. this could be \
+indicative of a few possible situations:
+
+    * the package was republished or the index was rewritten
+    * a replacement source in use (e.g. a mirror) isn't actually a mirror
+    * the lock file is corrupt
+
+unable to verify that `{0}` is the same as when the lockfile was generated
+", pkg.package_id(), expected, actual.unwrap_or("<none>"))
+}
@@ -31,6 +31,14 @@ pub trait Registry {
     /// Returns whether or not this registry will return summaries with
     /// the `precise` field in the source id listed.
     fn requires_precise(&self) -> bool;
+
+    /// Returns the names of all packages in the source `dep` points at, for
+    /// reporting what was found instead when a query came up empty.
+    ///
+    /// Most sources are far too large to list, so by default this is empty.
+    fn package_names(&mut self, _dep: &Dependency) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 impl<'a, T: ?Sized + Registry + 'a> Registry for Box<T> {
@@ -47,6 +55,10 @@ impl<'a, T: ?Sized + Registry + 'a> Registry for Box<T> {
     fn requires_precise(&self) -> bool {
         (**self).requires_precise()
     }
+
+    fn package_names(&mut self, dep: &Dependency) -> Vec<String> {
+        (**self).package_names(dep)
+    }
 }
 
 /// This structure represents a registry of known packages. It internally
@@ -433,6 +445,13 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
     fn requires_precise(&self) -> bool {
         false
     }
+
+    fn package_names(&mut self, dep: &Dependency) -> Vec<String> {
+        match self.sources.get_mut(dep.source_id()) {
+            Some(source) => source.package_names(dep),
+            None => Vec::new(),
+        }
+    }
 }
 
 fn lock(locked: &LockedMap,
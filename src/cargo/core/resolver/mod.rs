@@ -29,11 +29,19 @@
 //!   semver-compatible, so selecting the highest version possible will allow us
 //!   to hopefully satisfy as many dependencies at once.
 //!
-//! Beyond that, what's implemented below is just a naive backtracking version
-//! which should in theory try all possible combinations of dependencies and
-//! versions to see if one works. The first resolution that works causes
-//! everything to bail out immediately and return success, and only if *nothing*
-//! works do we actually return an error up the stack.
+//! Beyond that, what's implemented below is a backtracking version which should
+//! in theory try all possible combinations of dependencies and versions to see
+//! if one works. The first resolution that works causes everything to bail out
+//! immediately and return success, and only if *nothing* works do we actually
+//! return an error up the stack.
+//!
+//! When a dependency has no candidate left, the activated packages which ruled
+//! out each of its candidates are recorded. Backtracking then skips over every
+//! choice that was made after all of those packages were already activated, as
+//! picking something else there can't make the conflict go away, and the same
+//! conflict is remembered so the dependency is rejected straight away if it
+//! shows up again. The final error lists these packages along with the chain
+//! of packages depending on each of them.
 //!
 //! ## Performance
 //!
@@ -59,13 +67,23 @@ use core::{PackageId, Registry, SourceId, Summary, Dependency};
 use core::PackageIdSpec;
 use util::config::Config;
 use util::Graph;
-use util::errors::{CargoResult, CargoError};
+use util::errors::{CargoResult, CargoError, CargoErrorKind};
+use util::paths;
 use util::profile;
+use util::toml::edit::Document;
 use util::graph::{Nodes, Edges};
 
 pub use self::encode::{EncodableResolve, EncodableDependency, EncodablePackageId};
 pub use self::encode::{Metadata, WorkspaceResolve};
 
+/// Like `bail!`, but for a dependency graph which can't be resolved, as
+/// opposed to failing to query a source while resolving.
+macro_rules! unresolvable {
+    ($($fmt:tt)*) => (
+        return Err(CargoErrorKind::Unresolvable(format!($($fmt)*)).into())
+    )
+}
+
 mod encode;
 
 /// Represents a fully resolved package dependency graph. Each node in the graph
@@ -254,6 +272,10 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         v
     }
 
+    pub fn checksums(&self) -> &HashMap<PackageId, Option<String>> {
+        &self.checksums
+    }
+
     pub fn query(&self, spec: &str) -> CargoResult<&PackageId> {
         PackageIdSpec::query_str(spec, self.iter())
     }
@@ -343,6 +365,8 @@ struct Context<'a> {
     //       make these much cheaper to clone in general.
     activations: Activations,
     resolve_features: HashMap<PackageId, HashSet<String>>,
+    // The native libraries linked to so far, and which package links to each.
+    links: HashMap<String, PackageId>,
 
     // These are two cheaply-cloneable lists (O(1) clone) which are effectively
     // hash maps but are built up as "construction lists". We'll iterate these
@@ -352,23 +376,49 @@ struct Context<'a> {
 
     replacements: &'a [(PackageIdSpec, Dependency)],
 
+    // Whether candidates are tried starting from the lowest version rather than
+    // the highest, for `-Z minimal-versions`.
+    minimal_versions: bool,
+
     // These warnings are printed after resolution.
     warnings: RcList<String>,
 }
 
 type Activations = HashMap<String, HashMap<SourceId, Vec<Summary>>>;
 
+/// The reason an activated package rules out candidates for a dependency.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ConflictReason {
+    /// The package is a semver-compatible version of the candidates.
+    Semver,
+    /// The package already links to the named native library.
+    Links(String),
+}
+
+/// The activated packages which ruled out all candidates for a dependency.
+type ConflictMap = HashMap<PackageId, ConflictReason>;
+
 /// Builds the list of all packages required to build the first argument.
+///
+/// Warnings gathered during resolution are only printed if `print_warnings` is
+/// set and a `config` is given.
 pub fn resolve(summaries: &[(Summary, Method)],
                replacements: &[(PackageIdSpec, Dependency)],
                registry: &mut Registry,
-               config: Option<&Config>) -> CargoResult<Resolve> {
+               config: Option<&Config>,
+               print_warnings: bool) -> CargoResult<Resolve> {
+    let minimal_versions = match config {
+        Some(config) => config.cli_unstable().minimal_versions,
+        None => false,
+    };
     let cx = Context {
         resolve_graph: RcList::new(),
         resolve_features: HashMap::new(),
         resolve_replacements: RcList::new(),
         activations: HashMap::new(),
+        links: HashMap::new(),
         replacements: replacements,
+        minimal_versions: minimal_versions,
         warnings: RcList::new(),
     };
     let _p = profile::start("resolving");
@@ -397,13 +447,16 @@ pub fn resolve(summaries: &[(Summary, Method)],
     trace!("resolved: {:?}", resolve);
 
     // If we have a shell, emit warnings about required deps used as feature.
-    if let Some(config) = config {
-        let mut shell = config.shell();
-        let mut warnings = &cx.warnings;
-        while let Some(ref head) = warnings.head {
-            shell.warn(&head.0)?;
-            warnings = &head.1;
+    match config {
+        Some(config) if print_warnings => {
+            let mut shell = config.shell();
+            let mut warnings = &cx.warnings;
+            while let Some(ref head) = warnings.head {
+                shell.warn(&head.0)?;
+                warnings = &head.1;
+            }
         }
+        _ => {}
     }
 
     Ok(resolve)
@@ -553,10 +606,23 @@ struct BacktrackFrame<'a> {
 #[derive(Clone)]
 struct RemainingCandidates {
     remaining: RcVecIter<Candidate>,
+    // The activated packages which ruled out the candidates skipped so far.
+    conflicting_prev_active: ConflictMap,
 }
 
 impl RemainingCandidates {
-    fn next(&mut self, prev_active: &[Summary]) -> Option<Candidate> {
+    fn new(candidates: &Rc<Vec<Candidate>>) -> RemainingCandidates {
+        RemainingCandidates {
+            remaining: RcVecIter::new(Rc::clone(candidates)),
+            conflicting_prev_active: HashMap::new(),
+        }
+    }
+
+    /// Returns the next usable candidate, or the packages which ruled out all
+    /// candidates that were skipped once there are none left.
+    fn next(&mut self,
+            prev_active: &[Summary],
+            links: &HashMap<String, PackageId>) -> Result<Candidate, ConflictMap> {
         // Filter the set of candidates based on the previously activated
         // versions for this dependency. We can actually use a version if it
         // precisely matches an activated version or if it is otherwise
@@ -564,15 +630,67 @@ impl RemainingCandidates {
         // define "compatible" here in terms of the semver sense where if
         // the left-most nonzero digit is the same they're considered
         // compatible.
-        self.remaining.by_ref().map(|p| p.1).find(|b| {
-            prev_active.iter().any(|a| *a == b.summary) ||
-                prev_active.iter().all(|a| {
-                    !compatible(a.version(), b.summary.version())
-                })
-        })
+        //
+        // A version which links to a native library that another package
+        // already links to can't be used either.
+        for (_, b) in self.remaining.by_ref() {
+            if let Some(id) = links_conflict(&b.summary, links) {
+                let links = b.summary.links().unwrap().to_string();
+                self.conflicting_prev_active.insert(id.clone(),
+                                                    ConflictReason::Links(links));
+                continue
+            }
+            if prev_active.iter().any(|a| *a == b.summary) {
+                return Ok(b)
+            }
+            let existing = prev_active.iter().find(|a| {
+                compatible(a.version(), b.summary.version())
+            });
+            if let Some(a) = existing {
+                self.conflicting_prev_active.insert(a.package_id().clone(),
+                                                    ConflictReason::Semver);
+                continue
+            }
+            return Ok(b)
+        }
+        Err(self.conflicting_prev_active.clone())
     }
 }
 
+/// Describes how `id` ended up in the dependency graph, following a chain of
+/// packages depending on it up to the root, one line per package.
+fn describe_path(graph: &Graph<PackageId>, id: &PackageId, indent: &str) -> String {
+    let mut msg = String::new();
+    let mut seen = HashSet::new();
+    seen.insert(id);
+    let mut cur = id;
+    loop {
+        let dependent = graph.iter().filter(|&node| {
+            !seen.contains(&node) && graph.edges(node).map_or(false, |mut edges| {
+                edges.any(|edge| edge == cur)
+            })
+        }).min();
+        match dependent {
+            Some(dependent) => {
+                msg.push_str(&format!("\n{}... which is depended on by `{}`",
+                                      indent, dependent));
+                seen.insert(dependent);
+                cur = dependent;
+            }
+            None => return msg,
+        }
+    }
+}
+
+/// Returns the package which already links to the same native library as
+/// `summary`, if any.
+fn links_conflict<'a>(summary: &Summary,
+                      links: &'a HashMap<String, PackageId>) -> Option<&'a PackageId> {
+    summary.links().and_then(|l| links.get(l)).and_then(|id| {
+        if id == summary.package_id() { None } else { Some(id) }
+    })
+}
+
 /// Recursively activates the dependencies for `top`, in depth-first order,
 /// backtracking across possible candidates for each dependency as necessary.
 ///
@@ -591,6 +709,10 @@ fn activate_deps_loop<'a>(mut cx: Context<'a>,
     // use (those with more candidates).
     let mut backtrack_stack = Vec::new();
     let mut remaining_deps = BinaryHeap::new();
+    // Conflicts which are known to rule out every candidate of a dependency,
+    // keyed by the name of the dependency.
+    let mut past_conflicts: HashMap<String, Vec<(Dependency, ConflictMap)>> =
+        HashMap::new();
     for &(ref summary, ref method) in summaries {
         debug!("initial activation: {}", summary.package_id());
         let candidate = Candidate { summary: summary.clone(), replace: None };
@@ -629,12 +751,24 @@ fn activate_deps_loop<'a>(mut cx: Context<'a>,
                    candidates.len());
             trace!("{}[{}]>{} {} prev activations", parent.name(), cur,
                    dep.name(), prev_active.len());
-            let mut candidates = RemainingCandidates {
-                remaining: RcVecIter::new(Rc::clone(&candidates)),
-            };
-            (candidates.next(prev_active),
-             candidates.clone().next(prev_active).is_some(),
-             candidates)
+            let mut candidates = RemainingCandidates::new(&candidates);
+            let known = past_conflicts.get(dep.name()).and_then(|past| {
+                past.iter().find(|&&(ref d, ref conflicts)| {
+                    *d == dep && conflicts.keys().all(|id| cx.is_active(id))
+                })
+            });
+            match known {
+                Some(&(_, ref conflicts)) => {
+                    trace!("{}[{}]>{} -- known conflict", parent.name(), cur,
+                           dep.name());
+                    (Err(conflicts.clone()), false, candidates)
+                }
+                None => {
+                    (candidates.next(prev_active, &cx.links),
+                     candidates.clone().next(prev_active, &cx.links).is_ok(),
+                     candidates)
+                }
+            }
         };
 
         // Alright, for each candidate that's gotten this far, it meets the
@@ -650,7 +784,7 @@ fn activate_deps_loop<'a>(mut cx: Context<'a>,
         // turn. We could possibly fail to activate each candidate, so we try
         // each one in turn.
         let candidate = match next {
-            Some(candidate) => {
+            Ok(candidate) => {
                 // We have a candidate. Add an entry to the `backtrack_stack` so
                 // we can try the next one if this one fails.
                 if has_another {
@@ -665,23 +799,30 @@ fn activate_deps_loop<'a>(mut cx: Context<'a>,
                 }
                 candidate
             }
-            None => {
+            Err(conflicts) => {
                 // This dependency has no valid candidate. Backtrack until we
                 // find a dependency that does have a candidate to try, and try
                 // to activate that one.  This resets the `remaining_deps` to
                 // their state at the found level of the `backtrack_stack`.
                 trace!("{}[{}]>{} -- no candidates", parent.name(), cur,
                        dep.name());
+                {
+                    let past = past_conflicts.entry(dep.name().to_string())
+                                             .or_insert_with(Vec::new);
+                    if !past.iter().any(|&(ref d, ref c)| *d == dep && *c == conflicts) {
+                        past.push((dep.clone(), conflicts.clone()));
+                    }
+                }
                 match find_candidate(&mut backtrack_stack,
                                      &mut cx,
                                      &mut remaining_deps,
                                      &mut parent,
                                      &mut cur,
                                      &mut dep,
-                                     &mut features) {
+                                     &mut features,
+                                     &conflicts) {
                     None => return Err(activation_error(&cx, registry, &parent,
-                                                        &dep,
-                                                        cx.prev_active(&dep),
+                                                        &dep, &conflicts,
                                                         &candidates)),
                     Some(candidate) => candidate,
                 }
@@ -705,20 +846,36 @@ fn activate_deps_loop<'a>(mut cx: Context<'a>,
 // Searches up `backtrack_stack` until it finds a dependency with remaining
 // candidates. Resets `cx` and `remaining_deps` to that level and returns the
 // next candidate. If all candidates have been exhausted, returns None.
+//
+// `dep` of `parent` failed to resolve because of the activated packages in
+// `conflicts`. Choices made after `parent` and all of those packages were
+// activated can't have caused the failure, so they're skipped entirely.
 fn find_candidate<'a>(backtrack_stack: &mut Vec<BacktrackFrame<'a>>,
                       cx: &mut Context<'a>,
                       remaining_deps: &mut BinaryHeap<DepsFrame>,
                       parent: &mut Summary,
                       cur: &mut usize,
                       dep: &mut Dependency,
-                      features: &mut Rc<Vec<String>>) -> Option<Candidate> {
+                      features: &mut Rc<Vec<String>>,
+                      conflicts: &ConflictMap) -> Option<Candidate> {
+    // An optional dependency may only have been enabled after the frame's
+    // choice, so only required ones allow skipping frames.
+    let required = !dep.is_optional();
     while let Some(mut frame) = backtrack_stack.pop() {
+        if required &&
+           frame.context_backup.is_active(parent.package_id()) &&
+           conflicts.keys().all(|id| frame.context_backup.is_active(id)) {
+            trace!("skipping {} for {}, it isn't part of the conflict",
+                   frame.dep.name(), dep.name());
+            continue
+        }
         let (next, has_another) = {
             let prev_active = frame.context_backup.prev_active(&frame.dep);
-            (frame.remaining_candidates.next(prev_active),
-             frame.remaining_candidates.clone().next(prev_active).is_some())
+            let links = &frame.context_backup.links;
+            (frame.remaining_candidates.next(prev_active, links),
+             frame.remaining_candidates.clone().next(prev_active, links).is_ok())
         };
-        if let Some(candidate) = next {
+        if let Ok(candidate) = next {
             if has_another {
                 *cx = frame.context_backup.clone();
                 *remaining_deps = frame.deps_backup.clone();
@@ -744,42 +901,61 @@ fn activation_error(cx: &Context,
                     registry: &mut Registry,
                     parent: &Summary,
                     dep: &Dependency,
-                    prev_active: &[Summary],
+                    conflicts: &ConflictMap,
                     candidates: &[Candidate]) -> CargoError {
     if !candidates.is_empty() {
-        let mut msg = format!("failed to select a version for `{}` \
-                               (required by `{}`):\n\
-                               all possible versions conflict with \
-                               previously selected versions of `{}`",
-                              dep.name(), parent.name(),
-                              dep.name());
         let graph = cx.graph();
-        'outer: for v in prev_active.iter() {
-            for node in graph.iter() {
-                let edges = match graph.edges(node) {
-                    Some(edges) => edges,
-                    None => continue,
-                };
-                for edge in edges {
-                    if edge != v.package_id() { continue }
-
-                    msg.push_str(&format!("\n  version {} in use by {}",
-                                          v.version(), edge));
-                    continue 'outer;
-                }
+        let mut msg = format!("failed to select a version for `{}` \
+                               (required by `{}`):",
+                              dep.name(), parent.name());
+        msg.push_str(&describe_path(&graph, parent.package_id(), "  "));
+
+        let mut conflicts = conflicts.iter().collect::<Vec<_>>();
+        conflicts.sort_by(|&(a, a_reason), &(b, b_reason)| {
+            a_reason.cmp(b_reason).then(a.cmp(b))
+        });
+
+        let mut linked = conflicts.iter().filter_map(|&(id, reason)| {
+            match *reason {
+                ConflictReason::Links(ref links) => Some((links, id)),
+                ConflictReason::Semver => None,
+            }
+        }).peekable();
+        let links = linked.peek().map(|&(links, _)| links);
+        if let Some(links) = links {
+            msg.push_str(&format!("\nthe package `{}` links to the native \
+                                   library `{}`, but it conflicts with a \
+                                   previous package which links to `{}` as \
+                                   well:",
+                                  dep.name(), links, links));
+            for (_, id) in linked {
+                msg.push_str(&format!("\n  package `{}`", id));
+                msg.push_str(&describe_path(&graph, id, "    "));
             }
-            msg.push_str(&format!("\n  version {} in use by ??",
-                                  v.version()));
+            msg.push_str("\nonly one package in the dependency graph may link \
+                          to a given native library");
         }
 
-        msg.push_str(&format!("\n  possible versions to select: {}",
-                              candidates.iter()
-                                        .map(|v| v.summary.version())
-                                        .map(|v| v.to_string())
-                                        .collect::<Vec<_>>()
-                                        .join(", ")));
+        let mut semver = conflicts.iter().filter(|&&(_, reason)| {
+            *reason == ConflictReason::Semver
+        }).peekable();
+        if semver.peek().is_some() {
+            msg.push_str(&format!("\nall possible versions conflict with \
+                                   previously selected versions of `{}`",
+                                  dep.name()));
+            for &(id, _) in semver {
+                msg.push_str(&format!("\n  previously selected package `{}`", id));
+                msg.push_str(&describe_path(&graph, id, "    "));
+            }
+            msg.push_str(&format!("\n  possible versions to select: {}",
+                                  candidates.iter()
+                                            .map(|v| v.summary.version())
+                                            .map(|v| v.to_string())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")));
+        }
 
-        return msg.into()
+        return CargoErrorKind::Unresolvable(msg).into()
     }
 
     // Once we're all the way down here, we're definitely lost in the
@@ -796,6 +972,14 @@ fn activation_error(cx: &Context,
         Ok(candidates) => candidates,
         Err(e) => return e,
     };
+    // `*` doesn't match pre-releases, so they're looked up separately.
+    let any_dep = Dependency::new_override(dep.name(), dep.source_id());
+    match registry.query_vec(&any_dep) {
+        Ok(all) => {
+            candidates.extend(all.into_iter().filter(|s| s.version().is_prerelease()));
+        }
+        Err(e) => return e,
+    }
     candidates.sort_by(|a, b| {
         b.version().cmp(a.version())
     });
@@ -823,6 +1007,21 @@ fn activation_error(cx: &Context,
                               dep.source_id(),
                               versions);
 
+        // Pre-releases are only selected by requirements which include one,
+        // so point out the newest one which would otherwise have matched.
+        let prerelease = candidates.iter().map(|c| c.version()).find(|v| {
+            let mut release = (*v).clone();
+            release.pre.clear();
+            v.is_prerelease() && dep.version_req().matches(&release)
+        });
+        if let Some(version) = prerelease {
+            msg.push_str(&format!("\nthe pre-release version `{}` would match, \
+                                   but pre-releases are only selected when \
+                                   the version requirement includes one, \
+                                   e.g. `{} = \"{}\"`",
+                                  version, dep.name(), version));
+        }
+
         // If we have a path dependency with a locked version, then this may
         // indicate that we updated a sub-package and forgot to run `cargo
         // update`. In this case try to print a helpful error!
@@ -834,16 +1033,76 @@ fn activation_error(cx: &Context,
 
         msg
     } else {
-        format!("no matching package named `{}` found \
-                 (required by `{}`)\n\
-                 location searched: {}\n\
-                 version required: {}",
-                dep.name(), parent.name(),
-                dep.source_id(),
-                dep.version_req())
+        let mut msg = format!("no matching package named `{}` found \
+                               (required by `{}`)\n\
+                               location searched: {}\n\
+                               version required: {}",
+                              dep.name(), parent.name(),
+                              dep.source_id(),
+                              dep.version_req());
+
+        // Git repositories are easily pointed at under the wrong name, so
+        // say what was actually found in them.
+        let names = registry.package_names(dep);
+        if !names.is_empty() {
+            let names = names.iter().map(|n| format!("`{}`", n))
+                             .collect::<Vec<_>>();
+            let declared = match dependency_location(parent, dep.name()) {
+                Some(location) => format!(" declared at {}", location),
+                None => String::new(),
+            };
+            if names.len() == 1 {
+                msg.push_str(&format!("\nthe repository contains a package \
+                                       named {} instead, perhaps the \
+                                       dependency{} was meant to be named {}?",
+                                      names[0], declared, names[0]));
+            } else {
+                msg.push_str(&format!("\nthe repository contains packages \
+                                       named {} instead, perhaps the \
+                                       dependency{} was meant to be named \
+                                       after one of them?",
+                                      names.join(", "), declared));
+            }
+        }
+
+        msg
     };
 
-    msg.into()
+    CargoErrorKind::Unresolvable(msg).into()
+}
+
+/// Finds the line of `parent`'s manifest which declares the dependency `name`
+/// as `path:line`, if `parent` is a local package.
+fn dependency_location(parent: &Summary, name: &str) -> Option<String> {
+    let source_id = parent.package_id().source_id();
+    if !source_id.is_path() {
+        return None
+    }
+    let manifest = match source_id.url().to_file_path() {
+        Ok(root) => root.join("Cargo.toml"),
+        Err(..) => return None,
+    };
+    let doc = match paths::read(&manifest).and_then(|c| Document::parse(&c)) {
+        Ok(doc) => doc,
+        Err(..) => return None,
+    };
+
+    // `[target.'cfg(..)'.dependencies]` and friends are dependency tables
+    // too, as are the spellings with underscores.
+    let declares = |path: &[String]| {
+        let path = if path.len() > 2 && path[0] == "target" { &path[2..] } else { path };
+        if path.len() < 2 || path[1] != name {
+            return false
+        }
+        match &path[0][..] {
+            "dependencies" | "dev-dependencies" | "dev_dependencies" |
+            "build-dependencies" | "build_dependencies" => true,
+            _ => false,
+        }
+    };
+    doc.keys().into_iter().find(|&(ref path, _)| declares(&path[..])).map(|(_, lines)| {
+        format!("{}:{}", manifest.display(), lines.start + 1)
+    })
 }
 
 // Returns if `a` and `b` are compatible in the semver sense. This is a
@@ -880,7 +1139,12 @@ fn build_features<'a>(s: &'a Summary, method: &'a Method)
             for key in s.features().keys() {
                 add_feature(s, key, &mut deps, &mut used, &mut visited)?;
             }
+            // Dependencies enabled with `dep:` aren't features, but they've
+            // all been enabled through the features above.
             for dep in s.dependencies().iter().filter(|d| d.is_optional()) {
+                if s.is_explicit_dep(dep.name()) {
+                    continue
+                }
                 add_feature(s, dep.name(), &mut deps, &mut used,
                             &mut visited)?;
             }
@@ -910,6 +1174,13 @@ fn build_features<'a>(s: &'a Summary, method: &'a Method)
                        visited: &mut HashSet<&'a str>) -> CargoResult<()> {
         if feat.is_empty() { return Ok(()) }
 
+        // `dep:foo` enables the optional dependency `foo` without there being
+        // a feature named `foo`, so nothing is added to `used`.
+        if feat.starts_with("dep:") {
+            deps.entry(&feat[4..]).or_insert((false, Vec::new())).0 = true;
+            return Ok(())
+        }
+
         // If this feature is of the form `foo/bar`, then we just lookup package
         // `foo` and enable its feature `bar`. Otherwise this feature is of the
         // form `foo` and we need to recurse to enable the feature `foo` for our
@@ -920,7 +1191,9 @@ fn build_features<'a>(s: &'a Summary, method: &'a Method)
         match parts.next() {
             Some(feat) => {
                 let package = feat_or_package;
-                used.insert(package);
+                if !s.is_explicit_dep(package) {
+                    used.insert(package);
+                }
                 deps.entry(package)
                     .or_insert((false, Vec::new()))
                     .1.push(feat.to_string());
@@ -939,13 +1212,19 @@ fn build_features<'a>(s: &'a Summary, method: &'a Method)
                         // This is a feature, add it recursively.
                         for f in recursive {
                             if f == feat {
-                                bail!("Cyclic feature dependency: feature `{}` depends \
-                                        on itself", feat);
+                                unresolvable!("Cyclic feature dependency: feature `{}` depends \
+                                                on itself", feat);
                             }
 
                             add_feature(s, f, deps, used, visited)?;
                         }
                     }
+                    None if s.is_explicit_dep(feat) => {
+                        unresolvable!("Package `{}` does not have feature `{}`. It has an \
+                                       optional dependency with that name, but it can only \
+                                       be enabled through the features which include \
+                                       `dep:{}`", s.package_id(), feat, feat)
+                    }
                     None => {
                         // This is a dependency, mark it as explicitly requested.
                         deps.entry(feat).or_insert((false, Vec::new())).0 = true;
@@ -972,6 +1251,9 @@ impl<'a> Context<'a> {
                        .or_insert(Vec::new());
         if !prev.iter().any(|c| c == summary) {
             self.resolve_graph.push(GraphNode::Add(id.clone()));
+            if let Some(links) = summary.links() {
+                self.links.insert(links.to_string(), id.clone());
+            }
             prev.push(summary.clone());
             return false
         }
@@ -1008,9 +1290,15 @@ impl<'a> Context<'a> {
         let mut deps = deps.into_iter().map(|(dep, features)| {
             let mut candidates = self.query(registry, &dep)?;
             // When we attempt versions for a package, we'll want to start at
-            // the maximum version and work our way down.
+            // the maximum version and work our way down, unless the lowest
+            // versions were asked for.
+            let minimal_versions = self.minimal_versions;
             candidates.sort_by(|a, b| {
-                b.summary.version().cmp(a.summary.version())
+                if minimal_versions {
+                    a.summary.version().cmp(b.summary.version())
+                } else {
+                    b.summary.version().cmp(a.summary.version())
+                }
             });
             Ok((dep, Rc::new(candidates), Rc::new(features)))
         }).collect::<CargoResult<Vec<DepInfo>>>()?;
@@ -1051,19 +1339,20 @@ impl<'a> Context<'a> {
 
             let mut summaries = registry.query_vec(dep)?.into_iter();
             let s = summaries.next().ok_or_else(|| {
-                format!("no matching package for override `{}` found\n\
-                         location searched: {}\n\
-                         version required: {}",
-                         spec, dep.source_id(), dep.version_req())
+                CargoErrorKind::Unresolvable(format!(
+                    "no matching package for override `{}` found\n\
+                     location searched: {}\n\
+                     version required: {}",
+                    spec, dep.source_id(), dep.version_req()))
             })?;
             let summaries = summaries.collect::<Vec<_>>();
             if !summaries.is_empty() {
                 let bullets = summaries.iter().map(|s| {
                     format!("  * {}", s.package_id())
                 }).collect::<Vec<_>>();
-                bail!("the replacement specification `{}` matched \
-                       multiple packages:\n  * {}\n{}", spec, s.package_id(),
-                      bullets.join("\n"));
+                unresolvable!("the replacement specification `{}` matched \
+                               multiple packages:\n  * {}\n{}", spec, s.package_id(),
+                              bullets.join("\n"));
             }
 
             // The dependency should be hard-coded to have the same name and an
@@ -1082,9 +1371,9 @@ impl<'a> Context<'a> {
 
             // Make sure no duplicates
             if let Some(&(ref spec, _)) = potential_matches.next() {
-                bail!("overlapping replacement specifications found:\n\n  \
-                       * {}\n  * {}\n\nboth specifications match: {}",
-                      matched_spec, spec, summary.package_id());
+                unresolvable!("overlapping replacement specifications found:\n\n  \
+                               * {}\n  * {}\n\nboth specifications match: {}",
+                              matched_spec, spec, summary.package_id());
             }
 
             for dep in summary.dependencies() {
@@ -1096,6 +1385,13 @@ impl<'a> Context<'a> {
         Ok(ret)
     }
 
+    fn is_active(&self, id: &PackageId) -> bool {
+        self.activations.get(id.name())
+            .and_then(|v| v.get(id.source_id()))
+            .map(|v| v.iter().any(|s| s.package_id() == id))
+            .unwrap_or(false)
+    }
+
     fn prev_active(&self, dep: &Dependency) -> &[Summary] {
         self.activations.get(dep.name())
             .and_then(|v| v.get(dep.source_id()))
@@ -1142,7 +1438,7 @@ impl<'a> Context<'a> {
             base.extend(dep.features().iter().cloned());
             for feature in base.iter() {
                 if feature.contains("/") {
-                    bail!("feature names may not contain slashes: `{}`", feature);
+                    unresolvable!("feature names may not contain slashes: `{}`", feature);
                 }
             }
             ret.push((dep.clone(), base));
@@ -1155,8 +1451,8 @@ impl<'a> Context<'a> {
             let unknown = feature_deps.keys().map(|s| &s[..])
                                       .collect::<Vec<&str>>();
             let features = unknown.join(", ");
-            bail!("Package `{}` does not have these features: `{}`",
-                    s.package_id(), features)
+            unresolvable!("Package `{}` does not have these features: `{}`",
+                            s.package_id(), features)
         }
 
         // Record what list of features is active for this package.
@@ -1230,8 +1526,8 @@ fn check_cycles(resolve: &Resolve, activations: &Activations)
                  -> CargoResult<()> {
         // See if we visited ourselves
         if !visited.insert(id) {
-            bail!("cyclic package dependency: package `{}` depends on itself",
-                  id);
+            unresolvable!("cyclic package dependency: package `{}` depends on itself",
+                          id);
         }
 
         // If we've already checked this node no need to recurse again as we'll
@@ -2,7 +2,7 @@ use std::fmt;
 use std::io::prelude::*;
 
 use atty;
-use termcolor::Color::{Green, Red, Yellow};
+use termcolor::Color::{Cyan, Green, Red, Yellow};
 use termcolor::{self, StandardStream, Color, ColorSpec, WriteColor};
 
 use util::errors::CargoResult;
@@ -23,6 +23,9 @@ pub struct Shell {
     err: ShellOut,
     /// How verbose messages should be
     verbosity: Verbosity,
+    /// Whether a progress line was printed which has to be cleared before
+    /// anything else is printed.
+    needs_clear: bool,
 }
 
 impl fmt::Debug for Shell {
@@ -68,6 +71,7 @@ impl Shell {
                 ColorChoice::CargoAuto,
             ),
             verbosity: Verbosity::Verbose,
+            needs_clear: false,
         }
     }
 
@@ -76,6 +80,7 @@ impl Shell {
         Shell {
             err: ShellOut::Write(out),
             verbosity: Verbosity::Verbose,
+            needs_clear: false,
         }
     }
 
@@ -89,11 +94,54 @@ impl Shell {
         match self.verbosity {
             Verbosity::Quiet => Ok(()),
             _ => {
+                self.clear_progress()?;
                 self.err.print(status, message, color, justified)
             }
         }
     }
 
+    /// Prints a status message which is overwritten by the next one, or by
+    /// whatever is printed next, for reporting the progress of something
+    /// which takes a while.
+    ///
+    /// Nothing is printed unless stderr is a terminal, as the line can only be
+    /// overwritten there.
+    pub fn progress<T, U>(&mut self, status: T, message: U) -> CargoResult<()>
+        where T: fmt::Display, U: fmt::Display
+    {
+        if self.verbosity == Verbosity::Quiet || !self.is_err_tty() {
+            return Ok(())
+        }
+        if let ShellOut::Stream(ref mut err, _) = self.err {
+            err.reset()?;
+            err.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Cyan)))?;
+            write!(err, "\r{:>12}", status)?;
+            err.reset()?;
+            // Clear the rest of the line, as it may be shorter than the last.
+            write!(err, " {}\x1b[K", message)?;
+            err.flush()?;
+        }
+        self.needs_clear = true;
+        Ok(())
+    }
+
+    /// Clears the line printed by `progress`, if there's one.
+    pub fn clear_progress(&mut self) -> CargoResult<()> {
+        if self.needs_clear {
+            self.needs_clear = false;
+            write!(self.err.as_write(), "\r\x1b[K")?;
+        }
+        Ok(())
+    }
+
+    /// Whether stderr is a terminal.
+    pub fn is_err_tty(&self) -> bool {
+        match self.err {
+            ShellOut::Stream(..) => atty::is(atty::Stream::Stderr),
+            ShellOut::Write(_) => false,
+        }
+    }
+
     /// Get a reference to the underlying writer
     pub fn err(&mut self) -> &mut Write {
         self.err.as_write()
@@ -149,6 +197,26 @@ impl Shell {
         }
     }
 
+    /// Print a diagnostic from the compiler: its `label` like `error[E0425]`
+    /// colored by its `level`, followed by its message and the `rest` of it.
+    /// Unlike other messages these are printed even when quiet.
+    pub fn diagnostic(&mut self,
+                      level: &str,
+                      label: &str,
+                      message: &str,
+                      rest: &str) -> CargoResult<()> {
+        let color = match level {
+            "error" | "error: internal compiler error" => Red,
+            "warning" => Yellow,
+            "note" => Green,
+            _ => Cyan,
+        };
+        self.clear_progress()?;
+        self.err.print(&format!("{}:", label), &message, color, false)?;
+        write!(self.err.as_write(), "{}", rest)?;
+        Ok(())
+    }
+
     /// Update the verbosity of the shell
     pub fn set_verbosity(&mut self, verbosity: Verbosity) {
         self.verbosity = verbosity;
@@ -22,6 +22,20 @@ pub trait Source: Registry {
     /// version specified.
     fn download(&mut self, package: &PackageId) -> CargoResult<Package>;
 
+    /// Gets ready to `download` all of `packages`.
+    ///
+    /// Sources which have to fetch packages over the network can fetch them
+    /// all at once here, rather than one at a time as they're downloaded.
+    fn prefetch(&mut self, _packages: &[PackageId]) -> CargoResult<()> {
+        Ok(())
+    }
+
+    /// Whether `download` can hand out `package` without fetching it over the
+    /// network first.
+    fn is_downloaded(&self, _package: &PackageId) -> bool {
+        true
+    }
+
     /// Generates a unique string which represents the fingerprint of the
     /// current state of the source.
     ///
@@ -61,6 +75,16 @@ impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
         (**self).download(id)
     }
 
+    /// Forwards to `Source::prefetch`
+    fn prefetch(&mut self, packages: &[PackageId]) -> CargoResult<()> {
+        (**self).prefetch(packages)
+    }
+
+    /// Forwards to `Source::is_downloaded`
+    fn is_downloaded(&self, package: &PackageId) -> bool {
+        (**self).is_downloaded(package)
+    }
+
     /// Forwards to `Source::fingerprint`
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
         (**self).fingerprint(pkg)
@@ -165,7 +165,7 @@ impl SourceId {
     /// This is the main cargo registry by default, but it can be overridden in
     /// a `.cargo/config`.
     pub fn crates_io(config: &Config) -> CargoResult<SourceId> {
-        let cfg = ops::registry_configuration(config)?;
+        let cfg = ops::registry_configuration(config, None)?;
         let url = if let Some(ref index) = cfg.index {
             static WARNED: AtomicBool = ATOMIC_BOOL_INIT;
             if !WARNED.swap(true, SeqCst) {
@@ -218,6 +218,11 @@ impl SourceId {
         }
     }
 
+    /// Is this source from a remote registry
+    pub fn is_remote_registry(&self) -> bool {
+        self.inner.kind == Kind::Registry
+    }
+
     /// Is this source from a git repository
     pub fn is_git(&self) -> bool {
         match self.inner.kind {
@@ -296,7 +301,12 @@ impl SourceId {
         if self.is_path() {
             if let Ok(p) = self.inner.url.to_file_path().unwrap().strip_prefix(workspace) {
                 self.inner.kind.hash(into);
-                p.to_str().unwrap().hash(into);
+                // Hash UTF-8 paths as strings so existing hashes stay the
+                // same, anything else is hashed as the native path.
+                match p.to_str() {
+                    Some(s) => s.hash(into),
+                    None => p.hash(into),
+                }
                 return
             }
         }
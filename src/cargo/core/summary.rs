@@ -22,6 +22,7 @@ struct Inner {
     dependencies: Vec<Dependency>,
     features: BTreeMap<String, Vec<String>>,
     checksum: Option<String>,
+    links: Option<String>,
 }
 
 impl Summary {
@@ -29,7 +30,8 @@ impl Summary {
                dependencies: Vec<Dependency>,
                features: BTreeMap<String, Vec<String>>) -> CargoResult<Summary> {
         for dep in dependencies.iter() {
-            if features.get(dep.name()).is_some() {
+            if features.get(dep.name()).is_some() &&
+               !(dep.is_optional() && is_explicit_dep(&features, dep.name())) {
                 bail!("Features and dependencies cannot have the \
                        same name: `{}`", dep.name())
             }
@@ -39,28 +41,13 @@ impl Summary {
             }
         }
         for (feature, list) in features.iter() {
-            for dep in list.iter() {
-                let mut parts = dep.splitn(2, '/');
-                let dep = parts.next().unwrap();
-                let is_reexport = parts.next().is_some();
-                if !is_reexport && features.get(dep).is_some() { continue }
-                match dependencies.iter().find(|d| d.name() == dep) {
-                    Some(d) => {
-                        if d.is_optional() || is_reexport { continue }
-                        bail!("Feature `{}` depends on `{}` which is not an \
-                               optional dependency.\nConsider adding \
-                               `optional = true` to the dependency",
-                               feature, dep)
-                    }
-                    None if is_reexport => {
-                        bail!("Feature `{}` requires a feature of `{}` which is not a \
-                               dependency", feature, dep)
-                    }
-                    None => {
-                        bail!("Feature `{}` includes `{}` which is neither \
-                               a dependency nor another feature", feature, dep)
-                    }
-                }
+            if feature.starts_with("dep:") {
+                bail!("Feature `{}` is not allowed, the `dep:` prefix is only \
+                       used to enable optional dependencies within a feature",
+                      feature)
+            }
+            for entry in list.iter() {
+                validate_feature_entry(feature, entry, &features, &dependencies)?;
             }
         }
         Ok(Summary {
@@ -69,6 +56,7 @@ impl Summary {
                 dependencies: dependencies,
                 features: features,
                 checksum: None,
+                links: None,
             }),
         })
     }
@@ -82,6 +70,17 @@ impl Summary {
     pub fn checksum(&self) -> Option<&str> {
         self.inner.checksum.as_ref().map(|s| &s[..])
     }
+    /// The native library this package links to, see the `links` manifest
+    /// key. Only one package in a graph may link to a given library.
+    pub fn links(&self) -> Option<&str> {
+        self.inner.links.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns whether `name` is an optional dependency which doesn't have a
+    /// feature of the same name, because it's enabled with `dep:name`.
+    pub fn is_explicit_dep(&self, name: &str) -> bool {
+        is_explicit_dep(&self.inner.features, name)
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         Rc::make_mut(&mut self.inner).package_id = id;
@@ -93,6 +92,11 @@ impl Summary {
         self
     }
 
+    pub fn set_links(mut self, links: Option<String>) -> Summary {
+        Rc::make_mut(&mut self.inner).links = links;
+        self
+    }
+
     pub fn map_dependencies<F>(mut self, f: F) -> Summary
                                where F: FnMut(Dependency) -> Dependency {
         {
@@ -117,6 +121,79 @@ impl Summary {
     }
 }
 
+/// Returns whether any feature enables the dependency `name` with `dep:name`.
+///
+/// Optional dependencies are implicitly features of the same name, unless
+/// they're referred to this way.
+fn is_explicit_dep(features: &BTreeMap<String, Vec<String>>, name: &str) -> bool {
+    features.values().flat_map(|list| list).any(|entry| {
+        entry.starts_with("dep:") && &entry[4..] == name
+    })
+}
+
+/// Checks that `entry`, listed by `feature`, refers to another feature, an
+/// optional dependency (either by name or as `dep:name`) or a feature of a
+/// dependency (as `name/feature`).
+pub fn validate_feature_entry(feature: &str,
+                              entry: &str,
+                              features: &BTreeMap<String, Vec<String>>,
+                              dependencies: &[Dependency]) -> CargoResult<()> {
+    if entry.starts_with("dep:") {
+        let name = &entry[4..];
+        if name.contains('/') {
+            bail!("Feature `{}` includes `{}`, but `dep:` can't be used to \
+                   enable a feature of a dependency, use `{}` instead",
+                  feature, entry, name)
+        }
+        return match dependencies.iter().find(|d| d.name() == name) {
+            Some(d) if d.is_optional() => Ok(()),
+            Some(_) => {
+                bail!("Feature `{}` includes `{}`, but `{}` is not an optional \
+                       dependency.\nConsider adding `optional = true` to the \
+                       dependency", feature, entry, name)
+            }
+            None => {
+                bail!("Feature `{}` includes `{}`, but `{}` is not a dependency",
+                      feature, entry, name)
+            }
+        }
+    }
+
+    let mut parts = entry.splitn(2, '/');
+    let dep = parts.next().unwrap();
+    let is_reexport = parts.next().is_some();
+    if !is_reexport && features.get(dep).is_some() {
+        return Ok(())
+    }
+    match dependencies.iter().find(|d| d.name() == dep) {
+        Some(d) => {
+            if is_reexport {
+                return Ok(())
+            }
+            if !d.is_optional() {
+                bail!("Feature `{}` depends on `{}` which is not an \
+                       optional dependency.\nConsider adding \
+                       `optional = true` to the dependency",
+                       feature, dep)
+            }
+            if is_explicit_dep(features, dep) {
+                bail!("Feature `{}` includes `{}`, but `{}` is only enabled \
+                       with `dep:{}` elsewhere, use `dep:{}` here as well",
+                      feature, dep, dep, dep, dep)
+            }
+            Ok(())
+        }
+        None if is_reexport => {
+            bail!("Feature `{}` requires a feature of `{}` which is not a \
+                   dependency", feature, dep)
+        }
+        None => {
+            bail!("Feature `{}` includes `{}` which is neither \
+                   a dependency nor another feature", feature, dep)
+        }
+    }
+}
+
 impl PartialEq for Summary {
     fn eq(&self, other: &Summary) -> bool {
         self.inner.package_id == other.inner.package_id
@@ -7,7 +7,7 @@ use glob::glob;
 use url::Url;
 
 use core::{Package, VirtualManifest, EitherManifest, SourceId};
-use core::{PackageIdSpec, Dependency, Profile, Profiles};
+use core::{PackageIdSpec, Dependency, Profile, Profiles, ProfileOverrides};
 use util::{Config, Filesystem};
 use util::errors::{CargoResult, CargoResultExt};
 use util::paths;
@@ -231,6 +231,12 @@ impl<'cfg> Workspace<'cfg> {
         })
     }
 
+    /// Places the build output in `dir` rather than the configured target
+    /// directory.
+    pub fn set_target_dir(&mut self, dir: Filesystem) {
+        self.target_dir = Some(dir);
+    }
+
     /// Returns the root [replace] section of this workspace.
     ///
     /// This may be from a virtual crate or an actual crate.
@@ -547,6 +553,8 @@ impl<'cfg> Workspace<'cfg> {
                 check: Profile::default_check(),
                 check_test: Profile::default_check_test(),
                 doctest: Profile::default_doctest(),
+                dev_overrides: ProfileOverrides::default(),
+                release_overrides: ProfileOverrides::default(),
             };
 
             for pkg in self.members().filter(|p| p.manifest_path() != root_manifest) {
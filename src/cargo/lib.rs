@@ -1,3 +1,51 @@
+//! Cargo as a library.
+//!
+//! Everything the `cargo` binary does is implemented here, so other tools can
+//! load manifests, resolve dependency graphs and drive builds without
+//! shelling out. The crate is organized as:
+//!
+//! * `core`: the data model, i.e. `Workspace`, `Package`, `Manifest`,
+//!   `Dependency`, `Resolve` and the dependency resolver itself.
+//! * `sources`: where packages come from, such as paths, git repositories and
+//!   registries.
+//! * `ops`: one entry point per operation Cargo supports, e.g.
+//!   `ops::read_package`, `ops::resolve_ws` and `ops::compile`.
+//! * `util`: configuration (`Config`), errors and everything else shared.
+//! * `cli`: helpers only needed by the binary and by external subcommands.
+//!
+//! Every operation starts from a `Config`, which reads `.cargo/config` files
+//! and the environment, and most then work on a `Workspace`:
+//!
+//! ```no_run
+//! extern crate cargo;
+//!
+//! use cargo::core::Workspace;
+//! use cargo::ops::{self, CompileMode, CompileOptions};
+//! use cargo::util::Config;
+//!
+//! # fn main() { run().unwrap() }
+//! fn run() -> cargo::CargoResult<()> {
+//!     let config = Config::default()?;
+//!     let manifest = config.cwd().join("Cargo.toml");
+//!     let ws = Workspace::new(&manifest, &config)?;
+//!
+//!     // Look at the resolved dependency graph...
+//!     let (_packages, resolve) = ops::resolve_ws(&ws)?;
+//!     for id in resolve.iter() {
+//!         println!("{}", id);
+//!     }
+//!
+//!     // ...and build the workspace, as `cargo build` would.
+//!     let options = CompileOptions::default(&config, CompileMode::Build);
+//!     ops::compile(&ws, &options)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! To hook into a build, e.g. to inspect the resolved graph before anything
+//! is built or to intercept invocations of rustc, implement `ops::Executor`
+//! and pass it to `ops::compile_with_exec`.
+
 #![deny(unused)]
 #![cfg_attr(test, deny(warnings))]
 #![recursion_limit="128"]
@@ -41,14 +89,14 @@ use std::fmt;
 use std::error::Error;
 
 use error_chain::ChainedError;
-use serde::Deserialize;
-use serde::ser;
-use docopt::Docopt;
 
 use core::Shell;
 use core::shell::Verbosity::Verbose;
 
 pub use util::{CargoError, CargoErrorKind, CargoResult, CliError, CliResult, Config};
+// These used to live at the root of the crate, keep them here for existing
+// external subcommands.
+pub use cli::{call_main_without_stdin, exit_with_error, print_json};
 
 pub const CARGO_ENV: &'static str = "CARGO";
 
@@ -58,6 +106,7 @@ macro_rules! bail {
     )
 }
 
+pub mod cli;
 pub mod core;
 pub mod ops;
 pub mod sources;
@@ -108,58 +157,6 @@ impl fmt::Display for VersionInfo {
     }
 }
 
-pub fn call_main_without_stdin<'de, Flags: Deserialize<'de>>(
-            exec: fn(Flags, &mut Config) -> CliResult,
-            config: &mut Config,
-            usage: &str,
-            args: &[String],
-            options_first: bool) -> CliResult
-{
-    let docopt = Docopt::new(usage).unwrap()
-        .options_first(options_first)
-        .argv(args.iter().map(|s| &s[..]))
-        .help(true);
-
-    let flags = docopt.deserialize().map_err(|e| {
-        let code = if e.fatal() {1} else {0};
-        CliError::new(e.to_string().into(), code)
-    })?;
-
-    exec(flags, config)
-}
-
-pub fn print_json<T: ser::Serialize>(obj: &T) {
-    let encoded = serde_json::to_string(&obj).unwrap();
-    println!("{}", encoded);
-}
-
-pub fn exit_with_error(err: CliError, shell: &mut Shell) -> ! {
-    debug!("exit_with_error; err={:?}", err);
-
-    let CliError { error, exit_code, unknown } = err;
-    // exit_code == 0 is non-fatal error, e.g. docopt version info
-    let fatal = exit_code != 0;
-
-    let hide = unknown && shell.verbosity() != Verbose;
-
-    if let Some(error) = error {
-        if hide {
-            drop(shell.error("An unknown error occurred"))
-        } else if fatal {
-            drop(shell.error(&error))
-        } else {
-            drop(writeln!(shell.err(), "{}", error))
-        }
-
-        if !handle_cause(error, shell) || hide {
-            drop(writeln!(shell.err(), "\nTo learn more, run the command again \
-                                        with --verbose."));
-        }
-    }
-
-    std::process::exit(exit_code)
-}
-
 pub fn handle_error(err: CargoError, shell: &mut Shell) {
     debug!("handle_error; err={:?}", &err);
 
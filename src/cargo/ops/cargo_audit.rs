@@ -0,0 +1,222 @@
+use std::fmt;
+
+use semver::VersionReq;
+use serde_json;
+
+use core::{PackageId, Resolve, Workspace};
+use ops;
+use util::{network, paths, CargoResult, CargoResultExt, Config};
+use util::errors::CargoErrorKind;
+
+/// Where the copy of the database fetched last is kept, relative to Cargo's
+/// home directory. It's used when the network can't be, with `--frozen`.
+const CACHE: &'static str = "advisory-db.json";
+
+pub struct AuditOptions<'a> {
+    /// Overrides the `audit.database` configuration key
+    pub database: Option<&'a str>,
+}
+
+/// The vulnerability database, a JSON document at the configured URL.
+#[derive(Deserialize)]
+struct Database {
+    advisories: Vec<Advisory>,
+}
+
+#[derive(Deserialize)]
+struct Advisory {
+    /// Identifier of the advisory, e.g. `RUSTSEC-2017-0001`
+    id: String,
+    /// Name of the affected package
+    package: String,
+    title: String,
+    /// `critical`, `high`, `medium` or `low`, if known
+    severity: Option<String>,
+    url: Option<String>,
+    /// Versions with the vulnerability fixed
+    #[serde(default)]
+    patched: Vec<String>,
+    /// Versions which never had the vulnerability
+    #[serde(default)]
+    unaffected: Vec<String>,
+    /// Affected commits, for packages used from git, each at least 7 hex
+    /// digits of the commit id
+    #[serde(default)]
+    revisions: Vec<String>,
+}
+
+/// An advisory which applies to a locked package.
+struct Finding<'a> {
+    advisory: &'a Advisory,
+    id: &'a PackageId,
+    /// The package itself followed by the packages that depend on it, all the
+    /// way up to a workspace member.
+    chain: Vec<&'a PackageId>,
+}
+
+/// Executes `cargo audit`.
+///
+/// The vulnerability database is fetched from `audit.database` (or the copy
+/// cached by the last run when the network isn't available), and each
+/// package in the lock file is checked against its advisories. Registry
+/// packages are affected if their version is neither patched nor unaffected,
+/// git packages if their locked revision is listed. Any finding is an error.
+pub fn audit(ws: &Workspace, opts: &AuditOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!("a Cargo.lock must exist to audit dependencies, run \
+                       `cargo generate-lockfile` first"),
+    };
+    let database = load_database(config, opts)?;
+
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+    let mut findings = Vec::new();
+    for id in ids {
+        for advisory in database.advisories.iter().filter(|a| a.package == id.name()) {
+            if affects(advisory, id)? {
+                findings.push(Finding {
+                    advisory: advisory,
+                    id: id,
+                    chain: chain(&resolve, id),
+                });
+            }
+        }
+    }
+
+    if findings.is_empty() {
+        config.shell().status("Finished", format!(
+            "no known vulnerabilities in {} locked packages",
+            resolve.iter().count()))?;
+        return Ok(())
+    }
+    for finding in findings.iter() {
+        print_finding(finding);
+    }
+    bail!("found {} known {} in the locked dependencies",
+          findings.len(),
+          if findings.len() == 1 { "vulnerability" } else { "vulnerabilities" })
+}
+
+fn load_database(config: &Config, opts: &AuditOptions) -> CargoResult<Database> {
+    let cache = config.home().join(CACHE).into_path_unlocked();
+    let contents = if config.network_allowed() {
+        let url = match opts.database {
+            Some(url) => url.to_string(),
+            None => match config.get_string("audit.database")? {
+                Some(url) => url.val,
+                None => bail!("no vulnerability database is configured, set \
+                               `audit.database` to its URL or pass `--database`"),
+            },
+        };
+        config.shell().status("Fetching", format!("advisory database `{}`", url))?;
+        let contents = download(config, &url)?;
+        paths::write(&cache, &contents)?;
+        contents
+    } else {
+        paths::read_bytes(&cache).chain_err(|| {
+            "the advisory database hasn't been fetched yet, and the network \
+             can't be used as `--frozen` was passed"
+        })?
+    };
+    serde_json::from_slice(&contents).chain_err(|| {
+        "failed to parse the advisory database"
+    })
+}
+
+fn download(config: &Config, url: &str) -> CargoResult<Vec<u8>> {
+    let mut handle = config.http()?.borrow_mut();
+    handle.get(true)?;
+    handle.url(url)?;
+    handle.follow_location(true)?;
+    let mut body = Vec::new();
+    network::with_retry(config, || {
+        body = Vec::new();
+        {
+            let mut handle = handle.transfer();
+            handle.write_function(|buf| {
+                body.extend_from_slice(buf);
+                Ok(buf.len())
+            })?;
+            handle.perform()?;
+        }
+        let code = handle.response_code()?;
+        if code != 200 && code != 0 {
+            let url = handle.effective_url()?.unwrap_or(url);
+            Err(CargoErrorKind::HttpNot200(code, url.to_string()).into())
+        } else {
+            Ok(())
+        }
+    }).chain_err(|| format!("failed to fetch the advisory database from `{}`", url))?;
+    config.record_fetched(url, body.len() as u64);
+    Ok(body)
+}
+
+fn affects(advisory: &Advisory, id: &PackageId) -> CargoResult<bool> {
+    let source_id = id.source_id();
+    if source_id.is_git() {
+        let locked = match source_id.precise() {
+            Some(precise) => precise,
+            None => return Ok(false),
+        };
+        for rev in advisory.revisions.iter() {
+            // Anything shorter than git's own abbreviations would match
+            // unrelated commits too.
+            if rev.len() < 7 || !rev.chars().all(|c| c.is_digit(16)) {
+                bail!("invalid revision `{}` in advisory {}, expected at least \
+                       7 hexadecimal digits", rev, advisory.id)
+            }
+            if locked.starts_with(&rev.to_lowercase()[..]) {
+                return Ok(true)
+            }
+        }
+        return Ok(false)
+    }
+    if !source_id.is_registry() {
+        return Ok(false)
+    }
+    for req in advisory.patched.iter().chain(advisory.unaffected.iter()) {
+        let req = VersionReq::parse(req).chain_err(|| {
+            format!("invalid version requirement `{}` in advisory {}", req, advisory.id)
+        })?;
+        if req.matches(id.version()) {
+            return Ok(false)
+        }
+    }
+    Ok(true)
+}
+
+fn chain<'a>(resolve: &'a Resolve, id: &'a PackageId) -> Vec<&'a PackageId> {
+    let mut chain = vec![id];
+    chain.extend(resolve.path_to_top(id));
+    chain
+}
+
+fn print_finding(finding: &Finding) {
+    let advisory = finding.advisory;
+    println!("{} ({}): {}",
+             advisory.id,
+             advisory.severity.as_ref().map(|s| &s[..]).unwrap_or("unknown severity"),
+             advisory.title);
+    println!("  package:  {}", Short(finding.id));
+    if !advisory.patched.is_empty() {
+        println!("  patched:  {}", advisory.patched.join(", "));
+    }
+    if let Some(ref url) = advisory.url {
+        println!("  url:      {}", url);
+    }
+    let path = finding.chain.iter().rev().map(|id| Short(id).to_string())
+                      .collect::<Vec<_>>();
+    println!("  path:     {}", path.join(" -> "));
+    println!("");
+}
+
+/// Displays a package as `name version` without its source.
+struct Short<'a>(&'a PackageId);
+
+impl<'a> fmt::Display for Short<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} v{}", self.0.name(), self.0.version())
+    }
+}
@@ -0,0 +1,390 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use core::{Verbosity, Workspace};
+use ops::FingerprintStats;
+use sources::git;
+use util::{cache_manifest, CacheLockMode, Config};
+use util::errors::{CargoResult, CargoResultExt};
+
+/// How long an entry has to be unused before `cargo cache stats` counts it as
+/// stale.
+const STALE_DAYS: u64 = 30;
+
+/// Number and total size of some entries in the global cache.
+struct Usage {
+    count: usize,
+    bytes: u64,
+}
+
+/// Executes `cargo cache stats`.
+///
+/// Summarizes what's in the global cache: git databases and checkouts along
+/// with registry indices, downloaded crates and their unpacked sources. When
+/// run verbosely inside a workspace the hit rate of its fingerprints is
+/// included too, i.e. how often units were found fresh rather than having to
+/// be rebuilt, if builds keep count of that.
+pub fn cache_stats(config: &Config, ws: Option<&Workspace>) -> CargoResult<()> {
+    // Nothing is modified so there's no need to hold any locks, the numbers
+    // may just be a little stale if another cargo is running.
+    let git = config.git_path().into_path_unlocked();
+    let rows = vec![
+        ("git databases", usage(&git.join("db"), 1)?),
+        ("git checkouts", usage(&git.join("checkouts"), 2)?),
+        ("registry indices",
+         usage(&config.registry_index_path().into_path_unlocked(), 1)?),
+        ("registry crates",
+         usage(&config.registry_cache_path().into_path_unlocked(), 2)?),
+        ("registry sources",
+         usage(&config.registry_source_path().into_path_unlocked(), 2)?),
+    ];
+    for &(name, ref usage) in rows.iter() {
+        writeln!(config.shell().err(), "{:<18} {:>6}   {:>10}", format!("{}:", name),
+                 usage.count, human_readable_bytes(usage.bytes))?;
+    }
+
+    let manifest = cache_manifest::load(config)?;
+    if !manifest.cargo_version.is_empty() {
+        let home = config.home().clone().into_path_unlocked();
+        let cutoff = cache_manifest::now().saturating_sub(STALE_DAYS * 24 * 60 * 60);
+        let mut stale = Usage { count: 0, bytes: 0 };
+        for (key, entry) in manifest.entries.iter() {
+            let path = home.join(key);
+            if entry.last_used < cutoff && path.exists() {
+                stale.count += 1;
+                stale.bytes += size(&path)?;
+            }
+        }
+        writeln!(config.shell().err(), "{:<18} {:>6}   {:>10}",
+                 format!("unused {}+ days:", STALE_DAYS),
+                 stale.count, human_readable_bytes(stale.bytes))?;
+        writeln!(config.shell().err(), "last written by:   {}", manifest.cargo_version)?;
+    }
+
+    // The hit rate is only known if builds were asked to keep count.
+    let ws = match ws {
+        Some(ws) if config.shell().verbosity() == Verbosity::Verbose => ws,
+        _ => return Ok(()),
+    };
+    let stats = FingerprintStats::load(&ws.target_dir().into_path_unlocked());
+    let total = stats.fresh + stats.rebuilt;
+    if total == 0 {
+        writeln!(config.shell().err(), "fingerprint cache:  nothing recorded yet, builds \
+                 keep count with `build.fingerprint-stats = true`")?;
+    } else {
+        writeln!(config.shell().err(),
+                 "fingerprint cache:  {} fresh, {} rebuilt ({:.1}% hit rate)",
+                 stats.fresh, stats.rebuilt,
+                 stats.fresh as f64 * 100.0 / total as f64)?;
+    }
+    Ok(())
+}
+
+/// Executes `cargo cache gc`.
+///
+/// Removes everything from the global cache which Cargo can recreate without
+/// touching the network: git checkouts, which are made from the git databases,
+/// and unpacked crate sources, which come from the downloaded `.crate` files.
+///
+/// With `unused_for`, only what the cache manifest says hasn't been used for
+/// that long is removed, or what it doesn't know about at all.
+pub fn cache_gc(config: &Config, unused_for: Option<Duration>) -> CargoResult<()> {
+    // Builds hold the lock shared, so nothing is removed from under them.
+    let _lock = config.acquire_package_cache_lock(CacheLockMode::Exclusive)?;
+    let manifest = cache_manifest::load(config)?;
+    let cutoff = unused_for.map(|d| cache_manifest::now().saturating_sub(d.as_secs()));
+    let keep = |path: &Path| {
+        let cutoff = match cutoff {
+            Some(cutoff) => cutoff,
+            None => return false,
+        };
+        cache_manifest::key(config, path)
+            .and_then(|key| manifest.entries.get(&key))
+            .map(|entry| entry.last_used >= cutoff)
+            .unwrap_or(false)
+    };
+
+    let rows = vec![
+        ("git checkouts", config.git_path().into_path_unlocked().join("checkouts")),
+        ("registry sources", config.registry_source_path().into_path_unlocked()),
+    ];
+    let mut freed = 0;
+    for &(name, ref root) in rows.iter() {
+        let removed = remove_entries(root, 2, &keep)?;
+        if removed.count == 0 {
+            continue
+        }
+        freed += removed.bytes;
+        config.shell().status("Removed", format!("{} {}", removed.count, name))?;
+    }
+
+    forget_removed(config)?;
+    config.shell().status("Freed", human_readable_bytes(freed))?;
+    Ok(())
+}
+
+pub struct CacheCleanOptions<'a> {
+    /// How much space the cache may take up at most, e.g. `10GB`.
+    pub max_size: Option<&'a str>,
+    /// How long entries may go unused, e.g. `30d`.
+    pub older_than: Option<&'a str>,
+}
+
+/// An entry of the cache which `cargo cache clean` may remove.
+struct Candidate {
+    path: PathBuf,
+    bytes: u64,
+    last_used: u64,
+}
+
+/// Executes `cargo cache clean`.
+///
+/// Unlike `cache_gc`, this considers git databases and downloaded crates as
+/// well, which have to be fetched again once they're removed. Entries are
+/// removed least recently used first, according to the cache manifest, until
+/// none is older than `older_than` and all of them fit in `max_size`. Entries
+/// the manifest doesn't know about count as the least recently used.
+pub fn cache_clean(config: &Config, opts: &CacheCleanOptions) -> CargoResult<()> {
+    let max_size = match opts.max_size {
+        Some(s) => Some(parse_size(s)?),
+        None => None,
+    };
+    let older_than = match opts.older_than {
+        Some(s) => Some(parse_age(s)?),
+        None => None,
+    };
+    if max_size.is_none() && older_than.is_none() {
+        bail!("`cargo cache clean` needs `--max-size` or `--older-than`, \
+               or both")
+    }
+
+    // Builds hold the lock shared, so nothing is removed from under them.
+    let _lock = config.acquire_package_cache_lock(CacheLockMode::Exclusive)?;
+    let manifest = cache_manifest::load(config)?;
+    let git = config.git_path().into_path_unlocked();
+    let roots = [
+        (git.join("db"), 1),
+        (git.join("checkouts"), 2),
+        (config.registry_cache_path().into_path_unlocked(), 2),
+        (config.registry_source_path().into_path_unlocked(), 2),
+    ];
+    let mut candidates = Vec::new();
+    for &(ref root, depth) in roots.iter() {
+        for path in entries(root, depth)? {
+            let last_used = cache_manifest::key(config, &path)
+                .and_then(|key| manifest.entries.get(&key))
+                .map(|entry| entry.last_used)
+                .unwrap_or(0);
+            candidates.push(Candidate {
+                bytes: size(&path)?,
+                path: path,
+                last_used: last_used,
+            });
+        }
+    }
+    candidates.sort_by_key(|c| c.last_used);
+
+    let cutoff = older_than.map(|age| cache_manifest::now().saturating_sub(age.as_secs()));
+    let mut total = candidates.iter().map(|c| c.bytes).sum::<u64>();
+    let mut removed = Usage { count: 0, bytes: 0 };
+    for candidate in candidates {
+        let too_old = cutoff.map(|cutoff| candidate.last_used < cutoff).unwrap_or(false);
+        let too_big = max_size.map(|max| total > max).unwrap_or(false);
+        if !too_old && !too_big {
+            // Everything after this was used even more recently.
+            break
+        }
+        remove(&candidate.path)?;
+        total -= candidate.bytes;
+        removed.count += 1;
+        removed.bytes += candidate.bytes;
+    }
+
+    forget_removed(config)?;
+    config.shell().status("Removed", format!("{} entries", removed.count))?;
+    config.shell().status("Freed", human_readable_bytes(removed.bytes))?;
+    Ok(())
+}
+
+/// Parses a size like `10GB`, `512MiB` or `1000`, in bytes.
+fn parse_size(s: &str) -> CargoResult<u64> {
+    let digits = s.find(|c: char| !c.is_digit(10) && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let multiplier: u64 = match &unit.trim().to_lowercase()[..] {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "kib" => 1 << 10,
+        "m" | "mb" => 1000 * 1000,
+        "mib" => 1 << 20,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "gib" => 1 << 30,
+        "t" | "tb" => 1000 * 1000 * 1000 * 1000,
+        "tib" => 1 << 40,
+        _ => bail!("invalid size `{}`, expected a number followed by a unit like \
+                    `MB` or `GiB`", s),
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok((n * multiplier as f64) as u64),
+        _ => bail!("invalid size `{}`, expected a number followed by a unit like \
+                    `MB` or `GiB`", s),
+    }
+}
+
+/// Parses an age like `30d`, `12h` or `2w`.
+fn parse_age(s: &str) -> CargoResult<Duration> {
+    let digits = s.find(|c: char| !c.is_digit(10)).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let seconds: u64 = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("invalid age `{}`, expected a number followed by `s`, `m`, \
+                    `h`, `d` or `w`", s),
+    };
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(seconds)) {
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => bail!("invalid age `{}`, expected a number followed by `s`, `m`, \
+                      `h`, `d` or `w`", s),
+    }
+}
+
+/// The entries `depth` levels below `root`, see `usage`.
+fn entries(root: &Path, depth: usize) -> CargoResult<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    if !root.is_dir() {
+        return Ok(entries)
+    }
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if is_hidden(&path) {
+            continue
+        }
+        if depth > 1 {
+            if path.is_dir() {
+                entries.extend(self::entries(&path, depth - 1)?);
+            }
+        } else {
+            entries.push(path);
+        }
+    }
+    Ok(entries)
+}
+
+fn remove(path: &Path) -> CargoResult<()> {
+    let result = if path.is_dir() {
+        // Git checkouts may be read-only, which would otherwise stop them from
+        // being removed on some platforms.
+        let _ = git::set_checkout_readonly(path, false);
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.chain_err(|| format!("failed to remove `{}`", path.display()))
+}
+
+/// Removes whatever isn't in the cache anymore from the cache manifest.
+fn forget_removed(config: &Config) -> CargoResult<()> {
+    let home = config.home().clone().into_path_unlocked();
+    cache_manifest::update(config, |manifest| {
+        let gone = manifest.entries.keys()
+                                   .filter(|key| !home.join(key).exists())
+                                   .cloned()
+                                   .collect::<Vec<_>>();
+        for key in gone {
+            manifest.entries.remove(&key);
+        }
+    })
+}
+
+/// Removes the entries `depth` levels below `root` for which `keep` is false,
+/// see `usage`, and returns how many there were and how much space they took.
+fn remove_entries(root: &Path, depth: usize, keep: &Fn(&Path) -> bool)
+                  -> CargoResult<Usage> {
+    let mut removed = Usage { count: 0, bytes: 0 };
+    if !root.is_dir() {
+        return Ok(removed)
+    }
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if is_hidden(&path) || !path.is_dir() {
+            continue
+        }
+        if depth > 1 {
+            let inner = remove_entries(&path, depth - 1, keep)?;
+            removed.count += inner.count;
+            removed.bytes += inner.bytes;
+        } else if !keep(&path) {
+            removed.count += 1;
+            removed.bytes += size(&path)?;
+            remove(&path)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Counts the entries `depth` levels below `root` and the size of everything
+/// within them.
+///
+/// Git checkouts are laid out as `checkouts/<repo>/<rev>` and registries as
+/// `cache/<registry>/<crate>`, so those are counted two levels deep.
+fn usage(root: &Path, depth: usize) -> CargoResult<Usage> {
+    let mut usage = Usage { count: 0, bytes: 0 };
+    if !root.is_dir() {
+        return Ok(usage)
+    }
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        // Lock files live alongside the entries we're counting.
+        if is_hidden(&path) {
+            continue
+        }
+        if depth > 1 {
+            if path.is_dir() {
+                let inner = usage(&path, depth - 1)?;
+                usage.count += inner.count;
+                usage.bytes += inner.bytes;
+            }
+        } else {
+            usage.count += 1;
+            usage.bytes += size(&path)?;
+        }
+    }
+    Ok(usage)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().and_then(|s| s.to_str())
+        .map(|s| s.starts_with('.'))
+        .unwrap_or(false)
+}
+
+fn size(path: &Path) -> CargoResult<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(meta.len())
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Formats a number of bytes for humans, e.g. `1.5 MiB`.
+pub fn human_readable_bytes(bytes: u64) -> String {
+    const UNITS: [&'static str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes)
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
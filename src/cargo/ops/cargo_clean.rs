@@ -35,7 +35,8 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
     let mut cx = Context::new(ws, &resolve, &packages, opts.config,
                                    BuildConfig {
                                        host_triple,
-                                       requested_target: opts.target.map(|s| s.to_owned()),
+                                       requested_target: ops::requested_target(opts.config,
+                                                                               opts.target)?,
                                        release: opts.release,
                                        jobs: 1,
                                        ..BuildConfig::default()
@@ -54,7 +55,8 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
                 let Profiles {
                     ref release, ref dev, ref test, ref bench, ref doc,
                     ref custom_build, ref test_deps, ref bench_deps, ref check,
-                    ref check_test, ref doctest,
+                    ref check_test, ref doctest, dev_overrides: _,
+                    release_overrides: _,
                 } = *profiles;
                 let profiles = [release, dev, test, bench, doc, custom_build,
                                 test_deps, bench_deps, check, check_test, doctest];
@@ -74,6 +76,7 @@ pub fn clean(ws: &Workspace, opts: &CleanOptions) -> CargoResult<()> {
 
     for unit in units.iter() {
         rm_rf(&cx.fingerprint_dir(unit))?;
+        rm_rf(&cx.incremental_dir(unit))?;
         if unit.target.is_custom_build() {
             if unit.profile.run_custom_build {
                 rm_rf(&cx.build_script_out_dir(unit))?;
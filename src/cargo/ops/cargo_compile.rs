@@ -22,17 +22,20 @@
 //!       previously compiled dependency
 //!
 
+use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::default::Default;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use core::{Source, Package, Target};
+use core::{Lto, Source, Package, Target};
 use core::{Profile, TargetKind, Profiles, Workspace, PackageId, PackageIdSpec};
 use core::resolver::Resolve;
 use ops::{self, BuildOutput, Executor, DefaultExecutor};
+use super::cargo_cache::human_readable_bytes;
 use util::config::Config;
-use util::{CargoResult, profile};
+use util::{paths, CargoResult, profile};
 use util::errors::{CargoResultExt, CargoError};
 
 /// Contains information about how a package should be compiled.
@@ -65,6 +68,22 @@ pub struct CompileOptions<'a> {
     /// The specified target will be compiled with all the available arguments,
     /// note that this only accounts for the *final* invocation of rustc
     pub target_rustc_args: Option<&'a [String]>,
+    /// Only report which units would be rebuilt, without building anything
+    pub dry_run: bool,
+    /// Report how long each unit took to build, see `cargo build --timings`
+    pub timings: bool,
+    /// Show warnings again for every unit they come from, see
+    /// `cargo build --all-warnings`
+    pub all_warnings: bool,
+    /// Fail the build if a workspace member has warnings, see
+    /// `cargo build --deny-warnings`
+    pub deny_warnings: bool,
+    /// Print the size of the artifacts of a release build, see
+    /// `cargo build --size-report`
+    pub size_report: bool,
+    /// Normalize paths and timestamps, and check that building again gives
+    /// identical artifacts, see `cargo build --reproducible`
+    pub reproducible: bool,
 }
 
 impl<'a> CompileOptions<'a> {
@@ -84,6 +103,12 @@ impl<'a> CompileOptions<'a> {
             message_format: MessageFormat::Human,
             target_rustdoc_args: None,
             target_rustc_args: None,
+            dry_run: false,
+            timings: false,
+            all_warnings: false,
+            deny_warnings: false,
+            size_report: false,
+            reproducible: false,
         }
     }
 }
@@ -184,6 +209,9 @@ pub fn compile<'a>(ws: &Workspace<'a>, options: &CompileOptions<'a>)
     compile_with_exec(ws, options, Arc::new(DefaultExecutor))
 }
 
+/// Like `compile`, but with an `Executor` to hook into the build, e.g. to look
+/// at the resolved dependency graph before anything is built or to intercept
+/// each invocation of rustc.
 pub fn compile_with_exec<'a>(ws: &Workspace<'a>,
                              options: &CompileOptions<'a>,
                              exec: Arc<Executor>)
@@ -200,7 +228,84 @@ pub fn compile_with_exec<'a>(ws: &Workspace<'a>,
             }
         }
     }
-    compile_ws(ws, None, options, exec)
+    let compilation = compile_ws(ws, None, options, exec)?;
+    if options.reproducible {
+        verify_reproducible(ws, options, &compilation)?;
+    }
+    Ok(compilation)
+}
+
+/// Builds everything a second time in a fresh target directory and checks
+/// that each artifact is identical to the one from the first build, see
+/// `cargo build --reproducible`.
+fn verify_reproducible<'a>(ws: &Workspace<'a>,
+                           options: &CompileOptions<'a>,
+                           first: &ops::Compilation<'a>) -> CargoResult<()> {
+    let config = options.config;
+    let check_dir = ws.target_dir().join("reproducible-check");
+    let check_path = check_dir.clone().into_path_unlocked();
+    if check_path.exists() {
+        fs::remove_dir_all(&check_path).chain_err(|| {
+            format!("failed to remove `{}`", check_path.display())
+        })?;
+    }
+    config.shell().status("Verifying", format!(
+        "the build is reproducible by building it again in `{}`",
+        check_path.display()))?;
+
+    let mut check_ws = Workspace::new(ws.current_manifest(), config)?;
+    check_ws.set_target_dir(check_dir);
+    let second = compile_ws(&check_ws, None, options, Arc::new(DefaultExecutor))?;
+
+    // Only what the second, clean, build produced is compared, the first one
+    // may have left behind artifacts of earlier builds.
+    let artifacts = artifacts(&second.root_output)?;
+    for path in artifacts.iter() {
+        let ours = first.root_output.join(path);
+        let theirs = second.root_output.join(path);
+        let a = paths::read_bytes(&ours)?;
+        let b = paths::read_bytes(&theirs)?;
+        if a == b {
+            continue
+        }
+        let offset = a.iter().zip(b.iter()).position(|(a, b)| a != b)
+                      .unwrap_or(cmp::min(a.len(), b.len()));
+        bail!("the build isn't reproducible, `{}` differs from `{}` starting at \
+               byte {}", ours.display(), theirs.display(), offset)
+    }
+
+    fs::remove_dir_all(&check_path).chain_err(|| {
+        format!("failed to remove `{}`", check_path.display())
+    })?;
+    config.shell().status("Verified", format!(
+        "{} artifacts are identical in both builds", artifacts.len()))?;
+    Ok(())
+}
+
+/// The artifacts below `root`, sorted and relative to it. Intermediate output
+/// (fingerprints, build scripts, incremental caches) and dep-info files,
+/// which list absolute paths, are left out.
+fn artifacts(root: &Path) -> CargoResult<Vec<PathBuf>> {
+    fn walk(root: &Path, dir: &Path, ret: &mut Vec<PathBuf>) -> CargoResult<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            if path.is_dir() {
+                match name {
+                    ".fingerprint" | "build" | "incremental" | "native" => {}
+                    _ => walk(root, &path, ret)?,
+                }
+            } else if !name.ends_with(".d") && !name.starts_with('.') {
+                ret.push(path.strip_prefix(root).unwrap().to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    let mut ret = Vec::new();
+    walk(root, root, &mut ret)?;
+    ret.sort();
+    Ok(ret)
 }
 
 pub fn compile_ws<'a>(ws: &Workspace<'a>,
@@ -213,7 +318,13 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
                          release, mode, message_format,
                          ref filter,
                          ref target_rustdoc_args,
-                         ref target_rustc_args } = *options;
+                         ref target_rustc_args,
+                         dry_run,
+                         timings,
+                         all_warnings,
+                         deny_warnings,
+                         size_report,
+                         reproducible } = *options;
 
     let target = target.map(|s| s.to_string());
 
@@ -224,11 +335,13 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
     let profiles = ws.profiles();
 
     let specs = spec.into_package_id_specs(ws)?;
+    let dev_deps = unify_dev_features(config)? || needs_dev_deps(mode, filter);
     let resolve = ops::resolve_ws_precisely(ws,
                                             source,
                                             features,
                                             all_features,
                                             no_default_features,
+                                            dev_deps,
                                             &specs)?;
     let (packages, resolve_with_overrides) = resolve;
 
@@ -255,7 +368,7 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         (Some(args), _) => {
             let all_features = resolve_all_features(&resolve_with_overrides,
                                                     to_builds[0].package_id());
-            let targets = generate_targets(to_builds[0], profiles,
+            let targets = generate_targets(config, to_builds[0], profiles,
                                            mode, filter, &all_features, release)?;
             if targets.len() == 1 {
                 let (target, profile) = targets[0];
@@ -271,7 +384,7 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         (None, Some(args)) => {
             let all_features = resolve_all_features(&resolve_with_overrides,
                                                     to_builds[0].package_id());
-            let targets = generate_targets(to_builds[0], profiles,
+            let targets = generate_targets(config, to_builds[0], profiles,
                                            mode, filter, &all_features, release)?;
             if targets.len() == 1 {
                 let (target, profile) = targets[0];
@@ -288,7 +401,7 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
             for &to_build in to_builds.iter() {
                 let all_features = resolve_all_features(&resolve_with_overrides,
                                                         to_build.package_id());
-                let targets = generate_targets(to_build, profiles, mode,
+                let targets = generate_targets(config, to_build, profiles, mode,
                                                filter, &all_features, release)?;
                 package_targets.push((to_build, targets));
             }
@@ -301,12 +414,32 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
         }
     }
 
+    for &(pkg, ref targets) in package_targets.iter() {
+        for &(target, profile) in targets.iter() {
+            let built = !profile.test && !profile.doc && !profile.check;
+            if built && target.is_dylib() && profile.lto != Lto::Bool(false) {
+                config.shell().warn(format!("`lto` is ignored for the library of `{}`, \
+                                             rustc can't optimize the `dylib` crate type \
+                                             at link time", pkg.name()))?;
+            }
+        }
+    }
+
+    exec.resolved(&resolve_with_overrides, &packages, &package_targets)
+        .chain_err(|| "the build was stopped after resolving dependencies")?;
+
     let mut ret = {
         let _p = profile::start("compiling");
         let mut build_config = scrape_build_config(config, jobs, target)?;
         build_config.release = release;
         build_config.test = mode == CompileMode::Test || mode == CompileMode::Bench;
         build_config.json_messages = message_format == MessageFormat::Json;
+        build_config.dry_run = dry_run;
+        build_config.timings = timings;
+        build_config.all_warnings = all_warnings;
+        build_config.deny_warnings |= deny_warnings;
+        build_config.size_report |= size_report;
+        build_config.reproducible = reproducible;
         if let CompileMode::Doc { deps } = mode {
             build_config.doc_all = deps;
         }
@@ -323,6 +456,8 @@ pub fn compile_ws<'a>(ws: &Workspace<'a>,
 
     ret.to_doc_test = to_builds.into_iter().cloned().collect();
 
+    report_fetched(config)?;
+
     return Ok(ret);
 
     fn resolve_all_features(resolve_with_overrides: &Resolve,
@@ -376,6 +511,39 @@ impl<'a> FilterRule<'a> {
     }
 }
 
+/// Whether the features enabled by dev-dependencies are unified into every
+/// build, configured with `build.unify-dev-features`.
+///
+/// By default they are, so a crate is compiled just the same whether it's
+/// built for tests or not. Turning this off means crates aren't rebuilt
+/// between `cargo build` and `cargo test`, but dev-dependencies can no longer
+/// affect what ends up in a normal build.
+pub fn unify_dev_features(config: &Config) -> CargoResult<bool> {
+    Ok(config.get_bool("build.unify-dev-features")?.map(|v| v.val).unwrap_or(true))
+}
+
+/// Whether building `filter` in `mode` may involve the dev-dependencies of
+/// the packages being built, i.e. any tests, benchmarks or examples.
+pub fn needs_dev_deps(mode: CompileMode, filter: &CompileFilter) -> bool {
+    match mode {
+        CompileMode::Test |
+        CompileMode::Bench |
+        CompileMode::Doctest |
+        CompileMode::Check { test: true } => true,
+        CompileMode::Build |
+        CompileMode::Check { test: false } |
+        CompileMode::Doc { .. } => {
+            match *filter {
+                CompileFilter::Default { .. } => false,
+                CompileFilter::Only { ref examples, ref tests, ref benches, .. } => {
+                    examples.is_specific() || tests.is_specific() ||
+                        benches.is_specific()
+                }
+            }
+        }
+    }
+}
+
 impl<'a> CompileFilter<'a> {
     pub fn new(lib_only: bool,
                bins: &'a [String], all_bins: bool,
@@ -551,7 +719,18 @@ fn propose_indicated_targets<'a>(pkg: &'a Package,
                                 bail!("no {} target named `{}`\n\nDid you mean `{}`?",
                                       desc, name, suggested_name)
                             }
-                            None => bail!("no {} target named `{}`", desc, name),
+                            None => {
+                                let available = pkg.targets().iter()
+                                    .filter(|t| is_expected_kind(t))
+                                    .map(|t| t.name())
+                                    .collect::<Vec<_>>();
+                                if available.is_empty() {
+                                    bail!("no {} target named `{}`", desc, name)
+                                }
+                                bail!("no {} target named `{}`\n\n\
+                                       Available {} targets:\n    {}",
+                                      desc, name, desc, available.join("\n    "))
+                            }
                         }
                     }
                 };
@@ -568,7 +747,12 @@ fn propose_indicated_targets<'a>(pkg: &'a Package,
 }
 
 /// Collect the targets that are libraries or have all required features available.
-fn filter_compatible_targets<'a>(mut proposals: Vec<BuildProposal<'a>>,
+///
+/// Targets which weren't asked for explicitly are skipped if they lack some of
+/// their features, which is only mentioned in verbose output.
+fn filter_compatible_targets<'a>(config: &Config,
+                                 pkg: &Package,
+                                 mut proposals: Vec<BuildProposal<'a>>,
                                  features: &HashSet<String>)
         -> CargoResult<Vec<(&'a Target, &'a Profile)>> {
     let mut compatible = Vec::with_capacity(proposals.len());
@@ -579,7 +763,16 @@ fn filter_compatible_targets<'a>(mut proposals: Vec<BuildProposal<'a>>,
         };
         if proposal.target.is_lib() || unavailable_features.is_empty() {
             compatible.push((proposal.target, proposal.profile));
-        } else if proposal.required {
+        } else if !proposal.required {
+            let missing = unavailable_features.iter()
+                                              .map(|f| format!("`{}`", f))
+                                              .collect::<Vec<_>>();
+            config.shell().verbose(|shell| {
+                shell.status("Skipping", format!(
+                    "target `{}` of {}, which requires the features: {}",
+                    proposal.target.name(), pkg, missing.join(", ")))
+            })?;
+        } else {
             let required_features = proposal.target.required_features().unwrap();
             let quoted_required_features: Vec<String> = required_features.iter()
                                                                          .map(|s| format!("`{}`",s))
@@ -596,7 +789,8 @@ fn filter_compatible_targets<'a>(mut proposals: Vec<BuildProposal<'a>>,
 
 /// Given the configuration for a build, this function will generate all
 /// target/profile combinations needed to be built.
-fn generate_targets<'a>(pkg: &'a Package,
+fn generate_targets<'a>(config: &Config,
+                        pkg: &'a Package,
                         profiles: &'a Profiles,
                         mode: CompileMode,
                         filter: &CompileFilter,
@@ -678,13 +872,47 @@ fn generate_targets<'a>(pkg: &'a Package,
         }
     };
 
-    filter_compatible_targets(targets, features)
+    filter_compatible_targets(config, pkg, targets, features)
+}
+
+/// Summarizes how much was fetched over the network for this build, broken
+/// down per remote, when running very verbosely.
+fn report_fetched(config: &Config) -> CargoResult<()> {
+    let fetched = config.fetched();
+    let total = fetched.values().sum::<u64>();
+    if total == 0 || !config.extra_verbose() {
+        return Ok(())
+    }
+    let mut shell = config.shell();
+    for (remote, &bytes) in fetched.iter() {
+        shell.status("Fetched", format!("{} from `{}`",
+                                        human_readable_bytes(bytes), remote))?;
+    }
+    shell.status("Fetched", format!("{} in total", human_readable_bytes(total)))
+}
+
+/// The target triple to build for: the one given with `--target`, otherwise
+/// `build.target`. `None` means the host.
+pub fn requested_target(config: &Config, target: Option<&str>)
+                        -> CargoResult<Option<String>> {
+    if let Some(target) = target {
+        return Ok(Some(target.to_string()))
+    }
+    match config.get_string("build.target")? {
+        Some(ref v) if v.val.is_empty() => {
+            bail!("`build.target` is empty in {}, expected a target triple", v.definition)
+        }
+        Some(v) => Ok(Some(v.val)),
+        None => Ok(None),
+    }
 }
 
 /// Parse all config files to learn about build configuration. Currently
 /// configured options are:
 ///
 /// * build.jobs
+/// * build.pipelining
+/// * build.sandbox
 /// * build.target
 /// * target.$target.ar
 /// * target.$target.linker
@@ -713,12 +941,26 @@ fn scrape_build_config(config: &Config,
         None => None,
     };
     let jobs = jobs.or(cfg_jobs).unwrap_or(::num_cpus::get() as u32);
-    let cfg_target = config.get_string("build.target")?.map(|s| s.val);
-    let target = target.or(cfg_target);
+    let target = requested_target(config, target.as_ref().map(|s| &s[..]))?;
+    let pipelining = config.get_bool("build.pipelining")?.map(|v| v.val).unwrap_or(false);
+    let deny_warnings = match config.get_string("build.warnings")? {
+        Some(ref v) if v.val == "deny" => true,
+        Some(ref v) if v.val == "warn" => false,
+        Some(v) => {
+            bail!("build.warnings must be `warn` or `deny`, but found `{}` in {}",
+                  v.val, v.definition)
+        }
+        None => false,
+    };
+    let size_report = config.get_bool("build.size-report")?.map(|v| v.val).unwrap_or(false);
     let mut base = ops::BuildConfig {
         host_triple: config.rustc()?.host.clone(),
         requested_target: target.clone(),
         jobs: jobs,
+        pipelining: pipelining,
+        deny_warnings: deny_warnings,
+        size_report: size_report,
+        script_sandbox: scrape_script_sandbox(config)?,
         ..Default::default()
     };
     base.host = scrape_target_config(config, &base.host_triple)?;
@@ -726,9 +968,65 @@ fn scrape_build_config(config: &Config,
         Some(triple) => scrape_target_config(config, triple)?,
         None => base.host.clone(),
     };
+    if base.target.linker.is_none() {
+        if let Some(ref triple) = target {
+            base.target.linker = musl_linker(&base.host_triple, triple);
+        }
+    }
     Ok(base)
 }
 
+/// Reads `[build.sandbox]`, which only takes effect with `enabled = true`.
+fn scrape_script_sandbox(config: &Config) -> CargoResult<Option<ops::ScriptSandbox>> {
+    if !config.get_bool("build.sandbox.enabled")?.map(|v| v.val).unwrap_or(false) {
+        return Ok(None)
+    }
+    let list = |key: &str| -> CargoResult<Option<Vec<String>>> {
+        Ok(config.get_list(key)?.map(|l| {
+            l.val.into_iter().map(|(s, _)| s).collect()
+        }))
+    };
+    // Build scripts still have to be able to find a C compiler and the like.
+    let env = list("build.sandbox.env")?.unwrap_or_else(|| {
+        vec!["PATH".to_string(), "SYSTEMROOT".to_string()]
+    });
+    Ok(Some(ops::ScriptSandbox {
+        env: env,
+        wrapper: config.get_path_and_args("build.sandbox.wrapper")?.map(|v| v.val),
+        allow: list("build.sandbox.allow")?.unwrap_or_default(),
+    }))
+}
+
+/// The linker for a musl target of another architecture than the host's,
+/// which cross toolchains install as `<arch>-linux-musl-gcc`. If it's missing
+/// rustc fails saying so, rather than with the errors of the host's `cc`.
+fn musl_linker(host: &str, triple: &str) -> Option<PathBuf> {
+    let arch = triple.split('-').next().unwrap_or("");
+    let musl = triple.contains("-linux-musl");
+    if !musl || host.split('-').next() == Some(arch) {
+        return None
+    }
+    Some(PathBuf::from(format!("{}-linux-musl-gcc", arch)))
+}
+
+/// Reads `target.<triple>.post-link`, a list of commands each given as a
+/// string of whitespace-separated arguments.
+fn scrape_post_link(config: &Config, key: &str) -> CargoResult<Vec<Vec<String>>> {
+    let list = match config.get_list(key)? {
+        Some(list) => list.val,
+        None => return Ok(Vec::new()),
+    };
+    let mut commands = Vec::new();
+    for (command, definition) in list {
+        let args = command.split_whitespace().map(|s| s.to_string()).collect::<Vec<_>>();
+        if args.is_empty() {
+            bail!("`{}` contains an empty command (in {})", key, definition.display())
+        }
+        commands.push(args);
+    }
+    Ok(commands)
+}
+
 fn scrape_target_config(config: &Config, triple: &str)
                         -> CargoResult<ops::TargetConfig> {
 
@@ -737,6 +1035,8 @@ fn scrape_target_config(config: &Config, triple: &str)
         ar: config.get_path(&format!("{}.ar", key))?.map(|v| v.val),
         linker: config.get_path(&format!("{}.linker", key))?.map(|v| v.val),
         overrides: HashMap::new(),
+        post_link: scrape_post_link(config, &format!("{}.post-link", key))?,
+        strip: config.get_path(&format!("{}.strip", key))?.map(|v| v.val),
     };
     let table = match config.get_table(&key)? {
         Some(table) => table.val,
@@ -744,7 +1044,7 @@ fn scrape_target_config(config: &Config, triple: &str)
     };
     for (lib_name, value) in table {
         match lib_name.as_str() {
-            "ar" | "linker" | "runner" | "rustflags" => {
+            "ar" | "linker" | "runner" | "rustflags" | "post-link" | "strip" => {
                 continue
             },
             _ => {}
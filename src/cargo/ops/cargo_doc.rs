@@ -19,6 +19,7 @@ pub fn doc(ws: &Workspace, options: &DocOptions) -> CargoResult<()> {
                                             options.compile_opts.features,
                                             options.compile_opts.all_features,
                                             options.compile_opts.no_default_features,
+                                            true,
                                             &specs)?;
     let (packages, resolve_with_overrides) = resolve;
 
@@ -72,13 +73,14 @@ pub fn doc(ws: &Workspace, options: &DocOptions) -> CargoResult<()> {
         // nothing we can do about it and otherwise if it's getting overwritten
         // then that's also ok!
         let mut target_dir = ws.target_dir();
-        if let Some(triple) = options.compile_opts.target {
-            target_dir.push(Path::new(triple).file_stem().unwrap());
+        let config = options.compile_opts.config;
+        if let Some(triple) = ops::requested_target(config, options.compile_opts.target)? {
+            target_dir.push(Path::new(&triple).file_stem().unwrap());
         }
         let path = target_dir.join("doc").join(&name).join("index.html");
         let path = path.into_path_unlocked();
         if fs::metadata(&path).is_ok() {
-            let mut shell = options.compile_opts.config.shell();
+            let mut shell = config.shell();
             shell.status("Opening", path.display())?;
             match open_docs(&path) {
                 Ok(m) => shell.status("Launching", m)?,
@@ -0,0 +1,323 @@
+use std::path::Path;
+
+use semver::{Version, VersionReq};
+use toml::Value;
+
+use core::{Dependency, Package, PackageIdSpec, Source, SourceId, Workspace};
+use core::dependency::Kind;
+use sources::SourceConfigMap;
+use util::{paths, Config};
+use util::errors::{CargoError, CargoResult, CargoResultExt};
+use util::toml::edit::{self, Document};
+
+/// Where the dependencies added by `cargo add` come from.
+pub enum DepSource<'a> {
+    /// The registry, crates.io unless it's replaced.
+    Registry,
+    /// The package at this path, relative to the current directory.
+    Path(&'a str),
+    /// A git repository, along with the branch, tag or revision to use.
+    Git {
+        url: &'a str,
+        branch: Option<&'a str>,
+        tag: Option<&'a str>,
+        rev: Option<&'a str>,
+    },
+}
+
+pub struct AddOptions<'a> {
+    /// The member to add the dependencies to, the current package if `None`.
+    pub package: Option<&'a str>,
+    /// Package names, each optionally followed by `@` and a version
+    /// requirement.
+    pub deps: &'a [String],
+    pub source: DepSource<'a>,
+    pub kind: Kind,
+    pub target: Option<&'a str>,
+    pub features: &'a [String],
+    pub no_default_features: bool,
+    pub optional: bool,
+}
+
+pub struct RemoveOptions<'a> {
+    /// The member to remove the dependencies from, the current package if
+    /// `None`.
+    pub package: Option<&'a str>,
+    pub deps: &'a [String],
+    pub kind: Kind,
+    pub target: Option<&'a str>,
+}
+
+/// The keys of a dependency, in the order they're written in.
+const KEYS: [&'static str; 11] = ["version", "registry", "path", "git", "branch", "tag",
+                                  "rev", "features", "default-features", "optional",
+                                  "package"];
+
+/// Executes `cargo add`.
+///
+/// Each dependency is added to the manifest of the package, or updated if
+/// it's there already: its source and version are replaced if new ones are
+/// given, features are added to the ones it had, and all of its other
+/// settings are kept.
+pub fn add(ws: &Workspace, opts: &AddOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let pkg = selected_package(ws, opts.package)?;
+    match opts.source {
+        DepSource::Registry => {}
+        _ if opts.deps.len() > 1 => {
+            bail!("only one dependency can be added at a time with `--path` or `--git`")
+        }
+        _ => {}
+    }
+    let features = opts.features.iter()
+        .flat_map(|s| s.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|s| !s.is_empty())
+        .map(|s| Value::String(s.to_string()))
+        .collect::<Vec<_>>();
+    let table = table_path(opts.kind, opts.target);
+    let table = table.iter().map(|s| &s[..]).collect::<Vec<_>>();
+
+    let mut added = Vec::new();
+    edit_manifest(ws, pkg, |doc| {
+        for dep in opts.deps {
+            let (name, req) = match dep.find('@') {
+                Some(i) => (&dep[..i], Some(&dep[i + 1..])),
+                None => (&dep[..], None),
+            };
+            if name.is_empty() {
+                bail!("invalid dependency `{}`, expected a package name", dep)
+            }
+            if let Some(req) = req {
+                VersionReq::parse(req).chain_err(|| {
+                    format!("invalid version requirement `{}` for `{}`", req, name)
+                })?;
+            }
+
+            let existing = doc.get(&table, name);
+            let mut fields = match existing {
+                Some(Value::String(version)) => {
+                    vec![("version".to_string(), Value::String(version))]
+                }
+                Some(Value::Table(t)) => t.into_iter().collect(),
+                Some(_) => bail!("the dependency `{}` isn't a string or a table", name),
+                None => Vec::new(),
+            };
+
+            let source = match opts.source {
+                DepSource::Registry => None,
+                DepSource::Path(path) => {
+                    Some(vec![("path", Value::String(relative_path(ws, pkg, path)?))])
+                }
+                DepSource::Git { url, branch, tag, rev } => {
+                    let mut git = vec![("git", Value::String(url.to_string()))];
+                    let reference = [("branch", branch), ("tag", tag), ("rev", rev)];
+                    for &(key, value) in reference.iter() {
+                        if let Some(value) = value {
+                            git.push((key, Value::String(value.to_string())));
+                        }
+                    }
+                    Some(git)
+                }
+            };
+            let version = match req {
+                Some(req) => Some(req.to_string()),
+                None if source.is_none() && fields.is_empty() => {
+                    Some(latest_version(config, name)?)
+                }
+                None => None,
+            };
+            if let Some(source) = source {
+                fields.retain(|&(ref k, _)| {
+                    !["version", "path", "git", "branch", "tag", "rev"].contains(&&k[..])
+                });
+                for (key, value) in source {
+                    fields.push((key.to_string(), value));
+                }
+            }
+            if let Some(ref version) = version {
+                set(&mut fields, "version", Value::String(version.clone()));
+            }
+            if !features.is_empty() {
+                let mut all = match fields.iter().find(|f| f.0 == "features") {
+                    Some(&(_, Value::Array(ref a))) => a.clone(),
+                    _ => Vec::new(),
+                };
+                for feature in features.iter() {
+                    if !all.contains(feature) {
+                        all.push(feature.clone());
+                    }
+                }
+                set(&mut fields, "features", Value::Array(all));
+            }
+            if opts.no_default_features {
+                set(&mut fields, "default-features", Value::Boolean(false));
+            }
+            if opts.optional {
+                set(&mut fields, "optional", Value::Boolean(true));
+            }
+
+            fields.sort_by_key(|&(ref k, _)| {
+                KEYS.iter().position(|key| key == k).unwrap_or(KEYS.len())
+            });
+            let value = match fields.len() {
+                1 if fields[0].0 == "version" => edit::to_inline(&fields[0].1),
+                _ => edit::inline_table(fields.iter().map(|&(ref k, ref v)| (&k[..], v))),
+            };
+            doc.set(&table, name, &value);
+
+            let from = match opts.source {
+                DepSource::Path(path) => format!(" ({})", path),
+                DepSource::Git { url, .. } => format!(" ({})", url),
+                DepSource::Registry => String::new(),
+            };
+            let version = version.map(|v| format!(" v{}", v)).unwrap_or_default();
+            added.push(format!("{}{}{} to {}", name, version, from,
+                               describe(opts.kind, opts.target)));
+        }
+        Ok(())
+    })?;
+
+    for msg in added {
+        config.shell().status("Adding", msg)?;
+    }
+    Ok(())
+}
+
+/// Executes `cargo remove`.
+pub fn remove(ws: &Workspace, opts: &RemoveOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let pkg = selected_package(ws, opts.package)?;
+    let table = table_path(opts.kind, opts.target);
+    let table = table.iter().map(|s| &s[..]).collect::<Vec<_>>();
+    let section = describe(opts.kind, opts.target);
+
+    edit_manifest(ws, pkg, |doc| {
+        for dep in opts.deps {
+            if !doc.remove(&table, dep) {
+                bail!("the dependency `{}` could not be found in {}", dep, section)
+            }
+        }
+        Ok(())
+    })?;
+
+    for dep in opts.deps {
+        config.shell().status("Removing", format!("{} from {}", dep, section))?;
+    }
+    Ok(())
+}
+
+fn selected_package<'a>(ws: &'a Workspace, spec: Option<&str>) -> CargoResult<&'a Package> {
+    let spec = match spec {
+        Some(spec) => PackageIdSpec::parse(spec)?,
+        None => return ws.current(),
+    };
+    ws.members().find(|pkg| spec.matches(pkg.package_id())).ok_or_else(|| {
+        CargoError::from(format!("package `{}` is not a member of the workspace", spec))
+    })
+}
+
+/// Edits the manifest of `pkg` with `f`. The manifest is only left changed if
+/// the result can be loaded, so that a broken manifest is never left behind.
+fn edit_manifest<F>(ws: &Workspace, pkg: &Package, f: F) -> CargoResult<()>
+    where F: FnOnce(&mut Document) -> CargoResult<()>
+{
+    let path = pkg.manifest_path();
+    let original = paths::read(path)?;
+    let mut doc = Document::parse(&original).chain_err(|| {
+        format!("failed to parse manifest at `{}`", path.display())
+    })?;
+    f(&mut doc)?;
+
+    paths::write(path, doc.to_string().as_bytes())?;
+    if let Err(e) = Workspace::new(ws.current_manifest(), ws.config()) {
+        paths::write(path, original.as_bytes())?;
+        return Err(e).chain_err(|| {
+            format!("the edited manifest would be invalid, so `{}` was left unchanged",
+                    path.display())
+        })
+    }
+    Ok(())
+}
+
+fn table_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Normal => "dependencies",
+        Kind::Development => "dev-dependencies",
+        Kind::Build => "build-dependencies",
+    }
+}
+
+/// The table dependencies of `kind` for `target` are listed in.
+fn table_path(kind: Kind, target: Option<&str>) -> Vec<String> {
+    let name = table_name(kind).to_string();
+    match target {
+        Some(target) => vec!["target".to_string(), target.to_string(), name],
+        None => vec![name],
+    }
+}
+
+fn describe(kind: Kind, target: Option<&str>) -> String {
+    let name = table_name(kind);
+    match target {
+        Some(target) => format!("{} for target `{}`", name, target),
+        None => name.to_string(),
+    }
+}
+
+fn set(fields: &mut Vec<(String, Value)>, key: &str, value: Value) {
+    match fields.iter_mut().find(|f| f.0 == key) {
+        Some(field) => {
+            field.1 = value;
+            return
+        }
+        None => {}
+    }
+    fields.push((key.to_string(), value));
+}
+
+/// `path`, which is relative to the current directory, as it's written in the
+/// manifest of `pkg`: relative to its directory if possible.
+fn relative_path(ws: &Workspace, pkg: &Package, path: &str) -> CargoResult<String> {
+    let absolute = paths::normalize_path(&ws.config().cwd().join(path));
+    if !absolute.join("Cargo.toml").exists() {
+        bail!("no package found at `{}`", path)
+    }
+    let root = pkg.root();
+    let relative = paths::ancestors(root)
+        .filter_map(|ancestor| paths::relative_within(&absolute, root, ancestor))
+        .next()
+        .unwrap_or(absolute);
+    let relative = if relative == Path::new("") {
+        ".".to_string()
+    } else {
+        relative.display().to_string()
+    };
+    Ok(relative.replace('\\', "/"))
+}
+
+/// The newest version of `name` in the registry, which is a prerelease only
+/// if there's nothing else.
+fn latest_version(config: &Config, name: &str) -> CargoResult<String> {
+    let source_id = SourceId::crates_io(config)?;
+    let map = SourceConfigMap::new(config)?;
+    let mut source = map.load(&source_id)?;
+    source.update()?;
+    let dep = Dependency::parse_no_deprecated(name, None, &source_id)?;
+    let mut latest: Option<Version> = None;
+    source.query(&dep, &mut |summary| {
+        let version = summary.version();
+        let newer = match latest {
+            Some(ref latest) => {
+                (version.pre.is_empty(), version) > (latest.pre.is_empty(), latest)
+            }
+            None => true,
+        };
+        if newer {
+            latest = Some(version.clone());
+        }
+    }).chain_err(|| format!("failed to query the registry for `{}`", name))?;
+    match latest {
+        Some(latest) => Ok(latest.to_string()),
+        None => bail!("the package `{}` could not be found in {}", name, source_id),
+    }
+}
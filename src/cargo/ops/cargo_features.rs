@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+
+use core::{PackageId, Resolve, Workspace};
+use ops::{self, Packages};
+use util::errors::CargoResult;
+
+/// Executes `cargo features`.
+///
+/// Features are unified across the dependency graph, so a package is built
+/// with every feature that anything depending on it asks for. This prints the
+/// features each package ends up with when building `spec`, and which of them
+/// (or which whole packages) are only there because of dev-dependencies, i.e.
+/// what `build.unify-dev-features = false` would leave out of normal builds.
+pub fn report_features(ws: &Workspace,
+                       features: &[String],
+                       all_features: bool,
+                       no_default_features: bool,
+                       spec: Packages) -> CargoResult<()> {
+    let specs = spec.into_package_id_specs(ws)?;
+    let resolve = |dev_deps| {
+        ops::resolve_ws_precisely(ws, None, features, all_features,
+                                  no_default_features, dev_deps, &specs)
+            .map(|(_, resolve)| resolve)
+    };
+    let normal = resolve(false)?;
+    let unified = resolve(true)?;
+
+    let normal_ids = normal.iter().collect::<HashSet<_>>();
+    let mut ids = unified.iter().collect::<Vec<_>>();
+    ids.sort();
+    for id in ids {
+        let mut line = format!("{} v{}: {}", id.name(), id.version(),
+                               list(unified.features_sorted(id)));
+        if !normal_ids.contains(id) {
+            line.push_str(" (only used by dev-dependencies)");
+        } else {
+            let extra = dev_only_features(&normal, &unified, id);
+            if !extra.is_empty() {
+                line.push_str(&format!(" (dev-dependencies add: {})",
+                                       list(extra)));
+            }
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Returns the features of `id` which are only enabled once dev-dependencies
+/// are included in the resolve.
+fn dev_only_features<'a>(normal: &Resolve, unified: &'a Resolve, id: &PackageId)
+                         -> Vec<&'a str> {
+    let normal = normal.features(id);
+    unified.features_sorted(id).into_iter()
+        .filter(|f| !normal.contains(*f))
+        .collect()
+}
+
+fn list(features: Vec<&str>) -> String {
+    if features.is_empty() {
+        "no features".to_string()
+    } else {
+        features.join(", ")
+    }
+}
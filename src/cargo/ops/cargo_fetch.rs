@@ -5,7 +5,10 @@ use util::CargoResult;
 /// Executes `cargo fetch`.
 pub fn fetch<'a>(ws: &Workspace<'a>) -> CargoResult<(Resolve, PackageSet<'a>)> {
     let (packages, resolve) = ops::resolve_ws(ws)?;
-    for id in resolve.iter() {
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+    packages.prefetch(&ids)?;
+    for id in ids {
         packages.get(id)?;
     }
     Ok((resolve, packages))
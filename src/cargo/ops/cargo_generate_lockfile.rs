@@ -6,13 +6,14 @@ use core::{Resolve, SourceId, Workspace};
 use core::resolver::Method;
 use ops;
 use util::config::Config;
-use util::CargoResult;
+use util::{CargoResult, ToSemver};
 
 pub struct UpdateOptions<'a> {
     pub config: &'a Config,
     pub to_update: &'a [String],
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    pub dry_run: bool,
 }
 
 pub fn generate_lockfile(ws: &Workspace) -> CargoResult<()> {
@@ -37,6 +38,10 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
 
     let previous_resolve = match ops::load_pkg_lockfile(ws)? {
         Some(resolve) => resolve,
+        None if opts.dry_run => {
+            bail!("a Cargo.lock must exist to preview an update, run \
+                   `cargo generate-lockfile` first")
+        }
         None => return generate_lockfile(ws),
     };
     let mut registry = PackageRegistry::new(opts.config)?;
@@ -59,6 +64,10 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
                         //       seems like a pretty hokey reason to single out
                         //       the registry as well.
                         let precise = if dep.source_id().is_registry() {
+                            if let Err(e) = precise.to_semver() {
+                                bail!("invalid version given to `--precise` \
+                                       for `{}`: {}", dep.name(), e)
+                            }
                             format!("{}={}", dep.name(), precise)
                         } else {
                             precise.to_string()
@@ -105,7 +114,11 @@ pub fn update_lockfile(ws: &Workspace, opts: &UpdateOptions)
         }
     }
 
-    ops::write_pkg_lockfile(ws, &resolve)?;
+    if opts.dry_run {
+        opts.config.shell().warn("not updating Cargo.lock due to dry run")?;
+    } else {
+        ops::write_pkg_lockfile(ws, &resolve)?;
+    }
     return Ok(());
 
     fn fill_with_deps<'a>(resolve: &'a Resolve, dep: &'a PackageId,
@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json;
+
+use core::{Dependency, PackageId, Workspace};
+use core::dependency::Kind;
+use ops::{self, Packages};
+use util::CargoResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+pub struct GraphOptions<'a> {
+    pub spec: Packages<'a>,
+    pub features: &'a [String],
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub format: GraphFormat,
+    pub dev_deps: bool,
+    pub build_deps: bool,
+    pub depth: Option<u32>,
+    pub focus: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Node<'a> {
+    id: &'a PackageId,
+    name: &'a str,
+    version: String,
+    source: String,
+    features: Vec<&'a str>,
+}
+
+/// An edge of the graph, for each way a package depends on another.
+#[derive(Serialize)]
+struct Edge<'a> {
+    from: &'a PackageId,
+    to: &'a PackageId,
+    kind: &'static str,
+    req: String,
+    features: &'a [String],
+    uses_default_features: bool,
+}
+
+/// Executes `cargo graph`.
+///
+/// The dependency graph of `spec` is printed in the GraphViz `dot` language or
+/// as JSON. Every edge carries the requirement it comes from and the features
+/// it asks for, while every node carries the features it's built with.
+///
+/// Dev- and build-dependencies can be left out, the graph can be cut off at a
+/// depth from the workspace members, and with `focus` only the packages which
+/// lead to that package and those it depends on are kept.
+pub fn graph(ws: &Workspace, opts: &GraphOptions) -> CargoResult<()> {
+    let specs = opts.spec.into_package_id_specs(ws)?;
+    let (packages, resolve) = ops::resolve_ws_precisely(ws, None, opts.features,
+                                                        opts.all_features,
+                                                        opts.no_default_features,
+                                                        opts.dev_deps, &specs)?;
+    let roots = specs.iter().map(|spec| {
+        spec.query(resolve.iter())
+    }).collect::<CargoResult<Vec<_>>>()?;
+
+    // Walk the graph breadth first so `depth` is the shortest distance from
+    // the roots.
+    let mut edges = Vec::new();
+    let mut depths = BTreeMap::new();
+    let mut queue = roots.iter().map(|&id| (id, 0)).collect::<Vec<_>>();
+    for &id in roots.iter() {
+        depths.insert(id, 0);
+    }
+    let mut i = 0;
+    while i < queue.len() {
+        let (id, depth) = queue[i];
+        i += 1;
+        if opts.depth.map_or(false, |max| depth >= max) {
+            continue
+        }
+        let pkg = packages.get(id)?;
+        for dep_id in resolve.deps(id).collect::<BTreeSet<_>>() {
+            let deps = declarations(pkg.dependencies(), dep_id);
+            for dep in deps {
+                let kind = match dep.kind() {
+                    Kind::Normal => "normal",
+                    Kind::Development if opts.dev_deps => "dev",
+                    Kind::Build if opts.build_deps => "build",
+                    Kind::Development | Kind::Build => continue,
+                };
+                edges.push(Edge {
+                    from: id,
+                    to: dep_id,
+                    kind: kind,
+                    req: dep.version_req().to_string(),
+                    features: dep.features(),
+                    uses_default_features: dep.uses_default_features(),
+                });
+                if !depths.contains_key(dep_id) {
+                    depths.insert(dep_id, depth + 1);
+                    queue.push((dep_id, depth + 1));
+                }
+            }
+        }
+    }
+
+    let mut ids = depths.keys().cloned().collect::<BTreeSet<_>>();
+    if let Some(focus) = opts.focus {
+        let focus = resolve.query(focus)?;
+        if !ids.contains(focus) {
+            bail!("package `{}` isn't in the graph", focus)
+        }
+        let mut kept = reachable(focus, &edges, |e| (e.from, e.to));
+        kept.extend(reachable(focus, &edges, |e| (e.to, e.from)));
+        ids = kept;
+        edges.retain(|e| ids.contains(e.from) && ids.contains(e.to));
+    }
+
+    let nodes = ids.iter().map(|&id| {
+        Node {
+            id: id,
+            name: id.name(),
+            version: id.version().to_string(),
+            source: id.source_id().to_string(),
+            features: resolve.features_sorted(id),
+        }
+    }).collect::<Vec<_>>();
+
+    match opts.format {
+        GraphFormat::Dot => print_dot(&nodes, &edges),
+        GraphFormat::Json => {
+            let mut graph = BTreeMap::new();
+            graph.insert("nodes", serde_json::to_value(&nodes)?);
+            graph.insert("edges", serde_json::to_value(&edges)?);
+            println!("{}", serde_json::to_string(&graph)?);
+        }
+    }
+    Ok(())
+}
+
+/// The dependencies in `deps` which `id` was selected for.
+pub fn declarations<'a>(deps: &'a [Dependency], id: &PackageId) -> Vec<&'a Dependency> {
+    let matching = deps.iter().filter(|d| d.matches_id(id)).collect::<Vec<_>>();
+    if !matching.is_empty() {
+        return matching
+    }
+    // Replaced and patched packages come from somewhere else than what the
+    // dependency asked for.
+    deps.iter().filter(|d| d.name() == id.name()).collect()
+}
+
+/// Every package reachable from `start` following `edges` in the direction
+/// given by `ends`.
+fn reachable<'a, F>(start: &'a PackageId,
+                    edges: &[Edge<'a>],
+                    ends: F) -> BTreeSet<&'a PackageId>
+    where F: Fn(&Edge<'a>) -> (&'a PackageId, &'a PackageId)
+{
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![start];
+    while let Some(id) = pending.pop() {
+        if !seen.insert(id) {
+            continue
+        }
+        for edge in edges {
+            let (from, to) = ends(edge);
+            if from == id {
+                pending.push(to);
+            }
+        }
+    }
+    seen
+}
+
+fn print_dot(nodes: &[Node], edges: &[Edge]) {
+    let index = nodes.iter().enumerate().map(|(i, n)| (n.id, i))
+                     .collect::<BTreeMap<_, _>>();
+    println!("digraph dependencies {{");
+    for (i, node) in nodes.iter().enumerate() {
+        let mut label = format!("{} v{}", node.name, node.version);
+        if !node.features.is_empty() {
+            label.push_str(&format!("\n[{}]", node.features.join(", ")));
+        }
+        println!("    N{} [label=\"{}\"];", i, escape(&label));
+    }
+    for edge in edges {
+        let mut label = edge.req.clone();
+        let mut features = edge.features.iter().map(|f| &f[..]).collect::<Vec<_>>();
+        if !edge.uses_default_features {
+            features.insert(0, "no-default-features");
+        }
+        if !features.is_empty() {
+            label.push_str(&format!("\n[{}]", features.join(", ")));
+        }
+        let style = match edge.kind {
+            "dev" => ", style=dashed",
+            "build" => ", style=dotted",
+            _ => "",
+        };
+        println!("    N{} -> N{} [label=\"{}\"{}];",
+                 index[edge.from], index[edge.to], escape(&label), style);
+    }
+    println!("}}");
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
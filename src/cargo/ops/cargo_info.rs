@@ -0,0 +1,149 @@
+use semver::{Version, VersionReq};
+
+use core::{Dependency, Package, PackageId, Source, SourceId, Workspace};
+use core::dependency::Kind;
+use ops;
+use sources::SourceConfigMap;
+use util::Config;
+use util::errors::{CargoResult, CargoResultExt};
+
+/// Executes `cargo info`.
+///
+/// `spec` is a package name, optionally followed by `@` and a version
+/// requirement. If `ws` is given and the package is part of its dependency
+/// graph, the version in the graph is shown. Otherwise it's looked up in the
+/// registry, picking the newest version that matches, which is downloaded for
+/// the metadata that isn't in the index.
+pub fn info(config: &Config, ws: Option<&Workspace>, spec: &str) -> CargoResult<()> {
+    let (name, req) = match spec.find('@') {
+        Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+        None => (spec, None),
+    };
+    if name.is_empty() {
+        bail!("invalid package `{}`, expected a package name", spec)
+    }
+    let req = match req {
+        Some(req) => Some(VersionReq::parse(req).chain_err(|| {
+            format!("invalid version requirement `{}` for `{}`", req, name)
+        })?),
+        None => None,
+    };
+
+    if let Some(ws) = ws {
+        let (packages, resolve) = ops::resolve_ws(ws)?;
+        let found = resolve.iter().filter(|id| {
+            id.name() == name && req.as_ref().map(|r| r.matches(id.version())).unwrap_or(true)
+        }).max_by_key(|id| id.version().clone());
+        if let Some(id) = found {
+            let available = if id.source_id().is_registry() {
+                let source_id = id.source_id().with_precise(None);
+                let mut source = SourceConfigMap::new(config)?.load(&source_id)?;
+                source.update()?;
+                Some(versions(&mut *source, name, None)?)
+            } else {
+                None
+            };
+            print(packages.get(id)?, available.as_ref().map(|v| &v[..]));
+            return Ok(())
+        }
+    }
+
+    let source_id = SourceId::crates_io(config)?;
+    let mut source = SourceConfigMap::new(config)?.load(&source_id)?;
+    source.update()?;
+    let all = versions(&mut *source, name, None)?;
+    if all.is_empty() {
+        bail!("the package `{}` could not be found in {}", name, source_id)
+    }
+    let matching = versions(&mut *source, name, req.as_ref())?;
+    // Prereleases are only picked if there's nothing else.
+    let version = matching.iter().find(|v| v.pre.is_empty())
+                          .or_else(|| matching.first());
+    let version = match version {
+        Some(version) => version,
+        None => bail!("no version of `{}` matches `{}`, the available versions are {}",
+                      name, req.unwrap(), list(&all)),
+    };
+    let id = PackageId::new(name, version.clone(), &source_id)?;
+    let pkg = source.download(&id)?;
+    print(&pkg, Some(&all));
+    Ok(())
+}
+
+/// The versions of `name` in `source` which match `req`, newest first.
+fn versions(source: &mut Source, name: &str, req: Option<&VersionReq>)
+            -> CargoResult<Vec<Version>> {
+    let req = req.map(|r| r.to_string());
+    let dep = Dependency::parse_no_deprecated(name, req.as_ref().map(|s| &s[..]),
+                                              source.source_id())?;
+    let mut versions = Vec::new();
+    source.query(&dep, &mut |summary| versions.push(summary.version().clone()))
+          .chain_err(|| format!("failed to query the registry for `{}`", name))?;
+    versions.sort();
+    versions.reverse();
+    versions.dedup();
+    Ok(versions)
+}
+
+fn list(versions: &[Version]) -> String {
+    versions.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn print(pkg: &Package, versions: Option<&[Version]>) {
+    let metadata = pkg.manifest().metadata();
+    println!("{}", pkg.package_id());
+    if let Some(ref description) = metadata.description {
+        println!("{}", description.trim());
+    }
+    if let Some(ref license) = metadata.license {
+        println!("license: {}", license);
+    } else if let Some(ref file) = metadata.license_file {
+        println!("license: see `{}`", file);
+    }
+    let urls = [("homepage", &metadata.homepage),
+                ("repository", &metadata.repository),
+                ("documentation", &metadata.documentation)];
+    for &(what, url) in urls.iter() {
+        if let Some(ref url) = *url {
+            println!("{}: {}", what, url);
+        }
+    }
+    if let Some(versions) = versions {
+        println!("versions: {}", list(versions));
+    }
+
+    let features = pkg.summary().features();
+    if !features.is_empty() {
+        println!("features:");
+        for (feature, enables) in features {
+            let enables = enables.iter().map(|f| format!("\"{}\"", f))
+                                 .collect::<Vec<_>>();
+            println!("  {} = [{}]", feature, enables.join(", "));
+        }
+    }
+
+    for &(kind, title) in [(Kind::Normal, "dependencies"),
+                           (Kind::Build, "build-dependencies"),
+                           (Kind::Development, "dev-dependencies")].iter() {
+        let mut deps = pkg.dependencies().iter().filter(|d| d.kind() == kind)
+                          .collect::<Vec<_>>();
+        if deps.is_empty() {
+            continue
+        }
+        deps.sort_by(|a, b| a.name().cmp(b.name()));
+        println!("{}:", title);
+        for dep in deps {
+            let mut line = format!("  {} {}", dep.name(), dep.version_req());
+            if !dep.source_id().is_registry() {
+                line.push_str(&format!(" ({})", dep.source_id()));
+            }
+            if let Some(platform) = dep.platform() {
+                line.push_str(&format!(" for {}", platform));
+            }
+            if dep.is_optional() {
+                line.push_str(" (optional)");
+            }
+            println!("{}", line);
+        }
+    }
+}
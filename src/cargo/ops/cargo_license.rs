@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+
+use serde_json;
+
+use core::{Package, Workspace};
+use ops::{self, Packages};
+use util::{CargoResult, Config};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum LicenseFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+pub struct LicenseOptions<'a> {
+    pub spec: Packages<'a>,
+    pub format: LicenseFormat,
+}
+
+/// The licensing information of a single package in the report.
+#[derive(Serialize)]
+struct Entry<'a> {
+    name: &'a str,
+    version: String,
+    source: String,
+    license: Option<&'a str>,
+    license_file: Option<&'a str>,
+    authors: &'a [String],
+    repository: Option<&'a str>,
+}
+
+/// Executes `cargo license`.
+///
+/// Every package shipped with `spec`, see `ops::resolve_ws_shipped`, is
+/// listed with its `license`, `license-file`, `authors` and `repository`.
+///
+/// If `license.allowed` is configured, dependencies (but not the workspace
+/// members themselves) must be licensed under those licenses, or the command
+/// fails after printing the report.
+pub fn license(ws: &Workspace, opts: &LicenseOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let specs = opts.spec.into_package_id_specs(ws)?;
+    let (packages, resolve) = ops::resolve_ws_shipped(ws, &specs)?;
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+    let packages = ids.iter()
+                      .map(|id| packages.get(id))
+                      .collect::<CargoResult<Vec<_>>>()?;
+    let entries = packages.iter().map(|pkg| entry(pkg)).collect::<Vec<_>>();
+
+    match opts.format {
+        LicenseFormat::Text => print_table(&entries),
+        LicenseFormat::Json => {
+            let mut report = BTreeMap::new();
+            report.insert("packages", &entries);
+            println!("{}", serde_json::to_string(&report)?);
+        }
+        LicenseFormat::Csv => print_csv(&entries),
+    }
+
+    let allowed = match allowed_licenses(config)? {
+        Some(allowed) => allowed,
+        None => return Ok(()),
+    };
+    let members = ws.members().map(|pkg| pkg.package_id()).collect::<Vec<_>>();
+    let mut disallowed = Vec::new();
+    for (pkg, entry) in packages.iter().zip(entries.iter()) {
+        if members.contains(&pkg.package_id()) {
+            continue
+        }
+        let ok = match entry.license {
+            Some(license) => is_allowed(license, &allowed),
+            None => false,
+        };
+        if !ok {
+            let license = match (entry.license, entry.license_file) {
+                (Some(license), _) => license.to_string(),
+                (None, Some(file)) => format!("only a license file, `{}`", file),
+                (None, None) => "no license".to_string(),
+            };
+            disallowed.push(format!("{} v{} ({})", entry.name, entry.version, license));
+        }
+    }
+    if disallowed.is_empty() {
+        return Ok(())
+    }
+    bail!("{} {} licensed under something not listed in `license.allowed`:\n  {}",
+          disallowed.len(),
+          if disallowed.len() == 1 { "dependency is" } else { "dependencies are" },
+          disallowed.join("\n  "))
+}
+
+fn entry(pkg: &Package) -> Entry {
+    let metadata = pkg.manifest().metadata();
+    let id = pkg.package_id();
+    Entry {
+        name: id.name(),
+        version: id.version().to_string(),
+        source: id.source_id().to_string(),
+        license: metadata.license.as_ref().map(|s| &s[..]),
+        license_file: metadata.license_file.as_ref().map(|s| &s[..]),
+        authors: &metadata.authors,
+        repository: metadata.repository.as_ref().map(|s| &s[..]),
+    }
+}
+
+fn allowed_licenses(config: &Config) -> CargoResult<Option<Vec<String>>> {
+    Ok(config.get_list("license.allowed")?.map(|list| {
+        list.val.into_iter().map(|(license, _)| license).collect()
+    }))
+}
+
+/// Whether the SPDX license expression `license` is satisfied by the
+/// `allowed` licenses.
+///
+/// It's enough for one alternative of an `OR` (or `/`, which crates.io used to
+/// accept) to be allowed, while everything combined with `AND` has to be. A
+/// license `WITH` an exception is allowed if the license itself is. Anything
+/// which can't be parsed isn't allowed.
+fn is_allowed(license: &str, allowed: &[String]) -> bool {
+    let spaced = license.replace('(', " ( ").replace(')', " ) ").replace('/', " OR ");
+    let tokens = spaced.split_whitespace().collect::<Vec<_>>();
+    let mut pos = 0;
+    match any_of(&tokens, &mut pos, allowed) {
+        Some(ok) => ok && pos == tokens.len(),
+        None => false,
+    }
+}
+
+// expr := all_of ("OR" all_of)*
+fn any_of(tokens: &[&str], pos: &mut usize, allowed: &[String]) -> Option<bool> {
+    let mut ok = false;
+    loop {
+        match all_of(tokens, pos, allowed) {
+            Some(next) => ok = ok || next,
+            None => return None,
+        }
+        if tokens.get(*pos) != Some(&"OR") {
+            return Some(ok)
+        }
+        *pos += 1;
+    }
+}
+
+// all_of := single ("AND" single)*
+fn all_of(tokens: &[&str], pos: &mut usize, allowed: &[String]) -> Option<bool> {
+    let mut ok = true;
+    loop {
+        match single(tokens, pos, allowed) {
+            Some(next) => ok = ok && next,
+            None => return None,
+        }
+        if tokens.get(*pos) != Some(&"AND") {
+            return Some(ok)
+        }
+        *pos += 1;
+    }
+}
+
+// single := "(" expr ")" | license ["WITH" exception]
+fn single(tokens: &[&str], pos: &mut usize, allowed: &[String]) -> Option<bool> {
+    let token = match tokens.get(*pos) {
+        Some(token) => *token,
+        None => return None,
+    };
+    *pos += 1;
+    match token {
+        "(" => {
+            let ok = any_of(tokens, pos, allowed);
+            if tokens.get(*pos) != Some(&")") {
+                return None
+            }
+            *pos += 1;
+            ok
+        }
+        ")" | "AND" | "OR" | "WITH" => None,
+        license => {
+            if tokens.get(*pos) == Some(&"WITH") {
+                if *pos + 1 >= tokens.len() {
+                    return None
+                }
+                *pos += 2;
+            }
+            Some(allowed.iter().any(|a| a == license))
+        }
+    }
+}
+
+fn print_table(entries: &[Entry]) {
+    const HEADER: [&'static str; 4] = ["Name", "Version", "License", "Repository"];
+    let cells = entries.iter().map(|e| {
+        let license = match (e.license, e.license_file) {
+            (Some(license), _) => license.to_string(),
+            (None, Some(file)) => format!("see {}", file),
+            (None, None) => "---".to_string(),
+        };
+        [e.name.to_string(),
+         e.version.clone(),
+         license,
+         e.repository.unwrap_or("---").to_string()]
+    }).collect::<Vec<_>>();
+
+    let mut widths = [0; 4];
+    for i in 0..4 {
+        widths[i] = cells.iter().map(|c| c[i].len())
+                         .chain(Some(HEADER[i].len()))
+                         .max().unwrap();
+    }
+    println!("{:<w0$}   {:<w1$}   {:<w2$}   {}",
+             HEADER[0], HEADER[1], HEADER[2], HEADER[3],
+             w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+    for c in cells.iter() {
+        println!("{:<w0$}   {:<w1$}   {:<w2$}   {}",
+                 c[0], c[1], c[2], c[3],
+                 w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+    }
+}
+
+fn print_csv(entries: &[Entry]) {
+    println!("name,version,source,license,license_file,authors,repository");
+    for e in entries {
+        let fields = [e.name.to_string(),
+                      e.version.clone(),
+                      e.source.clone(),
+                      e.license.unwrap_or("").to_string(),
+                      e.license_file.unwrap_or("").to_string(),
+                      e.authors.join("; "),
+                      e.repository.unwrap_or("").to_string()];
+        let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+        println!("{}", fields.join(","));
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{csv_field, is_allowed};
+
+    #[test]
+    fn license_expressions() {
+        let allowed = vec!["MIT".to_string(), "Apache-2.0".to_string()];
+        assert!(is_allowed("MIT", &allowed));
+        assert!(is_allowed("MIT/Apache-2.0", &allowed));
+        assert!(is_allowed("GPL-3.0 OR MIT", &allowed));
+        assert!(is_allowed("MIT AND Apache-2.0", &allowed));
+        assert!(is_allowed("(MIT OR GPL-3.0) AND Apache-2.0", &allowed));
+        assert!(is_allowed("Apache-2.0 WITH LLVM-exception", &allowed));
+        assert!(!is_allowed("GPL-3.0", &allowed));
+        assert!(!is_allowed("MIT AND GPL-3.0", &allowed));
+        assert!(!is_allowed("(MIT OR Apache-2.0) AND GPL-3.0", &allowed));
+        assert!(!is_allowed("", &allowed));
+        assert!(!is_allowed("(MIT", &allowed));
+        assert!(!is_allowed("MIT OR", &allowed));
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(csv_field("MIT"), "MIT");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::cmp;
+
+use semver::{Version, VersionReq};
+
+use core::{Dependency, GitReference, PackageId, Source, Workspace};
+use ops;
+use sources::{git, SourceConfigMap};
+use util::errors::{CargoResult, CargoResultExt};
+
+/// A single row of the table printed by `cargo outdated`.
+struct Outdated {
+    name: String,
+    locked: String,
+    compatible: Option<String>,
+    latest: Option<String>,
+}
+
+/// Executes `cargo outdated`.
+///
+/// Every git dependency in the lock file has its locked revision compared to
+/// the head of the branch or tag it tracks on the remote, and every registry
+/// dependency has its locked version compared to the newest semver compatible
+/// version (which `cargo update` would pick) and the newest version overall.
+/// Nothing is downloaded beyond the registry index.
+pub fn outdated(ws: &Workspace) -> CargoResult<()> {
+    let config = ws.config();
+    let resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!("a Cargo.lock must exist to check for outdated \
+                       dependencies, run `cargo generate-lockfile` first"),
+    };
+    let map = SourceConfigMap::new(config)?;
+
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+
+    let mut sources = HashMap::new();
+    let mut remote_heads = HashMap::new();
+    let mut rows = Vec::new();
+    for id in ids {
+        let source_id = id.source_id();
+        if source_id.is_git() {
+            let reference = source_id.git_reference().unwrap();
+            let refname = match *reference {
+                GitReference::Branch(ref b) => format!("refs/heads/{}", b),
+                GitReference::Tag(ref t) => format!("refs/tags/{}", t),
+                // Revisions are pinned, there's nothing to update to.
+                GitReference::Rev(..) => continue,
+            };
+            let locked = match source_id.precise() {
+                Some(precise) => precise.to_string(),
+                None => continue,
+            };
+            if !remote_heads.contains_key(source_id.url()) {
+                let remote = git::GitRemote::new(source_id.url());
+                config.shell().status("Querying",
+                    format!("git repository `{}`", source_id.url()))?;
+                let heads = remote.ls_remote(config)?;
+                remote_heads.insert(source_id.url().clone(), heads);
+            }
+            let heads = &remote_heads[source_id.url()];
+            // Annotated tags are advertised twice, prefer the commit they
+            // point at.
+            let peeled = format!("{}^{{}}", refname);
+            let head = heads.iter().find(|h| h.0 == peeled)
+                            .or_else(|| heads.iter().find(|h| h.0 == refname))
+                            .map(|h| h.1.to_string());
+            if head.as_ref() != Some(&locked) {
+                rows.push(Outdated {
+                    name: id.name().to_string(),
+                    locked: short_rev(&locked),
+                    compatible: head.as_ref().map(|h| short_rev(h)),
+                    latest: head.as_ref().map(|h| short_rev(h)),
+                });
+            }
+        } else if source_id.is_registry() {
+            let source_id = source_id.with_precise(None);
+            if !sources.contains_key(&source_id) {
+                let mut source = map.load(&source_id)?;
+                source.update()?;
+                sources.insert(source_id.clone(), source);
+            }
+            let source = sources.get_mut(&source_id).unwrap();
+            let (compatible, latest) = newest_versions(&mut **source, id)?;
+            let compatible = newer(compatible, id.version());
+            let latest = newer(latest, id.version());
+            if compatible.is_some() || latest.is_some() {
+                rows.push(Outdated {
+                    name: id.name().to_string(),
+                    locked: id.version().to_string(),
+                    compatible: compatible.map(|v| v.to_string()),
+                    latest: latest.map(|v| v.to_string()),
+                });
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        config.shell().status("Finished", "all dependencies are up to date")?;
+        return Ok(())
+    }
+    print_table(&rows);
+    Ok(())
+}
+
+/// Returns the newest version of `id` that's semver compatible with the
+/// locked version, along with the newest version available at all.
+fn newest_versions(source: &mut Source, id: &PackageId)
+                   -> CargoResult<(Option<Version>, Option<Version>)> {
+    let dep = Dependency::parse_no_deprecated(id.name(), None,
+                                              &id.source_id().with_precise(None))?;
+    let compatible_req = VersionReq::parse(&format!("^{}", id.version()))?;
+    let mut compatible = None;
+    let mut latest = None;
+    source.query(&dep, &mut |summary| {
+        let version = summary.version();
+        // Like the resolver, only consider prereleases when we're already
+        // on one.
+        if !version.pre.is_empty() && id.version().pre.is_empty() {
+            return
+        }
+        if compatible_req.matches(version) {
+            compatible = cmp::max(compatible.take(), Some(version.clone()));
+        }
+        latest = cmp::max(latest.take(), Some(version.clone()));
+    }).chain_err(|| format!("failed to query the registry for `{}`", id.name()))?;
+    Ok((compatible, latest))
+}
+
+fn newer(version: Option<Version>, than: &Version) -> Option<Version> {
+    match version {
+        Some(ref v) if v > than => version.clone(),
+        _ => None,
+    }
+}
+
+fn short_rev(rev: &str) -> String {
+    rev.chars().take(8).collect()
+}
+
+fn print_table(rows: &[Outdated]) {
+    const HEADER: [&'static str; 4] = ["Name", "Locked", "Compatible", "Latest"];
+    let cells = rows.iter().map(|row| {
+        [row.name.clone(),
+         row.locked.clone(),
+         row.compatible.clone().unwrap_or_else(|| "---".to_string()),
+         row.latest.clone().unwrap_or_else(|| "---".to_string())]
+    }).collect::<Vec<_>>();
+
+    let mut widths = [0; 4];
+    for i in 0..4 {
+        widths[i] = cells.iter().map(|c| c[i].len())
+                         .chain(Some(HEADER[i].len()))
+                         .max().unwrap();
+    }
+    println!("{:<w0$}   {:<w1$}   {:<w2$}   {}",
+             HEADER[0], HEADER[1], HEADER[2], HEADER[3],
+             w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+    for c in cells.iter() {
+        println!("{:<w0$}   {:<w1$}   {:<w2$}   {}",
+                 c[0], c[1], c[2], c[3],
+                 w0 = widths[0], w1 = widths[1], w2 = widths[2]);
+    }
+}
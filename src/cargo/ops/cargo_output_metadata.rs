@@ -50,6 +50,7 @@ fn metadata_full(ws: &Workspace,
                                          &opt.features,
                                          opt.all_features,
                                          opt.no_default_features,
+                                         true,
                                          &specs)?;
     let (packages, resolve) = deps;
 
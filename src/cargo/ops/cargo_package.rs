@@ -7,6 +7,7 @@ use std::sync::Arc;
 use flate2::read::GzDecoder;
 use flate2::{GzBuilder, Compression};
 use git2;
+use semver::VersionReq;
 use tar::{Archive, Builder, Header, EntryType};
 
 use core::{Package, Workspace, Source, SourceId};
@@ -23,13 +24,105 @@ pub struct PackageOpts<'cfg> {
     pub verify: bool,
     pub jobs: Option<u32>,
     pub target: Option<&'cfg str>,
+    /// The alternative registry the package is for, see `[registries]`.
+    pub registry: Option<String>,
+}
+
+/// The largest `.crate` file crates.io accepts.
+const MAX_CRATE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Something to fix before a package is published, see
+/// `cargo publish --check`.
+pub struct PublishProblem {
+    /// `error` for what the registry rejects, `warning` for what's only
+    /// recommended.
+    pub level: &'static str,
+    /// Names the kind of problem, like `wildcard-dependency`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl PublishProblem {
+    fn error(code: &'static str, message: String) -> PublishProblem {
+        PublishProblem { level: "error", code: code, message: message }
+    }
+
+    fn warning(code: &'static str, message: String) -> PublishProblem {
+        PublishProblem { level: "warning", code: code, message: message }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == "error"
+    }
+}
+
+/// Finds what would keep the current package from being published, or what
+/// it had better fix first, without packaging or uploading it.
+pub fn check_publish(ws: &Workspace) -> CargoResult<Vec<PublishProblem>> {
+    let pkg = ws.current()?;
+    let config = ws.config();
+    let mut problems = Vec::new();
+
+    let md = pkg.manifest().metadata();
+    let missing = |field: &Option<String>| field.as_ref().map_or(true, |s| s.is_empty());
+    if missing(&md.description) {
+        problems.push(PublishProblem::error("missing-metadata", "manifest has no \
+            description, which crates.io requires".to_string()));
+    }
+    if missing(&md.license) && missing(&md.license_file) {
+        problems.push(PublishProblem::error("missing-metadata", "manifest has no \
+            license or license-file, which crates.io requires".to_string()));
+    }
+    if missing(&md.documentation) && missing(&md.homepage) && missing(&md.repository) {
+        problems.push(PublishProblem::warning("missing-metadata", "manifest has no \
+            documentation, homepage or repository".to_string()));
+    }
+
+    for dep in pkg.dependencies() {
+        if dep.source_id().is_path() && !dep.specified_req() {
+            problems.push(PublishProblem::error("path-dependency-without-version",
+                format!("path dependency `{}` does not specify a version, which \
+                         is what's used once published", dep.name())));
+        } else if dep.specified_req() && *dep.version_req() == VersionReq::any() {
+            problems.push(PublishProblem::error("wildcard-dependency",
+                format!("dependency `{}` allows any version (`*`), which crates.io \
+                         rejects", dep.name())));
+        }
+    }
+
+    // Only the compressed size counts, so the package is built to find it.
+    let mut src = PathSource::new(pkg.root(),
+                                  pkg.package_id().source_id(),
+                                  config);
+    src.update()?;
+    let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+    let dir = ws.target_dir().join("package");
+    let dst = dir.open_rw(&format!(".{}", filename), config, "package scratch space")?;
+    dst.file().set_len(0)?;
+    tar(ws, &src, dst.file(), &filename).chain_err(|| {
+        "failed to prepare local package for uploading"
+    })?;
+    let size = dst.file().metadata()?.len();
+    if size > MAX_CRATE_SIZE {
+        problems.push(PublishProblem::error("package-too-large",
+            format!("the package is {}, larger than the {} crates.io accepts",
+                    ops::human_readable_bytes(size),
+                    ops::human_readable_bytes(MAX_CRATE_SIZE))));
+    }
+
+    Ok(problems)
 }
 
 pub fn package(ws: &Workspace,
                opts: &PackageOpts) -> CargoResult<Option<FileLock>> {
     let pkg = ws.current()?;
     let config = ws.config();
-    if !pkg.manifest().features().activated().is_empty() {
+    // Alternative registries are only used by whoever set them up, so it's
+    // up to them whether the feature which enables them may be relied on.
+    let unstable = pkg.manifest().features().activated().iter().any(|feature| {
+        opts.registry.is_none() || feature != "alternative-registries"
+    });
+    if unstable {
         bail!("cannot package or publish crates which activate nightly-only \
                cargo features")
     }
@@ -308,6 +401,12 @@ fn run_verify(ws: &Workspace, tar: &File, opts: &PackageOpts) -> CargoResult<()>
         mode: ops::CompileMode::Build,
         target_rustdoc_args: None,
         target_rustc_args: None,
+        dry_run: false,
+        timings: false,
+        all_warnings: false,
+        deny_warnings: false,
+        size_report: false,
+        reproducible: false,
     }, Arc::new(DefaultExecutor))?;
 
     Ok(())
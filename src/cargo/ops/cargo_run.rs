@@ -25,6 +25,27 @@ pub fn run(ws: &Workspace,
         }
     };
 
+    // Among several binaries, the one named by `default-run` runs unless
+    // something else is asked for.
+    let default_bins;
+    let default_options;
+    let options = match pkg.manifest().default_run() {
+        Some(name) if !options.filter.is_specific() => {
+            default_bins = [name.to_string()];
+            default_options = ops::CompileOptions {
+                filter: ops::CompileFilter::new(false,
+                                                &default_bins, false,
+                                                &[], false,
+                                                &[], false,
+                                                &[], false,
+                                                false),
+                ..*options
+            };
+            &default_options
+        }
+        _ => options,
+    };
+
     let mut bins = pkg.manifest().targets().iter().filter(|a| {
         !a.is_lib() && !a.is_custom_build() && if !options.filter.is_specific() {
             a.is_bin()
@@ -41,9 +62,16 @@ pub fn run(ws: &Workspace,
     }
     if bins.next().is_some() {
         if !options.filter.is_specific() {
-            bail!("`cargo run` requires that a project only have one \
-                   executable; use the `--bin` option to specify which one \
-                   to run")
+            let mut names = pkg.manifest().targets().iter()
+                               .filter(|t| t.is_bin())
+                               .map(|t| format!("    {}", t.name()))
+                               .collect::<Vec<_>>();
+            names.sort();
+            bail!("`cargo run` could not determine which binary to run, as \
+                   the package has several of them; use the `--bin` option \
+                   to pick one, or set `default-run` in the manifest\n\n\
+                   available binaries:\n{}",
+                  names.join("\n"))
         } else {
             bail!("`cargo run` can run at most one executable, but \
                    multiple were specified")
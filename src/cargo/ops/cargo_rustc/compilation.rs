@@ -1,5 +1,5 @@
 use std::collections::{HashMap, HashSet};
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
 use semver::Version;
 
@@ -43,7 +43,7 @@ pub struct Compilation<'cfg> {
 
     /// Extra environment variables that were passed to compilations and should
     /// be passed to future invocations of programs.
-    pub extra_env: HashMap<PackageId, Vec<(String, String)>>,
+    pub extra_env: HashMap<PackageId, Vec<(String, OsString)>>,
 
     pub to_doc_test: Vec<Package>,
 
@@ -94,10 +94,16 @@ impl<'cfg> Compilation<'cfg> {
         self.fill_env(process(cmd), pkg, true)
     }
 
+    /// The runner from `target.<triple>.runner`. Emscripten builds tests and
+    /// binaries as JavaScript, which are run with `node` unless there's one.
     fn target_runner(&self) -> CargoResult<&Option<(PathBuf, Vec<String>)>> {
         self.target_runner.get_or_try_init(|| {
             let key = format!("target.{}.runner", self.target);
-            Ok(self.config.get_path_and_args(&key)?.map(|v| v.val))
+            let runner = self.config.get_path_and_args(&key)?.map(|v| v.val);
+            if runner.is_none() && self.target.ends_with("-emscripten") {
+                return Ok(Some((PathBuf::from("node"), Vec::new())))
+            }
+            Ok(runner)
         })
     }
 
@@ -1,5 +1,6 @@
 #![allow(deprecated)]
 
+use std::borrow::Cow;
 use std::collections::{HashSet, HashMap, BTreeSet};
 use std::collections::hash_map::Entry;
 use std::env;
@@ -12,10 +13,10 @@ use std::cell::RefCell;
 
 use jobserver::Client;
 
-use core::{Package, PackageId, PackageSet, Resolve, Target, Profile};
+use core::{Lto, Package, PackageId, PackageSet, Resolve, Target, Profile};
 use core::{TargetKind, Profiles, Dependency, Workspace};
 use core::dependency::Kind as DepKind;
-use util::{self, ProcessBuilder, internal, Config, profile, Cfg, CfgExpr};
+use util::{self, ProcessBuilder, Config, profile, Cfg, CfgExpr};
 use util::errors::{CargoResult, CargoResultExt};
 
 use super::TargetConfig;
@@ -23,6 +24,7 @@ use super::custom_build::{BuildState, BuildScripts, BuildDeps};
 use super::fingerprint::Fingerprint;
 use super::layout::Layout;
 use super::links::Links;
+use super::shared_cache::SharedCache;
 use super::{Kind, Compilation, BuildConfig};
 
 /// All information needed to define a Unit.
@@ -86,12 +88,16 @@ pub struct Context<'a, 'cfg: 'a> {
     pub build_script_overridden: HashSet<(PackageId, Kind)>,
     pub build_explicit_deps: HashMap<Unit<'a>, BuildDeps>,
     pub fingerprints: HashMap<Unit<'a>, Arc<Fingerprint>>,
+    /// Why each unit found to be dirty needs to be rebuilt
+    pub stale_reasons: HashMap<Unit<'a>, String>,
     pub compiled: HashSet<Unit<'a>>,
     pub build_config: BuildConfig,
     pub build_scripts: HashMap<Unit<'a>, Arc<BuildScripts>>,
     pub links: Links<'a>,
     pub used_in_plugin: HashSet<Unit<'a>>,
     pub jobserver: Client,
+    /// The cache of dependencies shared by all projects, if it's enabled
+    pub shared_cache: Option<Arc<SharedCache>>,
 
     /// The target directory layout for the host (and target if it is the same as host)
     host: Layout,
@@ -100,7 +106,8 @@ pub struct Context<'a, 'cfg: 'a> {
     target_info: TargetInfo,
     host_info: TargetInfo,
     profiles: &'a Profiles,
-    incremental_enabled: bool,
+    /// `CARGO_INCREMENTAL`, which takes precedence over the profiles.
+    incremental_env: Option<bool>,
 
     /// For each Unit, a list all files produced as a triple of
     ///
@@ -147,31 +154,26 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                build_config: BuildConfig,
                profiles: &'a Profiles) -> CargoResult<Context<'a, 'cfg>> {
 
+        // A dry run only looks at what's been built before, so it mustn't
+        // create or lock the target directory.
         let dest = if build_config.release { "release" } else { "debug" };
-        let host_layout = Layout::new(ws, None, dest)?;
+        let layout: fn(&Workspace, Option<&str>, &str) -> CargoResult<Layout> =
+            if build_config.dry_run { Layout::peek } else { Layout::new };
+        let host_layout = layout(ws, None, dest)?;
         let target_layout = match build_config.requested_target.as_ref() {
-            Some(target) => Some(Layout::new(ws, Some(target), dest)?),
+            Some(target) => Some(layout(ws, Some(target), dest)?),
             None => None,
         };
 
-        // Enable incremental builds if the user opts in. For now,
-        // this is an environment variable until things stabilize a
-        // bit more.
-        let incremental_enabled = match env::var("CARGO_INCREMENTAL") {
-            Ok(v) => v == "1",
-            Err(_) => false,
+        // `CARGO_INCREMENTAL=1` enables incremental builds for the profiles
+        // which don't disable them, `CARGO_INCREMENTAL=0` disables them
+        // altogether, see `incremental_args`.
+        let incremental_env = match env::var("CARGO_INCREMENTAL") {
+            Ok(ref v) if v == "1" => Some(true),
+            Ok(ref v) if v == "0" => Some(false),
+            _ => None,
         };
 
-        // -Z can only be used on nightly builds; other builds complain loudly.
-        // Since incremental builds only work on nightly anyway, we silently
-        // ignore CARGO_INCREMENTAL on anything but nightly. This allows users
-        // to always have CARGO_INCREMENTAL set without getting unexpected
-        // errors on stable/beta builds.
-        let is_nightly =
-            config.rustc()?.verbose_version.contains("-nightly") ||
-            config.rustc()?.verbose_version.contains("-dev");
-        let incremental_enabled = incremental_enabled && is_nightly;
-
         // Load up the jobserver that we'll use to manage our parallelism. This
         // is the same as the GNU make implementation of a jobserver, and
         // intentionally so! It's hoped that we can interact with GNU make and
@@ -200,14 +202,16 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             build_state: Arc::new(BuildState::new(&build_config)),
             build_config: build_config,
             fingerprints: HashMap::new(),
+            stale_reasons: HashMap::new(),
             profiles: profiles,
             compiled: HashSet::new(),
             build_scripts: HashMap::new(),
             build_explicit_deps: HashMap::new(),
             links: Links::new(),
             used_in_plugin: HashSet::new(),
-            incremental_enabled: incremental_enabled,
+            incremental_env: incremental_env,
             jobserver: jobserver,
+            shared_cache: SharedCache::new(config)?,
             build_script_overridden: HashSet::new(),
 
             // TODO: Pre-Calculate these with a topo-sort, rather than lazy-calculating
@@ -221,13 +225,17 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
     pub fn prepare(&mut self) -> CargoResult<()> {
         let _p = profile::start("preparing layout");
 
-        self.host.prepare().chain_err(|| {
-            internal("couldn't prepare build directories")
-        })?;
-        if let Some(ref mut target) = self.target {
-            target.prepare().chain_err(|| {
-                internal("couldn't prepare build directories")
+        if !self.build_config.dry_run {
+            let host = self.host.root().to_path_buf();
+            self.host.prepare().chain_err(|| {
+                format!("failed to create the build directories in `{}`", host.display())
             })?;
+            if let Some(ref mut target) = self.target {
+                let root = target.root().to_path_buf();
+                target.prepare().chain_err(|| {
+                    format!("failed to create the build directories in `{}`", root.display())
+                })?;
+            }
         }
 
         self.compilation.host_deps_output = self.host.deps().to_path_buf();
@@ -354,6 +362,12 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 rustlib.push("rustlib");
                 rustlib.push(self.target_triple());
                 rustlib.push("lib");
+                // Without it rustc only complains that it can't find `std`.
+                if self.target_triple().contains("-musl") && !rustlib.exists() {
+                    bail!("the `{0}` target isn't installed for rustc (no `{1}`), it can \
+                           be added with `rustup target add {0}`",
+                          self.target_triple(), rustlib.display())
+                }
                 self.compilation.target_dylib_path = Some(rustlib);
             }
         }
@@ -521,9 +535,12 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         // No metadata for bin because of an issue
         // - wasm32 rustc/emcc encodes the .wasm name in the .js (rust-lang/cargo#4535)
         //
-        // Two exceptions
+        // Three exceptions
         // 1) Upstream dependencies (we aren't exporting + need to resolve name conflict)
         // 2) __CARGO_DEFAULT_LIB_METADATA env var
+        // 3) Other packages of the same name in the build, e.g. two versions
+        //    of a crate from different paths, which would otherwise overwrite
+        //    each other's output
         //
         // Note, though, that the compiler's build system at least wants
         // path dependencies (eg libstd) to have hashes in filenames. To account for
@@ -539,7 +556,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             (unit.target.is_dylib() || unit.target.is_cdylib() ||
                  (unit.target.is_bin() && self.target_triple().starts_with("wasm32-"))) &&
             unit.pkg.package_id().source_id().is_path() &&
-            !__cargo_default_lib_metadata.is_ok()
+            !__cargo_default_lib_metadata.is_ok() &&
+            !self.resolve.iter().any(|id| {
+                id.name() == unit.pkg.name() && id != unit.pkg.package_id()
+            })
         {
             return None;
         }
@@ -573,7 +593,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         // Throw in the profile we're compiling with. This helps caching
         // panic=abort and panic=unwind artifacts, additionally with various
         // settings like debuginfo and whatnot.
-        unit.profile.hash(&mut hasher);
+        self.hashed_profile(unit).hash(&mut hasher);
 
         // Artifacts compiled for the host should have a different metadata
         // piece than those compiled for the target, so make sure we throw in
@@ -784,16 +804,12 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
     /// For a package, return all targets which are registered as dependencies
     /// for that package.
-    pub fn dep_targets(&self, unit: &Unit<'a>) -> CargoResult<Vec<Unit<'a>>> {
-        if unit.profile.run_custom_build {
-            return self.dep_run_custom_build(unit)
-        } else if unit.profile.doc && !unit.profile.test {
-            return self.doc_deps(unit);
-        }
-
+    /// The packages which `unit` depends on according to the manifest of its
+    /// package, taking into account what kind of target it is, the platform
+    /// and the activated features.
+    fn used_deps(&self, unit: &Unit<'a>) -> Vec<&'a PackageId> {
         let id = unit.pkg.package_id();
-        let deps = self.resolve.deps(id);
-        let mut ret = deps.filter(|dep| {
+        self.resolve.deps(id).filter(|dep| {
             unit.pkg.dependencies().iter().filter(|d| {
                 d.name() == dep.name() && d.version_req().matches(dep.version())
             }).any(|d| {
@@ -827,7 +843,79 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 // actually used!
                 true
             })
-        }).filter_map(|id| {
+        }).collect()
+    }
+
+    /// Downloads the packages of everything `units` depend on, directly or
+    /// not, a level of the dependency graph at a time.
+    ///
+    /// `dep_targets` would otherwise get them one after the other as it comes
+    /// across them, while this lets sources fetch many at once.
+    pub fn prefetch_deps(&self, units: &[Unit<'a>]) -> CargoResult<()> {
+        let mut visited = HashSet::new();
+        let mut level = units.to_vec();
+        while !level.is_empty() {
+            let ids = level.iter().flat_map(|unit| self.used_deps(unit))
+                           .collect::<Vec<_>>();
+            self.packages.prefetch(&ids)?;
+            let mut next = Vec::new();
+            for unit in level.iter() {
+                for dep in self.dep_targets(unit)? {
+                    if visited.insert(dep) {
+                        next.push(dep);
+                    }
+                }
+            }
+            level = next;
+        }
+        Ok(())
+    }
+
+    /// Returns the packages the build of `units` needs which would have to be
+    /// downloaded first, as far as that can be told without downloading
+    /// anything, for a dry run. What they depend on themselves can't be
+    /// known until they're downloaded.
+    pub fn missing_deps(&self, units: &[Unit<'a>]) -> CargoResult<Vec<&'a PackageId>> {
+        let mut missing = Vec::new();
+        let mut visited = HashSet::new();
+        let mut level = units.to_vec();
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for unit in level.iter() {
+                if !visited.insert(*unit) {
+                    continue
+                }
+                // A package's build script has dependencies of its own.
+                let mut ids = self.used_deps(unit);
+                if let Some(build) = unit.pkg.targets().iter().find(|t| t.is_custom_build()) {
+                    ids.extend(self.used_deps(&Unit { target: build, ..*unit }));
+                }
+                let mut complete = true;
+                for id in ids {
+                    if !self.packages.is_downloaded(id) {
+                        complete = false;
+                        if !missing.contains(&id) {
+                            missing.push(id);
+                        }
+                    }
+                }
+                if complete {
+                    next.extend(self.dep_targets(unit)?);
+                }
+            }
+            level = next;
+        }
+        Ok(missing)
+    }
+
+    pub fn dep_targets(&self, unit: &Unit<'a>) -> CargoResult<Vec<Unit<'a>>> {
+        if unit.profile.run_custom_build {
+            return self.dep_run_custom_build(unit)
+        } else if unit.profile.doc && !unit.profile.test {
+            return self.doc_deps(unit);
+        }
+
+        let mut ret = self.used_deps(unit).into_iter().filter_map(|id| {
             match self.get_package(id) {
                 Ok(pkg) => {
                     pkg.targets().iter().find(|t| t.is_lib()).map(|t| {
@@ -853,7 +941,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         ret.extend(self.dep_build_script(unit));
 
         // If this target is a binary, test, example, etc, then it depends on
-        // the library of the same package. The call to `resolve.deps` above
+        // the library of the same package. The call to `used_deps` above
         // didn't include `pkg` in the return values, so we need to special case
         // it here and see if we need to push `(pkg, pkg_lib_target)`.
         if unit.target.is_lib() && !unit.profile.doc {
@@ -1021,6 +1109,11 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
     /// Gets a package for the given package id.
     pub fn get_package(&self, id: &PackageId) -> CargoResult<&'a Package> {
+        // A dry run checks for what's missing up front with `missing_deps`,
+        // so this is just to make sure nothing is ever downloaded.
+        if self.build_config.dry_run && !self.packages.is_downloaded(id) {
+            bail!("`{}` would have to be downloaded", id)
+        }
         self.packages.get(id)
     }
 
@@ -1034,6 +1127,47 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.target_config(kind).ar.as_ref().map(|s| s.as_ref())
     }
 
+    /// The commands from `target.<triple>.post-link` to run on the artifact of
+    /// `unit`, which are only run for binaries and cdylibs.
+    pub fn post_link_commands(&self, unit: &Unit) -> &[Vec<String>] {
+        if is_linked(unit) {
+            &self.target_config(unit.kind).post_link
+        } else {
+            &[]
+        }
+    }
+
+    /// The command stripping the artifact of `unit` as the `strip` setting of
+    /// its profile asks, which runs before the `post-link` ones. MSVC keeps
+    /// the debuginfo in separate `.pdb` files and wasm has tools of its own,
+    /// so nothing is stripped for them.
+    pub fn strip_command(&self, unit: &Unit<'a>) -> Option<Vec<String>> {
+        let strip = match self.unit_profile(unit).strip {
+            Some(ref strip) if is_linked(unit) => strip.clone(),
+            _ => return None,
+        };
+        let triple = match unit.kind {
+            Kind::Host => self.host_triple(),
+            Kind::Target => self.target_triple(),
+        };
+        if triple.contains("-msvc") || triple.starts_with("wasm32-") ||
+           triple.starts_with("asmjs-") {
+            return None
+        }
+        let tool = match self.target_config(unit.kind).strip {
+            Some(ref tool) => tool.display().to_string(),
+            None => "strip".to_string(),
+        };
+        let mut command = vec![tool];
+        if strip == "debuginfo" {
+            command.push("-S".to_string());
+        } else if unit.target.is_cdylib() {
+            // The symbols a library exports have to stay.
+            command.push("-x".to_string());
+        }
+        Some(command)
+    }
+
     /// Get the list of cfg printed out from the compiler for the specified kind
     pub fn cfg(&self, kind: Kind) -> &[Cfg] {
         let info = match kind {
@@ -1081,16 +1215,84 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         self.lib_profile()
     }
 
-    pub fn incremental_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
-        if self.incremental_enabled {
+    /// The profile `unit` is actually built with, which is `unit.profile` with
+    /// the `[profile.*.package]` overrides for its package applied.
+    pub fn unit_profile(&self, unit: &Unit<'a>) -> Cow<'a, Profile> {
+        self.profile_for(unit.pkg.package_id(), unit.profile)
+    }
+
+    /// Whether the `lto` setting of the profile is passed to rustc for `unit`,
+    /// only the final artifacts for the target are optimized at link time.
+    pub fn lto_applies(&self, unit: &Unit<'a>) -> bool {
+        unit.target.can_lto() && !unit.target.for_host()
+    }
+
+    /// The profile of `unit` as far as it affects what it's compiled to,
+    /// which `lto` doesn't for the libraries the final artifacts are linked
+    /// from. Toggling it then only rebuilds those artifacts.
+    pub fn hashed_profile(&self, unit: &Unit<'a>) -> Cow<'a, Profile> {
+        let mut profile = self.unit_profile(unit);
+        if !self.lto_applies(unit) && profile.lto != Lto::Bool(false) {
+            profile.to_mut().lto = Lto::Bool(false);
+        }
+        profile
+    }
+
+    /// `profile` with the overrides for `pkg` applied, if there are any.
+    pub fn profile_for(&self, pkg: &PackageId, profile: &'a Profile) -> Cow<'a, Profile> {
+        let overrides = if self.build_config.release {
+            &self.profiles.release_overrides
+        } else {
+            &self.profiles.dev_overrides
+        };
+        let member = self.ws.members().any(|p| p.package_id() == pkg);
+        match overrides.get(pkg, member) {
+            Some(o) => {
+                let mut profile = profile.clone();
+                o.apply(&mut profile);
+                Cow::Owned(profile)
+            }
+            None => Cow::Borrowed(profile),
+        }
+    }
+
+    /// Whether `unit` is compiled incrementally, which the `incremental`
+    /// setting of its profile decides unless `CARGO_INCREMENTAL` is set. The
+    /// release and bench profiles aren't incremental by default, even with
+    /// `CARGO_INCREMENTAL=1`.
+    fn incremental(&self, unit: &Unit<'a>) -> bool {
+        let profile = self.unit_profile(unit).incremental;
+        match self.incremental_env {
+            Some(false) => false,
+            Some(true) => profile.unwrap_or(true),
+            None => profile.unwrap_or(false),
+        }
+    }
+
+    /// The directory rustc keeps the incremental cache of `unit` in, one for
+    /// each unit so that they can't invalidate each other's.
+    pub fn incremental_dir(&self, unit: &Unit<'a>) -> PathBuf {
+        let hash = util::short_hash(&(unit.pkg.package_id().stable_hash(self.ws.root()),
+                                      unit.target.name(),
+                                      unit.target.kind(),
+                                      &*self.unit_profile(unit),
+                                      unit.kind));
+        self.layout(unit.kind).incremental()
+            .join(format!("{}-{}", unit.target.crate_name(), hash))
+    }
+
+    pub fn incremental_args(&self, unit: &Unit<'a>) -> CargoResult<Vec<String>> {
+        // The incremental cache isn't part of the output, but whether it was
+        // warm can still change what's produced.
+        if self.incremental(unit) && !self.build_config.reproducible {
             if unit.pkg.package_id().source_id().is_path() {
                 // Only enable incremental compilation for sources the user can modify.
                 // For things that change infrequently, non-incremental builds yield
                 // better performance.
                 // (see also https://github.com/rust-lang/cargo/issues/3972)
-                return Ok(vec![format!("-Zincremental={}",
-                                       self.layout(unit.kind).incremental().display())]);
-            } else if unit.profile.codegen_units.is_none() {
+                return Ok(vec!["-C".to_string(),
+                               format!("incremental={}", self.incremental_dir(unit).display())]);
+            } else if self.unit_profile(unit).codegen_units.is_none() {
                 // For non-incremental builds we set a higher number of
                 // codegen units so we get faster compiles. It's OK to do
                 // so because the user has already opted into slower
@@ -1102,10 +1304,52 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         Ok(vec![])
     }
 
+    /// Whether rustc is asked to emit the metadata of `unit` separately and
+    /// notify Cargo once it's written, so that its dependents which only need
+    /// that can start building before it's fully built (`build.pipelining`).
+    ///
+    /// That's only done for libraries which are just rlibs: anything else is
+    /// linked, or loaded by rustc, so its dependents need all of it anyway.
+    pub fn emits_rmeta(&self, unit: &Unit) -> bool {
+        self.build_config.pipelining &&
+            unit.target.is_lib() &&
+            !unit.profile.check &&
+            !unit.profile.doc &&
+            !unit.profile.test &&
+            !unit.profile.run_custom_build &&
+            unit.target.rustc_crate_types().iter().all(|t| *t == "lib" || *t == "rlib")
+    }
+
+    /// Whether `parent` can start building as soon as the metadata of its
+    /// dependency `dep` is available, see `emits_rmeta`. That's the case
+    /// when `parent` is an rlib itself, as nothing is linked into it.
+    pub fn only_requires_rmeta(&self, parent: &Unit, dep: &Unit) -> bool {
+        self.emits_rmeta(parent) && self.emits_rmeta(dep)
+    }
+
     pub fn rustflags_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
         env_args(self.config, &self.build_config, self.info(&unit.kind), unit.kind, "RUSTFLAGS")
     }
 
+    /// Flags which remap the paths rustc embeds in its output (debuginfo,
+    /// panic messages, `file!()`) for `--reproducible` builds, so that they
+    /// don't depend on where the source or the target directory are.
+    ///
+    /// rustc applies the last matching prefix, so the more specific ones come
+    /// last: the target directory is usually inside the package.
+    pub fn reproducible_args(&self, unit: &Unit) -> Vec<String> {
+        if !self.build_config.reproducible {
+            return Vec::new()
+        }
+        let remap = |from: &Path, to: &str| {
+            format!("--remap-path-prefix={}={}", from.display(), to)
+        };
+        let target_dir = self.ws.target_dir().into_path_unlocked();
+        vec![remap(self.config.cwd(), "."),
+             remap(unit.pkg.root(), &format!("{}-{}", unit.pkg.name(), unit.pkg.version())),
+             remap(&target_dir, "target")]
+    }
+
     pub fn rustdocflags_args(&self, unit: &Unit) -> CargoResult<Vec<String>> {
         env_args(self.config, &self.build_config, self.info(&unit.kind), unit.kind, "RUSTDOCFLAGS")
     }
@@ -1235,6 +1479,14 @@ impl fmt::Display for Metadata {
     }
 }
 
+/// Whether `unit` is linked into a binary or a cdylib, which `post-link`
+/// commands and `strip` apply to.
+fn is_linked(unit: &Unit) -> bool {
+    !unit.profile.test && !unit.profile.doc && !unit.profile.check &&
+        !unit.profile.run_custom_build &&
+        (unit.target.is_bin() || unit.target.is_bin_example() || unit.target.is_cdylib())
+}
+
 /// Takes rustc output (using specialized command line args), and calculates the file prefix and
 /// suffix for the given crate type, or returns None if the type is not supported. (e.g. for a
 /// rust library like libcargo.rlib, prefix = "lib", suffix = "rlib").
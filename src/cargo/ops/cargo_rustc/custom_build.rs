@@ -1,4 +1,5 @@
 use std::collections::{HashMap, BTreeSet, HashSet};
+use std::env;
 use std::fs;
 use std::path::{PathBuf, Path};
 use std::str;
@@ -7,11 +8,11 @@ use std::sync::{Mutex, Arc};
 use core::PackageId;
 use util::{Freshness, Cfg};
 use util::errors::{CargoResult, CargoResultExt, CargoError};
-use util::{internal, profile, paths};
+use util::{internal, profile, paths, ProcessBuilder};
 use util::machine_message;
 
 use super::job::Work;
-use super::{fingerprint, Kind, Context, Unit};
+use super::{fingerprint, Kind, Context, ScriptSandbox, Unit};
 
 /// Contains the parsed output of a custom build script.
 #[derive(Clone, Debug, Hash)]
@@ -93,7 +94,9 @@ pub fn prepare<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     let (freshness, dirty, fresh) =
             fingerprint::prepare_build_cmd(cx, unit)?;
 
-    Ok((work_dirty.then(dirty), work_fresh.then(fresh), freshness))
+    let invalidate = fingerprint::prepare_invalidate(cx, unit);
+
+    Ok((invalidate.then(work_dirty).then(dirty), work_fresh.then(fresh), freshness))
 }
 
 fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
@@ -112,9 +115,12 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // environment variables. Note that the profile-related environment
     // variables are not set with this the build script's profile but rather the
     // package's library profile.
-    let profile = cx.lib_profile();
+    let profile = cx.profile_for(unit.pkg.package_id(), cx.lib_profile());
     let to_exec = to_exec.into_os_string();
     let mut cmd = cx.compilation.host_process(to_exec, unit.pkg)?;
+    if cx.build_config.reproducible {
+        super::reproducible_env(&mut cmd);
+    }
     cmd.env("OUT_DIR", &build_output)
        .env("CARGO_MANIFEST_DIR", unit.pkg.root())
        .env("NUM_JOBS", &cx.jobs().to_string())
@@ -160,6 +166,18 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         }
     }
 
+    // Unless its package is allowed to run it unrestricted, a sandboxed build
+    // script gets a directory of its own for temporary files, which is emptied
+    // before every run like OUT_DIR.
+    let sandbox_tmp = match cx.build_config.script_sandbox {
+        Some(ref sandbox) if !sandbox.allow.iter().any(|p| p == unit.pkg.name()) => {
+            let tmp = build_output.parent().unwrap().join("tmp");
+            sandbox_cmd(&mut cmd, sandbox, &tmp);
+            Some(tmp)
+        }
+        _ => None,
+    };
+
     // Gather the set of native dependencies that this package has along with
     // some other variables to close over.
     //
@@ -196,8 +214,10 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     let deps = BuildDeps::new(&output_file, prev_output.as_ref());
     cx.build_explicit_deps.insert(*unit, deps);
 
-    fs::create_dir_all(&script_output)?;
-    fs::create_dir_all(&build_output)?;
+    if !cx.build_config.dry_run {
+        fs::create_dir_all(&script_output)?;
+        fs::create_dir_all(&build_output)?;
+    }
 
     let root_output = cx.target_root().to_path_buf();
 
@@ -207,13 +227,28 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     // Note that this has to do some extra work just before running the command
     // to determine extra environment variables and such.
     let dirty = Work::new(move |state| {
-        // Make sure that OUT_DIR exists.
-        //
-        // If we have an old build directory, then just move it into place,
-        // otherwise create it!
-        if fs::metadata(&build_output).is_err() {
-            fs::create_dir(&build_output).chain_err(|| {
-                internal("failed to create script output directory for \
+        // Every run starts out with an empty OUT_DIR, so that nothing which
+        // an earlier run generated, before whatever made the script rerun
+        // changed, is picked up by the build by mistake.
+        if fs::metadata(&build_output).is_ok() {
+            fs::remove_dir_all(&build_output).chain_err(|| {
+                format!("failed to clean the script output directory `{}`",
+                        build_output.display())
+            })?;
+        }
+        fs::create_dir(&build_output).chain_err(|| {
+            internal("failed to create script output directory for \
+                      build command")
+        })?;
+        if let Some(ref tmp) = sandbox_tmp {
+            if fs::metadata(tmp).is_ok() {
+                fs::remove_dir_all(tmp).chain_err(|| {
+                    format!("failed to clean the script temporary directory `{}`",
+                            tmp.display())
+                })?;
+            }
+            fs::create_dir(tmp).chain_err(|| {
+                internal("failed to create script temporary directory for \
                           build command")
             })?;
         }
@@ -301,6 +336,32 @@ fn build_work<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     Ok((dirty, fresh))
 }
 
+/// Restricts the build script run by `cmd` as `sandbox` says: it only gets
+/// the listed variables of Cargo's environment besides those set for it, `tmp`
+/// for temporary files and, unless `HOME` is listed, as its home directory, and
+/// it's run through the wrapper if there is one.
+fn sandbox_cmd(cmd: &mut ProcessBuilder, sandbox: &ScriptSandbox, tmp: &Path) {
+    cmd.env_clear();
+    for key in sandbox.env.iter() {
+        if cmd.get_envs().contains_key(key) {
+            continue
+        }
+        if let Some(val) = env::var_os(key) {
+            cmd.env(key, val);
+        }
+    }
+    for key in ["TMPDIR", "TMP", "TEMP"].iter() {
+        cmd.env(key, tmp);
+    }
+    if !sandbox.env.iter().any(|k| k == "HOME") {
+        cmd.env("HOME", tmp);
+    }
+    if let Some((ref wrapper, ref args)) = sandbox.wrapper {
+        let script = cmd.get_program().clone();
+        cmd.program(wrapper).args(args).arg(script);
+    }
+}
+
 impl BuildState {
     pub fn new(config: &super::BuildConfig) -> BuildState {
         let mut overrides = HashMap::new();
@@ -0,0 +1,100 @@
+//! Diagnostics from rustc, which is always asked for them as JSON so that
+//! Cargo can show them through its own shell, colored according to its
+//! settings, and only once when several units run into the same one, like an
+//! error in a macro which is expanded in both a library and its tests.
+
+use serde_json::Value;
+
+/// A diagnostic, as it's shown.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// `error`, `warning`, `note` or `help`.
+    pub level: String,
+    /// The level along with the code, if there's one, as in `error[E0425]`.
+    pub label: String,
+    /// The rest of the first line.
+    pub message: String,
+    /// The lines after the first one, each with its trailing newline.
+    pub rest: String,
+}
+
+impl Diagnostic {
+    /// Reads a diagnostic emitted by rustc with `--error-format json`. Its
+    /// `rendered` text is shown if there's one, older versions of rustc
+    /// don't include it so it's rendered here otherwise.
+    pub fn from_json(json: &Value) -> Option<Diagnostic> {
+        let level = match json["level"].as_str() {
+            Some(level) if !level.is_empty() => level,
+            _ => return None,
+        };
+        let rendered = match json["rendered"].as_str() {
+            Some(rendered) => rendered.to_string(),
+            None => render(json),
+        };
+        let (first, rest) = match rendered.find('\n') {
+            Some(i) => (&rendered[..i], &rendered[i + 1..]),
+            None => (&rendered[..], ""),
+        };
+        let (label, message) = match first.find(": ") {
+            Some(i) if first.starts_with(level) => (&first[..i], &first[i + 2..]),
+            _ => (level, first),
+        };
+        let mut rest = rest.to_string();
+        if !rest.is_empty() && !rest.ends_with('\n') {
+            rest.push('\n');
+        }
+        Some(Diagnostic {
+            level: level.to_string(),
+            label: label.to_string(),
+            message: message.to_string(),
+            rest: rest,
+        })
+    }
+
+    /// Whether this is a warning about the code, rather than the count of
+    /// warnings rustc prints at the end, which Cargo replaces with its own.
+    pub fn is_warning(&self) -> bool {
+        self.level == "warning" && !self.is_summary()
+    }
+
+    /// Whether this is only rustc's count of the warnings of a unit, like
+    /// `2 warnings emitted`.
+    pub fn is_summary(&self) -> bool {
+        self.level == "warning" && self.rest.trim().is_empty() &&
+            (self.message.ends_with(" warning emitted") ||
+             self.message.ends_with(" warnings emitted"))
+    }
+
+    /// The whole diagnostic, by which duplicates are recognized.
+    pub fn text(&self) -> String {
+        format!("{}: {}\n{}", self.label, self.message, self.rest)
+    }
+}
+
+/// Renders a diagnostic in the style of rustc: its message, where it is and
+/// the notes attached to it.
+pub fn render(json: &Value) -> String {
+    let level = json["level"].as_str().unwrap_or("error");
+    let message = json["message"].as_str().unwrap_or("");
+    let mut out = match json["code"]["code"].as_str() {
+        Some(code) => format!("{}[{}]: {}\n", level, code, message),
+        None => format!("{}: {}\n", level, message),
+    };
+    let spans = json["spans"].as_array().map(|s| &s[..]).unwrap_or(&[]);
+    for span in spans.iter().filter(|s| s["is_primary"].as_bool() == Some(true)) {
+        out.push_str(&format!("  --> {}:{}:{}\n",
+                              span["file_name"].as_str().unwrap_or("<unknown>"),
+                              span["line_start"],
+                              span["column_start"]));
+        if let Some(label) = span["label"].as_str() {
+            out.push_str(&format!("   = {}\n", label));
+        }
+    }
+    let children = json["children"].as_array().map(|c| &c[..]).unwrap_or(&[]);
+    for child in children {
+        out.push_str(&format!("   = {}: {}\n",
+                              child["level"].as_str().unwrap_or("note"),
+                              child["message"].as_str().unwrap_or("")));
+    }
+    out
+}
@@ -2,7 +2,7 @@ use std::env;
 use std::fs::{self, File, OpenOptions};
 use std::hash::{self, Hasher};
 use std::io::prelude::*;
-use std::io::{BufReader, SeekFrom};
+use std::io::{self, BufReader, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
@@ -14,7 +14,7 @@ use serde_json;
 use core::{Package, TargetKind};
 use util;
 use util::{Fresh, Dirty, Freshness, internal, profile};
-use util::errors::{CargoResult, CargoResultExt};
+use util::errors::{CargoError, CargoResult, CargoResultExt};
 use util::paths;
 
 use super::job::Work;
@@ -98,6 +98,17 @@ pub fn prepare_target<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
         }
     }
 
+    match compare {
+        Err(ref e) => {
+            cx.stale_reasons.insert(*unit, stale_reason(&loc, e));
+        }
+        Ok(()) if missing_outputs => {
+            cx.stale_reasons.insert(*unit, "some of its outputs are missing"
+                                           .to_string());
+        }
+        Ok(()) => {}
+    }
+
     let allow_failure = unit.profile.rustc_args.is_some();
     let write_fingerprint = Work::new(move |_| {
         match fingerprint.update_local() {
@@ -145,6 +156,9 @@ pub struct Fingerprint {
     #[serde(skip_serializing, skip_deserializing)]
     memoized_hash: Mutex<Option<u64>>,
     rustflags: Vec<String>,
+    /// The program rustc was run through, see `build.rustc-wrapper`
+    #[serde(default)]
+    rustc_wrapper: Option<String>,
 }
 
 fn serialize_deps<S>(deps: &[(String, Arc<Fingerprint>)], ser: S)
@@ -170,6 +184,7 @@ fn deserialize_deps<'de, D>(d: D) -> Result<Vec<(String, Arc<Fingerprint>)>, D::
             deps: Vec::new(),
             memoized_hash: Mutex::new(Some(hash)),
             rustflags: Vec::new(),
+            rustc_wrapper: None,
         }))
     }).collect())
 }
@@ -219,19 +234,26 @@ impl Fingerprint {
 
     fn compare(&self, old: &Fingerprint) -> CargoResult<()> {
         if self.rustc != old.rustc {
-            bail!("rust compiler has changed")
+            bail!("the rust compiler has changed")
+        }
+        if self.rustc_wrapper != old.rustc_wrapper {
+            bail!("the rustc wrapper has changed: previously {} now {}",
+                  describe_wrapper(&old.rustc_wrapper),
+                  describe_wrapper(&self.rustc_wrapper))
         }
         if self.features != old.features {
-            bail!("features have changed: {} != {}", self.features, old.features)
+            bail!("the features have changed: previously {} now {}",
+                  old.features, self.features)
         }
         if self.target != old.target {
-            bail!("target configuration has changed")
+            bail!("the target configuration has changed")
         }
         if self.profile != old.profile {
-            bail!("profile configuration has changed")
+            bail!("the profile configuration has changed")
         }
         if self.rustflags != old.rustflags {
-            return Err(internal("RUSTFLAGS has changed"))
+            bail!("the compiler flags have changed: previously {:?} now {:?}",
+                  old.rustflags, self.rustflags)
         }
         if self.local.len() != old.local.len() {
             bail!("local lens changed");
@@ -241,8 +263,8 @@ impl Fingerprint {
                 (&LocalFingerprint::Precalculated(ref a),
                  &LocalFingerprint::Precalculated(ref b)) => {
                     if a != b {
-                        bail!("precalculated components have changed: {} != {}",
-                              a, b)
+                        bail!("the source has changed: previously {} now {}",
+                              b, a)
                     }
                 }
                 (&LocalFingerprint::MtimeBased(ref on_disk_mtime, ref ap),
@@ -257,6 +279,9 @@ impl Fingerprint {
                     };
 
                     if should_rebuild {
+                        if let Some(path) = stale_input(ap) {
+                            bail!("the file `{}` has changed", path.display())
+                        }
                         bail!("mtime based components have changed: previously {:?} now {:?}, \
                                paths are {:?} and {:?}",
                               *previously_built_mtime, *on_disk_mtime, ap, bp)
@@ -280,8 +305,11 @@ impl Fingerprint {
             bail!("number of dependencies has changed")
         }
         for (a, b) in self.deps.iter().zip(old.deps.iter()) {
+            if a.0 != b.0 {
+                bail!("the dependency `{}` was replaced by `{}`", b.0, a.0)
+            }
             if a.1.hash() != b.1.hash() {
-                bail!("new ({}) != old ({})", a.0, b.0)
+                bail!("the dependency `{}` has changed", a.0)
             }
         }
         Ok(())
@@ -299,8 +327,14 @@ impl hash::Hash for Fingerprint {
             ref local,
             memoized_hash: _,
             ref rustflags,
+            ref rustc_wrapper,
         } = *self;
         (rustc, features, target, profile, local, rustflags).hash(h);
+        // Only hashed when there is one, so that fingerprints from before
+        // wrappers were tracked stay valid.
+        if let Some(ref wrapper) = *rustc_wrapper {
+            wrapper.hash(h);
+        }
 
         h.write_usize(deps.len());
         for &(ref name, ref fingerprint) in deps {
@@ -311,6 +345,13 @@ impl hash::Hash for Fingerprint {
     }
 }
 
+fn describe_wrapper(wrapper: &Option<String>) -> String {
+    match *wrapper {
+        Some(ref wrapper) => format!("`{}`", wrapper),
+        None => "none".to_string(),
+    }
+}
+
 impl hash::Hash for MtimeSlot {
     fn hash<H: Hasher>(&self, h: &mut H) {
         self.0.lock().unwrap().hash(h)
@@ -386,17 +427,34 @@ fn calculate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
     let extra_flags = if unit.profile.doc {
         cx.rustdocflags_args(unit)?
     } else {
-        cx.rustflags_args(unit)?
+        let mut flags = cx.rustflags_args(unit)?;
+        flags.extend(cx.reproducible_args(unit));
+        // Dependents only find the metadata when it was emitted separately.
+        if cx.emits_rmeta(unit) {
+            flags.push("--emit=dep-info,metadata,link".to_string());
+        }
+        // The artifact is only what it should be once the commands ran.
+        for command in cx.post_link_commands(unit) {
+            flags.push(format!("post-link={}", command.join(" ")));
+        }
+        flags
+    };
+    // rustdoc isn't run through the wrapper.
+    let rustc_wrapper = if unit.profile.doc {
+        None
+    } else {
+        cx.config.rustc()?.wrapper.as_ref().map(|w| w.display().to_string())
     };
     let fingerprint = Arc::new(Fingerprint {
         rustc: util::hash_u64(&cx.config.rustc()?.verbose_version),
         target: util::hash_u64(&unit.target),
-        profile: util::hash_u64(&unit.profile),
+        profile: util::hash_u64(&*cx.hashed_profile(unit)),
         features: format!("{:?}", cx.resolve.features_sorted(unit.pkg.package_id())),
         deps: deps,
         local: vec![local],
         memoized_hash: Mutex::new(None),
         rustflags: extra_flags,
+        rustc_wrapper: rustc_wrapper,
     });
     cx.fingerprints.insert(*unit, Arc::clone(&fingerprint));
     Ok(fingerprint)
@@ -448,9 +506,13 @@ pub fn prepare_build_cmd<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
         local: local,
         memoized_hash: Mutex::new(None),
         rustflags: Vec::new(),
+        rustc_wrapper: None,
     };
     let compare = compare_old_fingerprint(&loc, &fingerprint);
     log_compare(unit, &compare);
+    if let Err(ref e) = compare {
+        cx.stale_reasons.insert(*unit, stale_reason(&loc, e));
+    }
 
     // When we write out the fingerprint, we may want to actually change the
     // kind of fingerprint being recorded. If we started out, then the previous
@@ -547,11 +609,76 @@ fn write_fingerprint(loc: &Path, fingerprint: &Fingerprint) -> CargoResult<()> {
     Ok(())
 }
 
+/// Running totals of how many units were found to be fresh or rebuilt, kept in
+/// the target directory for `cargo cache stats` if `build.fingerprint-stats` is
+/// turned on.
+#[derive(Serialize, Deserialize, Default)]
+pub struct FingerprintStats {
+    pub fresh: u64,
+    pub rebuilt: u64,
+}
+
+impl FingerprintStats {
+    /// Loads the totals for `target_dir`, which are all zero if nothing has
+    /// been built there yet.
+    pub fn load(target_dir: &Path) -> FingerprintStats {
+        paths::read(&target_dir.join(STATS_FILE)).ok().and_then(|s| {
+            serde_json::from_str(&s).ok()
+        }).unwrap_or_default()
+    }
+}
+
+const STATS_FILE: &'static str = ".fingerprint-stats.json";
+
+/// Adds the outcome of a build to the running totals in the target directory,
+/// if they're kept at all.
+pub fn record_stats(cx: &Context, fresh: usize, rebuilt: usize) -> CargoResult<()> {
+    let enabled = cx.config.get_bool("build.fingerprint-stats")?.map(|v| v.val);
+    if !enabled.unwrap_or(false) {
+        return Ok(())
+    }
+    let target_dir = cx.ws.target_dir().into_path_unlocked();
+    let mut stats = FingerprintStats::load(&target_dir);
+    stats.fresh += fresh as u64;
+    stats.rebuilt += rebuilt as u64;
+    paths::write(&target_dir.join(STATS_FILE),
+                 &serde_json::to_vec(&stats).unwrap())
+}
+
+/// Returns work which removes the fingerprint of `unit`, to be run right
+/// before it's rebuilt.
+///
+/// The new fingerprint is only written once the build has finished, so if
+/// Cargo is interrupted in between the old one could otherwise still match
+/// whatever outputs were left half written.
+pub fn prepare_invalidate<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> Work {
+    let name = if unit.profile.run_custom_build {
+        "build".to_string()
+    } else {
+        filename(cx, unit)
+    };
+    let loc = cx.fingerprint_dir(unit).join(name);
+    Work::new(move |_| {
+        for path in [loc.clone(), loc.with_extension("json")].iter() {
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).chain_err(|| {
+                        format!("failed to remove fingerprint `{}`", path.display())
+                    })
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
 /// Prepare for work when a package starts to build
 pub fn prepare_init<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult<()> {
     let new1 = cx.fingerprint_dir(unit);
 
-    if fs::metadata(&new1).is_err() {
+    if fs::metadata(&new1).is_err() && !cx.build_config.dry_run {
         fs::create_dir(&new1)?;
     }
 
@@ -589,6 +716,16 @@ fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
     }
 }
 
+/// Describes why the fingerprint at `loc` failed to compare equal, phrased
+/// for `cargo build --dry-run`.
+fn stale_reason(loc: &Path, err: &CargoError) -> String {
+    if !loc.exists() {
+        "it has not been built yet".to_string()
+    } else {
+        err.to_string()
+    }
+}
+
 // Parse the dep-info into a list of paths
 pub fn parse_dep_info(dep_info: &Path) -> CargoResult<Option<Vec<PathBuf>>> {
     macro_rules! fs_try {
@@ -645,6 +782,33 @@ fn pkg_fingerprint(cx: &Context, pkg: &Package) -> CargoResult<String> {
     source.fingerprint(pkg)
 }
 
+/// The modification time of `path`, or for a directory the latest one of the
+/// directory itself and everything in it, recursively.
+///
+/// Editing a file only touches the directory it's in, yet a build script
+/// which printed `rerun-if-changed` for a directory expects to be rerun if
+/// anything below it changes. Symbolic links aren't followed, so this can't
+/// get stuck in a cycle.
+fn newest_mtime(path: &Path, meta: &fs::Metadata) -> FileTime {
+    let mut mtime = FileTime::from_last_modification_time(meta);
+    if !meta.is_dir() {
+        return mtime
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(..) => return mtime,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if let Ok(meta) = fs::symlink_metadata(entry.path()) {
+            let mtime2 = newest_mtime(&entry.path(), &meta);
+            if mtime2 > mtime {
+                mtime = mtime2;
+            }
+        }
+    }
+    mtime
+}
+
 fn mtime_if_fresh<I>(output: &Path, paths: I) -> Option<FileTime>
     where I: IntoIterator,
           I::Item: AsRef<Path>,
@@ -655,8 +819,20 @@ fn mtime_if_fresh<I>(output: &Path, paths: I) -> Option<FileTime>
     };
     let mtime = FileTime::from_last_modification_time(&meta);
 
-    let any_stale = paths.into_iter().any(|path| {
-        let path = path.as_ref();
+    if first_stale(mtime, paths).is_some() {
+        None
+    } else {
+        Some(mtime)
+    }
+}
+
+/// Returns the first of `paths` which is missing or was modified after
+/// `mtime`. A directory counts as modified if anything inside it was.
+fn first_stale<I>(mtime: FileTime, paths: I) -> Option<PathBuf>
+    where I: IntoIterator,
+          I::Item: AsRef<Path>,
+{
+    paths.into_iter().map(|p| p.as_ref().to_path_buf()).find(|path| {
         let meta = match fs::metadata(path) {
             Ok(meta) => meta,
             Err(..) => {
@@ -664,19 +840,27 @@ fn mtime_if_fresh<I>(output: &Path, paths: I) -> Option<FileTime>
                 return true
             }
         };
-        let mtime2 = FileTime::from_last_modification_time(&meta);
+        let mtime2 = newest_mtime(path, &meta);
         if mtime2 > mtime {
             info!("stale: {} -- {} vs {}", path.display(), mtime2, mtime);
             true
         } else {
             false
         }
-    });
+    })
+}
 
-    if any_stale {
-        None
-    } else {
-        Some(mtime)
+/// Finds the input listed in the dep-info file `dep_info` which caused it to
+/// be considered stale, if it's a dep-info file at all.
+fn stale_input(dep_info: &Path) -> Option<PathBuf> {
+    let meta = match fs::metadata(dep_info) {
+        Ok(meta) => meta,
+        Err(..) => return None,
+    };
+    let mtime = FileTime::from_last_modification_time(&meta);
+    match parse_dep_info(dep_info) {
+        Ok(Some(paths)) => first_stale(mtime, paths),
+        _ => None,
     }
 }
 
@@ -3,18 +3,25 @@ use std::collections::hash_map::HashMap;
 use std::fmt;
 use std::io;
 use std::mem;
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
 
 use crossbeam::{self, Scope};
 use jobserver::{Acquired, HelperThread};
 
 use core::{PackageId, Target, Profile};
-use util::{Config, DependencyQueue, Fresh, Dirty, Freshness};
+use core::dependency::Kind as DepKind;
+use util::{Artifact, Config, DependencyQueue, Fresh, Dirty, Freshness};
 use util::{CargoResult, ProcessBuilder, profile, internal, CargoResultExt};
+use util::errors::{CargoError, CargoErrorKind};
+use util::{job, paths};
 use {handle_error};
 
 use super::{Context, Kind, Unit};
+use super::diagnostics::Diagnostic;
+use super::fingerprint;
 use super::job::Job;
+use super::timings::{self, UnitTime};
 
 /// A management structure of the entire dependency graph to compile.
 ///
@@ -30,6 +37,14 @@ pub struct JobQueue<'a> {
     compiled: HashSet<&'a PackageId>,
     documented: HashSet<&'a PackageId>,
     counts: HashMap<&'a PackageId, usize>,
+    stale_reasons: HashMap<Key<'a>, String>,
+    dirty: HashSet<Key<'a>>,
+    /// When each unit started and finished, relative to the start of the
+    /// build, in the order they were started.
+    times: Vec<(Key<'a>, Duration, Option<Duration>)>,
+    /// The `--extern` crates each unit compiled in this build didn't use,
+    /// see `-Z unused-dependencies`.
+    unused_externs: HashMap<Key<'a>, Vec<String>>,
     is_release: bool,
 }
 
@@ -52,13 +67,18 @@ struct Key<'a> {
 
 pub struct JobState<'a> {
     tx: Sender<Message<'a>>,
+    key: Key<'a>,
 }
 
 enum Message<'a> {
     Run(String),
+    Restored(PackageId),
+    Diagnostic(Diagnostic),
     Stdout(String),
     Stderr(String),
     Token(io::Result<Acquired>),
+    Metadata(Key<'a>),
+    UnusedExterns(Key<'a>, Vec<String>),
     Finish(Key<'a>, CargoResult<()>),
 }
 
@@ -67,6 +87,17 @@ impl<'a> JobState<'a> {
         let _ = self.tx.send(Message::Run(cmd.to_string()));
     }
 
+    /// Notes that the outputs of the unit were taken from the shared build
+    /// cache instead of compiling it, see `build.shared-cache`.
+    pub fn restored(&self, id: &PackageId) {
+        let _ = self.tx.send(Message::Restored(id.clone()));
+    }
+
+    /// Shows a diagnostic from rustc, unless it was shown already.
+    pub fn diagnostic(&self, diagnostic: Diagnostic) {
+        let _ = self.tx.send(Message::Diagnostic(diagnostic));
+    }
+
     pub fn stdout(&self, out: &str) {
         let _ = self.tx.send(Message::Stdout(out.to_string()));
     }
@@ -74,6 +105,18 @@ impl<'a> JobState<'a> {
     pub fn stderr(&self, err: &str) {
         let _ = self.tx.send(Message::Stderr(err.to_string()));
     }
+
+    /// Notes that rustc has written the metadata of the unit being built, so
+    /// that the units which only need that can start, see `build.pipelining`.
+    pub fn metadata_ready(&self) {
+        let _ = self.tx.send(Message::Metadata(self.key));
+    }
+
+    /// Notes which of the crates passed with `--extern` the unit didn't use,
+    /// as reported by rustc.
+    pub fn unused_externs(&self, names: Vec<String>) {
+        let _ = self.tx.send(Message::UnusedExterns(self.key, names));
+    }
 }
 
 impl<'a> JobQueue<'a> {
@@ -88,6 +131,10 @@ impl<'a> JobQueue<'a> {
             compiled: HashSet::new(),
             documented: HashSet::new(),
             counts: HashMap::new(),
+            stale_reasons: HashMap::new(),
+            dirty: HashSet::new(),
+            times: Vec::new(),
+            unused_externs: HashMap::new(),
             is_release: cx.build_config.release,
         }
     }
@@ -99,8 +146,42 @@ impl<'a> JobQueue<'a> {
                          fresh: Freshness) -> CargoResult<()> {
         let key = Key::new(unit);
         let deps = key.dependencies(cx)?;
+        let deps = deps.into_iter().map(|(dep, only_metadata)| {
+            (dep, if only_metadata { Artifact::Metadata } else { Artifact::All })
+        }).collect::<Vec<_>>();
         self.queue.queue(Fresh, key, Vec::new(), &deps).push((job, fresh));
         *self.counts.entry(key.pkg).or_insert(0) += 1;
+        if let Some(reason) = cx.stale_reasons.get(unit) {
+            self.stale_reasons.insert(key, reason.clone());
+        }
+        Ok(())
+    }
+
+    /// Walk the dependency graph without executing any jobs, printing each
+    /// unit of work which would be rebuilt along with why.
+    ///
+    /// Freshness is propagated exactly as it is in `execute`, so a unit whose
+    /// own fingerprint is fine is still reported if one of its dependencies
+    /// would be rebuilt.
+    pub fn dry_run<'cfg>(&mut self, cx: &Context<'a, 'cfg>) -> CargoResult<()> {
+        while let Some((fresh, key, jobs)) = self.queue.dequeue() {
+            let fresh = jobs.iter().fold(fresh, |fresh, &(_, f)| {
+                f.combine(fresh)
+            });
+            if fresh == Dirty {
+                let reason = self.mark_dirty(cx, key)?;
+                cx.config.shell().status("Dirty", format!("{}: {}",
+                                                          key.description(),
+                                                          reason))?;
+            }
+            self.queue.finish(&key, fresh);
+        }
+        let message = if self.dirty.is_empty() {
+            "nothing would be rebuilt".to_string()
+        } else {
+            format!("{} unit(s) would be rebuilt", self.dirty.len())
+        };
+        cx.config.shell().status("Finished", format!("dry run, {}", message))?;
         Ok(())
     }
 
@@ -109,9 +190,13 @@ impl<'a> JobQueue<'a> {
     /// This function will spawn off `config.jobs()` workers to build all of the
     /// necessary dependencies, in order. Freshness is propagated as far as
     /// possible along each dependency chain.
-    pub fn execute(&mut self, cx: &mut Context) -> CargoResult<()> {
+    pub fn execute<'cfg>(&mut self, cx: &mut Context<'a, 'cfg>) -> CargoResult<()> {
         let _p = profile::start("executing the job graph");
 
+        // Stop cleanly if we're interrupted rather than dying with jobs still
+        // running, see `drain_the_queue`.
+        let _defer = job::defer_interrupts();
+
         // We need to give a handle to the send half of our message queue to the
         // jobserver helper thread. Unfortunately though we need the handle to be
         // `'static` as that's typically what's required when spawning a
@@ -140,15 +225,15 @@ impl<'a> JobQueue<'a> {
         })
     }
 
-    fn drain_the_queue(&mut self,
-                       cx: &mut Context,
+    fn drain_the_queue<'cfg>(&mut self,
+                             cx: &mut Context<'a, 'cfg>,
                        scope: &Scope<'a>,
                        jobserver_helper: &HelperThread)
                        -> CargoResult<()> {
-        use std::time::Instant;
-
         let mut tokens = Vec::new();
         let mut queue = Vec::new();
+        let mut diagnostics = HashSet::new();
+        let mut warnings = WarningCount::default();
         trace!("queue: {:#?}", self.queue);
 
         // Iteratively execute the entire dependency graph. Each turn of the
@@ -161,7 +246,15 @@ impl<'a> JobQueue<'a> {
         // After a job has finished we update our internal state if it was
         // successful and otherwise wait for pending work to finish if it failed
         // and then immediately return.
+        //
+        // If Cargo is interrupted no more work is started, everything still
+        // running is killed and once it's all finished the build is reported
+        // as interrupted. Units are only marked fresh once they've finished
+        // successfully, so nothing killed halfway is mistaken for being fresh
+        // by the next build.
         let mut error = None;
+        let mut interrupted = false;
+        let target_dir = cx.ws.target_dir().into_path_unlocked();
         let start_time = Instant::now();
         loop {
             // Dequeue as much work as we can, learning about everything
@@ -187,8 +280,26 @@ impl<'a> JobQueue<'a> {
             // Now that we've learned of all possible work that we can execute
             // try to spawn it so long as we've got a jobserver token which says
             // we're able to perform some parallel work.
-            while error.is_none() && self.active < tokens.len() + 1 && !queue.is_empty() {
+            while error.is_none() && !job::interrupted() &&
+                  self.active < tokens.len() + 1 && !queue.is_empty() {
                 let (key, job, fresh) = queue.remove(0);
+                if fresh == Dirty && !self.dirty.contains(&key) {
+                    // Before anything is rebuilt make sure there's room for
+                    // it, rather than failing halfway through.
+                    if self.dirty.is_empty() {
+                        paths::check_disk_space(&target_dir, 0)?;
+                    }
+                    let reason = self.mark_dirty(cx, key)?;
+                    if cx.config.extra_verbose() {
+                        cx.config.shell().status("Dirty", format!("{}: {}",
+                                                                  key.description(),
+                                                                  reason))?;
+                    }
+                }
+                if cx.build_config.timings &&
+                   !self.times.iter().any(|&(k, _, _)| k == key) {
+                    self.times.push((key, start_time.elapsed(), None));
+                }
                 self.run(key, fresh, job, cx.config, scope)?;
             }
 
@@ -205,13 +316,49 @@ impl<'a> JobQueue<'a> {
             // to the jobserver itself.
             tokens.truncate(self.active - 1);
 
-            match self.rx.recv().unwrap() {
+            if job::interrupted() && !interrupted {
+                interrupted = true;
+                job::kill_children();
+                cx.config.shell().warn("interrupted, waiting for running jobs \
+                                        to finish...")?;
+            }
+
+            // Wake up every now and then to notice interruptions, as the jobs
+            // themselves may not have received the signal.
+            let msg = match self.rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(internal("job queue channel disconnected"))
+                }
+            };
+            match msg {
                 Message::Run(cmd) => {
                     cx.config.shell().verbose(|c| c.status("Running", &cmd))?;
                 }
+                Message::Diagnostic(ref diagnostic) if diagnostic.is_summary() => {}
+                Message::Diagnostic(diagnostic) => {
+                    let warning = diagnostic.is_warning();
+                    let new = diagnostics.insert(diagnostic.text());
+                    if warning {
+                        warnings.add(new);
+                    }
+                    if new || (warning && cx.build_config.all_warnings) {
+                        cx.config.shell().diagnostic(&diagnostic.level, &diagnostic.label,
+                                                     &diagnostic.message, &diagnostic.rest)?;
+                    }
+                }
+                Message::Restored(id) => {
+                    cx.config.shell().verbose(|c| {
+                        c.status("Restored", format!("{} from the shared build cache", id))
+                    })?;
+                }
+                // Build scripts talk to Cargo on their stdout, which isn't
+                // something for ours, that's reserved for machine-readable
+                // output like `--message-format json`.
                 Message::Stdout(out) => {
                     if cx.config.extra_verbose() {
-                        println!("{}", out);
+                        writeln!(cx.config.shell().err(), "{}", out)?;
                     }
                 }
                 Message::Stderr(err) => {
@@ -226,9 +373,23 @@ impl<'a> JobQueue<'a> {
                         assert!(!tokens.is_empty());
                         drop(tokens.pop());
                     }
+                    if let Some(time) = self.times.iter_mut().find(|t| t.0 == key) {
+                        time.2 = Some(start_time.elapsed());
+                    }
                     match result {
                         Ok(()) => self.finish(key, cx)?,
+                        Err(e) if job::interrupted() => {
+                            // Most likely killed by the interruption, which
+                            // is all there is to report.
+                            debug!("{:?} failed after interruption: {}", key, e);
+                        }
                         Err(e) => {
+                            // Compilers only report running out of space in
+                            // their own output, so check for that ourselves.
+                            let e = match paths::check_disk_space(&target_dir, 0) {
+                                Err(space) => CargoError::with_chain(e, space.0),
+                                Ok(()) => e,
+                            };
                             let msg = "The following warnings were emitted during compilation:";
                             self.emit_warnings(Some(msg), key, cx)?;
 
@@ -245,6 +406,14 @@ impl<'a> JobQueue<'a> {
                         }
                     }
                 }
+                Message::Metadata(key) => {
+                    info!("metadata ready: {:?}", key);
+                    let fresh = self.pending[&key].fresh;
+                    self.queue.metadata_ready(&key, fresh);
+                }
+                Message::UnusedExterns(key, names) => {
+                    self.unused_externs.insert(key, names);
+                }
                 Message::Token(acquired_token) => {
                     tokens.push(acquired_token.chain_err(|| {
                         "failed to acquire jobserver token"
@@ -253,6 +422,10 @@ impl<'a> JobQueue<'a> {
             }
         }
 
+        if let Some(summary) = warnings.summary(cx.build_config.all_warnings) {
+            cx.config.shell().warn(summary)?;
+        }
+
         let build_type = if self.is_release { "release" } else { "dev" };
         let profile = cx.lib_profile();
         let mut opt_type = String::from(if profile.opt_level == "0" { "unoptimized" }
@@ -265,12 +438,22 @@ impl<'a> JobQueue<'a> {
                                    duration.as_secs(),
                                    duration.subsec_nanos() / 10_000_000);
         if self.queue.is_empty() {
+            if cx.config.cli_unstable().unused_dependencies {
+                self.report_unused_dependencies(cx)?;
+            }
             let message = format!("{} [{}] target(s) in {}",
                                   build_type,
                                   opt_type,
                                   time_elapsed);
             cx.config.shell().status("Finished", message)?;
+            let rebuilt = self.dirty.len();
+            fingerprint::record_stats(cx, self.pending.len() - rebuilt, rebuilt)?;
+            if cx.build_config.timings {
+                self.report_timings(cx, duration)?;
+            }
             Ok(())
+        } else if job::interrupted() {
+            Err(CargoErrorKind::Interrupted.into())
         } else if let Some(e) = error {
             Err(e)
         } else {
@@ -279,6 +462,118 @@ impl<'a> JobQueue<'a> {
         }
     }
 
+    /// Prints the summary of `cargo build --timings` and writes its reports.
+    fn report_timings<'cfg>(&self,
+                            cx: &Context<'a, 'cfg>,
+                            wall_time: Duration) -> CargoResult<()> {
+        let mut units = Vec::new();
+        for &(key, start, end) in self.times.iter() {
+            let end = end.unwrap_or(wall_time);
+            let deps = key.dependencies(cx)?.iter().filter_map(|&(dep, _)| {
+                self.times.iter().position(|&(k, _, _)| k == dep)
+            }).collect();
+            units.push(UnitTime {
+                name: key.description(),
+                start: timings::seconds(start),
+                duration: timings::seconds(end - start),
+                rebuilt: self.dirty.contains(&key),
+                deps: deps,
+            });
+        }
+        let dir = cx.ws.target_dir().into_path_unlocked().join("cargo-timings");
+        timings::report(cx.config, dir, &units, timings::seconds(wall_time),
+                        cx.jobs())
+    }
+
+    /// Warns about the dependencies of the members which none of their
+    /// targets use, see `-Z unused-dependencies`.
+    ///
+    /// Only the units compiled in this build are checked by rustc, so a
+    /// dependency is only reported once all the targets which may use it were
+    /// compiled: the libraries and binaries for `[dependencies]`, the tests,
+    /// benchmarks and examples for `[dev-dependencies]` and the build script
+    /// for `[build-dependencies]`.
+    fn report_unused_dependencies<'cfg>(&self, cx: &Context<'a, 'cfg>) -> CargoResult<()> {
+        // The packages passed to each unit, and whether it used them.
+        let mut externs = HashMap::new();
+        for (key, unused) in self.unused_externs.iter() {
+            let unit = Unit {
+                pkg: cx.get_package(key.pkg)?,
+                target: key.target,
+                profile: key.profile,
+                kind: key.kind,
+            };
+            let deps = cx.dep_targets(&unit)?.into_iter().filter(|dep| {
+                dep.target.linkable() && !dep.profile.doc && dep.pkg != unit.pkg
+            }).map(|dep| {
+                (dep.pkg.name(), !unused.contains(&dep.target.crate_name()))
+            }).collect::<Vec<_>>();
+            externs.insert(*key, deps);
+        }
+
+        for pkg in cx.ws.members() {
+            let kinds = [(DepKind::Normal, "dependencies"),
+                         (DepKind::Development, "dev-dependencies"),
+                         (DepKind::Build, "build-dependencies")];
+            for &(kind, section) in kinds.iter() {
+                let checks = |target: &Target| match kind {
+                    DepKind::Normal => target.is_lib() || target.is_bin(),
+                    DepKind::Development => {
+                        target.is_test() || target.is_bench() || target.is_example()
+                    }
+                    DepKind::Build => target.is_custom_build(),
+                };
+                let keys = externs.keys().filter(|key| {
+                    key.pkg == pkg.package_id() &&
+                        (checks(key.target) ||
+                         (kind == DepKind::Development && key.profile.test))
+                }).collect::<Vec<_>>();
+                let all_checked = pkg.targets().iter().filter(|t| checks(t)).all(|t| {
+                    keys.iter().any(|key| key.target == t)
+                });
+                if keys.is_empty() || !all_checked {
+                    continue
+                }
+
+                for dep in pkg.dependencies().iter().filter(|d| d.kind() == kind) {
+                    let uses = keys.iter().flat_map(|key| externs[*key].iter())
+                                   .filter(|&&(name, _)| name == dep.name())
+                                   .map(|&(_, used)| used)
+                                   .collect::<Vec<_>>();
+                    if !uses.is_empty() && !uses.iter().any(|&used| used) {
+                        cx.config.shell().warn(format!(
+                            "unused dependency `{}` in `[{}]` of `{}`",
+                            dep.name(), section, pkg.name()))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that `key` is going to be rebuilt, returning why.
+    ///
+    /// Units which are stale themselves have the reason recorded when their
+    /// fingerprint was compared, otherwise they're only being rebuilt because
+    /// one of their dependencies is.
+    fn mark_dirty<'cfg>(&mut self,
+                        cx: &Context<'a, 'cfg>,
+                        key: Key<'a>) -> CargoResult<String> {
+        let reason = match self.stale_reasons.get(&key) {
+            Some(reason) => reason.clone(),
+            None => {
+                let deps = key.dependencies(cx)?;
+                match deps.iter().find(|&&(dep, _)| self.dirty.contains(&dep)) {
+                    Some(&(dep, _)) => format!("its dependency {} needs to be \
+                                                rebuilt", dep.description()),
+                    None => "it is always rebuilt".to_string(),
+                }
+            }
+        };
+        self.dirty.insert(key);
+        Ok(reason)
+    }
+
     /// Executes a job in the `scope` given, pushing the spawned thread's
     /// handled onto `threads`.
     fn run(&mut self,
@@ -296,6 +591,7 @@ impl<'a> JobQueue<'a> {
         let doit = move || {
             let res = job.run(fresh, &JobState {
                 tx: my_tx.clone(),
+                key: key,
             });
             my_tx.send(Message::Finish(key, res)).unwrap();
         };
@@ -387,6 +683,37 @@ impl<'a> JobQueue<'a> {
     }
 }
 
+/// How many warnings were emitted during a build, and how many of them were
+/// duplicates of one already shown.
+#[derive(Default)]
+struct WarningCount {
+    total: usize,
+    duplicates: usize,
+}
+
+impl WarningCount {
+    fn add(&mut self, new: bool) {
+        self.total += 1;
+        if !new {
+            self.duplicates += 1;
+        }
+    }
+
+    /// The line printed once the build finishes, if there were warnings.
+    fn summary(&self, all_warnings: bool) -> Option<String> {
+        if self.total == 0 {
+            return None
+        }
+        let mut summary = format!("{} warning{} emitted", self.total,
+                                  if self.total == 1 { "" } else { "s" });
+        if self.duplicates > 0 && !all_warnings {
+            summary.push_str(&format!(" ({} duplicate{} suppressed)", self.duplicates,
+                                      if self.duplicates == 1 { "" } else { "s" }));
+        }
+        Some(summary)
+    }
+}
+
 impl<'a> Key<'a> {
     fn new(unit: &Unit<'a>) -> Key<'a> {
         Key {
@@ -397,22 +724,56 @@ impl<'a> Key<'a> {
         }
     }
 
+    /// A human readable name for this unit of work, e.g.
+    /// `foo v0.1.0 (file:///foo) (bin "foo")`.
+    fn description(&self) -> String {
+        let target = if self.profile.run_custom_build {
+            "build script run".to_string()
+        } else if self.target.is_custom_build() {
+            "build script".to_string()
+        } else if self.target.is_lib() {
+            "lib".to_string()
+        } else {
+            let kind = if self.target.is_bin() {
+                "bin"
+            } else if self.target.is_example() {
+                "example"
+            } else if self.target.is_test() {
+                "test"
+            } else {
+                "bench"
+            };
+            format!("{} \"{}\"", kind, self.target.name())
+        };
+        let target = if self.profile.test && !self.target.is_test() &&
+                        !self.target.is_bench() {
+            format!("{} test", target)
+        } else if self.profile.doc {
+            format!("{} docs", target)
+        } else {
+            target
+        };
+        format!("{} ({})", self.pkg, target)
+    }
+
+    /// The units this one depends on, along with whether their metadata is
+    /// all that's needed to start building this one.
     fn dependencies<'cfg>(&self, cx: &Context<'a, 'cfg>)
-                          -> CargoResult<Vec<Key<'a>>> {
-        let unit = Unit {
+                          -> CargoResult<Vec<(Key<'a>, bool)>> {
+        let parent = Unit {
             pkg: cx.get_package(self.pkg)?,
             target: self.target,
             profile: self.profile,
             kind: self.kind,
         };
-        let targets = cx.dep_targets(&unit)?;
+        let targets = cx.dep_targets(&parent)?;
         Ok(targets.iter().filter_map(|unit| {
             // Binaries aren't actually needed to *compile* tests, just to run
             // them, so we don't include this dependency edge in the job graph.
             if self.target.is_test() && unit.target.is_bin() {
                 None
             } else {
-                Some(Key::new(unit))
+                Some((Key::new(unit), cx.only_requires_rmeta(&parent, unit)))
             }
         }).collect())
     }
@@ -48,6 +48,12 @@
 //!     # packages
 //!     .fingerprint/
 //! ```
+//!
+//! All of the above is placed in a directory per target triple and profile,
+//! e.g. `target/debug`. The root of the target directory also holds the
+//! version of this layout in `.layout-version`, see `util::layout_version`.
+//! When the layout changes, bump `layout_version::TARGET_DIR` and teach
+//! `check_version` how to migrate from the previous one.
 
 use std::fs;
 use std::io;
@@ -55,6 +61,7 @@ use std::path::{PathBuf, Path};
 
 use core::Workspace;
 use util::{Config, FileLock, CargoResult, Filesystem};
+use util::layout_version;
 
 /// Contains the paths of all target output locations.
 ///
@@ -68,7 +75,7 @@ pub struct Layout {
     fingerprint: PathBuf,
     examples: PathBuf,
     /// The lockfile for a build, will be unlocked when this struct is `drop`ped.
-    _lock: FileLock,
+    _lock: Option<FileLock>,
 }
 
 pub fn is_bad_artifact_name(name: &str) -> bool {
@@ -87,6 +94,21 @@ impl Layout {
     pub fn new(ws: &Workspace,
                triple: Option<&str>,
                dest: &str) -> CargoResult<Layout> {
+        check_version(ws.config(), &ws.target_dir())?;
+        Layout::at(ws.config(), Layout::dir(ws, triple, dest)?)
+    }
+
+    /// Calculate the paths for build output like `new`, but without creating
+    /// or locking anything, for looking at what earlier builds left behind
+    /// without touching it, e.g. in a dry run.
+    pub fn peek(ws: &Workspace,
+                triple: Option<&str>,
+                dest: &str) -> CargoResult<Layout> {
+        let root = Layout::dir(ws, triple, dest)?.into_path_unlocked();
+        Ok(Layout::with_root(root, None))
+    }
+
+    fn dir(ws: &Workspace, triple: Option<&str>, dest: &str) -> CargoResult<Filesystem> {
         let mut path = ws.target_dir();
         // Flexible target specifications often point at filenames, so interpret
         // the target triple as a Path and then just use the file stem as the
@@ -95,7 +117,7 @@ impl Layout {
             path.push(Path::new(triple).file_stem().ok_or_else(|| "target was empty")?);
         }
         path.push(dest);
-        Layout::at(ws.config(), path)
+        Ok(path)
     }
 
     /// Calculate the paths for build output, lock the build directory, and return as a Layout.
@@ -107,8 +129,11 @@ impl Layout {
         // compile.
         let lock = root.open_rw(".cargo-lock", config, "build directory")?;
         let root = root.into_path_unlocked();
+        Ok(Layout::with_root(root, Some(lock)))
+    }
 
-        Ok(Layout {
+    fn with_root(root: PathBuf, lock: Option<FileLock>) -> Layout {
+        Layout {
             deps: root.join("deps"),
             native: root.join("native"),
             build: root.join("build"),
@@ -117,7 +142,7 @@ impl Layout {
             examples: root.join("examples"),
             root: root,
             _lock: lock,
-        })
+        }
     }
 
     #[cfg(not(target_os = "macos"))]
@@ -189,3 +214,47 @@ impl Layout {
     /// Fetch the build path.
     pub fn build(&self) -> &Path { &self.build }
 }
+
+/// Makes sure the target directory has the current layout version, see
+/// `util::layout_version`.
+fn check_version(config: &Config, target_dir: &Filesystem) -> CargoResult<()> {
+    let path = target_dir.clone().into_path_unlocked();
+    let fresh = match fs::read_dir(&path) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => true,
+    };
+    create_target_dir(&path)?;
+    layout_version::check(config, target_dir, "target directory",
+                          layout_version::TARGET_DIR, fresh,
+                          "run `cargo clean` to build everything again with this one",
+                          |found, _| {
+        match found {
+            // Target directories were laid out as in version 1 before they
+            // were versioned.
+            0 => Ok(()),
+            // There's no way to migrate anything else, so everything has to be
+            // built again from scratch. The target directory may be anywhere
+            // and hold anything else too, so it's up to `cargo clean` to
+            // remove it.
+            _ => bail!("the artifacts in it were built by an older version of \
+                        Cargo and can't be reused, run `cargo clean` to build \
+                        everything again"),
+        }
+    })
+}
+
+/// Creates the target directory `path` if it doesn't exist yet, pointing out
+/// which directory it is if that fails.
+fn create_target_dir(path: &Path) -> CargoResult<()> {
+    if let Err(e) = fs::create_dir_all(path) {
+        let hint = if e.kind() == io::ErrorKind::PermissionDenied {
+            "\n\nSet `build.target-dir` or `CARGO_TARGET_DIR` to build somewhere \
+             else if it can't be written to."
+        } else {
+            ""
+        };
+        bail!("failed to create the target directory `{}`: {}{}",
+              path.display(), e, hint)
+    }
+    Ok(())
+}
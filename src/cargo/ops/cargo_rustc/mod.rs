@@ -10,13 +10,14 @@ use same_file::is_same_file;
 use serde_json;
 
 use core::{Package, PackageId, PackageSet, Target, Resolve};
-use core::{Profile, Profiles, Workspace};
+use core::{Lto, Profile, Profiles, Workspace};
 use core::shell::ColorChoice;
 use util::{self, ProcessBuilder, machine_message};
-use util::{Config, internal, profile, join_paths};
+use util::{CacheLockMode, Config, internal, profile, join_paths};
 use util::errors::{CargoResult, CargoResultExt};
 use util::Freshness;
 
+use self::diagnostics::Diagnostic;
 use self::job::{Job, Work};
 use self::job_queue::JobQueue;
 
@@ -25,17 +26,22 @@ use self::output_depinfo::output_depinfo;
 pub use self::compilation::Compilation;
 pub use self::context::{Context, Unit, TargetFileType};
 pub use self::custom_build::{BuildOutput, BuildMap, BuildScripts};
+pub use self::fingerprint::FingerprintStats;
 pub use self::layout::is_bad_artifact_name;
 
 mod compilation;
 mod context;
 mod custom_build;
+mod diagnostics;
 mod fingerprint;
 mod job;
 mod job_queue;
 mod layout;
 mod links;
 mod output_depinfo;
+mod shared_cache;
+mod sizes;
+mod timings;
 
 /// Whether an object is for the host arch, or the target arch.
 ///
@@ -69,6 +75,37 @@ pub struct BuildConfig {
     pub doc_all: bool,
     /// Whether to print std output in json format (for machine reading)
     pub json_messages: bool,
+    /// Whether to only report what would be rebuilt instead of building it
+    pub dry_run: bool,
+    /// Whether to report how long each unit took to build
+    pub timings: bool,
+    /// Whether to show every warning, instead of only the first of identical
+    /// ones from several units
+    pub all_warnings: bool,
+    /// Whether a warning in a workspace member fails the build
+    pub deny_warnings: bool,
+    /// Whether to report the size of the artifacts after a release build
+    pub size_report: bool,
+    /// Whether to make the output independent of where and when it's built
+    pub reproducible: bool,
+    /// Whether libraries can start building as soon as the metadata of the
+    /// libraries they depend on is available, see `Context::emits_rmeta`
+    pub pipelining: bool,
+    /// The restrictions build scripts run under, if they're sandboxed
+    pub script_sandbox: Option<ScriptSandbox>,
+}
+
+/// How build scripts are run when they're sandboxed, from `[build.sandbox]`
+#[derive(Clone, Default)]
+pub struct ScriptSandbox {
+    /// The variables of Cargo's environment which build scripts get, all
+    /// others are removed
+    pub env: Vec<String>,
+    /// A command build scripts are run with, along with its arguments, to
+    /// confine them further. It's followed by the path of the script.
+    pub wrapper: Option<(PathBuf, Vec<String>)>,
+    /// Names of the packages whose build scripts run unrestricted
+    pub allow: Vec<String>,
 }
 
 /// Information required to build for a target
@@ -80,6 +117,11 @@ pub struct TargetConfig {
     pub linker: Option<PathBuf>,
     /// Special build options for any necessary input files (filename -> options)
     pub overrides: HashMap<String, BuildOutput>,
+    /// Commands run on each binary and cdylib once it's linked, with its path
+    /// as their last argument.
+    pub post_link: Vec<Vec<String>>,
+    /// The `strip` tool for this target, used for the `strip` profile setting.
+    pub strip: Option<PathBuf>,
 }
 
 pub type PackagesToBuild<'a> = [(&'a Package, Vec<(&'a Target, &'a Profile)>)];
@@ -88,6 +130,18 @@ pub type PackagesToBuild<'a> = [(&'a Package, Vec<(&'a Target, &'a Profile)>)];
 /// directly, we'll use an Executor, giving clients an opportunity to intercept
 /// the build calls.
 pub trait Executor: Send + Sync + 'static {
+    /// Called once dependencies have been resolved, before anything is built,
+    /// with the resolved graph, every package in it and the targets of each
+    /// package that were requested. Tools embedding Cargo can use this to
+    /// inspect the build plan, and in case of an `Err` nothing is built.
+    fn resolved(&self,
+                _resolve: &Resolve,
+                _packages: &PackageSet,
+                _to_build: &PackagesToBuild)
+                -> CargoResult<()> {
+        Ok(())
+    }
+
     /// Called after a rustc process invocation is prepared up-front for a given
     /// unit of work (may still be modified for runtime-known dependencies, when
     /// the work is actually executed).
@@ -156,12 +210,35 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
         })
     }).collect::<Vec<_>>();
 
+    // The sources of dependencies are read from the package cache until
+    // everything is built, so make sure nothing is removed from it meanwhile.
+    // Anything which still has to be downloaded locks the cache for that by
+    // itself, which doesn't wait for other builds.
+    let _lock = config.acquire_package_cache_lock(CacheLockMode::Shared)?;
+
     let mut cx = Context::new(ws, resolve, packages, config,
                                    build_config, profiles)?;
 
     let mut queue = JobQueue::new(&cx);
 
     cx.prepare()?;
+
+    // A dry run doesn't download anything, and what depends on packages which
+    // haven't been downloaded yet can't be looked at.
+    if cx.build_config.dry_run {
+        let missing = cx.missing_deps(&units)?;
+        if !missing.is_empty() {
+            for id in missing.iter() {
+                config.shell().status("Dirty", format!("{}: not downloaded yet", id))?;
+            }
+            config.shell().status("Finished", format!(
+                "dry run, {} package(s) would be downloaded, run `cargo fetch` \
+                 to find out what would be rebuilt", missing.len()))?;
+            return Ok(cx.compilation)
+        }
+    } else {
+        cx.prefetch_deps(&units)?;
+    }
     cx.probe_target_info(&units)?;
     cx.build_used_in_plugin_map(&units)?;
     custom_build::build_map(&mut cx, &units)?;
@@ -175,9 +252,17 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
         compile(&mut cx, &mut queue, unit, Arc::clone(&exec))?;
     }
 
+    // For a dry run we stop short of doing anything and just report what
+    // would have been done.
+    if cx.build_config.dry_run {
+        queue.dry_run(&cx)?;
+        return Ok(cx.compilation)
+    }
+
     // Now that we've figured out everything that we're going to do, do it!
     queue.execute(&mut cx)?;
 
+    let mut artifacts = Vec::new();
     for unit in units.iter() {
         for &(ref dst, ref link_dst, file_type) in cx.target_filenames(unit)?.iter() {
             if file_type == TargetFileType::DebugInfo {
@@ -196,10 +281,14 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
                                            dst.clone()));
             } else if unit.target.is_bin() || unit.target.is_example() {
                 cx.compilation.binaries.push(bindst.clone());
+                artifacts.push(bindst.clone());
             } else if unit.target.is_lib() {
                 let pkgid = unit.pkg.package_id().clone();
                 cx.compilation.libraries.entry(pkgid).or_insert(HashSet::new())
                   .insert((unit.target.clone(), dst.clone()));
+                if !unit.profile.doc {
+                    artifacts.push(bindst.clone());
+                }
             }
         }
 
@@ -207,7 +296,7 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
             if !unit.target.is_lib() { continue }
 
             if dep.profile.run_custom_build {
-                let out_dir = cx.build_script_out_dir(dep).display().to_string();
+                let out_dir = cx.build_script_out_dir(dep).into_os_string();
                 cx.compilation.extra_env.entry(dep.pkg.package_id().clone())
                   .or_insert(Vec::new())
                   .push(("OUT_DIR".to_string(), out_dir));
@@ -240,13 +329,17 @@ pub fn compile_targets<'a, 'cfg: 'a>(ws: &Workspace<'cfg>,
 
         cx.compilation.extra_env.entry(pkg.clone())
             .or_insert_with(Vec::new)
-            .extend(output.env.iter().cloned());
+            .extend(output.env.iter().map(|&(ref k, ref v)| (k.clone(), v.clone().into())));
 
         for dir in output.library_paths.iter() {
             cx.compilation.native_dirs.insert(dir.clone());
         }
     }
     cx.compilation.target = cx.target_triple().to_string();
+
+    if cx.build_config.size_report && cx.build_config.release {
+        sizes::report(config, &cx.compilation.root_output, &artifacts)?;
+    }
     Ok(cx.compilation)
 }
 
@@ -272,13 +365,23 @@ fn compile<'a, 'cfg: 'a>(cx: &mut Context<'a, 'cfg>,
         (Work::noop(), Work::noop(), Freshness::Fresh)
     } else {
         let (mut freshness, dirty, fresh) = fingerprint::prepare_target(cx, unit)?;
-        let work = if unit.profile.doc {
+        let shared = shared_cache::prepare(cx, unit)?;
+        let mut work = if unit.profile.doc {
             rustdoc(cx, unit)?
         } else {
             rustc(cx, unit, Arc::clone(&exec))?
         };
+        let mut fresh = fresh;
+        if let Some(entry) = shared {
+            fresh = entry.clone().fresh().then(fresh);
+            work = entry.compile(work);
+        }
         // Need to link targets on both the dirty and fresh
-        let dirty = work.then(link_targets(cx, unit, false)?).then(dirty);
+        let dirty = fingerprint::prepare_invalidate(cx, unit)
+                                .then(work)
+                                .then(post_link(cx, unit)?)
+                                .then(link_targets(cx, unit, false)?)
+                                .then(dirty);
         let fresh = link_targets(cx, unit, true)?.then(fresh);
 
         if exec.force_rebuild(unit) {
@@ -344,7 +447,21 @@ fn rustc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 
     rustc.args(&cx.incremental_args(unit)?);
     rustc.args(&cx.rustflags_args(unit)?);
+    rustc.args(&cx.reproducible_args(unit));
+    if cx.build_config.reproducible {
+        reproducible_env(&mut rustc);
+    }
     let json_messages = cx.build_config.json_messages;
+    let is_member = cx.ws.members().any(|p| p.package_id() == unit.pkg.package_id());
+    // Warnings of dependencies are theirs to fix, so they're never denied.
+    let deny_warnings = cx.build_config.deny_warnings && is_member;
+    // With `-Z unused-dependencies` rustc lists the `--extern` crates the
+    // members didn't use, instead of warning about each of them, so that the
+    // job queue can tell which dependencies no target needs at all.
+    if cx.config.cli_unstable().unused_dependencies && is_member {
+        rustc.arg("-W").arg("unused-crate-dependencies");
+        rustc.arg("-Z").arg("json-unused-externs=silent");
+    }
     let package_id = unit.pkg.package_id().clone();
     let target = unit.target.clone();
 
@@ -385,45 +502,82 @@ fn rustc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
         }
 
         state.running(&rustc);
-        if json_messages {
-            exec.exec_json(rustc, &package_id, &target,
-                &mut |line| if !line.is_empty() {
-                    Err(internal(&format!("compiler stdout is not empty: `{}`", line)))
-                } else {
-                    Ok(())
-                },
-                &mut |line| {
-                    // stderr from rustc can have a mix of JSON and non-JSON output
-                    if line.starts_with('{') {
-                        // Handle JSON lines
-                        let compiler_message = serde_json::from_str(line).map_err(|_| {
-                            internal(&format!("compiler produced invalid json: `{}`", line))
-                        })?;
-
-                        machine_message::emit(&machine_message::FromCompiler {
-                            package_id: &package_id,
-                            target: &target,
-                            message: compiler_message,
-                        });
-                    } else {
-                        // Forward non-JSON to stderr
-                        writeln!(io::stderr(), "{}", line)?;
+        let mut warnings = 0;
+        exec.exec_json(rustc, &package_id, &target,
+            &mut |line| if !line.is_empty() {
+                Err(internal(&format!("compiler stdout is not empty: `{}`", line)))
+            } else {
+                Ok(())
+            },
+            &mut |line| {
+                // stderr from rustc can have a mix of JSON and non-JSON output
+                if !line.starts_with('{') {
+                    writeln!(io::stderr(), "{}", line)?;
+                    return Ok(())
+                }
+                let mut compiler_message: serde_json::Value =
+                    serde_json::from_str(line).map_err(|_| {
+                        internal(&format!("compiler produced invalid json: `{}`", line))
+                    })?;
+
+                if let Some(names) = compiler_message.get("unused_extern_names") {
+                    let names = names.as_array().map(|names| {
+                        names.iter()
+                             .filter_map(|name| name.as_str())
+                             .map(|name| name.to_string())
+                             .collect()
+                    }).unwrap_or_default();
+                    state.unused_externs(names);
+                    return Ok(())
+                }
+
+                // With `--json=artifacts` each file is announced as soon as
+                // it's written.
+                if compiler_message.get("artifact").is_some() {
+                    if compiler_message["emit"] == "metadata" {
+                        state.metadata_ready();
                     }
-                    Ok(())
+                    return Ok(())
                 }
-            ).chain_err(|| {
-                format!("Could not compile `{}`.", name)
-            })?;
-        } else {
-            exec.exec(rustc, &package_id, &target).map_err(|e| e.into_internal()).chain_err(|| {
-                format!("Could not compile `{}`.", name)
-            })?;
+
+                let diagnostic = Diagnostic::from_json(&compiler_message);
+                if diagnostic.as_ref().map_or(false, |d| d.is_warning()) {
+                    warnings += 1;
+                }
+                if json_messages {
+                    if compiler_message["rendered"].is_null() {
+                        let rendered = diagnostics::render(&compiler_message);
+                        compiler_message["rendered"] = serde_json::Value::String(rendered);
+                    }
+                    machine_message::emit(&machine_message::FromCompiler {
+                        package_id: &package_id,
+                        target: &target,
+                        message: compiler_message,
+                    });
+                } else if let Some(diagnostic) = diagnostic {
+                    state.diagnostic(diagnostic);
+                }
+                Ok(())
+            }
+        ).map_err(|e| {
+            // The diagnostics explain the failure, unless they're only part
+            // of the JSON on stdout.
+            if json_messages { e } else { e.into_internal() }
+        }).chain_err(|| {
+            format!("Could not compile `{}`.", name)
+        })?;
+
+        // The outputs are left in place but the unit isn't marked fresh, so
+        // the next build shows the warnings again.
+        if deny_warnings && warnings > 0 {
+            bail!("Could not compile `{}` because warnings are denied ({} warning{}).",
+                  name, warnings, if warnings == 1 { "" } else { "s" })
         }
 
         if do_rename && real_name != crate_name {
             let dst = &filenames[0].0;
             let src = dst.with_file_name(dst.file_name().unwrap()
-                                            .to_str().unwrap()
+                                            .to_string_lossy()
                                             .replace(&real_name, &crate_name));
             if src.exists() && src.file_name() != dst.file_name() {
                 fs::rename(&src, &dst).chain_err(|| {
@@ -489,6 +643,40 @@ fn rustc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
     }
 }
 
+/// Strips the artifact of `unit` and runs its `post-link` commands on it.
+fn post_link<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>) -> CargoResult<Work> {
+    let mut commands = cx.strip_command(unit).into_iter().collect::<Vec<_>>();
+    commands.extend(cx.post_link_commands(unit).iter().cloned());
+    if commands.is_empty() {
+        return Ok(Work::noop())
+    }
+    let filenames = cx.target_filenames(unit)?;
+    let artifacts = filenames.iter().filter(|&&(_, _, file_type)| {
+        file_type == TargetFileType::Normal
+    }).map(|&(ref path, _, _)| path.clone()).collect::<Vec<_>>();
+    // When rustc writes both a `.js` and a `.wasm` file, the commands are
+    // given the `.wasm` one.
+    let artifact = match artifacts.iter().find(|p| p.extension() == Some(OsStr::new("wasm")))
+                                  .or_else(|| artifacts.first()) {
+        Some(artifact) => artifact.clone(),
+        None => return Ok(Work::noop()),
+    };
+    let cwd = unit.pkg.root().to_path_buf();
+    let name = unit.pkg.name().to_string();
+
+    Ok(Work::new(move |state| {
+        for command in commands.iter() {
+            let mut cmd = util::process(&command[0]);
+            cmd.args(&command[1..]).arg(&artifact).cwd(&cwd);
+            state.running(&cmd);
+            cmd.exec().chain_err(|| {
+                format!("post-link command `{}` failed for `{}`", command.join(" "), name)
+            })?;
+        }
+        Ok(())
+    }))
+}
+
 /// Link the compiled target (often of form `foo-{metadata_hash}`) to the
 /// final target. This must happen during both "Fresh" and "Compile"
 fn link_targets<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
@@ -502,6 +690,8 @@ fn link_targets<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
         .map(|s| s.to_owned())
         .collect();
     let json_messages = cx.build_config.json_messages;
+    let executable = !profile.check && !profile.doc &&
+                     (profile.test || target.is_bin() || target.is_bin_example());
 
     Ok(Work::new(move |_| {
         // If we're a "root crate", e.g. the target of this compilation, then we
@@ -509,20 +699,21 @@ fn link_targets<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
         // above. This means that `cargo build` will produce binaries in
         // `target/debug` which one probably expects.
         let mut destinations = vec![];
-        for &(ref src, ref link_dst, _file_type) in filenames.iter() {
+        let mut executable_path = None;
+        for &(ref src, ref link_dst, file_type) in filenames.iter() {
             // This may have been a `cargo rustc` command which changes the
             // output, so the source may not actually exist.
             if !src.exists() {
                 continue
             }
-            let dst = match link_dst.as_ref() {
-                Some(dst) => dst,
-                None => {
-                    destinations.push(src.display().to_string());
-                    continue;
-                }
-            };
+            let dst = link_dst.as_ref().unwrap_or(src);
+            if executable && file_type == TargetFileType::Normal {
+                executable_path = Some(dst.display().to_string());
+            }
             destinations.push(dst.display().to_string());
+            if link_dst.is_none() {
+                continue
+            }
 
             debug!("linking {} to {}", src.display(), dst.display());
             if is_same_file(src, dst).unwrap_or(false) {
@@ -564,6 +755,7 @@ fn link_targets<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
                 profile: &profile,
                 features: features,
                 filenames: destinations,
+                executable: executable_path,
                 fresh: fresh,
             });
         }
@@ -662,7 +854,9 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
     // Create the documentation directory ahead of time as rustdoc currently has
     // a bug where concurrent invocations will race to create this directory if
     // it doesn't already exist.
-    fs::create_dir_all(&doc_dir)?;
+    if !cx.build_config.dry_run {
+        fs::create_dir_all(&doc_dir)?;
+    }
 
     rustdoc.arg("-o").arg(doc_dir);
 
@@ -702,18 +896,24 @@ fn rustdoc<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 //
 // The heuristic here is fairly simple, but the key idea is that the path is
 // always "relative" to the current directory in order to be found easily. The
-// path is only actually relative if the current directory is an ancestor if it.
-// This means that non-path dependencies (git/registry) will likely be shown as
-// absolute paths instead of relative paths.
+// path is only actually relative if the current directory is an ancestor if it,
+// or if both are within the workspace, as when Cargo is run from a
+// subdirectory, in which case it goes up with `..`. This means that non-path
+// dependencies (git/registry) will likely be shown as absolute paths instead
+// of relative paths.
 fn root_path(cx: &Context, unit: &Unit) -> PathBuf {
     let absolute = unit.pkg.root().join(unit.target.src_path());
     let cwd = cx.config.cwd();
-    if absolute.starts_with(cwd) {
+    // Reproducible builds pass the absolute path so that it's remapped like
+    // every other path, see `Context::reproducible_args`.
+    if cx.build_config.reproducible {
+        absolute
+    } else if absolute.starts_with(cwd) {
         util::without_prefix(&absolute, cwd).map(|s| {
             s.to_path_buf()
         }).unwrap_or(absolute)
     } else {
-        absolute
+        util::paths::relative_within(&absolute, cwd, cx.ws.root()).unwrap_or(absolute)
     }
 }
 
@@ -721,11 +921,13 @@ fn build_base_args<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
                              cmd: &mut ProcessBuilder,
                              unit: &Unit<'a>,
                              crate_types: &[&str]) {
+    let profile = cx.unit_profile(unit);
     let Profile {
-        ref opt_level, lto, codegen_units, ref rustc_args, debuginfo,
+        ref opt_level, ref lto, codegen_units, ref rustc_args, debuginfo,
         debug_assertions, overflow_checks, rpath, test, doc: _doc,
-        run_custom_build, ref panic, rustdoc_args: _, check,
-    } = *unit.profile;
+        run_custom_build, ref panic, rustdoc_args: _, check, crt_static,
+        strip: _, ref split_debuginfo,
+    } = *profile;
     assert!(!run_custom_build);
 
     // Move to cwd so the root_path() passed below is actually correct
@@ -741,8 +943,11 @@ fn build_base_args<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
         ColorChoice::CargoAuto => {}
     }
 
-    if cx.build_config.json_messages {
-        cmd.arg("--error-format").arg("json");
+    // Diagnostics are shown by Cargo, see `diagnostics`.
+    cmd.arg("--error-format").arg("json");
+    let pipelined = cx.emits_rmeta(unit);
+    if pipelined {
+        cmd.arg("--json=artifacts");
     }
 
     if !test {
@@ -753,6 +958,8 @@ fn build_base_args<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 
     if check {
         cmd.arg("--emit=dep-info,metadata");
+    } else if pipelined {
+        cmd.arg("--emit=dep-info,metadata,link");
     } else {
         cmd.arg("--emit=dep-info,link");
     }
@@ -787,18 +994,31 @@ fn build_base_args<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 
     // Disable LTO for host builds as prefer_dynamic and it are mutually
     // exclusive.
-    if unit.target.can_lto() && lto && !unit.target.for_host() {
-        cmd.args(&["-C", "lto"]);
-    } else if let Some(n) = codegen_units {
-        // There are some restrictions with LTO and codegen-units, so we
-        // only add codegen units when LTO is not used.
-        cmd.arg("-C").arg(&format!("codegen-units={}", n));
+    let lto_applies = cx.lto_applies(unit);
+    if lto_applies {
+        match *lto {
+            Lto::Bool(true) => { cmd.args(&["-C", "lto"]); }
+            Lto::Named(ref kind) => { cmd.arg("-C").arg(format!("lto={}", kind)); }
+            Lto::Bool(false) => {}
+        }
+    }
+    if let Some(n) = codegen_units {
+        // There are some restrictions with fat LTO and codegen-units, so we
+        // only add codegen units when it's not used. Thin LTO works across
+        // the codegen units.
+        if !(lto_applies && lto.is_fat()) {
+            cmd.arg("-C").arg(&format!("codegen-units={}", n));
+        }
     }
 
     if let Some(debuginfo) = debuginfo {
         cmd.arg("-C").arg(format!("debuginfo={}", debuginfo));
     }
 
+    if let Some(ref split_debuginfo) = *split_debuginfo {
+        cmd.arg("-C").arg(format!("split-debuginfo={}", split_debuginfo));
+    }
+
     if let Some(ref args) = *rustc_args {
         cmd.args(args);
     }
@@ -864,6 +1084,13 @@ fn build_base_args<'a, 'cfg>(cx: &mut Context<'a, 'cfg>,
 
     if unit.kind == Kind::Target {
         opt(cmd, "--target", "", cx.requested_target().map(|s| s.as_ref()));
+
+        // Build scripts and procedural macros are loaded by the host, so
+        // only what's built for the target is linked statically.
+        if let Some(crt_static) = crt_static {
+            let sign = if crt_static { "+" } else { "-" };
+            cmd.arg("-C").arg(format!("target-feature={}crt-static", sign));
+        }
     }
 
     opt(cmd, "-C", "ar=", cx.ar(unit.kind).map(|s| s.as_ref()));
@@ -890,12 +1117,16 @@ fn build_deps_args<'a, 'cfg>(cmd: &mut ProcessBuilder,
         });
     }
 
-    for unit in cx.dep_targets(unit)?.iter() {
-        if unit.profile.run_custom_build {
-            cmd.env("OUT_DIR", &cx.build_script_out_dir(unit));
+    let mut deps = cx.dep_targets(unit)?;
+    if cx.build_config.reproducible {
+        deps.sort_by_key(|u| (u.pkg.package_id().clone(), u.target.name().to_string()));
+    }
+    for dep in deps.iter() {
+        if dep.profile.run_custom_build {
+            cmd.env("OUT_DIR", &cx.build_script_out_dir(dep));
         }
-        if unit.target.linkable() && !unit.profile.doc {
-            link_to(cmd, cx, unit)?;
+        if dep.target.linkable() && !dep.profile.doc {
+            link_to(cmd, cx, unit, dep)?;
         }
     }
 
@@ -903,11 +1134,20 @@ fn build_deps_args<'a, 'cfg>(cmd: &mut ProcessBuilder,
 
     fn link_to<'a, 'cfg>(cmd: &mut ProcessBuilder,
                          cx: &mut Context<'a, 'cfg>,
+                         parent: &Unit<'a>,
                          unit: &Unit<'a>) -> CargoResult<()> {
+        // The rlib may not have been written yet when only the metadata is
+        // needed, see `Context::only_requires_rmeta`.
+        let only_rmeta = cx.only_requires_rmeta(parent, unit);
         for &(ref dst, _, file_type) in cx.target_filenames(unit)?.iter() {
             if file_type != TargetFileType::Linkable {
                 continue
             }
+            let dst = if only_rmeta {
+                dst.with_extension("rmeta")
+            } else {
+                dst.clone()
+            };
             let mut v = OsString::new();
             v.push(&unit.target.crate_name());
             v.push("=");
@@ -920,6 +1160,15 @@ fn build_deps_args<'a, 'cfg>(cmd: &mut ProcessBuilder,
     }
 }
 
+/// Zeroes the timestamps which tools like `ar` embed in what they produce, for
+/// `--reproducible` builds. A `SOURCE_DATE_EPOCH` that's already set is kept.
+fn reproducible_env(cmd: &mut ProcessBuilder) {
+    if env::var_os("SOURCE_DATE_EPOCH").is_none() {
+        cmd.env("SOURCE_DATE_EPOCH", "0");
+    }
+    cmd.env("ZERO_AR_DATE", "1");
+}
+
 fn envify(s: &str) -> String {
     s.chars()
      .flat_map(|c| c.to_uppercase())
@@ -0,0 +1,265 @@
+//! A cache of compiled dependencies shared by every project, enabled with
+//! `build.shared-cache`.
+//!
+//! A library from a registry or a git repository compiles to the same files
+//! in every project which uses it with the same features, profile, flags and
+//! dependencies. With the shared cache its outputs are stored in
+//! `$CARGO_HOME/build-cache`, in a directory named after a hash of all of
+//! that, and the next build which needs them hard links them into its target
+//! directory instead of compiling the library again. The packages of the
+//! workspace and other path dependencies are always compiled in place.
+//!
+//! What a library compiles to also depends on the output of its build script
+//! and on what its dependencies compiled to, which are only known once those
+//! have run. So the hash of each unit is only completed when its job runs,
+//! fresh or not, from the hashes of its dependencies, see `Entry::hash`.
+
+#![allow(deprecated)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher, SipHasher};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use core::PackageId;
+use util::Config;
+use util::errors::{CargoResult, CargoResultExt};
+
+use super::context::{Context, Metadata, TargetFileType, Unit};
+use super::custom_build::BuildState;
+use super::job::Work;
+use super::Kind;
+
+pub struct SharedCache {
+    dir: PathBuf,
+    /// The complete hashes of the units of this build which are cached, by
+    /// their metadata.
+    hashes: Mutex<HashMap<Metadata, u64>>,
+}
+
+impl SharedCache {
+    /// The shared cache, if `build.shared-cache` enables it.
+    pub fn new(config: &Config) -> CargoResult<Option<Arc<SharedCache>>> {
+        match config.get_bool("build.shared-cache")? {
+            Some(ref v) if v.val => {}
+            _ => return Ok(None),
+        }
+        let dir = config.home().join("build-cache").into_path_unlocked();
+        Ok(Some(Arc::new(SharedCache {
+            dir: dir,
+            hashes: Mutex::new(HashMap::new()),
+        })))
+    }
+}
+
+/// What's needed to look up a unit in the shared cache, and to store it.
+#[derive(Clone)]
+pub struct Entry {
+    cache: Arc<SharedCache>,
+    metadata: Metadata,
+    /// The hash of everything known before the build starts.
+    base: u64,
+    /// The units whose outputs this one is compiled against.
+    deps: Vec<Metadata>,
+    build_state: Arc<BuildState>,
+    package_id: PackageId,
+    kind: Kind,
+    /// The files the unit compiles to, and whether each has to be there.
+    files: Vec<(PathBuf, bool)>,
+}
+
+/// The cache entry of `unit`, none if the cache is disabled or the unit is
+/// always compiled in place.
+pub fn prepare<'a, 'cfg>(cx: &mut Context<'a, 'cfg>, unit: &Unit<'a>)
+                         -> CargoResult<Option<Entry>> {
+    let cache = match cx.shared_cache {
+        Some(ref cache) => Arc::clone(cache),
+        None => return Ok(None),
+    };
+    if !cacheable(unit) {
+        return Ok(None)
+    }
+    let metadata = match cx.target_metadata(unit) {
+        Some(metadata) => metadata,
+        None => return Ok(None),
+    };
+
+    let mut deps = Vec::new();
+    for dep in cx.dep_targets(unit)?.iter() {
+        // The output of the build script is part of `Entry::hash`.
+        if dep.profile.run_custom_build {
+            continue
+        }
+        match cx.target_metadata(dep) {
+            Some(ref metadata) if cacheable(dep) => deps.push(metadata.clone()),
+            _ => return Ok(None),
+        }
+    }
+
+    let filenames = cx.target_filenames(unit)?;
+    let files = filenames.iter().map(|&(ref path, _, file_type)| {
+        (path.clone(), file_type != TargetFileType::DebugInfo)
+    }).collect::<Vec<_>>();
+
+    let mut hasher = SipHasher::new_with_keys(0, 0);
+    metadata.hash(&mut hasher);
+    match unit.kind {
+        Kind::Host => cx.host_triple().hash(&mut hasher),
+        Kind::Target => cx.target_triple().hash(&mut hasher),
+    }
+    cx.rustflags_args(unit)?.hash(&mut hasher);
+    cx.reproducible_args(unit).hash(&mut hasher);
+    cx.incremental_args(unit)?.hash(&mut hasher);
+    for &(ref path, _) in files.iter() {
+        path.file_name().hash(&mut hasher);
+    }
+
+    Ok(Some(Entry {
+        cache: cache,
+        metadata: metadata,
+        base: hasher.finish(),
+        deps: deps,
+        build_state: Arc::clone(&cx.build_state),
+        package_id: unit.pkg.package_id().clone(),
+        kind: unit.kind,
+        files: files,
+    }))
+}
+
+/// Only the libraries of packages which can't be edited in place are cached.
+fn cacheable(unit: &Unit) -> bool {
+    !unit.pkg.package_id().source_id().is_path() &&
+        unit.target.is_lib() &&
+        !unit.profile.doc &&
+        !unit.profile.test &&
+        !unit.profile.run_custom_build
+}
+
+impl Entry {
+    /// Wraps the `work` compiling the unit so that its outputs are taken from
+    /// the cache if they're there, and stored in it otherwise.
+    pub fn compile(self, work: Work) -> Work {
+        Work::new(move |state| {
+            let hash = match self.hash() {
+                Some(hash) => hash,
+                None => return work.call(state),
+            };
+            if self.restore(hash)? {
+                state.restored(&self.package_id);
+                return Ok(())
+            }
+
+            // The outputs may be hard links into the cache, which rustc
+            // mustn't write through.
+            for &(ref path, _) in self.files.iter() {
+                if path.exists() {
+                    fs::remove_file(path).chain_err(|| {
+                        format!("failed to remove `{}`", path.display())
+                    })?;
+                }
+            }
+            work.call(state)?;
+
+            // The build is fine without the cache, so failing to fill it
+            // isn't an error.
+            if let Err(e) = self.store(hash) {
+                debug!("failed to store {} in the shared build cache: {}",
+                       self.package_id, e);
+            }
+            Ok(())
+        })
+    }
+
+    /// Work which only completes the hash of the unit, for when it's fresh,
+    /// so that the units depending on it can still be looked up.
+    pub fn fresh(self) -> Work {
+        Work::new(move |_| {
+            self.hash();
+            Ok(())
+        })
+    }
+
+    /// Completes the hash of the unit with the output of its build script
+    /// and the hashes of its dependencies, none if one of them couldn't be
+    /// hashed.
+    fn hash(&self) -> Option<u64> {
+        let mut hashes = self.cache.hashes.lock().unwrap();
+        let mut hasher = SipHasher::new_with_keys(0, 0);
+        self.base.hash(&mut hasher);
+        for dep in self.deps.iter() {
+            match hashes.get(dep) {
+                Some(hash) => hash.hash(&mut hasher),
+                None => return None,
+            }
+        }
+        let outputs = self.build_state.outputs.lock().unwrap();
+        if let Some(output) = outputs.get(&(self.package_id.clone(), self.kind)) {
+            output.library_links.hash(&mut hasher);
+            output.cfgs.hash(&mut hasher);
+            output.env.hash(&mut hasher);
+        }
+        let hash = hasher.finish();
+        hashes.insert(self.metadata.clone(), hash);
+        Some(hash)
+    }
+
+    fn dir(&self, hash: u64) -> PathBuf {
+        self.cache.dir.join(format!("{:016x}", hash))
+    }
+
+    /// Links the outputs of the unit from the cache, if they're all there.
+    fn restore(&self, hash: u64) -> CargoResult<bool> {
+        let dir = self.dir(hash);
+        let complete = self.files.iter().all(|&(ref path, required)| {
+            !required || dir.join(path.file_name().unwrap()).exists()
+        });
+        if !dir.exists() || !complete {
+            return Ok(false)
+        }
+        for &(ref path, _) in self.files.iter() {
+            let src = dir.join(path.file_name().unwrap());
+            if !src.exists() {
+                continue
+            }
+            if path.exists() {
+                fs::remove_file(path).chain_err(|| {
+                    format!("failed to remove `{}`", path.display())
+                })?;
+            }
+            link_or_copy(&src, path)?;
+        }
+        Ok(true)
+    }
+
+    /// Stores the outputs of the unit in the cache, unless another build did
+    /// already. They're put into place all at once, so that no other build
+    /// finds them halfway there.
+    fn store(&self, hash: u64) -> CargoResult<()> {
+        let dir = self.dir(hash);
+        if dir.exists() {
+            return Ok(())
+        }
+        let tmp = self.cache.dir.join(format!("{:016x}.{}", hash, process::id()));
+        fs::create_dir_all(&tmp)?;
+        for &(ref path, _) in self.files.iter() {
+            if path.exists() {
+                link_or_copy(path, &tmp.join(path.file_name().unwrap()))?;
+            }
+        }
+        if fs::rename(&tmp, &dir).is_err() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        Ok(())
+    }
+}
+
+fn link_or_copy(src: &Path, dst: &Path) -> CargoResult<()> {
+    if fs::hard_link(src, dst).is_err() {
+        fs::copy(src, dst).chain_err(|| {
+            format!("failed to copy `{}` to `{}`", src.display(), dst.display())
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Reporting for `cargo build --release --size-report`.
+//!
+//! Once a release build finishes, the size of every binary and library it
+//! produced for the requested packages is printed along with how much it
+//! changed since the last report for the same profile and target. The sizes
+//! are remembered in `.cargo-sizes.json`, next to the artifacts.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+
+use ops::human_readable_bytes;
+use util::{CargoResult, Config};
+use util::paths;
+
+/// Prints the sizes of `artifacts` and records them in `dir`.
+pub fn report(config: &Config, dir: &Path, artifacts: &[PathBuf]) -> CargoResult<()> {
+    let record = dir.join(".cargo-sizes.json");
+    // A missing or unreadable record only means there's nothing to compare
+    // against.
+    let previous: BTreeMap<String, u64> = paths::read(&record).ok().and_then(|s| {
+        serde_json::from_str(&s).ok()
+    }).unwrap_or_default();
+
+    let mut sizes = BTreeMap::new();
+    for path in artifacts {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let size = fs::metadata(path)?.len();
+        sizes.insert(name, size);
+    }
+    if sizes.is_empty() {
+        return Ok(())
+    }
+
+    {
+        let mut shell = config.shell();
+        let out = shell.err();
+        writeln!(out, "\nartifact sizes:")?;
+        for (name, &size) in sizes.iter() {
+            writeln!(out, "  {:>10}  {} ({})", human_readable_bytes(size), name,
+                     delta(previous.get(name).cloned(), size))?;
+        }
+    }
+
+    paths::write(&record, &serde_json::to_vec(&sizes).unwrap())?;
+    Ok(())
+}
+
+/// How `size` compares to the previous one, e.g. `+1.5 KiB`.
+fn delta(previous: Option<u64>, size: u64) -> String {
+    match previous {
+        None => "new".to_string(),
+        Some(previous) if previous == size => "unchanged".to_string(),
+        Some(previous) if previous < size => {
+            format!("+{}", human_readable_bytes(size - previous))
+        }
+        Some(previous) => format!("-{}", human_readable_bytes(previous - size)),
+    }
+}
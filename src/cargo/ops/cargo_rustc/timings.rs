@@ -0,0 +1,190 @@
+//! Reporting for `cargo build --timings`.
+//!
+//! The job queue records when each unit of work started and finished, and
+//! this module turns that into a summary printed to the console along with a
+//! JSON and an HTML report in `target/cargo-timings`. Both reports include the
+//! critical path, the chain of dependent units which bounded the length of the
+//! whole build no matter how many jobs were running in parallel.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json;
+
+use util::{CargoResult, Config};
+use util::paths;
+
+/// How long a single unit of work took to build.
+#[derive(Serialize)]
+pub struct UnitTime {
+    /// Description of the unit, e.g. `foo v0.1.0 (lib)`
+    pub name: String,
+    /// Seconds since the start of the build that this unit started
+    pub start: f64,
+    /// Seconds this unit took to build
+    pub duration: f64,
+    /// Whether the unit was rebuilt, rather than fresh
+    pub rebuilt: bool,
+    /// Indices of the units this one had to wait for
+    pub deps: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    wall_time: f64,
+    jobs: u32,
+    concurrency: f64,
+    critical_path: &'a [usize],
+    units: &'a [UnitTime],
+}
+
+/// Number of units listed in the console summary.
+const SLOWEST: usize = 10;
+
+pub fn seconds(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1_000_000_000.0
+}
+
+/// Prints a summary of `units` and writes the full reports to `dir`.
+pub fn report(config: &Config,
+              dir: PathBuf,
+              units: &[UnitTime],
+              wall_time: f64,
+              jobs: u32) -> CargoResult<()> {
+    let busy = units.iter().map(|u| u.duration).sum::<f64>();
+    let concurrency = if wall_time > 0.0 { busy / wall_time } else { 0.0 };
+    let path = critical_path(units);
+    let path_time = path.iter().map(|&i| units[i].duration).sum::<f64>();
+
+    {
+        let mut shell = config.shell();
+        let out = shell.err();
+        writeln!(out, "\nbuild took {:.2}s, with an average of {:.1} of {} \
+                       jobs running", wall_time, concurrency, jobs)?;
+        let mut slowest = (0..units.len()).filter(|&i| units[i].rebuilt)
+                                          .collect::<Vec<_>>();
+        slowest.sort_by(|&a, &b| {
+            units[b].duration.partial_cmp(&units[a].duration).unwrap()
+        });
+        for &i in slowest.iter().take(SLOWEST) {
+            let marker = if path.contains(&i) { "*" } else { " " };
+            writeln!(out, "  {:>8.2}s {} {}", units[i].duration, marker,
+                     units[i].name)?;
+        }
+        writeln!(out, "critical path (marked with `*`) took {:.2}s:",
+                 path_time)?;
+        for &i in path.iter() {
+            writeln!(out, "    {}", units[i].name)?;
+        }
+    }
+
+    let report = Report {
+        wall_time: wall_time,
+        jobs: jobs,
+        concurrency: concurrency,
+        critical_path: &path,
+        units: units,
+    };
+    fs::create_dir_all(&dir)?;
+    let json = dir.join("cargo-timing.json");
+    paths::write(&json, &serde_json::to_vec(&report).unwrap())?;
+    let html = dir.join("cargo-timing.html");
+    paths::write(&html, render_html(&report).as_bytes())?;
+    config.shell().status("Timings", format!("report written to `{}`",
+                                             html.display()))?;
+    Ok(())
+}
+
+/// Finds the chain of dependent units with the longest total duration.
+///
+/// Dependencies always finish before their dependents start, so visiting units
+/// in the order they finished means every dependency has been visited first.
+fn critical_path(units: &[UnitTime]) -> Vec<usize> {
+    let mut order = (0..units.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let a = units[a].start + units[a].duration;
+        let b = units[b].start + units[b].duration;
+        a.partial_cmp(&b).unwrap()
+    });
+
+    let mut longest = vec![0.0; units.len()];
+    let mut prev = vec![None; units.len()];
+    for &i in order.iter() {
+        let mut best = 0.0;
+        for &dep in units[i].deps.iter() {
+            if longest[dep] > best || prev[i].is_none() {
+                best = longest[dep];
+                prev[i] = Some(dep);
+            }
+        }
+        longest[i] = best + units[i].duration;
+    }
+
+    let mut end = match (0..units.len()).max_by(|&a, &b| {
+        longest[a].partial_cmp(&longest[b]).unwrap()
+    }) {
+        Some(end) => end,
+        None => return Vec::new(),
+    };
+    let mut path = vec![end];
+    while let Some(p) = prev[end] {
+        path.push(p);
+        end = p;
+    }
+    path.reverse();
+    path
+}
+
+fn render_html(report: &Report) -> String {
+    let mut rows = String::new();
+    let scale = if report.wall_time > 0.0 { 100.0 / report.wall_time } else { 0.0 };
+    for (i, unit) in report.units.iter().enumerate() {
+        let class = if report.critical_path.contains(&i) {
+            "critical"
+        } else if unit.rebuilt {
+            "rebuilt"
+        } else {
+            "fresh"
+        };
+        rows.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td>{:.2}s</td><td>{:.2}s</td>\
+             <td class=\"timeline\"><div style=\"margin-left: {:.2}%; \
+             width: {:.2}%\"></div></td></tr>\n",
+            class, escape(&unit.name), unit.start, unit.duration,
+            unit.start * scale, unit.duration * scale));
+    }
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Cargo build timings</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; width: 100%; }}
+td, th {{ padding: 2px 8px; text-align: left; white-space: nowrap; }}
+td.timeline {{ width: 60%; }}
+td.timeline div {{ height: 12px; min-width: 1px; background: #6a9fd8; }}
+tr.critical td.timeline div {{ background: #d86a6a; }}
+tr.fresh {{ color: #999; }}
+</style>
+</head>
+<body>
+<h1>Cargo build timings</h1>
+<p>Total time: {:.2}s, average concurrency: {:.1} of {} jobs.
+Units on the critical path are shown in red.</p>
+<table>
+<tr><th>Unit</th><th>Start</th><th>Duration</th><th>Timeline</th></tr>
+{}</table>
+</body>
+</html>
+"#, report.wall_time, report.concurrency, report.jobs, rows)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+     .replace('"', "&quot;")
+}
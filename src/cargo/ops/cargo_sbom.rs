@@ -0,0 +1,315 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{self, Value};
+
+use core::{Package, PackageId, Resolve, Workspace};
+use ops::{self, Packages};
+use util::{short_hash, CargoResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum SbomFormat {
+    Cyclonedx,
+    Spdx,
+}
+
+pub struct SbomOptions<'a> {
+    pub spec: Packages<'a>,
+    pub format: SbomFormat,
+}
+
+/// Executes `cargo sbom`.
+///
+/// Prints a software bill of materials for what's shipped with `spec`, see
+/// `ops::resolve_ws_shipped`, as a CycloneDX 1.4 or SPDX 2.3 JSON document.
+/// Each package is identified by its package URL, which for git dependencies
+/// includes the locked revision, and registry packages carry the checksum
+/// recorded in the lock file.
+pub fn sbom(ws: &Workspace, opts: &SbomOptions) -> CargoResult<()> {
+    let specs = opts.spec.into_package_id_specs(ws)?;
+    let (packages, resolve) = ops::resolve_ws_shipped(ws, &specs)?;
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+    let packages = ids.iter()
+                      .map(|id| packages.get(id))
+                      .collect::<CargoResult<Vec<_>>>()?;
+    let members = ws.members().map(|pkg| pkg.package_id().clone()).collect::<Vec<_>>();
+
+    let bom = Bom {
+        resolve: &resolve,
+        packages: packages,
+        members: members,
+        timestamp: timestamp(),
+    };
+    let document = match opts.format {
+        SbomFormat::Cyclonedx => bom.cyclonedx(),
+        SbomFormat::Spdx => bom.spdx(),
+    };
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    Ok(())
+}
+
+struct Bom<'a> {
+    resolve: &'a Resolve,
+    packages: Vec<&'a Package>,
+    members: Vec<PackageId>,
+    timestamp: String,
+}
+
+impl<'a> Bom<'a> {
+    fn cyclonedx(&self) -> Value {
+        let component = |pkg: &Package| {
+            let id = pkg.package_id();
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": bom_ref(id),
+                "name": id.name(),
+                "version": id.version().to_string(),
+            });
+            if let Some(purl) = purl(id) {
+                component["purl"] = json!(purl);
+            }
+            if let Some(checksum) = self.checksum(id) {
+                component["hashes"] = json!([{"alg": "SHA-256", "content": checksum}]);
+            }
+            if let Some(ref license) = pkg.manifest().metadata().license {
+                component["licenses"] = json!([{"expression": license}]);
+            }
+            if let Some(vcs) = vcs_location(id) {
+                component["externalReferences"] = json!([{"type": "vcs", "url": vcs}]);
+            }
+            component
+        };
+
+        let mut metadata = json!({
+            "timestamp": self.timestamp,
+            "tools": [{"name": "cargo", "version": cargo_version()}],
+        });
+        // A single member is what the whole document describes, otherwise the
+        // members are just components like everything else.
+        let root = if self.members.len() == 1 {
+            self.packages.iter().find(|p| p.package_id() == &self.members[0])
+        } else {
+            None
+        };
+        if let Some(root) = root {
+            let mut root = component(*root);
+            root["type"] = json!("application");
+            metadata["component"] = root;
+        }
+        let components = self.packages.iter()
+            .filter(|p| root.map(|r| r.package_id() != p.package_id()).unwrap_or(true))
+            .map(|p| component(*p))
+            .collect::<Vec<_>>();
+        let dependencies = self.packages.iter().map(|pkg| {
+            json!({
+                "ref": bom_ref(pkg.package_id()),
+                "dependsOn": self.deps(pkg.package_id()).iter()
+                                 .map(|id| bom_ref(id))
+                                 .collect::<Vec<_>>(),
+            })
+        }).collect::<Vec<_>>();
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.4",
+            "version": 1,
+            "metadata": metadata,
+            "components": components,
+            "dependencies": dependencies,
+        })
+    }
+
+    fn spdx(&self) -> Value {
+        let spdx_id = |id: &PackageId| {
+            let i = self.packages.iter().position(|p| p.package_id() == id).unwrap();
+            format!("SPDXRef-Package-{}", i)
+        };
+        let packages = self.packages.iter().map(|pkg| {
+            let id = pkg.package_id();
+            let metadata = pkg.manifest().metadata();
+            let mut package = json!({
+                "SPDXID": spdx_id(id),
+                "name": id.name(),
+                "versionInfo": id.version().to_string(),
+                "downloadLocation": vcs_location(id)
+                    .unwrap_or_else(|| "NOASSERTION".to_string()),
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": metadata.license.clone()
+                    .unwrap_or_else(|| "NOASSERTION".to_string()),
+                "copyrightText": "NOASSERTION",
+            });
+            if let Some(checksum) = self.checksum(id) {
+                package["checksums"] = json!([{
+                    "algorithm": "SHA256",
+                    "checksumValue": checksum,
+                }]);
+            }
+            if let Some(purl) = purl(id) {
+                package["externalRefs"] = json!([{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                }]);
+            }
+            package
+        }).collect::<Vec<_>>();
+
+        let mut relationships = self.members.iter().map(|id| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": spdx_id(id),
+            })
+        }).collect::<Vec<_>>();
+        for pkg in self.packages.iter() {
+            for dep in self.deps(pkg.package_id()) {
+                relationships.push(json!({
+                    "spdxElementId": spdx_id(pkg.package_id()),
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": spdx_id(dep),
+                }));
+            }
+        }
+
+        let name = self.members.iter()
+            .map(|id| format!("{}-{}", id.name(), id.version()))
+            .collect::<Vec<_>>()
+            .join("+");
+        let ids = self.packages.iter().map(|p| p.package_id()).collect::<Vec<_>>();
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": name,
+            "documentNamespace": format!("https://spdx.org/spdxdocs/{}-{}",
+                                         name, short_hash(&ids)),
+            "creationInfo": {
+                "created": self.timestamp,
+                "creators": [format!("Tool: cargo-{}", cargo_version())],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+
+    fn checksum(&self, id: &PackageId) -> Option<&str> {
+        self.resolve.checksums().get(id)
+            .and_then(|c| c.as_ref())
+            .map(|c| &c[..])
+    }
+
+    /// The dependencies of `id` which are part of the document, sorted.
+    fn deps(&self, id: &PackageId) -> Vec<&'a PackageId> {
+        let mut deps = self.resolve.deps(id)
+            .filter(|dep| self.packages.iter().any(|p| p.package_id() == *dep))
+            .collect::<Vec<_>>();
+        deps.sort();
+        deps
+    }
+}
+
+/// A reference which is unique within the document: the package along with
+/// its source, including the locked revision for git.
+fn bom_ref(id: &PackageId) -> String {
+    format!("{}@{} ({})", id.name(), id.version(), id.source_id().to_url())
+}
+
+/// The package URL (https://github.com/package-url/purl-spec) of `id`.
+/// Packages from a path have none, they aren't available anywhere else.
+fn purl(id: &PackageId) -> Option<String> {
+    let source_id = id.source_id();
+    let base = format!("pkg:cargo/{}@{}", id.name(), id.version());
+    if source_id.is_default_registry() {
+        Some(base)
+    } else if source_id.is_registry() {
+        Some(format!("{}?repository_url={}", base, encode(source_id.url().as_str())))
+    } else {
+        vcs_location(id).map(|vcs| format!("{}?vcs_url={}", base, encode(&vcs)))
+    }
+}
+
+/// `git+<url>@<revision>` for git packages, in the format of SPDX's
+/// `downloadLocation` and the `vcs_url` of package URLs.
+fn vcs_location(id: &PackageId) -> Option<String> {
+    let source_id = id.source_id();
+    if !source_id.is_git() {
+        return None
+    }
+    let mut location = format!("git+{}", source_id.url());
+    if let Some(rev) = source_id.precise() {
+        location.push('@');
+        location.push_str(rev);
+    }
+    Some(location)
+}
+
+/// Percent-encodes a qualifier value of a package URL.
+fn encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        match b {
+            b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' |
+            b'-' | b'.' | b'_' | b'~' | b':' | b'/' => encoded.push(b as char),
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+fn cargo_version() -> String {
+    let version = ::version();
+    format!("{}.{}.{}", version.major, version.minor, version.patch)
+}
+
+/// The current time in RFC 3339 format, or the time in `SOURCE_DATE_EPOCH` so
+/// that documents can be reproduced.
+fn timestamp() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH").ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Converts days since the epoch into a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::{encode, timestamp};
+
+    #[test]
+    fn source_date_epoch() {
+        env::set_var("SOURCE_DATE_EPOCH", "1510000000");
+        assert_eq!(timestamp(), "2017-11-06T20:26:40Z");
+        env::set_var("SOURCE_DATE_EPOCH", "951782400");
+        assert_eq!(timestamp(), "2000-02-29T00:00:00Z");
+        env::remove_var("SOURCE_DATE_EPOCH");
+    }
+
+    #[test]
+    fn purl_qualifiers() {
+        assert_eq!(encode("git+https://github.com/a/b@1234"),
+                   "git%2Bhttps://github.com/a/b%401234");
+    }
+}
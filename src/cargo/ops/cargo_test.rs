@@ -1,15 +1,22 @@
+use std::collections::HashMap;
 use std::ffi::{OsString, OsStr};
+use std::io::{self, Write};
+use std::process::{Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
 
 use ops::{self, Compilation};
-use util::{self, CargoTestError, Test, ProcessError};
-use util::errors::{CargoResult, CargoErrorKind, CargoError};
-use core::Workspace;
+use util::{self, job, process_error, CargoTestError, Config, ProcessBuilder, Test, ProcessError};
+use util::errors::{CargoResult, CargoResultExt, CargoErrorKind, CargoError};
+use core::{TargetKind, Workspace};
 
 pub struct TestOptions<'a> {
     pub compile_opts: ops::CompileOptions<'a>,
     pub no_run: bool,
     pub no_fail_fast: bool,
     pub only_doc: bool,
+    /// How many test binaries are run at the same time.
+    pub test_jobs: u32,
 }
 
 pub fn run_tests(ws: &Workspace,
@@ -18,13 +25,21 @@ pub fn run_tests(ws: &Workspace,
     let compilation = compile_tests(ws, options)?;
 
     if options.no_run {
+        // Tests which aren't run here are typically run under a debugger or
+        // on another machine, which takes knowing where they are. With JSON
+        // messages that's the `executable` of their artifacts instead.
+        if options.compile_opts.message_format == ops::MessageFormat::Human {
+            for &(_, _, _, ref exe) in &compilation.tests {
+                println!("{}", exe.display());
+            }
+        }
         return Ok(None)
     }
     let (test, mut errors) = if options.only_doc {
         assert!(options.compile_opts.filter.is_specific());
         run_doc_tests(options, test_args, &compilation)?
     } else {
-        run_unit_tests(options, test_args, &compilation)?
+        run_unit_tests(options, test_args, &compilation, true)?
     };
 
     // If we have an error and want to fail fast, return
@@ -61,7 +76,7 @@ pub fn run_benches(ws: &Workspace,
     if options.no_run {
         return Ok(None)
     }
-    let (test, errors) = run_unit_tests(options, &args, &compilation)?;
+    let (test, errors) = run_unit_tests(options, &args, &compilation, false)?;
     match errors.len() {
         0 => Ok(None),
         _ => Ok(Some(CargoTestError::new(test, errors))),
@@ -78,16 +93,28 @@ fn compile_tests<'a>(ws: &Workspace<'a>,
     Ok(compilation)
 }
 
-/// Run the unit and integration tests of a project.
+/// A test binary to run.
+struct TestBinary {
+    kind: TargetKind,
+    test: String,
+    /// The path of the binary, relative to the current directory if it's
+    /// below it.
+    display: String,
+    cmd: ProcessBuilder,
+}
+
+/// Run the unit and integration tests of a project. If `summarize` is set and
+/// there's more than one test binary, a line adding up their results comes
+/// last.
 fn run_unit_tests(options: &TestOptions,
                   test_args: &[String],
-                  compilation: &Compilation)
+                  compilation: &Compilation,
+                  summarize: bool)
                   -> CargoResult<(Test, Vec<ProcessError>)> {
     let config = options.compile_opts.config;
     let cwd = options.compile_opts.config.cwd();
 
-    let mut errors = Vec::new();
-
+    let mut tests = Vec::new();
     for &(ref pkg, ref kind, ref test, ref exe) in &compilation.tests {
         let to_display = match util::without_prefix(exe, cwd) {
             Some(path) => path,
@@ -95,19 +122,70 @@ fn run_unit_tests(options: &TestOptions,
         };
         let mut cmd = compilation.target_process(exe, pkg)?;
         cmd.args(test_args);
+        tests.push(TestBinary {
+            kind: kind.clone(),
+            test: test.clone(),
+            display: to_display.display().to_string(),
+            cmd: cmd,
+        });
+    }
+
+    let (mut errors, ran, totals) = if options.test_jobs > 1 && tests.len() > 1 {
+        let (errors, totals) = run_concurrently(config, &tests,
+                                                options.test_jobs as usize,
+                                                options.no_fail_fast)?;
+        (errors, totals.binaries, Some(totals))
+    } else {
+        let (errors, ran) = run_sequentially(config, &tests, options.no_fail_fast)?;
+        (errors, ran, None)
+    };
+    if summarize && ran > 1 {
+        let summary = match totals {
+            Some(totals) => {
+                format!("{} test binaries: {} passed; {} failed; {} ignored",
+                        ran, totals.passed, totals.failed, totals.ignored)
+            }
+            // The output of binaries run one at a time isn't seen by Cargo,
+            // only whether they succeeded.
+            None => {
+                format!("{} test binaries: {} succeeded; {} failed",
+                        ran, ran - errors.len(), errors.len())
+            }
+        };
+        config.shell().status("Summary", summary)?;
+    }
+
+    if errors.len() == 1 {
+        let (kind, test, e) = errors.pop().unwrap();
+        Ok((Test::UnitTest(kind, test), vec![e]))
+    } else {
+        Ok((Test::Multiple, errors.into_iter().map((|(_, _, e)| e)).collect()))
+    }
+}
+
+/// Runs `tests` one after the other, with their output going straight to the
+/// terminal. Also returns how many of them were run.
+fn run_sequentially(config: &Config,
+                    tests: &[TestBinary],
+                    no_fail_fast: bool)
+                    -> CargoResult<(Vec<(TargetKind, String, ProcessError)>, usize)> {
+    let mut errors = Vec::new();
+    let mut ran = 0;
+    for test in tests {
         config.shell().concise(|shell| {
-            shell.status("Running", to_display.display().to_string())
+            shell.status("Running", &test.display)
         })?;
         config.shell().verbose(|shell| {
-            shell.status("Running", cmd.to_string())
+            shell.status("Running", test.cmd.to_string())
         })?;
 
-        let result = cmd.exec();
+        let result = test.cmd.exec();
+        ran += 1;
 
         match result {
             Err(CargoError(CargoErrorKind::ProcessErrorKind(e), .. )) => {
-                errors.push((kind.clone(), test.clone(), e));
-                if !options.no_fail_fast {
+                errors.push((test.kind.clone(), test.test.clone(), e));
+                if !no_fail_fast {
                     break;
                 }
             }
@@ -118,12 +196,115 @@ fn run_unit_tests(options: &TestOptions,
             Ok(()) => {}
         }
     }
+    Ok((errors, ran))
+}
+
+/// Runs up to `jobs` of `tests` at the same time.
+///
+/// Their output is captured so it doesn't get interleaved. Once a binary has
+/// finished, and all of those before it, its output is printed in one piece,
+/// so everything shows up in the same order as when running them one after
+/// the other.
+fn run_concurrently(config: &Config,
+                    tests: &[TestBinary],
+                    jobs: usize,
+                    no_fail_fast: bool)
+                    -> CargoResult<(Vec<(TargetKind, String, ProcessError)>, TestCounts)> {
+    let (tx, rx) = mpsc::channel();
+    let mut started = 0;
+    let mut running = 0;
+    let mut finished = HashMap::new();
+    let mut printed = 0;
+    let mut errors = Vec::new();
+    let mut totals = TestCounts::default();
+    loop {
+        // Nothing new is started after a failure, unless asked to go on.
+        while running < jobs && started < tests.len() &&
+              (errors.is_empty() || no_fail_fast) {
+            let tx = tx.clone();
+            let mut cmd = tests[started].cmd.build_command();
+            cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+            let index = started;
+            thread::spawn(move || {
+                let output = cmd.spawn().and_then(|child| {
+                    let _tracked = job::track(&child);
+                    child.wait_with_output()
+                });
+                let _ = tx.send((index, output));
+            });
+            started += 1;
+            running += 1;
+        }
+
+        while let Some(output) = finished.remove(&printed) {
+            let test = &tests[printed];
+            printed += 1;
+            config.shell().concise(|shell| {
+                shell.status("Running", &test.display)
+            })?;
+            config.shell().verbose(|shell| {
+                shell.status("Running", test.cmd.to_string())
+            })?;
+            let output: Output = output.chain_err(|| {
+                CargoErrorKind::ProcessErrorKind(process_error(
+                    &format!("could not execute process {}", test.cmd), None, None))
+            })?;
+            io::stdout().write_all(&output.stdout)?;
+            config.shell().err().write_all(&output.stderr)?;
+            totals.add(&output.stdout);
+            totals.binaries += 1;
+
+            if !output.status.success() {
+                let e = process_error(
+                    &format!("process didn't exit successfully: {}", test.cmd),
+                    Some(&output.status), None);
+                errors.push((test.kind.clone(), test.test.clone(), e));
+            }
+        }
+
+        if running == 0 {
+            break
+        }
+        let (index, output) = rx.recv().unwrap();
+        finished.insert(index, output);
+        running -= 1;
+    }
 
-    if errors.len() == 1 {
-        let (kind, test, e) = errors.pop().unwrap();
-        Ok((Test::UnitTest(kind, test), vec![e]))
-    } else {
-        Ok((Test::Multiple, errors.into_iter().map((|(_, _, e)| e)).collect()))
+    Ok((errors, totals))
+}
+
+/// The results of test binaries added up.
+#[derive(Default)]
+struct TestCounts {
+    binaries: usize,
+    passed: u64,
+    failed: u64,
+    ignored: u64,
+}
+
+impl TestCounts {
+    /// Adds the counts in the `test result: ok. 3 passed; 0 failed; ...` line
+    /// of a test binary's `stdout`.
+    fn add(&mut self, stdout: &[u8]) {
+        let stdout = String::from_utf8_lossy(stdout);
+        let line = match stdout.lines().rev().find(|l| l.starts_with("test result:")) {
+            Some(line) => line,
+            None => return,
+        };
+        let counts = line.splitn(2, ". ").nth(1).unwrap_or("");
+        for count in counts.split(';') {
+            let mut words = count.split_whitespace();
+            let n = match words.next().and_then(|n| n.parse::<u64>().ok()) {
+                Some(n) => n,
+                None => continue,
+            };
+            match words.next() {
+                Some("passed") => self.passed += n,
+                Some("failed") => self.failed += n,
+                Some("ignored") => self.ignored += n,
+                _ => {}
+            }
+        }
     }
 }
 
@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use core::{PackageId, Workspace};
+use core::dependency::Kind;
+use ops::{self, Packages};
+use ops::cargo_graph::declarations;
+use util::CargoResult;
+
+pub struct TreeOptions<'a> {
+    pub spec: Packages<'a>,
+    pub features: &'a [String],
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub duplicates: bool,
+    pub invert: Option<&'a str>,
+}
+
+/// The edges of the dependency graph, sorted so the output is stable.
+type Edges<'a> = BTreeMap<&'a PackageId, BTreeSet<&'a PackageId>>;
+
+/// What is printed after a package, keyed by the package and the one it's
+/// printed under.
+type Annotations<'a> = BTreeMap<(&'a PackageId, &'a PackageId), String>;
+
+/// Executes `cargo tree`.
+///
+/// Prints the dependency graph of each package in `spec`. With `duplicates`,
+/// only packages with several copies in the graph are printed instead, each
+/// copy with the chain of packages depending on it up to the roots. With
+/// `invert`, that package is printed with everything depending on it, along
+/// with the kind of each of those dependencies.
+pub fn tree(ws: &Workspace, opts: &TreeOptions) -> CargoResult<()> {
+    let specs = opts.spec.into_package_id_specs(ws)?;
+    let (packages, resolve) = ops::resolve_ws_precisely(ws, None, opts.features,
+                                                        opts.all_features,
+                                                        opts.no_default_features,
+                                                        true, &specs)?;
+    let roots = specs.iter().map(|spec| {
+        spec.query(resolve.iter())
+    }).collect::<CargoResult<BTreeSet<_>>>()?;
+
+    let mut edges = Edges::new();
+    let mut pending = roots.iter().cloned().collect::<Vec<_>>();
+    while let Some(id) = pending.pop() {
+        if edges.contains_key(id) {
+            continue
+        }
+        let deps = resolve.deps(id).collect::<BTreeSet<_>>();
+        pending.extend(deps.iter().cloned());
+        edges.insert(id, deps);
+    }
+
+    let mut dependents = Edges::new();
+    for (&id, deps) in edges.iter() {
+        for &dep in deps {
+            dependents.entry(dep).or_insert_with(BTreeSet::new).insert(id);
+        }
+    }
+
+    if let Some(invert) = opts.invert {
+        let target = resolve.query(invert)?;
+        if !edges.contains_key(target) {
+            bail!("package `{}` isn't a dependency of the selected packages",
+                  target)
+        }
+        let mut annotations = Annotations::new();
+        for (&id, deps) in edges.iter() {
+            let pkg = packages.get(id)?;
+            for &dep in deps {
+                let kinds = declarations(pkg.dependencies(), dep).iter().map(|d| {
+                    let kind = match d.kind() {
+                        Kind::Normal => "normal",
+                        Kind::Development => "dev",
+                        Kind::Build => "build",
+                    };
+                    match d.platform() {
+                        Some(platform) => format!("{} for {}", kind, platform),
+                        None => kind.to_string(),
+                    }
+                }).collect::<Vec<_>>();
+                annotations.insert((id, dep), format!(" [{}]", kinds.join(", ")));
+            }
+        }
+        print_node(target, None, &dependents, &annotations,
+                   &mut HashSet::new(), &mut Vec::new());
+        return Ok(())
+    }
+
+    let annotations = Annotations::new();
+    if !opts.duplicates {
+        for (i, root) in roots.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print_node(root, None, &edges, &annotations,
+                       &mut HashSet::new(), &mut Vec::new());
+        }
+        return Ok(())
+    }
+
+    let mut copies = BTreeMap::new();
+    for &id in edges.keys() {
+        copies.entry(id.name()).or_insert_with(Vec::new).push(id);
+    }
+    let mut first = true;
+    for ids in copies.values().filter(|ids| ids.len() > 1) {
+        for id in ids {
+            if !first {
+                println!();
+            }
+            first = false;
+            print_node(id, None, &dependents, &annotations,
+                       &mut HashSet::new(), &mut Vec::new());
+        }
+    }
+    Ok(())
+}
+
+/// Prints `id`, which is printed under `parent`, and, unless they were printed
+/// already, everything it has an edge to.
+///
+/// Each entry of `levels` is whether more siblings follow at that depth, which
+/// decides how the lines leading up to `id` are drawn.
+fn print_node<'a>(id: &'a PackageId,
+                  parent: Option<&'a PackageId>,
+                  edges: &Edges<'a>,
+                  annotations: &Annotations<'a>,
+                  visited: &mut HashSet<&'a PackageId>,
+                  levels: &mut Vec<bool>) {
+    let mut line = String::new();
+    if let Some((&more, parents)) = levels.split_last() {
+        for &parent_more in parents {
+            line.push_str(if parent_more { "│   " } else { "    " });
+        }
+        line.push_str(if more { "├── " } else { "└── " });
+    }
+    line.push_str(&id.to_string());
+    if let Some(annotation) = parent.and_then(|p| annotations.get(&(id, p))) {
+        line.push_str(annotation);
+    }
+
+    let children = match edges.get(id) {
+        Some(children) => children.iter().cloned().collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    if !visited.insert(id) && !children.is_empty() {
+        println!("{} (*)", line);
+        return
+    }
+    println!("{}", line);
+
+    for (i, child) in children.iter().enumerate() {
+        levels.push(i + 1 < children.len());
+        print_node(child, Some(id), edges, annotations, visited, levels);
+        levels.pop();
+    }
+}
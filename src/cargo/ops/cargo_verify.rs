@@ -0,0 +1,245 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use hex::ToHex;
+
+use core::{GitReference, PackageId, SourceId, Workspace};
+use ops;
+use sources::{git, registry, SourceConfigMap};
+use util::{CacheLockMode, Config, Sha256};
+use util::cache_manifest::{self, CacheManifest};
+use util::errors::{CargoResult, CargoResultExt};
+
+pub struct VerifyOptions {
+    /// Whether the repair plan should be carried out after it's printed
+    pub repair: bool,
+}
+
+/// A single inconsistency between `Cargo.lock` and the local cache.
+struct Problem {
+    /// What's wrong, phrased for the user
+    description: String,
+    /// Cache entries which have to be removed before they're fetched again
+    remove: Vec<PathBuf>,
+}
+
+/// Executes `cargo verify`.
+///
+/// Every package pinned in the lock file is checked against the global cache:
+/// git databases must contain the locked revision, git checkouts must be
+/// complete and unmodified checkouts of that revision, and downloaded registry
+/// tarballs must match their recorded checksum. Anything found to be broken
+/// is printed as a repair plan, which is then executed if requested.
+pub fn verify(ws: &Workspace, opts: &VerifyOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!("a Cargo.lock must exist before the cache can be \
+                       verified, run `cargo generate-lockfile` first"),
+    };
+    let map = SourceConfigMap::new(config)?;
+
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+
+    let mut problems = Vec::new();
+    {
+        // Hold the package cache lock for as long as we're inspecting the
+        // cache, but be sure to release it before any repair fetches.
+        let _lock = config.acquire_package_cache_lock(CacheLockMode::Shared)?;
+        let manifest = cache_manifest::load(config)?;
+        let mut visited = HashSet::new();
+        for id in ids {
+            let source_id = map.replacement_id(id.source_id())?;
+            if source_id.is_git() {
+                if visited.insert(source_id.clone()) {
+                    verify_git(config, &manifest, &source_id, &mut problems)?;
+                }
+            } else if source_id.is_remote_registry() {
+                let checksum = resolve.checksums().get(id)
+                                      .and_then(|c| c.as_ref());
+                verify_tarball(config, &manifest, id, &source_id, checksum,
+                               &mut problems)?;
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        config.shell().status("Verified",
+                              "all locked dependencies in the cache")?;
+        return Ok(())
+    }
+
+    for problem in problems.iter() {
+        config.shell().warn(&problem.description)?;
+    }
+    println!("repair plan:");
+    for problem in problems.iter() {
+        for path in problem.remove.iter() {
+            println!("    remove `{}`", path.display());
+        }
+    }
+    println!("    fetch all locked dependencies");
+
+    if !opts.repair {
+        bail!("found {} problem(s) in the cache, rerun with `--repair` to \
+               fix them", problems.len())
+    }
+
+    {
+        let _lock = config.acquire_package_cache_lock(CacheLockMode::Exclusive)?;
+        for problem in problems.iter() {
+            for path in problem.remove.iter() {
+                remove(path)?;
+            }
+        }
+    }
+    ops::fetch(ws)?;
+    config.shell().status("Repaired",
+                          format!("{} problem(s) in the cache", problems.len()))?;
+    Ok(())
+}
+
+fn verify_git(config: &Config,
+              manifest: &CacheManifest,
+              source_id: &SourceId,
+              problems: &mut Vec<Problem>) -> CargoResult<()> {
+    let precise = match source_id.precise() {
+        Some(precise) => precise,
+        None => return Ok(()),
+    };
+    // The git lock is held by our caller.
+    let git_path = config.git_path().into_path_unlocked();
+    let ident = git::ident(source_id.url())?;
+    let remote = git::GitRemote::new(source_id.url());
+    let db_path = git_path.join("db").join(&ident);
+    if !check_origin(config, manifest, &db_path, source_id, problems) {
+        return Ok(())
+    }
+
+    let db = match remote.db_at(&db_path) {
+        Ok(db) => db,
+        Err(..) => {
+            problems.push(Problem {
+                description: format!("the git database for `{}` is missing",
+                                     source_id.url()),
+                remove: Vec::new(),
+            });
+            return Ok(())
+        }
+    };
+    let rev = match db.rev_for(&GitReference::Rev(precise.to_string())) {
+        Ok(rev) => rev,
+        Err(..) => {
+            problems.push(Problem {
+                description: format!("the git database for `{}` does not \
+                                      contain the locked revision {}",
+                                     source_id.url(), precise),
+                remove: Vec::new(),
+            });
+            return Ok(())
+        }
+    };
+
+    let short_id = db.to_short_id(rev.clone())?;
+    let checkout = git_path.join("checkouts").join(&ident)
+                           .join(short_id.as_str());
+    if !check_origin(config, manifest, &checkout, source_id, problems) {
+        return Ok(())
+    }
+    if checkout.exists() && !db.has_pristine_checkout(&rev, &checkout) {
+        problems.push(Problem {
+            description: format!("the checkout of `{}` at `{}` does not \
+                                  match revision {}",
+                                 source_id.url(), checkout.display(), rev),
+            remove: vec![checkout],
+        });
+    }
+    Ok(())
+}
+
+fn verify_tarball(config: &Config,
+                  manifest: &CacheManifest,
+                  pkg: &PackageId,
+                  source_id: &SourceId,
+                  checksum: Option<&String>,
+                  problems: &mut Vec<Problem>) -> CargoResult<()> {
+    let checksum = match checksum {
+        Some(checksum) => checksum,
+        None => return Ok(()),
+    };
+    let (tarball, unpacked) = registry::cache_locations(source_id, pkg, config);
+    if !tarball.exists() {
+        return Ok(())
+    }
+    if !check_origin(config, manifest, &tarball, source_id, problems) {
+        return Ok(())
+    }
+    let actual = sha256(&tarball).chain_err(|| {
+        format!("failed to read `{}`", tarball.display())
+    })?;
+    if actual != *checksum {
+        problems.push(Problem {
+            description: format!("the downloaded archive for `{}` does not \
+                                  match its checksum", pkg),
+            remove: vec![tarball, unpacked],
+        });
+    }
+    Ok(())
+}
+
+/// Checks that the cache manifest, if it knows of `path`, says that it came
+/// from `source_id`. Otherwise it's a problem, and false is returned.
+///
+/// Entries are named after a hash of where they come from, so this only
+/// happens if something other than Cargo put them there.
+fn check_origin(config: &Config,
+                manifest: &CacheManifest,
+                path: &Path,
+                source_id: &SourceId,
+                problems: &mut Vec<Problem>) -> bool {
+    let entry = match cache_manifest::key(config, path).and_then(|k| manifest.entries.get(&k)) {
+        Some(entry) => entry,
+        None => return true,
+    };
+    if entry.url == source_id.url().as_str() {
+        return true
+    }
+    problems.push(Problem {
+        description: format!("`{}` came from `{}` rather than `{}`",
+                             path.display(), entry.url, source_id.url()),
+        remove: vec![path.to_path_buf()],
+    });
+    false
+}
+
+fn sha256(path: &Path) -> CargoResult<String> {
+    let mut file = File::open(path)?;
+    let mut state = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break
+        }
+        state.update(&buf[..n]);
+    }
+    Ok(state.finish().to_hex())
+}
+
+fn remove(path: &Path) -> CargoResult<()> {
+    let res = if path.is_dir() {
+        // Git checkouts may be read-only, which would otherwise stop them from
+        // being removed on some platforms.
+        let _ = git::set_checkout_readonly(path, false);
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        return Ok(())
+    };
+    res.chain_err(|| format!("failed to remove `{}`", path.display()))?;
+    Ok(())
+}
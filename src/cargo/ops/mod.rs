@@ -1,11 +1,13 @@
 pub use self::cargo_clean::{clean, CleanOptions};
 pub use self::cargo_compile::{compile, compile_with_exec, compile_ws, CompileOptions};
+pub use self::cargo_compile::requested_target;
 pub use self::cargo_compile::{CompileFilter, CompileMode, FilterRule, MessageFormat, Packages};
 pub use self::cargo_read_manifest::{read_package, read_packages};
 pub use self::cargo_rustc::{compile_targets, Compilation, Kind, Unit};
 pub use self::cargo_rustc::{Context, is_bad_artifact_name};
-pub use self::cargo_rustc::{BuildOutput, BuildConfig, TargetConfig};
-pub use self::cargo_rustc::{Executor, DefaultExecutor};
+pub use self::cargo_rustc::{BuildOutput, BuildConfig, ScriptSandbox, TargetConfig};
+pub use self::cargo_rustc::{Executor, DefaultExecutor, PackagesToBuild};
+pub use self::cargo_rustc::FingerprintStats;
 pub use self::cargo_run::run;
 pub use self::cargo_install::{install, install_list, uninstall};
 pub use self::cargo_new::{new, init, NewOptions, VersionControl};
@@ -15,29 +17,53 @@ pub use self::cargo_generate_lockfile::{update_lockfile};
 pub use self::cargo_generate_lockfile::UpdateOptions;
 pub use self::lockfile::{load_pkg_lockfile, write_pkg_lockfile};
 pub use self::cargo_test::{run_tests, run_benches, TestOptions};
-pub use self::cargo_package::{package, PackageOpts};
+pub use self::cargo_package::{package, check_publish, PackageOpts, PublishProblem};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
 pub use self::registry::{registry_login, search, http_proxy_exists, http_handle};
 pub use self::registry::{modify_owners, yank, OwnersOptions, PublishOpts};
 pub use self::cargo_fetch::fetch;
 pub use self::cargo_pkgid::pkgid;
-pub use self::resolve::{resolve_ws, resolve_ws_precisely, resolve_with_previous};
+pub use self::resolve::{resolve_ws, resolve_ws_precisely, resolve_ws_shipped,
+                        resolve_with_previous};
 pub use self::cargo_output_metadata::{output_metadata, OutputMetadataOptions, ExportInfo};
+pub use self::cargo_verify::{verify, VerifyOptions};
+pub use self::cargo_outdated::outdated;
+pub use self::cargo_audit::{audit, AuditOptions};
+pub use self::cargo_cache::{cache_stats, cache_gc, cache_clean, CacheCleanOptions};
+pub use self::cargo_cache::human_readable_bytes;
+pub use self::cargo_features::report_features;
+pub use self::cargo_graph::{graph, GraphFormat, GraphOptions};
+pub use self::cargo_info::info;
+pub use self::cargo_license::{license, LicenseFormat, LicenseOptions};
+pub use self::cargo_sbom::{sbom, SbomFormat, SbomOptions};
+pub use self::cargo_tree::{tree, TreeOptions};
+pub use self::cargo_edit::{add, remove, AddOptions, DepSource, RemoveOptions};
 
+mod cargo_audit;
+mod cargo_cache;
 mod cargo_clean;
 mod cargo_compile;
 mod cargo_doc;
+mod cargo_edit;
+mod cargo_features;
 mod cargo_fetch;
 mod cargo_generate_lockfile;
+mod cargo_graph;
+mod cargo_info;
 mod cargo_install;
+mod cargo_license;
 mod cargo_new;
+mod cargo_outdated;
 mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
 mod cargo_run;
+mod cargo_sbom;
 mod cargo_rustc;
 mod cargo_test;
+mod cargo_tree;
+mod cargo_verify;
 mod lockfile;
 mod registry;
 mod resolve;
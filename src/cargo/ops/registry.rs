@@ -5,18 +5,25 @@ use std::time::Duration;
 
 use curl::easy::{Easy, SslOpt};
 use git2;
-use registry::{Registry, NewCrate, NewCrateDependency};
+use registry::{Crate, Registry, NewCrate, NewCrateDependency};
+use registry::{Error as RegistryError, ErrorKind as RegistryErrorKind};
+use semver::Version;
 
 use url::percent_encoding::{percent_encode, QUERY_ENCODE_SET};
 
 use version;
 use core::source::Source;
-use core::{Package, SourceId, Workspace};
+use core::{Dependency, Package, SourceId, Workspace};
+// Named so as not to clash with the client of the registry API.
+use core::Registry as PackageRegistry;
 use core::dependency::Kind;
 use core::manifest::ManifestMetadata;
 use ops;
-use sources::{RegistrySource};
-use util::config::{self, Config};
+use sources::{RegistrySource, SourceConfigMap};
+use util::config::{self, Config, Definition};
+use util::credential;
+use util::network;
+use util::machine_message;
 use util::paths;
 use util::ToUrl;
 use util::errors::{CargoError, CargoResult, CargoResultExt};
@@ -36,11 +43,20 @@ pub struct PublishOpts<'cfg> {
     pub jobs: Option<u32>,
     pub target: Option<&'cfg str>,
     pub dry_run: bool,
+    /// The alternative registry to publish to, see `[registries]`.
+    pub registry: Option<String>,
+    /// Only report what would keep the package from being published.
+    pub check: bool,
+    pub message_format: ops::MessageFormat,
 }
 
 pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
     let pkg = ws.current()?;
 
+    if opts.check {
+        return report_publish_check(ws, opts)
+    }
+
     if !pkg.publish() {
         bail!("some crates cannot be published.\n\
                `{}` is marked as unpublishable", pkg.name());
@@ -51,8 +67,10 @@ pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
 
     let (mut registry, reg_id) = registry(opts.config,
                                           opts.token.clone(),
-                                          opts.index.clone())?;
-    verify_dependencies(pkg, &reg_id)?;
+                                          opts.index.clone(),
+                                          opts.registry.clone())?;
+    verify_dependencies(pkg, &reg_id, opts.registry.is_some())?;
+    check_upload_allowed(opts.config, pkg, &mut registry)?;
 
     // Prepare a tarball, with a non-surpressable warning if metadata
     // is missing since this is being put online.
@@ -64,17 +82,159 @@ pub fn publish(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
         allow_dirty: opts.allow_dirty,
         target: opts.target,
         jobs: opts.jobs,
+        registry: opts.registry.clone(),
     })?.unwrap();
 
     // Upload said tarball to the specified destination
     opts.config.shell().status("Uploading", pkg.package_id().to_string())?;
-    transmit(opts.config, pkg, tarball.file(), &mut registry, opts.dry_run)?;
+    transmit(opts.config, pkg, tarball.file(), &mut registry, &reg_id, opts.dry_run)?;
 
     Ok(())
 }
 
-fn verify_dependencies(pkg: &Package, registry_src: &SourceId)
-                       -> CargoResult<()> {
+/// Reports the problems `cargo publish --check` finds, failing if any of them
+/// would have the registry reject the package.
+fn report_publish_check(ws: &Workspace, opts: &PublishOpts) -> CargoResult<()> {
+    let pkg = ws.current()?;
+    let problems = ops::check_publish(ws)?;
+    for problem in problems.iter() {
+        match opts.message_format {
+            ops::MessageFormat::Human if problem.is_error() => {
+                opts.config.shell().error(&problem.message)?
+            }
+            ops::MessageFormat::Human => opts.config.shell().warn(&problem.message)?,
+            ops::MessageFormat::Json => {
+                machine_message::emit(&machine_message::PublishCheck {
+                    package_id: pkg.package_id(),
+                    level: problem.level,
+                    code: problem.code,
+                    message: &problem.message,
+                });
+            }
+        }
+    }
+
+    let errors = problems.iter().filter(|p| p.is_error()).count();
+    if errors > 0 {
+        bail!("`{}` can't be published, {} problem{} found",
+              pkg.name(), errors, if errors == 1 { "" } else { "s" })
+    }
+    opts.config.shell().status("Checked", format!("{} can be published",
+                                                  pkg.package_id()))?;
+    Ok(())
+}
+
+/// Asks the registry whether the package can be uploaded with the token, so
+/// that it doesn't get packaged and verified only to be rejected: its name
+/// has to be new or owned by whoever the token belongs to, and its version
+/// can't have been published yet.
+///
+/// Registries which can't answer that, or not with this token, are left to
+/// reject the upload themselves.
+fn check_upload_allowed(config: &Config,
+                        pkg: &Package,
+                        registry: &mut Registry) -> CargoResult<()> {
+    // Not finding the crate means its name is still free.
+    let versions = match retry_api(config, || registry.versions(pkg.name())) {
+        Ok(versions) => versions,
+        Err(e) => {
+            debug!("not checking `{}` before uploading it: {}", pkg.name(), e);
+            return Ok(())
+        }
+    };
+
+    let published = versions.iter().find(|v| {
+        v.num.parse::<Version>().ok().as_ref() == Some(pkg.version())
+    });
+    if let Some(published) = published {
+        bail!("`{} v{}` has already been published{}\n\
+               Versions can't be published twice, bump the `version` in \
+               Cargo.toml to publish these changes",
+              pkg.name(), published.num,
+              if published.yanked { ", and was yanked since" } else { "" })
+    }
+
+    let owners = retry_api(config, || registry.list_owners(pkg.name()));
+    let me = retry_api(config, || registry.me());
+    let (owners, me) = match (owners, me) {
+        (Ok(owners), Ok(me)) => (owners, me),
+        (Err(e), _) | (_, Err(e)) => {
+            debug!("not checking the owners of `{}`: {}", pkg.name(), e);
+            return Ok(())
+        }
+    };
+    // Teams own crates through their members, which can't be told here.
+    if owners.iter().any(|o| o.login == me.login || o.login.contains(':')) {
+        return Ok(())
+    }
+    let owners = owners.iter().map(|o| format!("`{}`", o.login))
+                       .collect::<Vec<_>>().join(", ");
+    bail!("crate `{}` is owned by {}, but not by `{}` whose token is used\n\
+           If this is the same crate, one of its owners can add you with \
+           `cargo owner --add {}`, otherwise rename the package in Cargo.toml",
+          pkg.name(), owners, me.login, me.login)
+}
+
+/// Explains in terms of what to do about it why the registry rejected an
+/// upload, for the errors it's known to answer with.
+fn upload_error_hint(pkg: &Package, err: &RegistryError) -> Option<String> {
+    match *err.kind() {
+        RegistryErrorKind::Unauthorized(_) => {
+            Some("the registry rejected the token, get a new one from it and \
+                  run `cargo login` with it".to_string())
+        }
+        RegistryErrorKind::RateLimited(retry_after) => {
+            Some(format!("the registry is limiting how often crates can be \
+                          published, try again {}", match retry_after {
+                Some(secs) => format!("in {} seconds", secs),
+                None => "later".to_string(),
+            }))
+        }
+        // Uploads aren't retried, as they may have been published anyway.
+        RegistryErrorKind::ServerError(..) => {
+            Some(format!("the registry failed to handle the upload, check \
+                          whether `{} v{}` was published before trying again",
+                         pkg.name(), pkg.version()))
+        }
+        RegistryErrorKind::Api(ref errors) => errors.iter().filter_map(|e| {
+            if e.contains("is already uploaded") {
+                Some(format!("`{} v{}` has already been published, bump the \
+                              `version` in Cargo.toml to publish these changes",
+                             pkg.name(), pkg.version()))
+            } else if e.contains("don't seem to be an owner") {
+                Some(format!("crate `{}` belongs to someone else, one of its \
+                              owners can add you with `cargo owner --add`, \
+                              otherwise rename the package in Cargo.toml",
+                             pkg.name()))
+            } else if e.contains("max upload size") {
+                Some("the package is too large for the registry, leave out \
+                      what it doesn't need with `exclude` in Cargo.toml"
+                     .to_string())
+            } else {
+                None
+            }
+        }).next(),
+        _ => None,
+    }
+}
+
+/// Makes a request to the registry API, retrying it while the registry is
+/// rate limiting or failing to handle requests, see `network::with_retry`.
+///
+/// Uploads aren't made with this, as a failed one may have been published
+/// anyway.
+fn retry_api<T, F>(config: &Config, mut request: F) -> CargoResult<T>
+    where F: FnMut() -> Result<T, RegistryError>
+{
+    network::with_retry(config, || request().map_err(CargoError::from))
+}
+
+/// Checks that the dependencies can be found by whoever uses the package once
+/// it's published. Packages on alternative registries may depend on those of
+/// any other registry, the index records where they are.
+fn verify_dependencies(pkg: &Package,
+                       registry_src: &SourceId,
+                       alternative: bool) -> CargoResult<()> {
     for dep in pkg.dependencies().iter() {
         if dep.source_id().is_path() {
             if !dep.specified_req() {
@@ -83,7 +243,9 @@ fn verify_dependencies(pkg: &Package, registry_src: &SourceId)
                        a version", dep.name())
             }
         } else if dep.source_id() != registry_src {
-            if dep.source_id().is_registry() {
+            if dep.source_id().is_registry() && alternative {
+                continue
+            } else if dep.source_id().is_registry() {
                 bail!("crates cannot be published to crates.io with dependencies sourced from other\n\
                        registries either publish `{}` on crates.io or pull it into this repository\n\
                        and specify it with a path and version\n\
@@ -104,6 +266,7 @@ fn transmit(config: &Config,
             pkg: &Package,
             tarball: &File,
             registry: &mut Registry,
+            registry_src: &SourceId,
             dry_run: bool) -> CargoResult<()> {
     let deps = pkg.dependencies().iter().map(|dep| {
         NewCrateDependency {
@@ -113,6 +276,14 @@ fn transmit(config: &Config,
             features: dep.features().to_vec(),
             version_req: dep.version_req().to_string(),
             target: dep.platform().map(|s| s.to_string()),
+            // Path dependencies are published as dependencies on the
+            // registry being published to.
+            registry: if dep.source_id().is_registry() &&
+                         dep.source_id() != registry_src {
+                Some(dep.source_id().url().to_string())
+            } else {
+                None
+            },
             kind: match dep.kind() {
                 Kind::Normal => "normal",
                 Kind::Build => "build",
@@ -159,6 +330,7 @@ fn transmit(config: &Config,
         license: license.clone(),
         license_file: license_file.clone(),
         badges: badges.clone(),
+        links: pkg.manifest().links().map(|s| s.to_string()),
     }, tarball);
 
     match publish {
@@ -185,28 +357,53 @@ fn transmit(config: &Config,
 
             Ok(())
         },
-        Err(e) => Err(e.into()),
+        Err(e) => match upload_error_hint(pkg, &e) {
+            Some(hint) => Err(e).chain_err(|| hint),
+            None => Err(e.into()),
+        },
     }
 }
 
-pub fn registry_configuration(config: &Config) -> CargoResult<RegistryConfig> {
-    let index = config.get_string("registry.index")?.map(|p| p.val);
-    let token = config.get_string("registry.token")?.map(|p| p.val);
+/// The configuration of crates.io in `[registry]`, or of the alternative
+/// registry named `registry` in `[registries.<name>]`.
+pub fn registry_configuration(config: &Config,
+                              registry: Option<&str>) -> CargoResult<RegistryConfig> {
+    let table = match registry {
+        Some(registry) => format!("registries.{}", registry),
+        None => "registry".to_string(),
+    };
+    let index = config.get_string(&format!("{}.index", table))?.map(|p| p.val);
+    let token = config.get_string(&format!("{}.token", table))?.map(|p| p.val);
+    if let (Some(registry), None) = (registry, index.as_ref()) {
+        bail!("no index found for registry: `{}`, it's configured with \
+               `registries.{}.index`", registry, registry)
+    }
     Ok(RegistryConfig { index: index, token: token })
 }
 
 pub fn registry(config: &Config,
                 token: Option<String>,
-                index: Option<String>) -> CargoResult<(Registry, SourceId)> {
+                index: Option<String>,
+                registry: Option<String>) -> CargoResult<(Registry, SourceId)> {
+    if index.is_some() && registry.is_some() {
+        bail!("only one of `--index` or `--registry` can be given")
+    }
     // Parse all configuration options
     let RegistryConfig {
         token: token_config,
         index: _index_config,
-    } = registry_configuration(config)?;
-    let token = token.or(token_config);
-    let sid = match index {
-        Some(index) => SourceId::for_registry(&index.to_url()?)?,
-        None => SourceId::crates_io(config)?,
+    } = registry_configuration(config, registry.as_ref().map(|s| &s[..]))?;
+    let token = match token.or(token_config) {
+        Some(token) => Some(token),
+        None => {
+            let key = registry.as_ref().map_or(credential::CRATES_IO, |s| &s[..]);
+            credential::provider(config)?.get(key)?
+        }
+    };
+    let sid = match (index, registry) {
+        (Some(index), _) => SourceId::for_registry(&index.to_url()?)?,
+        (None, Some(registry)) => SourceId::alt_registry(config, &registry)?,
+        (None, None) => SourceId::crates_io(config)?,
     };
     let api_host = {
         let mut src = RegistrySource::remote(&sid, config);
@@ -293,15 +490,45 @@ pub fn http_timeout(config: &Config) -> CargoResult<Option<i64>> {
     Ok(env::var("HTTP_TIMEOUT").ok().and_then(|s| s.parse().ok()))
 }
 
-pub fn registry_login(config: &Config, token: String) -> CargoResult<()> {
-    let RegistryConfig { token: old_token, .. } = registry_configuration(config)?;
-    if let Some(old_token) = old_token {
-        if old_token == token {
-            return Ok(());
-        }
-    }
+/// Saves the token of crates.io, or of the alternative registry named
+/// `registry`.
+pub fn registry_login(config: &Config,
+                      token: String,
+                      registry: Option<String>) -> CargoResult<()> {
+    let registry = registry.as_ref().map(|s| &s[..]);
+    registry_configuration(config, registry)?;
+    let key = match registry {
+        Some(registry) => format!("registries.{}.token", registry),
+        None => "registry.token".to_string(),
+    };
+    let credentials = config.home().join("credentials").into_path_unlocked();
+    let in_credentials = match config.get_string(&key)? {
+        Some(ref old) if old.val == token => return Ok(()),
+        Some(old) => match old.definition {
+            Definition::Path(ref path) if *path == credentials => true,
+            // A token elsewhere in the configuration takes precedence over
+            // the one of any credential provider, so it has to be replaced
+            // with one in the credentials file, which takes precedence over
+            // the rest of the configuration.
+            _ => return config::save_credentials(config, token, registry),
+        },
+        None => false,
+    };
 
-    config::save_credentials(config, token)
+    let name = registry.unwrap_or(credential::CRATES_IO);
+    let provider = credential::provider(config)?;
+    if !in_credentials && provider.get(name)?.as_ref() == Some(&token) {
+        return Ok(())
+    }
+    provider.store(name, &token)?;
+    // A token saved in the credentials file by an earlier login is moved to
+    // the provider rather than kept in plain text. It's only removed once the
+    // new token made it there, as the provider may fall back to the
+    // credentials file itself.
+    if in_credentials && provider.get(name)?.as_ref() == Some(&token) {
+        config::remove_credentials(config, registry)?;
+    }
+    Ok(())
 }
 
 pub struct OwnersOptions {
@@ -311,6 +538,7 @@ pub struct OwnersOptions {
     pub to_add: Option<Vec<String>>,
     pub to_remove: Option<Vec<String>>,
     pub list: bool,
+    pub registry: Option<String>,
 }
 
 pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
@@ -324,12 +552,12 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
     };
 
     let (mut registry, _) = registry(config, opts.token.clone(),
-                                          opts.index.clone())?;
+                                     opts.index.clone(), opts.registry.clone())?;
 
     if let Some(ref v) = opts.to_add {
         let v = v.iter().map(|s| &s[..]).collect::<Vec<_>>();
-        let msg = registry.add_owners(&name, &v).map_err(|e| {
-            CargoError::from(format!("failed to invite owners to crate {}: {}", name, e))
+        let msg = retry_api(config, || registry.add_owners(&name, &v)).chain_err(|| {
+            format!("failed to invite owners to crate {}", name)
         })?;
 
         config.shell().status("Owner", msg)?;
@@ -339,14 +567,14 @@ pub fn modify_owners(config: &Config, opts: &OwnersOptions) -> CargoResult<()> {
         let v = v.iter().map(|s| &s[..]).collect::<Vec<_>>();
         config.shell().status("Owner", format!("removing {:?} from crate {}",
                                                     v, name))?;
-        registry.remove_owners(&name, &v).map_err(|e| {
-            CargoError::from(format!("failed to remove owners from crate {}: {}", name, e))
+        retry_api(config, || registry.remove_owners(&name, &v)).chain_err(|| {
+            format!("failed to remove owners from crate {}", name)
         })?;
     }
 
     if opts.list {
-        let owners = registry.list_owners(&name).map_err(|e| {
-            CargoError::from(format!("failed to list owners of crate {}: {}", name, e))
+        let owners = retry_api(config, || registry.list_owners(&name)).chain_err(|| {
+            format!("failed to list owners of crate {}", name)
         })?;
         for owner in owners.iter() {
             print!("{}", owner.login);
@@ -367,6 +595,7 @@ pub fn yank(config: &Config,
             version: Option<String>,
             token: Option<String>,
             index: Option<String>,
+            registry: Option<String>,
             undo: bool) -> CargoResult<()> {
     let name = match krate {
         Some(name) => name,
@@ -381,17 +610,17 @@ pub fn yank(config: &Config,
         None => bail!("a version must be specified to yank")
     };
 
-    let (mut registry, _) = registry(config, token, index)?;
+    let (mut registry, _) = registry(config, token, index, registry)?;
 
     if undo {
         config.shell().status("Unyank", format!("{}:{}", name, version))?;
-        registry.unyank(&name, &version).map_err(|e| {
-            CargoError::from(format!("failed to undo a yank: {}", e))
+        retry_api(config, || registry.unyank(&name, &version)).chain_err(|| {
+            "failed to undo a yank"
         })?;
     } else {
         config.shell().status("Yank", format!("{}:{}", name, version))?;
-        registry.yank(&name, &version).map_err(|e| {
-            CargoError::from(format!("failed to yank: {}", e))
+        retry_api(config, || registry.yank(&name, &version)).chain_err(|| {
+            "failed to yank"
         })?;
     }
 
@@ -410,10 +639,20 @@ pub fn search(query: &str,
         }
     }
 
-    let (mut registry, _) = registry(config, None, index)?;
-    let (crates, total_crates) = registry.search(query, limit).map_err(|e| {
-        CargoError::from(format!("failed to retrieve search results from the registry: {}", e))
-    })?;
+    // Search wherever packages would actually come from.
+    let sid = match index {
+        Some(index) => SourceId::for_registry(&index.to_url()?)?,
+        None => SourceId::crates_io(config)?,
+    };
+    let sid = SourceConfigMap::new(config)?.replacement_id(&sid)?;
+    let (crates, total_crates) = match search_api(config, &sid)? {
+        Some(mut registry) => {
+            retry_api(config, || registry.search(query, limit)).chain_err(|| {
+                "failed to retrieve search results from the registry"
+            })?
+        }
+        None => search_index(config, &sid, query, limit)?,
+    };
 
     let list_items = crates.iter()
         .map(|krate| (
@@ -451,3 +690,70 @@ pub fn search(query: &str,
 
     Ok(())
 }
+
+/// A client for the search API of the registry `sid`, if it has one.
+fn search_api(config: &Config, sid: &SourceId) -> CargoResult<Option<Registry>> {
+    if !sid.is_remote_registry() {
+        return Ok(None)
+    }
+    let mut src = RegistrySource::remote(sid, config);
+    src.update().chain_err(|| format!("failed to update {}", sid))?;
+    let api = match src.config()?.and_then(|c| c.api) {
+        Some(api) => api,
+        None => return Ok(None),
+    };
+    Ok(Some(Registry::new_handle(api, None, http_handle(config)?)))
+}
+
+/// Searches the index of the registry `sid` for packages with all of the
+/// `+` separated words of `query` in their names, for registries without a
+/// search API. Exact matches come first.
+///
+/// The index doesn't have descriptions, so there are none in the results.
+fn search_index(config: &Config, sid: &SourceId, query: &str, limit: u8)
+                -> CargoResult<(Vec<Crate>, u32)> {
+    let mut src = match sid.url().to_file_path() {
+        Ok(path) if sid.is_registry() && !sid.is_remote_registry() => {
+            RegistrySource::local(sid, &path, config)
+        }
+        _ if sid.is_remote_registry() => RegistrySource::remote(sid, config),
+        _ => bail!("{} can't be searched, only registries can", sid),
+    };
+    src.update().chain_err(|| format!("failed to update {}", sid))?;
+    let names = src.list_packages().chain_err(|| {
+        format!("{} doesn't have a search API and its index can't be searched", sid)
+    })?;
+
+    let query = query.to_lowercase().replace('-', "_");
+    let words = query.split('+').filter(|w| !w.is_empty()).collect::<Vec<_>>();
+    let mut matches = names.into_iter().filter(|name| {
+        let name = name.to_lowercase().replace('-', "_");
+        words.iter().all(|word| name.contains(word))
+    }).collect::<Vec<_>>();
+    matches.sort_by_key(|name| name.to_lowercase().replace('-', "_") != query);
+    let total = matches.len() as u32;
+
+    let mut crates = Vec::new();
+    for name in matches {
+        if crates.len() == limit as usize {
+            break
+        }
+        let dep = Dependency::parse_no_deprecated(&name, None, sid)?;
+        let mut max_version: Option<Version> = None;
+        src.query(&dep, &mut |summary| {
+            let version = summary.version();
+            if max_version.as_ref().map(|max| version > max).unwrap_or(true) {
+                max_version = Some(version.clone());
+            }
+        })?;
+        // Packages with every version yanked are left out.
+        if let Some(max_version) = max_version {
+            crates.push(Crate {
+                name: name,
+                description: None,
+                max_version: max_version.to_string(),
+            });
+        }
+    }
+    Ok((crates, total))
+}
@@ -1,5 +1,7 @@
 use std::collections::HashSet;
 
+use url::Url;
+
 use core::{PackageId, PackageIdSpec, PackageSet, Source, SourceId, Workspace};
 use core::registry::PackageRegistry;
 use core::resolver::{self, Resolve, Method};
@@ -20,13 +22,27 @@ pub fn resolve_ws<'a>(ws: &Workspace<'a>) -> CargoResult<(PackageSet<'a>, Resolv
     Ok((packages, resolve))
 }
 
+/// Resolves what's shipped of the packages in `specs`, that is their
+/// dependencies without the dev-dependencies. Packages which are only used by
+/// dev-dependencies are only needed to test them and don't end up in what's
+/// shipped, so they're left out of reports about it like licenses and bills
+/// of materials.
+pub fn resolve_ws_shipped<'a>(ws: &Workspace<'a>, specs: &[PackageIdSpec])
+                              -> CargoResult<(PackageSet<'a>, Resolve)> {
+    resolve_ws_precisely(ws, None, &[], false, false, false, specs)
+}
+
 /// Resolves dependencies for some packages of the workspace,
 /// taking into account `paths` overrides and activated features.
+///
+/// The dev-dependencies of the packages in `specs` are only included if
+/// `dev_deps` is set, or if `all_features` is, which always includes them.
 pub fn resolve_ws_precisely<'a>(ws: &Workspace<'a>,
                                 source: Option<Box<Source + 'a>>,
                                 features: &[String],
                                 all_features: bool,
                                 no_default_features: bool,
+                                dev_deps: bool,
                                 specs: &[PackageIdSpec])
                                 -> CargoResult<(PackageSet<'a>, Resolve)> {
     let features = features.iter()
@@ -70,7 +86,7 @@ pub fn resolve_ws_precisely<'a>(ws: &Workspace<'a>,
         Method::Everything
     } else {
         Method::Required {
-            dev_deps: true, // TODO: remove this option?
+            dev_deps: dev_deps,
             features: &features,
             uses_default_features: !no_default_features,
         }
@@ -257,22 +273,100 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         None => root_replace.to_vec(),
     };
 
-    let config = if warn {
-        Some(ws.config())
-    } else {
-        None
-    };
     let mut resolved = resolver::resolve(&summaries,
                                          &replace,
                                          registry,
-                                         config)?;
+                                         Some(ws.config()),
+                                         warn)?;
     resolved.register_used_patches(registry.patches());
     if let Some(previous) = previous {
         resolved.merge_from(previous)?;
     }
+    check_source_policy(ws, &resolved)?;
     Ok(resolved)
 }
 
+/// Checks the sources of every dependency in `resolve` against `[policy]`:
+///
+/// * `policy.forbidden-sources` lists kinds of sources, "git", "path" or
+///   "registry", which dependencies may not come from. The members of the
+///   workspace themselves are always allowed.
+/// * `policy.allowed-git` lists `host[/path]` prefixes of the only git
+///   repositories dependencies may come from.
+fn check_source_policy(ws: &Workspace, resolve: &Resolve) -> CargoResult<()> {
+    let config = ws.config();
+    let forbidden = match config.get_list("policy.forbidden-sources")? {
+        Some(list) => list.val,
+        None => Vec::new(),
+    };
+    for &(ref kind, ref definition) in forbidden.iter() {
+        match &kind[..] {
+            "git" | "path" | "registry" => {}
+            _ => bail!("unknown source kind `{}` in `policy.forbidden-sources` \
+                        (in {}), expected `git`, `path` or `registry`",
+                       kind, definition.display()),
+        }
+    }
+    let allowed_git = config.get_list("policy.allowed-git")?;
+    if forbidden.is_empty() && allowed_git.is_none() {
+        return Ok(())
+    }
+
+    let members = ws.members().map(|p| p.package_id()).collect::<HashSet<_>>();
+    let mut ids = resolve.iter().collect::<Vec<_>>();
+    ids.sort();
+    for parent in ids {
+        let mut deps = resolve.deps(parent).collect::<Vec<_>>();
+        deps.sort();
+        for dep in deps {
+            if members.contains(dep) {
+                continue
+            }
+            let source_id = dep.source_id();
+            let (kind, what) = if source_id.is_git() {
+                ("git", format!("the git repository `{}`", source_id.url()))
+            } else if source_id.is_path() {
+                let path = match source_id.url().to_file_path() {
+                    Ok(path) => path.display().to_string(),
+                    Err(()) => source_id.url().to_string(),
+                };
+                ("path", format!("the path `{}`", path))
+            } else if source_id.is_registry() {
+                ("registry", source_id.display_registry())
+            } else {
+                continue
+            };
+            if let Some(&(_, ref definition)) = forbidden.iter().find(|&&(ref k, _)| k == kind) {
+                bail!("dependency `{}` of `{}` comes from {}, but {} dependencies \
+                       are forbidden by `policy.forbidden-sources` (in {})",
+                      dep.name(), parent, what, kind, definition.display())
+            }
+            if let Some(ref allowed) = allowed_git {
+                if source_id.is_git() &&
+                   !allowed.val.iter().any(|&(ref a, _)| git_allowed(source_id.url(), a)) {
+                    bail!("dependency `{}` of `{}` comes from {}, which isn't \
+                           under any of the locations in `policy.allowed-git` \
+                           (in {})", dep.name(), parent, what, allowed.definition)
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the git repository at `url` is under `allowed`, a host optionally
+/// followed by a path, like `github.com/rust-lang`. A leading scheme is
+/// ignored.
+fn git_allowed(url: &Url, allowed: &str) -> bool {
+    let allowed = match allowed.find("://") {
+        Some(i) => &allowed[i + 3..],
+        None => allowed,
+    };
+    let allowed = allowed.trim_right_matches('/');
+    let location = format!("{}{}", url.host_str().unwrap_or(""), url.path());
+    location == allowed || location.starts_with(&format!("{}/", allowed))
+}
+
 /// Read the `paths` configuration variable to discover all path overrides that
 /// have been configured.
 fn add_overrides<'a>(registry: &mut PackageRegistry<'a>,
@@ -306,6 +400,8 @@ fn get_resolved_packages<'a>(resolve: &Resolve,
                              registry: PackageRegistry<'a>)
                              -> PackageSet<'a> {
     let ids: Vec<PackageId> = resolve.iter().cloned().collect();
-    registry.get(&ids)
+    let mut packages = registry.get(&ids);
+    packages.set_checksums(resolve.checksums());
+    packages
 }
 
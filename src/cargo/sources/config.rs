@@ -10,8 +10,8 @@ use std::path::{Path, PathBuf};
 use url::Url;
 
 use core::{Source, SourceId, GitReference};
-use sources::ReplacedSource;
-use util::{Config, ToUrl};
+use sources::{ReplacedSource, git};
+use util::{layout_version, Config, ToUrl};
 use util::config::ConfigValue;
 use util::errors::{CargoError, CargoResult, CargoResultExt};
 
@@ -53,6 +53,7 @@ impl<'cfg> SourceConfigMap<'cfg> {
     }
 
     pub fn empty(config: &'cfg Config) -> CargoResult<SourceConfigMap<'cfg>> {
+        layout_version::check_home(config)?;
         let mut base = SourceConfigMap {
             cfgs: HashMap::new(),
             id2name: HashMap::new(),
@@ -71,13 +72,56 @@ impl<'cfg> SourceConfigMap<'cfg> {
 
     pub fn load(&self, id: &SourceId) -> CargoResult<Box<Source + 'cfg>> {
         debug!("loading: {}", id);
+        let (new_id, orig_name, name) = match self.follow(id)? {
+            Some(replacement) => replacement,
+            None => return Ok(id.load(self.config)?),
+        };
+        let new_src = new_id.load(self.config)?;
+        let old_src = id.load(self.config)?;
+        if !new_src.supports_checksums() && old_src.supports_checksums() {
+            bail!("\
+cannot replace `{orig}` with `{name}`, the source `{orig}` supports \
+checksums, but `{name}` does not
+
+a lock file compatible with `{orig}` cannot be generated in this situation
+", orig = orig_name, name = name);
+        }
+
+        if old_src.requires_precise() && id.precise().is_none() {
+            bail!("\
+the source {orig} requires a lock file to be present first before it can be
+used against vendored source code
+
+remove the source replacement configuration, generate a lock file, and then
+restore the source replacement configuration to continue the build
+", orig = orig_name);
+        }
+
+        Ok(Box::new(ReplacedSource::new(id, &new_id, new_src)))
+    }
+
+    /// Returns the id of the source which will actually be used in place of
+    /// `id`, following any `replace-with` configuration.
+    pub fn replacement_id(&self, id: &SourceId) -> CargoResult<SourceId> {
+        Ok(match self.follow(id)? {
+            Some((new_id, _, _)) => new_id,
+            None => id.clone(),
+        })
+    }
+
+    /// Follows the chain of `replace-with` keys starting at `id`.
+    ///
+    /// Returns `None` if `id` isn't replaced, or otherwise the id of the
+    /// replacement source along with the names of the original and final
+    /// sources in the configuration.
+    fn follow(&self, id: &SourceId)
+              -> CargoResult<Option<(SourceId, &str, &str)>> {
         let mut name = match self.id2name.get(id) {
             Some(name) => name,
-            None => return Ok(id.load(self.config)?),
+            None => return Ok(None),
         };
         let mut path = Path::new("/");
         let orig_name = name;
-        let new_id;
         loop {
             let cfg = match self.cfgs.get(name) {
                 Some(cfg) => cfg,
@@ -91,11 +135,11 @@ impl<'cfg> SourceConfigMap<'cfg> {
                     name = s;
                     path = p;
                 }
-                None if *id == cfg.id => return Ok(id.load(self.config)?),
+                None if *id == cfg.id => return Ok(None),
                 None => {
-                    new_id = cfg.id.with_precise(id.precise()
-                                                 .map(|s| s.to_string()));
-                    break
+                    let new_id = cfg.id.with_precise(id.precise()
+                                                     .map(|s| s.to_string()));
+                    return Ok(Some((new_id, orig_name, name)))
                 }
             }
             debug!("following pointer to {}", name);
@@ -105,28 +149,6 @@ impl<'cfg> SourceConfigMap<'cfg> {
                        (configuration in `{}`)", name, path.display())
             }
         }
-        let new_src = new_id.load(self.config)?;
-        let old_src = id.load(self.config)?;
-        if !new_src.supports_checksums() && old_src.supports_checksums() {
-            bail!("\
-cannot replace `{orig}` with `{name}`, the source `{orig}` supports \
-checksums, but `{name}` does not
-
-a lock file compatible with `{orig}` cannot be generated in this situation
-", orig = orig_name, name = name);
-        }
-
-        if old_src.requires_precise() && id.precise().is_none() {
-            bail!("\
-the source {orig} requires a lock file to be present first before it can be
-used against vendored source code
-
-remove the source replacement configuration, generate a lock file, and then
-restore the source replacement configuration to continue the build
-", orig = orig_name);
-        }
-
-        Ok(Box::new(ReplacedSource::new(id, &new_id, new_src)))
     }
 
     fn add(&mut self, name: &str, cfg: SourceConfig) {
@@ -160,7 +182,12 @@ restore the source replacement configuration to continue the build
             srcs.push(SourceId::for_directory(&path)?);
         }
         if let Some(val) = table.get("git") {
-            let url = url(val, &format!("source.{}.git", name))?;
+            let key = format!("source.{}.git", name);
+            let (s, path) = val.string(&key)?;
+            let url = git::to_url(s).chain_err(|| {
+                format!("configuration key `{}` specified an invalid \
+                         URL (in {})", key, path.display())
+            })?;
             let try = |s: &str| {
                 let val = match table.get(s) {
                     Some(s) => s,
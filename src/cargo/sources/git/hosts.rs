@@ -0,0 +1,272 @@
+//! Per-host settings for git repositories, configured in `[git.url."<base>"]`
+//! tables much like git's own `url.<base>.insteadOf` and
+//! `http.<url>.extraHeader`:
+//!
+//! ```toml
+//! [git.url."https://gitlab.example.com/"]
+//! instead-of = ["ssh://git@gitlab.example.com/"]
+//! headers = ["JOB-TOKEN: ..."]
+//! ```
+//!
+//! URLs starting with one of the `instead-of` prefixes are rewritten to start
+//! with the base instead, the longest prefix winning if several match. The
+//! `headers` are sent along with every HTTP(S) request to URLs starting with
+//! the base.
+//!
+//! libgit2 can't send extra headers, so URLs which need them are handed to it
+//! with a `cargo+` prefix on their scheme, which is served by a transport
+//! of our own, see `register_transport`.
+
+use std::io::prelude::*;
+use std::io::{self, Cursor};
+use std::str;
+use std::sync::{Arc, Mutex};
+
+use curl::easy::{Easy, List};
+use git2;
+use git2::transport::{Service, SmartSubtransport, SmartSubtransportStream, Transport};
+
+use ops;
+use util::Config;
+use util::config::ConfigValue;
+use util::errors::CargoResult;
+
+/// Prefixed to the scheme of URLs which are fetched with our own transport.
+const SCHEME_PREFIX: &'static str = "cargo+";
+
+struct UrlConfig {
+    base: String,
+    instead_of: Vec<String>,
+    headers: Vec<String>,
+}
+
+fn load(config: &Config) -> CargoResult<Vec<UrlConfig>> {
+    let table = match config.get_table("git.url")? {
+        Some(table) => table,
+        None => return Ok(Vec::new()),
+    };
+    let mut urls = Vec::new();
+    for (base, value) in table.val.iter() {
+        let key = format!("git.url.\"{}\"", base);
+        let (table, _) = value.table(&key)?;
+        let list = |name: &str| -> CargoResult<Vec<String>> {
+            match table.get(name) {
+                Some(&ConfigValue::String(ref s, _)) => Ok(vec![s.clone()]),
+                Some(value) => {
+                    let list = value.list(&format!("{}.{}", key, name))?;
+                    Ok(list.iter().map(|&(ref s, _)| s.clone()).collect())
+                }
+                None => Ok(Vec::new()),
+            }
+        };
+        let headers = list("headers")?;
+        for header in headers.iter() {
+            if !header.contains(':') {
+                bail!("invalid header `{}` in `{}.headers` (in {}), expected \
+                       `Name: value`", header, key, value.definition_path().display())
+            }
+        }
+        urls.push(UrlConfig {
+            base: base.clone(),
+            instead_of: list("instead-of")?,
+            headers: headers,
+        });
+    }
+    Ok(urls)
+}
+
+/// The headers to send along with requests to `url`.
+fn headers_for<'a>(urls: &'a [UrlConfig], url: &str) -> Vec<&'a str> {
+    urls.iter()
+        .filter(|u| url.starts_with(&u.base[..]))
+        .flat_map(|u| u.headers.iter().map(|h| &h[..]))
+        .collect()
+}
+
+/// Applies the `instead-of` rules to `location`, and makes it use our own
+/// transport if there are headers to send along with it.
+pub fn rewrite(config: &Config, location: &str) -> CargoResult<String> {
+    let urls = load(config)?;
+    let mut best: Option<(&str, &str)> = None;
+    for u in urls.iter() {
+        for prefix in u.instead_of.iter() {
+            let longer = best.map(|(p, _)| prefix.len() > p.len()).unwrap_or(true);
+            if location.starts_with(&prefix[..]) && longer {
+                best = Some((&prefix[..], &u.base[..]));
+            }
+        }
+    }
+    let mut location = match best {
+        Some((prefix, base)) => format!("{}{}", base, &location[prefix.len()..]),
+        None => location.to_string(),
+    };
+    let http = location.starts_with("http://") || location.starts_with("https://");
+    if http && !headers_for(&urls, &location).is_empty() {
+        location = format!("{}{}", SCHEME_PREFIX, location);
+    }
+    Ok(location)
+}
+
+/// Registers the transport for the URLs `rewrite` prefixes with `cargo+`,
+/// which is the one from `git2-curl` along with the configured headers.
+///
+/// Like `git2::transport::register` this needs to be synchronized with all
+/// other uses of libgit2, so it should only be called at startup.
+pub unsafe fn register_transport(config: &Config) -> CargoResult<()> {
+    let urls = load(config)?;
+    if urls.iter().all(|u| u.headers.is_empty()) {
+        return Ok(())
+    }
+    let handle = Arc::new(Mutex::new(ops::http_handle(config)?));
+    let urls = Arc::new(urls);
+    for scheme in ["http", "https"].iter() {
+        let handle = handle.clone();
+        let urls = urls.clone();
+        git2::transport::register(&format!("{}{}", SCHEME_PREFIX, scheme), move |remote| {
+            Transport::smart(remote, true, HeaderTransport {
+                handle: handle.clone(),
+                urls: urls.clone(),
+            })
+        })?;
+    }
+    Ok(())
+}
+
+struct HeaderTransport {
+    handle: Arc<Mutex<Easy>>,
+    urls: Arc<Vec<UrlConfig>>,
+}
+
+struct HeaderSubtransport {
+    handle: Arc<Mutex<Easy>>,
+    headers: Vec<String>,
+    service: &'static str,
+    url: String,
+    method: &'static str,
+    reader: Option<Cursor<Vec<u8>>>,
+}
+
+impl SmartSubtransport for HeaderTransport {
+    fn action(&self, url: &str, action: Service)
+              -> Result<Box<SmartSubtransportStream>, git2::Error> {
+        let (service, path, method) = match action {
+            Service::UploadPackLs => {
+                ("upload-pack", "/info/refs?service=git-upload-pack", "GET")
+            }
+            Service::UploadPack => ("upload-pack", "/git-upload-pack", "POST"),
+            Service::ReceivePackLs => {
+                ("receive-pack", "/info/refs?service=git-receive-pack", "GET")
+            }
+            Service::ReceivePack => ("receive-pack", "/git-receive-pack", "POST"),
+        };
+        let base = url.trim_left_matches(SCHEME_PREFIX);
+        let headers = headers_for(&self.urls, base).iter().map(|h| h.to_string()).collect();
+        Ok(Box::new(HeaderSubtransport {
+            handle: self.handle.clone(),
+            headers: headers,
+            service: service,
+            url: format!("{}{}", base, path),
+            method: method,
+            reader: None,
+        }))
+    }
+
+    fn close(&self) -> Result<(), git2::Error> {
+        Ok(())
+    }
+}
+
+impl HeaderSubtransport {
+    fn err(&self, msg: String) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, msg)
+    }
+
+    fn execute(&mut self, data: &[u8]) -> io::Result<()> {
+        debug!("request to {}", self.url);
+        let mut h = self.handle.lock().unwrap();
+        h.url(&self.url)?;
+        h.follow_location(true)?;
+        match self.method {
+            "GET" => h.get(true)?,
+            _ => h.post(true)?,
+        }
+
+        let mut headers = List::new();
+        if data.is_empty() {
+            headers.append("Accept: */*")?;
+        } else {
+            h.post_fields_copy(data)?;
+            headers.append(&format!("Accept: application/x-git-{}-result",
+                                    self.service))?;
+            headers.append(&format!("Content-Type: application/x-git-{}-request",
+                                    self.service))?;
+        }
+        headers.append("Expect:")?;
+        for header in self.headers.iter() {
+            headers.append(header)?;
+        }
+        h.http_headers(headers)?;
+
+        let mut content_type = None;
+        let mut body = Vec::new();
+        {
+            let mut transfer = h.transfer();
+            transfer.header_function(|header| {
+                let header = match str::from_utf8(header) {
+                    Ok(s) => s,
+                    Err(..) => return true,
+                };
+                let mut parts = header.splitn(2, ':');
+                let name = parts.next().unwrap().trim().to_lowercase();
+                if name == "content-type" {
+                    content_type = parts.next().map(|v| v.trim().to_string());
+                }
+                true
+            })?;
+            transfer.write_function(|buf| {
+                body.extend_from_slice(buf);
+                Ok(buf.len())
+            })?;
+            transfer.perform()?;
+        }
+
+        let code = h.response_code()?;
+        if code != 200 {
+            return Err(self.err(format!("failed to receive HTTP 200 response from `{}`: \
+                                         got {}", self.url, code)))
+        }
+        let expected = match self.method {
+            "GET" => format!("application/x-git-{}-advertisement", self.service),
+            _ => format!("application/x-git-{}-result", self.service),
+        };
+        if content_type.as_ref() != Some(&expected) {
+            return Err(self.err(format!("expected a Content-Type header with `{}` \
+                                         but found `{}`", expected,
+                                        content_type.unwrap_or_default())))
+        }
+        self.reader = Some(Cursor::new(body));
+        Ok(())
+    }
+}
+
+impl Read for HeaderSubtransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.reader.is_none() {
+            self.execute(&[])?;
+        }
+        self.reader.as_mut().unwrap().read(buf)
+    }
+}
+
+impl Write for HeaderSubtransport {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.reader.is_none() {
+            self.execute(data)?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
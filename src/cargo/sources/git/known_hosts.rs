@@ -0,0 +1,217 @@
+//! Trust-on-first-use pinning of the host keys of the SSH servers git
+//! repositories are fetched from.
+//!
+//! libgit2 doesn't consult `~/.ssh/known_hosts`, so it would connect to
+//! whichever server answers for a host. Instead, the fingerprint of the key a
+//! host presents the first time something is fetched from it is recorded in
+//! `$CARGO_HOME/git/known_hosts`, one `<host> <sha1 of the key>` per line, and
+//! every later fetch fails if the host presents a key which isn't recorded for
+//! it. The keys github.com and gitlab.com publish are known from the start.
+//!
+//! Only SHA-1 fingerprints of host keys are available from libgit2, which is
+//! why they're what's recorded.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use git2::Cert;
+use hex::ToHex;
+
+use util::{Config, paths};
+use util::errors::{CargoResult, CargoResultExt};
+
+/// The published host keys of the most common hosts, as the SHA-1 of the key,
+/// which is what libgit2 provides. Their SHA-256 fingerprints are in comments.
+const SEEDED: &'static [(&'static str, &'static str)] = &[
+    // RSA, SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s
+    ("github.com", "6f4c60375018bae0918e37d9162bc15ba40e6365"),
+    // ECDSA, SHA256:p2QAMXNIC1TJYWeIOttrVc98/R1BUFWu3/LiyKgUfQM
+    ("github.com", "3358ab5dd3e306c461c840f7487e93b697e30600"),
+    // Ed25519, SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU
+    ("github.com", "e9619e2ed56c2f2a71729db80bacc2ce9ccce8d4"),
+    // RSA, SHA256:ROQFvPThGrW4RuWLoL9tq9I9zJ42fK4XywyRtbOz/EQ
+    ("gitlab.com", "b5fa4b9508430c53f7c8675ec13bc73715a601d9"),
+    // ECDSA, SHA256:HbW3g8zUjNSksFbqTiUWPWg2Bq1x8xdGUrliXFzSnUw
+    ("gitlab.com", "4db6b9ab0209fcde106cbf0fc4560ad063a962ad"),
+    // Ed25519, SHA256:eUXGGm1YGsMAS7vkcx6JOJdOGHPem5gQp4taiCfCLB8
+    ("gitlab.com", "51f9a9fbcffc06f4cb194414eea6ccfad2f6202b"),
+];
+
+/// A host which presented a key other than the ones recorded for it.
+struct Mismatch {
+    host: String,
+    actual: String,
+    expected: Vec<String>,
+}
+
+/// The host keys known to Cargo, along with what happened to them during a
+/// fetch, which libgit2 only lets us observe from a callback.
+pub struct KnownHosts {
+    path: PathBuf,
+    hosts: HashMap<String, Vec<String>>,
+    /// Hosts seen for the first time, which get recorded by `save`
+    new: RefCell<Vec<(String, String)>>,
+    /// The host whose key didn't match, if any
+    mismatch: RefCell<Option<Mismatch>>,
+}
+
+impl KnownHosts {
+    pub fn load(config: &Config) -> CargoResult<KnownHosts> {
+        let path = config.git_path().into_path_unlocked().join("known_hosts");
+        let mut hosts = HashMap::new();
+        for &(host, fingerprint) in SEEDED {
+            hosts.entry(host.to_string()).or_insert_with(Vec::new)
+                 .push(fingerprint.to_string());
+        }
+        if path.exists() {
+            let contents = paths::read(&path)?;
+            for line in contents.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue
+                }
+                let mut parts = line.split_whitespace();
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(host), Some(fingerprint), None) => {
+                        hosts.entry(host.to_lowercase()).or_insert_with(Vec::new)
+                             .push(fingerprint.to_lowercase());
+                    }
+                    _ => bail!("invalid line `{}` in `{}`, expected \
+                                `<host> <fingerprint>`", line, path.display()),
+                }
+            }
+        }
+        Ok(KnownHosts {
+            path: path,
+            hosts: hosts,
+            new: RefCell::new(Vec::new()),
+            mismatch: RefCell::new(None),
+        })
+    }
+
+    /// The certificate check callback for libgit2: whether the connection to
+    /// `host`, which presented `cert`, may go ahead.
+    pub fn check(&self, cert: &Cert, host: &str) -> bool {
+        let fingerprint = match cert.as_hostkey().and_then(|k| k.hash_sha1()) {
+            Some(hash) => hash.to_hex(),
+            None => return false,
+        };
+        let host = host.to_lowercase();
+        match self.hosts.get(&host) {
+            Some(known) => {
+                if known.contains(&fingerprint) {
+                    return true
+                }
+                *self.mismatch.borrow_mut() = Some(Mismatch {
+                    host: host,
+                    actual: fingerprint,
+                    expected: known.clone(),
+                });
+                false
+            }
+            None => {
+                let mut new = self.new.borrow_mut();
+                if !new.iter().any(|&(ref h, _)| *h == host) {
+                    new.push((host, fingerprint));
+                }
+                true
+            }
+        }
+    }
+
+    /// Fails if a host presented a key other than the ones recorded for it,
+    /// and otherwise records the keys of the hosts seen for the first time.
+    /// This is to be called once the connection is over, whether or not it
+    /// succeeded.
+    pub fn finish(&self, config: &Config) -> CargoResult<()> {
+        if let Some(mismatch) = self.mismatch() {
+            bail!("{}", mismatch)
+        }
+        self.save(config)
+    }
+
+    /// The error to fail the fetch with if a host presented a key other than
+    /// the ones recorded for it.
+    fn mismatch(&self) -> Option<String> {
+        self.mismatch.borrow().as_ref().map(|m| {
+            format!("\
+the host key of `{host}` doesn't match the one recorded for it
+
+expected: {expected}
+actual:   {actual}
+
+this could be indicative of a few possible situations:
+
+    * someone is intercepting the connection to `{host}`
+    * the host's key was replaced
+
+if it's known that the key was replaced, put `{host} {actual}` in place of the
+old key in `{path}`",
+                    host = m.host,
+                    expected = m.expected.join(", "),
+                    actual = m.actual,
+                    path = self.path.display())
+        })
+    }
+
+    /// Records the keys of the hosts seen for the first time.
+    fn save(&self, config: &Config) -> CargoResult<()> {
+        let new = self.new.borrow();
+        if new.is_empty() {
+            return Ok(())
+        }
+        (|| -> CargoResult<()> {
+            fs::create_dir_all(self.path.parent().unwrap())?;
+            let mut file = OpenOptions::new().create(true).append(true)
+                                             .open(&self.path)?;
+            for &(ref host, ref fingerprint) in new.iter() {
+                writeln!(file, "{} {}", host, fingerprint)?;
+            }
+            Ok(())
+        })().chain_err(|| {
+            format!("failed to record host keys in `{}`", self.path.display())
+        })?;
+        for &(ref host, ref fingerprint) in new.iter() {
+            config.shell().status("Trusting",
+                                  format!("host key of `{}` (SHA-1 {}) from now on",
+                                          host, fingerprint))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `url` is fetched over SSH, either as an `ssh://` URL or in the
+/// `[user@]host:path` form git accepts.
+pub fn is_ssh(url: &str) -> bool {
+    if url.starts_with("ssh://") || url.starts_with("git+ssh://") ||
+       url.starts_with("ssh+git://") {
+        return true
+    }
+    if url.contains("://") {
+        return false
+    }
+    match (url.find(':'), url.find('/')) {
+        (Some(colon), Some(slash)) => colon < slash,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_ssh;
+
+    #[test]
+    fn ssh_urls() {
+        assert!(is_ssh("ssh://git@github.com/rust-lang/cargo"));
+        assert!(is_ssh("git@github.com:rust-lang/cargo"));
+        assert!(is_ssh("github.com:cargo"));
+        assert!(!is_ssh("https://github.com/rust-lang/cargo"));
+        assert!(!is_ssh("cargo+https://github.com/rust-lang/cargo"));
+        assert!(!is_ssh("file:///tmp/cargo"));
+        assert!(!is_ssh("/tmp/cargo:repo"));
+    }
+}
@@ -1,4 +1,8 @@
 pub use self::utils::{GitRemote, GitDatabase, GitCheckout, GitRevision, fetch};
-pub use self::source::{GitSource, canonicalize_url};
+pub use self::utils::{set_checkout_readonly, FetchStrategy};
+pub use self::source::{GitSource, canonicalize_url, ident, to_url};
+pub use self::hosts::register_transport;
+mod hosts;
+mod known_hosts;
 mod utils;
 mod source;
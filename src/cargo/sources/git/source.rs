@@ -5,11 +5,12 @@ use url::Url;
 use core::source::{Source, SourceId};
 use core::GitReference;
 use core::{Package, PackageId, Summary, Registry, Dependency};
-use util::Config;
+use util::{signature, CacheLockMode, Config, ToUrl};
 use util::errors::{CargoError, CargoResult};
+use util::cache_manifest::CacheEntry;
 use util::hex::short_hash;
 use sources::PathSource;
-use sources::git::utils::{GitRemote, GitRevision};
+use sources::git::utils::{GitDatabase, GitRemote, GitRevision, FetchStrategy};
 
 /* TODO: Refactor GitSource to delegate to a PathSource
  */
@@ -23,6 +24,16 @@ pub struct GitSource<'cfg> {
     config: &'cfg Config,
 }
 
+/// How revisions are materialized in the `checkouts` folder, configured with
+/// the `git.checkout-strategy` key.
+#[derive(PartialEq)]
+enum CheckoutStrategy {
+    /// A working checkout cloned from the database, the default
+    Checkout,
+    /// `git archive <rev> | tar -x`, leaving no `.git` directory behind
+    Archive,
+}
+
 impl<'cfg> GitSource<'cfg> {
     pub fn new(source_id: &SourceId,
                config: &'cfg Config) -> CargoResult<GitSource<'cfg>> {
@@ -51,6 +62,60 @@ impl<'cfg> GitSource<'cfg> {
 
     pub fn url(&self) -> &Url { self.remote.url() }
 
+    fn checkout_strategy(&self) -> CargoResult<CheckoutStrategy> {
+        let strategy = match self.config.get_string("git.checkout-strategy")? {
+            Some(s) => s,
+            None => return Ok(CheckoutStrategy::Checkout),
+        };
+        match &strategy.val[..] {
+            "checkout" => Ok(CheckoutStrategy::Checkout),
+            "archive" => Ok(CheckoutStrategy::Archive),
+            s => bail!("unknown git checkout strategy `{}` in {}, expected \
+                        `checkout` or `archive`", s, strategy.definition),
+        }
+    }
+
+    fn fetch_strategy(&self) -> CargoResult<FetchStrategy> {
+        let strategy = match self.config.get_string("git.fetch-strategy")? {
+            Some(s) => s,
+            None => return Ok(FetchStrategy::Mirror),
+        };
+        match &strategy.val[..] {
+            "mirror" => Ok(FetchStrategy::Mirror),
+            "minimal" => Ok(FetchStrategy::Minimal),
+            s => bail!("unknown git fetch strategy `{}` in {}, expected \
+                        `mirror` or `minimal`", s, strategy.definition),
+        }
+    }
+
+    /// Checks the signature of the tag this source is pinned to, see
+    /// `util::signature`. Only annotated tags can be signed, so dependencies
+    /// on branches or revisions are never signed.
+    ///
+    /// Once locked, the source is checked out at `rev` rather than at the
+    /// tag, so the tag has to point at `rev` for its signature to vouch for
+    /// what's checked out.
+    fn verify_signature(&self, repo: &GitDatabase, rev: &GitRevision) -> CargoResult<()> {
+        let tag = match self.source_id.git_reference() {
+            Some(&GitReference::Tag(ref tag)) => tag,
+            _ => {
+                let what = format!("git repository `{}`, which isn't pinned \
+                                    to a tag,", self.remote.url());
+                return signature::verify(self.config, &what, &[], None)
+            }
+        };
+        let tagged = repo.rev_for(&GitReference::Tag(tag.clone()))?;
+        if tagged != *rev {
+            bail!("tag `{}` of `{}` points at {} rather than at the locked \
+                   revision {}", tag, self.remote.url(), tagged, rev)
+        }
+        let what = format!("tag `{}` of `{}`", tag, self.remote.url());
+        match repo.tag_signature(tag, self.config)? {
+            Some((data, sig)) => signature::verify(self.config, &what, &data, Some(&sig)),
+            None => signature::verify(self.config, &what, &[], None),
+        }
+    }
+
     pub fn read_packages(&mut self) -> CargoResult<Vec<Package>> {
         if self.path_source.is_none() {
             self.update()?;
@@ -59,7 +124,9 @@ impl<'cfg> GitSource<'cfg> {
     }
 }
 
-fn ident(url: &Url) -> CargoResult<String> {
+/// Returns the name of the directory used for `url` underneath both the `db`
+/// and `checkouts` folders of the git cache.
+pub fn ident(url: &Url) -> CargoResult<String> {
     let url = canonicalize_url(url)?;
     let ident = url.path_segments().and_then(|mut s| s.next_back()).unwrap_or("");
 
@@ -72,6 +139,45 @@ fn ident(url: &Url) -> CargoResult<String> {
     Ok(format!("{}-{}", ident, short_hash(&url)))
 }
 
+/// Parses the location of a git repository.
+///
+/// Along with regular URLs like `https://`, `ssh://`, `git://` and `file://`
+/// this accepts the scp-like syntax understood by git itself, e.g.
+/// `git@github.com:rust-lang/cargo.git`, which is turned into the equivalent
+/// `ssh://git@github.com/rust-lang/cargo.git`. That's also how such
+/// locations are recorded in lock files.
+pub fn to_url(location: &str) -> CargoResult<Url> {
+    if let Some(url) = scp_like_url(location) {
+        return Ok(url)
+    }
+    location.to_url()
+}
+
+/// Recognizes `[user@]host:path`, following git's rules: there's no `://`,
+/// and the colon comes before any slash. Single letter hosts are Windows drive
+/// letters rather than hosts.
+fn scp_like_url(location: &str) -> Option<Url> {
+    if location.contains("://") {
+        return None
+    }
+    let colon = match location.find(':') {
+        Some(i) => i,
+        None => return None,
+    };
+    if location[..colon].contains('/') {
+        return None
+    }
+    let (user, host) = match location[..colon].rfind('@') {
+        Some(i) => (&location[..i + 1], &location[i + 1..colon]),
+        None => ("", &location[..colon]),
+    };
+    if host.len() < 2 {
+        return None
+    }
+    let path = location[colon + 1..].trim_left_matches('/');
+    Url::parse(&format!("ssh://{}{}/{}", user, host, path)).ok()
+}
+
 // Some hacks and heuristics for making equivalent URLs hash the same
 pub fn canonicalize_url(url: &Url) -> CargoResult<Url> {
     let mut url = url.clone();
@@ -92,10 +198,13 @@ pub fn canonicalize_url(url: &Url) -> CargoResult<Url> {
     // differently, and we're gonna be hashing them. This wants a more
     // general solution, and also we're almost certainly not using the
     // same case conversion rules that GitHub does. (#84)
+    //
+    // The URL is rebuilt rather than having its scheme changed as `ssh://`
+    // can't be turned into `https://` in place, and the `git@` user of ssh
+    // URLs is dropped so that all of them end up the same.
     if url.host_str() == Some("github.com") {
-        url.set_scheme("https").unwrap();
         let path = url.path().to_lowercase();
-        url.set_path(&path);
+        url = format!("https://github.com{}", path).to_url()?;
     }
 
     // Repos generally can be accessed with or w/o '.git'
@@ -138,6 +247,17 @@ impl<'cfg> Registry for GitSource<'cfg> {
     fn requires_precise(&self) -> bool {
         true
     }
+
+    fn package_names(&mut self, _dep: &Dependency) -> Vec<String> {
+        let packages = match self.path_source {
+            Some(ref src) => src.read_packages().unwrap_or_default(),
+            None => return Vec::new(),
+        };
+        let mut names = packages.iter().map(|p| p.name().to_string())
+                                .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
 }
 
 impl<'cfg> Source for GitSource<'cfg> {
@@ -146,46 +266,89 @@ impl<'cfg> Source for GitSource<'cfg> {
     }
 
     fn update(&mut self) -> CargoResult<()> {
-        let lock = self.config.git_path()
-            .open_rw(".cargo-lock-git", self.config, "the git checkouts")?;
+        // The cache is only locked for downloads once it turns out something
+        // has to be fetched or checked out.
+        let mut _lock = None;
+        let git_path = self.config.git_path().into_path_unlocked();
 
-        let db_path = lock.parent().join("db").join(&self.ident);
+        let db_path = git_path.join("db").join(&self.ident);
 
         // Resolve our reference to an actual revision, and check if the
         // database already has that revision. If it does, we just load a
         // database pinned at that revision, and if we don't we issue an update
         // to try to find the revision.
         let actual_rev = self.remote.rev_for(&db_path, &self.reference);
-        let should_update = actual_rev.is_err() ||
+        let in_database = actual_rev.is_ok();
+        let should_update = !in_database ||
                             self.source_id.precise().is_none();
 
         let (repo, actual_rev) = if should_update {
+            _lock = Some(self.config.acquire_package_cache_lock(CacheLockMode::Download)?);
             self.config.shell().status("Updating",
                 format!("git repository `{}`", self.remote.url()))?;
 
             trace!("updating git source `{:?}`", self.remote);
 
-            let repo = self.remote.checkout(&db_path, self.config)?;
+            // Locked revisions are most likely found on the branch or tag the
+            // dependency asks for, which is a lot more likely to be fetchable
+            // by itself than the revision.
+            let declared = self.source_id.git_reference().unwrap();
+            let refs = if *declared == self.reference {
+                vec![declared]
+            } else {
+                vec![declared, &self.reference]
+            };
+            let repo = self.remote.checkout_for(&db_path, &refs, &self.reference,
+                                                self.fetch_strategy()?, self.config)?;
             let rev = repo.rev_for(&self.reference).map_err(CargoError::into_internal)?;
             (repo, rev)
         } else {
             (self.remote.db_at(&db_path)?, actual_rev.unwrap())
         };
 
+        if signature::enabled(self.config)? {
+            self.verify_signature(&repo, &actual_rev)?;
+        }
+
         // Don’t use the full hash,
         // to contribute less to reaching the path length limit on Windows:
         // https://github.com/servo/servo/pull/14397
         let short_id = repo.to_short_id(actual_rev.clone()).unwrap();
 
-        let checkout_path = lock.parent().join("checkouts")
+        let checkout_path = git_path.join("checkouts")
             .join(&self.ident).join(short_id.as_str());
+        if _lock.is_none() && !checkout_path.join(".cargo-ok").exists() {
+            _lock = Some(self.config.acquire_package_cache_lock(CacheLockMode::Download)?);
+        }
 
         // Copy the database to the checkout location. After this we could drop
         // the lock on the database as we no longer needed it, but we leave it
         // in scope so the destructors here won't tamper with too much.
         // Checkout is immutable, so we don't need to protect it with a lock once
         // it is created.
-        repo.copy_to(actual_rev.clone(), &checkout_path, self.config)?;
+        //
+        // Dependencies pinned to a tag or exact revision that the shared
+        // database already had don't need a working checkout at all, so the
+        // tree is exported straight out of the database instead. An existing
+        // working checkout is left alone though.
+        let pinned = match *self.source_id.git_reference().unwrap() {
+            GitReference::Tag(..) | GitReference::Rev(..) => true,
+            GitReference::Branch(..) => false,
+        };
+        let strategy = self.checkout_strategy()?;
+        if strategy == CheckoutStrategy::Archive && repo.can_export(&actual_rev)? {
+            repo.archive_to(&actual_rev, &checkout_path, self.config)?;
+        } else if pinned && in_database && !checkout_path.join(".git").exists() &&
+                  repo.can_export(&actual_rev)? {
+            repo.export_to(&actual_rev, &checkout_path, self.config)?;
+        } else {
+            repo.copy_to(actual_rev.clone(), &checkout_path, self.config)?;
+        }
+
+        let url = self.remote.url().as_str();
+        self.config.record_cache_use(&db_path, CacheEntry::new("git-db", url, None));
+        self.config.record_cache_use(&checkout_path, CacheEntry::new(
+            "git-checkout", url, Some(&actual_rev.to_string())));
 
         let source_id = self.source_id.with_precise(Some(actual_rev.to_string()));
         let path_source = PathSource::new_recursive(&checkout_path,
@@ -213,7 +376,7 @@ impl<'cfg> Source for GitSource<'cfg> {
 #[cfg(test)]
 mod test {
     use url::Url;
-    use super::ident;
+    use super::{ident, to_url};
     use util::ToUrl;
 
     #[test]
@@ -256,6 +419,29 @@ mod test {
         assert_eq!(ident1, ident2);
     }
 
+    #[test]
+    fn test_canonicalize_idents_scp_like_urls() {
+        let ident1 = ident(&url("https://github.com/PistonDevelopers/piston")).unwrap();
+        let ident2 = ident(&to_url("git@github.com:PistonDevelopers/piston.git").unwrap()).unwrap();
+        let ident3 = ident(&url("ssh://git@github.com/PistonDevelopers/piston")).unwrap();
+        assert_eq!(ident1, ident2);
+        assert_eq!(ident1, ident3);
+    }
+
+    #[test]
+    fn test_scp_like_urls() {
+        assert_eq!(to_url("git@example.com:org/repo.git").unwrap().as_str(),
+                   "ssh://git@example.com/org/repo.git");
+        assert_eq!(to_url("example.com:/srv/repo").unwrap().as_str(),
+                   "ssh://example.com/srv/repo");
+        assert_eq!(to_url("file:///srv/repo").unwrap().as_str(),
+                   "file:///srv/repo");
+        // Windows paths and URLs with a scheme aren't scp-like.
+        assert_ne!(to_url("C:/srv/repo").unwrap().scheme(), "ssh");
+        assert_eq!(to_url("ssh://git@example.com:2222/repo").unwrap().port(),
+                   Some(2222));
+    }
+
     #[test]
     fn test_canonicalize_cannot_be_a_base_urls() {
         assert!(ident(&url("github.com:PistonDevelopers/piston")).is_err());
@@ -1,18 +1,25 @@
 use std::env;
 use std::fmt;
 use std::fs::{self, File};
+use std::io::prelude::*;
 use std::mem;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
 
 use curl::easy::{Easy, List};
 use git2::{self, ObjectType};
 use serde::ser::{self, Serialize};
+use tar::Archive;
+use tempdir::TempDir;
 use url::Url;
 
 use core::GitReference;
-use util::{ToUrl, internal, Config, network};
-use util::errors::{CargoResult, CargoResultExt, CargoError};
+use ops::human_readable_bytes;
+use sources::git::hosts;
+use sources::git::known_hosts::{self, KnownHosts};
+use util::{ToUrl, internal, job, process, process_error, Config, ProcessBuilder, network, paths};
+use util::errors::{CargoResult, CargoResultExt, CargoError, CargoErrorKind};
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct GitRevision(git2::Oid);
@@ -44,12 +51,30 @@ impl GitShortID {
     }
 }
 
+/// Which references are fetched into git databases, configured with the
+/// `git.fetch-strategy` key.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FetchStrategy {
+    /// Every branch and tag, the default
+    Mirror,
+    /// Only the branch, tag or revision a dependency is pinned to, falling
+    /// back to everything if that doesn't turn up the revision
+    Minimal,
+}
+
+/// Fetches every branch of a repository, used by `FetchStrategy::Mirror`.
+const MIRROR_REFSPEC: &'static str = "refs/heads/*:refs/heads/*";
+
 /// `GitRemote` represents a remote repository. It gets cloned into a local
 /// `GitDatabase`.
 #[derive(PartialEq, Clone, Debug, Serialize)]
 pub struct GitRemote {
     #[serde(serialize_with = "serialize_str")]
     url: Url,
+    /// Set for repositories on the local filesystem, which are then handed to
+    /// libgit2 as is rather than as a `file://` URL.
+    #[serde(skip_serializing)]
+    path: Option<PathBuf>,
 }
 
 /// `GitDatabase` is a local clone of a remote repository's database. Multiple
@@ -78,13 +103,31 @@ pub struct GitCheckout<'a> {
 
 impl GitRemote {
     pub fn new(url: &Url) -> GitRemote {
-        GitRemote { url: url.clone() }
+        // `file://` URLs are fetched straight from their path.
+        GitRemote { url: url.clone(), path: url.to_file_path().ok() }
+    }
+
+    /// A remote for the repository at `path`, typically a bare repository
+    /// used as a mirror or a test fixture.
+    ///
+    /// Its `url` is the equivalent `file://` URL, so it's identified the same
+    /// way as a remote created from that URL.
+    pub fn from_path(path: &Path) -> CargoResult<GitRemote> {
+        Ok(GitRemote { url: path.to_url()?, path: Some(path.to_path_buf()) })
     }
 
     pub fn url(&self) -> &Url {
         &self.url
     }
 
+    /// The location of the repository as understood by libgit2.
+    fn location(&self) -> &str {
+        match self.path.as_ref().and_then(|p| p.to_str()) {
+            Some(path) => path,
+            None => self.url.as_str(),
+        }
+    }
+
     pub fn rev_for(&self, path: &Path, reference: &GitReference)
                    -> CargoResult<GitRevision> {
         let db = self.db_at(path)?;
@@ -92,15 +135,39 @@ impl GitRemote {
     }
 
     pub fn checkout(&self, into: &Path, cargo_config: &Config) -> CargoResult<GitDatabase> {
+        self.checkout_with(into, &[], None, cargo_config)
+    }
+
+    /// Like `checkout`, but with `FetchStrategy::Minimal` each of `refs` is
+    /// fetched by itself in turn until `want` can be resolved. Everything is
+    /// only fetched if none of them turns it up, e.g. because the server
+    /// doesn't allow fetching revisions by their id.
+    pub fn checkout_for(&self,
+                        into: &Path,
+                        refs: &[&GitReference],
+                        want: &GitReference,
+                        strategy: FetchStrategy,
+                        cargo_config: &Config) -> CargoResult<GitDatabase> {
+        match strategy {
+            FetchStrategy::Mirror => self.checkout(into, cargo_config),
+            FetchStrategy::Minimal => self.checkout_with(into, refs, Some(want), cargo_config),
+        }
+    }
+
+    fn checkout_with(&self,
+                     into: &Path,
+                     refs: &[&GitReference],
+                     want: Option<&GitReference>,
+                     cargo_config: &Config) -> CargoResult<GitDatabase> {
         let repo = match git2::Repository::open(into) {
             Ok(mut repo) => {
-                self.fetch_into(&mut repo, cargo_config).chain_err(|| {
+                self.fetch_into(&mut repo, refs, want, cargo_config).chain_err(|| {
                     format!("failed to fetch into {}", into.display())
                 })?;
                 repo
             }
             Err(..) => {
-                self.clone_into(into, cargo_config).chain_err(|| {
+                self.clone_into(into, refs, want, cargo_config).chain_err(|| {
                     format!("failed to clone into: {}", into.display())
                 })?
             }
@@ -113,6 +180,48 @@ impl GitRemote {
         })
     }
 
+    /// Lists the references advertised by the remote along with the revision
+    /// each one points at, like `git ls-remote`, without fetching anything.
+    pub fn ls_remote(&self, cargo_config: &Config)
+                     -> CargoResult<Vec<(String, GitRevision)>> {
+        if !cargo_config.network_allowed() {
+            bail!("attempting to query a git repository, but --frozen \
+                   was specified")
+        }
+        // libgit2 needs a repository to hang an anonymous remote off of, but
+        // nothing is ever written into it.
+        let tmp = TempDir::new("cargo-ls-remote")?;
+        let repo = git2::Repository::init_bare(tmp.path())?;
+        let location = hosts::rewrite(cargo_config, self.location())?;
+        let known_hosts = if known_hosts::is_ssh(&location) {
+            Some(KnownHosts::load(cargo_config)?)
+        } else {
+            None
+        };
+        // Connecting needs the same credentials and host key checks as a
+        // fetch from the same repository would.
+        with_authentication(self.url.as_str(), &repo.config()?, |f| {
+            let mut remote = repo.remote_anonymous(&location)?;
+            let result = network::with_retry(cargo_config, || {
+                let mut cb = git2::RemoteCallbacks::new();
+                cb.credentials(&mut *f);
+                if let Some(ref known_hosts) = known_hosts {
+                    cb.certificate_check(move |cert, host| known_hosts.check(cert, host));
+                }
+                let connection = remote.connect_auth(git2::Direction::Fetch,
+                                                     Some(cb), None)?;
+                let heads = connection.list()?.iter().map(|head| {
+                    (head.name().to_string(), GitRevision(head.oid()))
+                }).collect();
+                Ok(heads)
+            });
+            if let Some(ref known_hosts) = known_hosts {
+                known_hosts.finish(cargo_config)?;
+            }
+            result
+        }).chain_err(|| format!("failed to list references of `{}`", self.url))
+    }
+
     pub fn db_at(&self, db_path: &Path) -> CargoResult<GitDatabase> {
         let repo = git2::Repository::open(db_path)?;
         Ok(GitDatabase {
@@ -122,19 +231,38 @@ impl GitRemote {
         })
     }
 
-    fn fetch_into(&self, dst: &mut git2::Repository, cargo_config: &Config) -> CargoResult<()> {
-        // Create a local anonymous remote in the repository to fetch the url
-        let refspec = "refs/heads/*:refs/heads/*";
-        fetch(dst, &self.url, refspec, cargo_config)
+    fn fetch_into(&self,
+                  dst: &mut git2::Repository,
+                  refs: &[&GitReference],
+                  want: Option<&GitReference>,
+                  cargo_config: &Config) -> CargoResult<()> {
+        if let Some(want) = want {
+            for refspec in refs.iter().filter_map(|r| minimal_refspec(r)) {
+                let fetched = fetch_from(dst, &self.url, self.location(), &refspec,
+                                         git2::AutotagOption::None, cargo_config);
+                match fetched {
+                    Ok(()) if resolve(dst, want).is_ok() => return Ok(()),
+                    Ok(()) => debug!("fetching {} didn't turn up {:?}", refspec, want),
+                    Err(e) => debug!("failed to fetch {}: {}", refspec, e),
+                }
+            }
+        }
+        fetch_from(dst, &self.url, self.location(), MIRROR_REFSPEC,
+                   git2::AutotagOption::All, cargo_config)
     }
 
-    fn clone_into(&self, dst: &Path, cargo_config: &Config) -> CargoResult<git2::Repository> {
+    fn clone_into(&self,
+                  dst: &Path,
+                  refs: &[&GitReference],
+                  want: Option<&GitReference>,
+                  cargo_config: &Config) -> CargoResult<git2::Repository> {
         if fs::metadata(&dst).is_ok() {
             fs::remove_dir_all(dst)?;
         }
+        paths::check_disk_space(dst, 0)?;
         fs::create_dir_all(dst)?;
         let mut repo = git2::Repository::init_bare(dst)?;
-        fetch(&mut repo, &self.url, "refs/heads/*:refs/heads/*", cargo_config)?;
+        self.fetch_into(&mut repo, refs, want, cargo_config)?;
         Ok(repo)
     }
 }
@@ -150,50 +278,166 @@ impl GitDatabase {
             Ok(repo) => {
                 let mut checkout = GitCheckout::new(dest, self, rev, repo);
                 if !checkout.is_fresh() {
+                    set_checkout_readonly(dest, false)?;
                     checkout.fetch(cargo_config)?;
-                    checkout.reset()?;
+                    checkout.fetch_pinned()?;
+                    checkout.reset(cargo_config)?;
                     assert!(checkout.is_fresh());
+                } else {
+                    checkout.update_submodules(cargo_config)?;
                 }
                 checkout
             }
-            Err(..) => GitCheckout::clone_into(dest, self, rev)?,
+            Err(..) => GitCheckout::clone_into(dest, self, rev, cargo_config)?,
         };
-        checkout.update_submodules(cargo_config)?;
         Ok(checkout)
     }
 
-    pub fn rev_for(&self, reference: &GitReference) -> CargoResult<GitRevision> {
-        let id = match *reference {
-            GitReference::Tag(ref s) => {
-                (|| -> CargoResult<git2::Oid> {
-                    let refname = format!("refs/tags/{}", s);
-                    let id = self.repo.refname_to_id(&refname)?;
-                    let obj = self.repo.find_object(id, None)?;
-                    let obj = obj.peel(ObjectType::Commit)?;
-                    Ok(obj.id())
-                })().chain_err(|| {
-                    format!("failed to find tag `{}`", s)
-                })?
-            }
-            GitReference::Branch(ref s) => {
-                (|| {
-                    let b = self.repo.find_branch(s, git2::BranchType::Local)?;
-                    b.get().target().ok_or_else(|| {
-                        CargoError::from(format!("branch `{}` did not have a target", s))
+    /// Writes out the tree of `rev` into `dest` the same way `git archive`
+    /// would, without creating a repository or working checkout there.
+    ///
+    /// Like checkouts, a `.cargo-ok` file marks a complete export and holds
+    /// the revision that was exported, so this is a noop if `dest` is already
+    /// an export of `rev`.
+    pub fn export_to(&self, rev: &GitRevision, dest: &Path, cargo_config: &Config)
+                     -> CargoResult<()> {
+        if is_exported(rev, dest) {
+            return Ok(())
+        }
+        info!("export {} at {} to {}", self.path.display(), rev, dest.display());
+        let tree = self.repo.find_commit(rev.0)?.tree()?;
+        export_with(rev, dest, cargo_config, |tmp| {
+            export_tree(&self.repo, &tree, tmp).chain_err(|| {
+                format!("failed to export revision {} into {}", rev, dest.display())
+            })
+        })
+    }
+
+    /// Materializes the tree of `rev` at `dest` by piping `git archive`
+    /// through `tar`, so no `.git` directory ends up in the checkout.
+    ///
+    /// This requires `git` to be installed. The result is marked complete in
+    /// the same way as `export_to`.
+    pub fn archive_to(&self, rev: &GitRevision, dest: &Path, cargo_config: &Config)
+                      -> CargoResult<()> {
+        if is_exported(rev, dest) {
+            return Ok(())
+        }
+        info!("archive {} at {} to {}", self.path.display(), rev, dest.display());
+        let mut cmd = git_command(cargo_config)?;
+        cmd.arg("archive").arg("--format=tar").arg(rev.to_string()).cwd(&self.path);
+
+        export_with(rev, dest, cargo_config, |tmp| {
+            // The archive is unpacked as `git` writes it rather than being
+            // buffered in memory, since repositories can be arbitrarily large.
+            let mut child = cmd.build_command()
+                               .stdin(Stdio::null())
+                               .stdout(Stdio::piped())
+                               .spawn()
+                               .chain_err(|| format!("could not execute process `{}`", cmd))?;
+            let unpacked = {
+                let _tracked = job::track(&child);
+                let unpacked = Archive::new(child.stdout.take().unwrap()).unpack(tmp);
+                let exit = child.wait().chain_err(|| {
+                    format!("failed to wait for `{}`", cmd)
+                })?;
+                if !exit.success() {
+                    let err: CargoError = CargoErrorKind::ProcessErrorKind(process_error(
+                        &format!("process didn't exit successfully: `{}`", cmd),
+                        Some(&exit), None)).into();
+                    return Err(err).chain_err(|| {
+                        format!("failed to archive revision {} of `{}`", rev, self.remote.url())
                     })
-                })().chain_err(|| {
-                    format!("failed to find branch `{}`", s)
-                })?
-            }
-            GitReference::Rev(ref s) => {
-                let obj = self.repo.revparse_single(s)?;
-                match obj.as_tag() {
-                    Some(tag) => tag.target_id(),
-                    None => obj.id(),
                 }
-            }
+                unpacked
+            };
+            unpacked.chain_err(|| {
+                format!("failed to unpack revision {} into {}", rev, dest.display())
+            })
+        })
+    }
+
+    /// Compares the files of an export at `dest` against the tree of `rev`.
+    fn export_matches(&self, rev: &GitRevision, dest: &Path) -> bool {
+        return match self.repo.find_commit(rev.0).and_then(|c| c.tree()) {
+            Ok(tree) => matches(&self.repo, &tree, dest),
+            Err(..) => false,
+        };
+
+        fn matches(repo: &git2::Repository, tree: &git2::Tree, dest: &Path)
+                   -> bool {
+            tree.iter().all(|entry| {
+                let path = match entry.name() {
+                    Some(name) => dest.join(name),
+                    None => return false,
+                };
+                match entry.kind() {
+                    Some(ObjectType::Tree) => {
+                        repo.find_tree(entry.id())
+                            .map(|t| matches(repo, &t, &path))
+                            .unwrap_or(false)
+                    }
+                    Some(ObjectType::Blob) if entry.filemode() == 0o120000 => {
+                        fs::symlink_metadata(&path).is_ok()
+                    }
+                    Some(ObjectType::Blob) => {
+                        let mut contents = Vec::new();
+                        let read = File::open(&path).and_then(|mut f| {
+                            f.read_to_end(&mut contents)
+                        });
+                        read.is_ok() && repo.find_blob(entry.id())
+                            .map(|b| b.content() == &contents[..])
+                            .unwrap_or(false)
+                    }
+                    _ => false,
+                }
+            })
+        }
+    }
+
+    /// Returns whether the tree of `rev` can be exported with `export_to` or
+    /// `archive_to`.
+    ///
+    /// Trees which contain submodules can't be, as their contents don't live
+    /// in this database.
+    pub fn can_export(&self, rev: &GitRevision) -> CargoResult<bool> {
+        let tree = self.repo.find_commit(rev.0)?.tree()?;
+        Ok(tree.get_name(".gitmodules").is_none())
+    }
+
+    /// Returns the signed contents and the signature of the annotated tag
+    /// `tag`, or `None` if it isn't signed.
+    ///
+    /// libgit2 can't hand out the raw tag object which was signed, so this is
+    /// read with `git cat-file`.
+    pub fn tag_signature(&self, tag: &str, cargo_config: &Config)
+                         -> CargoResult<Option<(Vec<u8>, String)>> {
+        let refname = format!("refs/tags/{}", tag);
+        let id = self.repo.refname_to_id(&refname)?;
+        // Lightweight tags are just a ref to a commit, there's nothing to sign.
+        if self.repo.find_tag(id).is_err() {
+            return Ok(None)
+        }
+        let output = git_command(cargo_config)?.arg("cat-file")
+                                               .arg("tag")
+                                               .arg(id.to_string())
+                                               .cwd(&self.path)
+                                               .exec_with_output()
+                                               .chain_err(|| {
+            format!("failed to read tag `{}` of `{}`", tag, self.remote.url())
+        })?;
+        let data = output.stdout;
+        let marker = b"-----BEGIN PGP SIGNATURE-----";
+        let start = match data.windows(marker.len()).position(|w| w == &marker[..]) {
+            Some(start) => start,
+            None => return Ok(None),
         };
-        Ok(GitRevision(id))
+        let signature = String::from_utf8_lossy(&data[start..]).into_owned();
+        Ok(Some((data[..start].to_vec(), signature)))
+    }
+
+    pub fn rev_for(&self, reference: &GitReference) -> CargoResult<GitRevision> {
+        resolve(&self.repo, reference)
     }
 
     pub fn to_short_id(&self, revision: GitRevision) -> CargoResult<GitShortID> {
@@ -205,6 +449,20 @@ impl GitDatabase {
         self.repo.revparse_single(reference)?;
         Ok(())
     }
+
+    /// Checks whether `dest` holds a complete checkout of `rev` whose tracked
+    /// files haven't been modified since it was created.
+    pub fn has_pristine_checkout(&self, rev: &GitRevision, dest: &Path) -> bool {
+        if !dest.join(".git").exists() {
+            return is_exported(rev, dest) && self.export_matches(rev, dest)
+        }
+        let repo = match git2::Repository::open(dest) {
+            Ok(repo) => repo,
+            Err(..) => return false,
+        };
+        let checkout = GitCheckout::new(dest, self, rev.clone(), repo);
+        checkout.is_fresh() && !checkout.is_dirty()
+    }
 }
 
 impl<'a> GitCheckout<'a> {
@@ -221,28 +479,46 @@ impl<'a> GitCheckout<'a> {
     }
 
     fn clone_into(into: &Path, database: &'a GitDatabase,
-                  revision: GitRevision)
+                  revision: GitRevision, cargo_config: &Config)
                   -> CargoResult<GitCheckout<'a>>
     {
-        let repo = GitCheckout::clone_repo(database.path(), into)?;
-        let checkout = GitCheckout::new(into, database, revision, repo);
-        checkout.reset()?;
-        Ok(checkout)
-    }
-
-    fn clone_repo(source: &Path, into: &Path) -> CargoResult<git2::Repository> {
         let dirname = into.parent().unwrap();
 
         fs::create_dir_all(&dirname).chain_err(|| {
             format!("Couldn't mkdir {}", dirname.display())
         })?;
 
+        // The checkout is built in a temporary directory next to its final
+        // location and only renamed into place once it's complete, so being
+        // interrupted never leaves a partial checkout at `into`. If anything
+        // fails along the way the temporary directory is removed again.
+        paths::check_disk_space(dirname, 0)?;
+        let name = into.file_name().and_then(|s| s.to_str()).unwrap_or("checkout");
+        let tmp = TempDir::new_in(dirname, &format!(".{}", name))?;
+        {
+            let repo = GitCheckout::clone_repo(database.path(), tmp.path())?;
+            let checkout = GitCheckout::new(tmp.path(), database,
+                                            revision.clone(), repo);
+            checkout.fetch_pinned()?;
+            checkout.reset(cargo_config)?;
+        }
+
         if fs::metadata(&into).is_ok() {
+            let _ = set_checkout_readonly(into, false);
             fs::remove_dir_all(into).chain_err(|| {
                 format!("Couldn't rmdir {}", into.display())
             })?;
         }
+        fs::rename(tmp.path(), into).chain_err(|| {
+            format!("Couldn't move checkout into {}", into.display())
+        })?;
+        tmp.into_path();
 
+        let repo = git2::Repository::open(into)?;
+        Ok(GitCheckout::new(into, database, revision, repo))
+    }
+
+    fn clone_repo(source: &Path, into: &Path) -> CargoResult<git2::Repository> {
         let url = source.to_url()?;
         let url = url.to_string();
         let repo = git2::Repository::clone(&url, into)
@@ -263,6 +539,17 @@ impl<'a> GitCheckout<'a> {
         }
     }
 
+    fn is_dirty(&self) -> bool {
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(false)
+            .include_ignored(false)
+            .exclude_submodules(true);
+        match self.repo.statuses(Some(&mut opts)) {
+            Ok(statuses) => !statuses.is_empty(),
+            Err(..) => true,
+        }
+    }
+
     fn fetch(&mut self, cargo_config: &Config) -> CargoResult<()> {
         info!("fetch {}", self.repo.path().display());
         let url = self.database.path.to_url()?;
@@ -271,7 +558,21 @@ impl<'a> GitCheckout<'a> {
         Ok(())
     }
 
-    fn reset(&self) -> CargoResult<()> {
+    /// Databases fetched with `FetchStrategy::Minimal` may only have the
+    /// revision under a tag or `refs/cargo`, neither of which cloning or
+    /// `fetch` pick up, so those are fetched as well if it's missing.
+    fn fetch_pinned(&self) -> CargoResult<()> {
+        if self.repo.find_object(self.revision.0, None).is_ok() {
+            return Ok(())
+        }
+        let url = self.database.path.to_url()?;
+        let mut remote = self.repo.remote_anonymous(url.as_str())?;
+        remote.fetch(&["+refs/tags/*:refs/tags/*", "+refs/cargo/*:refs/cargo/*"],
+                     None, None)?;
+        Ok(())
+    }
+
+    fn reset(&self, cargo_config: &Config) -> CargoResult<()> {
         // If we're interrupted while performing this reset (e.g. we die because
         // of a signal) Cargo needs to be sure to try to check out this repo
         // again on the next go-round.
@@ -279,12 +580,21 @@ impl<'a> GitCheckout<'a> {
         // To enable this we have a dummy file in our checkout, .cargo-ok, which
         // if present means that the repo has been successfully reset and is
         // ready to go. Hence if we start to do a reset, we make sure this file
-        // *doesn't* exist, and then once we're done we create the file.
+        // *doesn't* exist, and then once we're done, submodules included, we
+        // create the file.
+        //
+        // A read-only checkout has to be made writable for the reset, and is
+        // made read-only again before it's marked as done.
         let ok_file = self.location.join(".cargo-ok");
         let _ = fs::remove_file(&ok_file);
+        set_checkout_readonly(&self.location, false)?;
         info!("reset {} to {}", self.repo.path().display(), self.revision);
         let object = self.repo.find_object(self.revision.0, None)?;
         self.repo.reset(&object, git2::ResetType::Hard, None)?;
+        self.update_submodules(cargo_config)?;
+        if read_only_checkouts(cargo_config)? {
+            set_checkout_readonly(&self.location, true)?;
+        }
         File::create(ok_file)?;
         Ok(())
     }
@@ -358,6 +668,184 @@ impl<'a> GitCheckout<'a> {
     }
 }
 
+/// Whether checkouts are made read-only, which is opt-in with
+/// `git.read-only-checkouts = true`.
+fn read_only_checkouts(config: &Config) -> CargoResult<bool> {
+    Ok(config.get_bool("git.read-only-checkouts")?.map(|v| v.val).unwrap_or(false))
+}
+
+/// Sets or clears write permission on every file and directory in the
+/// checkout at `path`.
+///
+/// With `git.read-only-checkouts` turned on, checkouts are made read-only
+/// once they're complete so builds fail loudly if anything tries to modify
+/// dependency sources, including by adding or removing files. The `.git`
+/// directory and `.cargo-ok` marker are left alone, as is `path` itself which
+/// holds them. Write permission has to be restored before a checkout can be
+/// reset or removed.
+pub fn set_checkout_readonly(path: &Path, readonly: bool) -> CargoResult<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        match entry.file_name().to_str() {
+            Some(".git") | Some(".cargo-ok") => continue,
+            _ => {}
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            // A directory has to be writable while its contents are changed,
+            // so it's made read-only after them and writable before them.
+            if !readonly {
+                set_readonly(&path, false)?;
+            }
+            set_checkout_readonly(&path, readonly)?;
+            if readonly {
+                set_readonly(&path, true)?;
+            }
+        } else if file_type.is_file() {
+            set_readonly(&path, readonly)?;
+        }
+    }
+    return Ok(());
+
+    fn set_readonly(path: &Path, readonly: bool) -> CargoResult<()> {
+        set_mode(path, readonly).chain_err(|| {
+            format!("failed to change permissions of `{}`", path.display())
+        })
+    }
+
+    #[cfg(unix)]
+    fn set_mode(path: &Path, readonly: bool) -> CargoResult<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        let mode = perms.mode();
+        perms.set_mode(if readonly { mode & !0o222 } else { mode | 0o200 });
+        fs::set_permissions(path, perms)?;
+        Ok(())
+    }
+    #[cfg(windows)]
+    fn set_mode(path: &Path, readonly: bool) -> CargoResult<()> {
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_readonly(readonly);
+        fs::set_permissions(path, perms)?;
+        Ok(())
+    }
+}
+
+/// Writes an export of `rev` to `dest` with `fill`, for `GitDatabase::export_to`
+/// and `archive_to`.
+///
+/// Like `GitCheckout::clone_into`, the export is made in a temporary directory
+/// next to `dest` and only renamed into place once it's complete, so being
+/// interrupted never leaves a partial export at `dest`. The `.cargo-ok` marker
+/// is written last, right before the rename.
+fn export_with<F>(rev: &GitRevision, dest: &Path, cargo_config: &Config, fill: F)
+                  -> CargoResult<()>
+    where F: FnOnce(&Path) -> CargoResult<()>
+{
+    let dirname = dest.parent().unwrap();
+    fs::create_dir_all(dirname).chain_err(|| {
+        format!("Couldn't mkdir {}", dirname.display())
+    })?;
+    paths::check_disk_space(dirname, 0)?;
+    let name = dest.file_name().and_then(|s| s.to_str()).unwrap_or("checkout");
+    let tmp = TempDir::new_in(dirname, &format!(".{}", name))?;
+    fill(tmp.path())?;
+    if read_only_checkouts(cargo_config)? {
+        set_checkout_readonly(tmp.path(), true)?;
+    }
+    File::create(tmp.path().join(".cargo-ok"))?.write_all(rev.to_string().as_bytes())?;
+
+    if fs::metadata(dest).is_ok() {
+        let _ = set_checkout_readonly(dest, false);
+        fs::remove_dir_all(dest).chain_err(|| {
+            format!("Couldn't rmdir {}", dest.display())
+        })?;
+    }
+    fs::rename(tmp.path(), dest).chain_err(|| {
+        format!("Couldn't move export into {}", dest.display())
+    })?;
+    tmp.into_path();
+    Ok(())
+}
+
+/// Returns whether `dest` is a complete export of `rev`, as written by
+/// `GitDatabase::export_to`.
+fn is_exported(rev: &GitRevision, dest: &Path) -> bool {
+    let mut contents = String::new();
+    match File::open(dest.join(".cargo-ok")) {
+        Ok(mut f) => f.read_to_string(&mut contents).is_ok() &&
+                     contents == rev.to_string(),
+        Err(..) => false,
+    }
+}
+
+fn export_tree(repo: &git2::Repository, tree: &git2::Tree, dest: &Path)
+               -> CargoResult<()> {
+    fs::create_dir_all(dest)?;
+    for entry in tree.iter() {
+        let name = entry.name().ok_or_else(|| {
+            internal(format!("non-utf8 path in tree {}", tree.id()))
+        })?;
+        let path = dest.join(name);
+        match entry.kind() {
+            Some(ObjectType::Tree) => {
+                let tree = repo.find_tree(entry.id())?;
+                export_tree(repo, &tree, &path)?;
+            }
+            Some(ObjectType::Blob) => {
+                let blob = repo.find_blob(entry.id())?;
+                export_blob(&path, blob.content(), entry.filemode())?;
+            }
+            _ => bail!("cannot export `{}`, it is not a file or directory",
+                       path.display()),
+        }
+    }
+    Ok(())
+}
+
+fn export_blob(path: &Path, contents: &[u8], mode: i32) -> CargoResult<()> {
+    const SYMLINK: i32 = 0o120000;
+    const EXECUTABLE: i32 = 0o100755;
+
+    if mode == SYMLINK {
+        if let Ok(target) = ::std::str::from_utf8(contents) {
+            return symlink(Path::new(target), path)
+        }
+    }
+    File::create(path)?.write_all(contents)?;
+    if mode == EXECUTABLE {
+        set_executable(path)?;
+    }
+    return Ok(());
+
+    #[cfg(unix)]
+    fn symlink(target: &Path, path: &Path) -> CargoResult<()> {
+        ::std::os::unix::fs::symlink(target, path)?;
+        Ok(())
+    }
+    #[cfg(windows)]
+    fn symlink(target: &Path, path: &Path) -> CargoResult<()> {
+        // Creating symlinks requires elevated privileges on Windows, so
+        // mirror git's `core.symlinks = false` behavior and write out the
+        // link target as a plain file.
+        let target = target.to_str().unwrap();
+        File::create(path)?.write_all(target.as_bytes())?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn set_executable(path: &Path) -> CargoResult<()> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+    #[cfg(windows)]
+    fn set_executable(_path: &Path) -> CargoResult<()> {
+        Ok(())
+    }
+}
+
 /// Prepare the authentication callbacks for cloning a git repository.
 ///
 /// The main purpose of this function is to construct the "authentication
@@ -558,10 +1046,99 @@ fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F)
     })
 }
 
+/// The refspec fetching only what `reference` points at, none if that can't
+/// be fetched by itself.
+fn minimal_refspec(reference: &GitReference) -> Option<String> {
+    match *reference {
+        GitReference::Branch(ref b) => Some(format!("+refs/heads/{0}:refs/heads/{0}", b)),
+        GitReference::Tag(ref t) => Some(format!("+refs/tags/{0}:refs/tags/{0}", t)),
+        // Only complete object ids can be asked for, and only from servers
+        // which allow it.
+        GitReference::Rev(ref r) if r.len() == 40 && r.chars().all(|c| c.is_digit(16)) => {
+            Some(format!("+{0}:refs/cargo/{0}", r))
+        }
+        GitReference::Rev(..) => None,
+    }
+}
+
+/// A `git` command for working on Cargo's own databases and checkouts.
+///
+/// Unless `git.isolate-config` is turned off, the system and global
+/// configuration of git is ignored so that nothing in there, like filters,
+/// hooks or `core.fsmonitor`, can run programs on them or alter what ends up
+/// in checkouts. Settings which would do so are also turned off explicitly,
+/// for versions of git which don't support `GIT_CONFIG_GLOBAL`.
+fn git_command(config: &Config) -> CargoResult<ProcessBuilder> {
+    let mut cmd = process("git");
+    // Whatever repository these may point at, it isn't the one we're after.
+    for var in ["GIT_DIR", "GIT_WORK_TREE", "GIT_INDEX_FILE", "GIT_OBJECT_DIRECTORY",
+                "GIT_ALTERNATE_OBJECT_DIRECTORIES", "GIT_COMMON_DIR"].iter() {
+        cmd.env_remove(var);
+    }
+    let isolate = config.get_bool("git.isolate-config")?.map(|v| v.val).unwrap_or(true);
+    if isolate {
+        let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+        cmd.env("GIT_CONFIG_NOSYSTEM", "1")
+           .env("GIT_CONFIG_GLOBAL", null)
+           .env_remove("GIT_CONFIG_PARAMETERS")
+           .arg("-c").arg("core.fsmonitor=false")
+           .arg("-c").arg(format!("core.hooksPath={}", null))
+           .arg("-c").arg(format!("core.attributesFile={}", null))
+           .arg("-c").arg("core.autocrlf=false");
+    }
+    Ok(cmd)
+}
+
+/// Resolves `reference` to the revision it points at in `repo`.
+fn resolve(repo: &git2::Repository, reference: &GitReference) -> CargoResult<GitRevision> {
+    let id = match *reference {
+        GitReference::Tag(ref s) => {
+            (|| -> CargoResult<git2::Oid> {
+                let refname = format!("refs/tags/{}", s);
+                let id = repo.refname_to_id(&refname)?;
+                let obj = repo.find_object(id, None)?;
+                let obj = obj.peel(ObjectType::Commit)?;
+                Ok(obj.id())
+            })().chain_err(|| {
+                format!("failed to find tag `{}`", s)
+            })?
+        }
+        GitReference::Branch(ref s) => {
+            (|| {
+                let b = repo.find_branch(s, git2::BranchType::Local)?;
+                b.get().target().ok_or_else(|| {
+                    CargoError::from(format!("branch `{}` did not have a target", s))
+                })
+            })().chain_err(|| {
+                format!("failed to find branch `{}`", s)
+            })?
+        }
+        GitReference::Rev(ref s) => {
+            let obj = repo.revparse_single(s)?;
+            match obj.as_tag() {
+                Some(tag) => tag.target_id(),
+                None => obj.id(),
+            }
+        }
+    };
+    Ok(GitRevision(id))
+}
+
 pub fn fetch(repo: &mut git2::Repository,
              url: &Url,
              refspec: &str,
              config: &Config) -> CargoResult<()> {
+    fetch_from(repo, url, url.as_str(), refspec, git2::AutotagOption::All, config)
+}
+
+/// Like `fetch`, but hands `location` to libgit2 instead of the URL, which is
+/// still used for everything else.
+fn fetch_from(repo: &mut git2::Repository,
+              url: &Url,
+              location: &str,
+              refspec: &str,
+              tags: git2::AutotagOption,
+              config: &Config) -> CargoResult<()> {
     if !config.network_allowed() {
         bail!("attempting to update a git repository, but --frozen \
                was specified")
@@ -585,28 +1162,93 @@ pub fn fetch(repo: &mut git2::Repository,
     // repo check to see if it's a little too old and could benefit from a gc.
     // In theory this shouldn't be too too expensive compared to the network
     // request we're about to issue.
-    maybe_gc_repo(repo)?;
+    maybe_gc_repo(repo, config)?;
 
+    let location = hosts::rewrite(config, location)?;
+    // The callback can't tell valid TLS certificates from invalid ones, so
+    // it's only installed for SSH.
+    let known_hosts = if known_hosts::is_ssh(&location) {
+        Some(KnownHosts::load(config)?)
+    } else {
+        None
+    };
     debug!("doing a fetch for {}", url);
     with_authentication(url.as_str(), &repo.config()?, |f| {
+        let mut progress = FetchProgress::new();
         let mut cb = git2::RemoteCallbacks::new();
         cb.credentials(f);
+        if let Some(ref known_hosts) = known_hosts {
+            cb.certificate_check(move |cert, host| known_hosts.check(cert, host));
+        }
+        cb.transfer_progress(|stats| {
+            // Failing to print isn't a reason to cancel the fetch.
+            let _ = progress.tick(config, &stats);
+            true
+        });
 
         // Create a local anonymous remote in the repository to fetch the url
-        let mut remote = repo.remote_anonymous(url.as_str())?;
+        let mut remote = repo.remote_anonymous(&location)?;
         let mut opts = git2::FetchOptions::new();
         opts.remote_callbacks(cb)
-            .download_tags(git2::AutotagOption::All);
+            .download_tags(tags);
 
-        network::with_retry(config, || {
+        let result = network::with_retry(config, || {
             debug!("initiating fetch of {} from {}", refspec, url);
             remote.fetch(&[refspec], Some(&mut opts), None)
                 .map_err(CargoError::from)
-        })?;
+        });
+        config.shell().clear_progress()?;
+        if let Some(ref known_hosts) = known_hosts {
+            known_hosts.finish(config)?;
+        }
+        result?;
+        config.record_fetched(url.as_str(),
+                              remote.stats().received_bytes() as u64);
         Ok(())
     })
 }
 
+/// Reports how far along a fetch is, as objects received, bytes received and
+/// the transfer speed, so that long fetches don't look like they're stuck.
+struct FetchProgress {
+    start: Instant,
+    last_update: Option<Instant>,
+}
+
+impl FetchProgress {
+    fn new() -> FetchProgress {
+        FetchProgress { start: Instant::now(), last_update: None }
+    }
+
+    fn tick(&mut self, config: &Config, stats: &git2::Progress) -> CargoResult<()> {
+        // Fetches which finish quickly aren't worth reporting on, and updating
+        // too often just makes the line flicker.
+        let now = Instant::now();
+        if now.duration_since(self.start) < Duration::from_millis(500) {
+            return Ok(())
+        }
+        if let Some(last) = self.last_update {
+            if now.duration_since(last) < Duration::from_millis(100) {
+                return Ok(())
+            }
+        }
+        self.last_update = Some(now);
+
+        let msg = if stats.received_objects() < stats.total_objects() {
+            let elapsed = now.duration_since(self.start);
+            let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+            let speed = (stats.received_bytes() as f64 / secs) as u64;
+            format!("{}/{} objects, {} ({}/s)",
+                    stats.received_objects(), stats.total_objects(),
+                    human_readable_bytes(stats.received_bytes() as u64),
+                    human_readable_bytes(speed))
+        } else {
+            format!("resolving deltas {}/{}", stats.indexed_deltas(), stats.total_deltas())
+        };
+        config.shell().progress("Fetching", msg)
+    }
+}
+
 /// Cargo has a bunch of long-lived git repositories in its global cache and
 /// some, like the index, are updated very frequently. Right now each update
 /// creates a new "pack file" inside the git database, and over time this can
@@ -620,7 +1262,7 @@ pub fn fetch(repo: &mut git2::Repository,
 /// we may not even have `git` installed on the system! As a result we
 /// opportunistically try a `git gc` when the pack directory looks too big, and
 /// failing that we just blow away the repository and start over.
-fn maybe_gc_repo(repo: &mut git2::Repository) -> CargoResult<()> {
+fn maybe_gc_repo(repo: &mut git2::Repository, config: &Config) -> CargoResult<()> {
     // Here we arbitrarily declare that if you have more than 100 files in your
     // `pack` folder that we need to do a gc.
     let entries = match repo.path().join("objects/pack").read_dir() {
@@ -642,7 +1284,8 @@ fn maybe_gc_repo(repo: &mut git2::Repository) -> CargoResult<()> {
     // likely to fail though as we may not have `git` installed. Note that
     // libgit2 doesn't currently implement the gc operation, so there's no
     // equivalent there.
-    match Command::new("git").arg("gc").current_dir(repo.path()).output() {
+    let gc = git_command(config)?.arg("gc").cwd(repo.path()).build_command().output();
+    match gc {
         Ok(out) => {
             debug!("git-gc status: {}\n\nstdout ---\n{}\nstderr ---\n{}",
                    out.status,
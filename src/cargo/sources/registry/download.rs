@@ -0,0 +1,106 @@
+//! Downloading `.crate` files of remote registries into the cache, shared by
+//! the git and the sparse (HTTP) index protocols.
+
+use std::fs;
+use std::path::Path;
+
+use core::{PackageId, SourceId};
+use util::download::{download_all, Download};
+use util::{Config, FileLock, Filesystem, ToUrl};
+use util::errors::{CargoResult, CargoResultExt};
+
+/// The `.crate` file of `pkg` in `cache_path`, if it's been downloaded already.
+pub fn cached(config: &Config,
+              cache_path: &Filesystem,
+              pkg: &PackageId) -> CargoResult<Option<FileLock>> {
+    // Attempt to open an read-only copy first to avoid an exclusive write
+    // lock and also work with read-only filesystems. Note that we check the
+    // length of the file like below to handle interrupted downloads of older
+    // versions of Cargo, which wrote to the cache directly.
+    let filename = crate_filename(pkg);
+    if let Ok(dst) = cache_path.open_ro(Path::new(&filename), config, &filename) {
+        if dst.file().metadata()?.len() > 0 {
+            return Ok(Some(dst))
+        }
+    }
+    Ok(None)
+}
+
+/// Opens the `.crate` file of `pkg` once it's been downloaded with `fetch`.
+pub fn open(config: &Config,
+            cache_path: &Filesystem,
+            pkg: &PackageId) -> CargoResult<FileLock> {
+    let filename = crate_filename(pkg);
+    cache_path.open_ro(Path::new(&filename), config, &filename)
+}
+
+/// Which of `pkgs`, paired with their checksums, haven't been downloaded yet.
+pub fn missing<'a>(config: &Config,
+                   cache_path: &Filesystem,
+                   pkgs: &'a [(PackageId, String)])
+                   -> CargoResult<Vec<(&'a PackageId, &'a str)>> {
+    let mut missing = Vec::new();
+    for &(ref pkg, ref checksum) in pkgs {
+        if cached(config, cache_path, pkg)?.is_none() {
+            missing.push((pkg, &checksum[..]));
+        }
+    }
+    Ok(missing)
+}
+
+/// Downloads the `.crate` files of `pkgs`, which are paired with their
+/// checksums, from the `dl` endpoint of the registry `source_id` into
+/// `cache_path`, all at once.
+///
+/// Each one is written next to where it goes in the cache first, and only
+/// moved there once it's complete and matches its checksum, so whatever's in
+/// the cache can be trusted. The package cache has to be locked for
+/// downloads.
+pub fn fetch(config: &Config,
+             cache_path: &Filesystem,
+             source_id: &SourceId,
+             dl: &str,
+             pkgs: &[(&PackageId, &str)]) -> CargoResult<()> {
+    let mut started = Vec::new();
+    let mut downloads = Vec::new();
+    for &(pkg, checksum) in pkgs {
+        let dst = cache_path.join(crate_filename(pkg)).into_path_unlocked();
+        if fs::metadata(&dst).map(|m| m.len() > 0).unwrap_or(false) {
+            continue
+        }
+        cache_path.create_dir()?;
+        config.shell().status("Downloading", pkg)?;
+
+        let mut url = dl.to_url()?;
+        url.path_segments_mut().unwrap()
+            .push(pkg.name())
+            .push(&pkg.version().to_string())
+            .push("download");
+        // An interrupted download is resumed from where it was cut off the
+        // next time.
+        let part = dst.with_extension("crate.part");
+        downloads.push(Download { url: url.to_string(), dst: part });
+        started.push((dst, pkg, checksum));
+    }
+
+    let (sha256, received) = download_all(config, &downloads)?;
+    config.record_fetched(source_id.url().as_str(), received);
+
+    for (((dst, pkg, checksum), download), sha256) in started.into_iter()
+                                                             .zip(downloads)
+                                                             .zip(sha256) {
+        if sha256 != checksum {
+            // Don't resume from something broken next time.
+            let _ = fs::remove_file(&download.dst);
+            bail!("failed to verify the checksum of `{}`", pkg)
+        }
+        fs::rename(&download.dst, &dst).chain_err(|| {
+            format!("failed to move `{}` into the cache", download.dst.display())
+        })?;
+    }
+    Ok(())
+}
+
+fn crate_filename(pkg: &PackageId) -> String {
+    format!("{}-{}.crate", pkg.name(), pkg.version())
+}
@@ -0,0 +1,244 @@
+//! Access to a registry index over plain HTTP, fetching only the index files
+//! of the crates which are actually looked up rather than cloning the whole
+//! index repository.
+//!
+//! Such a "sparse" registry is configured with a `sparse+` prefixed URL, for
+//! example `sparse+https://example.com/index/`. The server hosts the files of
+//! the index at the same paths as they have in the git repository of a
+//! regular registry, `config.json` included.
+//!
+//! Every file fetched is cached in the index directory of the registry, along
+//! with the `ETag` and `Last-Modified` headers it was served with. Checking it
+//! for changes later on is then a conditional request, which the server can
+//! answer with a bodiless `304 Not Modified`.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use curl::easy::List;
+use serde_json;
+
+use core::{PackageId, SourceId};
+use ops;
+use sources::registry::{download, RegistryData, RegistryConfig};
+use util::{network, paths, Config, FileLock, Filesystem};
+use util::errors::{CargoErrorKind, CargoResult, CargoResultExt};
+
+pub struct HttpRegistry<'cfg> {
+    index_path: Filesystem,
+    cache_path: Filesystem,
+    source_id: SourceId,
+    config: &'cfg Config,
+    /// The URL of the index, without the `sparse+` prefix.
+    url: String,
+    /// Whether the index was asked to be updated, after which cached files are
+    /// checked with the server before they're used. Before that they're
+    /// trusted, so that lock files can be built from without the network.
+    updating: Cell<bool>,
+    /// The files which were checked with the server in this session already.
+    fresh: RefCell<HashSet<PathBuf>>,
+}
+
+impl<'cfg> HttpRegistry<'cfg> {
+    pub fn new(source_id: &SourceId, config: &'cfg Config, name: &str)
+               -> HttpRegistry<'cfg> {
+        let url = source_id.url().as_str();
+        HttpRegistry {
+            index_path: config.registry_index_path().join(name),
+            cache_path: config.registry_cache_path().join(name),
+            source_id: source_id.clone(),
+            config: config,
+            url: url["sparse+".len()..].trim_right_matches('/').to_string(),
+            updating: Cell::new(false),
+            fresh: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Fetches the index file at `path` into `dst`, unless the copy already
+    /// there is still up to date. A file which isn't on the server (anymore)
+    /// is removed.
+    fn fetch(&self, path: &Path, dst: &Path) -> CargoResult<()> {
+        if !self.config.network_allowed() {
+            if dst.exists() {
+                return Ok(())
+            }
+            bail!("the index file `{}` hasn't been fetched yet, and the \
+                   network can't be used as `--frozen` was passed",
+                  path.display())
+        }
+
+        let components = path.iter()
+                             .map(|c| c.to_string_lossy().into_owned())
+                             .collect::<Vec<_>>();
+        let url = format!("{}/{}", self.url, components.join("/"));
+        let validators = validators_path(dst);
+
+        let mut headers = List::new();
+        if dst.exists() {
+            if let Ok(saved) = paths::read(&validators) {
+                for line in saved.lines() {
+                    let mut parts = line.splitn(2, ": ");
+                    match (parts.next(), parts.next()) {
+                        (Some("etag"), Some(v)) => {
+                            headers.append(&format!("If-None-Match: {}", v))?;
+                        }
+                        (Some("last-modified"), Some(v)) => {
+                            headers.append(&format!("If-Modified-Since: {}", v))?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let mut handle = self.config.http()?.borrow_mut();
+        handle.get(true)?;
+        handle.url(&url)?;
+        handle.follow_location(true)?;
+        handle.http_headers(headers)?;
+        let mut body = Vec::new();
+        let mut saved = Vec::new();
+        let result = network::with_retry(self.config, || {
+            body = Vec::new();
+            saved = Vec::new();
+            {
+                let mut handle = handle.transfer();
+                handle.write_function(|buf| {
+                    body.extend_from_slice(buf);
+                    Ok(buf.len())
+                })?;
+                handle.header_function(|line| {
+                    let line = str::from_utf8(line).unwrap_or("").trim();
+                    let mut parts = line.splitn(2, ':');
+                    if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                        let name = name.trim().to_lowercase();
+                        if name == "etag" || name == "last-modified" {
+                            saved.push(format!("{}: {}", name, value.trim()));
+                        }
+                    }
+                    true
+                })?;
+                handle.perform()?;
+            }
+            let code = handle.response_code()?;
+            match code {
+                // `file://` URLs don't have a status.
+                0 | 200 | 304 | 404 | 410 => Ok(code),
+                _ => {
+                    let url = handle.effective_url()?.unwrap_or(&url);
+                    Err(CargoErrorKind::HttpNot200(code, url.to_string()).into())
+                }
+            }
+        });
+        // The handle is shared, so the validators mustn't be sent along with
+        // whatever request it's used for next.
+        handle.http_headers(List::new())?;
+        let code = result.chain_err(|| format!("failed to fetch `{}`", url))?;
+        self.config.record_fetched(self.source_id.url().as_str(), body.len() as u64);
+
+        match code {
+            304 => {}
+            404 | 410 => {
+                for file in [dst, &validators].iter() {
+                    if file.exists() {
+                        fs::remove_file(file)?;
+                    }
+                }
+            }
+            _ => {
+                fs::create_dir_all(dst.parent().unwrap())?;
+                // Readers may not hold any lock, so they must never see a
+                // file which is only partially written.
+                let tmp = dst.with_extension("tmp");
+                paths::write(&tmp, &body)?;
+                fs::rename(&tmp, dst)?;
+                paths::write(&validators, saved.join("\n").as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the validators (`ETag` and `Last-Modified`) of the cached index file
+/// `path` are kept. Crate names can't contain a `.`, so this never clashes
+/// with another index file.
+fn validators_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap().to_string_lossy();
+    path.with_file_name(format!("{}.headers", name))
+}
+
+impl<'cfg> RegistryData for HttpRegistry<'cfg> {
+    fn index_path(&self) -> &Filesystem {
+        &self.index_path
+    }
+
+    fn load(&self,
+            root: &Path,
+            path: &Path,
+            data: &mut FnMut(&[u8]) -> CargoResult<()>) -> CargoResult<()> {
+        let file = root.join(path);
+        let fresh = self.fresh.borrow().contains(path);
+        if !fresh && (self.updating.get() || !file.exists()) {
+            self.fetch(path, &file)?;
+            self.fresh.borrow_mut().insert(path.to_path_buf());
+        }
+        if !file.exists() {
+            bail!("`{}` isn't in the index", path.display())
+        }
+        data(&paths::read_bytes(&file)?)
+    }
+
+    fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
+        let root = self.index_path.clone().into_path_unlocked();
+        let mut config = None;
+        self.load(&root, Path::new("config.json"), &mut |json| {
+            config = Some(serde_json::from_slice(json)?);
+            Ok(())
+        })?;
+        Ok(config)
+    }
+
+    fn update_index(&mut self) -> CargoResult<()> {
+        // Like for git indexes, weed out problems with the HTTP configuration
+        // before anything's fetched.
+        ops::http_handle(self.config)?;
+
+        // Nothing's fetched right away, only the files which are looked up
+        // later on are, as needed.
+        if !self.updating.get() {
+            self.config.shell().status("Updating", self.source_id.display_registry())?;
+            self.updating.set(true);
+        }
+        Ok(())
+    }
+
+    fn download(&mut self, pkg: &PackageId, checksum: &str)
+                -> CargoResult<FileLock> {
+        if let Some(dst) = download::cached(self.config, &self.cache_path, pkg)? {
+            return Ok(dst)
+        }
+        let dl = self.config()?.unwrap().dl;
+        download::fetch(self.config, &self.cache_path, &self.source_id, &dl,
+                        &[(pkg, checksum)])?;
+        download::open(self.config, &self.cache_path, pkg)
+    }
+
+    fn prefetch(&mut self, pkgs: &[(PackageId, String)]) -> CargoResult<()> {
+        let missing = download::missing(self.config, &self.cache_path, pkgs)?;
+        if missing.is_empty() {
+            return Ok(())
+        }
+        let dl = self.config()?.unwrap().dl;
+        download::fetch(self.config, &self.cache_path, &self.source_id, &dl, &missing)
+    }
+
+    fn is_downloaded(&self, pkg: &PackageId) -> bool {
+        match download::cached(self.config, &self.cache_path, pkg) {
+            Ok(Some(..)) => true,
+            _ => false,
+        }
+    }
+}
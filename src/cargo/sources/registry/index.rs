@@ -9,13 +9,14 @@ use core::dependency::Dependency;
 use core::{SourceId, Summary, PackageId};
 use sources::registry::{RegistryPackage, INDEX_LOCK};
 use sources::registry::RegistryData;
-use util::{CargoError, CargoResult, internal, Filesystem, Config};
+use util::{network, CargoError, CargoResult, internal, Filesystem, Config, ToSemver};
 
 pub struct RegistryIndex<'cfg> {
     source_id: SourceId,
     path: Filesystem,
     cache: HashMap<String, Vec<(Summary, bool)>>,
     hashes: HashMap<String, HashMap<Version, String>>, // (name, vers) => cksum
+    signatures: HashMap<String, HashMap<Version, String>>, // (name, vers) => sig
     config: &'cfg Config,
     locked: bool,
 }
@@ -31,6 +32,7 @@ impl<'cfg> RegistryIndex<'cfg> {
             path: path.clone(),
             cache: HashMap::new(),
             hashes: HashMap::new(),
+            signatures: HashMap::new(),
             config: config,
             locked: locked,
         }
@@ -53,6 +55,17 @@ impl<'cfg> RegistryIndex<'cfg> {
         }).map(|s| s.clone())
     }
 
+    /// Return the signature listed for a specified PackageId, if it's signed.
+    pub fn signature(&mut self,
+                     pkg: &PackageId,
+                     load: &mut RegistryData)
+                     -> CargoResult<Option<String>> {
+        self.summaries(pkg.name(), load)?;
+        Ok(self.signatures.get(pkg.name())
+               .and_then(|v| v.get(pkg.version()))
+               .cloned())
+    }
+
     /// Parse the on-disk metadata for the package provided
     ///
     /// Returns a list of pairs of (summary, yanked) for the package name
@@ -124,9 +137,11 @@ impl<'cfg> RegistryIndex<'cfg> {
 
         // We ignore lookup failures as those are just crates which don't exist
         // or we haven't updated the registry yet. If we actually ran the
-        // closure though then we care about those errors.
-        if hit_closure {
-            err?;
+        // closure though then we care about those errors, and so we do about
+        // a registry which couldn't be reached to look the crate up at all.
+        match err {
+            Err(ref e) if !hit_closure && !network::maybe_spurious(e) => {}
+            err => err?,
         }
 
         Ok(ret)
@@ -139,13 +154,18 @@ impl<'cfg> RegistryIndex<'cfg> {
     fn parse_registry_package(&mut self, line: &str)
                               -> CargoResult<(Summary, bool)> {
         let RegistryPackage {
-            name, vers, cksum, deps, features, yanked
+            name, vers, cksum, deps, features, yanked, sig, links
         } = super::DEFAULT_ID.set(&self.source_id, || {
             serde_json::from_str::<RegistryPackage>(line)
         })?;
         let pkgid = PackageId::new(&name, &vers, &self.source_id)?;
         let summary = Summary::new(pkgid, deps.inner, features)?;
-        let summary = summary.set_checksum(cksum.clone());
+        let summary = summary.set_checksum(cksum.clone()).set_links(links);
+        if let Some(sig) = sig {
+            self.signatures.entry(name.to_string())
+                .or_insert_with(HashMap::new)
+                .insert(vers.clone(), sig);
+        }
         if self.hashes.contains_key(&name[..]) {
             self.hashes.get_mut(&name[..]).unwrap().insert(vers, cksum);
         } else {
@@ -175,8 +195,10 @@ impl<'cfg> RegistryIndex<'cfg> {
             match source_id.precise() {
                 Some(p) if p.starts_with(dep.name()) &&
                            p[dep.name().len()..].starts_with('=') => {
+                    // Build metadata doesn't take part in version
+                    // comparisons, so `1.0.0` picks `1.0.0+abc` as well.
                     let vers = &p[dep.name().len() + 1..];
-                    s.version().to_string() == vers
+                    vers.to_semver().map(|v| *s.version() == v).unwrap_or(false)
                 }
                 _ => true,
             }
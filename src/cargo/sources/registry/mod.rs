@@ -109,6 +109,13 @@
 //! modifications to this file that should happen over time are yanks of a
 //! particular version.
 //!
+//! ## Sparse indexes
+//!
+//! Instead of through a git repository, an index can also be served as plain
+//! files over HTTP, which is picked with a `sparse+` prefix on its URL. Only
+//! the files of the crates which are actually looked up are fetched then, see
+//! `http_remote` for the details.
+//!
 //! # Downloading Packages
 //!
 //! The purpose of the Index was to provide an efficient method to resolve the
@@ -161,10 +168,14 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt;
-use std::fs::File;
-use std::path::{PathBuf, Path};
+use std::fs::{self, File};
+use std::io::SeekFrom;
+use std::io::prelude::*;
+use std::path::{Component, PathBuf, Path};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use flate2::read::GzDecoder;
+use hex::ToHex;
 use semver::Version;
 use serde::de;
 use tar::Archive;
@@ -172,12 +183,17 @@ use tar::Archive;
 use core::{Source, SourceId, PackageId, Package, Summary, Registry};
 use core::dependency::{Dependency, Kind};
 use sources::PathSource;
-use util::{CargoResult, Config, internal, FileLock, Filesystem};
+use util::{CacheLockMode, CargoResult, Config, internal, FileLock, Filesystem, Sha256};
+use util::cache_manifest::CacheEntry;
 use util::errors::CargoResultExt;
 use util::hex;
+use util::paths;
+use util::signature;
 use util::to_url::ToUrl;
 
 const INDEX_LOCK: &'static str = ".cargo-index-lock";
+/// Holds when the index was last updated, in seconds since the Unix epoch.
+const LAST_UPDATED: &'static str = ".last-updated";
 pub static CRATES_IO: &'static str = "https://github.com/rust-lang/crates.io-index";
 
 pub struct RegistrySource<'cfg> {
@@ -210,6 +226,8 @@ struct RegistryPackage<'a> {
     features: BTreeMap<String, Vec<String>>,
     cksum: String,
     yanked: Option<bool>,
+    sig: Option<String>,
+    links: Option<String>,
 }
 
 struct DependencyList {
@@ -239,22 +257,91 @@ pub trait RegistryData {
     fn download(&mut self,
                 pkg: &PackageId,
                 checksum: &str) -> CargoResult<FileLock>;
+
+    /// Gets ready to `download` all of `pkgs`, which are paired with their
+    /// checksums, see `Source::prefetch`.
+    fn prefetch(&mut self, _pkgs: &[(PackageId, String)]) -> CargoResult<()> {
+        Ok(())
+    }
+
+    /// Whether `download` can hand out `pkg` without fetching it, see
+    /// `Source::is_downloaded`.
+    fn is_downloaded(&self, _pkg: &PackageId) -> bool {
+        true
+    }
 }
 
+mod download;
 mod index;
 mod remote;
+mod http_remote;
 mod local;
 
+/// The most a `.crate` tarball may unpack into, to keep what's practically a
+/// zip bomb from filling up the disk.
+const MAX_UNPACKED_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Whether `c` is an ordinary path component, as opposed to a root, prefix,
+/// `.` or `..`, which entries of a tarball have no business containing.
+fn is_normal(c: &Component) -> bool {
+    match *c {
+        Component::Normal(..) => true,
+        _ => false,
+    }
+}
+
+/// Lexically resolves the `.` and `..` components of the relative `path`,
+/// returning `None` if it's absolute or if it leaves the directory it's
+/// relative to.
+fn resolve_within(path: &Path) -> Option<PathBuf> {
+    let mut ret = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::Normal(c) => ret.push(c),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !ret.pop() {
+                    return None
+                }
+            }
+            Component::RootDir | Component::Prefix(..) => return None,
+        }
+    }
+    Some(ret)
+}
+
 fn short_name(id: &SourceId) -> String {
     let hash = hex::short_hash(id);
     let ident = id.url().host_str().unwrap_or("").to_string();
     format!("{}-{}", ident, hash)
 }
 
+/// Returns the locations of the downloaded `.crate` tarball for `pkg` in the
+/// global cache and of the directory it gets unpacked into, for the remote
+/// registry `source_id`.
+///
+/// Note that neither path is locked, nor is either guaranteed to exist.
+pub fn cache_locations(source_id: &SourceId,
+                       pkg: &PackageId,
+                       config: &Config) -> (PathBuf, PathBuf) {
+    let name = short_name(source_id);
+    let tarball = config.registry_cache_path().join(&name)
+        .join(&format!("{}-{}.crate", pkg.name(), pkg.version()));
+    let src = config.registry_source_path().join(&name)
+        .join(&format!("{}-{}", pkg.name(), pkg.version()));
+    (tarball.into_path_unlocked(), src.into_path_unlocked())
+}
+
 impl<'cfg> RegistrySource<'cfg> {
     pub fn remote(source_id: &SourceId,
                   config: &'cfg Config) -> RegistrySource<'cfg> {
         let name = short_name(source_id);
+        if source_id.url().scheme().starts_with("sparse+") {
+            // Index files are fetched one at a time and written atomically,
+            // there's no repository to lock.
+            let ops = http_remote::HttpRegistry::new(source_id, config, &name);
+            return RegistrySource::new(source_id, config, &name, Box::new(ops), false)
+        }
         let ops = remote::RemoteRegistry::new(source_id, config, &name);
         RegistrySource::new(source_id, config, &name, Box::new(ops), true)
     }
@@ -293,13 +380,52 @@ impl<'cfg> RegistrySource<'cfg> {
         self.ops.config()
     }
 
+    /// The names of all packages in the index, for searching registries
+    /// without a search API.
+    ///
+    /// This requires that the index has been at least checked out. Sparse
+    /// registries only have the files which were looked up, so they can't be
+    /// listed.
+    pub fn list_packages(&self) -> CargoResult<Vec<String>> {
+        if self.source_id.url().scheme().starts_with("sparse+") {
+            bail!("the packages of sparse registries can't be listed")
+        }
+        let root = self.ops.index_path().clone().into_path_unlocked();
+        let mut names = Vec::new();
+        let mut dirs = vec![root];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir).chain_err(|| {
+                format!("failed to read the index directory `{}`", dir.display())
+            })? {
+                let path = entry?.path();
+                let name = match path.file_name().and_then(|n| n.to_str()) {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                };
+                // Dot files are Cargo's own (and git's), and `config.json`
+                // can't be a package as names can't contain a `.`.
+                if name.starts_with('.') || name.contains('.') {
+                    continue
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
     /// Unpacks a downloaded package into a location where it's ready to be
     /// compiled.
     ///
     /// No action is taken if the source looks like it's already unpacked.
     fn unpack_package(&self,
                       pkg: &PackageId,
-                      tarball: &FileLock)
+                      tarball: &FileLock,
+                      checksum: &str)
                       -> CargoResult<PathBuf> {
         let dst = self.src_path.join(&format!("{}-{}", pkg.name(),
                                               pkg.version()));
@@ -313,46 +439,177 @@ impl<'cfg> RegistrySource<'cfg> {
             return Ok(dst)
         }
 
-        let gz = GzDecoder::new(tarball.file())?;
-        let mut tar = Archive::new(gz);
-        let prefix = dst.file_name().unwrap();
-        let parent = dst.parent().unwrap();
-        for entry in tar.entries()? {
-            let mut entry = entry.chain_err(|| "failed to iterate over archive")?;
-            let entry_path = entry.path()
-                .chain_err(|| "failed to read entry path")?
-                .into_owned();
-
-            // We're going to unpack this tarball into the global source
-            // directory, but we want to make sure that it doesn't accidentally
-            // (or maliciously) overwrite source code from other crates. Cargo
-            // itself should never generate a tarball that hits this error, and
-            // crates.io should also block uploads with these sorts of tarballs,
-            // but be extra sure by adding a check here as well.
-            if !entry_path.starts_with(prefix) {
-                return Err(format!("invalid tarball downloaded, contains \
-                                    a file at {:?} which isn't under {:?}",
-                                   entry_path, prefix).into())
+        // The tarball may have been sitting in the cache for a long time, and
+        // whatever is unpacked here is trusted from then on, so check once more
+        // that it's what the index entry the lock file was generated from
+        // says it is.
+        let actual = {
+            let mut file = tarball.file();
+            let mut state = Sha256::new();
+            let mut buf = [0; 64 * 1024];
+            loop {
+                let n = file.read(&mut buf).chain_err(|| {
+                    format!("failed to read `{}`", tarball.path().display())
+                })?;
+                if n == 0 {
+                    break
+                }
+                state.update(&buf[..n]);
             }
+            file.seek(SeekFrom::Start(0))?;
+            state.finish().to_hex()
+        };
+        if actual != checksum {
+            bail!("\
+the archive of `{}` doesn't match the checksum recorded for it
 
-            // Once that's verified, unpack the entry as usual.
-            entry.unpack_in(parent).chain_err(|| {
-                format!("failed to unpack entry at `{}`", entry_path.display())
-            })?;
+expected: {}
+actual:   {}
+
+the archive at `{}` isn't the one that was published, this could be \
+indicative of a few possible situations:
+
+    * the archive was modified after it was downloaded
+    * a replacement source in use (e.g. a mirror) served a different archive
+    * the registry's contents were tampered with
+
+nothing was unpacked from it, run `cargo verify --repair` to download it again
+", pkg, checksum, actual, tarball.path().display())
         }
+
+        (|| -> CargoResult<()> {
+            // The unpacked sources are bound to take up at least as much space as
+            // the compressed tarball.
+            paths::check_disk_space(&dst, tarball.file().metadata()?.len())?;
+            let gz = GzDecoder::new(tarball.file())?;
+            let mut tar = Archive::new(gz);
+            // Whatever modes the archive lists, nothing unpacked is setuid or
+            // writable by anyone else, nor gets extended attributes.
+            tar.set_preserve_permissions(false);
+            tar.set_mask(0o022);
+            tar.set_unpack_xattrs(false);
+            let prefix = dst.file_name().unwrap();
+            let parent = dst.parent().unwrap();
+            let mut unpacked_size = 0u64;
+            for entry in tar.entries()? {
+                let mut entry = entry.chain_err(|| "failed to iterate over archive")?;
+                let entry_path = entry.path()
+                    .chain_err(|| "failed to read entry path")?
+                    .into_owned();
+
+                // We're going to unpack this tarball into the global source
+                // directory, but we want to make sure that it doesn't accidentally
+                // (or maliciously) overwrite source code from other crates. Cargo
+                // itself should never generate a tarball that hits this error, and
+                // crates.io should also block uploads with these sorts of tarballs,
+                // but be extra sure by adding a check here as well.
+                if !entry_path.components().all(|c| is_normal(&c)) {
+                    return Err(format!("invalid tarball downloaded, contains \
+                                        a file at {:?} which isn't a plain \
+                                        relative path", entry_path).into())
+                }
+                if !entry_path.starts_with(prefix) {
+                    return Err(format!("invalid tarball downloaded, contains \
+                                        a file at {:?} which isn't under {:?}",
+                                       entry_path, prefix).into())
+                }
+
+                // Hard links may only point at something else in the package,
+                // or they'd be a way to write outside of it all the same.
+                // Symlinks can't be checked that way, as where they end up
+                // depends on the other symlinks they lead through, and
+                // `cargo package` never creates them anyway.
+                let kind = entry.header().entry_type();
+                if kind.is_symlink() {
+                    return Err(format!("invalid tarball downloaded, contains \
+                                        a symlink at {:?}", entry_path).into())
+                } else if kind.is_hard_link() {
+                    let target = entry.link_name()
+                        .chain_err(|| "failed to read link name")?
+                        .map(|l| l.into_owned())
+                        .unwrap_or_default();
+                    match resolve_within(&target) {
+                        Some(ref path) if path.starts_with(prefix) => {}
+                        _ => {
+                            return Err(format!("invalid tarball downloaded, \
+                                                contains a link at {:?} to {:?} \
+                                                which isn't under {:?}",
+                                               entry_path, target, prefix).into())
+                        }
+                    }
+                } else if kind.is_character_special() ||
+                          kind.is_block_special() ||
+                          kind.is_fifo() {
+                    return Err(format!("invalid tarball downloaded, contains \
+                                        a device or fifo at {:?}",
+                                       entry_path).into())
+                }
+
+                // A small tarball can still unpack into a huge amount of data.
+                unpacked_size += entry.header().size()
+                    .chain_err(|| "failed to read entry size")?;
+                if unpacked_size > MAX_UNPACKED_SIZE {
+                    return Err(format!("invalid tarball downloaded, unpacks to \
+                                        more than the limit of {} bytes",
+                                       MAX_UNPACKED_SIZE).into())
+                }
+
+                // Once that's verified, unpack the entry as usual.
+                entry.unpack_in(parent).chain_err(|| {
+                    format!("failed to unpack entry at `{}`", entry_path.display())
+                })?;
+            }
+            Ok(())
+        })().chain_err(|| {
+            internal(format!("failed to unpack package `{}`", pkg))
+        })?;
         File::create(&ok)?;
         Ok(dst.clone())
     }
 
     fn do_update(&mut self) -> CargoResult<()> {
+        let _lock = self.config.acquire_package_cache_lock(CacheLockMode::Download)?;
         self.ops.update_index()?;
         let path = self.ops.index_path();
+        self.config.record_cache_use(&path.clone().into_path_unlocked(),
+                                     CacheEntry::new("registry-index",
+                                                     self.source_id.url().as_str(),
+                                                     None));
         self.index = index::RegistryIndex::new(&self.source_id,
                                                path,
                                                self.config,
                                                self.index_locked);
+        // Local registries are managed by whoever put them in place, nothing
+        // is written into them.
+        if self.source_id.is_remote_registry() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)
+                                       .map(|d| d.as_secs())
+                                       .unwrap_or(0);
+            path.create_dir()?;
+            let stamp = path.clone().into_path_unlocked().join(LAST_UPDATED);
+            paths::write(&stamp, now.to_string().as_bytes())?;
+        }
         Ok(())
     }
+
+    /// Whether the index was updated within `registry.index-max-staleness`,
+    /// in which case it's used as is rather than updated again.
+    fn recently_updated(&self) -> CargoResult<bool> {
+        let max = self.config.index_max_staleness()?;
+        if !self.source_id.is_remote_registry() || max == Duration::from_secs(0) {
+            return Ok(false)
+        }
+        let stamp = self.ops.index_path().clone().into_path_unlocked().join(LAST_UPDATED);
+        let updated = match paths::read(&stamp).ok().and_then(|s| s.trim().parse().ok()) {
+            Some(secs) => UNIX_EPOCH + Duration::from_secs(secs),
+            None => return Ok(false),
+        };
+        // A clock which went backwards doesn't make for a fresh index.
+        match SystemTime::now().duration_since(updated) {
+            Ok(age) => Ok(age < max),
+            Err(_) => Ok(false),
+        }
+    }
 }
 
 impl<'cfg> Registry for RegistrySource<'cfg> {
@@ -401,18 +658,29 @@ impl<'cfg> Source for RegistrySource<'cfg> {
         // querying phase. Note that precise in this case is only
         // `Some("locked")` as other `Some` values indicate a `cargo update
         // --precise` request
-        if self.source_id.precise() != Some("locked") {
+        if self.source_id.precise() != Some("locked") && !self.recently_updated()? {
             self.do_update()?;
         }
         Ok(())
     }
 
     fn download(&mut self, package: &PackageId) -> CargoResult<Package> {
+        let _lock = self.config.acquire_package_cache_lock(CacheLockMode::Download)?;
         let hash = self.index.hash(package, &mut *self.ops)?;
         let path = self.ops.download(package, &hash)?;
-        let path = self.unpack_package(package, &path).chain_err(|| {
-            internal(format!("failed to unpack package `{}`", package))
-        })?;
+        if signature::enabled(self.config)? {
+            let signature = self.index.signature(package, &mut *self.ops)?;
+            let data = paths::read_bytes(path.path())?;
+            signature::verify(self.config,
+                              &format!("`{}`", package),
+                              &data,
+                              signature.as_ref().map(|s| &s[..]))?;
+        }
+        let tarball = path.path().to_path_buf();
+        let path = self.unpack_package(package, &path, &hash)?;
+        let url = self.source_id.url().as_str();
+        self.config.record_cache_use(&tarball, CacheEntry::new("registry-crate", url, None));
+        self.config.record_cache_use(&path, CacheEntry::new("registry-src", url, None));
         let mut src = PathSource::new(&path, &self.source_id, self.config);
         src.update()?;
         let pkg = src.download(package)?;
@@ -430,6 +698,18 @@ impl<'cfg> Source for RegistrySource<'cfg> {
         Ok(Package::new(manifest, pkg.manifest_path()))
     }
 
+    fn prefetch(&mut self, packages: &[PackageId]) -> CargoResult<()> {
+        let _lock = self.config.acquire_package_cache_lock(CacheLockMode::Download)?;
+        let pkgs = packages.iter().map(|id| {
+            Ok((id.clone(), self.index.hash(id, &mut *self.ops)?))
+        }).collect::<CargoResult<Vec<_>>>()?;
+        self.ops.prefetch(&pkgs)
+    }
+
+    fn is_downloaded(&self, package: &PackageId) -> bool {
+        self.ops.is_downloaded(package)
+    }
+
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
         Ok(pkg.package_id().version().to_string())
     }
@@ -1,21 +1,16 @@
 use std::cell::{RefCell, Ref, Cell};
-use std::io::SeekFrom;
-use std::io::prelude::*;
 use std::mem;
 use std::path::Path;
 
 use git2;
-use hex::ToHex;
 use serde_json;
 
 use core::{PackageId, SourceId};
 use ops;
 use sources::git;
-use sources::registry::{RegistryData, RegistryConfig, INDEX_LOCK};
-use util::network;
-use util::{FileLock, Filesystem, LazyCell};
-use util::{Config, Sha256, ToUrl};
-use util::errors::{CargoErrorKind, CargoResult, CargoResultExt};
+use sources::registry::{download, RegistryData, RegistryConfig, INDEX_LOCK};
+use util::{Config, FileLock, Filesystem, LazyCell};
+use util::errors::{CargoResult, CargoResultExt};
 
 pub struct RemoteRegistry<'cfg> {
     index_path: Filesystem,
@@ -180,74 +175,29 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
 
     fn download(&mut self, pkg: &PackageId, checksum: &str)
                 -> CargoResult<FileLock> {
-        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
-        let path = Path::new(&filename);
-
-        // Attempt to open an read-only copy first to avoid an exclusive write
-        // lock and also work with read-only filesystems. Note that we check the
-        // length of the file like below to handle interrupted downloads.
-        //
-        // If this fails then we fall through to the exclusive path where we may
-        // have to redownload the file.
-        if let Ok(dst) = self.cache_path.open_ro(path, self.config, &filename) {
-            let meta = dst.file().metadata()?;
-            if meta.len() > 0 {
-                return Ok(dst)
-            }
-        }
-        let mut dst = self.cache_path.open_rw(path, self.config, &filename)?;
-        let meta = dst.file().metadata()?;
-        if meta.len() > 0 {
+        if let Some(dst) = download::cached(self.config, &self.cache_path, pkg)? {
             return Ok(dst)
         }
-        self.config.shell().status("Downloading", pkg)?;
-
-        let config = self.config()?.unwrap();
-        let mut url = config.dl.to_url()?;
-        url.path_segments_mut().unwrap()
-            .push(pkg.name())
-            .push(&pkg.version().to_string())
-            .push("download");
-
-        // TODO: don't download into memory, but ensure that if we ctrl-c a
-        //       download we should resume either from the start or the middle
-        //       on the next time
-        let url = url.to_string();
-        let mut handle = self.config.http()?.borrow_mut();
-        handle.get(true)?;
-        handle.url(&url)?;
-        handle.follow_location(true)?;
-        let mut state = Sha256::new();
-        let mut body = Vec::new();
-        network::with_retry(self.config, || {
-            state = Sha256::new();
-            body = Vec::new();
-            {
-                let mut handle = handle.transfer();
-                handle.write_function(|buf| {
-                    state.update(buf);
-                    body.extend_from_slice(buf);
-                    Ok(buf.len())
-                })?;
-                handle.perform()?;
-            }
-            let code = handle.response_code()?;
-            if code != 200 && code != 0 {
-                let url = handle.effective_url()?.unwrap_or(&url);
-                Err(CargoErrorKind::HttpNot200(code, url.to_string()).into())
-            } else {
-                Ok(())
-            }
-        })?;
+        let dl = self.config()?.unwrap().dl;
+        download::fetch(self.config, &self.cache_path, &self.source_id, &dl,
+                        &[(pkg, checksum)])?;
+        download::open(self.config, &self.cache_path, pkg)
+    }
 
-        // Verify what we just downloaded
-        if state.finish().to_hex() != checksum {
-            bail!("failed to verify the checksum of `{}`", pkg)
+    fn prefetch(&mut self, pkgs: &[(PackageId, String)]) -> CargoResult<()> {
+        let missing = download::missing(self.config, &self.cache_path, pkgs)?;
+        if missing.is_empty() {
+            return Ok(())
         }
+        let dl = self.config()?.unwrap().dl;
+        download::fetch(self.config, &self.cache_path, &self.source_id, &dl, &missing)
+    }
 
-        dst.write_all(&body)?;
-        dst.seek(SeekFrom::Start(0))?;
-        Ok(dst)
+    fn is_downloaded(&self, pkg: &PackageId) -> bool {
+        match download::cached(self.config, &self.cache_path, pkg) {
+            Ok(Some(..)) => true,
+            _ => false,
+        }
     }
 }
 
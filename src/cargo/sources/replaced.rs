@@ -41,6 +41,11 @@ impl<'cfg> Registry for ReplacedSource<'cfg> {
     fn requires_precise(&self) -> bool {
         self.inner.requires_precise()
     }
+
+    fn package_names(&mut self, dep: &Dependency) -> Vec<String> {
+        let dep = dep.clone().map_source(&self.to_replace, &self.replace_with);
+        self.inner.package_names(&dep)
+    }
 }
 
 impl<'cfg> Source for ReplacedSource<'cfg> {
@@ -64,6 +69,20 @@ impl<'cfg> Source for ReplacedSource<'cfg> {
         Ok(pkg.map_source(&self.replace_with, &self.to_replace))
     }
 
+    fn prefetch(&mut self, packages: &[PackageId]) -> CargoResult<()> {
+        let packages = packages.iter().map(|id| {
+            id.with_source_id(&self.replace_with)
+        }).collect::<Vec<_>>();
+        self.inner.prefetch(&packages).chain_err(|| {
+            format!("failed to download replaced source {}",
+                    self.to_replace)
+        })
+    }
+
+    fn is_downloaded(&self, id: &PackageId) -> bool {
+        self.inner.is_downloaded(&id.with_source_id(&self.replace_with))
+    }
+
     fn fingerprint(&self, id: &Package) -> CargoResult<String> {
         self.inner.fingerprint(id)
     }
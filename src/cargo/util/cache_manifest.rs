@@ -0,0 +1,157 @@
+//! A description of the caches in Cargo's home directory, kept alongside them.
+//!
+//! Cargo's home directory holds a `CACHE-README` explaining its layout to
+//! whoever comes across it, and a `cache-manifest.json` for tools, Cargo's own
+//! `cache` and `verify` commands included. The manifest records the layout
+//! version of the caches, the version of Cargo which last wrote to them, and
+//! for every git database, git checkout, registry index, downloaded crate and
+//! unpacked crate source where it came from and when it was last used.
+//!
+//! Uses are recorded with `Config::record_cache_use` and written out once the
+//! package cache is no longer locked, so the manifest is updated at most once
+//! per command.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use util::{layout_version, paths, CargoResult, CargoResultExt, Config};
+use version;
+
+/// The machine-readable manifest, relative to Cargo's home directory.
+pub const MANIFEST: &'static str = "cache-manifest.json";
+
+/// The human-readable description, relative to Cargo's home directory.
+pub const README: &'static str = "CACHE-README";
+
+const README_CONTENTS: &'static str = "\
+This directory is Cargo's home. Besides configuration and installed binaries
+it holds caches of everything Cargo has downloaded:
+
+    git/db/<repo>/              bare clones of git dependencies
+    git/checkouts/<repo>/<rev>  checkouts of the revisions which are used
+    registry/index/<registry>/  copies of registry indexes
+    registry/cache/<registry>/  downloaded .crate files
+    registry/src/<registry>/    unpacked .crate files
+    build-cache/<hash>/         compiled dependencies shared by all projects,
+                                if `build.shared-cache` is enabled
+
+All of it can be downloaded again, so it's safe to delete. `cargo cache gc`
+frees up space without having to download anything again.
+
+`cache-manifest.json` describes every entry: where it came from and when it
+was last used.
+";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheManifest {
+    /// The layout version of the caches, see `layout_version::HOME`.
+    pub layout_version: u32,
+    /// The version of Cargo which last wrote to the caches.
+    pub cargo_version: String,
+    /// Every entry, keyed by its path relative to Cargo's home directory with
+    /// `/` separators.
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheEntry {
+    /// What the entry is, e.g. `git-checkout` or `registry-crate`.
+    pub kind: String,
+    /// The URL of the git repository or registry it came from.
+    pub url: String,
+    /// The revision of git checkouts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// When the entry was last used, in seconds since the Unix epoch.
+    pub last_used: u64,
+}
+
+impl CacheEntry {
+    pub fn new(kind: &str, url: &str, rev: Option<&str>) -> CacheEntry {
+        CacheEntry {
+            kind: kind.to_string(),
+            url: url.to_string(),
+            rev: rev.map(|s| s.to_string()),
+            last_used: now(),
+        }
+    }
+}
+
+/// The seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// The key of `path` in `CacheManifest::entries`, none if it isn't in Cargo's
+/// home directory.
+pub fn key(config: &Config, path: &Path) -> Option<String> {
+    let home = config.home().clone().into_path_unlocked();
+    path.strip_prefix(&home).ok().map(|relative| {
+        relative.iter()
+                .map(|c| c.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/")
+    })
+}
+
+/// Reads the manifest, which is empty if it hasn't been written yet.
+pub fn load(config: &Config) -> CargoResult<CacheManifest> {
+    let path = config.home().clone().into_path_unlocked().join(MANIFEST);
+    if !path.exists() {
+        return Ok(CacheManifest::default())
+    }
+    let mut lock = config.home().open_ro(MANIFEST, config, "cache manifest")?;
+    read(&mut lock, &path)
+}
+
+/// Updates the manifest with `f`, which is handed its current contents.
+///
+/// Whoever calls this must make sure that nothing is removed from the cache
+/// while this is in progress, usually by holding the package cache lock.
+/// Other Cargo processes may however record their own uses, so the manifest
+/// is locked by itself while it's being updated.
+pub fn update<F>(config: &Config, f: F) -> CargoResult<()>
+    where F: FnOnce(&mut CacheManifest)
+{
+    let home = config.home().clone().into_path_unlocked();
+    if !home.exists() {
+        return Ok(())
+    }
+    let path = home.join(MANIFEST);
+    let mut lock = config.home().open_rw(MANIFEST, config, "cache manifest")?;
+    let mut manifest = read(&mut lock, &path)?;
+    f(&mut manifest);
+    manifest.layout_version = layout_version::HOME;
+    manifest.cargo_version = version().to_string();
+
+    let json = serde_json::to_string_pretty(&manifest)?;
+    lock.seek(SeekFrom::Start(0))?;
+    lock.file().set_len(0)?;
+    lock.write_all(json.as_bytes())?;
+    lock.write_all(b"\n")?;
+
+    let readme = home.join(README);
+    if !readme.exists() {
+        // The manifest is fine without it, so failing to write it is no
+        // reason to fail anything.
+        let _ = paths::write(&readme, README_CONTENTS.as_bytes());
+    }
+    Ok(())
+}
+
+fn read<R: Read>(file: &mut R, path: &Path) -> CargoResult<CacheManifest> {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    if contents.trim().is_empty() {
+        return Ok(CacheManifest::default())
+    }
+    serde_json::from_str(&contents).chain_err(|| {
+        format!("failed to parse the cache manifest `{}`", path.display())
+    })
+}
@@ -68,6 +68,18 @@ impl CfgExpr {
             CfgExpr::Value(ref e) => cfg.contains(e),
         }
     }
+
+    /// Whether `key` is used anywhere in the expression, either on its own or
+    /// as `key = "..."`.
+    pub fn mentions(&self, key: &str) -> bool {
+        match *self {
+            CfgExpr::Not(ref e) => e.mentions(key),
+            CfgExpr::All(ref e) |
+            CfgExpr::Any(ref e) => e.iter().any(|e| e.mentions(key)),
+            CfgExpr::Value(Cfg::Name(ref n)) |
+            CfgExpr::Value(Cfg::KeyPair(ref n, _)) => n == key,
+        }
+    }
 }
 
 impl FromStr for CfgExpr {
@@ -1,5 +1,5 @@
-use std::cell::{RefCell, RefMut};
-use std::collections::HashSet;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{BTreeMap, HashSet};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::hash_map::HashMap;
 use std::env;
@@ -11,6 +11,7 @@ use std::mem;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Once, ONCE_INIT};
+use std::time::Duration;
 
 use curl::easy::Easy;
 use jobserver;
@@ -21,10 +22,12 @@ use core::shell::Verbosity;
 use core::{Shell, CliUnstable};
 use ops;
 use util::Rustc;
+use util::cache_manifest::{self, CacheEntry};
 use util::errors::{CargoResult, CargoResultExt, CargoError, internal};
+use util::layout_version;
 use util::paths;
 use util::toml as cargo_toml;
-use util::{Filesystem, LazyCell};
+use util::{FileLock, Filesystem, LazyCell};
 
 use self::ConfigValue as CV;
 
@@ -42,6 +45,8 @@ pub struct Config {
     rustc: LazyCell<Rustc>,
     /// A collection of configuration options
     values: LazyCell<HashMap<String, ConfigValue>>,
+    /// Values given with `--config`, which take precedence over the files
+    cli_config: Option<ConfigValue>,
     /// The current working directory of cargo
     cwd: PathBuf,
     /// The location of the cargo executable (path to current process)
@@ -54,12 +59,91 @@ pub struct Config {
     frozen: bool,
     /// `locked` is set if we should not update lock files
     locked: bool,
+    /// `require_signatures` is set if every downloaded package must be signed
+    require_signatures: bool,
+    /// `refresh_index` is set if registry indexes are updated however recently
+    /// they were updated before
+    refresh_index: bool,
     /// A global static IPC control mechanism (used for managing parallel builds)
     jobserver: Option<jobserver::Client>,
     /// Cli flags of the form "-Z something"
     cli_flags: CliUnstable,
     /// A handle on curl easy mode for http calls
     easy: LazyCell<RefCell<Easy>>,
+    /// Bytes fetched over the network so far, keyed by the remote's url
+    fetched: RefCell<BTreeMap<String, u64>>,
+    /// The lock on the package cache, while any shared or exclusive
+    /// `PackageCacheLock` is alive
+    package_cache_lock: RefCell<Option<FileLock>>,
+    /// The lock on downloads into the package cache, while any download or
+    /// exclusive `PackageCacheLock` is alive
+    package_download_lock: RefCell<Option<FileLock>>,
+    /// How many shared, download and exclusive `PackageCacheLock`s are alive
+    package_cache_holders: Cell<(usize, usize, usize)>,
+    /// Entries of the package cache used so far which haven't been written
+    /// to the cache manifest yet, keyed like `CacheManifest::entries`
+    cache_uses: RefCell<BTreeMap<String, CacheEntry>>,
+}
+
+/// The file in Cargo's home directory which is locked to access the package
+/// cache, see `Config::acquire_package_cache_lock`.
+const PACKAGE_CACHE_LOCK: &'static str = ".package-cache";
+
+/// The file in Cargo's home directory which is locked to add to the package
+/// cache, see `Config::acquire_package_cache_lock`.
+const PACKAGE_DOWNLOAD_LOCK: &'static str = ".package-cache-download";
+
+/// How the package cache is locked, see `Config::acquire_package_cache_lock`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheLockMode {
+    /// For reading from the cache, e.g. compiling the sources in it. Any
+    /// number of Cargo processes can hold this at the same time.
+    Shared,
+    /// For adding to the cache, e.g. updating an index or downloading and
+    /// unpacking a crate. Only a single process can hold this, but it doesn't
+    /// wait for processes reading from the cache.
+    Download,
+    /// For removing from the cache. Only a single process can hold this, and
+    /// only while no other one holds the lock in any mode.
+    Exclusive,
+}
+
+/// A guard keeping the package cache locked until it's dropped, see
+/// `Config::acquire_package_cache_lock`.
+pub struct PackageCacheLock<'cfg> {
+    config: &'cfg Config,
+    mode: CacheLockMode,
+}
+
+impl<'cfg> Drop for PackageCacheLock<'cfg> {
+    fn drop(&mut self) {
+        let (shared, download, exclusive) = self.config.package_cache_holders.get();
+        let (shared, download, exclusive) = match self.mode {
+            CacheLockMode::Shared => (shared - 1, download, exclusive),
+            CacheLockMode::Download => (shared, download - 1, exclusive),
+            CacheLockMode::Exclusive => (shared, download, exclusive - 1),
+        };
+        self.config.package_cache_holders.set((shared, download, exclusive));
+        if shared == 0 && download == 0 && exclusive == 0 {
+            // Written while the cache is still locked, so that nothing which
+            // was used is removed in the meantime.
+            if let Err(e) = self.config.flush_cache_uses() {
+                debug!("failed to update the cache manifest: {}", e);
+            }
+        }
+        if download == 0 && exclusive == 0 {
+            *self.config.package_download_lock.borrow_mut() = None;
+        }
+        let mut slot = self.config.package_cache_lock.borrow_mut();
+        if shared == 0 && exclusive == 0 {
+            *slot = None;
+        } else if exclusive == 0 && self.mode == CacheLockMode::Exclusive {
+            if let Some(ref mut lock) = *slot {
+                // Going back to a shared lock never has to wait.
+                let _ = lock.relock(false, self.config, "package cache");
+            }
+        }
+    }
 }
 
 impl Config {
@@ -83,11 +167,14 @@ impl Config {
             rustc: LazyCell::new(),
             cwd: cwd,
             values: LazyCell::new(),
+            cli_config: None,
             cargo_exe: LazyCell::new(),
             rustdoc: LazyCell::new(),
             extra_verbose: false,
             frozen: false,
             locked: false,
+            require_signatures: false,
+            refresh_index: false,
             jobserver: unsafe {
                 if GLOBAL_JOBSERVER.is_null() {
                     None
@@ -97,6 +184,11 @@ impl Config {
             },
             cli_flags: CliUnstable::default(),
             easy: LazyCell::new(),
+            fetched: RefCell::new(BTreeMap::new()),
+            cache_uses: RefCell::new(BTreeMap::new()),
+            package_cache_lock: RefCell::new(None),
+            package_download_lock: RefCell::new(None),
+            package_cache_holders: Cell::new((0, 0, 0)),
         }
     }
 
@@ -135,6 +227,71 @@ impl Config {
         self.home_path.join("registry").join("src")
     }
 
+    /// Locks the package cache in Cargo's home directory, that is the git
+    /// databases and checkouts along with the registry indices, downloaded
+    /// crates and their unpacked sources, until the returned guard is dropped.
+    ///
+    /// There are two locks. Builds hold the first one shared so that any
+    /// number of them can read from the cache at once, and `cargo cache gc`
+    /// holds it exclusively so that nothing is removed from under a build.
+    /// Updates and downloads only add to the cache, which doesn't get in the
+    /// way of builds, so they just hold the second one exclusively so that
+    /// only one process writes to the cache at a time. This way a build can
+    /// download what it's missing while other builds read from the cache.
+    /// Removing from the cache holds both locks, and the first is always
+    /// taken before the second, so processes can't end up waiting for each
+    /// other.
+    ///
+    /// The locks are only taken once per process, the guards just keep count.
+    /// Asking for exclusive access while the lock is held shared upgrades it,
+    /// which isn't atomic, and it's downgraded again once the last exclusive
+    /// guard is dropped.
+    ///
+    /// If the home directory can't be written to, nothing can modify the
+    /// cache anyway, so shared access is granted without a lock.
+    pub fn acquire_package_cache_lock(&self, mode: CacheLockMode)
+                                      -> CargoResult<PackageCacheLock> {
+        let msg = "package cache";
+        let (shared, download, exclusive) = self.package_cache_holders.get();
+        {
+            let mut slot = self.package_cache_lock.borrow_mut();
+            if let Some(ref mut lock) = *slot {
+                if mode == CacheLockMode::Exclusive && exclusive == 0 {
+                    lock.relock(true, self, msg)?;
+                }
+            } else if mode == CacheLockMode::Exclusive {
+                *slot = Some(self.home_path.open_rw(PACKAGE_CACHE_LOCK, self, msg)?);
+            } else if mode == CacheLockMode::Shared && shared == 0 {
+                match self.home_path.open_shared_create(PACKAGE_CACHE_LOCK, self, msg) {
+                    Ok(lock) => *slot = Some(lock),
+                    Err(e) => {
+                        let path = self.home_path.join(PACKAGE_CACHE_LOCK);
+                        if path.into_path_unlocked().exists() {
+                            return Err(e)
+                        }
+                    }
+                }
+            }
+        }
+        if mode != CacheLockMode::Shared && download == 0 && exclusive == 0 {
+            let lock = self.home_path.open_rw(PACKAGE_DOWNLOAD_LOCK, self,
+                                              "package cache downloads")?;
+            *self.package_download_lock.borrow_mut() = Some(lock);
+        }
+        self.package_cache_holders.set(match mode {
+            CacheLockMode::Shared => (shared + 1, download, exclusive),
+            CacheLockMode::Download => (shared, download + 1, exclusive),
+            CacheLockMode::Exclusive => (shared, download, exclusive + 1),
+        });
+        let lock = PackageCacheLock { config: self, mode: mode };
+        // The caches are about to be modified, and only get stamped with
+        // their layout version then, see `layout_version`.
+        if mode != CacheLockMode::Shared && download == 0 && exclusive == 0 {
+            layout_version::stamp_home(self)?;
+        }
+        Ok(lock)
+    }
+
     /// Get a reference to the shell, for e.g. writing error messages
     pub fn shell(&self) -> RefMut<Shell> {
         self.shell.borrow_mut()
@@ -148,7 +305,24 @@ impl Config {
     /// Get the path to the `rustc` executable
     pub fn rustc(&self) -> CargoResult<&Rustc> {
         self.rustc.get_or_try_init(|| Rustc::new(self.get_tool("rustc")?,
-                                                 self.maybe_get_tool("rustc_wrapper")?))
+                                                 self.rustc_wrapper()?))
+    }
+
+    /// The program rustc is run through, e.g. a compiler cache, from
+    /// `RUSTC_WRAPPER` or `build.rustc-wrapper`. An empty `RUSTC_WRAPPER`
+    /// turns off a configured wrapper.
+    fn rustc_wrapper(&self) -> CargoResult<Option<PathBuf>> {
+        if let Some(wrapper) = env::var_os("RUSTC_WRAPPER") {
+            if wrapper.is_empty() {
+                return Ok(None)
+            }
+            return Ok(Some(PathBuf::from(wrapper)))
+        }
+        if let Some(wrapper) = self.get_path("build.rustc-wrapper")? {
+            return Ok(Some(wrapper.val))
+        }
+        // The key was spelled with an underscore at first.
+        Ok(self.get_path("build.rustc_wrapper")?.map(|wrapper| wrapper.val))
     }
 
     /// Get the path to the `cargo` executable
@@ -267,6 +441,10 @@ impl Config {
     fn get_env<V: FromStr>(&self, key: &str) -> CargoResult<Option<Value<V>>>
         where CargoError: From<V::Err>
     {
+        // Values given with `--config` take precedence over the environment.
+        if self.is_cli_config(key) {
+            return Ok(None)
+        }
         let key = key.replace(".", "_")
                      .replace("-", "_")
                      .chars()
@@ -511,6 +689,101 @@ impl Config {
         !self.frozen && !self.locked
     }
 
+    /// Requires every package downloaded from a registry or git to be signed
+    /// by a trusted key, as `--require-signatures` does.
+    pub fn set_require_signatures(&mut self, require: bool) {
+        self.require_signatures = require;
+    }
+
+    /// Whether packages without a valid signature are rejected, either through
+    /// `--require-signatures` or the `signatures.require` key.
+    pub fn signatures_required(&self) -> CargoResult<bool> {
+        if self.require_signatures {
+            return Ok(true)
+        }
+        Ok(self.get_bool("signatures.require")?.map(|v| v.val).unwrap_or(false))
+    }
+
+    /// Updates registry indexes even when they were updated within
+    /// `registry.index-max-staleness`, as `--refresh` does.
+    pub fn set_refresh_index(&mut self, refresh: bool) {
+        self.refresh_index = refresh;
+    }
+
+    /// How long a registry index is used as is after it was last updated,
+    /// rather than being updated again. This is `registry.index-max-staleness`
+    /// minutes, or zero when it isn't set or with `--refresh`.
+    pub fn index_max_staleness(&self) -> CargoResult<Duration> {
+        if self.refresh_index {
+            return Ok(Duration::from_secs(0))
+        }
+        match self.get_i64("registry.index-max-staleness")? {
+            Some(v) => {
+                if v.val < 0 {
+                    bail!("registry.index-max-staleness must not be negative, \
+                           but found {} in {}", v.val, v.definition)
+                }
+                Ok(Duration::from_secs(v.val as u64 * 60))
+            }
+            None => Ok(Duration::from_secs(0)),
+        }
+    }
+
+    /// Sets the `KEY=VALUE` overrides given with `--config`, where `KEY` is
+    /// a dotted path like `term.verbose` and `VALUE` a TOML value. They take
+    /// precedence over the configuration files and the environment, later
+    /// ones over earlier ones, so this has to be called before any
+    /// configuration is read.
+    pub fn set_cli_config(&mut self, overrides: &[String]) -> CargoResult<()> {
+        if self.values.borrow().is_some() {
+            return Err("Config values already found".into());
+        }
+        // Relative paths are relative to the current directory, see
+        // `Definition::root`.
+        let path = self.cwd.join(".cargo").join("--config");
+        let mut cfg = CV::Table(HashMap::new(), path.clone());
+        for arg in overrides {
+            let (key, value) = match arg.find('=') {
+                Some(i) => (arg[..i].trim(), arg[i + 1..].trim()),
+                None => bail!("invalid `--config` argument `{}`, expected `KEY=VALUE`", arg),
+            };
+            if key.split('.').any(|k| k.is_empty()) {
+                bail!("invalid `--config` argument `{}`, expected `KEY=VALUE` where \
+                       `KEY` is like `term.verbose`", arg)
+            }
+            let mut value = match format!("value = {}", value).parse::<toml::Value>() {
+                Ok(toml::Value::Table(mut t)) => t.remove("value").unwrap(),
+                _ => bail!("invalid `--config` argument `{}`, expected the value to be \
+                            TOML, like `true`, `1` or `\"text\"`", arg),
+            };
+            for k in key.split('.').rev() {
+                let mut table = toml::value::Table::new();
+                table.insert(k.to_string(), value);
+                value = toml::Value::Table(table);
+            }
+            cfg.merge_over(CV::from_toml(&path, value)?);
+        }
+        self.cli_config = Some(cfg);
+        Ok(())
+    }
+
+    fn is_cli_config(&self, key: &str) -> bool {
+        let mut val = match self.cli_config {
+            Some(ref val) => val,
+            None => return false,
+        };
+        for part in key.split('.') {
+            val = match *val {
+                CV::Table(ref map, _) => match map.get(part) {
+                    Some(val) => val,
+                    None => return false,
+                },
+                _ => return false,
+            };
+        }
+        true
+    }
+
     /// Loads configuration from the filesystem
     pub fn load_values(&self) -> CargoResult<HashMap<String, ConfigValue>> {
         let mut cfg = CV::Table(HashMap::new(), PathBuf::from("."));
@@ -539,6 +812,9 @@ impl Config {
         }).chain_err(|| "Couldn't load Cargo configuration")?;
 
         self.load_credentials(&mut cfg)?;
+        if let Some(ref cli_config) = self.cli_config {
+            cfg.merge_over(cli_config.clone());
+        }
         match cfg {
             CV::Table(map, _) => Ok(map),
             _ => unreachable!(),
@@ -565,7 +841,7 @@ impl Config {
             format!("could not parse TOML configuration in `{}`", credentials.display())
         })?;
 
-        let value = CV::from_toml(&credentials, toml).chain_err(|| {
+        let mut value = CV::from_toml(&credentials, toml).chain_err(|| {
             format!("failed to load TOML configuration from `{}`", credentials.display())
         })?;
 
@@ -574,6 +850,18 @@ impl Config {
             _ => unreachable!(),
         };
 
+        // The tokens of alternative registries are in `[registries.<name>]`,
+        // next to where they're configured, everything else is crates.io's.
+        let registries = match value {
+            CV::Table(ref mut new, _) => new.remove("registries"),
+            _ => unreachable!(),
+        };
+        if let Some(registries) = registries {
+            cfg.entry("registries".into())
+               .or_insert_with(|| CV::Table(HashMap::new(), PathBuf::from(".")))
+               .merge(registries)?;
+        }
+
         let registry = cfg.entry("registry".into())
                           .or_insert_with(|| CV::Table(HashMap::new(), PathBuf::from(".")));
 
@@ -624,6 +912,33 @@ impl Config {
             ops::http_handle(self).map(RefCell::new)
         })
     }
+
+    /// Records that `bytes` were fetched over the network from `remote`.
+    pub fn record_fetched(&self, remote: &str, bytes: u64) {
+        *self.fetched.borrow_mut().entry(remote.to_string()).or_insert(0) += bytes;
+    }
+
+    /// Records that the entry of the package cache at `path` was used, see
+    /// `util::cache_manifest`.
+    pub fn record_cache_use(&self, path: &Path, entry: CacheEntry) {
+        if let Some(key) = cache_manifest::key(self, path) {
+            self.cache_uses.borrow_mut().insert(key, entry);
+        }
+    }
+
+    /// Writes the uses recorded with `record_cache_use` to the cache manifest.
+    fn flush_cache_uses(&self) -> CargoResult<()> {
+        let uses = mem::replace(&mut *self.cache_uses.borrow_mut(), BTreeMap::new());
+        if uses.is_empty() {
+            return Ok(())
+        }
+        cache_manifest::update(self, |manifest| manifest.entries.extend(uses))
+    }
+
+    /// Bytes fetched over the network by this process so far, per remote.
+    pub fn fetched(&self) -> BTreeMap<String, u64> {
+        self.fetched.borrow().clone()
+    }
 }
 
 #[derive(Eq, PartialEq, Clone, Copy)]
@@ -827,6 +1142,28 @@ impl ConfigValue {
         }
     }
 
+    /// Merges `from` on top of this value. Unlike with `merge` the values in
+    /// `from` win, even if they are of a different type, except that lists
+    /// are still concatenated.
+    fn merge_over(&mut self, from: ConfigValue) {
+        match (self, from) {
+            (&mut CV::List(ref mut old, _), CV::List(ref mut new, _)) => {
+                let new = mem::replace(new, Vec::new());
+                old.extend(new.into_iter());
+            }
+            (&mut CV::Table(ref mut old, _), CV::Table(ref mut new, _)) => {
+                let new = mem::replace(new, HashMap::new());
+                for (key, value) in new {
+                    match old.entry(key) {
+                        Occupied(mut entry) => entry.get_mut().merge_over(value),
+                        Vacant(entry) => { entry.insert(value); }
+                    }
+                }
+            }
+            (old, new) => *old = new,
+        }
+    }
+
     pub fn expected<T>(&self, wanted: &str, key: &str) -> CargoResult<T> {
         Err(format!("expected a {}, but found a {} for `{}` in {}",
                     wanted, self.desc(), key,
@@ -884,8 +1221,34 @@ fn walk_tree<F>(pwd: &Path, mut walk: F) -> CargoResult<()>
     Ok(())
 }
 
+/// Saves `token` in the credentials file, as the token of crates.io or of the
+/// alternative registry named `registry`.
 pub fn save_credentials(cfg: &Config,
-                       token: String) -> CargoResult<()> {
+                       token: String,
+                       registry: Option<&str>) -> CargoResult<()> {
+    edit_credentials(cfg, registry, |table, path| {
+        table.insert("token".to_string(),
+                     ConfigValue::String(token, path.to_path_buf()).into_toml());
+    })
+}
+
+/// Removes the token of crates.io or of the alternative registry named
+/// `registry` from the credentials file, if it's in there.
+pub fn remove_credentials(cfg: &Config, registry: Option<&str>) -> CargoResult<()> {
+    let path = cfg.home_path.join("credentials").into_path_unlocked();
+    if !path.exists() {
+        return Ok(())
+    }
+    edit_credentials(cfg, registry, |table, _| {
+        table.remove("token");
+    })
+}
+
+/// Rewrites the credentials file with `edit` applied to the table of the
+/// registry, which is created if it doesn't exist yet.
+fn edit_credentials<F>(cfg: &Config, registry: Option<&str>, edit: F) -> CargoResult<()>
+    where F: FnOnce(&mut toml::value::Table, &Path)
+{
     let mut file = {
         cfg.home_path.create_dir()?;
         cfg.home_path.open_rw(Path::new("credentials"), cfg,
@@ -898,10 +1261,21 @@ pub fn save_credentials(cfg: &Config,
                       file.path().display())
     })?;
     let mut toml = cargo_toml::parse(&contents, file.path(), cfg)?;
-    toml.as_table_mut()
-        .unwrap()
-        .insert("token".to_string(),
-                ConfigValue::String(token, file.path().to_path_buf()).into_toml());
+    {
+        let mut table = toml.as_table_mut().unwrap();
+        if let Some(registry) = registry {
+            for key in &["registries", registry] {
+                // Moved out of `table` so that it can be replaced.
+                let value = {table}.entry(key.to_string())
+                                 .or_insert_with(|| toml::Value::Table(Default::default()));
+                table = match *value {
+                    toml::Value::Table(ref mut table) => table,
+                    _ => bail!("`{}` in `{}` isn't a table", key, file.path().display()),
+                };
+            }
+        }
+        edit(table, file.path());
+    }
 
     let contents = toml.to_string();
     file.seek(SeekFrom::Start(0))?;
@@ -0,0 +1,248 @@
+//! Storage for registry API tokens.
+//!
+//! `cargo login` hands the token to a `CredentialProvider`, chosen with the
+//! `registry.credential-provider` configuration key:
+//!
+//! * `os` (the default) stores tokens in the credential store of the operating
+//!   system, the Keychain on macOS and the Secret Service (through
+//!   `secret-tool`) elsewhere. If there's no such store, or it can't be used,
+//!   the credentials file is used instead.
+//! * `file` always uses `$CARGO_HOME/credentials`, which is only readable by
+//!   its owner.
+//! * Anything else is a command which is run as `<command> get <registry>` to
+//!   print the token of a registry and `<command> store <registry>` to store
+//!   the token given on its stdin, for external helpers.
+//!
+//! Tokens are stored under the name of their registry, `crates-io` for
+//! crates.io and the name in `[registries.<name>]` for alternative
+//! registries. A `registry.token`, or `registries.<name>.token`, in the
+//! configuration always takes precedence over any of these. A token which an
+//! earlier `cargo login` left in the credentials file is moved to the
+//! provider on the next login.
+
+use std::str;
+
+use util::{process, CargoResult, CargoResultExt, Config, ProcessBuilder};
+use util::config;
+
+/// The name the token of crates.io is stored under.
+pub const CRATES_IO: &'static str = "crates-io";
+
+/// Somewhere tokens can be stored and later read back from.
+pub trait CredentialProvider {
+    /// A description of where tokens end up, for messages.
+    fn name(&self) -> String;
+
+    /// Returns the token stored for `registry`, if any.
+    fn get(&self, registry: &str) -> CargoResult<Option<String>>;
+
+    /// Stores `token` as the token of `registry`, replacing any previous one.
+    fn store(&self, registry: &str, token: &str) -> CargoResult<()>;
+}
+
+/// Returns the provider configured with `registry.credential-provider`.
+pub fn provider<'cfg>(config: &'cfg Config) -> CargoResult<Box<CredentialProvider + 'cfg>> {
+    let key = "registry.credential-provider";
+    let value = match config.get_string(key)? {
+        Some(value) => value,
+        None => return Ok(Box::new(Os { config: config })),
+    };
+    match &value.val[..] {
+        "os" => Ok(Box::new(Os { config: config })),
+        "file" => Ok(Box::new(File { config: config })),
+        command => {
+            let mut words = command.split_whitespace();
+            let mut process = match words.next() {
+                Some(program) => process(program),
+                None => bail!("`{}` in {} is empty", key, value.definition),
+            };
+            for word in words {
+                process.arg(word);
+            }
+            Ok(Box::new(Helper { process: process }))
+        }
+    }
+}
+
+/// The credentials file in Cargo's home directory.
+struct File<'cfg> {
+    config: &'cfg Config,
+}
+
+impl<'cfg> CredentialProvider for File<'cfg> {
+    fn name(&self) -> String {
+        format!("`{}`", self.config.home().join("credentials").display())
+    }
+
+    fn get(&self, _registry: &str) -> CargoResult<Option<String>> {
+        // The credentials file is merged into the configuration when it's
+        // loaded, so there's nothing which isn't in `registry.token`, or
+        // `registries.<name>.token`, already.
+        Ok(None)
+    }
+
+    fn store(&self, registry: &str, token: &str) -> CargoResult<()> {
+        let registry = if registry == CRATES_IO { None } else { Some(registry) };
+        config::save_credentials(self.config, token.to_string(), registry)
+    }
+}
+
+/// The credential store of the operating system, falling back to the
+/// credentials file if it can't be used.
+struct Os<'cfg> {
+    config: &'cfg Config,
+}
+
+impl<'cfg> CredentialProvider for Os<'cfg> {
+    fn name(&self) -> String {
+        os::NAME.to_string()
+    }
+
+    fn get(&self, registry: &str) -> CargoResult<Option<String>> {
+        match os::get(registry) {
+            Ok(token) => Ok(token),
+            Err(e) => {
+                debug!("failed to read token from {}: {}", os::NAME, e);
+                Ok(None)
+            }
+        }
+    }
+
+    fn store(&self, registry: &str, token: &str) -> CargoResult<()> {
+        match os::store(registry, token) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                debug!("failed to store token in {}: {}", os::NAME, e);
+                let file = File { config: self.config };
+                self.config.shell().warn(format!(
+                    "the token could not be stored in the {}, saving it in {} \
+                     instead", os::NAME, file.name()))?;
+                file.store(registry, token)
+            }
+        }
+    }
+}
+
+/// An external program, see the module documentation.
+struct Helper {
+    process: ProcessBuilder,
+}
+
+impl CredentialProvider for Helper {
+    fn name(&self) -> String {
+        self.process.to_string()
+    }
+
+    fn get(&self, registry: &str) -> CargoResult<Option<String>> {
+        let output = self.process.clone().arg("get").arg(registry)
+            .exec_with_output()
+            .chain_err(|| format!("failed to get the token of `{}` from {}",
+                                  registry, self.name()))?;
+        let token = str::from_utf8(&output.stdout).chain_err(|| {
+            format!("{} printed a token which isn't valid UTF-8", self.name())
+        })?.trim();
+        if token.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(token.to_string()))
+        }
+    }
+
+    fn store(&self, registry: &str, token: &str) -> CargoResult<()> {
+        self.process.clone().arg("store").arg(registry)
+            .exec_with_input(token.as_bytes())
+            .chain_err(|| format!("failed to store the token of `{}` with {}",
+                                  registry, self.name()))?;
+        Ok(())
+    }
+}
+
+/// Tokens are stored under this service name, with the registry as account.
+#[cfg_attr(windows, allow(dead_code))]
+const SERVICE: &'static str = "cargo-registry";
+
+#[cfg(target_os = "macos")]
+mod os {
+    use util::{process, CargoResult};
+    use super::SERVICE;
+
+    pub const NAME: &'static str = "macOS Keychain";
+
+    pub fn get(registry: &str) -> CargoResult<Option<String>> {
+        let output = process("security")
+            .arg("find-generic-password")
+            .arg("-s").arg(SERVICE)
+            .arg("-a").arg(registry)
+            .arg("-w")
+            .exec_with_output();
+        // `security` fails when there's no such password.
+        match output {
+            Ok(output) => Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string())),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn store(registry: &str, token: &str) -> CargoResult<()> {
+        // The command is read from stdin so the token doesn't end up on the
+        // command line of a process, where anyone could see it.
+        let command = format!("add-generic-password -U -s {} -a {} -w {}\n",
+                              quote(SERVICE), quote(registry), quote(token));
+        process("security").arg("-i").exec_with_input(command.as_bytes())?;
+        Ok(())
+    }
+
+    fn quote(s: &str) -> String {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod os {
+    use util::{process, CargoResult};
+    use super::SERVICE;
+
+    pub const NAME: &'static str = "Secret Service";
+
+    pub fn get(registry: &str) -> CargoResult<Option<String>> {
+        let output = process("secret-tool")
+            .arg("lookup")
+            .arg("service").arg(SERVICE)
+            .arg("registry").arg(registry)
+            .exec_with_output()?;
+        let token = String::from_utf8_lossy(&output.stdout);
+        let token = token.trim();
+        if token.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(token.to_string()))
+        }
+    }
+
+    pub fn store(registry: &str, token: &str) -> CargoResult<()> {
+        // `secret-tool` reads the secret from stdin.
+        process("secret-tool")
+            .arg("store")
+            .arg(format!("--label=Cargo token for {}", registry))
+            .arg("service").arg(SERVICE)
+            .arg("registry").arg(registry)
+            .exec_with_input(token.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// There are no bindings to the Credential Manager in Cargo yet, so on Windows
+/// tokens always end up in the credentials file.
+#[cfg(windows)]
+mod os {
+    use util::CargoResult;
+
+    pub const NAME: &'static str = "Windows Credential Manager";
+
+    pub fn get(_registry: &str) -> CargoResult<Option<String>> {
+        Ok(None)
+    }
+
+    pub fn store(_registry: &str, _token: &str) -> CargoResult<()> {
+        bail!("not supported yet")
+    }
+}
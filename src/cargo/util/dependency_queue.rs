@@ -34,6 +34,20 @@ pub struct DependencyQueue<K: Eq + Hash, V> {
     /// The packages which are currently being built, waiting for a call to
     /// `finish`.
     pending: HashSet<K>,
+
+    /// Pairs of a package and one of its dependencies where the package only
+    /// needs the dependency's metadata, and so can be built as soon as
+    /// `metadata_ready` is called for the dependency.
+    metadata_deps: HashSet<(K, K)>,
+}
+
+/// What a package needs of one of its dependencies before it can be built.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Artifact {
+    /// Just its metadata, which may be available before it's fully built.
+    Metadata,
+    /// The dependency has to be completely built.
+    All,
 }
 
 /// Indication of the freshness of a package.
@@ -66,6 +80,7 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
             reverse_dep_map: HashMap::new(),
             dirty: HashSet::new(),
             pending: HashSet::new(),
+            metadata_deps: HashSet::new(),
         }
     }
 
@@ -77,7 +92,7 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
                  fresh: Freshness,
                  key: K,
                  value: V,
-                 dependencies: &[K]) -> &mut V {
+                 dependencies: &[(K, Artifact)]) -> &mut V {
         let slot = match self.dep_map.entry(key.clone()) {
             Occupied(v) => return &mut v.into_mut().1,
             Vacant(v) => v,
@@ -88,11 +103,14 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
         }
 
         let mut my_dependencies = HashSet::new();
-        for dep in dependencies {
+        for &(ref dep, artifact) in dependencies {
             my_dependencies.insert(dep.clone());
             let rev = self.reverse_dep_map.entry(dep.clone())
                                           .or_insert_with(HashSet::new);
             rev.insert(key.clone());
+            if artifact == Artifact::Metadata {
+                self.metadata_deps.insert((key.clone(), dep.clone()));
+            }
         }
         &mut slot.insert((my_dependencies, value)).1
     }
@@ -124,6 +142,35 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
         self.dep_map.len() + self.pending.len()
     }
 
+    /// Indicate that the metadata of a package which is still being built is
+    /// available.
+    ///
+    /// Packages which only need the metadata of this one no longer wait for
+    /// it, possibly allowing the next invocation of `dequeue` to return them.
+    /// Hearing about the metadata again, or only once the package has been
+    /// built, changes nothing.
+    pub fn metadata_ready(&mut self, key: &K, fresh: Freshness) {
+        if !self.pending.contains(key) {
+            return
+        }
+        let reverse_deps = match self.reverse_dep_map.get(key) {
+            Some(deps) => deps,
+            None => return,
+        };
+        for dep in reverse_deps.iter() {
+            if !self.metadata_deps.contains(&(dep.clone(), key.clone())) {
+                continue
+            }
+            if fresh == Dirty {
+                self.dirty.insert(dep.clone());
+            }
+            // It may have been dequeued already.
+            if let Some(&mut (ref mut deps, _)) = self.dep_map.get_mut(dep) {
+                deps.remove(key);
+            }
+        }
+    }
+
     /// Indicate that a package has been built.
     ///
     /// This function will update the dependency queue with this information,
@@ -138,7 +185,14 @@ impl<K: Hash + Eq + Clone, V> DependencyQueue<K, V> {
             if fresh == Dirty {
                 self.dirty.insert(dep.clone());
             }
-            assert!(self.dep_map.get_mut(dep).unwrap().0.remove(key));
+            // Packages which only needed the metadata may have stopped
+            // waiting for this one, or even started already.
+            let metadata_only = self.metadata_deps.contains(&(dep.clone(), key.clone()));
+            let removed = match self.dep_map.get_mut(dep) {
+                Some(&mut (ref mut deps, _)) => deps.remove(key),
+                None => false,
+            };
+            assert!(removed || metadata_only);
         }
     }
 }
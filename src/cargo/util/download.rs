@@ -0,0 +1,275 @@
+//! Fetching many files over HTTP at once, see `download_all`.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use curl::easy::HttpVersion;
+use curl::multi::{EasyHandle, Multi};
+use hex::ToHex;
+
+use ops;
+use util::{network, Config, Sha256};
+use util::errors::{CargoError, CargoErrorKind, CargoResult, CargoResultExt};
+
+/// A file to fetch with `download_all`.
+pub struct Download {
+    /// Where to fetch it from.
+    pub url: String,
+    /// Where to write it to. If this already holds the beginning of the file,
+    /// from an earlier attempt which was cut off, only the rest is requested.
+    pub dst: PathBuf,
+}
+
+/// What's been received of a download so far, shared with the callbacks
+/// given to libcurl.
+struct Transfer {
+    file: File,
+    /// Where the next part of the body goes in `file`.
+    offset: u64,
+    /// The status of the last response, none for `file://` URLs.
+    status: Option<u32>,
+    /// How many bytes were received over the network.
+    received: u64,
+    /// The SHA-256 of what's in `file` so far.
+    sha256: Sha256,
+}
+
+/// A download which libcurl is working on.
+struct Active {
+    index: usize,
+    handle: EasyHandle,
+    transfer: Arc<Mutex<Transfer>>,
+    retries: i64,
+}
+
+/// Fetches all of `downloads` and returns the SHA-256 of every file, hex
+/// encoded and in the same order, along with how many bytes were received.
+///
+/// Files are hashed as they're written, so checking them against a checksum
+/// doesn't take reading them back.
+///
+/// Up to `http.max-parallel-downloads` of them are in flight at the same time.
+/// Unless `http.multiplexing` is disabled, they're multiplexed over a shared
+/// HTTP/2 connection when the server supports it, rather than each opening
+/// its own connection.
+///
+/// Every download is retried on its own after spurious network errors, up to
+/// `net.retry` times, asking for just what's still missing with a `Range`
+/// header.
+pub fn download_all(config: &Config, downloads: &[Download])
+                    -> CargoResult<(Vec<String>, u64)> {
+    let multiplexing = config.get_bool("http.multiplexing")?
+                             .map(|v| v.val)
+                             .unwrap_or(true);
+    let max = match config.get_i64("http.max-parallel-downloads")? {
+        Some(v) => {
+            if v.val < 1 {
+                bail!("http.max-parallel-downloads must be at least 1, but found {} in {}",
+                      v.val, v.definition)
+            }
+            v.val as usize
+        }
+        None => 16,
+    };
+    let retries = config.net_retry()?;
+
+    let mut multi = Multi::new();
+    multi.pipelining(false, multiplexing)?;
+    if multiplexing {
+        // Streams are cheap while connections aren't, so don't open many.
+        multi.set_max_host_connections(2)?;
+    }
+
+    let mut pending = (0..downloads.len()).map(|i| (i, retries))
+                                          .collect::<VecDeque<_>>();
+    let mut active: Vec<Option<Active>> = Vec::new();
+    let mut running = 0;
+    let mut received = 0;
+    let mut sha256 = vec![None; downloads.len()];
+    loop {
+        while running < max {
+            let (index, retries) = match pending.pop_front() {
+                Some(next) => next,
+                None => break,
+            };
+            let (mut handle, transfer) = start(config, &multi, &downloads[index],
+                                               multiplexing)?;
+            let token = match active.iter().position(|a| a.is_none()) {
+                Some(token) => token,
+                None => {
+                    active.push(None);
+                    active.len() - 1
+                }
+            };
+            handle.set_token(token)?;
+            active[token] = Some(Active {
+                index: index,
+                handle: handle,
+                transfer: transfer,
+                retries: retries,
+            });
+            running += 1;
+        }
+        if running == 0 {
+            let sha256 = sha256.into_iter().map(|s| s.unwrap()).collect();
+            return Ok((sha256, received))
+        }
+
+        multi.perform()?;
+        let mut finished = Vec::new();
+        multi.messages(|msg| {
+            let token = match msg.token() {
+                Ok(token) => token,
+                Err(_) => return,
+            };
+            if let Some(ref a) = active[token] {
+                if let Some(result) = msg.result_for(&a.handle) {
+                    finished.push((token, result));
+                }
+            }
+        });
+
+        for (token, result) in finished {
+            let a = active[token].take().unwrap();
+            running -= 1;
+            let mut easy = multi.remove(a.handle)?;
+            let download = &downloads[a.index];
+            received += a.transfer.lock().unwrap().received;
+
+            // What was kept from an earlier attempt may be more than what's
+            // there now, in which case it's started over.
+            let code = easy.response_code().unwrap_or(0);
+            if result.is_ok() && code == 416 {
+                a.transfer.lock().unwrap().file.set_len(0)?;
+                pending.push_back((a.index, a.retries));
+                continue
+            }
+
+            let result = result.map_err(CargoError::from).and_then(|()| {
+                match code {
+                    // `file://` URLs don't have a status.
+                    0 | 200 | 206 => Ok(()),
+                    code => {
+                        let url = easy.effective_url()?.unwrap_or(&download.url);
+                        Err(CargoErrorKind::HttpNot200(code, url.to_string()).into())
+                    }
+                }
+            });
+            match result {
+                Ok(()) => {
+                    let hash = a.transfer.lock().unwrap().sha256.finish().to_hex();
+                    sha256[a.index] = Some(hash);
+                }
+                Err(ref e) if network::maybe_spurious(e) && a.retries > 0 => {
+                    let msg = format!("spurious network error ({} tries \
+                                       remaining): {}", a.retries, e);
+                    config.shell().warn(msg)?;
+                    pending.push_back((a.index, a.retries - 1));
+                }
+                Err(e) => {
+                    return Err(e).chain_err(|| {
+                        format!("failed to download from `{}`", download.url)
+                    })
+                }
+            }
+        }
+
+        if running > 0 {
+            multi.wait(&mut [], Duration::from_secs(1))?;
+        }
+    }
+}
+
+/// Hands `download` to `multi`, resuming it if its `dst` isn't empty.
+fn start(config: &Config,
+         multi: &Multi,
+         download: &Download,
+         multiplexing: bool) -> CargoResult<(EasyHandle, Arc<Mutex<Transfer>>)> {
+    let mut file = OpenOptions::new().read(true).write(true).create(true)
+        .open(&download.dst)
+        .chain_err(|| format!("failed to open `{}`", download.dst.display()))?;
+    let offset = file.metadata()?.len();
+
+    // What's there already is part of what's hashed.
+    let mut sha256 = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).chain_err(|| {
+            format!("failed to read `{}`", download.dst.display())
+        })?;
+        if n == 0 {
+            break
+        }
+        sha256.update(&buf[..n]);
+    }
+
+    let transfer = Arc::new(Mutex::new(Transfer {
+        file: file,
+        offset: offset,
+        status: None,
+        received: 0,
+        sha256: sha256,
+    }));
+
+    let mut handle = ops::http_handle(config)?;
+    handle.get(true)?;
+    handle.url(&download.url)?;
+    handle.follow_location(true)?;
+    if multiplexing {
+        // libcurl may have been built without HTTP/2, and then it's just
+        // HTTP/1.1 with a connection per download.
+        let _ = handle.http_version(HttpVersion::V2TLS);
+        handle.pipewait(true)?;
+    }
+    if offset > 0 {
+        handle.range(&format!("{}-", offset))?;
+    }
+
+    let headers = transfer.clone();
+    handle.header_function(move |line| {
+        let line = str::from_utf8(line).unwrap_or("");
+        if !line.starts_with("HTTP/") {
+            return true
+        }
+        let status = line.split_whitespace().nth(1)
+                         .and_then(|code| code.parse::<u32>().ok());
+        let mut transfer = headers.lock().unwrap();
+        transfer.status = status;
+        // A server which doesn't support ranges sends everything.
+        if status == Some(200) && transfer.offset > 0 {
+            transfer.offset = 0;
+            transfer.sha256 = Sha256::new();
+            return transfer.file.set_len(0).is_ok()
+        }
+        true
+    })?;
+    let body = transfer.clone();
+    handle.write_function(move |data| {
+        let mut transfer = body.lock().unwrap();
+        match transfer.status {
+            None | Some(200) | Some(206) => {}
+            // The bodies of redirects and errors aren't part of the file.
+            Some(_) => return Ok(data.len()),
+        }
+        let written = {
+            let offset = transfer.offset;
+            let file = &mut transfer.file;
+            file.seek(SeekFrom::Start(offset)).and_then(|_| file.write_all(data))
+        };
+        if written.is_err() {
+            // Anything short of `data.len()` makes libcurl fail the transfer.
+            return Ok(0)
+        }
+        transfer.offset += data.len() as u64;
+        transfer.received += data.len() as u64;
+        transfer.sha256.update(data);
+        Ok(data.len())
+    })?;
+
+    Ok((multi.add(handle)?, transfer))
+}
@@ -9,6 +9,7 @@ use std::str;
 use std::string;
 
 use core::TargetKind;
+use util::{job, network};
 
 use curl;
 use git2;
@@ -40,6 +41,7 @@ error_chain! {
         Parse(string::ParseError);
         Git(git2::Error);
         Curl(curl::Error);
+        CurlMulti(curl::MultiError);
     }
 
     errors {
@@ -59,6 +61,19 @@ error_chain! {
             description("failed to get a 200 response")
             display("failed to get 200 response from `{}`, got {}", url, code)
         }
+        Interrupted {
+            description("interrupted")
+            display("interrupted")
+        }
+        Unresolvable(msg: String) {
+            description("failed to resolve dependencies")
+            display("{}", msg)
+        }
+        NoSpace(detail: String) {
+            description("out of disk space")
+            display("{}, try freeing some up with `cargo clean` or \
+                     `cargo cache gc`", detail)
+        }
     }
 }
 
@@ -67,13 +82,65 @@ impl CargoError {
         CargoError(CargoErrorKind::Internal(Box::new(self.0)), self.1)
     }
 
+    /// Whether this error, or anything that led to it, is a device having
+    /// run out of space.
+    ///
+    /// Git and other libraries only give us a message to go on, so this looks
+    /// for the operating system's description of the error anywhere in the
+    /// chain.
+    pub fn is_out_of_space(&self) -> bool {
+        #[cfg(unix)]
+        const MESSAGE: &'static str = "No space left on device";
+        #[cfg(windows)]
+        const MESSAGE: &'static str = "not enough space on the disk";
+
+        self.iter().any(|e| e.to_string().contains(MESSAGE))
+    }
+
+    /// Whether this error, or any `CargoError` that led to it, is of a kind
+    /// matching `f`.
+    fn any_kind<F>(&self, f: F) -> bool
+        where F: Fn(&CargoErrorKind) -> bool
+    {
+        // See `handle_cause` in lib.rs for why this is needed to downcast.
+        unsafe fn extend_lifetime(r: &Error) -> &(Error + 'static) {
+            ::std::mem::transmute::<&Error, &Error>(r)
+        }
+
+        self.iter().any(|e| {
+            let e = unsafe { extend_lifetime(e) };
+            e.downcast_ref::<CargoError>().map(|e| f(e.kind())).unwrap_or(false)
+        })
+    }
+
+    /// The exit code `cargo` uses for this error, see `EXIT_*`.
+    pub fn exit_code(&self) -> i32 {
+        let unresolvable = self.any_kind(|k| match *k {
+            CargoErrorKind::Unresolvable(..) => true,
+            _ => false,
+        });
+        match *self.kind() {
+            CargoErrorKind::Interrupted => job::INTERRUPTED_STATUS,
+            // Internal errors are also used to hide the details of some
+            // failures, e.g. rustc's, so only the outermost one counts.
+            CargoErrorKind::Internal(..) => EXIT_INTERNAL,
+            _ if network::maybe_spurious(self) => EXIT_NETWORK,
+            _ if unresolvable => EXIT_UNRESOLVABLE,
+            _ => EXIT_FAILURE,
+        }
+    }
+
     fn is_human(&self) -> bool {
         match self.0 {
             CargoErrorKind::Msg(_) |
             CargoErrorKind::TomlSer(_) |
             CargoErrorKind::TomlDe(_) |
             CargoErrorKind::Curl(_) |
+            CargoErrorKind::CurlMulti(_) |
             CargoErrorKind::HttpNot200(..) |
+            CargoErrorKind::Interrupted |
+            CargoErrorKind::Unresolvable(_) |
+            CargoErrorKind::NoSpace(_) |
             CargoErrorKind::ProcessErrorKind(_) |
             CargoErrorKind::CrateRegistry(_) => true,
             CargoErrorKind::ParseSemver(_) |
@@ -159,6 +226,17 @@ impl CargoTestError {
 // =============================================================================
 // CLI errors
 
+/// Exit code for a failed compilation, failing tests, and any error which
+/// isn't covered by one of the codes below.
+pub const EXIT_FAILURE: i32 = 101;
+/// Exit code for a dependency graph which can't be resolved, e.g. because no
+/// version of a dependency matches its requirement.
+pub const EXIT_UNRESOLVABLE: i32 = 102;
+/// Exit code for a network operation which failed even after retrying.
+pub const EXIT_NETWORK: i32 = 103;
+/// Exit code for a bug in Cargo itself.
+pub const EXIT_INTERNAL: i32 = 104;
+
 pub type CliResult = Result<(), CliError>;
 
 #[derive(Debug)]
@@ -202,7 +280,16 @@ impl CliError {
 
 impl From<CargoError> for CliError {
     fn from(err: CargoError) -> CliError {
-        CliError::new(err, 101)
+        let code = err.exit_code();
+        let err = match *err.kind() {
+            CargoErrorKind::NoSpace(..) => err,
+            _ if err.is_out_of_space() => {
+                let detail = "ran out of disk space".to_string();
+                CargoError::with_chain(err, CargoErrorKind::NoSpace(detail))
+            }
+            _ => err,
+        };
+        CliError::new(err, code)
     }
 }
 
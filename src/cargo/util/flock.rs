@@ -11,13 +11,14 @@ use libc;
 use util::Config;
 use util::errors::{CargoResult, CargoResultExt};
 
+#[derive(Debug)]
 pub struct FileLock {
     f: Option<File>,
     path: PathBuf,
     state: State,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 enum State {
     Unlocked,
     Shared,
@@ -45,6 +46,38 @@ impl FileLock {
         self.path.parent().unwrap()
     }
 
+    /// Turns the shared lock held on this file into an exclusive one, or the
+    /// exclusive one into a shared one.
+    ///
+    /// Note that this isn't atomic: other processes may get to lock the file
+    /// in between releasing the current lock and acquiring the new one. If the
+    /// process must block waiting for the lock, the `msg` is printed to
+    /// `config`.
+    pub fn relock(&mut self,
+                  exclusive: bool,
+                  config: &Config,
+                  msg: &str) -> CargoResult<()> {
+        assert!(self.state != State::Unlocked);
+        let state = if exclusive {State::Exclusive} else {State::Shared};
+        if self.state == state {
+            return Ok(())
+        }
+        {
+            let f = self.f.as_ref().unwrap();
+            if exclusive {
+                acquire(config, msg, &self.path,
+                        &|| f.try_lock_exclusive(),
+                        &|| f.lock_exclusive())?;
+            } else {
+                acquire(config, msg, &self.path,
+                        &|| f.try_lock_shared(),
+                        &|| f.lock_shared())?;
+            }
+        }
+        self.state = state;
+        Ok(())
+    }
+
     /// Removes all sibling files to this locked file.
     ///
     /// This can be useful if a directory is locked with a sentinel file but it
@@ -168,6 +201,7 @@ impl Filesystem {
         self.open(path.as_ref(),
                   OpenOptions::new().read(true).write(true).create(true),
                   State::Exclusive,
+                  true,
                   config,
                   msg)
     }
@@ -190,6 +224,24 @@ impl Filesystem {
         self.open(path.as_ref(),
                   OpenOptions::new().read(true),
                   State::Shared,
+                  false,
+                  config,
+                  msg)
+    }
+
+    /// Opens shared access to a file like `open_ro`, except that the file is
+    /// created if it doesn't exist yet (including intermediate directories),
+    /// as is needed for files which only exist to be locked.
+    pub fn open_shared_create<P>(&self,
+                                 path: P,
+                                 config: &Config,
+                                 msg: &str) -> CargoResult<FileLock>
+        where P: AsRef<Path>
+    {
+        self.open(path.as_ref(),
+                  OpenOptions::new().read(true).write(true).create(true),
+                  State::Shared,
+                  true,
                   config,
                   msg)
     }
@@ -198,15 +250,16 @@ impl Filesystem {
             path: &Path,
             opts: &OpenOptions,
             state: State,
+            create: bool,
             config: &Config,
             msg: &str) -> CargoResult<FileLock> {
         let path = self.root.join(path);
 
-        // If we want an exclusive lock then if we fail because of NotFound it's
-        // likely because an intermediate directory didn't exist, so try to
-        // create the directory and then continue.
+        // If we're allowed to create the file then if we fail because of
+        // NotFound it's likely because an intermediate directory didn't exist,
+        // so try to create the directory and then continue.
         let f = opts.open(&path).or_else(|e| {
-            if e.kind() == io::ErrorKind::NotFound && state == State::Exclusive {
+            if e.kind() == io::ErrorKind::NotFound && create {
                 create_dir_all(path.parent().unwrap())?;
                 opts.open(&path)
             } else {
@@ -15,14 +15,19 @@ pub fn find_project(pwd: &Path, file: &str) -> CargoResult<PathBuf> {
 
 /// Iteratively search for `file` in `pwd` and its parents, returning
 /// the path to the file.
+///
+/// If there's none, the error lists the directories which were searched, and
+/// for `Cargo.toml` suggests creating a package with `cargo init`.
 pub fn find_project_manifest(pwd: &Path, file: &str) -> CargoResult<PathBuf> {
     let mut current = pwd;
+    let mut searched = Vec::new();
 
     loop {
         let manifest = current.join(file);
         if fs::metadata(&manifest).is_ok() {
             return Ok(manifest)
         }
+        searched.push(current.display().to_string());
 
         match current.parent() {
             Some(p) => current = p,
@@ -30,8 +35,13 @@ pub fn find_project_manifest(pwd: &Path, file: &str) -> CargoResult<PathBuf> {
         }
     }
 
-    bail!("could not find `{}` in `{}` or any parent directory",
-          file, pwd.display())
+    let mut msg = format!("could not find `{}` in `{}` or any parent directory\n\n\
+                           searched:\n  {}",
+                          file, pwd.display(), searched.join("\n  "));
+    if file == "Cargo.toml" {
+        msg.push_str("\n\nTo create a package here, run `cargo init`.");
+    }
+    Err(msg.into())
 }
 
 /// Find the root Cargo.toml
@@ -14,18 +14,103 @@
 //! Conveniently whenever a process in the job object spawns a new process the
 //! child will be associated with the job object as well. This means if we add
 //! ourselves to the job object we create then everything will get torn down!
+//!
+//! On Unix Cargo additionally catches SIGINT and SIGTERM while it's running a
+//! build, see `defer_interrupts`. Rather than dying immediately it then stops
+//! starting new work, kills the processes it spawned (which may not have
+//! received the signal themselves, e.g. with `kill <pid>`) and waits for them
+//! before exiting with `INTERRUPTED_STATUS`. That way nothing is left running
+//! in the background and no half-built artifact is left looking fresh.
+
+use std::collections::HashSet;
+use std::process::Child;
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::sync::atomic::{AtomicBool, AtomicUsize, ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT};
+use std::sync::atomic::Ordering::SeqCst;
 
 pub use self::imp::Setup;
 
+/// Exit status of Cargo when interrupted, the same as a shell reports for a
+/// process killed by SIGINT.
+pub const INTERRUPTED_STATUS: i32 = 130;
+
+static INTERRUPTED: AtomicBool = ATOMIC_BOOL_INIT;
+static DEFERRING: AtomicUsize = ATOMIC_USIZE_INIT;
+
 pub fn setup() -> Option<Setup> {
     unsafe { imp::setup() }
 }
 
+/// Whether Cargo has been asked to stop, e.g. with Ctrl-C.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(SeqCst)
+}
+
+/// Defers interruptions for as long as the returned value is alive.
+///
+/// By default a signal kills Cargo right away. Code which is able to stop
+/// cleanly instead, by checking `interrupted` regularly, can ask for the
+/// signal to only be recorded. A second signal always kills Cargo
+/// immediately, in case cleaning up is taking too long.
+pub fn defer_interrupts() -> DeferInterrupts {
+    DEFERRING.fetch_add(1, SeqCst);
+    DeferInterrupts { _priv: () }
+}
+
+pub struct DeferInterrupts {
+    _priv: (),
+}
+
+impl Drop for DeferInterrupts {
+    fn drop(&mut self) {
+        DEFERRING.fetch_sub(1, SeqCst);
+    }
+}
+
+/// Keeps track of `child` until the returned value is dropped, so it can be
+/// killed if Cargo is interrupted.
+pub fn track(child: &Child) -> TrackedChild {
+    children().lock().unwrap().insert(child.id());
+    TrackedChild { id: child.id() }
+}
+
+pub struct TrackedChild {
+    id: u32,
+}
+
+impl Drop for TrackedChild {
+    fn drop(&mut self) {
+        children().lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Kills every process currently being tracked.
+pub fn kill_children() {
+    for &id in children().lock().unwrap().iter() {
+        info!("killing child process {}", id);
+        unsafe { imp::kill(id) }
+    }
+}
+
+fn children() -> &'static Mutex<HashSet<u32>> {
+    static INIT: Once = ONCE_INIT;
+    static mut CHILDREN: *const Mutex<HashSet<u32>> = 0 as *const _;
+    unsafe {
+        INIT.call_once(|| {
+            CHILDREN = Box::into_raw(Box::new(Mutex::new(HashSet::new())));
+        });
+        &*CHILDREN
+    }
+}
+
 #[cfg(unix)]
 mod imp {
     use std::env;
+    use std::sync::atomic::Ordering::SeqCst;
     use libc;
 
+    use super::{INTERRUPTED, DEFERRING};
+
     pub type Setup = ();
 
     pub unsafe fn setup() -> Option<()> {
@@ -36,8 +121,34 @@ mod imp {
         if env::var("__CARGO_TEST_SETSID_PLEASE_DONT_USE_ELSEWHERE").is_ok() {
             libc::setsid();
         }
+
+        // Leave signals which we were told to ignore (e.g. by `nohup`) alone.
+        for &signal in [libc::SIGINT, libc::SIGTERM].iter() {
+            let handler = handle_signal as extern fn(libc::c_int);
+            let prev = libc::signal(signal, handler as libc::sighandler_t);
+            if prev == libc::SIG_IGN {
+                libc::signal(signal, libc::SIG_IGN);
+            }
+        }
         Some(())
     }
+
+    extern fn handle_signal(signal: libc::c_int) {
+        // Only async-signal-safe operations are allowed in here, so all we do
+        // is record the interruption or fall back to the default action of
+        // killing ourselves.
+        let again = INTERRUPTED.swap(true, SeqCst);
+        if again || DEFERRING.load(SeqCst) == 0 {
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        }
+    }
+
+    pub unsafe fn kill(id: u32) {
+        libc::kill(id as libc::pid_t, libc::SIGTERM);
+    }
 }
 
 #[cfg(windows)]
@@ -55,6 +166,11 @@ mod imp {
         job: Handle,
     }
 
+    pub unsafe fn kill(_id: u32) {
+        // Nothing to do here, interruptions aren't caught on Windows and our
+        // children are killed along with us through the job object.
+    }
+
     pub struct Handle {
         inner: winapi::HANDLE,
     }
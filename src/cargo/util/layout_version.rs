@@ -0,0 +1,147 @@
+//! Versioning of the directories Cargo keeps its output and caches in.
+//!
+//! The root of the target directory and of Cargo's home directory each hold a
+//! `.layout-version` file with the version of the layout they were last used
+//! with. A newer Cargo which finds an older layout migrates it, or throws away
+//! what it can't migrate so that it's rebuilt or downloaded again, rather than
+//! tripping over stale files. An older Cargo refuses to touch a layout newer
+//! than it understands.
+//!
+//! Cargo's home directory may well be read-only when everything needed is in
+//! it already, so its stamp is only written once the caches are modified.
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use util::{paths, CacheLockMode, CargoError, CargoResult, CargoResultExt, Config, FileLock,
+           Filesystem};
+
+/// The file in the root of a versioned directory holding its version.
+pub const STAMP: &'static str = ".layout-version";
+
+/// The version of the target directory layout, see `ops::cargo_rustc::layout`.
+pub const TARGET_DIR: u32 = 1;
+
+/// The version of the layout of the caches in Cargo's home directory, its
+/// `git` and `registry` directories.
+pub const HOME: u32 = 1;
+
+/// Makes sure that `dir`, which is the `what` (e.g. "target directory"), has
+/// layout version `current`.
+///
+/// `fresh` is whether nothing was stored in `dir` yet, in which case it's
+/// simply stamped. Otherwise a missing stamp means version 0, from before
+/// layouts were versioned. An older version is passed to `migrate`, along with
+/// the lock on the stamp which is held until the directory is stamped with
+/// `current`. A newer version is an error, with `start_over` telling how to
+/// discard the directory instead.
+pub fn check<F>(config: &Config,
+                dir: &Filesystem,
+                what: &str,
+                current: u32,
+                fresh: bool,
+                start_over: &str,
+                migrate: F) -> CargoResult<()>
+    where F: FnOnce(u32, &FileLock) -> CargoResult<()>
+{
+    let mut lock = dir.open_rw(STAMP, config, "layout version")?;
+    let mut contents = String::new();
+    lock.read_to_string(&mut contents)?;
+    let found = match contents.trim() {
+        "" if fresh => None,
+        "" => Some(0),
+        s => Some(parse(s, lock.path())?),
+    };
+    let path = lock.parent().to_path_buf();
+    match found {
+        Some(found) if found == current => return Ok(()),
+        Some(found) if found > current => {
+            return Err(newer(what, &path, found, current, start_over))
+        }
+        Some(found) => {
+            migrate(found, &lock).chain_err(|| {
+                format!("failed to migrate the {} `{}` from layout version {} to {}",
+                        what, path.display(), found, current)
+            })?;
+        }
+        None => {}
+    }
+
+    lock.seek(SeekFrom::Start(0))?;
+    lock.file().set_len(0)?;
+    lock.write_all(format!("{}\n", current).as_bytes())?;
+    Ok(())
+}
+
+fn parse(s: &str, path: &Path) -> CargoResult<u32> {
+    s.parse::<u32>().chain_err(|| {
+        format!("failed to parse the layout version in `{}`", path.display())
+    })
+}
+
+/// The error for a directory with a layout newer than `current`, see `check`.
+fn newer(what: &str, path: &Path, found: u32, current: u32, start_over: &str) -> CargoError {
+    format!("the {} `{}` has layout version {}, but this version of Cargo \
+             only understands version {}\n\n\
+             It was last used by a newer version of Cargo. Keep using that \
+             one, or {}.",
+            what, path.display(), found, current, start_over).into()
+}
+
+const HOME_START_OVER: &'static str =
+    "remove its `git` and `registry` directories to download everything again";
+
+/// Fails if the caches in Cargo's home directory have a layout newer than
+/// this Cargo understands, without writing anything. They're only stamped,
+/// or migrated, by `stamp_home` once they're modified.
+pub fn check_home(config: &Config) -> CargoResult<()> {
+    let home = config.home().clone().into_path_unlocked();
+    let stamp = home.join(STAMP);
+    if !stamp.exists() {
+        return Ok(())
+    }
+    let contents = paths::read(&stamp)?;
+    let found = match contents.trim() {
+        "" => return Ok(()),
+        s => parse(s, &stamp)?,
+    };
+    if found > HOME {
+        return Err(newer("Cargo home", &home, found, HOME, HOME_START_OVER))
+    }
+    Ok(())
+}
+
+/// Makes sure the caches in Cargo's home directory have the current layout
+/// version before they're modified, see `check`. This is called with the
+/// package cache locked for downloads.
+pub fn stamp_home(config: &Config) -> CargoResult<()> {
+    let home = config.home().clone().into_path_unlocked();
+    let git = config.git_path().into_path_unlocked();
+    let registry = home.join("registry");
+    let fresh = !git.exists() && !registry.exists();
+    check(config, config.home(), "Cargo home", HOME, fresh, HOME_START_OVER,
+          |found, _| {
+        match found {
+            // The caches were laid out as in version 1 before they were
+            // versioned.
+            0 => Ok(()),
+            // Anything else can't be migrated, but it's all just a copy of
+            // what's available elsewhere, so it's downloaded again.
+            _ => {
+                let _lock = config.acquire_package_cache_lock(CacheLockMode::Exclusive)?;
+                config.shell().status("Removing", format!(
+                    "caches in `{}` left by an older version of Cargo",
+                    home.display()))?;
+                for dir in [&git, &registry].iter() {
+                    if dir.exists() {
+                        fs::remove_dir_all(dir).chain_err(|| {
+                            format!("failed to remove `{}`", dir.display())
+                        })?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    })
+}
@@ -0,0 +1,80 @@
+//! Support for the `CARGO_LOG` environment variable.
+//!
+//! Cargo logs through the `log` crate, with each message targeted at the
+//! module it came from. `CARGO_LOG` uses the same directive syntax as
+//! `RUST_LOG`, e.g. `CARGO_LOG=git=debug,resolver=trace`. It also accepts short
+//! names for the parts of Cargo that are most often worth debugging, so people
+//! filing bug reports don't need to know how the source is laid out.
+
+/// Short names accepted in `CARGO_LOG`, along with the modules they enable.
+const TARGETS: &'static [(&'static str, &'static [&'static str])] = &[
+    ("resolver", &["cargo::core::resolver", "cargo::core::registry",
+                   "cargo::ops::resolve"]),
+    ("git", &["cargo::sources::git"]),
+    ("registry", &["cargo::sources::registry"]),
+    ("fingerprint", &["cargo::ops::cargo_rustc::fingerprint"]),
+    ("process", &["cargo::util::process_builder",
+                  "cargo::ops::cargo_rustc::job_queue"]),
+    ("network", &["cargo::util::network"]),
+];
+
+/// Translates a `CARGO_LOG` value into a filter understood by `env_logger`.
+///
+/// Every directive naming one of the short targets above is expanded into one
+/// directive per module it covers. Anything else, such as a bare level or a
+/// full module path, is passed through as is, as is a trailing `/regex`.
+pub fn expand_filters(spec: &str) -> String {
+    let (directives, regex) = match spec.find('/') {
+        Some(i) => (&spec[..i], Some(&spec[i..])),
+        None => (spec, None),
+    };
+
+    let mut expanded = Vec::new();
+    for directive in directives.split(',').map(|s| s.trim()) {
+        if directive.is_empty() {
+            continue
+        }
+        let (name, level) = match directive.find('=') {
+            Some(i) => (&directive[..i], Some(&directive[i..])),
+            None => (directive, None),
+        };
+        match TARGETS.iter().find(|&&(target, _)| target == name) {
+            Some(&(_, modules)) => {
+                for module in modules {
+                    expanded.push(format!("{}{}", module, level.unwrap_or("")));
+                }
+            }
+            None => expanded.push(directive.to_string()),
+        }
+    }
+
+    let mut filters = expanded.join(",");
+    if let Some(regex) = regex {
+        filters.push_str(regex);
+    }
+    filters
+}
+
+#[cfg(test)]
+mod test {
+    use super::expand_filters;
+
+    #[test]
+    fn expands_short_targets() {
+        assert_eq!(expand_filters("git=debug"), "cargo::sources::git=debug");
+        assert_eq!(expand_filters("fingerprint"),
+                   "cargo::ops::cargo_rustc::fingerprint");
+        assert_eq!(expand_filters("process=trace,git=info"),
+                   "cargo::util::process_builder=trace,\
+                    cargo::ops::cargo_rustc::job_queue=trace,\
+                    cargo::sources::git=info");
+    }
+
+    #[test]
+    fn passes_everything_else_through() {
+        assert_eq!(expand_filters("debug"), "debug");
+        assert_eq!(expand_filters("cargo::core=info, git=warn/fetch"),
+                   "cargo::core=info,cargo::sources::git=warn/fetch");
+        assert_eq!(expand_filters(""), "");
+    }
+}
@@ -33,6 +33,9 @@ pub struct Artifact<'a> {
     pub profile: &'a Profile,
     pub features: Vec<String>,
     pub filenames: Vec<String>,
+    /// The path of the executable which was built, if any, e.g. for binaries
+    /// and tests.
+    pub executable: Option<String>,
     pub fresh: bool,
 }
 
@@ -56,3 +59,17 @@ impl<'a> Message for BuildScript<'a> {
         "build-script-executed"
     }
 }
+
+#[derive(Serialize)]
+pub struct PublishCheck<'a> {
+    pub package_id: &'a PackageId,
+    pub level: &'a str,
+    pub code: &'a str,
+    pub message: &'a str,
+}
+
+impl<'a> Message for PublishCheck<'a> {
+    fn reason(&self) -> &str {
+        "publish-check"
+    }
+}
@@ -1,6 +1,6 @@
 pub use self::cfg::{Cfg, CfgExpr};
-pub use self::config::{Config, ConfigValue, homedir};
-pub use self::dependency_queue::{DependencyQueue, Fresh, Dirty, Freshness};
+pub use self::config::{Config, ConfigValue, homedir, CacheLockMode, PackageCacheLock};
+pub use self::dependency_queue::{DependencyQueue, Fresh, Dirty, Freshness, Artifact};
 pub use self::errors::{CargoResult, CargoResultExt, CargoError, CargoErrorKind, Test, CliResult};
 pub use self::errors::{CliError, ProcessError, CargoTestError};
 pub use self::errors::{process_error, internal};
@@ -19,18 +19,24 @@ pub use self::to_url::ToUrl;
 pub use self::vcs::{GitRepo, HgRepo, PijulRepo, FossilRepo};
 pub use self::read2::read2;
 
+pub mod cache_manifest;
 pub mod config;
+pub mod credential;
+pub mod download;
 pub mod errors;
 pub mod graph;
 pub mod hex;
 pub mod important_paths;
 pub mod job;
+pub mod layout_version;
 pub mod lev_distance;
+pub mod logging;
 pub mod machine_message;
 pub mod network;
 pub mod paths;
 pub mod process_builder;
 pub mod profile;
+pub mod signature;
 pub mod to_semver;
 pub mod to_url;
 pub mod toml;
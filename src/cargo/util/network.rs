@@ -1,25 +1,29 @@
 use std;
 use std::error::Error;
+use std::thread;
+use std::time::Duration;
 
 use error_chain::ChainedError;
 use util::Config;
 use util::errors::{CargoError, CargoErrorKind, CargoResult};
 use git2;
 
-fn maybe_spurious<E, EKind>(err: &E) -> bool
-    where E: ChainedError<ErrorKind=EKind> + 'static {
-    //Error inspection in non-verbose mode requires inspecting the
-    //error kind to avoid printing Internal errors. The downcasting
-    //machinery requires &(Error + 'static), but the iterator (and
-    //underlying `cause`) return &Error. Because the borrows are
-    //constrained to this handling method, and because the original
-    //error object is constrained to be 'static, we're casting away
-    //the borrow's actual lifetime for purposes of downcasting and
-    //inspecting the error chain
-    unsafe fn extend_lifetime(r: &Error) -> &(Error + 'static) {
-        std::mem::transmute::<&Error, &Error>(r)
-    }
+//Error inspection in non-verbose mode requires inspecting the
+//error kind to avoid printing Internal errors. The downcasting
+//machinery requires &(Error + 'static), but the iterator (and
+//underlying `cause`) return &Error. Because the borrows are
+//constrained to the inspecting functions, and because the original
+//error object is constrained to be 'static, we're casting away
+//the borrow's actual lifetime for purposes of downcasting and
+//inspecting the error chain
+unsafe fn extend_lifetime(r: &Error) -> &(Error + 'static) {
+    std::mem::transmute::<&Error, &Error>(r)
+}
 
+/// Whether `err` is, or was caused by, a network failure which may go away
+/// when retried.
+pub fn maybe_spurious<E, EKind>(err: &E) -> bool
+    where E: ChainedError<ErrorKind=EKind> + 'static {
     for e in err.iter() {
         let e = unsafe { extend_lifetime(e) };
         if let Some(cargo_err) = e.downcast_ref::<CargoError>() {
@@ -42,6 +46,9 @@ fn maybe_spurious<E, EKind>(err: &E) -> bool
                 &CargoErrorKind::HttpNot200(code, ref _url) if 500 <= code && code < 600 => {
                     return true
                 }
+                &CargoErrorKind::CrateRegistry(ref kind) if kind.is_spurious() => {
+                    return true
+                }
                 _ => ()
             }
         }
@@ -49,10 +56,27 @@ fn maybe_spurious<E, EKind>(err: &E) -> bool
     false
 }
 
+/// How long a registry asked to wait before retrying the request which
+/// failed with `err`, if it did.
+fn retry_after(err: &CargoError) -> Option<Duration> {
+    err.iter().filter_map(|e| {
+        let e = unsafe { extend_lifetime(e) };
+        match e.downcast_ref::<CargoError>().map(|e| e.kind()) {
+            Some(&CargoErrorKind::CrateRegistry(ref kind)) => kind.retry_after(),
+            _ => None,
+        }
+    }).next()
+}
+
+/// Registries asking to wait longer than this before retrying aren't retried,
+/// as Cargo would seem stuck.
+const MAX_RETRY_AFTER: u64 = 60;
+
 /// Wrapper method for network call retry logic.
 ///
 /// Retry counts provided by Config object `net.retry`. Config shell outputs
-/// a warning on per retry.
+/// a warning on per retry. Registries which are rate limiting or failing
+/// to handle requests are given the time they ask for before the retry.
 ///
 /// Closure must return a `CargoResult`.
 ///
@@ -67,17 +91,27 @@ pub fn with_retry<T, F>(config: &Config, mut callback: F) -> CargoResult<T>
 {
     let mut remaining = config.net_retry()?;
     loop {
-        match callback() {
+        let err = match callback() {
             Ok(ret) => return Ok(ret),
-            Err(ref e) if maybe_spurious(e) && remaining > 0 => {
-                let msg = format!("spurious network error ({} tries \
-                          remaining): {}", remaining, e);
-                config.shell().warn(msg)?;
-                remaining -= 1;
-            }
-            //todo impl from
-            Err(e) => return Err(e.into()),
+            Err(e) => e,
+        };
+        let wait = retry_after(&err);
+        if !maybe_spurious(&err) || remaining == 0 ||
+           wait.map_or(false, |wait| wait.as_secs() > MAX_RETRY_AFTER) {
+            return Err(err)
+        }
+        let msg = match wait {
+            Some(wait) => format!("{} ({} tries remaining, retrying in {} \
+                                   second{})", err, remaining, wait.as_secs(),
+                                  if wait.as_secs() == 1 { "" } else { "s" }),
+            None => format!("spurious network error ({} tries \
+                             remaining): {}", remaining, err),
+        };
+        config.shell().warn(msg)?;
+        if let Some(wait) = wait {
+            thread::sleep(wait);
         }
+        remaining -= 1;
     }
 }
 #[test]
@@ -104,3 +138,27 @@ fn with_retry_finds_nested_spurious_errors() {
     let result = with_retry(&config, || results.pop().unwrap());
     assert_eq!(result.unwrap(), ())
 }
+
+#[test]
+fn with_retry_waits_for_registries_to_recover() {
+    //Rate limiting and server errors of registries are retried after the
+    //time they ask for
+    use registry::ErrorKind as RegistryErrorKind;
+    let error1 = CargoErrorKind::CrateRegistry(RegistryErrorKind::RateLimited(Some(0))).into();
+    let error2 = CargoErrorKind::CrateRegistry(
+        RegistryErrorKind::ServerError(503, None, Vec::new())).into();
+    let mut results: Vec<CargoResult<()>> = vec![Ok(()), Err(error1), Err(error2)];
+    let config = Config::default().unwrap();
+    let result = with_retry(&config, || results.pop().unwrap());
+    assert_eq!(result.unwrap(), ())
+}
+
+#[test]
+fn with_retry_gives_up_on_long_waits() {
+    use registry::ErrorKind as RegistryErrorKind;
+    let error = CargoErrorKind::CrateRegistry(RegistryErrorKind::RateLimited(Some(3600))).into();
+    let mut results: Vec<CargoResult<()>> = vec![Ok(()), Err(error)];
+    let config = Config::default().unwrap();
+    let result = with_retry(&config, || results.pop().unwrap());
+    assert!(result.is_err())
+}
@@ -5,8 +5,10 @@ use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf, Component};
 
+use fs2;
+
 use util::{internal, CargoResult};
-use util::errors::CargoResultExt;
+use util::errors::{CargoErrorKind, CargoResultExt};
 
 pub fn join_paths<T: AsRef<OsStr>>(paths: &[T], env: &str) -> CargoResult<OsString> {
     env::join_paths(paths.iter()).or_else(|e| {
@@ -68,6 +70,30 @@ pub fn without_prefix<'a>(long_path: &'a Path, prefix: &'a Path) -> Option<&'a P
     }
 }
 
+/// The path leading from the directory `base` to `path`, going up with `..`
+/// as far as needed. Both have to be within `root`, so that the result never
+/// leaves it.
+pub fn relative_within(path: &Path, base: &Path, root: &Path) -> Option<PathBuf> {
+    let (path, base) = match (without_prefix(path, root), without_prefix(base, root)) {
+        (Some(path), Some(base)) => (path, base),
+        _ => return None,
+    };
+    let mut path = path.components().peekable();
+    let mut base = base.components().peekable();
+    while path.peek().is_some() && path.peek() == base.peek() {
+        path.next();
+        base.next();
+    }
+    let mut relative = PathBuf::new();
+    for _ in base {
+        relative.push("..");
+    }
+    for component in path {
+        relative.push(component.as_os_str());
+    }
+    Some(relative)
+}
+
 pub fn read(path: &Path) -> CargoResult<String> {
     match String::from_utf8(read_bytes(path)?) {
         Ok(s) => Ok(s),
@@ -114,6 +140,43 @@ pub fn append(path: &Path, contents: &[u8]) -> CargoResult<()> {
     })
 }
 
+/// Space which should always be left free on a device before Cargo starts
+/// writing something sizeable to it, like a clone or an unpacked crate.
+/// With less than this it's bound to run out part of the way through.
+const MIN_FREE_SPACE: u64 = 16 * 1024 * 1024;
+
+/// Checks that the device holding `path` has room for another `needed`
+/// bytes, plus some slack, failing with `CargoErrorKind::NoSpace` if not.
+///
+/// `path` doesn't need to exist yet. If the available space can't be
+/// determined at all the check passes.
+pub fn check_disk_space(path: &Path, needed: u64) -> CargoResult<()> {
+    let existing = match ancestors(path).find(|p| p.exists()) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    //HACK: lets Cargo's own tests pretend to be running out of space.
+    let available = env::var("__CARGO_TEST_AVAILABLE_SPACE").ok()
+                             .and_then(|s| s.parse().ok())
+                             .map(Ok)
+                             .unwrap_or_else(|| fs2::available_space(existing));
+    let available = match available {
+        Ok(available) => available,
+        Err(e) => {
+            debug!("failed to query available space at {}: {}",
+                   existing.display(), e);
+            return Ok(())
+        }
+    };
+    if available < needed.saturating_add(MIN_FREE_SPACE) {
+        let detail = format!("only {:.1} MiB of disk space is left for `{}`",
+                             available as f64 / (1024.0 * 1024.0),
+                             path.display());
+        return Err(CargoErrorKind::NoSpace(detail).into())
+    }
+    Ok(())
+}
+
 #[cfg(unix)]
 pub fn path2bytes(path: &Path) -> CargoResult<&[u8]> {
     use std::os::unix::prelude::*;
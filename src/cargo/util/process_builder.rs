@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::env;
 use std::ffi::{OsString, OsStr};
 use std::fmt;
+use std::io::Write;
 use std::path::Path;
 use std::process::{Command, Stdio, Output};
 
@@ -10,6 +11,7 @@ use shell_escape::escape;
 
 use util::{CargoResult, CargoResultExt, CargoError, process_error, read2};
 use util::errors::CargoErrorKind;
+use util::job;
 
 /// A builder object for an external process, similar to `std::process::Command`.
 #[derive(Clone, Debug)]
@@ -20,6 +22,9 @@ pub struct ProcessBuilder {
     args: Vec<OsString>,
     /// Any environment variables that should be set for the program.
     env: HashMap<String, Option<OsString>>,
+    /// Whether the program starts out with an empty environment, rather than
+    /// inheriting Cargo's.
+    env_clear: bool,
     /// Which directory to run the program from.
     cwd: Option<OsString>,
     /// The `make` jobserver. See the [jobserver crate][jobserver_docs] for
@@ -89,6 +94,13 @@ impl ProcessBuilder {
         self
     }
 
+    /// (chainable) Don't let the process inherit any environment variables,
+    /// it only gets those set with `env`.
+    pub fn env_clear(&mut self) -> &mut ProcessBuilder {
+        self.env_clear = true;
+        self
+    }
+
     /// Get the executable name.
     pub fn get_program(&self) -> &OsString {
         &self.program
@@ -107,6 +119,9 @@ impl ProcessBuilder {
     /// Get an environment variable as the process will see it (will inherit from environment
     /// unless explicitally unset).
     pub fn get_env(&self, var: &str) -> Option<OsString> {
+        if self.env_clear {
+            return self.env.get(var).cloned().and_then(|s| s)
+        }
         self.env.get(var).cloned().or_else(|| Some(env::var_os(var)))
             .and_then(|s| s)
     }
@@ -126,8 +141,12 @@ impl ProcessBuilder {
 
     /// Run the process, waiting for completion, and mapping non-success exit codes to an error.
     pub fn exec(&self) -> CargoResult<()> {
+        debug!("executing `{}`", self.debug_string());
         let mut command = self.build_command();
-        let exit = command.status().chain_err(|| {
+        let exit = command.spawn().and_then(|mut child| {
+            let _tracked = job::track(&child);
+            child.wait()
+        }).chain_err(|| {
             CargoErrorKind::ProcessErrorKind(
                 process_error(&format!("could not execute process `{}`",
                                    self.debug_string()), None, None))
@@ -149,6 +168,7 @@ impl ProcessBuilder {
     pub fn exec_replace(&self) -> CargoResult<()> {
         use std::os::unix::process::CommandExt;
 
+        debug!("executing `{}`", self.debug_string());
         let mut command = self.build_command();
         let error = command.exec();
         Err(CargoError::with_chain(error,
@@ -166,6 +186,7 @@ impl ProcessBuilder {
 
     /// Execute the process, returning the stdio output, or an error if non-zero exit status.
     pub fn exec_with_output(&self) -> CargoResult<Output> {
+        debug!("executing `{}`", self.debug_string());
         let mut command = self.build_command();
 
         let output = command.output().chain_err(|| {
@@ -184,6 +205,31 @@ impl ProcessBuilder {
         }
     }
 
+    /// Like `exec_with_output`, but writes `input` to the process's stdin.
+    pub fn exec_with_input(&self, input: &[u8]) -> CargoResult<Output> {
+        debug!("executing `{}`", self.debug_string());
+        let mut command = self.build_command();
+        command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        let output = command.spawn().and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(input)?;
+            child.wait_with_output()
+        }).chain_err(|| {
+            CargoErrorKind::ProcessErrorKind(
+                process_error(
+                    &format!("could not execute process `{}`", self.debug_string()),
+                          None, None))
+        })?;
+
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(CargoErrorKind::ProcessErrorKind(process_error(
+                &format!("process didn't exit successfully: `{}`", self.debug_string()),
+                Some(&output.status), Some(&output))).into())
+        }
+    }
+
     /// Execute a command, passing each line of stdout and stderr to the supplied callbacks, which
     /// can mutate the string data.
     ///
@@ -198,6 +244,7 @@ impl ProcessBuilder {
         let mut stdout = Vec::new();
         let mut stderr = Vec::new();
 
+        debug!("executing `{}`", self.debug_string());
         let mut cmd = self.build_command();
         cmd.stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -206,6 +253,7 @@ impl ProcessBuilder {
         let mut callback_error = None;
         let status = (|| {
             let mut child = cmd.spawn()?;
+            let _tracked = job::track(&child);
             let out = child.stdout.take().unwrap();
             let err = child.stderr.take().unwrap();
             read2(out, err, &mut |is_out, data, eof| {
@@ -277,6 +325,9 @@ impl ProcessBuilder {
         for arg in &self.args {
             command.arg(arg);
         }
+        if self.env_clear {
+            command.env_clear();
+        }
         for (k, v) in &self.env {
             match *v {
                 Some(ref v) => { command.env(k, v); }
@@ -307,6 +358,7 @@ pub fn process<T: AsRef<OsStr>>(cmd: T) -> ProcessBuilder {
         args: Vec::new(),
         cwd: None,
         env: HashMap::new(),
+        env_clear: false,
         jobserver: None,
     }
 }
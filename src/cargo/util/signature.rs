@@ -0,0 +1,104 @@
+//! Verification of the signatures of downloaded packages.
+//!
+//! Registries may list a detached, ASCII armored OpenPGP signature of each
+//! `.crate` file in their index (the `sig` field), and git dependencies pinned
+//! to an annotated tag may be signed with `git tag -s`. When public keys are
+//! listed in `signatures.trusted-keys`, any such signature has to be made by
+//! one of them before the package is unpacked or built.
+//!
+//! Missing signatures are fine unless `signatures.require` is set (or
+//! `--require-signatures` is passed), in which case every registry package and
+//! git dependency has to carry a valid signature.
+//!
+//! Signatures are checked with `gpg` (or whatever `signatures.gpg` names) in
+//! a scratch home directory holding nothing but the trusted keys, so keys in
+//! the user's own keyring are never trusted by accident.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use tempdir::TempDir;
+
+use util::{process, CargoResult, CargoResultExt, Config};
+
+/// Whether there's anything to verify at all, that is if trusted keys are
+/// configured or signatures are required.
+pub fn enabled(config: &Config) -> CargoResult<bool> {
+    Ok(config.signatures_required()? || !trusted_keys(config)?.is_empty())
+}
+
+/// Checks `signature`, a detached signature of `data`, against the trusted
+/// keys in the configuration.
+///
+/// `what` describes what was signed, for error messages.
+pub fn verify(config: &Config,
+              what: &str,
+              data: &[u8],
+              signature: Option<&str>) -> CargoResult<()> {
+    let required = config.signatures_required()?;
+    let signature = match signature {
+        Some(signature) => signature,
+        None if required => {
+            bail!("{} is not signed, but signatures are required", what)
+        }
+        None => return Ok(()),
+    };
+    let keys = trusted_keys(config)?;
+    if keys.is_empty() {
+        if required {
+            bail!("{} is signed, but signatures can't be verified as no keys \
+                   are listed in `signatures.trusted-keys`", what)
+        }
+        debug!("not verifying the signature of {}, no trusted keys", what);
+        return Ok(())
+    }
+
+    check(config, &keys, data, signature).chain_err(|| {
+        format!("failed to verify the signature of {}", what)
+    })
+}
+
+fn check(config: &Config,
+         keys: &[PathBuf],
+         data: &[u8],
+         signature: &str) -> CargoResult<()> {
+    let program = config.get_path("signatures.gpg")?.map(|p| p.val)
+        .unwrap_or_else(|| PathBuf::from("gpg"));
+    let home = TempDir::new("cargo-gpg")?;
+    let gpg = |args: &[&str]| {
+        let mut gpg = process(&program);
+        gpg.arg("--batch").arg("--no-tty")
+            .arg("--homedir").arg(home.path())
+            .args(args);
+        gpg
+    };
+
+    for key in keys {
+        gpg(&["--import"]).arg(key).exec_with_output().chain_err(|| {
+            format!("failed to import the trusted key `{}`", key.display())
+        })?;
+    }
+
+    let data_path = home.path().join("data");
+    let signature_path = home.path().join("data.asc");
+    File::create(&data_path)?.write_all(data)?;
+    File::create(&signature_path)?.write_all(signature.as_bytes())?;
+    gpg(&["--verify"]).arg(&signature_path).arg(&data_path)
+        .exec_with_output()
+        .chain_err(|| "the signature wasn't made by a trusted key, or it \
+                       doesn't match")?;
+    Ok(())
+}
+
+/// The public key files listed in `signatures.trusted-keys`, relative to the
+/// directory containing the `.cargo` folder they were configured in.
+fn trusted_keys(config: &Config) -> CargoResult<Vec<PathBuf>> {
+    let list = match config.get_list("signatures.trusted-keys")? {
+        Some(list) => list.val,
+        None => return Ok(Vec::new()),
+    };
+    Ok(list.into_iter().map(|(key, definition)| {
+        definition.parent().unwrap().parent().unwrap().join(key)
+    }).collect())
+}
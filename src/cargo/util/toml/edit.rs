@@ -0,0 +1,749 @@
+//! Editing manifests in place, leaving everything which isn't edited exactly
+//! as it was written, comments and formatting included.
+//!
+//! A document is only understood as far as that takes: it's a list of items,
+//! each of which is a table header, a key/value pair (which spans several
+//! lines for multi-line strings and arrays) or a blank or comment line. Edits
+//! replace, insert or remove whole items, and leave all other lines alone.
+
+use std::fmt;
+use std::ops::Range;
+
+use toml::{self, Value};
+
+use util::errors::{CargoResult, CargoResultExt};
+
+/// A TOML document which can be edited without reformatting it.
+pub struct Document {
+    lines: Vec<String>,
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+/// A span of lines in a `Document`.
+struct Item {
+    start: usize,
+    end: usize,
+    kind: Kind,
+    /// The comment at the end of the last line, if there is one.
+    comment: Option<String>,
+}
+
+#[derive(PartialEq)]
+enum Kind {
+    /// `[a.b]`
+    Table(Vec<String>),
+    /// `[[a.b]]`
+    ArrayOfTables(Vec<String>),
+    /// `a.b = value`
+    Entry(Vec<String>),
+    /// A blank line or a comment.
+    Trivia,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> CargoResult<Document> {
+        toml::from_str::<Value>(text)?;
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let trailing_newline = text.is_empty() || text.ends_with('\n');
+        let text = if text.ends_with('\n') { &text[..text.len() - 1] } else { text };
+        let lines = if text.is_empty() {
+            Vec::new()
+        } else {
+            text.split('\n').map(|l| l.trim_right_matches('\r').to_string()).collect()
+        };
+        let doc = Document {
+            lines: lines,
+            newline: newline,
+            trailing_newline: trailing_newline,
+        };
+        doc.items().chain_err(|| "failed to parse the document for editing")?;
+        Ok(doc)
+    }
+
+    /// Looks up `key` in the table at `table`, however it's written.
+    pub fn get(&self, table: &[&str], key: &str) -> Option<Value> {
+        let mut value = match toml::from_str::<Value>(&self.to_string()) {
+            Ok(value) => value,
+            Err(_) => return None,
+        };
+        for k in table.iter().chain(Some(&key)) {
+            value = match value {
+                Value::Table(mut t) => match t.remove(*k) {
+                    Some(v) => v,
+                    None => return None,
+                },
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Every key declared in the document, as its full path from the root
+    /// along with the span of lines (counting from 0) it takes up, in the
+    /// order they're written. Dotted keys and keys under table headers get
+    /// the same paths they'd have in the parsed document. Keys within an
+    /// inline table take up the lines of the entry the table is the value of,
+    /// and a table header takes up its own line.
+    pub fn keys(&self) -> Vec<(Vec<String>, Range<usize>)> {
+        let items = match self.items() {
+            Ok(items) => items,
+            Err(..) => return Vec::new(),
+        };
+        let mut keys = Vec::new();
+        let mut table = Vec::new();
+        for item in items {
+            let span = item.start..item.end;
+            match item.kind {
+                Kind::Table(path) | Kind::ArrayOfTables(path) => {
+                    table = path.clone();
+                    keys.push((path, span));
+                }
+                Kind::Entry(key) => {
+                    let mut path = table.clone();
+                    path.extend(key.iter().cloned());
+                    let text = self.lines[span.clone()].join("\n");
+                    let mut value = toml::from_str::<Value>(text.trim()).ok();
+                    for k in key.iter() {
+                        value = match value {
+                            Some(Value::Table(mut t)) => t.remove(k),
+                            _ => None,
+                        };
+                    }
+                    keys.push((path.clone(), span.clone()));
+                    if let Some(Value::Table(t)) = value {
+                        inline_keys(&path, &t, &span, &mut keys);
+                    }
+                }
+                Kind::Trivia => {}
+            }
+        }
+        keys
+    }
+
+    /// The line at `i`, counting from 0.
+    pub fn line(&self, i: usize) -> &str {
+        &self.lines[i]
+    }
+
+    /// Sets `key` in the table at `table` to `value`, which is written as is
+    /// and has to be a valid inline value. An existing entry is replaced in
+    /// place, a `[table.key]` table is replaced by the entry, and the table
+    /// is added at the end if it doesn't exist yet.
+    pub fn set(&mut self, table: &[&str], key: &str, value: &str) {
+        let mut path = table.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        path.push(key.to_string());
+        self.remove_tables(&path);
+        let items = self.items().unwrap();
+        let entry = |indent: &str, comment: Option<&String>| {
+            let mut line = format!("{}{} = {}", indent, key_repr(key), value);
+            if let Some(comment) = comment {
+                line.push(' ');
+                line.push_str(comment);
+            }
+            line
+        };
+
+        let (header, end) = match section(&items, table) {
+            Some(section) => section,
+            None => {
+                if self.lines.last().map(|l| !l.trim().is_empty()).unwrap_or(false) {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(format!("[{}]", path_repr(table)));
+                self.lines.push(entry("", None));
+                return
+            }
+        };
+        let entries = items[header + 1..end].iter().filter(|item| {
+            match item.kind {
+                Kind::Entry(ref k) => k.len() == 1,
+                _ => false,
+            }
+        }).collect::<Vec<_>>();
+
+        // Dotted keys like `key.version = "1"` are replaced too.
+        let mut stale = items[header + 1..end].iter().filter(|item| {
+            match item.kind {
+                Kind::Entry(ref k) => k[0] == key && k.len() > 1,
+                _ => false,
+            }
+        }).map(|item| (item.start, item.end)).collect::<Vec<_>>();
+
+        let existing = entries.iter().find(|item| {
+            item.kind == Kind::Entry(vec![key.to_string()])
+        });
+        if let Some(item) = existing {
+            let indent = indent(&self.lines[item.start]).to_string();
+            let line = entry(&indent, item.comment.as_ref());
+            stale.push((item.start, item.end));
+            self.replace(stale, item.start, vec![line]);
+            return
+        }
+
+        // Entries are kept sorted if they already are.
+        let keys = entries.iter().map(|item| match item.kind {
+            Kind::Entry(ref k) => &k[0][..],
+            _ => unreachable!(),
+        }).collect::<Vec<_>>();
+        let sorted = keys.windows(2).all(|w| w[0] <= w[1]);
+        let (at, indent) = match entries.last() {
+            Some(last) => {
+                let next = if sorted {
+                    entries.iter().find(|item| match item.kind {
+                        Kind::Entry(ref k) => &k[0][..] > key,
+                        _ => false,
+                    })
+                } else {
+                    None
+                };
+                match next {
+                    Some(next) => (next.start, indent(&self.lines[next.start]).to_string()),
+                    None => (last.end, indent(&self.lines[last.start]).to_string()),
+                }
+            }
+            None => (items[header].end, String::new()),
+        };
+        let line = entry(&indent, None);
+        self.replace(stale, at, vec![line]);
+    }
+
+    /// Removes `key` from the table at `table`, whether it's an entry or a
+    /// `[table.key]` table of its own. Returns whether there was anything to
+    /// remove.
+    pub fn remove(&mut self, table: &[&str], key: &str) -> bool {
+        let mut path = table.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        path.push(key.to_string());
+        let mut removed = self.remove_tables(&path);
+        let items = self.items().unwrap();
+        if let Some((header, end)) = section(&items, table) {
+            let stale = items[header + 1..end].iter().filter(|item| {
+                match item.kind {
+                    Kind::Entry(ref k) => k[0] == key,
+                    _ => false,
+                }
+            }).map(|item| (item.start, item.end)).collect::<Vec<_>>();
+            if !stale.is_empty() {
+                removed = true;
+                self.replace(stale, 0, Vec::new());
+            }
+        }
+        removed
+    }
+
+    /// Removes the tables at `path` and below, along with their entries.
+    fn remove_tables(&mut self, path: &[String]) -> bool {
+        let items = self.items().unwrap();
+        let mut stale = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            match item.kind {
+                Kind::Table(ref p) | Kind::ArrayOfTables(ref p) if p.starts_with(path) => {}
+                _ => continue,
+            }
+            // Blank lines and comments after the last entry are left, they
+            // may well be about what comes next.
+            let end = items[i + 1..].iter()
+                .take_while(|item| !is_header(item))
+                .filter(|item| item.kind != Kind::Trivia)
+                .last()
+                .map(|item| item.end)
+                .unwrap_or(item.end);
+            let (mut start, mut end) = (item.start, end);
+            // Don't leave two blank lines, or one at the start or the end,
+            // behind.
+            let blank = |i: usize| self.lines.get(i).map(|l| l.trim().is_empty());
+            let blank_before = start == 0 || blank(start - 1) == Some(true);
+            if blank(end) == Some(true) && blank_before {
+                end += 1;
+            } else if blank(end).is_none() && start > 0 && blank_before {
+                start -= 1;
+            }
+            stale.push((start, end));
+        }
+        if stale.is_empty() {
+            return false
+        }
+        self.replace(stale, 0, Vec::new());
+        true
+    }
+
+    /// Removes the `stale` spans of lines and inserts `new` at line `at`.
+    fn replace(&mut self, stale: Vec<(usize, usize)>, at: usize, new: Vec<String>) {
+        let lines = ::std::mem::replace(&mut self.lines, Vec::new());
+        let mut new = Some(new);
+        for (i, line) in lines.into_iter().enumerate() {
+            if i == at {
+                self.lines.extend(new.take().unwrap());
+            }
+            if !stale.iter().any(|&(start, end)| start <= i && i < end) {
+                self.lines.push(line);
+            }
+        }
+        if let Some(new) = new {
+            self.lines.extend(new);
+        }
+    }
+
+    fn items(&self) -> CargoResult<Vec<Item>> {
+        let mut items = Vec::new();
+        let mut i = 0;
+        while i < self.lines.len() {
+            let line = self.lines[i].trim();
+            let start = i;
+            let mut value = ValueScanner::default();
+            let kind = if line.is_empty() || line.starts_with('#') {
+                Kind::Trivia
+            } else if line.starts_with("[[") {
+                let (path, rest) = parse_key(&line[2..])?;
+                if !rest.starts_with("]]") {
+                    bail!("expected `]]` at the end of line {}", i + 1)
+                }
+                value.scan(&rest[2..]);
+                Kind::ArrayOfTables(path)
+            } else if line.starts_with('[') {
+                let (path, rest) = parse_key(&line[1..])?;
+                if !rest.starts_with(']') {
+                    bail!("expected `]` at the end of line {}", i + 1)
+                }
+                value.scan(&rest[1..]);
+                Kind::Table(path)
+            } else {
+                let (key, rest) = parse_key(line)?;
+                if !rest.starts_with('=') {
+                    bail!("expected `=` after the key on line {}", i + 1)
+                }
+                value.scan(&rest[1..]);
+                while !value.done() {
+                    i += 1;
+                    if i == self.lines.len() {
+                        bail!("unterminated value starting on line {}", start + 1)
+                    }
+                    value.scan(&self.lines[i]);
+                }
+                Kind::Entry(key)
+            };
+            i += 1;
+            items.push(Item {
+                start: start,
+                end: i,
+                kind: kind,
+                comment: value.comment,
+            });
+        }
+        Ok(items)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.newline)?;
+            }
+            f.write_str(line)?;
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            f.write_str(self.newline)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats `value` the way it's written inline, e.g. `{ version = "1.0" }`.
+pub fn to_inline(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => quote(s),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => format!("{:?}", f),
+        Value::Boolean(b) => b.to_string(),
+        Value::Datetime(ref d) => d.to_string(),
+        Value::Array(ref a) => {
+            let values = a.iter().map(to_inline).collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Table(ref t) => inline_table(t.iter().map(|(k, v)| (&k[..], v))),
+    }
+}
+
+/// Formats an inline table with the `entries` in the order given.
+pub fn inline_table<'a, I>(entries: I) -> String
+    where I: IntoIterator<Item = (&'a str, &'a Value)>
+{
+    let entries = entries.into_iter()
+                         .map(|(k, v)| format!("{} = {}", key_repr(k), to_inline(v)))
+                         .collect::<Vec<_>>();
+    if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", entries.join(", "))
+    }
+}
+
+/// Adds the keys of the inline `table` at `path`, which is written on the
+/// lines in `span`, to `keys`.
+fn inline_keys(path: &[String],
+               table: &toml::value::Table,
+               span: &Range<usize>,
+               keys: &mut Vec<(Vec<String>, Range<usize>)>) {
+    for (k, v) in table.iter() {
+        let mut path = path.to_vec();
+        path.push(k.clone());
+        keys.push((path.clone(), span.clone()));
+        if let Value::Table(ref t) = *v {
+            inline_keys(&path, t, span, keys);
+        }
+    }
+}
+
+/// The span `[header, end)` of items making up the table at `path`.
+fn section(items: &[Item], path: &[&str]) -> Option<(usize, usize)> {
+    let header = match items.iter().position(|item| match item.kind {
+        Kind::Table(ref p) => p.len() == path.len() && p.iter().zip(path).all(|(a, b)| a == b),
+        _ => false,
+    }) {
+        Some(header) => header,
+        None => return None,
+    };
+    let end = items[header + 1..].iter()
+                                 .position(is_header)
+                                 .map(|n| header + 1 + n)
+                                 .unwrap_or(items.len());
+    Some((header, end))
+}
+
+fn is_header(item: &Item) -> bool {
+    match item.kind {
+        Kind::Table(..) | Kind::ArrayOfTables(..) => true,
+        _ => false,
+    }
+}
+
+fn indent(line: &str) -> &str {
+    &line[..line.len() - line.trim_left().len()]
+}
+
+fn path_repr(path: &[&str]) -> String {
+    path.iter().map(|k| key_repr(k)).collect::<Vec<_>>().join(".")
+}
+
+fn key_repr(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(is_bare) {
+        key.to_string()
+    } else if !key.contains('\'') && !key.chars().any(|c| c.is_control()) {
+        format!("'{}'", key)
+    } else {
+        quote(key)
+    }
+}
+
+/// Whether `c` can be part of a bare key.
+fn is_bare(c: char) -> bool {
+    match c {
+        'a'...'z' | 'A'...'Z' | '0'...'9' | '-' | '_' => true,
+        _ => false,
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parses a (possibly dotted) key at the start of `s`, returning it along with
+/// the rest of `s` after any whitespace.
+fn parse_key(s: &str) -> CargoResult<(Vec<String>, &str)> {
+    let mut key = Vec::new();
+    let mut rest = s.trim_left();
+    loop {
+        let (part, after) = if rest.starts_with('"') {
+            let mut part = String::new();
+            let mut chars = rest[1..].char_indices();
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        end = Some(i + 2);
+                        break
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => part.push('\n'),
+                        Some((_, 't')) => part.push('\t'),
+                        Some((_, 'r')) => part.push('\r'),
+                        Some((_, 'b')) => part.push('\u{8}'),
+                        Some((_, 'f')) => part.push('\u{c}'),
+                        Some((j, 'u')) | Some((j, 'U')) => {
+                            let len = if rest[1 + j..].starts_with('u') { 4 } else { 8 };
+                            let hex = rest.get(2 + j..2 + j + len).unwrap_or("");
+                            match u32::from_str_radix(hex, 16).ok()
+                                                               .and_then(::std::char::from_u32) {
+                                Some(c) => part.push(c),
+                                None => bail!("invalid escape in key `{}`", s.trim()),
+                            }
+                            for _ in 0..len {
+                                chars.next();
+                            }
+                        }
+                        Some((_, c)) => part.push(c),
+                        None => {}
+                    },
+                    c => part.push(c),
+                }
+            }
+            match end {
+                Some(end) => (part, &rest[end..]),
+                None => bail!("unterminated key `{}`", s.trim()),
+            }
+        } else if rest.starts_with('\'') {
+            match rest[1..].find('\'') {
+                Some(end) => (rest[1..end + 1].to_string(), &rest[end + 2..]),
+                None => bail!("unterminated key `{}`", s.trim()),
+            }
+        } else {
+            let end = rest.find(|c: char| !is_bare(c)).unwrap_or(rest.len());
+            if end == 0 {
+                bail!("expected a key in `{}`", s.trim())
+            }
+            (rest[..end].to_string(), &rest[end..])
+        };
+        key.push(part);
+        rest = after.trim_left();
+        if rest.starts_with('.') {
+            rest = rest[1..].trim_left();
+        } else {
+            return Ok((key, rest))
+        }
+    }
+}
+
+/// Follows a value across lines, to find out where it ends.
+#[derive(Default)]
+struct ValueScanner {
+    /// How many arrays and inline tables are open.
+    depth: usize,
+    /// The delimiter of the multi-line string which is open, if any.
+    multiline: Option<&'static str>,
+    /// The comment on the line scanned last.
+    comment: Option<String>,
+}
+
+impl ValueScanner {
+    fn done(&self) -> bool {
+        self.depth == 0 && self.multiline.is_none()
+    }
+
+    fn scan(&mut self, line: &str) {
+        self.comment = None;
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if let Some(delim) = self.multiline {
+                if bytes[i..].starts_with(delim.as_bytes()) {
+                    self.multiline = None;
+                    i += 3;
+                } else if bytes[i] == b'\\' && delim == "\"\"\"" {
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+                continue
+            }
+            match bytes[i] {
+                b'#' => {
+                    self.comment = Some(line[i..].trim_right().to_string());
+                    return
+                }
+                b'"' | b'\'' => {
+                    let delim = if bytes[i] == b'"' { "\"\"\"" } else { "'''" };
+                    if bytes[i..].starts_with(delim.as_bytes()) {
+                        self.multiline = Some(delim);
+                        i += 3;
+                        continue
+                    }
+                    let quote = bytes[i];
+                    i += 1;
+                    while i < bytes.len() && bytes[i] != quote {
+                        if bytes[i] == b'\\' && quote == b'"' {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                b'[' | b'{' => {
+                    self.depth += 1;
+                    i += 1;
+                }
+                b']' | b'}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    i += 1;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Document;
+
+    fn edit<F: FnOnce(&mut Document)>(before: &str, f: F) -> String {
+        let mut doc = Document::parse(before).unwrap();
+        f(&mut doc);
+        doc.to_string()
+    }
+
+    #[test]
+    fn set_keeps_formatting() {
+        let before = "\
+# The package.
+[package]
+name = \"foo\"   # its name
+
+[dependencies]
+  bar = \"0.1\" # keep this
+  baz = { version = \"0.2\",
+          features = [\"a\"] }
+";
+        let after = edit(before, |doc| doc.set(&["dependencies"], "bar", "\"0.2\""));
+        assert_eq!(after, before.replace("bar = \"0.1\"", "bar = \"0.2\""));
+
+        let after = edit(before, |doc| doc.set(&["dependencies"], "bay", "\"1\""));
+        assert_eq!(after, before.replace("  baz", "  bay = \"1\"\n  baz"));
+
+        let after = edit(before, |doc| doc.set(&["dependencies"], "baz", "\"1\""));
+        assert_eq!(after, "\
+# The package.
+[package]
+name = \"foo\"   # its name
+
+[dependencies]
+  bar = \"0.1\" # keep this
+  baz = \"1\"
+");
+    }
+
+    #[test]
+    fn set_adds_tables() {
+        let before = "[package]\r\nname = \"foo\"\r\n";
+        let after = edit(before, |doc| {
+            doc.set(&["target", "cfg(unix)", "dependencies"], "bar", "\"1\"")
+        });
+        assert_eq!(after, "[package]\r\nname = \"foo\"\r\n\r\n\
+                           [target.'cfg(unix)'.dependencies]\r\nbar = \"1\"\r\n");
+    }
+
+    #[test]
+    fn set_replaces_tables() {
+        let before = "\
+[dependencies.bar]
+version = \"0.1\"
+
+[dev-dependencies]
+baz = \"1\"
+";
+        let after = edit(before, |doc| doc.set(&["dependencies"], "bar", "\"0.2\""));
+        assert_eq!(after, "\
+[dev-dependencies]
+baz = \"1\"
+
+[dependencies]
+bar = \"0.2\"
+");
+    }
+
+    #[test]
+    fn remove() {
+        let before = "\
+[dependencies]
+bar = \"0.1\"
+baz = '''
+x'''
+
+[dependencies.qux]
+version = \"1\"
+
+# Comment
+[features]
+";
+        let after = edit(before, |doc| {
+            assert!(doc.remove(&["dependencies"], "baz"));
+            assert!(doc.remove(&["dependencies"], "qux"));
+            assert!(!doc.remove(&["dependencies"], "nope"));
+        });
+        assert_eq!(after, "\
+[dependencies]
+bar = \"0.1\"
+
+# Comment
+[features]
+");
+    }
+
+    #[test]
+    fn keys() {
+        let doc = Document::parse("\
+[package]
+name = \"foo\"
+
+[target.'cfg(unix)'.dependencies]
+bar.version = \"1\"
+baz = { version = \"1\",
+        features = [\"a\"] }
+
+[dev_dependencies.qux]
+version = \"1\"
+").unwrap();
+        let keys = doc.keys().into_iter().map(|(path, lines)| {
+            (path.join("."), lines.start, lines.end)
+        }).collect::<Vec<_>>();
+        let expected = [
+            ("package", 0, 1),
+            ("package.name", 1, 2),
+            ("target.cfg(unix).dependencies", 3, 4),
+            ("target.cfg(unix).dependencies.bar.version", 4, 5),
+            ("target.cfg(unix).dependencies.baz", 5, 7),
+            ("target.cfg(unix).dependencies.baz.features", 5, 7),
+            ("target.cfg(unix).dependencies.baz.version", 5, 7),
+            ("dev_dependencies.qux", 8, 9),
+            ("dev_dependencies.qux.version", 9, 10),
+        ];
+        let expected = expected.iter().map(|&(path, start, end)| {
+            (path.to_string(), start, end)
+        }).collect::<Vec<_>>();
+        assert_eq!(keys, expected);
+        assert_eq!(doc.line(4), "bar.version = \"1\"");
+    }
+
+    #[test]
+    fn non_ascii_multi_line_strings() {
+        let before = "\
+[package]
+name = \"foo\"
+description = \"\"\"
+Ünïcödé — ☃\"\"\"
+license = '''é'''
+
+[dependencies]
+";
+        let after = edit(before, |doc| doc.set(&["dependencies"], "bar", "\"1\""));
+        assert_eq!(after, format!("{}bar = \"1\"\n", before));
+        let doc = Document::parse(before).unwrap();
+        let keys = doc.keys().into_iter().map(|(path, _)| path.join("."))
+                                         .collect::<Vec<_>>();
+        assert_eq!(keys, ["package", "package.name", "package.description",
+                          "package.license", "dependencies"]);
+    }
+}
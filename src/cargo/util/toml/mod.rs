@@ -16,12 +16,14 @@ use core::{SourceId, Profiles, PackageIdSpec, GitReference, WorkspaceConfig, Wor
 use core::{Summary, Manifest, Target, Dependency, PackageId};
 use core::{EitherManifest, VirtualManifest, Features, Feature};
 use core::dependency::{Kind, Platform};
-use core::manifest::{LibKind, Profile, ManifestMetadata};
-use sources::CRATES_IO;
+use core::summary;
+use core::manifest::{LibKind, Lto, Profile, ManifestMetadata, ProfileOverride, ProfileOverrides};
+use sources::{CRATES_IO, git};
 use util::paths;
 use util::{self, ToUrl, Config};
 use util::errors::{CargoError, CargoResult, CargoResultExt};
 
+pub mod edit;
 mod targets;
 use self::targets::targets;
 
@@ -332,7 +334,7 @@ impl<'de> de::Deserialize<'de> for U32OrBool {
 pub struct TomlProfile {
     #[serde(rename = "opt-level")]
     opt_level: Option<TomlOptLevel>,
-    lto: Option<bool>,
+    lto: Option<StringOrBool>,
     #[serde(rename = "codegen-units")]
     codegen_units: Option<u32>,
     debug: Option<U32OrBool>,
@@ -342,6 +344,13 @@ pub struct TomlProfile {
     panic: Option<String>,
     #[serde(rename = "overflow-checks")]
     overflow_checks: Option<bool>,
+    #[serde(rename = "crt-static")]
+    crt_static: Option<bool>,
+    strip: Option<StringOrBool>,
+    #[serde(rename = "split-debuginfo")]
+    split_debuginfo: Option<String>,
+    incremental: Option<bool>,
+    package: Option<BTreeMap<String, TomlProfile>>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -388,6 +397,8 @@ pub struct TomlProject {
     authors: Option<Vec<String>>,
     build: Option<StringOrBool>,
     links: Option<String>,
+    #[serde(rename = "default-run")]
+    default_run: Option<String>,
     exclude: Option<Vec<String>>,
     include: Option<Vec<String>>,
     publish: Option<bool>,
@@ -545,6 +556,18 @@ impl TomlManifest {
                                    build targets: {}", e));
         }
 
+        if let Some(ref run) = project.default_run {
+            if !targets.iter().any(|t| t.is_bin() && t.name() == run) {
+                let bins = targets.iter().filter(|t| t.is_bin())
+                                  .map(|t| format!("`{}`", t.name()))
+                                  .collect::<Vec<_>>();
+                bail!("`default-run` names the binary `{}`, which doesn't exist\n\
+                       available binaries: {}",
+                      run,
+                      if bins.is_empty() { "none".to_string() } else { bins.join(", ") })
+            }
+        }
+
         let mut deps = Vec::new();
         let replace;
         let patch;
@@ -592,7 +615,20 @@ impl TomlManifest {
             process_dependencies(&mut cx, build_deps, Some(Kind::Build))?;
 
             for (name, platform) in me.target.iter().flat_map(|t| t) {
-                cx.platform = Some(name.parse()?);
+                let parsed = name.parse()?;
+                // The cfg values dependencies are selected with come from the
+                // compiler, which knows nothing about the package's features.
+                if let Platform::Cfg(ref e) = parsed {
+                    if e.mentions("feature") {
+                        cx.warnings.push(format!(
+                            "`feature` is used in `target.'{}'`, but it's never \
+                             set when selecting dependencies, so this table \
+                             won't work as expected; make the dependencies \
+                             optional and enable them with the `[features]` \
+                             section instead", name));
+                    }
+                }
+                cx.platform = Some(parsed);
                 process_dependencies(&mut cx, platform.dependencies.as_ref(),
                                      None)?;
                 let build_deps = platform.build_dependencies.as_ref()
@@ -623,8 +659,27 @@ impl TomlManifest {
         let exclude = project.exclude.clone().unwrap_or_default();
         let include = project.include.clone().unwrap_or_default();
 
-        let summary = Summary::new(pkgid, deps, me.features.clone()
-            .unwrap_or_else(BTreeMap::new))?;
+        let features_map = me.features.clone().unwrap_or_else(BTreeMap::new);
+        for (feature, list) in features_map.iter() {
+            for entry in list.iter() {
+                summary::validate_feature_entry(feature, entry, &features_map,
+                                                &deps).chain_err(|| {
+                    let manifest = package_root.join("Cargo.toml");
+                    match feature_location(&manifest, feature, entry) {
+                        Some(line) => {
+                            format!("invalid entry `{}` in feature `{}` at {}:{}",
+                                    entry, feature, manifest.display(), line)
+                        }
+                        None => {
+                            format!("invalid entry `{}` in feature `{}`",
+                                    entry, feature)
+                        }
+                    }
+                })?;
+            }
+        }
+        let summary = Summary::new(pkgid, deps, features_map)?
+            .set_links(project.links.clone());
         let metadata = ManifestMetadata {
             description: project.description.clone(),
             homepage: project.homepage.clone(),
@@ -654,13 +709,14 @@ impl TomlManifest {
                        `[workspace]`, only one can be specified")
             }
         };
-        let profiles = build_profiles(&me.profile);
+        let profiles = build_profiles(&me.profile)?;
         let publish = project.publish.unwrap_or(true);
         let mut manifest = Manifest::new(summary,
                                          targets,
                                          exclude,
                                          include,
                                          project.links.clone(),
+                                         project.default_run.clone(),
                                          metadata,
                                          profiles,
                                          publish,
@@ -734,7 +790,7 @@ impl TomlManifest {
             };
             (me.replace(&mut cx)?, me.patch(&mut cx)?)
         };
-        let profiles = build_profiles(&me.profile);
+        let profiles = build_profiles(&me.profile)?;
         let workspace_config = match me.workspace {
             Some(ref config) => {
                 WorkspaceConfig::Root(
@@ -835,6 +891,27 @@ fn unique_build_targets(targets: &[Target], package_root: &Path) -> Result<(), S
     Ok(())
 }
 
+/// Finds the line of the manifest at `path` on which `feature` lists `entry`,
+/// falling back to the line on which `feature` itself is defined.
+fn feature_location(path: &Path, feature: &str, entry: &str) -> Option<usize> {
+    let doc = match paths::read(path).and_then(|c| edit::Document::parse(&c)) {
+        Ok(doc) => doc,
+        Err(..) => return None,
+    };
+    let lines = doc.keys().into_iter().find(|&(ref path, _)| {
+        path.len() == 2 && path[0] == "features" && path[1] == feature
+    });
+    let lines = match lines {
+        Some((_, lines)) => lines,
+        None => return None,
+    };
+    let values = [format!("\"{}\"", entry), format!("'{}'", entry)];
+    let line = lines.clone().find(|&i| {
+        values.iter().any(|v| doc.line(i).contains(&v[..]))
+    });
+    Some(line.unwrap_or(lines.start) + 1)
+}
+
 impl TomlDependency {
     fn to_dependency(&self,
                      name: &str,
@@ -904,7 +981,7 @@ impl TomlDependency {
                     .or_else(|| details.tag.clone().map(GitReference::Tag))
                     .or_else(|| details.rev.clone().map(GitReference::Rev))
                     .unwrap_or_else(|| GitReference::Branch("master".to_string()));
-                let loc = git.to_url()?;
+                let loc = git::to_url(git)?;
                 SourceId::for_git(&loc, reference)?
             },
             (None, Some(path), _) => {
@@ -1039,7 +1116,11 @@ impl fmt::Debug for PathValue {
     }
 }
 
-fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
+fn build_profiles(profiles: &Option<TomlProfiles>) -> CargoResult<Profiles> {
+    if let Some(ref toml) = *profiles {
+        validate_overrides(toml)?;
+        validate_values(toml)?;
+    }
     let profiles = profiles.as_ref();
     let mut profiles = Profiles {
         release: merge(Profile::default_release(),
@@ -1062,32 +1143,156 @@ fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
         check_test: merge(Profile::default_check_test(),
                           profiles.and_then(|p| p.dev.as_ref())),
         doctest: Profile::default_doctest(),
+        dev_overrides: overrides("dev", profiles.and_then(|p| p.dev.as_ref()))?,
+        release_overrides: overrides("release",
+                                     profiles.and_then(|p| p.release.as_ref()))?,
     };
     // The test/bench targets cannot have panic=abort because they'll all get
-    // compiled with --test which requires the unwind runtime currently
+    // compiled with --test which requires the unwind runtime currently, and
+    // neither can their dependencies since a crate built to abort can't be
+    // linked into one which unwinds. `cargo check --profile test` passes
+    // --test as well.
     profiles.test.panic = None;
     profiles.bench.panic = None;
     profiles.test_deps.panic = None;
     profiles.bench_deps.panic = None;
-    return profiles;
+    profiles.check_test.panic = None;
+    return Ok(profiles);
+
+    /// Rejects `package` tables which can't be honored.
+    fn validate_overrides(toml: &TomlProfiles) -> CargoResult<()> {
+        let named = [("test", &toml.test), ("doc", &toml.doc), ("bench", &toml.bench),
+                     ("dev", &toml.dev), ("release", &toml.release)];
+        for &(name, profile) in named.iter() {
+            let packages = match profile.as_ref().and_then(|p| p.package.as_ref()) {
+                Some(packages) => packages,
+                None => continue,
+            };
+            if name != "dev" && name != "release" {
+                bail!("`[profile.{}.package]` isn't supported, only the `dev` and \
+                       `release` profiles can be overridden for some packages", name)
+            }
+            for (spec, o) in packages {
+                if o.package.is_some() {
+                    bail!("`[profile.{}.package.\"{}\".package]` isn't supported, \
+                           overrides can't be nested", name, spec)
+                }
+                let shared = [("lto", o.lto.is_some()),
+                              ("rpath", o.rpath.is_some()),
+                              ("panic", o.panic.is_some()),
+                              ("crt-static", o.crt_static.is_some()),
+                              ("strip", o.strip.is_some()),
+                              ("split-debuginfo", o.split_debuginfo.is_some()),
+                              ("incremental", o.incremental.is_some())];
+                for &(key, set) in shared.iter() {
+                    if set {
+                        bail!("`{}` can't be set in `[profile.{}.package.\"{}\"]`, \
+                               it has to be the same for every package", key, name, spec)
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 
-    fn merge(profile: Profile, toml: Option<&TomlProfile>) -> Profile {
-        let &TomlProfile {
-            ref opt_level, lto, codegen_units, ref debug, debug_assertions, rpath,
-            ref panic, ref overflow_checks,
-        } = match toml {
-            Some(toml) => toml,
-            None => return profile,
+    /// Rejects values of settings which rustc or Cargo wouldn't understand.
+    fn validate_values(toml: &TomlProfiles) -> CargoResult<()> {
+        let named = [("test", &toml.test), ("doc", &toml.doc), ("bench", &toml.bench),
+                     ("dev", &toml.dev), ("release", &toml.release)];
+        for &(name, profile) in named.iter() {
+            let profile = match *profile {
+                Some(ref profile) => profile,
+                None => continue,
+            };
+            if let Some(ref s) = profile.panic {
+                if s != "unwind" && s != "abort" {
+                    bail!("`panic` in `[profile.{}]` must be `\"unwind\"` or `\"abort\"`, \
+                           but found `\"{}\"`", name, s)
+                }
+            }
+            if let Some(StringOrBool::String(ref s)) = profile.lto {
+                if s != "thin" && s != "fat" && s != "off" {
+                    bail!("`lto` in `[profile.{}]` must be `true`, `false`, `\"thin\"`, \
+                           `\"fat\"` or `\"off\"`, but found `\"{}\"`", name, s)
+                }
+            }
+            let overrides = profile.package.iter().flat_map(|p| p.values());
+            for codegen_units in Some(profile).into_iter().chain(overrides)
+                                              .filter_map(|p| p.codegen_units) {
+                if codegen_units == 0 {
+                    bail!("`codegen-units` in `[profile.{}]` must be at least 1", name)
+                }
+            }
+            if let Some(StringOrBool::String(ref s)) = profile.strip {
+                if s != "none" && s != "debuginfo" && s != "symbols" {
+                    bail!("`strip` in `[profile.{}]` must be `true`, `false`, \
+                           `\"none\"`, `\"debuginfo\"` or `\"symbols\"`, but found `\"{}\"`",
+                          name, s)
+                }
+            }
+            if let Some(ref s) = profile.split_debuginfo {
+                if s != "off" && s != "packed" && s != "unpacked" {
+                    bail!("`split-debuginfo` in `[profile.{}]` must be `\"off\"`, \
+                           `\"packed\"` or `\"unpacked\"`, but found `\"{}\"`", name, s)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn overrides(name: &str, toml: Option<&TomlProfile>) -> CargoResult<ProfileOverrides> {
+        let mut overrides = ProfileOverrides::default();
+        let packages = match toml.and_then(|t| t.package.as_ref()) {
+            Some(packages) => packages,
+            None => return Ok(overrides),
         };
-        let debug = match *debug {
+        for (spec, o) in packages {
+            let o = ProfileOverride {
+                opt_level: o.opt_level.as_ref().map(|o| o.0.clone()),
+                codegen_units: o.codegen_units,
+                debuginfo: debuginfo(&o.debug),
+                debug_assertions: o.debug_assertions,
+                overflow_checks: o.overflow_checks,
+            };
+            if spec == "*" {
+                overrides.all = Some(o);
+            } else {
+                let spec = PackageIdSpec::parse(spec).chain_err(|| {
+                    format!("invalid package in `[profile.{}.package]`", name)
+                })?;
+                overrides.packages.push((spec, o));
+            }
+        }
+        Ok(overrides)
+    }
+
+    fn debuginfo(debug: &Option<U32OrBool>) -> Option<Option<u32>> {
+        match *debug {
             Some(U32OrBool::U32(debug)) => Some(Some(debug)),
             Some(U32OrBool::Bool(true)) => Some(Some(2)),
             Some(U32OrBool::Bool(false)) => Some(None),
             None => None,
+        }
+    }
+
+    fn merge(profile: Profile, toml: Option<&TomlProfile>) -> Profile {
+        let &TomlProfile {
+            ref opt_level, ref lto, codegen_units, ref debug, debug_assertions, rpath,
+            ref panic, ref overflow_checks, crt_static, ref strip, ref split_debuginfo,
+            incremental, package: _,
+        } = match toml {
+            Some(toml) => toml,
+            None => return profile,
         };
+        let debug = debuginfo(debug);
         Profile {
             opt_level: opt_level.clone().unwrap_or(TomlOptLevel(profile.opt_level)).0,
-            lto: lto.unwrap_or(profile.lto),
+            lto: match *lto {
+                Some(StringOrBool::Bool(b)) => Lto::Bool(b),
+                Some(StringOrBool::String(ref s)) if s == "off" => Lto::Bool(false),
+                Some(StringOrBool::String(ref s)) => Lto::Named(s.clone()),
+                None => profile.lto,
+            },
             codegen_units: codegen_units,
             rustc_args: None,
             rustdoc_args: None,
@@ -1100,6 +1305,16 @@ fn build_profiles(profiles: &Option<TomlProfiles>) -> Profiles {
             run_custom_build: profile.run_custom_build,
             check: profile.check,
             panic: panic.clone().or(profile.panic),
+            crt_static: crt_static.or(profile.crt_static),
+            strip: match *strip {
+                Some(StringOrBool::Bool(true)) => Some("symbols".to_string()),
+                Some(StringOrBool::Bool(false)) => None,
+                Some(StringOrBool::String(ref s)) if s == "none" => None,
+                Some(StringOrBool::String(ref s)) => Some(s.clone()),
+                None => profile.strip,
+            },
+            split_debuginfo: split_debuginfo.clone().or(profile.split_debuginfo),
+            incremental: incremental.or(profile.incremental),
         }
     }
 }
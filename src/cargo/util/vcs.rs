@@ -1,9 +1,10 @@
+use std::io::{self, Write};
 use std::path::Path;
 use std::fs::create_dir;
 
 use git2;
 
-use util::{CargoResult, process};
+use util::{CargoResult, ProcessBuilder, process};
 
 pub struct HgRepo;
 pub struct GitRepo;
@@ -22,7 +23,7 @@ impl GitRepo {
 
 impl HgRepo {
     pub fn init(path: &Path, cwd: &Path) -> CargoResult<HgRepo> {
-        process("hg").cwd(cwd).arg("init").arg(path).exec()?;
+        run(process("hg").cwd(cwd).arg("init").arg(path))?;
         Ok(HgRepo)
     }
     pub fn discover(path: &Path, cwd: &Path) -> CargoResult<HgRepo> {
@@ -33,7 +34,7 @@ impl HgRepo {
 
 impl PijulRepo {
     pub fn init(path: &Path, cwd: &Path) -> CargoResult<PijulRepo> {
-        process("pijul").cwd(cwd).arg("init").arg(path).exec()?;
+        run(process("pijul").cwd(cwd).arg("init").arg(path))?;
         Ok(PijulRepo)
     }
 }
@@ -49,10 +50,10 @@ impl FossilRepo {
         db_path.push(db_fname);
 
         // then create the fossil DB in that location
-        process("fossil").cwd(cwd).arg("init").arg(&db_path).exec()?;
+        run(process("fossil").cwd(cwd).arg("init").arg(&db_path))?;
 
         // open it in that new directory
-        process("fossil").cwd(&path).arg("open").arg(db_fname).exec()?;
+        run(process("fossil").cwd(&path).arg("open").arg(db_fname))?;
 
         // set `target` as ignoreable and cleanable
         process("fossil").cwd(cwd).arg("settings")
@@ -64,3 +65,18 @@ impl FossilRepo {
         Ok(FossilRepo)
     }
 }
+
+/// Runs `cmd` with whatever it prints going to stderr, as stdout is reserved
+/// for Cargo's machine-readable output.
+fn run(cmd: &ProcessBuilder) -> CargoResult<()> {
+    let mut on_stdout = |line: &str| -> CargoResult<()> {
+        writeln!(io::stderr(), "{}", line)?;
+        Ok(())
+    };
+    let mut on_stderr = |line: &str| -> CargoResult<()> {
+        writeln!(io::stderr(), "{}", line)?;
+        Ok(())
+    };
+    cmd.exec_with_streaming(&mut on_stdout, &mut on_stderr, false)?;
+    Ok(())
+}
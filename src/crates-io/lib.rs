@@ -12,6 +12,7 @@ use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::{self, Cursor};
+use std::time::Duration;
 
 use curl::easy::{Easy, List};
 
@@ -40,8 +41,20 @@ body:
             Api(errs: Vec<String>) {
                 display("api errors: {}", errs.join(", "))
             }
-            Unauthorized {
-                display("unauthorized API access")
+            Unauthorized(errs: Vec<String>) {
+                display("unauthorized API access{}", details(errs))
+            }
+            RateLimited(retry_after: Option<u64>) {
+                description("too many requests to the registry")
+                display("too many requests to the registry{}", match *retry_after {
+                    Some(secs) => format!(", it asks to wait {} seconds", secs),
+                    None => String::new(),
+                })
+            }
+            ServerError(code: u32, retry_after: Option<u64>, errs: Vec<String>) {
+                description("the registry failed to handle the request")
+                display("the registry failed to handle the request (status {}){}",
+                        code, details(errs))
             }
             TokenMissing{
                 display("no upload token found, please run `cargo login`")
@@ -89,6 +102,7 @@ pub struct NewCrate {
     pub license_file: Option<String>,
     pub repository: Option<String>,
     pub badges: BTreeMap<String, BTreeMap<String, String>>,
+    pub links: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -100,6 +114,10 @@ pub struct NewCrateDependency {
     pub version_req: String,
     pub target: Option<String>,
     pub kind: String,
+    /// The index of the registry the dependency is from, if it's not the one
+    /// the crate is published to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -111,6 +129,13 @@ pub struct User {
     pub name: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct Version {
+    pub num: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
 pub struct Warnings {
     pub invalid_categories: Vec<String>,
     pub invalid_badges: Vec<String>,
@@ -122,6 +147,8 @@ pub struct Warnings {
 #[derive(Deserialize)] struct ApiError { detail: String }
 #[derive(Serialize)] struct OwnersReq<'a> { users: &'a [&'a str] }
 #[derive(Deserialize)] struct Users { users: Vec<User> }
+#[derive(Deserialize)] struct Me { user: User }
+#[derive(Deserialize)] struct CrateVersions { versions: Vec<Version> }
 #[derive(Deserialize)] struct TotalCrates { total: u32 }
 #[derive(Deserialize)] struct Crates { crates: Vec<Crate>, meta: TotalCrates }
 impl Registry {
@@ -160,6 +187,19 @@ impl Registry {
         Ok(serde_json::from_str::<Users>(&body)?.users)
     }
 
+    /// Every version of a crate, yanked ones included.
+    pub fn versions(&mut self, krate: &str) -> Result<Vec<Version>> {
+        self.handle.get(true)?;
+        let body = self.req(format!("/crates/{}", krate), None, Auth::Unauthorized)?;
+        Ok(serde_json::from_str::<CrateVersions>(&body)?.versions)
+    }
+
+    /// The user the token belongs to.
+    pub fn me(&mut self) -> Result<User> {
+        let body = self.get("/me".to_string())?;
+        Ok(serde_json::from_str::<Me>(&body)?.user)
+    }
+
     pub fn publish(&mut self, krate: &NewCrate, tarball: &File)
                    -> Result<Warnings> {
         let json = serde_json::to_string(krate)?;
@@ -300,6 +340,58 @@ impl Registry {
     }
 }
 
+impl ErrorKind {
+    /// How long to wait before making the request again, if the registry is
+    /// rate limiting or failing to handle requests for the time being.
+    ///
+    /// Registries say how long in a `Retry-After` header, those which don't
+    /// are given a second when rate limiting.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match *self {
+            ErrorKind::RateLimited(secs) => Some(Duration::from_secs(secs.unwrap_or(1))),
+            ErrorKind::ServerError(_, Some(secs), _) => Some(Duration::from_secs(secs)),
+            _ => None,
+        }
+    }
+
+    /// Whether the request may succeed when it's made again.
+    pub fn is_spurious(&self) -> bool {
+        match *self {
+            ErrorKind::RateLimited(..) | ErrorKind::ServerError(..) => true,
+            _ => false,
+        }
+    }
+}
+
+fn details(errs: &[String]) -> String {
+    if errs.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", errs.join(", "))
+    }
+}
+
+/// The error details in a response body, in the format of crates.io.
+fn api_errors(body: &str) -> Vec<String> {
+    serde_json::from_str::<ApiErrorList>(body).map(|errors| {
+        errors.errors.into_iter().map(|e| e.detail).collect()
+    }).unwrap_or_default()
+}
+
+/// The seconds to wait given in a `Retry-After` header, the HTTP date form
+/// isn't supported.
+fn retry_after(headers: &[String]) -> Option<u64> {
+    headers.iter().filter_map(|header| {
+        let mut parts = header.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if name.trim().to_lowercase() == "retry-after" => {
+                value.trim().parse().ok()
+            }
+            _ => None,
+        }
+    }).next()
+}
+
 fn handle(handle: &mut Easy,
           read: &mut FnMut(&mut [u8]) -> usize) -> Result<String> {
     let mut headers = Vec::new();
@@ -318,27 +410,29 @@ fn handle(handle: &mut Easy,
         handle.perform()?;
     }
 
-    match handle.response_code()? {
+    let code = handle.response_code()?;
+    let errs = api_errors(&String::from_utf8_lossy(&body));
+    match code {
         0 => {} // file upload url sometimes
         200 => {}
-        403 => return Err(Error::from_kind(ErrorKind::Unauthorized)),
+        401 | 403 => return Err(Error::from_kind(ErrorKind::Unauthorized(errs))),
         404 => return Err(Error::from_kind(ErrorKind::NotFound)),
-        code => return Err(Error::from_kind(ErrorKind::NotOkResponse(code, headers, body))),
+        429 => return Err(Error::from_kind(ErrorKind::RateLimited(retry_after(&headers)))),
+        500...599 => {
+            let retry_after = retry_after(&headers);
+            return Err(Error::from_kind(ErrorKind::ServerError(code, retry_after, errs)))
+        }
+        _ if !errs.is_empty() => return Err(Error::from_kind(ErrorKind::Api(errs))),
+        _ => return Err(Error::from_kind(ErrorKind::NotOkResponse(code, headers, body))),
     }
 
     let body = match String::from_utf8(body) {
         Ok(body) => body,
         Err(..) => return Err(Error::from_kind(ErrorKind::NonUtf8Body)),
     };
-    match serde_json::from_str::<ApiErrorList>(&body) {
-        Ok(errors) => {
-            return Err(Error::from_kind(ErrorKind::Api(errors
-                                                           .errors
-                                                           .into_iter()
-                                                           .map(|s| s.detail)
-                                                           .collect())))
-        }
-        Err(..) => {}
+    // crates.io answers some failures with errors in an OK response.
+    if !errs.is_empty() {
+        return Err(Error::from_kind(ErrorKind::Api(errs)))
     }
     Ok(body)
 }
@@ -16,7 +16,7 @@ _cargo()
 	local opt_verbose='-v --verbose'
 	local opt_quiet='-q --quiet'
 	local opt_color='--color'
-	local opt_common="$opt_help $opt_verbose $opt_quiet $opt_color"
+	local opt_common="$opt_help $opt_verbose $opt_quiet $opt_color --config"
 	local opt_pkg='-p --package'
 	local opt_feat='--features --all-features --no-default-features'
 	local opt_mani='--manifest-path'
@@ -26,36 +26,49 @@ _cargo()
 	local opt_lock='--frozen --locked'
 
 	local opt___nocmd="$opt_common -V --version --list"
-	local opt__bench="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --no-run"
-	local opt__build="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --release"
-	local opt__check="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --release"
+	local opt__add="$opt_common $opt_pkg $opt_mani $opt_lock --features --no-default-features --optional --dev --build --target --path --git --branch --tag --rev"
+	local opt__audit="$opt_common $opt_mani $opt_lock --database"
+	local opt__bench="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --refresh $opt_jobs $opt_test --message-format --target --lib --bin --example --no-run"
+	local opt__build="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --refresh $opt_jobs $opt_test --message-format --target --lib --bin --example --release --dry-run --timings --size-report --all-warnings --deny-warnings"
+	local opt__cache="$opt_common $opt_mani $opt_lock stats gc clean --unused-for --max-size --older-than"
+	local opt__check="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --refresh $opt_jobs $opt_test --message-format --target --lib --bin --example --release --all-warnings --deny-warnings"
 	local opt__clean="$opt_common $opt_pkg $opt_mani $opt_lock --target --release"
-	local opt__doc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs --message-format --bin --lib --target --open --no-deps --release"
-	local opt__fetch="$opt_common $opt_mani $opt_lock"
+	local opt__completions="$opt_help bash zsh fish"
+	local opt__doc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --refresh $opt_jobs --message-format --bin --lib --target --open --no-deps --release"
+	local opt__features="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --all --exclude"
+	local opt__fetch="$opt_common $opt_mani $opt_lock --refresh"
 	local opt__generate_lockfile="${opt__fetch}"
 	local opt__git_checkout="$opt_common $opt_lock --reference --url"
+	local opt__graph="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --all --exclude --format --no-dev-dependencies --no-build-dependencies --depth --focus"
 	local opt__help="$opt_help"
+	local opt__info="$opt_common $opt_mani $opt_lock"
 	local opt__init="$opt_common $opt_lock --bin --lib --name --vcs"
 	local opt__install="$opt_common $opt_feat $opt_jobs $opt_lock $opt_force --bin --branch --debug --example --git --list --path --rev --root --tag --vers"
+	local opt__license="$opt_common $opt_pkg $opt_mani $opt_lock --all --exclude --format"
 	local opt__locate_project="$opt_mani -h --help"
-	local opt__login="$opt_common $opt_lock --host"
+	local opt__login="$opt_common $opt_lock --host --registry"
 	local opt__metadata="$opt_common $opt_feat $opt_mani $opt_lock --format-version --no-deps"
 	local opt__new="$opt_common $opt_lock --vcs --bin --lib --name"
-	local opt__owner="$opt_common $opt_lock -a --add -r --remove -l --list --index --token"
+	local opt__outdated="$opt_common $opt_mani $opt_lock"
+	local opt__owner="$opt_common $opt_lock -a --add -r --remove -l --list --index --registry --token"
 	local opt__package="$opt_common $opt_mani $opt_lock $opt_jobs --allow-dirty -l --list --no-verify --no-metadata"
 	local opt__pkgid="${opt__fetch} $opt_pkg"
-	local opt__publish="$opt_common $opt_mani $opt_lock $opt_jobs --allow-dirty --dry-run --host --token --no-verify"
+	local opt__publish="$opt_common $opt_mani $opt_lock $opt_jobs --allow-dirty --dry-run --check --message-format --host --registry --token --no-verify"
 	local opt__read_manifest="$opt_help $opt_verbose $opt_mani $opt_color --no-deps"
-	local opt__run="$opt_common $opt_feat $opt_mani $opt_lock $opt_jobs --message-format --target --bin --example --release"
+	local opt__remove="$opt_common $opt_pkg $opt_mani $opt_lock --dev --build --target"
+	local opt__run="$opt_common $opt_feat $opt_mani $opt_lock --refresh $opt_jobs --message-format --target --bin --example --release"
 	local opt__rustc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --profile --target --lib --bin --example --release"
 	local opt__rustdoc="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --target --lib --bin --example --release --open"
+	local opt__sbom="$opt_common $opt_pkg $opt_mani $opt_lock --all --exclude --format"
 	local opt__search="$opt_common $opt_lock --host --limit"
-	local opt__test="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock $opt_jobs $opt_test --message-format --all --doc --target --lib --bin --example --no-run --release --no-fail-fast"
+	local opt__test="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --refresh $opt_jobs $opt_test --message-format --all --doc --target --lib --bin --example --no-run --release --no-fail-fast --test-jobs"
+	local opt__tree="$opt_common $opt_pkg $opt_feat $opt_mani $opt_lock --all --exclude -d --duplicates -i --invert"
 	local opt__uninstall="$opt_common $opt_lock --bin --root"
-	local opt__update="$opt_common $opt_pkg $opt_mani $opt_lock --aggressive --precise"
+	local opt__update="$opt_common $opt_pkg $opt_mani $opt_lock --aggressive --precise --dry-run"
+	local opt__verify="$opt_common $opt_mani $opt_lock --repair"
 	local opt__verify_project="${opt__fetch}"
 	local opt__version="$opt_help $opt_verbose $opt_color"
-	local opt__yank="$opt_common $opt_lock --vers --undo --index --token"
+	local opt__yank="$opt_common $opt_lock --vers --undo --index --registry --token"
 
 	if [[ $cword -eq 1 ]]; then
 		if [[ "$cur" == -* ]]; then
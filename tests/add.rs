@@ -0,0 +1,279 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs, Project};
+use hamcrest::assert_that;
+
+fn manifest(p: &Project) -> String {
+    let mut contents = String::new();
+    File::open(p.root().join("Cargo.toml")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    contents
+}
+
+#[test]
+fn add_from_registry() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+# Sorted, please.
+[dependencies]
+aaa = "1.0"   # the first one
+zzz = "1.0"
+"#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("aaa", "1.0.0").publish();
+    Package::new("zzz", "1.0.0").publish();
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0").publish();
+    Package::new("bar", "0.3.0-beta").publish();
+
+    assert_that(p.cargo("add").arg("bar"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[ADDING] bar v0.2.0 to dependencies
+"));
+    assert_eq!(manifest(&p), r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+# Sorted, please.
+[dependencies]
+aaa = "1.0"   # the first one
+bar = "0.2.0"
+zzz = "1.0"
+"#);
+
+    assert_that(p.cargo("add").arg("aaa@1.1").arg("--features").arg("a,b"),
+                execs().with_status(0).with_stderr("\
+[ADDING] aaa v1.1 to dependencies
+"));
+    assert_that(p.cargo("add").arg("aaa").arg("--features").arg("b c"),
+                execs().with_status(0));
+    assert_eq!(manifest(&p), r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+# Sorted, please.
+[dependencies]
+aaa = { version = "1.1", features = ["a", "b", "c"] }   # the first one
+bar = "0.2.0"
+zzz = "1.0"
+"#);
+}
+
+#[test]
+fn add_unknown_package() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("add").arg("baz"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] the package `baz` could not be found in registry `[..]`"));
+}
+
+#[test]
+fn add_path_and_git() {
+    let git = git::new("baz", |project| {
+        project.file("Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+"#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("add").arg("bar").arg("--path").arg("bar")
+                 .arg("--dev").arg("--target").arg("cfg(unix)"),
+                execs().with_status(0).with_stderr("\
+[ADDING] bar (bar) to dev-dependencies for target `cfg(unix)`
+"));
+    assert_that(p.cargo("add").arg("baz").arg("--git").arg(git.url().to_string())
+                 .arg("--branch").arg("master").arg("--optional"),
+                execs().with_status(0));
+    assert_eq!(manifest(&p), format!(r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+[target.'cfg(unix)'.dev-dependencies]
+bar = {{ path = "bar" }}
+
+[dependencies]
+baz = {{ git = "{}", branch = "master", optional = true }}
+"#, git.url()));
+
+    assert_that(p.cargo("add").arg("bar").arg("baz").arg("--path").arg("bar"),
+                execs().with_status(101).with_stderr("\
+[ERROR] only one dependency can be added at a time with `--path` or `--git`
+"));
+    assert_that(p.cargo("add").arg("qux").arg("--path").arg("qux"),
+                execs().with_status(101).with_stderr("\
+[ERROR] no package found at `qux`
+"));
+}
+
+#[test]
+fn add_keeps_invalid_result_out() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+[dependencies]
+bar = { path = "bar" }
+"#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    // Dev-dependencies can't be optional.
+    let before = manifest(&p);
+    assert_that(p.cargo("add").arg("bar").arg("--path").arg("bar")
+                 .arg("--dev").arg("--optional"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] the edited manifest would be invalid, so `[..]Cargo.toml` was left unchanged"));
+    assert_eq!(manifest(&p), before);
+}
+
+#[test]
+fn remove() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+[dependencies]
+bar = { path = "bar" }  # a comment
+baz = { path = "baz" }
+
+[dev-dependencies.baz]
+path = "baz"
+
+[build-dependencies]
+baz = { path = "baz" }
+"#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("remove").arg("baz"),
+                execs().with_status(0).with_stderr("\
+[REMOVING] baz from dependencies
+"));
+    assert_that(p.cargo("remove").arg("baz").arg("--dev"),
+                execs().with_status(0).with_stderr("\
+[REMOVING] baz from dev-dependencies
+"));
+    assert_eq!(manifest(&p), r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+
+[dependencies]
+bar = { path = "bar" }  # a comment
+
+[build-dependencies]
+baz = { path = "baz" }
+"#);
+
+    assert_that(p.cargo("remove").arg("qux").arg("--build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] the dependency `qux` could not be found in build-dependencies
+"));
+}
+
+#[test]
+fn remove_with_non_ascii_multi_line_description() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+description = """
+Ünïcödé — ☃"""
+
+[dependencies]
+bar = { path = "bar" }
+"#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("remove").arg("bar"),
+                execs().with_status(0).with_stderr("\
+[REMOVING] bar from dependencies
+"));
+    assert_eq!(manifest(&p), r#"[package]
+name = "foo"
+version = "0.0.1"
+authors = []
+description = """
+Ünïcödé — ☃"""
+
+[dependencies]
+"#);
+}
@@ -2,10 +2,13 @@
 extern crate cargotest;
 extern crate hamcrest;
 
+use std::fs::File;
+use std::io::prelude::*;
+
 use cargotest::ChannelChanger;
 use cargotest::support::registry::{self, Package};
-use cargotest::support::{project, execs};
-use hamcrest::assert_that;
+use cargotest::support::{paths, project, execs};
+use hamcrest::{assert_that, existing_file};
 
 #[test]
 fn is_feature_gated() {
@@ -262,3 +265,51 @@ fn alt_registry_and_crates_io_deps() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..] secs"))
 
 }
+
+#[test]
+fn publish_to_alt_registry() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            cargo-features = ["alternative-registries"]
+
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies.bar]
+            version = "0.0.1"
+            registry = "alternative"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("bar", "0.0.1").alternative(true).publish();
+
+    // The token of the alternative registry is kept apart from crates.io's.
+    File::create(paths::home().join(".cargo/credentials")).unwrap()
+        .write_all(br#"
+            [registries.alternative]
+            token = "alt-token"
+        "#).unwrap();
+
+    assert_that(p.cargo("publish").masquerade_as_nightly_cargo()
+                 .arg("--no-verify")
+                 .arg("--registry").arg("alternative")
+                 .arg("-Zunstable-options"),
+                execs().with_status(0)
+                       .with_stderr_contains(format!("\
+[UPDATING] registry `{}`", registry::alt_registry()))
+                       .with_stderr_contains("\
+[UPLOADING] foo v0.0.1 ([..])"));
+
+    assert_that(&registry::dl_path().join("api/v1/crates/new"), existing_file());
+
+    // Without `--registry` it's crates.io, which may only have stable
+    // features relied on.
+    assert_that(p.cargo("publish").masquerade_as_nightly_cargo()
+                 .arg("--no-verify"),
+                execs().with_status(101));
+}
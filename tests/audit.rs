@@ -0,0 +1,218 @@
+#[macro_use]
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargotest::support::paths;
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs, path2url, Project};
+use hamcrest::assert_that;
+
+/// Writes an advisory database with `advisories` and returns its URL.
+fn database(advisories: &str) -> String {
+    let path = paths::root().join("advisories.json");
+    t!(t!(File::create(&path)).write_all(format!(r#"{{
+        "advisories": [{}]
+    }}"#, advisories).as_bytes()));
+    path2url(path).to_string()
+}
+
+const BAR_ADVISORY: &'static str = r#"{
+    "id": "TEST-2017-0001",
+    "package": "bar",
+    "title": "Out of bounds write in `bar::parse`",
+    "severity": "high",
+    "url": "https://example.com/TEST-2017-0001",
+    "patched": [">= 0.1.1"],
+    "unaffected": ["< 0.1.0"]
+}"#;
+
+/// `foo` depends on `baz`, which depends on `bar`.
+fn foo_with_bar(bar: &str) -> Project {
+    Package::new("bar", bar).publish();
+    Package::new("baz", "0.1.0").dep("bar", bar).publish();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    p
+}
+
+#[test]
+fn vulnerable_registry_dependency() {
+    let p = foo_with_bar("0.1.0");
+    let db = database(BAR_ADVISORY);
+
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(101).with_stdout("\
+TEST-2017-0001 (high): Out of bounds write in `bar::parse`
+  package:  bar v0.1.0
+  patched:  >= 0.1.1
+  url:      https://example.com/TEST-2017-0001
+  path:     foo v0.0.1 -> baz v0.1.0 -> bar v0.1.0
+
+")
+                       .with_stderr(&format!("\
+[FETCHING] advisory database `{}`
+[ERROR] found 1 known vulnerability in the locked dependencies
+", db)));
+}
+
+#[test]
+fn patched_and_unaffected_versions() {
+    let p = foo_with_bar("0.1.1");
+    let db = database(BAR_ADVISORY);
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(0).with_stdout("")
+                       .with_stderr_contains("\
+[FINISHED] no known vulnerabilities in 3 locked packages"));
+
+    Package::new("bar", "0.0.9").publish();
+    let p = project("qux")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "qux"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "=0.0.9"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(0));
+}
+
+#[test]
+fn database_from_config() {
+    let p = foo_with_bar("0.1.0");
+    let db = database(BAR_ADVISORY);
+    assert_that(p.cargo("audit").env("CARGO_AUDIT_DATABASE", &db),
+                execs().with_status(101)
+                       .with_stdout_contains("TEST-2017-0001 (high): [..]"));
+}
+
+#[test]
+fn no_database_configured() {
+    let p = foo_with_bar("0.1.0");
+    assert_that(p.cargo("audit"),
+                execs().with_status(101).with_stderr("\
+[ERROR] no vulnerability database is configured, set `audit.database` to its \
+URL or pass `--database`
+"));
+}
+
+#[test]
+fn lockfile_required() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("audit").arg("--database").arg(database("")),
+                execs().with_status(101).with_stderr("\
+[ERROR] a Cargo.lock must exist to audit dependencies, run \
+`cargo generate-lockfile` first
+"));
+}
+
+#[test]
+fn frozen_uses_last_fetched_database() {
+    let p = foo_with_bar("0.1.0");
+
+    assert_that(p.cargo("audit").arg("--frozen"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] the advisory database hasn't been fetched yet, and the network can't be \
+used as `--frozen` was passed"));
+
+    let db = database(BAR_ADVISORY);
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(101));
+    t!(fs::remove_file(paths::root().join("advisories.json")));
+
+    assert_that(p.cargo("audit").arg("--frozen"),
+                execs().with_status(101)
+                       .with_stdout_contains("TEST-2017-0001 (high): [..]")
+                       .with_stderr("\
+[ERROR] found 1 known vulnerability in the locked dependencies
+"));
+}
+
+#[test]
+fn vulnerable_git_revision() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+    let repo = t!(git2::Repository::open(&bar.root()));
+    let head = t!(repo.head()).target().unwrap().to_string();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = {{ git = '{}' }}
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    let db = database(&format!(r#"{{
+        "id": "TEST-2017-0002",
+        "package": "bar",
+        "title": "Unsound `Send` implementation",
+        "revisions": ["{}"]
+    }}"#, &head[..10]));
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(101).with_stdout("\
+TEST-2017-0002 (unknown severity): Unsound `Send` implementation
+  package:  bar v0.1.0
+  path:     foo v0.0.1 -> bar v0.1.0
+
+"));
+
+    // A version requirement doesn't match packages from git.
+    let db = database(BAR_ADVISORY);
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(0));
+
+    // A revision too short to tell commits apart is rejected.
+    let db = database(&format!(r#"{{
+        "id": "TEST-2017-0002",
+        "package": "bar",
+        "title": "Unsound `Send` implementation",
+        "revisions": ["{}"]
+    }}"#, &head[..3]));
+    assert_that(p.cargo("audit").arg("--database").arg(&db),
+                execs().with_status(101).with_stderr(&format!("\
+[ERROR] invalid revision `{}` in advisory TEST-2017-0002, expected at least \
+7 hexadecimal digits
+", &head[..3])));
+}
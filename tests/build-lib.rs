@@ -7,7 +7,7 @@ use hamcrest::{assert_that};
 fn verbose_output_for_lib(p: &Project) -> String {
     format!("\
 [COMPILING] {name} v{version} ({url})
-[RUNNING] `rustc --crate-name {name} src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name {name} src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir [..] \
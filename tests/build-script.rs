@@ -34,7 +34,7 @@ fn custom_build_script_failed() {
                 execs().with_status(101)
                        .with_stderr(&format!("\
 [COMPILING] foo v0.5.0 ({url})
-[RUNNING] `rustc --crate-name build_script_build build.rs --crate-type bin [..]`
+[RUNNING] `rustc --crate-name build_script_build build.rs --error-format json --crate-type bin [..]`
 [RUNNING] `[..][/]build-script-build`
 [ERROR] failed to run custom build command for `foo v0.5.0 ({url})`
 process didn't exit successfully: `[..][/]build-script-build` (exit code: 101)",
@@ -186,7 +186,7 @@ fn custom_build_script_rustc_flags() {
                 execs().with_status(101)
                        .with_stderr(&format!("\
 [COMPILING] bar v0.5.0 ({url})
-[RUNNING] `rustc --crate-name test {dir}{sep}src{sep}lib.rs --crate-type lib -C debuginfo=2 \
+[RUNNING] `rustc --crate-name test {dir}{sep}src{sep}lib.rs --error-format json --crate-type lib -C debuginfo=2 \
         -C metadata=[..] \
         -C extra-filename=-[..] \
         --out-dir {dir}{sep}target \
@@ -250,16 +250,13 @@ fn links_duplicates() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101)
+                execs().with_status(102)
                        .with_stderr("\
-[ERROR] Multiple packages link to native library `a`. A native library can be \
-linked only once.
-
-The root-package links to native library `a`.
-
-Package `a-sys v0.5.0 (file://[..])`
-    ... which is depended on by `foo v0.5.0 (file://[..])`
-also links to native library `a`.
+[ERROR] failed to select a version for `a-sys` (required by `foo`):
+the package `a-sys` links to the native library `a`, but it conflicts with a \
+previous package which links to `a` as well:
+  package `foo v0.5.0 (file://[..])`
+only one package in the dependency graph may link to a given native library
 "));
 }
 
@@ -304,18 +301,43 @@ fn links_duplicates_deep_dependency() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101)
+                execs().with_status(102)
                        .with_stderr("\
-[ERROR] Multiple packages link to native library `a`. A native library can be \
-linked only once.
+[ERROR] failed to select a version for `a-sys` (required by `a`):
+  ... which is depended on by `foo v0.5.0 (file://[..])`
+the package `a-sys` links to the native library `a`, but it conflicts with a \
+previous package which links to `a` as well:
+  package `foo v0.5.0 (file://[..])`
+only one package in the dependency graph may link to a given native library
+"));
+}
 
-The root-package links to native library `a`.
+#[test]
+fn links_picks_one_version_from_registry() {
+    Package::new("foo-sys", "0.1.0").links("foo").publish();
+    Package::new("foo-sys", "0.2.0").links("foo").publish();
+    Package::new("bar", "0.1.0").dep("foo-sys", "0.1").publish();
 
-Package `a-sys v0.5.0 (file://[..])`
-    ... which is depended on by `a v0.5.0 (file://[..])`
-    ... which is depended on by `foo v0.5.0 (file://[..])`
-also links to native library `a`.
-"));
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            foo-sys = "*"
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    // Both versions of `foo-sys` can't be used, even though they're
+    // incompatible, as they link to the same library.
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] foo-sys v0.1.0[..]")
+                       .with_stderr_does_not_contain("[..]foo-sys v0.2.0[..]"));
 }
 
 #[test]
@@ -832,19 +854,19 @@ fn build_cmd_with_a_build_cmd() {
 [COMPILING] a v0.5.0 (file://[..])
 [RUNNING] `rustc [..] a[/]build.rs [..] --extern b=[..]`
 [RUNNING] `[..][/]a-[..][/]build-script-build`
-[RUNNING] `rustc --crate-name a [..]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name a [..]lib.rs --error-format json --crate-type lib \
     --emit=dep-info,link -C debuginfo=2 \
     -C metadata=[..] \
     --out-dir [..]target[/]debug[/]deps \
     -L [..]target[/]debug[/]deps`
 [COMPILING] foo v0.5.0 (file://[..])
-[RUNNING] `rustc --crate-name build_script_build build.rs --crate-type bin \
+[RUNNING] `rustc --crate-name build_script_build build.rs --error-format json --crate-type bin \
     --emit=dep-info,link \
     -C debuginfo=2 -C metadata=[..] --out-dir [..] \
     -L [..]target[/]debug[/]deps \
     --extern a=[..]liba[..].rlib`
 [RUNNING] `[..][/]foo-[..][/]build-script-build`
-[RUNNING] `rustc --crate-name foo [..]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name foo [..]lib.rs --error-format json --crate-type lib \
     --emit=dep-info,link -C debuginfo=2 \
     -C metadata=[..] \
     --out-dir [..] \
@@ -1011,6 +1033,132 @@ Hello, World!
                 execs().with_status(0));
 }
 
+#[test]
+fn out_dir_is_emptied_before_rerunning() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::{self, File};
+            use std::path::Path;
+
+            fn main() {
+                println!("cargo:rerun-if-env-changed=GENERATE");
+                let out_dir = env::var("OUT_DIR").unwrap();
+                let left_over = fs::read_dir(&out_dir).unwrap().count();
+                assert_eq!(left_over, 0, "OUT_DIR isn't empty");
+                let name = env::var("GENERATE").unwrap();
+                File::create(Path::new(&out_dir).join(name)).unwrap();
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").env("GENERATE", "a"),
+                execs().with_status(0));
+    assert_that(p.cargo("build").env("GENERATE", "b").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `[..][/]build-script-build`"));
+}
+
+#[test]
+fn sandboxed_build_script_environment() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::path::Path;
+
+            fn main() {
+                assert!(env::var("SECRET").is_err(), "SECRET was passed on");
+                assert!(env::var("PATH").is_ok());
+                assert!(env::var("OUT_DIR").is_ok());
+                let tmp = env::var("TMPDIR").unwrap();
+                assert_eq!(Path::new(&tmp).file_name().unwrap(), "tmp");
+                assert!(Path::new(&tmp).is_dir());
+                assert_eq!(env::var("HOME").unwrap(), tmp);
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [build.sandbox]
+            enabled = true
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").env("SECRET", "hunter2"),
+                execs().with_status(0));
+}
+
+#[test]
+fn sandboxed_build_script_allowed_package() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                assert_eq!(std::env::var("SECRET").unwrap(), "hunter2");
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [build.sandbox]
+            enabled = true
+            allow = ["foo"]
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").env("SECRET", "hunter2"),
+                execs().with_status(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn sandboxed_build_script_wrapper() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                assert_eq!(std::env::var("WRAPPED").unwrap(), "1");
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [build.sandbox]
+            enabled = true
+            wrapper = ["env", "WRAPPED=1"]
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `env WRAPPED=1 [..][/]build-script-build`"));
+}
+
 #[test]
 fn release_with_build_script() {
     let p = project("foo")
@@ -1471,6 +1619,7 @@ fn cfg_test() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] `[..][/]foo-[..][EXE]`
 [RUNNING] `[..][/]test-[..][EXE]`
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo
 [RUNNING] [..] --cfg foo[..]", dir = p.url()))
                        .with_stdout_contains("test test_foo ... ok")
@@ -1574,6 +1723,7 @@ fn cfg_override_test() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] `[..][/]foo-[..][EXE]`
 [RUNNING] `[..][/]test-[..][EXE]`
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo
 [RUNNING] [..] --cfg foo[..]", dir = p.url()))
                        .with_stdout_contains("test test_foo ... ok")
@@ -1693,6 +1843,7 @@ fn env_test() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] `[..][/]foo-[..][EXE]`
 [RUNNING] `[..][/]test-[..][EXE]`
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo
 [RUNNING] [..] --crate-name foo[..]", dir = p.url()))
                        .with_stdout_contains_n("running 0 tests", 2)
@@ -2019,6 +2170,50 @@ fn fresh_builds_possible_with_multiple_metadata_overrides() {
 }
 
 
+#[test]
+fn rerun_if_changed_directory_is_scanned_recursively() {
+    let p = project("a")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rerun-if-changed=data");
+            }
+        "#)
+        .file("data/nested/file.txt", "1")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr("\
+[FRESH] a v0.5.0 ([..])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+"));
+
+    // Modifying a file doesn't touch the directories it's in.
+    sleep_ms(1000);
+    File::create(p.root().join("data/nested/file.txt")).unwrap()
+        .write_all(b"2").unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr("\
+[COMPILING] a v0.5.0 ([..])
+[RUNNING] `[..][/]build-script-build`
+[RUNNING] `rustc [..] src[/]lib.rs [..]`
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+"));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr("\
+[FRESH] a v0.5.0 ([..])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+"));
+}
+
 #[test]
 fn rebuild_only_on_explicit_paths() {
     let p = project("a")
@@ -2408,15 +2603,21 @@ fn warnings_printed_on_vv() {
                        .with_stderr("\
 [UPDATING] registry `[..]`
 [DOWNLOADING] bar v0.1.0 ([..])
+[DIRTY] bar v0.1.0 (build script): [..]
 [COMPILING] bar v0.1.0
 [RUNNING] `rustc [..]`
+[DIRTY] bar v0.1.0 (build script run): [..]
 [RUNNING] `[..]`
 warning: foo
 warning: bar
+[DIRTY] bar v0.1.0 (lib): [..]
 [RUNNING] `rustc [..]`
+[DIRTY] foo v0.5.0 ([..]) (lib): [..]
 [COMPILING] foo v0.5.0 ([..])
 [RUNNING] `rustc [..]`
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[FETCHED] [..] from `file://[..]`
+[FETCHED] [..] in total
 "));
 }
 
@@ -2441,19 +2642,37 @@ fn output_shows_on_vv() {
         "#)
         .build();
 
+    // Both end up on stderr, stdout is only for machine-readable output.
     assert_that(p.cargo("build").arg("-vv"),
                 execs().with_status(0)
-                       .with_stdout("\
-stdout
-")
-                       .with_stderr("\
-[COMPILING] foo v0.5.0 ([..])
-[RUNNING] `rustc [..]`
-[RUNNING] `[..]`
-stderr
-[RUNNING] `rustc [..]`
-[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
-"));
+                       .with_stdout("")
+                       .with_stderr_contains("[DIRTY] foo v0.5.0 ([..]) (build script run): [..]")
+                       .with_stderr_contains("stderr")
+                       .with_stderr_contains("stdout"));
+}
+
+#[test]
+fn vv_output_stays_off_json_stdout() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                println!("not json");
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("-vv").arg("--message-format").arg("json"),
+                execs().with_status(0)
+                       .with_stdout_does_not_contain("not json")
+                       .with_stderr_contains("not json"));
 }
 
 #[test]
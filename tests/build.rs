@@ -43,13 +43,9 @@ fn cargo_fail_with_no_stderr() {
 }
 
 /// Check that the `CARGO_INCREMENTAL` environment variable results in
-/// `rustc` getting `-Zincremental` passed to it.
+/// `rustc` getting `-C incremental` passed to it.
 #[test]
 fn cargo_compile_incremental() {
-    if !is_nightly() {
-        return
-    }
-
     let p = project("foo")
         .file("Cargo.toml", &basic_bin_manifest("foo"))
         .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
@@ -58,14 +54,77 @@ fn cargo_compile_incremental() {
     assert_that(
         p.cargo("build").arg("-v").env("CARGO_INCREMENTAL", "1"),
         execs().with_stderr_contains(
-            "[RUNNING] `rustc [..] -Zincremental=[..][/]target[/]debug[/]incremental`\n")
+            "[RUNNING] `rustc [..] -C incremental=[..][/]target[/]debug[/]incremental[/]foo-[..]`\n")
             .with_status(0));
 
     assert_that(
         p.cargo("test").arg("-v").env("CARGO_INCREMENTAL", "1"),
         execs().with_stderr_contains(
-            "[RUNNING] `rustc [..] -Zincremental=[..][/]target[/]debug[/]incremental`\n")
+            "[RUNNING] `rustc [..] -C incremental=[..][/]target[/]debug[/]incremental[/]foo-[..]`\n")
                .with_status(0));
+
+    // Release builds aren't incremental unless their profile says so.
+    assert_that(
+        p.cargo("build").arg("--release").arg("-v").env("CARGO_INCREMENTAL", "1"),
+        execs().with_stderr_does_not_contain("[..]incremental=[..]")
+               .with_status(0));
+}
+
+#[test]
+fn incremental_profile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev]
+            incremental = true
+
+            [profile.release]
+            incremental = true
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr_contains(
+                    "[RUNNING] `rustc [..] -C incremental=[..][/]debug[/]incremental[/]foo-[..]`"));
+    assert_that(&p.root().join("target/debug/incremental"), existing_dir());
+
+    assert_that(p.cargo("build").arg("--release").arg("-v"),
+                execs().with_status(0).with_stderr_contains(
+                    "[RUNNING] `rustc [..] -C incremental=[..][/]release[/]incremental[/]foo-[..]`"));
+
+    // `CARGO_INCREMENTAL=0` takes precedence.
+    p.root().join("target").rm_rf();
+    assert_that(p.cargo("build").arg("-v").env("CARGO_INCREMENTAL", "0"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[..]incremental=[..]"));
+}
+
+#[test]
+fn clean_removes_incremental_cache() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev]
+            incremental = true
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let dir = p.root().join("target/debug/incremental");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    assert_that(p.cargo("clean").arg("-p").arg("foo"), execs().with_status(0));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
 }
 
 #[test]
@@ -308,8 +367,47 @@ fn cargo_compile_without_manifest() {
 
     assert_that(p.cargo("build"),
                 execs().with_status(101)
-                       .with_stderr("\
+                       .with_stderr_contains("\
 [ERROR] could not find `Cargo.toml` in `[..]` or any parent directory
+
+searched:
+  [..]")
+                       .with_stderr_contains(&format!("  {}", p.root().display()))
+                       .with_stderr_contains("\
+To create a package here, run `cargo init`."));
+}
+
+#[test]
+fn cargo_compile_from_subdirectory() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        .file("tests/data/input.txt", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v").cwd(p.root().join("tests").join("data")),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo ..[/]..[/]src[/]foo.rs [..]`"));
+    assert_that(&p.bin("foo"), existing_file());
+
+    assert_that(p.cargo("run").cwd(p.root().join("src")),
+                execs().with_status(0).with_stdout("i am foo\n"));
+}
+
+#[test]
+fn cargo_compile_with_uncreatable_target_dir() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        .file("not-a-dir", "")
+        .build();
+
+    assert_that(p.cargo("build")
+                 .env("CARGO_TARGET_DIR", p.root().join("not-a-dir").join("target")),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] failed to create the target directory `[..]not-a-dir[/]target`: [..]
 "));
 }
 
@@ -708,7 +806,7 @@ fn cargo_compile_with_dep_name_mismatch() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr(&format!(
+                execs().with_status(102).with_stderr(&format!(
 r#"[ERROR] no matching package named `notquitebar` found (required by `foo`)
 location searched: {proj_dir}/bar
 version required: *
@@ -736,7 +834,10 @@ fn cargo_compile_with_filename() {
 
     assert_that(p.cargo("build").arg("--bin").arg("bin.rs"),
                 execs().with_status(101).with_stderr("\
-[ERROR] no bin target named `bin.rs`"));
+[ERROR] no bin target named `bin.rs`
+
+Available bin targets:
+    a"));
 
     assert_that(p.cargo("build").arg("--bin").arg("a.rs"),
                 execs().with_status(101).with_stderr("\
@@ -746,7 +847,10 @@ Did you mean `a`?"));
 
     assert_that(p.cargo("build").arg("--example").arg("example.rs"),
                 execs().with_status(101).with_stderr("\
-[ERROR] no example target named `example.rs`"));
+[ERROR] no example target named `example.rs`
+
+Available example targets:
+    a"));
 
     assert_that(p.cargo("build").arg("--example").arg("a.rs"),
                 execs().with_status(101).with_stderr("\
@@ -787,7 +891,7 @@ fn compile_path_dep_then_change_version() {
     "#).unwrap();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] no matching version `= 0.0.1` found for package `bar` (required by `foo`)
 location searched: [..]
 versions found: 0.0.2
@@ -854,14 +958,14 @@ fn cargo_default_env_metadata_env_var() {
     assert_that(p.cargo("build").arg("-v"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] bar v0.0.1 ({url}/bar)
-[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs --crate-type dylib \
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs --error-format json --crate-type dylib \
         --emit=dep-info,link \
         -C prefer-dynamic -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir [..] \
         -L dependency={dir}[/]target[/]debug[/]deps`
 [COMPILING] foo v0.0.1 ({url})
-[RUNNING] `rustc --crate-name foo src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name foo src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link -C debuginfo=2 \
         -C metadata=[..] \
         -C extra-filename=[..] \
@@ -881,14 +985,14 @@ suffix = env::consts::DLL_SUFFIX,
     assert_that(p.cargo("build").arg("-v").env("__CARGO_DEFAULT_LIB_METADATA", "stable"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] bar v0.0.1 ({url}/bar)
-[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs --crate-type dylib \
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs --error-format json --crate-type dylib \
         --emit=dep-info,link \
         -C prefer-dynamic -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir [..] \
         -L dependency={dir}[/]target[/]debug[/]deps`
 [COMPILING] foo v0.0.1 ({url})
-[RUNNING] `rustc --crate-name foo src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name foo src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link -C debuginfo=2 \
         -C metadata=[..] \
         -C extra-filename=[..] \
@@ -1143,7 +1247,7 @@ fn self_dependency() {
         .file("src/test.rs", "fn main() {}")
         .build();
     assert_that(p.cargo("build"),
-                execs().with_status(101)
+                execs().with_status(102)
                        .with_stderr("\
 [ERROR] cyclic package dependency: package `test v0.0.0 ([..])` depends on itself
 "));
@@ -1211,7 +1315,7 @@ fn lto_build() {
     assert_that(p.cargo("build").arg("-v").arg("--release"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]main.rs --crate-type bin \
+[RUNNING] `rustc --crate-name test src[/]main.rs --error-format json --crate-type bin \
         --emit=dep-info,link \
         -C opt-level=3 \
         -C lto \
@@ -1240,7 +1344,7 @@ fn verbose_build() {
     assert_that(p.cargo("build").arg("-v"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir [..] \
@@ -1267,7 +1371,7 @@ fn verbose_release_build() {
     assert_that(p.cargo("build").arg("-v").arg("--release"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C opt-level=3 \
         -C metadata=[..] \
@@ -1310,7 +1414,7 @@ fn verbose_release_build_deps() {
     assert_that(p.cargo("build").arg("-v").arg("--release"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] foo v0.0.0 ({url}/foo)
-[RUNNING] `rustc --crate-name foo foo[/]src[/]lib.rs \
+[RUNNING] `rustc --crate-name foo foo[/]src[/]lib.rs --error-format json \
         --crate-type dylib --crate-type rlib \
         --emit=dep-info,link \
         -C prefer-dynamic \
@@ -1319,7 +1423,7 @@ fn verbose_release_build_deps() {
         --out-dir [..] \
         -L dependency={dir}[/]target[/]release[/]deps`
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C opt-level=3 \
         -C metadata=[..] \
@@ -2264,7 +2368,7 @@ fn cyclic_deps_rejected() {
         .build();
 
     assert_that(p.cargo("build").arg("-v"),
-                execs().with_status(101)
+                execs().with_status(102)
                        .with_stderr("\
 [ERROR] cyclic package dependency: package `a v0.0.1 ([..])` depends on itself
 "));
@@ -2682,6 +2786,69 @@ fn panic_abort_compiles_with_panic_abort() {
                        .with_stderr_contains("[..] -C panic=abort [..]"));
 }
 
+#[test]
+fn panic_abort_exempts_tests() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.dev]
+            panic = 'abort'
+        "#)
+        .file("src/lib.rs", "extern crate bar; #[test] fn it_works() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    // The tests and what they link against unwind.
+    assert_that(p.cargo("test").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[..] -C panic=abort [..]"));
+    assert_that(p.cargo("check").arg("--profile").arg("test").arg("-v"),
+                execs().with_stderr_does_not_contain("[RUNNING] `rustc --crate-name foo \
+                                                      [..]--test[..]panic=abort[..]`"));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar [..] -C panic=abort [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo [..] -C panic=abort [..]`"));
+}
+
+#[test]
+fn bad_panic_strategy() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev]
+            panic = 'exit'
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `panic` in `[profile.dev]` must be `\"unwind\"` or `\"abort\"`, but found `\"exit\"`
+"));
+}
+
 #[test]
 fn explicit_color_config_is_propagated_to_rustc() {
     let p = project("foo")
@@ -2768,6 +2935,7 @@ fn compiler_json_error_format() {
             "src_path":"[..]lib.rs"
         },
         "filenames":["[..].rlib"],
+        "executable": null,
         "fresh": false
     }
 
@@ -2801,6 +2969,7 @@ fn compiler_json_error_format() {
         },
         "features": [],
         "filenames": ["[..]"],
+        "executable": "[..]foo[..]",
         "fresh": false
     }
 "#));
@@ -2828,6 +2997,7 @@ fn compiler_json_error_format() {
             "src_path":"[..]lib.rs"
         },
         "filenames":["[..].rlib"],
+        "executable": null,
         "fresh": true
     }
 
@@ -2849,6 +3019,7 @@ fn compiler_json_error_format() {
         },
         "features": [],
         "filenames": ["[..]"],
+        "executable": "[..]foo[..]",
         "fresh": true
     }
 "#));
@@ -2908,6 +3079,7 @@ fn message_format_json_forward_stderr() {
         },
         "features":[],
         "filenames":["[..]"],
+        "executable": "[..]foo[..]",
         "fresh": false
     }
 "#));
@@ -3416,6 +3588,53 @@ fn rustc_wrapper() {
                 .with_status(0));
 }
 
+#[test]
+fn rustc_wrapper_from_config() {
+    if cfg!(windows) { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        .file(".cargo/config", r#"
+            [build]
+            rustc-wrapper = "/usr/bin/env"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr_contains(
+                    "[RUNNING] `/usr/bin/env rustc --crate-name foo [..]"));
+
+    // An empty `RUSTC_WRAPPER` turns the configured one off.
+    assert_that(p.cargo("build").arg("-v").env("RUSTC_WRAPPER", ""),
+                execs().with_status(0).with_stderr_contains(
+                    "[RUNNING] `rustc --crate-name foo [..]"));
+}
+
+#[test]
+fn rustc_wrapper_change_rebuilds() {
+    if cfg!(windows) { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--dry-run").env("RUSTC_WRAPPER", "/usr/bin/env"),
+                execs().with_status(0).with_stderr_contains("\
+[DIRTY] foo v0.0.1 ([..]) (bin \"foo\"): the rustc wrapper has changed: \
+previously none now `/usr/bin/env`"));
+    assert_that(p.cargo("build").arg("-v").env("RUSTC_WRAPPER", "/usr/bin/env"),
+                execs().with_status(0).with_stderr_contains(
+                    "[RUNNING] `/usr/bin/env rustc --crate-name foo [..]"));
+    assert_that(p.cargo("build").arg("-v").env("RUSTC_WRAPPER", "/usr/bin/env"),
+                execs().with_status(0).with_stderr_contains("[FRESH] foo [..]"));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr_contains(
+                    "[RUNNING] `rustc --crate-name foo [..]"));
+}
+
 #[test]
 fn cdylib_not_lifted() {
     let p = project("foo")
@@ -3521,6 +3740,7 @@ fn wasm32_final_outputs() {
         opts.features,
         opts.all_features,
         opts.no_default_features,
+        true,
         &specs,
     ).expect("Can't create resolve");
 
@@ -3909,10 +4129,10 @@ fn build_filter_infer_profile() {
     assert_that(p.cargo("build").arg("-v"),
         execs().with_status(0)
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]lib.rs --crate-type lib \
+            [RUNNING] `rustc --crate-name foo src[/]lib.rs --error-format json --crate-type lib \
             --emit=dep-info,link[..]")
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]main.rs --crate-type bin \
+            [RUNNING] `rustc --crate-name foo src[/]main.rs --error-format json --crate-type bin \
             --emit=dep-info,link[..]")
         );
 
@@ -3920,12 +4140,12 @@ fn build_filter_infer_profile() {
     assert_that(p.cargo("build").arg("-v").arg("--test=t1"),
         execs().with_status(0)
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]lib.rs --crate-type lib \
+            [RUNNING] `rustc --crate-name foo src[/]lib.rs --error-format json --crate-type lib \
             --emit=dep-info,link[..]")
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name t1 tests[/]t1.rs --emit=dep-info,link[..]")
+            [RUNNING] `rustc --crate-name t1 tests[/]t1.rs --error-format json --emit=dep-info,link[..]")
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]main.rs --crate-type bin \
+            [RUNNING] `rustc --crate-name foo src[/]main.rs --error-format json --crate-type bin \
             --emit=dep-info,link[..]")
         );
 
@@ -3933,13 +4153,13 @@ fn build_filter_infer_profile() {
     assert_that(p.cargo("build").arg("-v").arg("--bench=b1"),
         execs().with_status(0)
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]lib.rs --crate-type lib \
+            [RUNNING] `rustc --crate-name foo src[/]lib.rs --error-format json --crate-type lib \
             --emit=dep-info,link[..]")
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name b1 benches[/]b1.rs --emit=dep-info,link \
+            [RUNNING] `rustc --crate-name b1 benches[/]b1.rs --error-format json --emit=dep-info,link \
             -C opt-level=3[..]")
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]main.rs --crate-type bin \
+            [RUNNING] `rustc --crate-name foo src[/]main.rs --error-format json --crate-type bin \
             --emit=dep-info,link[..]")
         );
 }
@@ -3959,15 +4179,614 @@ fn all_targets_no_lib() {
         execs().with_status(0)
         // bin
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]main.rs --crate-type bin \
+            [RUNNING] `rustc --crate-name foo src[/]main.rs --error-format json --crate-type bin \
             --emit=dep-info,link[..]")
         // bench
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]main.rs --emit=dep-info,link \
+            [RUNNING] `rustc --crate-name foo src[/]main.rs --error-format json --emit=dep-info,link \
             -C opt-level=3 --test [..]")
         // unit test
         .with_stderr_contains("\
-            [RUNNING] `rustc --crate-name foo src[/]main.rs --emit=dep-info,link \
+            [RUNNING] `rustc --crate-name foo src[/]main.rs --error-format json --emit=dep-info,link \
             -C debuginfo=2 --test [..]")
         );
 }
+
+#[test]
+fn build_timings() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--timings"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+build took [..]s, with an average of [..] of [..] jobs running")
+                       .with_stderr_contains("\
+critical path (marked with `*`) took [..]s:")
+                       .with_stderr_contains("    bar v0.0.1 ([..]) (lib)")
+                       .with_stderr_contains("    foo v0.0.1 ([..]) (bin \"foo\")")
+                       .with_stderr_contains("\
+[..]Timings report written to `[..]cargo-timing.html`"));
+
+    let dir = p.root().join("target/cargo-timings");
+    assert_that(&dir.join("cargo-timing.html"), existing_file());
+    let mut json = String::new();
+    File::open(dir.join("cargo-timing.json")).unwrap()
+        .read_to_string(&mut json).unwrap();
+    assert!(json.contains("\"critical_path\":"), "{}", json);
+    assert!(json.contains("bar v0.0.1"), "{}", json);
+}
+
+#[test]
+fn cargo_log_short_names() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").env("CARGO_LOG", "process=debug"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+DEBUG:cargo::util::process_builder: executing `rustc --crate-name foo [..]`")
+                       .with_stderr_does_not_contain("[..]cargo::core::resolver[..]"));
+}
+
+#[test]
+fn executor_can_stop_build_after_resolution() {
+    use std::sync::Arc;
+    use cargo::core::{PackageSet, Resolve, Shell, Workspace};
+    use cargo::ops::{self, CompileMode, CompileOptions, Executor, PackagesToBuild};
+    use cargo::util::{CargoResult, Config};
+
+    struct Auditor;
+
+    impl Executor for Auditor {
+        fn resolved(&self,
+                    resolve: &Resolve,
+                    _packages: &PackageSet,
+                    to_build: &PackagesToBuild)
+                    -> CargoResult<()> {
+            assert_eq!(to_build.len(), 1);
+            assert_eq!(to_build[0].0.name(), "foo");
+            if resolve.iter().any(|id| id.name() == "bar") {
+                return Err("`bar` is not allowed".into())
+            }
+            Ok(())
+        }
+    }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    let config = Config::new(Shell::new(), p.root(), p.root());
+    let ws = Workspace::new(&p.root().join("Cargo.toml"), &config).unwrap();
+    let opts = CompileOptions::default(&config, CompileMode::Build);
+    let err = ops::compile_with_exec(&ws, &opts, Arc::new(Auditor)).err().unwrap();
+    let causes = err.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(causes, ["the build was stopped after resolving dependencies",
+                        "`bar` is not allowed"]);
+    assert_that(&p.root().join("target/debug/foo"), is_not(existing_file()));
+}
+
+#[test]
+fn build_reproducible() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::File;
+            use std::io::Write;
+            use std::path::Path;
+
+            fn main() {
+                let epoch = env::var("SOURCE_DATE_EPOCH").unwrap();
+                let out = Path::new(&env::var("OUT_DIR").unwrap()).join("epoch");
+                File::create(out).unwrap().write_all(epoch.as_bytes()).unwrap();
+            }
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() {
+                println!("{} {}", include_str!(concat!(env!("OUT_DIR"), "/epoch")), file!());
+                bar::bar();
+            }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", r#"pub fn bar() { println!("{}", file!()); }"#)
+        .build();
+
+    assert_that(p.cargo("build").arg("--reproducible").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar [..]--remap-path-prefix=[..]=bar-0.0.1 [..]`")
+                       .with_stderr_contains("\
+[VERIFYING] the build is reproducible by building it again in \
+`[..]reproducible-check`")
+                       .with_stderr_contains("\
+[..]Verified [..] artifacts are identical in both builds"));
+    assert_that(&p.root().join("target/reproducible-check"), is_not(existing_dir()));
+    assert_that(process(&p.bin("foo")),
+                execs().with_status(0).with_stdout("\
+0 foo-0.0.1[/]src[/]main.rs
+bar-0.0.1[/]src[/]lib.rs
+"));
+}
+
+#[test]
+fn build_reproducible_detects_divergence() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::File;
+            use std::io::Write;
+            use std::path::Path;
+            use std::time::{SystemTime, UNIX_EPOCH};
+
+            fn main() {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                let out = Path::new(&env::var("OUT_DIR").unwrap()).join("now");
+                File::create(out).unwrap()
+                    .write_all(format!("{:?}", now).as_bytes()).unwrap();
+            }
+        "#)
+        .file("src/main.rs", r#"
+            fn main() {
+                println!("{}", include_str!(concat!(env!("OUT_DIR"), "/now")));
+            }
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("--reproducible"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] the build isn't reproducible, `[..]foo[..]` differs from \
+`[..]reproducible-check[..]` starting at byte [..]"));
+    assert_that(&p.root().join("target/reproducible-check"), existing_dir());
+}
+
+#[test]
+fn pipelining() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file("src/main.rs", "extern crate foo; fn main() { foo::foo() }")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("-v").env("CARGO_BUILD_PIPELINING", "true"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]\
+--json=artifacts [..]--emit=dep-info,metadata,link [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]lib.rs [..]\
+--extern bar=[..]libbar-[..].rmeta`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]main.rs [..]\
+--extern foo=[..]libfoo-[..].rlib[..]`"));
+    assert_that(p.process(&p.bin("foo")), execs().with_status(0));
+
+    // Diagnostics are still rendered as usual.
+    File::create(p.root().join("bar/src/lib.rs")).unwrap().write_all(br#"
+        pub fn bar() { let unused = 1; }
+    "#).unwrap();
+    assert_that(p.cargo("build").env("CARGO_BUILD_PIPELINING", "true"),
+                execs().with_status(0)
+                       .with_stderr_contains("[..]unused variable: `unused`[..]"));
+}
+
+#[test]
+fn every_crate_type_reported() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            authors = []
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["rlib", "cdylib"]
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    let cdylib = format!("{}foo{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX);
+    assert_that(p.cargo("build").arg("--message-format").arg("json"),
+                execs().with_status(0).with_stdout_contains(&format!("\
+{{\"executable\":null,\"features\":[],\"filenames\":[\"[..]libfoo.rlib\",\"[..]{}\"[..]],\
+[..]\"reason\":\"compiler-artifact\"[..]", cdylib)));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
+    assert_that(&p.root().join("target/debug").join(&cdylib), existing_file());
+}
+
+#[test]
+fn compiler_diagnostics_shown_once() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            macro_rules! unused {
+                () => (fn dead() {})
+            }
+            unused!();
+        "#)
+        .build();
+
+    // The warning is the same for the library and its tests.
+    let output = p.cargo("test").arg("--no-run").arg("--color=never")
+                  .exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let warnings = stderr.lines().filter(|l| l.starts_with("warning: ") && l.contains("dead"));
+    assert_eq!(warnings.count(), 1, "{}", stderr);
+}
+
+#[test]
+fn warnings_summary_counts_duplicates() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn dead() {}")
+        .build();
+
+    // The library and its tests both warn about `dead`.
+    assert_that(p.cargo("build").arg("--all-targets"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] 2 warnings emitted (1 duplicate suppressed)
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn all_warnings_shows_duplicates() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn dead() {}")
+        .build();
+
+    let output = p.cargo("build").arg("--all-targets").arg("--all-warnings")
+                  .arg("--color=never")
+                  .exec_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let warnings = stderr.lines().filter(|l| l.starts_with("warning: ") && l.contains("dead"));
+    assert_eq!(warnings.count(), 2, "{}", stderr);
+    assert!(stderr.contains("warning: 2 warnings emitted\n"), "{}", stderr);
+}
+
+#[test]
+fn no_warnings_summary_without_warnings() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn used() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[..]warnings emitted[..]"));
+}
+
+#[test]
+fn deny_warnings() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn dead() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--deny-warnings"),
+                execs().with_status(101)
+                       .with_stderr_contains("[..]function is never used: `dead`[..]")
+                       .with_stderr_contains("\
+[ERROR] Could not compile `foo` because warnings are denied (1 warning).
+"));
+
+    // Nothing is fresh, so the warning fails the build again.
+    assert_that(p.cargo("build").arg("--deny-warnings"),
+                execs().with_status(101)
+                       .with_stderr_contains("[COMPILING] foo v0.0.1 ([..])")
+                       .with_stderr_contains("[..]function is never used: `dead`[..]"));
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+}
+
+#[test]
+fn deny_warnings_from_config() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn dead() {}")
+        .file(".cargo/config", r#"
+            [build]
+            warnings = "deny"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] Could not compile `foo` because warnings are denied (1 warning).
+"));
+}
+
+#[test]
+fn deny_warnings_ignores_dependencies() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "extern crate bar;")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "fn dead() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--deny-warnings"),
+                execs().with_status(0)
+                       .with_stderr_contains("[..]function is never used: `dead`[..]"));
+}
+
+#[test]
+fn bad_warnings_config() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [build]
+            warnings = "forbid"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] build.warnings must be `warn` or `deny`, but found `forbid` in [..]config
+"));
+}
+
+#[test]
+fn size_report() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--size-report"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("artifact sizes:"));
+
+    assert_that(p.cargo("build").arg("--release").arg("--size-report"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+
+artifact sizes:
+  [..]  foo[..] (new)
+"));
+    assert_that(&p.root().join("target/release/.cargo-sizes.json"), existing_file());
+
+    assert_that(p.cargo("build").arg("--release").arg("--size-report"),
+                execs().with_status(0)
+                       .with_stderr_contains("  [..]  foo[..] (unchanged)"));
+}
+
+#[test]
+fn size_report_from_config() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [build]
+            size-report = true
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("--release"),
+                execs().with_status(0)
+                       .with_stderr_contains("  [..]  libfoo.rlib (new)"));
+}
+
+#[test]
+fn compiler_diagnostics_are_rendered_in_json() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "fn dead() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--message-format").arg("json"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]\"reason\":\"compiler-message\"[..]")
+                       .with_stdout_contains("[..]\"rendered\":\"warning: [..]dead[..]"));
+}
+
+#[test]
+fn unused_dependencies() {
+    if !is_nightly() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            used = { path = "used" }
+            unused = { path = "unused" }
+
+            [dev-dependencies]
+            unused-dev = { path = "unused-dev" }
+        "#)
+        .file("src/lib.rs", "extern crate used;")
+        .file("tests/t.rs", "extern crate foo; #[test] fn t() {}")
+        .file("used/Cargo.toml", r#"
+            [package]
+            name = "used"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("used/src/lib.rs", "")
+        .file("unused/Cargo.toml", r#"
+            [package]
+            name = "unused"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("unused/src/lib.rs", "")
+        .file("unused-dev/Cargo.toml", r#"
+            [package]
+            name = "unused-dev"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("unused-dev/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("-Zunused-dependencies")
+                 .masquerade_as_nightly_cargo(),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] unused dependency `unused` in `[dependencies]` of `foo`")
+                       .with_stderr_does_not_contain("dependency `used`")
+                       .with_stderr_does_not_contain("unused-dev"));
+
+    // Only the targets compiled again are checked.
+    assert_that(p.cargo("build").arg("-Zunused-dependencies")
+                 .masquerade_as_nightly_cargo(),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("unused dependency"));
+
+    assert_that(p.cargo("test").arg("--no-run").arg("-Zunused-dependencies")
+                 .masquerade_as_nightly_cargo(),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] unused dependency `unused-dev` in `[dev-dependencies]` of `foo`"));
+}
@@ -0,0 +1,318 @@
+extern crate cargotest;
+extern crate hamcrest;
+extern crate serde_json;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::paths;
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::{assert_that, existing_file, is_not};
+
+#[test]
+fn stats() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [build]
+            fingerprint-stats = true
+        "#)
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("cache").arg("stats").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout("")
+                       .with_stderr_contains("registry crates:        0 [..]")
+                       .with_stderr_contains("\
+fingerprint cache:  nothing recorded yet, builds keep count with \
+`build.fingerprint-stats = true`"));
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("cache").arg("stats").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("git databases:          0 [..]")
+                       .with_stderr_contains("registry indices:       1 [..]")
+                       .with_stderr_contains("registry crates:        1 [..]")
+                       .with_stderr_contains("registry sources:       1 [..]")
+                       .with_stderr_contains("\
+fingerprint cache:  0 fresh, 2 rebuilt (0.0% hit rate)"));
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("cache").arg("stats").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+fingerprint cache:  2 fresh, 2 rebuilt (50.0% hit rate)"));
+
+    // The hit rate is only shown when asked for.
+    assert_that(p.cargo("cache").arg("stats"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("fingerprint cache:[..]"));
+}
+
+#[test]
+fn fingerprint_stats_off_by_default() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.root().join("target/.fingerprint-stats.json"),
+                is_not(existing_file()));
+}
+
+#[test]
+fn fetched_summary() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("build").arg("-vv"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FETCHED] [..] from `file://[..]`")
+                       .with_stderr_contains("[FETCHED] [..] in total"));
+
+    // Nothing is fetched the second time around.
+    assert_that(p.cargo("build").arg("-vv"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[FETCHED] [..]"));
+}
+
+#[test]
+fn gc() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("cache").arg("gc"),
+                execs().with_status(0)
+                       .with_stderr("\
+[..]Removed 1 registry sources
+[..]Freed [..]
+"));
+    assert_that(p.cargo("cache").arg("stats"),
+                execs().with_status(0)
+                       .with_stderr_contains("registry crates:        1 [..]")
+                       .with_stderr_contains("registry sources:       0 [..]"));
+
+    // The sources are unpacked again from the downloaded crate.
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--frozen"), execs().with_status(0));
+}
+
+#[test]
+fn manifest() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert!(paths::home().join(".cargo/CACHE-README").is_file());
+    let path = paths::home().join(".cargo/cache-manifest.json");
+    let mut contents = String::new();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    let mut manifest: serde_json::Value = serde_json::from_str(&contents).unwrap();
+    assert_eq!(manifest["layout-version"], 1);
+    assert!(manifest["cargo-version"].as_str().unwrap().starts_with("cargo "));
+    let mut kinds = Vec::new();
+    for (key, entry) in manifest["entries"].as_object().unwrap() {
+        assert!(entry["url"].as_str().unwrap().starts_with("file://"), "{}", key);
+        kinds.push(entry["kind"].as_str().unwrap().to_string());
+    }
+    kinds.sort();
+    assert_eq!(kinds, ["registry-crate", "registry-index", "registry-src"]);
+
+    assert_that(p.cargo("cache").arg("stats"),
+                execs().with_status(0)
+                       .with_stderr_contains("unused 30+ days:        0 [..]")
+                       .with_stderr_contains("last written by:   cargo [..]"));
+
+    // Nothing is removed which was used recently...
+    assert_that(p.cargo("cache").arg("gc").arg("--unused-for").arg("1"),
+                execs().with_status(0)
+                       .with_stderr("\
+[..]Freed 0 B
+"));
+
+    // ...but it is once it hasn't been used for a while.
+    for (_, entry) in manifest["entries"].as_object_mut().unwrap() {
+        entry["last-used"] = serde_json::Value::from(0);
+    }
+    File::create(&path).unwrap()
+        .write_all(manifest.to_string().as_bytes()).unwrap();
+    assert_that(p.cargo("cache").arg("stats"),
+                execs().with_status(0)
+                       .with_stderr_contains("unused 30+ days:        3 [..]"));
+    assert_that(p.cargo("cache").arg("gc").arg("--unused-for").arg("1"),
+                execs().with_status(0)
+                       .with_stderr("\
+[..]Removed 1 registry sources
+[..]Freed [..]
+"));
+    contents.clear();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(!contents.contains("registry-src"));
+}
+
+#[test]
+fn clean() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            baz = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    assert_that(p.cargo("cache").arg("clean"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] `cargo cache clean` needs `--max-size` or `--older-than`, or both"));
+    assert_that(p.cargo("cache").arg("clean").arg("--max-size").arg("lots"),
+                execs().with_status(101)
+                       .with_stderr_contains("[ERROR] invalid size `lots`, [..]"));
+    assert_that(p.cargo("cache").arg("clean").arg("--older-than").arg("30"),
+                execs().with_status(101)
+                       .with_stderr_contains("[ERROR] invalid age `30`, [..]"));
+    assert_that(p.cargo("cache").arg("clean").arg("--older-than")
+                 .arg("99999999999999999w"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[ERROR] invalid age `99999999999999999w`, [..]"));
+
+    // Pretend `bar` hasn't been used for a long time.
+    let path = paths::home().join(".cargo/cache-manifest.json");
+    let mut contents = String::new();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    let mut manifest: serde_json::Value = serde_json::from_str(&contents).unwrap();
+    for (key, entry) in manifest["entries"].as_object_mut().unwrap() {
+        if key.contains("bar-0.1.0") {
+            entry["last-used"] = serde_json::Value::from(0);
+        }
+    }
+    File::create(&path).unwrap()
+        .write_all(manifest.to_string().as_bytes()).unwrap();
+
+    assert_that(p.cargo("cache").arg("clean").arg("--older-than").arg("30d"),
+                execs().with_status(0)
+                       .with_stderr("\
+[..]Removed 2 entries
+[..]Freed [..]
+"));
+    contents.clear();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(!contents.contains("bar-0.1.0"));
+    assert!(contents.contains("baz-0.1.0"));
+
+    // A build downloads `bar` again.
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[DOWNLOADING] bar v0.1.0 [..]"));
+
+    // Everything goes if nothing may be kept.
+    assert_that(p.cargo("cache").arg("clean").arg("--max-size").arg("0"),
+                execs().with_status(0)
+                       .with_stderr("\
+[..]Removed 4 entries
+[..]Freed [..]
+"));
+    contents.clear();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    assert!(!contents.contains("registry-crate"));
+    assert!(!contents.contains("registry-src"));
+    assert!(contents.contains("registry-index"));
+}
+
+#[test]
+fn out_of_space() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("build").env("__CARGO_TEST_AVAILABLE_SPACE", "0"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[..]only 0.0 MiB of disk space is left for `[..]bar-0.1.0`, try freeing \
+some up with `cargo clean` or `cargo cache gc`"));
+
+    // Once the sources are in place the build itself checks as well.
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+    assert_that(p.cargo("build").env("__CARGO_TEST_AVAILABLE_SPACE", "0"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[..]only 0.0 MiB of disk space is left for `[..]target`, try freeing \
+some up with `cargo clean` or `cargo cache gc`"));
+}
@@ -229,7 +229,8 @@ fn z_flags_rejected() {
                  .arg("-Zprint-im-a-teapot"),
                 execs().with_status(101)
                        .with_stderr("\
-error: the `-Z` flag is only accepted on the nightly channel of Cargo
+error: the `-Z print-im-a-teapot` flag is only accepted on the nightly channel \
+of Cargo, but this is the `stable` channel
 "));
 
     assert_that(p.cargo("build")
@@ -211,8 +211,135 @@ fn cargo_help() {
                 execs().with_status(0));
 }
 
+#[test]
+fn cargo_help_exit_status() {
+    assert_that(cargo_process().arg("-h"),
+                execs().with_status(0)
+                       .with_stdout_contains("Exit status:")
+                       .with_stdout_contains("    102         Dependencies could not be resolved")
+                       .with_stdout_contains("    103         A network operation failed"));
+}
+
 #[test]
 fn explain() {
     assert_that(cargo_process().arg("--explain").arg("E0001"),
                 execs().with_status(0));
 }
+
+#[test]
+fn cargo_help_extended() {
+    assert_that(cargo_process().arg("help").arg("build"),
+                execs().with_status(0)
+                       .with_stdout_contains("Compile a local package and all of its dependencies")
+                       .with_stdout_contains("Examples:")
+                       .with_stdout_contains("    cargo build --release")
+                       .with_stdout_contains("        Build the current package with optimizations.")
+                       .with_stdout_contains("Configuration:")
+                       .with_stdout_contains("    build.jobs [..]Number of parallel jobs, like `--jobs`"));
+
+    // Commands without extended help just print their usage.
+    assert_that(cargo_process().arg("help").arg("pkgid"),
+                execs().with_status(0)
+                       .with_stdout_does_not_contain("Examples:")
+                       .with_stderr_does_not_contain("Examples:"));
+}
+
+#[test]
+fn global_flags_before_command() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("-v").arg("--color=never").arg("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `rustc [..]"));
+    assert_that(p.cargo("-q").arg("--manifest-path").arg(p.root().join("Cargo.toml"))
+                 .arg("build"),
+                execs().with_status(0).with_stderr(""));
+    assert_that(p.cargo("--color").arg("sometimes").arg("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] argument for --color must be auto, always, or never, but found `sometimes`
+"));
+    assert_that(p.cargo("--manifest-path").arg("Cargo.toml").arg("new").arg("bar"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] `--manifest-path` isn't accepted by `cargo new`
+"));
+}
+
+#[test]
+fn config_overrides() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [term]
+            verbose = false
+        "#)
+        .build();
+
+    assert_that(p.cargo("build").arg("--config").arg("term.verbose=true"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `rustc [..]"));
+    assert_that(p.cargo("--config=alias.b2=[\"build\", \"-v\"]").arg("b2"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] foo [..]"));
+    assert_that(p.cargo("build").arg("--config").arg("term.verbose=true")
+                 .env("CARGO_TERM_VERBOSE", "false"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] foo [..]"));
+}
+
+#[test]
+fn bad_config_overrides() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--config").arg("term.verbose"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] invalid `--config` argument `term.verbose`, expected `KEY=VALUE`
+"));
+    assert_that(p.cargo("build").arg("--config").arg("term..verbose=true"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] invalid `--config` argument `term..verbose=true`, expected `KEY=VALUE` where \
+`KEY` is like `term.verbose`
+"));
+    assert_that(p.cargo("build").arg("--config").arg("build.target-dir=some dir"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] invalid `--config` argument `build.target-dir=some dir`, expected the value \
+to be TOML, like `true`, `1` or `\"text\"`
+"));
+}
+
+#[test]
+fn config_overrides_take_precedence() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [alias]
+            b2 = "build"
+        "#)
+        .build();
+
+    // The list replaces the string from the configuration file.
+    assert_that(p.cargo("--config").arg("alias.b2=[\"build\", \"-v\"]").arg("b2"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `rustc [..]"));
+    // The last value given wins.
+    assert_that(p.cargo("build").arg("--config").arg("term.verbose=false")
+                 .arg("--config").arg("term.verbose=true"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] foo [..]"));
+    assert_that(p.cargo("build").arg("--config").arg("term.verbose=true")
+                 .arg("--config").arg("term.verbose=false"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[FRESH] foo [..]"));
+}
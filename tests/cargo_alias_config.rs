@@ -121,3 +121,46 @@ fn cant_shadow_builtin() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 "));
 }
+
+#[test]
+fn alias_of_alias() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", r#"
+            fn main() {
+         }"#)
+        .file(".cargo/config",r#"
+            [alias]
+            b-cargo-test = "b-release -v"
+            b-release = "b --release"
+         "#)
+        .build();
+
+    assert_that(p.cargo("b-cargo-test"),
+                execs().with_status(0).
+                with_stderr_contains("[COMPILING] foo v0.5.0 [..]").
+                with_stderr_contains("[RUNNING] `rustc --crate-name foo [..]-C opt-level=3[..]")
+                );
+}
+
+#[test]
+fn recursive_alias() {
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", r#"
+            fn main() {
+         }"#)
+        .file(".cargo/config",r#"
+            [alias]
+            b-cargo-test = "b-cargo-other --release"
+            b-cargo-other = "b-cargo-test"
+         "#)
+        .build();
+
+    assert_that(p.cargo("b-cargo-test"),
+                execs().with_status(101).
+                with_stderr("\
+[ERROR] alias `b-cargo-test` expands to itself: \
+b-cargo-test -> b-cargo-other -> b-cargo-test
+"));
+}
@@ -56,6 +56,9 @@ fn _process(t: &OsStr) -> cargo::util::ProcessBuilder {
      .env_remove("RUSTC_WRAPPER")
      .env_remove("RUSTFLAGS")
      .env_remove("CARGO_INCREMENTAL")
+     .env_remove("CARGO_LOG")
+     // Don't touch the credential store of whoever runs the tests.
+     .env("CARGO_REGISTRY_CREDENTIAL_PROVIDER", "file")
      .env_remove("XDG_CONFIG_HOME")      // see #2345
      .env("GIT_CONFIG_NOSYSTEM", "1")    // keep trying to sandbox ourselves
      .env_remove("EMAIL")
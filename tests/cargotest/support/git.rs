@@ -6,7 +6,7 @@ use url::Url;
 use git2;
 
 use cargo::util::ProcessError;
-use support::{ProjectBuilder, Project, project, path2url};
+use support::{ProjectBuilder, Project, project, path2url, paths};
 
 #[must_use]
 pub struct RepoBuilder {
@@ -88,6 +88,52 @@ pub fn new<F>(name: &str, callback: F) -> Result<Project, ProcessError>
     Ok(git_project)
 }
 
+/// Like `new`, but the returned path is a bare clone of the repository, as it
+/// would be found on a server or in a mirror.
+pub fn new_bare<F>(name: &str, callback: F) -> PathBuf
+    where F: FnOnce(ProjectBuilder) -> ProjectBuilder
+{
+    let git_project = t!(new(name, callback));
+    bare_clone(&git_project.root())
+}
+
+/// Makes a bare clone of the repository at `src`, named after it with a
+/// `.git` extension.
+pub fn bare_clone(src: &Path) -> PathBuf {
+    let name = src.file_name().unwrap().to_str().unwrap();
+    let dst = src.with_file_name(format!("{}.git", name));
+    t!(git2::build::RepoBuilder::new()
+        .bare(true)
+        .clone(path2url(src.to_path_buf()).as_str(), &dst));
+    dst
+}
+
+/// Lists the git checkouts made in the test's cargo home, one for each
+/// repository and revision.
+pub fn checkouts() -> Vec<PathBuf> {
+    checkouts_in(&paths::home().join(".cargo"))
+}
+
+/// Like `checkouts`, for the cargo home at `cargo_home`.
+pub fn checkouts_in(cargo_home: &Path) -> Vec<PathBuf> {
+    let mut checkouts = Vec::new();
+    let root = cargo_home.join("git/checkouts");
+    if !root.exists() {
+        return checkouts
+    }
+    for repo in t!(fs::read_dir(&root)) {
+        let repo = t!(repo).path();
+        if !repo.is_dir() {
+            continue
+        }
+        for checkout in t!(fs::read_dir(&repo)) {
+            checkouts.push(t!(checkout).path());
+        }
+    }
+    checkouts.sort();
+    checkouts
+}
+
 pub fn add(repo: &git2::Repository) {
     // FIXME(libgit2/libgit2#2514): apparently add_all will add all submodules
     // as well, and then fail b/c they're a directory. As a stopgap, we just
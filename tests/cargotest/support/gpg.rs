@@ -0,0 +1,85 @@
+//! OpenPGP keys for testing signature verification, generated with `gpg`.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use cargo::util::{process, ProcessBuilder};
+use support::paths;
+
+/// A key pair living in its own `gpg` home directory.
+#[derive(Clone)]
+pub struct Key {
+    home: PathBuf,
+    email: String,
+}
+
+/// Whether `gpg` is installed; tests which need it are skipped otherwise.
+pub fn available() -> bool {
+    process("gpg").arg("--version").exec_with_output().is_ok()
+}
+
+impl Key {
+    /// Generates a key without a passphrase for `name`.
+    pub fn generate(name: &str) -> Key {
+        let home = paths::root().join("gpg").join(name);
+        t!(fs::create_dir_all(&home));
+        set_private(&home);
+        let key = Key {
+            home: home,
+            email: format!("{}@example.com", name),
+        };
+        t!(key.gpg()
+              .arg("--passphrase").arg("")
+              .arg("--pinentry-mode").arg("loopback")
+              .arg("--quick-gen-key")
+              .arg(format!("{} <{}>", name, key.email))
+              .arg("default").arg("default").arg("never")
+              .exec_with_output());
+        key
+    }
+
+    /// Writes the ASCII armored public key to `dst`.
+    pub fn export(&self, dst: &Path) {
+        let output = t!(self.gpg().arg("--armor")
+                                  .arg("--export").arg(&self.email)
+                                  .exec_with_output());
+        t!(fs::create_dir_all(dst.parent().unwrap()));
+        t!(t!(fs::File::create(dst)).write_all(&output.stdout));
+    }
+
+    /// Returns an ASCII armored detached signature of `file`.
+    pub fn sign(&self, file: &Path) -> String {
+        let output = t!(self.gpg().arg("--armor")
+                                  .arg("--local-user").arg(&self.email)
+                                  .arg("--output").arg("-")
+                                  .arg("--detach-sign").arg(file)
+                                  .exec_with_output());
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Creates the annotated tag `name` at `HEAD` of the repository at
+    /// `repo`, signed with this key.
+    pub fn tag(&self, repo: &Path, name: &str) {
+        t!(process("git").arg("-c").arg(format!("user.signingkey={}", self.email))
+                         .arg("tag").arg("-s").arg("-m").arg(name).arg(name)
+                         .env("GNUPGHOME", &self.home)
+                         .cwd(repo)
+                         .exec_with_output());
+    }
+
+    fn gpg(&self) -> ProcessBuilder {
+        let mut gpg = process("gpg");
+        gpg.arg("--batch").arg("--homedir").arg(&self.home);
+        gpg
+    }
+}
+
+#[cfg(unix)]
+fn set_private(path: &Path) {
+    use std::os::unix::prelude::*;
+    t!(fs::set_permissions(path, fs::Permissions::from_mode(0o700)));
+}
+
+#[cfg(not(unix))]
+fn set_private(_path: &Path) {}
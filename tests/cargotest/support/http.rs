@@ -0,0 +1,207 @@
+//! A minimal HTTP server for tests which need to talk to a registry over the
+//! network rather than through `file://` URLs.
+//!
+//! Everything is served out of a directory: `GET` reads the file at the
+//! requested path, or the part of it asked for with a `Range: bytes=N-`
+//! header, `PUT` writes the request body to it, and `DELETE` (used to yank
+//! crates) always succeeds. Files are served with an `ETag` of their contents,
+//! and `304 Not Modified` is sent when it matches the `If-None-Match` header.
+//! Every request is recorded so tests can check what Cargo sent.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use url::Url;
+
+pub struct Server {
+    addr: SocketAddr,
+    done: Arc<AtomicBool>,
+    requests: Arc<Mutex<Vec<Request>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// A request received by a `Server`.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Server {
+    /// Starts serving the contents of `root` on a free port of localhost.
+    ///
+    /// The server is shut down when the returned value is dropped.
+    pub fn serve(root: PathBuf) -> Server {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let addr = t!(listener.local_addr());
+        let done = Arc::new(AtomicBool::new(false));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let thread = {
+            let done = done.clone();
+            let requests = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if done.load(Ordering::SeqCst) {
+                        break
+                    }
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue,
+                    };
+                    if let Some(request) = handle(stream, &root) {
+                        requests.lock().unwrap().push(request);
+                    }
+                }
+            })
+        };
+        Server {
+            addr: addr,
+            done: done,
+            requests: requests,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn url(&self) -> Url {
+        t!(Url::parse(&format!("http://{}", self.addr)))
+    }
+
+    /// All requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        // Wake up the accept loop so it notices it's done.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|&&(ref k, _)| k.to_lowercase() == name.to_lowercase())
+            .map(|&(_, ref v)| &v[..])
+    }
+}
+
+/// Reads a single request off of `stream` and answers it, closing the
+/// connection afterwards.
+fn handle(stream: TcpStream, root: &Path) -> Option<Request> {
+    let mut reader = BufReader::new(t!(stream.try_clone()));
+    let mut writer = stream;
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+        return None
+    }
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return None,
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return None
+        }
+        let line = line.trim_right();
+        if line.is_empty() {
+            break
+        }
+        if let Some(i) = line.find(':') {
+            headers.push((line[..i].trim().to_string(),
+                          line[i + 1..].trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method: method,
+        path: path,
+        headers: headers,
+        body: Vec::new(),
+    };
+    if request.header("Expect") == Some("100-continue") {
+        let _ = writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+    let len = request.header("Content-Length")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+    request.body = vec![0; len];
+    if reader.read_exact(&mut request.body).is_err() {
+        return None
+    }
+
+    let file = {
+        let path = match request.path.find('?') {
+            Some(i) => &request.path[..i],
+            None => &request.path[..],
+        };
+        root.join(path.trim_left_matches('/'))
+    };
+    let mut etag = None;
+    let (status, body) = match &request.method[..] {
+        "GET" | "HEAD" => {
+            let mut contents = Vec::new();
+            let start = request.header("Range")
+                .and_then(|r| r.trim_left_matches("bytes=").split('-').next())
+                .and_then(|s| s.parse::<usize>().ok());
+            match File::open(&file).and_then(|mut f| f.read_to_end(&mut contents)) {
+                Ok(_) => {
+                    let mut hasher = DefaultHasher::new();
+                    contents.hash(&mut hasher);
+                    let tag = format!("\"{:x}\"", hasher.finish());
+                    let matches = request.header("If-None-Match") == Some(&tag[..]);
+                    etag = Some(tag);
+                    match start {
+                        _ if matches => ("304 Not Modified", Vec::new()),
+                        Some(start) if start >= contents.len() => {
+                            ("416 Range Not Satisfiable", Vec::new())
+                        }
+                        Some(start) => ("206 Partial Content", contents[start..].to_vec()),
+                        None => ("200 OK", contents),
+                    }
+                }
+                Err(_) => ("404 Not Found", Vec::new()),
+            }
+        }
+        "PUT" => {
+            t!(fs::create_dir_all(file.parent().unwrap()));
+            t!(t!(File::create(&file)).write_all(&request.body));
+            ("200 OK", b"{\"ok\":true}".to_vec())
+        }
+        "DELETE" => ("200 OK", b"{\"ok\":true}".to_vec()),
+        _ => ("405 Method Not Allowed", Vec::new()),
+    };
+
+    let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+                           status, body.len());
+    if let Some(etag) = etag {
+        head.push_str(&format!("ETag: {}\r\n", etag));
+    }
+    head.push_str("\r\n");
+    // The client may have hung up already, which is its business.
+    let _ = writer.write_all(head.as_bytes());
+    if request.method != "HEAD" {
+        let _ = writer.write_all(&body);
+    }
+    Some(request)
+}
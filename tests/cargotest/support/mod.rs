@@ -28,6 +28,8 @@ macro_rules! t {
 
 pub mod paths;
 pub mod git;
+pub mod gpg;
+pub mod http;
 pub mod registry;
 pub mod cross_compile;
 pub mod publish;
@@ -850,6 +852,9 @@ fn substitute_macros(input: &str) -> String {
         ("[WARNING]",     "warning:"),
         ("[DOCUMENTING]", " Documenting"),
         ("[FRESH]",       "       Fresh"),
+        ("[DIRTY]",       "       Dirty"),
+        ("[FETCHED]",     "     Fetched"),
+        ("[FETCHING]",    "    Fetching"),
         ("[UPDATING]",    "    Updating"),
         ("[ADDING]",      "      Adding"),
         ("[REMOVING]",    "    Removing"),
@@ -862,6 +867,8 @@ fn substitute_macros(input: &str) -> String {
         ("[INSTALLING]",  "  Installing"),
         ("[REPLACING]",   "   Replacing"),
         ("[UNPACKING]",   "   Unpacking"),
+        ("[SUMMARY]",     "     Summary"),
+        ("[SKIPPING]",    "    Skipping"),
         ("[EXE]", if cfg!(windows) {".exe"} else {""}),
         ("[/]", if cfg!(windows) {"\\"} else {"/"}),
     ];
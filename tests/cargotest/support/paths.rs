@@ -80,6 +80,14 @@ impl CargoPathExt for Path {
             return
         }
 
+        // Read-only git checkouts have directories whose entries can't be
+        // removed until they're writable again.
+        let mut perms = t!(self.metadata()).permissions();
+        if perms.readonly() {
+            perms.set_readonly(false);
+            t!(fs::set_permissions(self, perms));
+        }
+
         for file in t!(fs::read_dir(self)) {
             let file = t!(file);
             if file.file_type().map(|m| m.is_dir()).unwrap_or(false) {
@@ -7,11 +7,13 @@ use flate2::Compression::Default;
 use flate2::write::GzEncoder;
 use git2;
 use hex::ToHex;
-use tar::{Builder, Header};
+use tar::{Builder, EntryType, Header};
 use url::Url;
 
 use support::paths;
 use support::git::repo;
+use support::gpg::Key;
+use support::http::Server;
 use cargo::util::Sha256;
 
 pub fn registry_path() -> PathBuf { paths::root().join("registry") }
@@ -27,10 +29,13 @@ pub struct Package {
     deps: Vec<Dependency>,
     files: Vec<(String, String)>,
     extra_files: Vec<(String, String)>,
+    symlinks: Vec<(String, String)>,
     yanked: bool,
     features: HashMap<String, Vec<String>>,
     local: bool,
     alternative: bool,
+    signer: Option<Key>,
+    links: Option<String>,
 }
 
 struct Dependency {
@@ -80,6 +85,62 @@ pub fn init() {
     fs::create_dir_all(dl_path().join("api/v1/crates")).unwrap();
 }
 
+/// Serves downloads and the API of the registry over HTTP instead of from
+/// `dl_path` through `file://` URLs. The index itself stays a local git
+/// repository.
+///
+/// Crates published through `Package` are available from the server, which
+/// stops when the returned value is dropped.
+pub fn serve() -> Server {
+    init();
+    let server = Server::serve(dl_path());
+    let url = server.url();
+    let config = format!(r#"{{"dl":"{0}","api":"{0}"}}"#,
+                         url.as_str().trim_right_matches('/'));
+    t!(t!(File::create(registry_path().join("config.json")))
+        .write_all(config.as_bytes()));
+    commit(&registry_path(), "config.json");
+    server
+}
+
+/// Serves the whole registry over HTTP like `serve`, with the index as a
+/// sparse index at `/registry` which crates.io is replaced with, rather than
+/// as a git repository.
+pub fn serve_sparse() -> Server {
+    init();
+    let server = Server::serve(paths::root());
+    let url = server.url();
+    let root = url.as_str().trim_right_matches('/');
+    let config = format!(r#"{{"dl":"{0}/dl","api":"{0}/dl"}}"#, root);
+    t!(t!(File::create(registry_path().join("config.json")))
+        .write_all(config.as_bytes()));
+
+    let path = paths::home().join(".cargo/config");
+    let mut contents = String::new();
+    t!(t!(File::open(&path)).read_to_string(&mut contents));
+    let contents = contents.replace(&format!("registry = '{}'", registry()),
+                                    &format!("registry = 'sparse+{}/registry'", root));
+    t!(t!(File::create(&path)).write_all(contents.as_bytes()));
+    server
+}
+
+/// Commits the change to `file` in the index at `registry_path`.
+fn commit(registry_path: &Path, file: &str) {
+    let repo = t!(git2::Repository::open(registry_path));
+    let mut index = t!(repo.index());
+    t!(index.add_path(Path::new(file)));
+    t!(index.write());
+    let id = t!(index.write_tree());
+
+    let tree = t!(repo.find_tree(id));
+    let sig = t!(repo.signature());
+    let parent = t!(repo.refname_to_id("refs/heads/master"));
+    let parent = t!(repo.find_commit(parent));
+    t!(repo.commit(Some("HEAD"), &sig, &sig,
+                   "Another commit", &tree,
+                   &[&parent]));
+}
+
 impl Package {
     pub fn new(name: &str, vers: &str) -> Package {
         init();
@@ -89,10 +150,13 @@ impl Package {
             deps: Vec::new(),
             files: Vec::new(),
             extra_files: Vec::new(),
+            symlinks: Vec::new(),
             yanked: false,
             features: HashMap::new(),
             local: false,
             alternative: false,
+            signer: None,
+            links: None,
         }
     }
 
@@ -116,6 +180,11 @@ impl Package {
         self
     }
 
+    pub fn symlink(&mut self, name: &str, target: &str) -> &mut Package {
+        self.symlinks.push((name.to_string(), target.to_string()));
+        self
+    }
+
     pub fn dep(&mut self, name: &str, vers: &str) -> &mut Package {
         self.full_dep(name, vers, None, "normal", &[], None)
     }
@@ -168,6 +237,19 @@ impl Package {
         self
     }
 
+    /// Makes the package link to the native library `links`, with an empty
+    /// build script unless one is added as a file.
+    pub fn links(&mut self, links: &str) -> &mut Package {
+        self.links = Some(links.to_string());
+        self
+    }
+
+    /// Lists a signature of the `.crate` file made with `key` in the index.
+    pub fn signed(&mut self, key: &Key) -> &mut Package {
+        self.signer = Some(key.clone());
+        self
+    }
+
     pub fn publish(&self) -> String {
         self.make_archive();
 
@@ -189,14 +271,21 @@ impl Package {
             t!(t!(File::open(&self.archive_dst())).read_to_end(&mut c));
             cksum(&c)
         };
-        let line = json!({
+        let mut line = json!({
             "name": self.name,
             "vers": self.vers,
             "deps": deps,
             "cksum": cksum,
             "features": self.features,
             "yanked": self.yanked,
-        }).to_string();
+        });
+        if let Some(ref key) = self.signer {
+            line["sig"] = json!(key.sign(&self.archive_dst()));
+        }
+        if let Some(ref links) = self.links {
+            line["links"] = json!(links);
+        }
+        let line = line.to_string();
 
         let file = match self.name.len() {
             1 => format!("1/{}", self.name),
@@ -221,20 +310,7 @@ impl Package {
 
         // Add the new file to the index
         if !self.local {
-            let repo = t!(git2::Repository::open(&registry_path));
-            let mut index = t!(repo.index());
-            t!(index.add_path(Path::new(&file)));
-            t!(index.write());
-            let id = t!(index.write_tree());
-
-            // Commit this change
-            let tree = t!(repo.find_tree(id));
-            let sig = t!(repo.signature());
-            let parent = t!(repo.refname_to_id("refs/heads/master"));
-            let parent = t!(repo.find_commit(parent));
-            t!(repo.commit(Some("HEAD"), &sig, &sig,
-                           "Another commit", &tree,
-                           &[&parent]));
+            commit(&registry_path, &file);
         }
 
         return cksum
@@ -247,6 +323,12 @@ impl Package {
             version = "{}"
             authors = []
         "#, self.name, self.vers);
+        if let Some(ref links) = self.links {
+            manifest.push_str(&format!(r#"
+                links = "{}"
+                build = "build.rs"
+            "#, links));
+        }
         for dep in self.deps.iter() {
             let target = match dep.target {
                 None => String::new(),
@@ -275,9 +357,21 @@ impl Package {
                 self.append(&mut a, name, contents);
             }
         }
+        if self.links.is_some() && !self.files.iter().any(|&(ref f, _)| f == "build.rs") {
+            self.append(&mut a, "build.rs", "fn main() {}");
+        }
         for &(ref name, ref contents) in self.extra_files.iter() {
             self.append_extra(&mut a, name, contents);
         }
+        for &(ref name, ref target) in self.symlinks.iter() {
+            let mut header = Header::new_ustar();
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            t!(header.set_path(&format!("{}-{}/{}", self.name, self.vers, name)));
+            t!(header.set_link_name(target));
+            header.set_cksum();
+            t!(a.append(&header, &[][..]));
+        }
     }
 
     fn append<W: Write>(&self, ar: &mut Builder<W>, file: &str, contents: &str) {
@@ -136,6 +136,48 @@ fn cfg_matches() {
     assert!(!e!(any((not(foo)), (all(foo, bar)))).matches(&[c!(foo)]));
 }
 
+#[test]
+fn cfg_mentions() {
+    assert!(e!(feature = "a").mentions("feature"));
+    assert!(e!(any(unix, (feature = "a"))).mentions("feature"));
+    assert!(e!(not(all(unix, feature))).mentions("feature"));
+    assert!(!e!(any(unix, (target_os = "linux"))).mentions("feature"));
+}
+
+#[test]
+fn feature_in_target_cfg_warns() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "a"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            foo = []
+
+            [target.'cfg(feature = "foo")'.dependencies]
+            b = { path = 'b' }
+        "#)
+        .file("src/lib.rs", "")
+        .file("b/Cargo.toml", r#"
+            [package]
+            name = "b"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("b/src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build").arg("--features").arg("foo"),
+                execs().with_status(0).with_stderr("\
+[WARNING] `feature` is used in `target.'cfg(feature = \"foo\")'`, but it's never \
+set when selecting dependencies, so this table won't work as expected; make the \
+dependencies optional and enable them with the `[features]` section instead
+[COMPILING] a v0.0.1 ([..])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+"));
+}
+
 #[test]
 fn cfg_easy() {
     let p = project("foo")
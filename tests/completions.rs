@@ -0,0 +1,57 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn bash() {
+    let p = project("foo").build();
+
+    assert_that(p.cargo("completions").arg("bash"),
+                execs().with_status(0)
+                       .with_stdout_contains("complete -F _cargo -o default cargo")
+                       .with_stdout_contains("\t\t\tcache) opts=\"stats gc -h --help \
+                                              --manifest-path -v --verbose -q --quiet \
+                                              --color --frozen --locked -Z\" ;;")
+                       .with_stdout_contains("[..]build) opts=\"[..] --all-features [..]\" ;;"));
+}
+
+#[test]
+fn zsh() {
+    let p = project("foo").build();
+
+    assert_that(p.cargo("completions").arg("zsh"),
+                execs().with_status(0)
+                       .with_stdout_contains("#compdef cargo")
+                       .with_stdout_contains("\t\t'build:Compile a local package and all \
+                                              of its dependencies'")
+                       .with_stdout_contains("[..]'(-p --package)--package[Package to \
+                                              build]:value:' \\"));
+}
+
+#[test]
+fn fish() {
+    let p = project("foo").build();
+
+    assert_that(p.cargo("completions").arg("fish"),
+                execs().with_status(0)
+                       .with_stdout_contains("complete -c cargo -n '__fish_use_subcommand' \
+                                              -f -a completions -d 'Generate shell \
+                                              completions for Cargo'")
+                       .with_stdout_contains("complete -c cargo -n \
+                                              '__fish_seen_subcommand_from build' \
+                                              -l release -d 'Build artifacts in release \
+                                              mode, with optimizations'"));
+}
+
+#[test]
+fn unknown_shell() {
+    let p = project("foo").build();
+
+    assert_that(p.cargo("completions").arg("tcsh"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] unsupported shell `tcsh`, expected one of `bash`, `zsh` or `fish`
+"));
+}
@@ -1,4 +1,5 @@
 extern crate cargotest;
+extern crate fs2;
 extern crate git2;
 extern crate hamcrest;
 
@@ -15,6 +16,7 @@ use cargotest::install::{has_installed_exe, cargo_home};
 use cargotest::support::git;
 use cargotest::support::registry::Package;
 use cargotest::support::{execs, project};
+use fs2::FileExt;
 use hamcrest::{assert_that, existing_file};
 
 fn pkg(name: &str, vers: &str) {
@@ -498,3 +500,84 @@ fn no_deadlock_with_git_dependencies() {
     }
 
 }
+
+#[test]
+fn package_cache_shared_by_builds_but_not_gc() {
+    const LOCKED_CACHE: &'static str = "waiting for file lock on package cache";
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    let p = p.build();
+
+    // Pretend another build is reading from the cache.
+    fs::create_dir_all(cargo_home()).unwrap();
+    let lock = File::create(cargo_home().join(".package-cache")).unwrap();
+    lock.lock_shared().unwrap();
+
+    let output = p.cargo("build").build_command().output().unwrap();
+    assert!(!str::from_utf8(&output.stderr).unwrap().contains(LOCKED_CACHE));
+    assert_that(output, execs().with_status(0));
+
+    let mut gc = p.cargo("cache").arg("gc").build_command();
+    gc.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut gc = gc.spawn().unwrap();
+    thread::sleep(Duration::from_millis(500));
+    assert!(gc.try_wait().unwrap().is_none());
+
+    lock.unlock().unwrap();
+    let output = gc.wait_with_output().unwrap();
+    assert!(str::from_utf8(&output.stderr).unwrap().contains(LOCKED_CACHE));
+    assert_that(output, execs().with_status(0));
+}
+
+#[test]
+fn download_while_another_build_reads_the_cache() {
+    const LOCKED_CACHE: &'static str = "waiting for file lock on package cache";
+
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.0"
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    let p = p.build();
+
+    // Pretend another build is reading from the cache, which mustn't stop
+    // this one from downloading what it's missing.
+    fs::create_dir_all(cargo_home()).unwrap();
+    let lock = File::create(cargo_home().join(".package-cache")).unwrap();
+    lock.lock_shared().unwrap();
+
+    let mut build = p.cargo("build").build_command();
+    build.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut build = build.spawn().unwrap();
+    for _ in 0..3000 {
+        if build.try_wait().unwrap().is_some() {
+            break
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    if build.try_wait().unwrap().is_none() {
+        build.kill().unwrap();
+        panic!("Deadlock!");
+    }
+    let output = build.wait_with_output().unwrap();
+    assert!(!str::from_utf8(&output.stderr).unwrap().contains(LOCKED_CACHE));
+    assert_that(output, execs().with_status(0)
+                               .with_stderr_contains("[DOWNLOADING] bar v0.1.0 [..]"));
+
+    lock.unlock().unwrap();
+}
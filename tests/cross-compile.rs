@@ -5,7 +5,7 @@ extern crate hamcrest;
 use cargo::util::process;
 use cargotest::{is_nightly, rustc_host};
 use cargotest::support::{project, execs, basic_bin_manifest, cross_compile};
-use hamcrest::{assert_that, existing_file};
+use hamcrest::{assert_that, existing_file, is_not};
 
 #[test]
 fn simple_cross() {
@@ -79,6 +79,58 @@ fn simple_cross_config() {
                 execs().with_status(0));
 }
 
+#[test]
+fn cross_config_host_target() {
+    if cross_compile::disabled() { return }
+
+    let p = project("foo")
+        .file(".cargo/config", &format!(r#"
+            [build]
+            target = "{}"
+        "#, cross_compile::alternate()))
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    let target = cross_compile::alternate();
+    let host = rustc_host();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--target").arg(&host),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] foo v0.5.0 [..]"));
+    assert_that(&p.target_bin(&target, "foo"), existing_file());
+    assert_that(&p.target_bin(&host, "foo"), existing_file());
+
+    // Both builds stay fresh, they don't share any fingerprints.
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0).with_stderr_contains("[FRESH] foo [..]"));
+    assert_that(p.cargo("build").arg("-v").arg("--target").arg(&host),
+                execs().with_status(0).with_stderr_contains("[FRESH] foo [..]"));
+
+    // `cargo clean -p` cleans the configured target too.
+    assert_that(p.cargo("clean").arg("-p").arg("foo"), execs().with_status(0));
+    assert_that(&p.target_bin(&target, "foo"), is_not(existing_file()));
+    assert_that(&p.target_bin(&host, "foo"), existing_file());
+}
+
+#[test]
+fn empty_target_config() {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [build]
+            target = ""
+        "#)
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] `build.target` is empty in [..]config, expected a target triple
+"));
+}
+
 #[test]
 fn simple_deps() {
     if cross_compile::disabled() { return }
@@ -308,7 +360,7 @@ fn linker_and_ar() {
                 execs().with_status(101)
                        .with_stderr_contains(&format!("\
 [COMPILING] foo v0.5.0 ({url})
-[RUNNING] `rustc --crate-name foo src[/]foo.rs --crate-type bin \
+[RUNNING] `rustc --crate-name foo src[/]foo.rs --error-format json --crate-type bin \
     --emit=dep-info,link -C debuginfo=2 \
     -C metadata=[..] \
     --out-dir {dir}[/]target[/]{target}[/]debug[/]deps \
@@ -429,7 +481,8 @@ fn cross_tests() {
 [COMPILING] foo v0.0.0 ({foo})
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]{triple}[/]debug[/]deps[/]foo-[..][EXE]
-[RUNNING] target[/]{triple}[/]debug[/]deps[/]bar-[..][EXE]", foo = p.url(), triple = target))
+[RUNNING] target[/]{triple}[/]debug[/]deps[/]bar-[..][EXE]
+[SUMMARY] 2 test binaries: [..]", foo = p.url(), triple = target))
                        .with_stdout_contains("test test_foo ... ok")
                        .with_stdout_contains("test test ... ok"));
 }
@@ -1000,8 +1053,59 @@ fn cross_test_dylib() {
 [COMPILING] foo v0.0.1 ({dir})
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]{arch}[/]debug[/]deps[/]foo-[..][EXE]
-[RUNNING] target[/]{arch}[/]debug[/]deps[/]test-[..][EXE]",
+[RUNNING] target[/]{arch}[/]debug[/]deps[/]test-[..][EXE]
+[SUMMARY] 2 test binaries: [..]",
                         dir = p.url(), arch = cross_compile::alternate()))
                        .with_stdout_contains_n("test foo ... ok", 2));
 
 }
+
+#[test]
+fn crt_static_profile() {
+    let target = rustc_host();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [profile.dev]
+            crt-static = false
+        "#)
+        .file("build.rs", "fn main() {}")
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Without `--target` everything could be loaded by the host.
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[..]crt-static[..]"));
+
+    assert_that(p.cargo("build").arg("-v").arg("--target").arg(&target),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]main.rs [..]--target [..] \
+    -C target-feature=-crt-static [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name build_script_build build.rs [..]`")
+                       .with_stderr_does_not_contain("\
+[RUNNING] `rustc --crate-name build_script_build [..]crt-static[..]`"));
+}
+
+#[test]
+fn missing_musl_target() {
+    let target = "aarch64-unknown-linux-musl";
+    let p = project("foo")
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/foo.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--target").arg(target),
+                execs().with_status(101)
+                       .with_stderr(&format!("\
+[ERROR] the `{0}` target isn't installed for rustc (no `[..]`), it can be added \
+with `rustup target add {0}`
+", target)));
+}
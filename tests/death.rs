@@ -121,6 +121,64 @@ fn ctrl_c_kills_everyone() {
             we won't be able to!");
 }
 
+#[cfg(unix)]
+#[test]
+fn interrupt_kills_children_and_reports() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", &format!(r#"
+            use std::net::TcpStream;
+            use std::io::Read;
+
+            fn main() {{
+                let mut socket = TcpStream::connect("{}").unwrap();
+                let _ = socket.read(&mut [0; 10]);
+                panic!("that read should never return");
+            }}
+        "#, addr))
+        .build();
+
+    let mut cargo = p.cargo("build").build_command();
+    cargo.stdin(Stdio::piped())
+         .stdout(Stdio::piped())
+         .stderr(Stdio::piped());
+    let mut child = cargo.spawn().unwrap();
+
+    // Unlike Ctrl-C in a terminal this only signals cargo itself, so it's up
+    // to cargo to take down the build script.
+    let mut sock = listener.accept().unwrap().0;
+    let r = unsafe { libc::kill(child.id() as i32, libc::SIGTERM) };
+    if r < 0 {
+        panic!("failed to kill: {}", io::Error::last_os_error());
+    }
+
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(130), "{:?} {:?}", status, status.signal());
+    match sock.read(&mut [0; 10]) {
+        Ok(n) => assert_eq!(n, 0),
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+    }
+
+    let mut stderr = String::new();
+    child.stderr.take().unwrap().read_to_string(&mut stderr).unwrap();
+    assert!(stderr.contains("interrupted, waiting for running jobs to finish"),
+            "{}", stderr);
+    assert!(stderr.contains("error: interrupted"), "{}", stderr);
+    assert!(!stderr.contains("failed to run custom build command"), "{}", stderr);
+}
+
 #[cfg(unix)]
 fn ctrl_c(child: &mut Child) {
     use libc;
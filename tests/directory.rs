@@ -190,7 +190,7 @@ fn simple_install_fail() {
         .build();
 
     assert_that(cargo_process().arg("install").arg("bar"),
-                execs().with_status(101).with_stderr(
+                execs().with_status(102).with_stderr(
 "  Installing bar v0.1.0
 error: failed to compile `bar v0.1.0`, intermediate artifacts can be found at `[..]`
 
@@ -275,7 +275,7 @@ fn not_there() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 error: no matching package named `foo` found (required by `bar`)
 location searched: [..]
 version required: ^0.1.0
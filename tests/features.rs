@@ -28,11 +28,39 @@ fn invalid1() {
                 execs().with_status(101).with_stderr("\
 [ERROR] failed to parse manifest at `[..]`
 
+Caused by:
+  invalid entry `baz` in feature `bar` at [..]Cargo.toml:8
+
 Caused by:
   Feature `bar` includes `baz` which is neither a dependency nor another feature
 "));
 }
 
+#[test]
+fn invalid_entry_in_multi_line_feature() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            foo = []
+            bar = [
+                "foo",
+                "baz",
+            ]
+        "#)
+        .file("src/main.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+  invalid entry `baz` in feature `bar` at [..]Cargo.toml:11
+"));
+}
+
 #[test]
 fn invalid2() {
     let p = project("foo")
@@ -82,6 +110,9 @@ fn invalid3() {
                 execs().with_status(101).with_stderr("\
 [ERROR] failed to parse manifest at `[..]`
 
+Caused by:
+  invalid entry `baz` in feature `bar` at [..]Cargo.toml:8
+
 Caused by:
   Feature `bar` depends on `baz` which is not an optional dependency.
 Consider adding `optional = true` to the dependency
@@ -112,7 +143,7 @@ fn invalid4() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] Package `bar v0.0.1 ([..])` does not have these features: `bar`
 "));
 
@@ -124,7 +155,7 @@ fn invalid4() {
     "#);
 
     assert_that(p.cargo("build").arg("--features").arg("test"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] Package `foo v0.0.1 ([..])` does not have these features: `test`
 "));
 }
@@ -173,6 +204,9 @@ fn invalid6() {
                 execs().with_status(101).with_stderr("\
 [ERROR] failed to parse manifest at `[..]`
 
+Caused by:
+  invalid entry `bar/baz` in feature `foo` at [..]Cargo.toml:8
+
 Caused by:
   Feature `foo` requires a feature of `bar` which is not a dependency
 "));
@@ -198,6 +232,9 @@ fn invalid7() {
                 execs().with_status(101).with_stderr("\
 [ERROR] failed to parse manifest at `[..]`
 
+Caused by:
+  invalid entry `bar/baz` in feature `foo` at [..]Cargo.toml:8
+
 Caused by:
   Feature `foo` requires a feature of `bar` which is not a dependency
 "));
@@ -227,7 +264,7 @@ fn invalid8() {
         .build();
 
     assert_that(p.cargo("build").arg("--features").arg("foo"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] feature names may not contain slashes: `foo/bar`
 "));
 }
@@ -355,7 +392,7 @@ fn no_transitive_dep_feature_requirement() {
         "#)
         .build();
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] feature names may not contain slashes: `bar/qux`
 "));
 }
@@ -475,7 +512,7 @@ fn cyclic_feature() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] Cyclic feature dependency: feature `default` depends on itself
 "));
 }
@@ -1055,13 +1092,13 @@ fn dep_feature_in_cmd_line() {
 
     // Trying to enable features of transitive dependencies is an error
     assert_that(p.cargo("build").arg("--features").arg("bar/some-feat"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] Package `foo v0.0.1 ([..])` does not have these features: `bar`
 "));
 
     // Hierarchical feature specification should still be disallowed
     assert_that(p.cargo("build").arg("--features").arg("derived/bar/some-feat"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [ERROR] feature names may not contain slashes: `bar/some-feat`
 "));
 }
@@ -1236,3 +1273,233 @@ fn many_cli_features_comma_and_space_delimited() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 ", dir = p.url())));
 }
+
+#[test]
+fn features_report() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            default = ["bar/std"]
+
+            [dependencies.bar]
+            path = "bar"
+
+            [dev-dependencies.bar]
+            path = "bar"
+            features = ["extra"]
+
+            [dev-dependencies.baz]
+            path = "baz"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            std = []
+            extra = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("features"),
+                execs().with_status(0).with_stdout("\
+bar v0.0.1: extra, std (dev-dependencies add: extra)
+baz v0.0.1: no features (only used by dev-dependencies)
+foo v0.0.1: default
+"));
+
+    assert_that(p.cargo("features").arg("--no-default-features"),
+                execs().with_status(0).with_stdout("\
+bar v0.0.1: extra (dev-dependencies add: extra)
+baz v0.0.1: no features (only used by dev-dependencies)
+foo v0.0.1: no features
+"));
+}
+
+#[test]
+fn dev_features_not_unified() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+
+            [dev-dependencies.bar]
+            path = "bar"
+            features = ["extra"]
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            fn main() { bar::check(); }
+
+            #[test]
+            fn dev_features_enabled() { assert!(bar::EXTRA); }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            extra = []
+        "#)
+        .file("bar/src/lib.rs", r#"
+            pub const EXTRA: bool = cfg!(feature = "extra");
+            pub fn check() { assert!(!EXTRA); }
+        "#)
+        .file(".cargo/config", r#"
+            [build]
+            unify-dev-features = false
+        "#)
+        .build();
+
+    assert_that(p.cargo("run"), execs().with_status(0));
+    assert_that(p.cargo("test"), execs().with_status(0));
+}
+
+#[test]
+fn dep_prefix_enables_optional_dependency() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = ["dep:bar"]
+            baz = ["dep:baz"]
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+
+            [dependencies.baz]
+            path = "baz"
+            optional = true
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(feature = "fancy")]
+            extern crate bar;
+            #[cfg(feature = "baz")]
+            extern crate baz;
+
+            fn main() {
+                assert!(!cfg!(feature = "bar"));
+            }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("run").arg("--features").arg("fancy baz"),
+                execs().with_status(0));
+    assert_that(p.cargo("run").arg("--all-features"),
+                execs().with_status(0));
+    assert_that(p.cargo("build").arg("--features").arg("bar"),
+                execs().with_status(102).with_stderr_contains("\
+[..]does not have feature `bar`. It has an optional dependency with that name, \
+but it can only be enabled through the features which include `dep:bar`
+"));
+}
+
+#[test]
+fn dep_prefix_invalid() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = [
+                "dep:bar",
+                "dep:baz",
+            ]
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+        "#)
+        .file("src/main.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid entry `dep:baz` in feature `fancy` at [..]Cargo.toml:10
+
+Caused by:
+  Feature `fancy` includes `dep:baz`, but `baz` is not a dependency
+"));
+
+    File::create(p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [features]
+        fancy = ["dep:bar"]
+        other = ["bar"]
+
+        [dependencies.bar]
+        path = "bar"
+        optional = true
+    "#).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid entry `bar` in feature `other` at [..]Cargo.toml:9
+
+Caused by:
+  Feature `other` includes `bar`, but `bar` is only enabled with `dep:bar` \
+elsewhere, use `dep:bar` here as well
+"));
+}
@@ -7,7 +7,7 @@ use std::io::prelude::*;
 use cargotest::sleep_ms;
 use cargotest::support::{project, execs, path2url};
 use cargotest::support::paths::CargoPathExt;
-use hamcrest::{assert_that, existing_file};
+use hamcrest::{assert_that, existing_dir, existing_file, is_not};
 
 #[test]
 fn modifying_and_moving() {
@@ -733,3 +733,92 @@ fn rebuild_if_environment_changes() {
 [RUNNING] `target[/]debug[/]env_change[EXE]`
 ", dir = p.url())));
 }
+
+#[test]
+fn dry_run_reports_stale_units() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.1"
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            authors = []
+            version = "0.0.1"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--dry-run"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[DIRTY] bar v0.0.1 ([..]) (lib): it has not been built yet")
+                       .with_stderr_contains("\
+[DIRTY] foo v0.0.1 ([..]) (bin \"foo\"): it has not been built yet")
+                       .with_stderr_contains("\
+[FINISHED] dry run, 2 unit(s) would be rebuilt")
+                       .with_stderr_does_not_contain("[COMPILING] [..]"));
+    assert_that(&p.bin("foo"), is_not(existing_file()));
+    assert_that(&p.root().join("target"), is_not(existing_dir()));
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--dry-run"),
+                execs().with_status(0).with_stderr("\
+[FINISHED] dry run, nothing would be rebuilt
+"));
+
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+    File::create(&p.root().join("bar/src/lib.rs")).unwrap()
+         .write_all(b"pub fn bar() {}").unwrap();
+    assert_that(p.cargo("build").arg("--dry-run"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[DIRTY] bar v0.0.1 ([..]) (lib): the file `[..]lib.rs` has changed")
+                       .with_stderr_contains("\
+[DIRTY] foo v0.0.1 ([..]) (bin \"foo\"): [..]")
+                       .with_stderr_contains("\
+[FINISHED] dry run, 2 unit(s) would be rebuilt"));
+}
+
+#[test]
+fn very_verbose_explains_rebuilds() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+            version = "0.0.1"
+
+            [features]
+            extra = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+    File::create(&p.root().join("src/lib.rs")).unwrap()
+         .write_all(b"pub fn foo() {}").unwrap();
+    assert_that(p.cargo("build").arg("-vv"),
+                execs().with_status(0).with_stderr_contains("\
+[DIRTY] foo v0.0.1 ([..]) (lib): the file `[..]lib.rs` has changed"));
+
+    assert_that(p.cargo("build").arg("-vv").arg("--features").arg("extra"),
+                execs().with_status(0).with_stderr_contains("\
+[DIRTY] foo v0.0.1 ([..]) (lib): the features have changed: \
+previously [] now [\"extra\"]"));
+
+    assert_that(p.cargo("build").arg("-vv").arg("--features").arg("extra"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[DIRTY] [..]"));
+}
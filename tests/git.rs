@@ -7,7 +7,9 @@ use std::fs::{self, File};
 use std::io::prelude::*;
 use std::path::Path;
 
-use cargo::util::process;
+use cargo::core::{GitReference, Shell};
+use cargo::sources::git::GitRemote;
+use cargo::util::{process, Config};
 use cargotest::sleep_ms;
 use cargotest::support::paths::{self, CargoPathExt};
 use cargotest::support::{git, project, execs, main_file, path2url};
@@ -436,15 +438,67 @@ fn cargo_compile_with_short_ssh_git() {
         .file("src/foo.rs", &main_file(r#""{}", dep1::hello()"#, &["dep1"]))
         .build();
 
-    assert_that(project.cargo("build"),
+    // Short ssh URLs are understood as their `ssh://` equivalent, which
+    // `--frozen` stops from being fetched.
+    assert_that(project.cargo("build").arg("--frozen"),
         execs()
+        .with_status(101)
         .with_stdout("")
-        .with_stderr(&format!("\
-[ERROR] failed to parse manifest at `[..]`
+        .with_stderr_contains("\
+[UPDATING] git repository `ssh://git@github.com/a/dep`")
+        .with_stderr_contains("\
+  Unable to update ssh://git@github.com/a/dep")
+        .with_stderr_contains("\
+  attempting to update a git repository, but --frozen was specified"));
+}
 
-Caused by:
-  invalid url `{}`: relative URL without a base
-", url)));
+#[test]
+fn scp_like_and_file_urls_in_lockfile() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    // `file://` URLs with and without a trailing `.git` or slash name the
+    // same repository, so they share a database.
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}/'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let lockfile = p.read_lockfile();
+    assert!(lockfile.contains(&format!("\"git+{}/#", bar.url())), "{}", lockfile);
+
+    let p2 = project("foo2")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo2"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p2.cargo("build"), execs().with_status(0));
+    let dbs = fs::read_dir(paths::home().join(".cargo/git/db")).unwrap().count();
+    assert_eq!(dbs, 1);
 }
 
 #[test]
@@ -2063,9 +2117,9 @@ fn include_overrides_gitignore() {
 [COMPILING] filetime [..]
 [RUNNING] `rustc --crate-name filetime [..]`
 [COMPILING] reduction [..]
-[RUNNING] `rustc --crate-name build_script_tango_build tango-build.rs --crate-type bin [..]`
+[RUNNING] `rustc --crate-name build_script_tango_build tango-build.rs --error-format json --crate-type bin [..]`
 [RUNNING] `[..][/]build-script-tango-build`
-[RUNNING] `rustc --crate-name reduction src[/]lib.rs --crate-type lib [..]`
+[RUNNING] `rustc --crate-name reduction src[/]lib.rs --error-format json --crate-type lib [..]`
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 "));
 
@@ -2089,7 +2143,7 @@ fn include_overrides_gitignore() {
 [FRESH] libc [..]
 [FRESH] filetime [..]
 [COMPILING] reduction [..]
-[RUNNING] `rustc --crate-name reduction src[/]lib.rs --crate-type lib [..]`
+[RUNNING] `rustc --crate-name reduction src[/]lib.rs --error-format json --crate-type lib [..]`
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 "));
 
@@ -2107,7 +2161,7 @@ fn include_overrides_gitignore() {
 [FRESH] filetime [..]
 [COMPILING] reduction [..]
 [RUNNING] `[..][/]build-script-tango-build`
-[RUNNING] `rustc --crate-name reduction src[/]lib.rs --crate-type lib [..]`
+[RUNNING] `rustc --crate-name reduction src[/]lib.rs --error-format json --crate-type lib [..]`
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 "));
 }
@@ -2174,3 +2228,794 @@ fn invalid_git_dependency_manifest() {
                              path2url(git_root),
                              )));
 }
+
+#[test]
+fn pinned_rev_exported_from_database() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let repo = git2::Repository::open(&bar.root()).unwrap();
+    let rev = repo.revparse_single("HEAD").unwrap().id();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+            rev = "{}"
+        "#, bar.url(), rev))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Now that the database has the revision, a fresh checkout of it is an
+    // export rather than a working checkout.
+    paths::home().join(".cargo/git/checkouts").rm_rf();
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    let checkout = git::checkouts().remove(0);
+    assert_that(&checkout.join("src/lib.rs"), existing_file());
+    assert!(!checkout.join(".git").exists());
+}
+
+#[test]
+fn archive_checkout_strategy() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file(".cargo/config", r#"
+            [git]
+            checkout-strategy = "archive"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    let checkout = git::checkouts().remove(0);
+    assert_that(&checkout.join("src/lib.rs"), existing_file());
+    assert!(!checkout.join(".git").exists());
+}
+
+#[test]
+fn partial_export_is_replaced() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file(".cargo/config", r#"
+            [git]
+            checkout-strategy = "archive"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    let checkout = git::checkouts().remove(0);
+    let dir = checkout.parent().unwrap().to_path_buf();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    // Simulate an export which was interrupted halfway through by an older
+    // Cargo, it has to be made again rather than used as is.
+    fs::remove_file(checkout.join(".cargo-ok")).unwrap();
+    fs::remove_file(checkout.join("src/lib.rs")).unwrap();
+
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&checkout.join(".cargo-ok"), existing_file());
+    assert_that(&checkout.join("src/lib.rs"), existing_file());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn archive_ignores_global_gitconfig() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}\n")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file(".cargo/config", r#"
+            [git]
+            checkout-strategy = "archive"
+        "#)
+        .build();
+
+    // This would have `git archive` write out CRLF line endings.
+    File::create(paths::home().join(".gitconfig")).unwrap()
+        .write_all(b"[core]\nautocrlf = true\n").unwrap();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let checkout = git::checkouts().remove(0);
+    let mut contents = String::new();
+    File::open(checkout.join("src/lib.rs")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "pub fn bar() {}\n");
+
+    // Unless it's asked for.
+    paths::home().join(".cargo/git/checkouts").rm_rf();
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [git]
+        checkout-strategy = "archive"
+        isolate-config = false
+    "#).unwrap();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let checkout = git::checkouts().remove(0);
+    contents.clear();
+    File::open(checkout.join("src/lib.rs")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "pub fn bar() {}\r\n");
+}
+
+#[test]
+fn bad_checkout_strategy() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [git]
+            checkout-strategy = "zip"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]unknown git checkout strategy `zip` in [..]config, expected `checkout` or `archive`
+"));
+}
+
+#[test]
+fn minimal_fetch_strategy() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+    let repo = git2::Repository::open(&bar.root()).unwrap();
+    git::tag(&repo, "v0.5.0");
+    let head = repo.head().unwrap().target().unwrap();
+    repo.branch("other", &repo.find_commit(head).unwrap(), false).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+            tag = "v0.5.0"
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file(".cargo/config", r#"
+            [git]
+            fetch-strategy = "minimal"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Only the tag made it into the database, not the branches.
+    let db = paths::home().join(".cargo/git/db");
+    let db = fs::read_dir(&db).unwrap().next().unwrap().unwrap().path();
+    let db = git2::Repository::open(&db).unwrap();
+    assert!(db.refname_to_id("refs/tags/v0.5.0").is_ok());
+    assert!(db.refname_to_id("refs/heads/master").is_err());
+    assert!(db.refname_to_id("refs/heads/other").is_err());
+
+    // Fresh checkouts are still made from what's in the database.
+    paths::home().join(".cargo/git/checkouts").rm_rf();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let checkout = git::checkouts().remove(0);
+    assert_that(&checkout.join("src/lib.rs"), existing_file());
+}
+
+#[test]
+fn bad_fetch_strategy() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [git]
+            fetch-strategy = "shallow"
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]unknown git fetch strategy `shallow` in [..]config, expected `mirror` or `minimal`
+"));
+}
+
+#[test]
+fn url_instead_of() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+    let base = format!("{}/", path2url(bar.root().parent().unwrap().to_path_buf()));
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = "https://git.example.com/bar"
+        "#)
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file(".cargo/config", &format!(r#"
+            [git.url."{}"]
+            instead-of = "https://git.example.com/"
+        "#, base))
+        .build();
+
+    // The rewritten URL is only used for fetching, everything else sticks to
+    // the one in the manifest.
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("\
+[UPDATING] git repository `https://git.example.com/bar`
+[COMPILING] bar v0.5.0 (https://git.example.com/bar#[..])
+[COMPILING] foo v0.5.0 ([..])
+[FINISHED] [..]
+"));
+}
+
+#[test]
+fn url_bad_header() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = "https://git.example.com/bar"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [git.url."https://git.example.com/"]
+            headers = ["Authorization Bearer secret"]
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[..]invalid header `Authorization Bearer secret` in \
+`git.url.\"https://git.example.com/\".headers` (in [..]config), expected `Name: value`
+"));
+}
+
+#[test]
+fn checkouts_are_read_only() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .file(".cargo/config", r#"
+            [git]
+            read-only-checkouts = true
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    let checkout = git::checkouts().remove(0);
+    let lib = fs::metadata(checkout.join("src/lib.rs")).unwrap();
+    assert!(lib.permissions().readonly());
+    let src = fs::metadata(checkout.join("src")).unwrap();
+    assert!(src.permissions().readonly());
+
+    // A checkout which has to be reset is made read-only again afterwards.
+    fs::remove_file(checkout.join(".cargo-ok")).unwrap();
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&checkout.join(".cargo-ok"), existing_file());
+    let lib = fs::metadata(checkout.join("src/lib.rs")).unwrap();
+    assert!(lib.permissions().readonly());
+    let src = fs::metadata(checkout.join("src")).unwrap();
+    assert!(src.permissions().readonly());
+}
+
+#[test]
+fn checkouts_are_writable_by_default() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    let checkout = git::checkouts().remove(0);
+    let lib = fs::metadata(checkout.join("src/lib.rs")).unwrap();
+    assert!(!lib.permissions().readonly());
+}
+
+#[test]
+fn partial_checkout_is_replaced() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Nothing but the finished checkout is left behind.
+    let checkouts = paths::home().join(".cargo/git/checkouts");
+    let dir = fs::read_dir(&checkouts).unwrap().next().unwrap().unwrap().path();
+    let entries = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path())
+                                    .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 1, "{:?}", entries);
+    let checkout = entries[0].clone();
+    assert_that(&checkout.join(".cargo-ok"), existing_file());
+
+    // Simulate a checkout which was interrupted halfway through by an older
+    // Cargo, it has to be reset rather than used as is.
+    fs::remove_file(checkout.join(".cargo-ok")).unwrap();
+    fs::remove_file(checkout.join("src/lib.rs")).unwrap();
+
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&checkout.join(".cargo-ok"), existing_file());
+    assert_that(&checkout.join("src/lib.rs"), existing_file());
+    let entries = fs::read_dir(&dir).unwrap().count();
+    assert_eq!(entries, 1);
+}
+
+#[test]
+fn dependency_named_differently_than_package() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.baz]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(102).with_stderr(&format!("\
+[UPDATING] git repository `{url}`
+[ERROR] no matching package named `baz` found (required by `foo`)
+location searched: {url}
+version required: *
+the repository contains a package named `bar` instead, perhaps the dependency \
+declared at [..]Cargo.toml:7 was meant to be named `bar`?
+", url = bar.url())));
+}
+
+#[test]
+fn dependency_named_differently_in_target_table() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [target.'cfg(unix)']
+            dependencies.baz = {{ git = '{}' }}
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(102).with_stderr_contains("\
+the repository contains a package named `bar` instead, perhaps the dependency \
+declared at [..]Cargo.toml:8 was meant to be named `bar`?
+"));
+}
+
+#[test]
+fn git_dep_in_non_ascii_directory() {
+    let git_project = git::new("dépôt", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn dep1() {}")
+    }).unwrap();
+
+    let p = project("prøjekt")
+        .file("Cargo.toml", &format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#, git_project.url()))
+        .file("src/main.rs", "extern crate dep1; fn main() { dep1::dep1() }")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr(&format!("\
+[UPDATING] git repository `{url}`
+[COMPILING] dep1 v0.5.0 ({url}#[..])
+[COMPILING] foo v0.5.0 ([..]prøjekt)
+[FINISHED] [..]
+", url = git_project.url())));
+    assert_that(&p.bin("foo"), existing_file());
+}
+
+#[test]
+fn paths_with_spaces() {
+    let bar = git::new("bar with spaces", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    }).unwrap();
+
+    let p = project("foo with spaces")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    // Both checkout strategies run in a cargo home containing spaces, the
+    // archive one by shelling out to `git`.
+    for strategy in ["checkout", "archive"].iter() {
+        let home = paths::root().join(format!("cargo home {}", strategy));
+        assert_that(p.cargo("build")
+                     .env("CARGO_HOME", &home)
+                     .env("CARGO_GIT_CHECKOUT_STRATEGY", strategy),
+                    execs().with_status(0));
+
+        let checkout = git::checkouts_in(&home).remove(0);
+        assert_that(&checkout.join("src/lib.rs"), existing_file());
+        p.root().join("target").rm_rf();
+    }
+}
+
+#[test]
+fn dep_on_bare_repository() {
+    let bar = git::new_bare("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "pub fn bar() {}")
+    });
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, path2url(bar.clone())))
+        .file("src/lib.rs", "extern crate bar; pub fn foo() { bar::bar() }")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr(&format!("\
+[UPDATING] git repository `{url}`
+[COMPILING] bar v0.5.0 ({url}#[..])
+[COMPILING] foo v0.5.0 ([..])
+[FINISHED] [..]
+", url = path2url(bar))));
+}
+
+#[test]
+fn git_remote_from_path() {
+    let bar = git::new_bare("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    });
+    let head = git2::Repository::open(&bar).unwrap().head().unwrap().target().unwrap();
+
+    let remote = GitRemote::from_path(&bar).unwrap();
+    assert_eq!(*remote.url(), path2url(bar.clone()));
+    assert_eq!(remote, GitRemote::new(&path2url(bar.clone())));
+
+    let config = Config::new(Shell::new(), paths::root(), paths::home());
+    let db_path = paths::root().join("db");
+    let master = GitReference::Branch("master".to_string());
+    let db = remote.checkout(&db_path, &config).unwrap();
+    assert_eq!(db.rev_for(&master).unwrap().to_string(), head.to_string());
+
+    // Fetching into the existing database works as well.
+    remote.checkout(&db_path, &config).unwrap();
+    assert_eq!(remote.rev_for(&db_path, &master).unwrap().to_string(),
+               head.to_string());
+}
+
+#[test]
+fn source_policy_forbids_git() {
+    let git_project = git::new("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            dep1 = {{ git = '{}' }}
+        "#, git_project.url()))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [policy]
+            forbidden-sources = ["git"]
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains(&format!("\
+[ERROR] dependency `dep1` of `foo v0.5.0 ([..])` comes from the git repository \
+`{}`, but git dependencies are forbidden by `policy.forbidden-sources` (in \
+[..]config)
+", git_project.url())));
+}
+
+#[test]
+fn source_policy_allowed_git() {
+    let git_project = git::new("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            dep1 = {{ git = '{}' }}
+        "#, git_project.url()))
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [policy]
+            allowed-git = ["github.com/rust-lang"]
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains(&format!("\
+[ERROR] dependency `dep1` of `foo v0.5.0 ([..])` comes from the git repository \
+`{}`, which isn't under any of the locations in `policy.allowed-git` (in \
+[..]config)
+", git_project.url())));
+
+    // The repository's own location is allowed, as is anything under it.
+    let allowed = git_project.url().path().trim_right_matches('/').to_string();
+    File::create(p.root().join(".cargo/config")).unwrap()
+        .write_all(format!(r#"
+            [policy]
+            allowed-git = ["github.com/rust-lang", "file://{}"]
+        "#, allowed).as_bytes()).unwrap();
+    assert_that(p.cargo("build"), execs().with_status(0));
+}
@@ -0,0 +1,136 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::{project, execs, Project};
+use hamcrest::assert_that;
+
+/// `foo` depends on `a`, which depends on `b`, and on `b` itself with other
+/// features. It also has a build-dependency on `cc` and a dev-dependency on
+/// `d`.
+fn foo() -> Project {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b", default-features = false, features = ["std"] }
+
+            [build-dependencies]
+            cc = { path = "cc" }
+
+            [dev-dependencies]
+            d = { path = "d" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", r#"
+            [package]
+            name = "a"
+            version = "1.0.0"
+            authors = []
+
+            [dependencies]
+            b = { path = "../b" }
+        "#)
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", r#"
+            [package]
+            name = "b"
+            version = "1.0.0"
+            authors = []
+
+            [features]
+            default = []
+            std = []
+        "#)
+        .file("b/src/lib.rs", "")
+        .file("cc/Cargo.toml", r#"
+            [package]
+            name = "cc"
+            version = "1.0.0"
+            authors = []
+        "#)
+        .file("cc/src/lib.rs", "")
+        .file("d/Cargo.toml", r#"
+            [package]
+            name = "d"
+            version = "1.0.0"
+            authors = []
+        "#)
+        .file("d/src/lib.rs", "")
+        .build()
+}
+
+#[test]
+fn dot() {
+    let p = foo();
+
+    assert_that(p.cargo("graph"),
+                execs().with_status(0).with_stdout("\
+digraph dependencies {
+    N0 [label=\"a v1.0.0\"];
+    N1 [label=\"b v1.0.0\\n[default, std]\"];
+    N2 [label=\"cc v1.0.0\"];
+    N3 [label=\"d v1.0.0\"];
+    N4 [label=\"foo v0.1.0\"];
+    N4 -> N0 [label=\"*\"];
+    N4 -> N1 [label=\"*\\n[no-default-features, std]\"];
+    N4 -> N2 [label=\"*\", style=dotted];
+    N4 -> N3 [label=\"*\", style=dashed];
+    N0 -> N1 [label=\"*\"];
+}
+"));
+}
+
+#[test]
+fn filters() {
+    let p = foo();
+
+    assert_that(p.cargo("graph")
+                 .arg("--no-dev-dependencies")
+                 .arg("--no-build-dependencies")
+                 .arg("--depth").arg("1"),
+                execs().with_status(0).with_stdout("\
+digraph dependencies {
+    N0 [label=\"a v1.0.0\"];
+    N1 [label=\"b v1.0.0\\n[default, std]\"];
+    N2 [label=\"foo v0.1.0\"];
+    N2 -> N0 [label=\"*\"];
+    N2 -> N1 [label=\"*\\n[no-default-features, std]\"];
+}
+"));
+
+    assert_that(p.cargo("graph")
+                 .arg("--format").arg("json")
+                 .arg("--focus").arg("a"),
+                execs().with_status(0).with_json(r#"
+                    {
+                      "nodes": [
+                        { "id": "a 1.0.0 [..]", "name": "a", "version": "1.0.0",
+                          "source": "[..]", "features": [] },
+                        { "id": "b 1.0.0 [..]", "name": "b", "version": "1.0.0",
+                          "source": "[..]", "features": ["default", "std"] },
+                        { "id": "foo 0.1.0 [..]", "name": "foo", "version": "0.1.0",
+                          "source": "[..]", "features": [] }
+                      ],
+                      "edges": [
+                        { "from": "foo 0.1.0 [..]", "to": "a 1.0.0 [..]",
+                          "kind": "normal", "req": "*", "features": [],
+                          "uses_default_features": true },
+                        { "from": "foo 0.1.0 [..]", "to": "b 1.0.0 [..]",
+                          "kind": "normal", "req": "*", "features": ["std"],
+                          "uses_default_features": false },
+                        { "from": "a 1.0.0 [..]", "to": "b 1.0.0 [..]",
+                          "kind": "normal", "req": "*", "features": [],
+                          "uses_default_features": true }
+                      ]
+                    }
+                "#));
+
+    assert_that(p.cargo("graph").arg("--focus").arg("nope"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] package id specification `nope` matched no packages"));
+}
@@ -0,0 +1,387 @@
+#[macro_use]
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+use cargotest::support::registry::{self, Package};
+use cargotest::support::{paths, project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn download() {
+    let server = registry::serve();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+[DOWNLOADING] bar v0.0.1 (registry `file://[..]`)
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ({dir})
+[FINISHED] [..]
+",
+        dir = p.url(),
+        reg = registry::registry())));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1, "{:?}", requests);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/bar/0.0.1/download");
+
+    // Downloaded crates are cached.
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn checksum_mismatch() {
+    let _server = registry::serve();
+    let pkg = Package::new("bar", "0.0.1");
+    pkg.publish();
+    t!(File::create(&pkg.archive_dst()));
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+  failed to verify the checksum of `bar v0.0.1 (registry `file://[..]`)`"));
+
+    // Nothing unverified is left behind, in the cache or to resume from.
+    let cache = t!(t!(fs::read_dir(paths::home().join(".cargo/registry/cache")))
+                   .next().unwrap()).path();
+    assert!(!cache.join("bar-0.0.1.crate").exists());
+    assert!(!cache.join("bar-0.0.1.crate.part").exists());
+}
+
+#[test]
+fn missing_crate_file() {
+    let server = registry::serve();
+    Package::new("bar", "0.0.1").publish();
+    t!(fs::remove_file(registry::dl_path().join("bar/0.0.1/download")));
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains(&format!("\
+  failed to get 200 response from `{}bar/0.0.1/download`, got 404",
+        server.url())));
+}
+
+#[test]
+fn yanked_versions_are_not_downloaded() {
+    let server = registry::serve();
+    Package::new("bar", "0.0.1").publish();
+    Package::new("bar", "0.0.2").yanked(true).publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let paths = server.requests().into_iter().map(|r| r.path).collect::<Vec<_>>();
+    assert_eq!(paths, vec!["/bar/0.0.1/download".to_string()]);
+}
+
+#[test]
+fn publish_and_yank() {
+    let server = registry::serve();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("publish").arg("--no-verify")
+                 .arg("--index").arg(registry::registry().to_string()),
+                execs().with_status(0)
+                       .with_stderr_contains("[UPLOADING] foo v0.0.1 ([..])"));
+
+    assert_that(p.cargo("yank").arg("--vers").arg("0.0.1")
+                 .arg("--index").arg(registry::registry().to_string()),
+                execs().with_status(0));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2, "{:?}", requests);
+    assert_eq!(requests[0].method, "PUT");
+    assert_eq!(requests[0].path, "/api/v1/crates/new");
+    assert_eq!(requests[0].header("Authorization"), Some("api-token"));
+    assert!(!requests[0].body.is_empty());
+    assert_eq!(requests[1].method, "DELETE");
+    assert_eq!(requests[1].path, "/api/v1/crates/foo/0.0.1/yank");
+    assert_eq!(requests[1].header("Authorization"), Some("api-token"));
+}
+
+#[test]
+fn parallel_downloads() {
+    let server = registry::serve();
+    for name in ["a", "b", "c", "d", "e"].iter() {
+        Package::new(name, "0.1.0").publish();
+    }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            a = "0.1"
+            b = "0.1"
+            c = "0.1"
+            d = "0.1"
+            e = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").env("CARGO_HTTP_MAX_PARALLEL_DOWNLOADS", "2"),
+                execs().with_status(0)
+                       .with_stderr_contains("[DOWNLOADING] e v0.1.0 [..]"));
+    let mut paths = server.requests().into_iter().map(|r| r.path).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, vec!["/a/0.1.0/download", "/b/0.1.0/download",
+                           "/c/0.1.0/download", "/d/0.1.0/download",
+                           "/e/0.1.0/download"]);
+}
+
+#[test]
+fn resume_partial_download() {
+    let server = registry::serve();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Pretend the download was cut off after 10 bytes.
+    let cache = t!(t!(fs::read_dir(paths::home().join(".cargo/registry/cache")))
+                   .next().unwrap()).path();
+    let crate_file = cache.join("bar-0.0.1.crate");
+    let contents = read(&crate_file);
+    t!(fs::remove_file(&crate_file));
+    t!(t!(File::create(cache.join("bar-0.0.1.crate.part"))).write_all(&contents[..10]));
+
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr_contains("[DOWNLOADING] bar v0.0.1 [..]"));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2, "{:?}", requests);
+    assert_eq!(requests[1].header("Range"), Some("bytes=10-"));
+    assert_eq!(read(&crate_file), contents);
+    assert!(!cache.join("bar-0.0.1.crate.part").exists());
+}
+
+#[test]
+fn sparse_index() {
+    let server = registry::serve_sparse();
+    Package::new("bar", "0.0.1").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `sparse+http://[..]/registry`
+[DOWNLOADING] bar v0.0.1 (registry `sparse+http://[..]/registry`)
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ({dir})
+[FINISHED] [..]
+",
+        dir = p.url())));
+
+    // Only what's needed is fetched from the index.
+    let mut paths = server.requests().into_iter().map(|r| r.path).collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, vec!["/dl/bar/0.0.1/download",
+                           "/registry/3/b/bar",
+                           "/registry/config.json"]);
+
+    // Building from the lock file again doesn't need the index at all.
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn sparse_index_conditional_requests() {
+    let server = registry::serve_sparse();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    // Nothing changed, so the cached copy is still good.
+    assert_that(p.cargo("update"), execs().with_status(0).with_stderr("\
+[UPDATING] registry `sparse+http://[..]/registry`
+"));
+    let requests = server.requests();
+    let last = requests.last().unwrap();
+    assert_eq!(last.path, "/registry/3/b/bar");
+    assert!(last.header("If-None-Match").is_some());
+
+    Package::new("bar", "0.0.2").publish();
+    assert_that(p.cargo("update"), execs().with_status(0).with_stderr("\
+[UPDATING] registry `sparse+http://[..]/registry`
+[UPDATING] bar v0.0.1 -> v0.0.2
+"));
+}
+
+#[test]
+fn sparse_index_missing_crate() {
+    let _server = registry::serve_sparse();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            nope = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] no matching package named `nope` found (required by `foo`)"));
+}
+
+#[test]
+fn resume_from_corrupt_partial_download() {
+    let server = registry::serve();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // What was received before the download was cut off got garbled.
+    let cache = t!(t!(fs::read_dir(paths::home().join(".cargo/registry/cache")))
+                   .next().unwrap()).path();
+    let crate_file = cache.join("bar-0.0.1.crate");
+    let contents = read(&crate_file);
+    t!(fs::remove_file(&crate_file));
+    t!(t!(File::create(cache.join("bar-0.0.1.crate.part"))).write_all(&[0; 10]));
+
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+  failed to verify the checksum of `bar v0.0.1 (registry `file://[..]`)`"));
+    assert!(!crate_file.exists());
+    assert!(!cache.join("bar-0.0.1.crate.part").exists());
+
+    // Starting over works.
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_eq!(read(&crate_file), contents);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3, "{:?}", requests);
+    assert_eq!(requests[1].header("Range"), Some("bytes=10-"));
+    assert_eq!(requests[2].header("Range"), None);
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    let mut contents = Vec::new();
+    t!(t!(File::open(path)).read_to_end(&mut contents));
+    contents
+}
@@ -0,0 +1,113 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::cargo_process;
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn from_registry() {
+    Package::new("baz", "1.0.0").publish();
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0")
+        .dep("baz", "^1.0")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.2.0"
+            authors = []
+            description = "A bar"
+            license = "MIT"
+            repository = "https://example.com/bar"
+
+            [dependencies]
+            baz = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .publish();
+    Package::new("bar", "0.3.0-alpha").publish();
+
+    assert_that(cargo_process().arg("info").arg("bar"),
+                execs().with_status(0).with_stderr("\
+[UPDATING] registry `[..]`
+[DOWNLOADING] bar v0.2.0 (registry `[..]`)
+").with_stdout("\
+bar v0.2.0 (registry `[..]`)
+A bar
+license: MIT
+repository: https://example.com/bar
+versions: 0.3.0-alpha, 0.2.0, 0.1.0
+dependencies:
+  baz ^1.0
+"));
+
+    assert_that(cargo_process().arg("info").arg("bar@0.1"),
+                execs().with_status(0).with_stdout("\
+bar v0.1.0 (registry `[..]`)
+versions: 0.3.0-alpha, 0.2.0, 0.1.0
+"));
+
+    assert_that(cargo_process().arg("info").arg("bar@2"),
+                execs().with_status(101).with_stderr("\
+[UPDATING] registry `[..]`
+[ERROR] no version of `bar` matches `[..]2`, the available versions are \
+0.3.0-alpha, 0.2.0, 0.1.0
+"));
+
+    assert_that(cargo_process().arg("info").arg("qux"),
+                execs().with_status(101).with_stderr("\
+[UPDATING] registry `[..]`
+[ERROR] the package `qux` could not be found in registry `[..]`
+"));
+}
+
+#[test]
+fn from_dependency_graph() {
+    Package::new("bar", "0.6.0").publish();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+            license = "MIT OR Apache-2.0"
+
+            [features]
+            default = ["std"]
+            std = []
+
+            [target.'cfg(unix)'.dependencies]
+            baz = { path = "../baz", optional = true }
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("info").arg("bar"),
+                execs().with_status(0).with_stdout(&format!("\
+bar v0.5.0 ({url}/bar)
+license: MIT OR Apache-2.0
+features:
+  default = [\"std\"]
+  std = []
+dependencies:
+  baz * ({url}/baz) for cfg(unix) (optional)
+", url = p.url())));
+}
@@ -0,0 +1,126 @@
+#[macro_use]
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargotest::support::paths;
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs, Project};
+use hamcrest::{assert_that, existing_file, is_not};
+
+fn foo() -> Project {
+    Package::new("bar", "0.1.0").publish();
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build()
+}
+
+fn read(path: &str) -> String {
+    let mut contents = String::new();
+    t!(t!(File::open(path)).read_to_string(&mut contents));
+    contents
+}
+
+fn write(path: &str, contents: &str) {
+    t!(t!(File::create(path)).write_all(contents.as_bytes()));
+}
+
+#[test]
+fn stamps_target_dir_and_home() {
+    let p = foo();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let target = p.root().join("target/.layout-version");
+    let home = paths::home().join(".cargo/.layout-version");
+    assert_eq!(read(target.to_str().unwrap()), "1\n");
+    assert_eq!(read(home.to_str().unwrap()), "1\n");
+}
+
+#[test]
+fn home_is_only_stamped_when_modified() {
+    let p = foo();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let home = paths::home().join(".cargo/.layout-version");
+    t!(fs::remove_file(&home));
+
+    // Everything is in the caches already, so they're just read.
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&home, is_not(existing_file()));
+
+    assert_that(p.cargo("update"), execs().with_status(0));
+    assert_eq!(read(home.to_str().unwrap()), "1\n");
+}
+
+#[test]
+fn unversioned_target_dir_is_kept() {
+    let p = foo();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    t!(fs::remove_file(p.root().join("target/.layout-version")));
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr("\
+[FINISHED] [..]
+"));
+    assert_that(&p.root().join("target/.layout-version"), existing_file());
+}
+
+#[test]
+fn newer_target_dir_is_refused() {
+    let p = foo();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let stamp = p.root().join("target/.layout-version");
+    write(stamp.to_str().unwrap(), "2\n");
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] the target directory `[..]target` has layout version 2, but this \
+version of Cargo only understands version 1
+
+It was last used by a newer version of Cargo. Keep using that one, or run \
+`cargo clean` to build everything again with this one.
+"));
+    assert_eq!(read(stamp.to_str().unwrap()), "2\n");
+
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
+}
+
+#[test]
+fn newer_home_is_refused() {
+    let p = foo();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let stamp = paths::home().join(".cargo/.layout-version");
+    write(stamp.to_str().unwrap(), "7\n");
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] the Cargo home `[..].cargo` has layout version 7, but this version of \
+Cargo only understands version 1
+
+It was last used by a newer version of Cargo. Keep using that one, or remove \
+its `git` and `registry` directories to download everything again.
+"));
+}
+
+#[test]
+fn bad_stamp() {
+    let p = foo();
+    assert_that(p.cargo("build"), execs().with_status(0));
+    write(p.root().join("target/.layout-version").to_str().unwrap(), "new");
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] failed to parse the layout version in `[..].layout-version`
+"));
+}
@@ -0,0 +1,147 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use cargotest::support::{project, execs, Project};
+use hamcrest::assert_that;
+
+/// `foo` depends on `bar` and `baz`, and has a dev-dependency on `qux`.
+fn foo() -> Project {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            baz = { path = "baz" }
+
+            [dev-dependencies]
+            qux = { path = "qux" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = ["Jane Doe <jane@example.com>", "John Doe"]
+            license = "MIT OR Apache-2.0"
+            repository = "https://example.com/bar"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.2.0"
+            authors = []
+            license-file = "LICENSE"
+        "#)
+        .file("baz/src/lib.rs", "")
+        .file("qux/Cargo.toml", r#"
+            [package]
+            name = "qux"
+            version = "0.3.0"
+            authors = []
+            license = "GPL-3.0"
+        "#)
+        .file("qux/src/lib.rs", "")
+        .build()
+}
+
+#[test]
+fn text_report() {
+    let p = foo();
+    assert_that(p.cargo("license"),
+                execs().with_status(0).with_stdout("\
+Name   Version   License             Repository
+bar    0.1.0     MIT OR Apache-2.0   https://example.com/bar
+baz    0.2.0     see LICENSE         ---
+foo    0.0.1     ---                 ---
+"));
+}
+
+#[test]
+fn json_report() {
+    let p = foo();
+    assert_that(p.cargo("license").arg("--format").arg("json"),
+                execs().with_status(0).with_json(r#"
+        {
+            "packages": [
+                {
+                    "name": "bar",
+                    "version": "0.1.0",
+                    "source": "[..]",
+                    "license": "MIT OR Apache-2.0",
+                    "license_file": null,
+                    "authors": ["Jane Doe <jane@example.com>", "John Doe"],
+                    "repository": "https://example.com/bar"
+                },
+                {
+                    "name": "baz",
+                    "version": "0.2.0",
+                    "source": "[..]",
+                    "license": null,
+                    "license_file": "LICENSE",
+                    "authors": [],
+                    "repository": null
+                },
+                {
+                    "name": "foo",
+                    "version": "0.0.1",
+                    "source": "[..]",
+                    "license": null,
+                    "license_file": null,
+                    "authors": [],
+                    "repository": null
+                }
+            ]
+        }
+    "#));
+}
+
+#[test]
+fn csv_report() {
+    let p = foo();
+    assert_that(p.cargo("license").arg("--format").arg("csv"),
+                execs().with_status(0).with_stdout("\
+name,version,source,license,license_file,authors,repository
+bar,0.1.0,[..],MIT OR Apache-2.0,,Jane Doe <jane@example.com>; John Doe,https://example.com/bar
+baz,0.2.0,[..],,LICENSE,,
+foo,0.0.1,[..],,,,
+"));
+}
+
+#[test]
+fn disallowed_licenses() {
+    let p = foo();
+    assert_that(p.cargo("license"), execs().with_status(0));
+
+    // `foo` itself isn't checked, and `qux` is only a dev-dependency.
+    fs::create_dir_all(p.root().join(".cargo")).unwrap();
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [license]
+        allowed = ["MIT"]
+    "#).unwrap();
+    assert_that(p.cargo("license"),
+                execs().with_status(101).with_stdout_contains("bar [..]").with_stderr("\
+[ERROR] 1 dependency is licensed under something not listed in `license.allowed`:
+  baz v0.2.0 (only a license file, `LICENSE`)
+"));
+
+    File::create(p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [license]
+        allowed = ["Apache-2.0", "LicenseRef-baz"]
+    "#).unwrap();
+    File::create(p.root().join("baz/Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "baz"
+        version = "0.2.0"
+        authors = []
+        license = "LicenseRef-baz"
+    "#).unwrap();
+    assert_that(p.cargo("license"), execs().with_status(0));
+}
@@ -402,3 +402,35 @@ fn crates_io_registry_url_is_optional() {
 "));
     assert_that(p.cargo("test"), execs().with_status(0));
 }
+
+#[test]
+fn search() {
+    setup();
+    Package::new("foo", "0.0.1").local(true).publish();
+    Package::new("foo", "0.1.0").local(true).publish();
+    Package::new("foo-bar", "0.2.0").local(true).publish();
+    Package::new("bar-foo", "0.3.0").local(true).publish();
+    Package::new("baz", "0.4.0").local(true).publish();
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("search").arg("foo"),
+                execs().with_status(0).with_stdout("\
+foo = \"0.1.0\"
+bar-foo = \"0.3.0\"
+foo-bar = \"0.2.0\"
+"));
+    assert_that(p.cargo("search").arg("foo").arg("bar").arg("--limit").arg("1"),
+                execs().with_status(0).with_stdout("\
+bar-foo = \"0.3.0\"
+... and 1 crates more (use --limit N to see more)
+"));
+}
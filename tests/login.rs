@@ -7,8 +7,8 @@ extern crate toml;
 use std::io::prelude::*;
 use std::fs::{self, File};
 
-use cargotest::cargo_process;
-use cargotest::support::execs;
+use cargotest::{cargo_process, ChannelChanger};
+use cargotest::support::{execs, project, basic_bin_manifest};
 use cargotest::support::registry::registry;
 use cargotest::install::cargo_home;
 use cargo::util::config::Config;
@@ -126,3 +126,121 @@ fn new_credentials_is_used_instead_old() {
     let token = config.get_string("registry.token").unwrap().map(|p| p.val);
     assert_eq!(token.unwrap(), TOKEN);
 }
+
+#[test]
+fn login_with_credential_helper() {
+    // Logs how it's invoked and keeps the token in a file next to itself.
+    let helper = project("helper")
+        .file("Cargo.toml", &basic_bin_manifest("helper"))
+        .file("src/main.rs", r#"
+            use std::env;
+            use std::fs::{File, OpenOptions};
+            use std::io::prelude::*;
+
+            fn main() {
+                let dir = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+                let args = env::args().skip(1).collect::<Vec<_>>();
+                let mut log = OpenOptions::new().create(true).append(true)
+                    .open(dir.join("log")).unwrap();
+                writeln!(log, "{}", args.join(" ")).unwrap();
+                match &args[0][..] {
+                    "get" => {
+                        let mut token = String::new();
+                        if let Ok(mut f) = File::open(dir.join("token")) {
+                            f.read_to_string(&mut token).unwrap();
+                        }
+                        print!("{}", token);
+                    }
+                    "store" => {
+                        let mut token = String::new();
+                        std::io::stdin().read_to_string(&mut token).unwrap();
+                        File::create(dir.join("token")).unwrap()
+                            .write_all(token.as_bytes()).unwrap();
+                    }
+                    _ => panic!("unexpected arguments {:?}", args),
+                }
+            }
+        "#)
+        .build();
+    assert_that(helper.cargo("build"), execs().with_status(0));
+    let bin = helper.bin("helper");
+
+    for _ in 0..2 {
+        assert_that(cargo_process().arg("login")
+                    .arg("--host").arg(registry().to_string()).arg(TOKEN)
+                    .env("CARGO_REGISTRY_CREDENTIAL_PROVIDER", &bin),
+                    execs().with_status(0));
+    }
+
+    let dir = bin.parent().unwrap();
+    let mut contents = String::new();
+    File::open(dir.join("token")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, TOKEN);
+
+    // The second login finds the token already stored.
+    contents.clear();
+    File::open(dir.join("log")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "get crates-io\nstore crates-io\nget crates-io\n");
+
+    assert_that(&cargo_home().join("credentials"), is_not(existing_file()));
+
+    // A token from an earlier login moves out of the credentials file.
+    t!(fs::remove_file(dir.join("token")));
+    setup_new_credentials();
+    assert_that(cargo_process().arg("login")
+                .arg("--host").arg(registry().to_string()).arg(TOKEN)
+                .env("CARGO_REGISTRY_CREDENTIAL_PROVIDER", &bin),
+                execs().with_status(0));
+    contents.clear();
+    File::open(dir.join("token")).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, TOKEN);
+    contents.clear();
+    File::open(&cargo_home().join("credentials")).unwrap()
+        .read_to_string(&mut contents).unwrap();
+    let toml: toml::Value = contents.parse().unwrap();
+    assert_eq!(toml.get("token"), None);
+}
+
+#[test]
+fn login_with_registry_name() {
+    let config = cargo_home().join("config");
+    t!(fs::create_dir_all(config.parent().unwrap()));
+    t!(t!(File::create(&config)).write_all(br#"
+        [registry]
+        credential-provider = "file"
+
+        [registries.alternative]
+        index = "https://example.com/index"
+    "#));
+
+    // `--registry` isn't stable yet.
+    assert_that(cargo_process().arg("login")
+                .arg("--registry").arg("alternative").arg(TOKEN),
+                execs().with_status(101).with_stderr("\
+[ERROR] the `--registry` flag is unstable, pass `-Z unstable-options` to enable it
+"));
+
+    assert_that(cargo_process().arg("login").masquerade_as_nightly_cargo()
+                .arg("--registry").arg("alternative").arg(TOKEN)
+                .arg("-Zunstable-options"),
+                execs().with_status(0));
+
+    // The token is kept apart from the one of crates.io.
+    let credentials = cargo_home().join("credentials");
+    let mut contents = String::new();
+    File::open(&credentials).unwrap().read_to_string(&mut contents).unwrap();
+    let toml: toml::Value = contents.parse().unwrap();
+    assert!(!check_host_token(toml.clone()));
+    assert_eq!(toml["registries"]["alternative"]["token"].as_str(), Some(TOKEN));
+
+    let config = Config::new(Shell::new(), cargo_home(), cargo_home());
+    let token = config.get_string("registries.alternative.token").unwrap().map(|p| p.val);
+    assert_eq!(token.unwrap(), TOKEN);
+
+    assert_that(cargo_process().arg("login").masquerade_as_nightly_cargo()
+                .arg("--registry").arg("nope").arg(TOKEN)
+                .arg("-Zunstable-options"),
+                execs().with_status(101).with_stderr("\
+[ERROR] no index found for registry: `nope`, it's configured with `registries.nope.index`
+"));
+}
@@ -25,7 +25,7 @@ fn net_retry_loads_from_config() {
         .build();
 
     assert_that(p.cargo("build").arg("-v"),
-                execs().with_status(101)
+                execs().with_status(103)
                 .with_stderr_contains("[WARNING] spurious network error \
 (1 tries remaining): [..]"));
 }
@@ -50,7 +50,7 @@ fn net_retry_git_outputs_warning() {
         .build();
 
     assert_that(p.cargo("build").arg("-v").arg("-j").arg("1"),
-                execs().with_status(101)
+                execs().with_status(103)
                 .with_stderr_contains("[WARNING] spurious network error \
 (2 tries remaining): [..]")
                 .with_stderr_contains("\
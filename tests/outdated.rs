@@ -0,0 +1,105 @@
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+
+use std::fs::File;
+use std::io::prelude::*;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn up_to_date() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("")
+                       .with_stderr_contains("[FINISHED] all dependencies are up to date"));
+}
+
+#[test]
+fn newer_registry_versions() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    Package::new("bar", "0.1.1").publish();
+    Package::new("bar", "0.2.0").publish();
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name   Locked   Compatible   Latest
+bar    0.1.0    0.1.1        0.2.0
+"));
+
+    // Nothing was changed.
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout_contains("bar    0.1.0 [..]"));
+}
+
+#[test]
+fn newer_git_revision() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = {{ git = '{}' }}
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0));
+
+    File::create(&bar.root().join("src/lib.rs")).unwrap()
+        .write_all(b"pub fn bar() {}").unwrap();
+    let repo = git2::Repository::open(&bar.root()).unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    assert_that(p.cargo("outdated"),
+                execs().with_status(0).with_stdout("\
+Name   Locked     Compatible   Latest
+bar    [..]   [..]     [..]
+"));
+}
@@ -476,7 +476,7 @@ fn override_wrong_name() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [UPDATING] registry [..]
 [UPDATING] git repository [..]
 error: no matching package for override `[..]foo:0.1.0` found
@@ -581,7 +581,7 @@ fn multiple_specs() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [UPDATING] registry [..]
 [UPDATING] git repository [..]
 error: overlapping replacement specifications found:
@@ -1271,7 +1271,7 @@ fn override_plus_dep() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 error: cyclic package dependency: [..]
 "));
 }
@@ -3,6 +3,7 @@ extern crate cargo;
 extern crate cargotest;
 extern crate hamcrest;
 
+use std::env;
 use std::fs::{self, File};
 use std::io::prelude::*;
 
@@ -948,7 +949,7 @@ fn invalid_path_dep_in_workspace_with_lockfile() {
     // Make sure we get a nice error. In the past this actually stack
     // overflowed!
     assert_that(p.cargo("build"),
-                execs().with_status(101)
+                execs().with_status(102)
                        .with_stderr("\
 error: no matching package named `bar` found (required by `foo`)
 location searched: [..]
@@ -986,3 +987,171 @@ fn workspace_produces_rlib() {
     assert_that(&p.root().join("target/debug/libfoo.rlib"), existing_file());
 
 }
+
+#[test]
+fn two_dylibs_with_the_same_name() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            baz = { path = "baz" }
+        "#)
+        .file("src/lib.rs", r#"
+            extern crate bar;
+            extern crate baz;
+            pub fn foo() { bar::v1(); baz::v2(); }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [lib]
+            crate-type = ["dylib", "rlib"]
+        "#)
+        .file("bar/src/lib.rs", "pub fn v1() {}")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("baz/src/lib.rs", r#"
+            extern crate bar;
+            pub fn v2() { bar::v2() }
+        "#)
+        .file("baz/bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.2.0"
+            authors = []
+
+            [lib]
+            crate-type = ["dylib", "rlib"]
+        "#)
+        .file("baz/bar/src/lib.rs", "pub fn v2() {}")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    // Each version gets its own dylib rather than one overwriting the other.
+    let prefix = format!("{}bar-", env::consts::DLL_PREFIX);
+    let dylibs = fs::read_dir(p.root().join("target/debug/deps")).unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| {
+            name.starts_with(&prefix) && name.ends_with(env::consts::DLL_SUFFIX)
+        })
+        .count();
+    assert_eq!(dylibs, 2);
+}
+
+#[test]
+fn non_ascii_project_directories() {
+    let p = project("ünïcödé")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+
+            [dependencies]
+            bar = { path = "dépendance" }
+        "#)
+        .file("build.rs", r#"
+            use std::env;
+            use std::fs::File;
+            use std::path::Path;
+
+            fn main() {
+                let out = env::var_os("OUT_DIR").unwrap();
+                File::create(Path::new(&out).join("generated.rs")).unwrap();
+            }
+        "#)
+        .file("src/main.rs", r#"
+            include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+
+            extern crate bar;
+
+            fn main() { bar::bar(); }
+        "#)
+        .file("dépendance/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("dépendance/src/lib.rs", "pub fn bar() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("\
+[COMPILING] bar v0.0.1 ([..]ünïcödé/dépendance)
+[COMPILING] foo v0.0.1 ([..]ünïcödé)
+[FINISHED] [..]
+"));
+    assert_that(&p.bin("foo"), existing_file());
+
+    // Nothing changed, so the fingerprints of the path dependency must match.
+    assert_that(p.cargo("build"),
+                execs().with_status(0)
+                       .with_stderr("[FINISHED] [..]"));
+}
+
+#[test]
+fn source_policy_forbids_path() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [workspace]
+            members = ["bar"]
+
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            baz = { path = "../baz" }
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [policy]
+            forbidden-sources = ["path"]
+        "#)
+        .build();
+    let _baz = project("baz")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    // `bar` is a member of the workspace, so only `baz` is a problem.
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] dependency `baz` of `foo v0.5.0 ([..])` comes from the path `[..]baz`, \
+but path dependencies are forbidden by `policy.forbidden-sources` (in \
+[..]config)
+"));
+}
@@ -2,8 +2,10 @@ extern crate cargotest;
 extern crate hamcrest;
 
 use std::env;
+use std::fs::File;
+use std::io::Write;
 
-use cargotest::is_nightly;
+use cargotest::{is_nightly, rustc_host};
 use cargotest::support::{project, execs};
 use hamcrest::assert_that;
 
@@ -27,7 +29,7 @@ fn profile_overrides() {
     assert_that(p.cargo("build").arg("-v"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C opt-level=1 \
         -C debug-assertions=on \
@@ -60,7 +62,7 @@ fn opt_level_override_0() {
     assert_that(p.cargo("build").arg("-v"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C debuginfo=2 \
         -C metadata=[..] \
@@ -90,7 +92,7 @@ fn debug_override_1() {
     assert_that(p.cargo("build").arg("-v"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C debuginfo=1 \
         -C metadata=[..] \
@@ -120,7 +122,7 @@ fn check_opt_level_override(profile_level: &str, rustc_level: &str) {
     assert_that(p.cargo("build").arg("-v"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C opt-level={level} \
         -C debuginfo=2 \
@@ -189,7 +191,7 @@ fn top_level_overrides_deps() {
     assert_that(p.cargo("build").arg("-v").arg("--release"),
                 execs().with_status(0).with_stderr(&format!("\
 [COMPILING] foo v0.0.0 ({url}/foo)
-[RUNNING] `rustc --crate-name foo foo[/]src[/]lib.rs \
+[RUNNING] `rustc --crate-name foo foo[/]src[/]lib.rs --error-format json \
         --crate-type dylib --crate-type rlib \
         --emit=dep-info,link \
         -C prefer-dynamic \
@@ -199,7 +201,7 @@ fn top_level_overrides_deps() {
         --out-dir {dir}[/]target[/]release[/]deps \
         -L dependency={dir}[/]target[/]release[/]deps`
 [COMPILING] test v0.0.0 ({url})
-[RUNNING] `rustc --crate-name test src[/]lib.rs --crate-type lib \
+[RUNNING] `rustc --crate-name test src[/]lib.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C opt-level=1 \
         -C debuginfo=2 \
@@ -284,3 +286,417 @@ fn profile_in_virtual_manifest_works() {
 [RUNNING] `rustc [..]`
 [FINISHED] dev [optimized] target(s) in [..]"));
 }
+
+#[test]
+fn package_overrides() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            baz = { path = "baz" }
+
+            [profile.dev.package."*"]
+            opt-level = 3
+
+            [profile.dev.package.baz]
+            opt-level = 1
+            debug = false
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]-C opt-level=3 -C debuginfo=2 [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name baz baz[/]src[/]lib.rs [..]-C opt-level=1 -C debug-assertions=on [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]lib.rs --error-format json --crate-type lib \
+        --emit=dep-info,link -C debuginfo=2 [..]`"));
+
+    // The overrides are part of the fingerprint.
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] bar [..]"));
+    p.change_file("Cargo.toml", r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = { path = "bar" }
+        baz = { path = "baz" }
+
+        [profile.dev.package."*"]
+        opt-level = 2
+    "#);
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]-C opt-level=2 [..]`"));
+}
+
+#[test]
+fn package_overrides_release() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.release.package.bar]
+            opt-level = 0
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build").arg("-v").arg("--release"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs --error-format json --crate-type lib \
+        --emit=dep-info,link -C debug-assertions=off -C metadata=[..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]lib.rs [..]-C opt-level=3 [..]`"));
+}
+
+#[test]
+fn package_overrides_reject_shared_settings() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev.package."*"]
+            panic = "abort"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `panic` can't be set in `[profile.dev.package.\"*\"]`, it has to be the same \
+for every package
+"));
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [profile.test.package."*"]
+            opt-level = 3
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `[profile.test.package]` isn't supported, only the `dev` and `release` profiles \
+can be overridden for some packages
+"));
+}
+
+#[test]
+fn split_debuginfo() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev]
+            split-debuginfo = "unpacked"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Older versions of rustc don't know the flag, so only the invocation is
+    // checked.
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]main.rs [..]-C debuginfo=2 \
+    -C split-debuginfo=unpacked [..]`"));
+}
+
+#[test]
+fn strip_tool() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev]
+            strip = "debuginfo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            strip = "nonexistent-strip"
+        "#, rustc_host()))
+        .build();
+
+    if cfg!(target_env = "msvc") {
+        return
+    }
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[RUNNING] `nonexistent-strip -S [..][/]target[/]debug[/]deps[/]foo[EXE]`")
+                       .with_stderr_does_not_contain("[RUNNING] `nonexistent-strip [..]rlib`"));
+}
+
+#[test]
+fn strip_changes_rebuild() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+
+    File::create(&p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [profile.dev]
+        strip = true
+    "#).unwrap();
+
+    if cfg!(target_env = "msvc") {
+        return
+    }
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[COMPILING] foo v0.0.1 ([..])")
+                       .with_stderr_contains("[RUNNING] `strip [..]foo[EXE]`"));
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] foo v0.0.1 ([..])"));
+}
+
+#[test]
+fn bad_strip_value() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release]
+            strip = "all"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `strip` in `[profile.release]` must be `true`, `false`, `\"none\"`, `\"debuginfo\"` \
+or `\"symbols\"`, but found `\"all\"`
+"));
+}
+
+#[test]
+fn thin_lto() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+
+            [profile.release]
+            lto = "thin"
+            codegen-units = 4
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    // Older versions of rustc don't know thin LTO, so only the invocations
+    // are checked.
+    assert_that(p.cargo("build").arg("--release").arg("-v"),
+                execs().with_stderr_contains("\
+[RUNNING] `rustc --crate-name bar bar[/]src[/]lib.rs [..]-C opt-level=3 \
+    -C codegen-units=4 [..]`")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]main.rs [..]-C opt-level=3 -C lto=thin \
+    -C codegen-units=4 [..]`"));
+}
+
+#[test]
+fn toggling_lto_only_rebuilds_final_artifacts() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--release"), execs().with_status(0));
+
+    File::create(&p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = { path = "bar" }
+
+        [profile.release]
+        lto = true
+    "#).unwrap();
+
+    assert_that(p.cargo("build").arg("--release").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[FRESH] bar v0.0.1 ([..])")
+                       .with_stderr_contains("[COMPILING] foo v0.0.1 ([..])")
+                       .with_stderr_contains("\
+[RUNNING] `rustc --crate-name foo src[/]main.rs [..]-C lto [..]`"));
+}
+
+#[test]
+fn bad_lto_and_codegen_units() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [profile.release]
+            lto = "full"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `lto` in `[profile.release]` must be `true`, `false`, `\"thin\"`, `\"fat\"` or \
+`\"off\"`, but found `\"full\"`
+"));
+
+    let p = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [profile.dev.package."*"]
+            codegen-units = 0
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `codegen-units` in `[profile.dev]` must be at least 1
+"));
+}
+
+#[test]
+fn lto_ignored_for_dylib() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [lib]
+            crate-type = ["dylib"]
+
+            [profile.release]
+            lto = true
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("build").arg("--release"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[WARNING] `lto` is ignored for the library of `foo`, rustc can't optimize the \
+`dylib` crate type at link time"));
+}
@@ -270,6 +270,53 @@ dependency `bar` does not specify a version
 "));
 }
 
+#[test]
+fn path_dependency_with_version() {
+    publish::setup();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [dependencies.bar]
+            path = "bar"
+            version = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("publish").arg("--no-verify")
+                 .arg("--index").arg(publish::registry().to_string()),
+                execs().with_status(0));
+
+    // The dependency is uploaded as one on the registry being published to,
+    // not as one from another registry.
+    let mut f = File::open(&publish::upload_path().join("api/v1/crates/new")).unwrap();
+    let mut sz = [0; 4];
+    assert_eq!(f.read(&mut sz).unwrap(), 4);
+    let sz = ((sz[0] as u32) <<  0) |
+             ((sz[1] as u32) <<  8) |
+             ((sz[2] as u32) << 16) |
+             ((sz[3] as u32) << 24);
+    let mut metadata = vec![0; sz as usize];
+    f.read_exact(&mut metadata).unwrap();
+    let metadata = String::from_utf8(metadata).unwrap();
+    assert!(metadata.contains(r#""name":"bar""#), "{}", metadata);
+    assert!(!metadata.contains(r#""registry""#), "{}", metadata);
+}
+
 #[test]
 fn unpublishable_crate() {
     publish::setup();
@@ -500,3 +547,97 @@ See [..]
     // Ensure the API request wasn't actually made
     assert!(!publish::upload_path().join("api/v1/crates/new").exists());
 }
+
+#[test]
+fn check_reports_problems() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            baz = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("publish").arg("--check"),
+                execs().with_status(101).with_stderr("\
+[ERROR] manifest has no description, which crates.io requires
+[ERROR] manifest has no license or license-file, which crates.io requires
+[WARNING] manifest has no documentation, homepage or repository
+[ERROR] path dependency `bar` does not specify a version, which is what's used once published
+[ERROR] dependency `baz` allows any version (`*`), which crates.io rejects
+[ERROR] `foo` can't be published, 4 problems found
+"));
+
+    assert_that(p.cargo("publish").arg("--check")
+                 .arg("--message-format").arg("json"),
+                execs().with_status(101)
+                       .with_stdout_contains("\
+{\"code\":\"wildcard-dependency\",\"level\":\"error\",\
+\"message\":\"dependency `baz` allows any version (`*`), which crates.io rejects\",\
+\"package_id\":\"foo 0.0.1 ([..])\",\"reason\":\"publish-check\"}"));
+}
+
+#[test]
+fn check_passes() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            repository = "https://example.com/foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Nothing is uploaded, so there's no need for a registry either.
+    assert_that(p.cargo("publish").arg("--check"),
+                execs().with_status(0).with_stderr(&format!("\
+     Checked foo v0.0.1 ({dir}) can be published
+", dir = p.url())));
+}
+
+#[test]
+fn version_already_published() {
+    publish::setup();
+
+    // The crate's page on the registry lists what's been published.
+    File::create(&publish::upload_path().join("api/v1/crates/foo")).unwrap()
+        .write_all(br#"{"versions": [{"num": "0.0.1", "yanked": true}]}"#).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("publish").arg("--no-verify")
+                 .arg("--index").arg(publish::registry().to_string()),
+                execs().with_status(101).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+[ERROR] `foo v0.0.1` has already been published, and was yanked since
+Versions can't be published twice, bump the `version` in Cargo.toml to publish these changes
+",
+        reg = publish::registry())));
+}
@@ -12,7 +12,7 @@ use cargotest::support::git;
 use cargotest::support::paths::{self, CargoPathExt};
 use cargotest::support::registry::{self, Package};
 use cargotest::support::{project, execs};
-use hamcrest::assert_that;
+use hamcrest::{assert_that, existing_dir, is_not};
 use url::Url;
 
 fn registry_path() -> PathBuf { paths::root().join("registry") }
@@ -108,7 +108,7 @@ fn nonexistent() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr("\
+                execs().with_status(102).with_stderr("\
 [UPDATING] registry [..]
 [ERROR] no matching package named `nonexistent` found (required by `foo`)
 location searched: registry [..]
@@ -135,7 +135,7 @@ fn wrong_version() {
     Package::new("foo", "0.0.2").publish();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 [ERROR] no matching version `>= 1.0.0` found for package `foo` (required by `foo`)
 location searched: registry [..]
 versions found: 0.0.2, 0.0.1
@@ -145,7 +145,7 @@ versions found: 0.0.2, 0.0.1
     Package::new("foo", "0.0.4").publish();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 [ERROR] no matching version `>= 1.0.0` found for package `foo` (required by `foo`)
 location searched: registry [..]
 versions found: 0.0.4, 0.0.3, 0.0.2, ...
@@ -185,6 +185,48 @@ Caused by:
 "));
 }
 
+#[test]
+fn tampered_cached_archive() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("bar", "0.0.1").publish();
+
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+
+    // Replace the cached archive, and make sure it's going to be unpacked
+    // again.
+    let registry = paths::home().join(".cargo/registry");
+    let cache = fs::read_dir(registry.join("cache")).unwrap()
+                   .next().unwrap().unwrap().path();
+    let src = fs::read_dir(registry.join("src")).unwrap()
+                 .next().unwrap().unwrap().path();
+    File::create(cache.join("bar-0.0.1.crate")).unwrap()
+        .write_all(b"not the published archive").unwrap();
+    src.join("bar-0.0.1").rm_rf();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+Caused by:
+  the archive of `bar v0.0.1 (registry `file://[..]`)` doesn't match the \
+checksum recorded for it
+")
+                       .with_stderr_contains("\
+nothing was unpacked from it, run `cargo verify --repair` to download it again
+"));
+    assert!(!src.join("bar-0.0.1/Cargo.toml").exists());
+}
+
 #[test]
 fn update_registry() {
     Package::new("init", "0.0.1").publish();
@@ -203,7 +245,7 @@ fn update_registry() {
         .build();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 [ERROR] no matching package named `notyet` found (required by `foo`)
 location searched: registry [..]
 version required: >= 0.0.0
@@ -252,7 +294,7 @@ fn package_with_path_deps() {
         .build();
 
     assert_that(p.cargo("package").arg("-v"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 [ERROR] failed to verify package tarball
 
 Caused by:
@@ -400,7 +442,7 @@ fn relying_on_a_yank_is_bad() {
     Package::new("bar", "0.0.1").dep("baz", "=0.0.2").publish();
 
     assert_that(p.cargo("build"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 [ERROR] no matching version `= 0.0.2` found for package `baz` (required by `bar`)
 location searched: registry [..]
 versions found: 0.0.1
@@ -435,7 +477,7 @@ fn yanks_in_lockfiles_are_ok() {
                 execs().with_status(0).with_stdout(""));
 
     assert_that(p.cargo("update"),
-                execs().with_status(101).with_stderr_contains("\
+                execs().with_status(102).with_stderr_contains("\
 [ERROR] no matching package named `bar` found (required by `foo`)
 location searched: registry [..]
 version required: *
@@ -868,6 +910,45 @@ fn update_transitive_dependency() {
 "));
 }
 
+#[test]
+fn update_dry_run() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            a = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("a", "0.1.0").publish();
+
+    assert_that(p.cargo("generate-lockfile"),
+                execs().with_status(0));
+    let mut lock = String::new();
+    File::open(p.root().join("Cargo.lock")).unwrap()
+        .read_to_string(&mut lock).unwrap();
+
+    Package::new("a", "0.1.1").publish();
+
+    assert_that(p.cargo("update").arg("--dry-run"),
+                execs().with_status(0)
+                       .with_stderr("\
+[UPDATING] registry `[..]`
+[UPDATING] a v0.1.0 -> v0.1.1
+[WARNING] not updating Cargo.lock due to dry run
+"));
+
+    let mut lock2 = String::new();
+    File::open(p.root().join("Cargo.lock")).unwrap()
+        .read_to_string(&mut lock2).unwrap();
+    assert_eq!(lock, lock2);
+}
+
 #[test]
 fn update_backtracking_ok() {
     let p = project("foo")
@@ -1032,6 +1113,44 @@ fn update_same_prefix_oh_my_how_was_this_a_bug() {
                 execs().with_status(0));
 }
 
+#[test]
+fn build_metadata() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            foo = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("foo", "0.1.0").publish();
+    Package::new("foo", "0.1.1+build.5").publish();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr_contains("\
+[DOWNLOADING] foo v0.1.1+build.5 (registry [..])"));
+    assert!(p.read_lockfile().contains("version = \"0.1.1+build.5\""));
+
+    assert_that(p.cargo("update").arg("-pfoo").arg("--precise=0.1.0"),
+                execs().with_status(0));
+    assert!(p.read_lockfile().contains("version = \"0.1.0\""));
+
+    // Build metadata is ignored when looking for the precise version.
+    assert_that(p.cargo("update").arg("-pfoo").arg("--precise=0.1.1"),
+                execs().with_status(0));
+    assert!(p.read_lockfile().contains("version = \"0.1.1+build.5\""));
+
+    assert_that(p.cargo("update").arg("-pfoo").arg("--precise=0.1"),
+                execs().with_status(101).with_stderr("\
+[ERROR] invalid version given to `--precise` for `foo`: cannot parse '0.1' as a semver
+"));
+}
+
 #[test]
 fn use_semver() {
     let p = project("foo")
@@ -1052,6 +1171,48 @@ fn use_semver() {
     assert_that(p.cargo("build"), execs().with_status(0));
 }
 
+#[test]
+fn prerelease_requires_opt_in() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            foo = "1.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("foo", "1.0.0").publish();
+    Package::new("foo", "1.1.0-beta.1").publish();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(102).with_stderr_contains("\
+[ERROR] no matching version `^1.1` found for package `foo` (required by `bar`)
+location searched: registry [..]
+versions found: 1.1.0-beta.1, 1.0.0
+the pre-release version `1.1.0-beta.1` would match, but pre-releases are only \
+selected when the version requirement includes one, e.g. `foo = \"1.1.0-beta.1\"`
+"));
+
+    File::create(p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [project]
+        name = "bar"
+        version = "0.5.0"
+        authors = []
+
+        [dependencies]
+        foo = "1.1.0-beta.1"
+    "#).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0).with_stderr_contains("\
+[DOWNLOADING] foo v1.1.0-beta.1 (registry [..])"));
+}
+
 #[test]
 fn only_download_relevant() {
     let p = project("foo")
@@ -1451,3 +1612,195 @@ Caused by:
   [..] contains a file at \"foo-0.1.0/src/lib.rs\" which isn't under \"foo-0.2.0\"
 "));
 }
+
+#[test]
+fn symlinks_rejected() {
+    // Each link stays within the package on its own, but together they
+    // lead out of it.
+    Package::new("foo", "0.2.0")
+            .symlink("l1", ".")
+            .symlink("l1/l2", "..")
+            .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "fo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            foo = "0.2"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("-vv"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+Caused by:
+  invalid tarball downloaded, contains a symlink at \"foo-0.2.0/l1\"
+"));
+}
+
+#[test]
+fn index_max_staleness() {
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            index-max-staleness = 60
+        "#)
+        .build();
+    assert_that(p.cargo("generate-lockfile"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+",
+        reg = registry::registry())));
+
+    // The index was just updated, so it's used as is.
+    Package::new("bar", "0.0.2").publish();
+    t!(fs::remove_file(p.root().join("Cargo.lock")));
+    assert_that(p.cargo("generate-lockfile"), execs().with_status(0).with_stderr(""));
+    assert!(p.read_lockfile().contains("name = \"bar\"\nversion = \"0.0.1\""));
+
+    assert_that(p.cargo("generate-lockfile").arg("--refresh"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+",
+        reg = registry::registry())));
+    assert!(p.read_lockfile().contains("name = \"bar\"\nversion = \"0.0.2\""));
+
+    // Updating is what `cargo update` is for, so it always does.
+    Package::new("bar", "0.0.3").publish();
+    assert_that(p.cargo("update"),
+                execs().with_status(0).with_stderr(&format!("\
+[UPDATING] registry `{reg}`
+[UPDATING] bar v0.0.2 -> v0.0.3
+",
+        reg = registry::registry())));
+}
+
+#[test]
+fn two_versions_of_a_dependency_dont_collide() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn one() {}")
+        .publish();
+    Package::new("bar", "0.2.0")
+        .file("src/lib.rs", "pub fn two() {}")
+        .publish();
+    Package::new("baz", "0.1.0")
+        .dep("bar", "0.2")
+        .file("src/lib.rs", "extern crate bar; pub fn baz() { bar::two() }")
+        .publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            baz = "0.1"
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            extern crate baz;
+            fn main() { bar::one(); baz::baz(); }
+        "#)
+        .build();
+
+    assert_that(p.cargo("build"), execs().with_status(0));
+    let rlibs = t!(fs::read_dir(p.root().join("target/debug/deps")))
+        .map(|e| t!(e).file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("libbar-") && name.ends_with(".rlib"))
+        .count();
+    assert_eq!(rlibs, 2);
+    assert_that(p.cargo("run"), execs().with_status(0));
+}
+
+#[test]
+fn minimal_versions() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.1.1").publish();
+    Package::new("bar", "0.1.2").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("-Zminimal-versions")
+                 .masquerade_as_nightly_cargo(),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[DOWNLOADING] bar v0.1.1 (registry [..])"));
+    let lock = p.read_lockfile();
+    assert!(lock.contains("version = \"0.1.1\""));
+    assert!(!lock.contains("version = \"0.1.2\""));
+
+    // The `-Z` flag is only accepted on nightly.
+    assert_that(p.cargo("update").arg("-Zminimal-versions"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] the `-Z minimal-versions` flag is only accepted on the nightly channel \
+of Cargo, but this is the `stable` channel
+"));
+}
+
+#[test]
+fn dry_run_does_not_download() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("bar", "0.0.1").publish();
+
+    assert_that(p.cargo("build").arg("--dry-run"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[DIRTY] bar v0.0.1[..]: not downloaded yet")
+                       .with_stderr_contains("\
+[FINISHED] dry run, 1 package(s) would be downloaded, run `cargo fetch` \
+to find out what would be rebuilt")
+                       .with_stderr_does_not_contain("[DOWNLOADING] [..]"));
+    assert_that(&p.root().join("target"), is_not(existing_dir()));
+
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--dry-run"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[FINISHED] dry run, 2 unit(s) would be rebuilt")
+                       .with_stderr_does_not_contain("[DOWNLOADING] [..]"));
+    assert_that(&p.root().join("target"), is_not(existing_dir()));
+}
@@ -57,6 +57,33 @@ Consider enabling them by passing e.g. `--features=\"a\"`
 "));
 }
 
+#[test]
+fn skipped_targets_are_reported() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            a = []
+            b = []
+
+            [[bin]]
+            name = "foo"
+            required-features = ["a", "b"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("build").arg("--features").arg("a").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("\
+[SKIPPING] target `foo` of foo v0.0.1 ([..]), which requires the features: `b`"));
+    assert_that(&p.bin("foo"), not(existing_file()));
+}
+
 #[test]
 fn build_bin_arg_features() {
     let p = project("foo")
@@ -354,7 +381,8 @@ fn test_multiple_required_features() {
 [COMPILING] foo v0.0.1 ({})
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo_1-[..][EXE]
-[RUNNING] target[/]debug[/]deps[/]foo_2-[..][EXE]", p.url()))
+[RUNNING] target[/]debug[/]deps[/]foo_2-[..][EXE]
+[SUMMARY] 2 test binaries: [..]", p.url()))
                 .with_stdout_contains_n("test test ... ok", 2));
 
     assert_that(p.cargo("test").arg("--no-default-features"),
@@ -995,6 +1023,11 @@ fn run_default_multiple_required_features() {
 
     assert_that(p.cargo("run"),
                 execs().with_status(101).with_stderr("\
-error: `cargo run` requires that a project only have one executable; \
-use the `--bin` option to specify which one to run"));
+error: `cargo run` could not determine which binary to run, as the package has \
+several of them; use the `--bin` option to pick one, or set `default-run` in the \
+manifest
+
+available binaries:
+    foo1
+    foo2"));
 }
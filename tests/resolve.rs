@@ -34,7 +34,7 @@ fn resolve(pkg: &PackageId, deps: Vec<Dependency>, registry: &[Summary])
     let mut registry = MyRegistry(registry);
     let summary = Summary::new(pkg.clone(), deps, BTreeMap::new()).unwrap();
     let method = Method::Everything;
-    let resolve = resolver::resolve(&[(summary, method)], &[], &mut registry, None)?;
+    let resolve = resolver::resolve(&[(summary, method)], &[], &mut registry, None, false)?;
     let res = resolve.iter().cloned().collect();
     Ok(res)
 }
@@ -364,6 +364,70 @@ fn resolving_with_deep_backtracking() {
                                        ("baz", "1.0.1")])));
 }
 
+#[test]
+fn resolving_with_links_backtracks() {
+    // Both versions link to the same native library, so they can't both be
+    // used even though they're semver incompatible.
+    let reg = registry(vec![
+        pkg!(("foo-sys", "1.0.0")).set_links(Some("foo".to_string())),
+        pkg!(("foo-sys", "2.0.0")).set_links(Some("foo".to_string())),
+        pkg!(("bar", "1.0.0") => [dep_req("foo-sys", "1")]),
+    ]);
+
+    let res = resolve(&pkg_id("root"), vec![
+        dep_req("foo-sys", "*"),
+        dep_req("bar", "1"),
+    ], &reg).unwrap();
+
+    assert_that(&res, contains(names(&[("root", "1.0.0"),
+                                       ("bar", "1.0.0"),
+                                       ("foo-sys", "1.0.0")])));
+    assert_that(&res, not(contains(names(&[("foo-sys", "2.0.0")]))));
+}
+
+#[test]
+fn resolving_with_links_conflict() {
+    let reg = registry(vec![
+        pkg!(("a-sys", "1.0.0")).set_links(Some("a".to_string())),
+        pkg!(("b-sys", "1.0.0")).set_links(Some("a".to_string())),
+    ]);
+
+    let res = resolve(&pkg_id("root"), vec![
+        dep_req("a-sys", "1"),
+        dep_req("b-sys", "1"),
+    ], &reg);
+
+    let err = res.err().unwrap().to_string();
+    assert!(err.contains("links to the native library `a`, but it conflicts \
+                          with a previous package which links to `a` as well"),
+            "{}", err);
+}
+
+#[test]
+fn resolving_with_deep_links_conflict_explains_path() {
+    let reg = registry(vec![
+        pkg!(("a-sys", "1.0.0")).set_links(Some("a".to_string())),
+        pkg!(("b-sys", "1.0.0")).set_links(Some("a".to_string())),
+        pkg!(("a", "1.0.0") => [dep_req("a-sys", "1")]),
+        pkg!(("b", "1.0.0") => [dep_req("b-sys", "1")]),
+    ]);
+
+    let res = resolve(&pkg_id("root"), vec![
+        dep_req("a", "1"),
+        dep_req("b", "1"),
+    ], &reg);
+
+    // Both the package being selected and the one already linking to `a` are
+    // reported along with the packages which depend on them.
+    let err = res.err().unwrap().to_string();
+    assert!(err.contains("links to the native library `a`, but it conflicts \
+                          with a previous package which links to `a` as well"),
+            "{}", err);
+    assert!(err.contains("\n  ... which is depended on by `root v1.0.0"),
+            "{}", err);
+    assert!(err.contains("\n    ... which is depended on by `"), "{}", err);
+}
+
 #[test]
 fn resolving_but_no_exists() {
     let reg = registry(vec![
@@ -410,3 +474,59 @@ fn hard_equality() {
                                        ("foo", "1.0.0"),
                                        ("bar", "1.0.0")])));
 }
+
+#[test]
+fn resolving_skips_choices_unrelated_to_conflict() {
+    // `x` needs a version of `c` which conflicts with the one `root` asks for.
+    // None of the twenty `a*` packages has anything to do with it, so the
+    // failure is reported without trying every combination of their versions.
+    let mut reg = vec![
+        pkg!(("c", "1.0.0")),
+        pkg!(("c", "1.1.0")),
+        pkg!(("c", "1.2.0")),
+        pkg!(("c", "1.3.0")),
+        pkg!(("x", "1.0.0") => [dep_req("c", ">=1.1.0")]),
+    ];
+    let mut deps = vec![dep_req("c", "=1.0.0"), dep_req("x", "1")];
+    for i in 0..20 {
+        let name = format!("a{}", i);
+        for &vers in &["1.0.0", "2.0.0"] {
+            let id = PackageId::new(&name, vers, &registry_loc()).unwrap();
+            reg.push(Summary::new(id, Vec::new(), BTreeMap::new()).unwrap());
+        }
+        deps.push(dep_req(&name, "*"));
+    }
+    let reg = registry(reg);
+
+    let err = resolve(&pkg_id("root"), deps, &reg).err().unwrap().to_string();
+    assert!(err.contains("\
+failed to select a version for `c` (required by `x`):
+  ... which is depended on by `root v1.0.0"), "{}", err);
+    assert!(err.contains("\
+all possible versions conflict with previously selected versions of `c`
+  previously selected package `c v1.0.0"), "{}", err);
+    assert!(err.contains("\
+possible versions to select: 1.3.0, 1.2.0, 1.1.0"), "{}", err);
+}
+
+#[test]
+fn resolving_conflict_explains_path() {
+    let reg = registry(vec![
+        pkg!(("c", "1.0.0")),
+        pkg!(("c", "1.1.0")),
+        pkg!(("b", "1.0.0") => [dep_req("c", "=1.0.0")]),
+        pkg!(("a", "1.0.0") => [dep_req("b", "1")]),
+        pkg!(("x", "1.0.0") => [dep_req("c", "=1.1.0")]),
+    ]);
+
+    let res = resolve(&pkg_id("root"), vec![
+        dep_req("a", "1"),
+        dep_req("x", "1"),
+    ], &reg);
+
+    let err = res.err().unwrap().to_string();
+    assert!(err.contains("all possible versions conflict with previously \
+                          selected versions of `c`"), "{}", err);
+    assert!(err.contains("... which is depended on by `root v1.0.0"),
+            "{}", err);
+}
@@ -254,9 +254,60 @@ fn too_many_bins() {
 
     assert_that(p.cargo("run"),
                 execs().with_status(101)
-                       .with_stderr("[ERROR] `cargo run` requires that a project only \
-                                     have one executable; use the `--bin` option \
-                                     to specify which one to run\n"));
+                       .with_stderr("\
+[ERROR] `cargo run` could not determine which binary to run, as the package has \
+several of them; use the `--bin` option to pick one, or set `default-run` in the \
+manifest
+
+available binaries:
+    a
+    b
+"));
+}
+
+#[test]
+fn default_run() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            default-run = "b"
+        "#)
+        .file("src/lib.rs", "")
+        .file("src/bin/a.rs", r#"fn main() { println!("a"); }"#)
+        .file("src/bin/b.rs", r#"fn main() { println!("b"); }"#)
+        .build();
+
+    assert_that(p.cargo("run"),
+                execs().with_status(0).with_stdout("b"));
+    assert_that(p.cargo("run").arg("--bin").arg("a"),
+                execs().with_status(0).with_stdout("a"));
+}
+
+#[test]
+fn default_run_missing() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            default-run = "c"
+        "#)
+        .file("src/bin/a.rs", "fn main() {}")
+        .build();
+
+    assert_that(p.cargo("run"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `default-run` names the binary `c`, which doesn't exist
+available binaries: `a`
+"));
 }
 
 #[test]
@@ -437,7 +488,10 @@ fn run_with_filename() {
 
     assert_that(p.cargo("run").arg("--bin").arg("bin.rs"),
                 execs().with_status(101).with_stderr("\
-[ERROR] no bin target named `bin.rs`"));
+[ERROR] no bin target named `bin.rs`
+
+Available bin targets:
+    a"));
 
     assert_that(p.cargo("run").arg("--bin").arg("a.rs"),
                 execs().with_status(101).with_stderr("\
@@ -447,7 +501,10 @@ Did you mean `a`?"));
 
     assert_that(p.cargo("run").arg("--example").arg("example.rs"),
                 execs().with_status(101).with_stderr("\
-[ERROR] no example target named `example.rs`"));
+[ERROR] no example target named `example.rs`
+
+Available example targets:
+    a"));
 
     assert_that(p.cargo("run").arg("--example").arg("a.rs"),
                 execs().with_status(101).with_stderr("\
@@ -561,14 +618,14 @@ fn example_with_release_flag() {
                 execs().with_status(0)
                        .with_stderr(&format!("\
 [COMPILING] bar v0.0.1 ({url}/bar)
-[RUNNING] `rustc --crate-name bar bar[/]src[/]bar.rs --crate-type lib \
+[RUNNING] `rustc --crate-name bar bar[/]src[/]bar.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C opt-level=3 \
         -C metadata=[..] \
         --out-dir {dir}[/]target[/]release[/]deps \
         -L dependency={dir}[/]target[/]release[/]deps`
 [COMPILING] foo v0.0.1 ({url})
-[RUNNING] `rustc --crate-name a examples[/]a.rs --crate-type bin \
+[RUNNING] `rustc --crate-name a examples[/]a.rs --error-format json --crate-type bin \
         --emit=dep-info,link \
         -C opt-level=3 \
         -C metadata=[..] \
@@ -589,14 +646,14 @@ fast2"));
                 execs().with_status(0)
                        .with_stderr(&format!("\
 [COMPILING] bar v0.0.1 ({url}/bar)
-[RUNNING] `rustc --crate-name bar bar[/]src[/]bar.rs --crate-type lib \
+[RUNNING] `rustc --crate-name bar bar[/]src[/]bar.rs --error-format json --crate-type lib \
         --emit=dep-info,link \
         -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir {dir}[/]target[/]debug[/]deps \
         -L dependency={dir}[/]target[/]debug[/]deps`
 [COMPILING] foo v0.0.1 ({url})
-[RUNNING] `rustc --crate-name a examples[/]a.rs --crate-type bin \
+[RUNNING] `rustc --crate-name a examples[/]a.rs --error-format json --crate-type bin \
         --emit=dep-info,link \
         -C debuginfo=2 \
         -C metadata=[..] \
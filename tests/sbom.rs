@@ -0,0 +1,175 @@
+#[macro_use]
+extern crate cargotest;
+extern crate git2;
+extern crate hamcrest;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs, Project};
+use hamcrest::assert_that;
+
+/// `foo` depends on `bar` from the registry and `baz` from git, and has a
+/// dev-dependency on `qux`. Returns the project and the revision of `baz`.
+fn foo() -> (Project, String) {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("qux", "0.1.0").publish();
+    let baz = git::new("baz", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "baz"
+                version = "0.2.0"
+                authors = []
+                license = "MIT"
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+    let repo = t!(git2::Repository::open(&baz.root()));
+    let head = t!(repo.head()).target().unwrap().to_string();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            baz = {{ git = '{}' }}
+
+            [dev-dependencies]
+            qux = "0.1"
+        "#, baz.url()))
+        .file("src/lib.rs", "")
+        .build();
+    (p, head)
+}
+
+#[test]
+fn cyclonedx() {
+    let (p, head) = foo();
+    assert_that(p.cargo("sbom").env("SOURCE_DATE_EPOCH", "0"),
+                execs().with_status(0).with_json(&format!(r#"
+        {{
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.4",
+            "version": 1,
+            "metadata": {{
+                "timestamp": "1970-01-01T00:00:00Z",
+                "tools": [{{"name": "cargo", "version": "[..]"}}],
+                "component": {{
+                    "type": "application",
+                    "bom-ref": "foo@0.0.1 ([..])",
+                    "name": "foo",
+                    "version": "0.0.1"
+                }}
+            }},
+            "components": [
+                {{
+                    "type": "library",
+                    "bom-ref": "bar@0.1.0 (registry+[..])",
+                    "name": "bar",
+                    "version": "0.1.0",
+                    "purl": "pkg:cargo/bar@0.1.0",
+                    "hashes": [{{"alg": "SHA-256", "content": "[..]"}}]
+                }},
+                {{
+                    "type": "library",
+                    "bom-ref": "baz@0.2.0 (git+[..]#{rev})",
+                    "name": "baz",
+                    "version": "0.2.0",
+                    "purl": "pkg:cargo/baz@0.2.0?vcs_url=git%2Bfile://[..]%40{rev}",
+                    "licenses": [{{"expression": "MIT"}}],
+                    "externalReferences": [{{"type": "vcs", "url": "git+file://[..]@{rev}"}}]
+                }}
+            ],
+            "dependencies": [
+                {{"ref": "bar@0.1.0 ([..])", "dependsOn": []}},
+                {{"ref": "baz@0.2.0 ([..])", "dependsOn": []}},
+                {{
+                    "ref": "foo@0.0.1 ([..])",
+                    "dependsOn": ["bar@0.1.0 ([..])", "baz@0.2.0 ([..])"]
+                }}
+            ]
+        }}
+    "#, rev = head)));
+}
+
+#[test]
+fn spdx() {
+    let (p, head) = foo();
+    assert_that(p.cargo("sbom").arg("--format").arg("spdx")
+                 .env("SOURCE_DATE_EPOCH", "0"),
+                execs().with_status(0).with_json(&format!(r#"
+        {{
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "foo-0.0.1",
+            "documentNamespace": "https://spdx.org/spdxdocs/foo-0.0.1-[..]",
+            "creationInfo": {{
+                "created": "1970-01-01T00:00:00Z",
+                "creators": ["Tool: cargo-[..]"]
+            }},
+            "packages": [
+                {{
+                    "SPDXID": "SPDXRef-Package-0",
+                    "name": "bar",
+                    "versionInfo": "0.1.0",
+                    "downloadLocation": "NOASSERTION",
+                    "filesAnalyzed": false,
+                    "licenseConcluded": "NOASSERTION",
+                    "licenseDeclared": "NOASSERTION",
+                    "copyrightText": "NOASSERTION",
+                    "checksums": [{{"algorithm": "SHA256", "checksumValue": "[..]"}}],
+                    "externalRefs": [{{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": "pkg:cargo/bar@0.1.0"
+                    }}]
+                }},
+                {{
+                    "SPDXID": "SPDXRef-Package-1",
+                    "name": "baz",
+                    "versionInfo": "0.2.0",
+                    "downloadLocation": "git+file://[..]@{rev}",
+                    "filesAnalyzed": false,
+                    "licenseConcluded": "NOASSERTION",
+                    "licenseDeclared": "MIT",
+                    "copyrightText": "NOASSERTION",
+                    "externalRefs": [{{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": "pkg:cargo/baz@0.2.0?vcs_url=[..]%40{rev}"
+                    }}]
+                }},
+                {{
+                    "SPDXID": "SPDXRef-Package-2",
+                    "name": "foo",
+                    "versionInfo": "0.0.1",
+                    "downloadLocation": "NOASSERTION",
+                    "filesAnalyzed": false,
+                    "licenseConcluded": "NOASSERTION",
+                    "licenseDeclared": "NOASSERTION",
+                    "copyrightText": "NOASSERTION"
+                }}
+            ],
+            "relationships": [
+                {{
+                    "spdxElementId": "SPDXRef-DOCUMENT",
+                    "relationshipType": "DESCRIBES",
+                    "relatedSpdxElement": "SPDXRef-Package-2"
+                }},
+                {{
+                    "spdxElementId": "SPDXRef-Package-2",
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": "SPDXRef-Package-0"
+                }},
+                {{
+                    "spdxElementId": "SPDXRef-Package-2",
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": "SPDXRef-Package-1"
+                }}
+            ]
+        }}
+    "#, rev = head)));
+}
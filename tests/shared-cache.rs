@@ -0,0 +1,101 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::paths;
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs, Project};
+use hamcrest::assert_that;
+
+fn dependent(name: &str) -> Project {
+    project(name)
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "{}"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#, name))
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar(); }")
+        .file(".cargo/config", r#"
+            [build]
+            shared-cache = true
+        "#)
+        .build()
+}
+
+fn publish() {
+    Package::new("baz", "0.1.0")
+        .file("src/lib.rs", "pub fn baz() {}")
+        .publish();
+    Package::new("bar", "0.1.0")
+        .dep("baz", "0.1.0")
+        .file("src/lib.rs", "extern crate baz; pub fn bar() { baz::baz() }")
+        .publish();
+}
+
+#[test]
+fn dependencies_are_compiled_once() {
+    publish();
+    let foo = dependent("foo");
+    let other = dependent("other");
+
+    assert_that(foo.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name baz [..]")
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]")
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name foo [..]"));
+    assert!(paths::home().join(".cargo/build-cache").is_dir());
+
+    assert_that(other.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[..]Restored baz v0.1.0[..]from the shared build cache")
+                       .with_stderr_contains("[..]Restored bar v0.1.0[..]from the shared build cache")
+                       .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name ba[..]")
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name other [..]"));
+    assert_that(other.cargo("run"), execs().with_status(0));
+
+    // The cache is used again once the target directory is gone.
+    assert_that(foo.cargo("clean"), execs().with_status(0));
+    assert_that(foo.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[..]Restored bar v0.1.0[..]")
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name foo [..]"));
+}
+
+#[test]
+fn different_profiles_are_compiled_separately() {
+    publish();
+    let foo = dependent("foo");
+    let other = dependent("other");
+
+    assert_that(foo.cargo("build"), execs().with_status(0));
+    assert_that(other.cargo("build").arg("--release").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name baz [..]")
+                       .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]"));
+    assert_that(other.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[..]Restored bar v0.1.0[..]"));
+}
+
+#[test]
+fn disabled_by_default() {
+    publish();
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar(); }")
+        .build();
+
+    assert_that(foo.cargo("build"), execs().with_status(0));
+    assert!(!paths::home().join(".cargo/build-cache").exists());
+}
@@ -0,0 +1,159 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::gpg::{self, Key};
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs, Project};
+use hamcrest::assert_that;
+
+/// A project depending on `deps`, which trusts `key` if there is one.
+fn trusting(key: Option<&Key>, deps: &str) -> Project {
+    let mut p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            {}
+        "#, deps))
+        .file("src/main.rs", "fn main() {}");
+    if key.is_some() {
+        p = p.file(".cargo/config", r#"
+            [signatures]
+            trusted-keys = ["keys/trusted.asc"]
+        "#);
+    }
+    let p = p.build();
+    if let Some(key) = key {
+        key.export(&p.root().join("keys/trusted.asc"));
+    }
+    p
+}
+
+#[test]
+fn registry_package_signed_by_trusted_key() {
+    if !gpg::available() {
+        return
+    }
+    let key = Key::generate("trusted");
+    Package::new("bar", "0.0.1").signed(&key).publish();
+
+    let p = trusting(Some(&key), r#"bar = "0.0.1""#);
+    assert_that(p.cargo("build").arg("--require-signatures"),
+                execs().with_status(0));
+}
+
+#[test]
+fn registry_package_signed_by_untrusted_key() {
+    if !gpg::available() {
+        return
+    }
+    let trusted = Key::generate("trusted");
+    let other = Key::generate("other");
+    Package::new("bar", "0.0.1").signed(&other).publish();
+
+    let p = trusting(Some(&trusted), r#"bar = "0.0.1""#);
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains("\
+  failed to verify the signature of `bar v0.0.1 (registry `file://[..]`)`")
+                                        .with_stderr_contains("\
+  the signature wasn't made by a trusted key, or it doesn't match"));
+}
+
+#[test]
+fn unsigned_registry_package_is_fine_by_default() {
+    Package::new("bar", "0.0.1").publish();
+
+    let p = trusting(None, r#"bar = "0.0.1""#);
+    assert_that(p.cargo("build"), execs().with_status(0));
+}
+
+#[test]
+fn require_signatures_rejects_unsigned_registry_package() {
+    Package::new("bar", "0.0.1").publish();
+
+    let p = trusting(None, r#"bar = "0.0.1""#);
+    assert_that(p.cargo("fetch").arg("--require-signatures"),
+                execs().with_status(101).with_stderr_contains("\
+  `bar v0.0.1 (registry `file://[..]`)` is not signed, but signatures are required"));
+
+    // The same goes for `signatures.require` in the configuration.
+    assert_that(p.cargo("build").env("CARGO_SIGNATURES_REQUIRE", "true"),
+                execs().with_status(101).with_stderr_contains("\
+  `bar v0.0.1 (registry `file://[..]`)` is not signed, but signatures are required"));
+}
+
+#[test]
+fn signed_package_without_trusted_keys_when_required() {
+    if !gpg::available() {
+        return
+    }
+    let key = Key::generate("trusted");
+    Package::new("bar", "0.0.1").signed(&key).publish();
+
+    let p = trusting(None, r#"bar = "0.0.1""#);
+    assert_that(p.cargo("build"), execs().with_status(0));
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--require-signatures"),
+                execs().with_status(101).with_stderr_contains("\
+  `bar v0.0.1 (registry `file://[..]`)` is signed, but signatures can't be \
+verified as no keys are listed in `signatures.trusted-keys`"));
+}
+
+#[test]
+fn git_tag_signed_by_trusted_key() {
+    if !gpg::available() {
+        return
+    }
+    let trusted = Key::generate("trusted");
+    let other = Key::generate("other");
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+    trusted.tag(&bar.root(), "good");
+    other.tag(&bar.root(), "bad");
+
+    let p = trusting(Some(&trusted), &format!(r#"bar = {{ git = "{}", tag = "good" }}"#,
+                                              bar.url()));
+    assert_that(p.cargo("build").arg("--require-signatures"),
+                execs().with_status(0));
+
+    // Once locked, the tag is still what's verified.
+    assert!(p.root().join("Cargo.lock").is_file());
+    assert_that(p.cargo("clean"), execs().with_status(0));
+    assert_that(p.cargo("build").arg("--require-signatures"),
+                execs().with_status(0));
+
+    let p = trusting(Some(&trusted), &format!(r#"bar = {{ git = "{}", tag = "bad" }}"#,
+                                              bar.url()));
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr_contains(&format!("\
+  failed to verify the signature of tag `bad` of `{}`", bar.url())));
+}
+
+#[test]
+fn require_signatures_rejects_git_branch() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = trusting(None, &format!(r#"bar = {{ git = "{}" }}"#, bar.url()));
+    assert_that(p.cargo("build").arg("--require-signatures"),
+                execs().with_status(101).with_stderr_contains(&format!("\
+  git repository `{}`, which isn't pinned to a tag, is not signed, but \
+signatures are required", bar.url())));
+}
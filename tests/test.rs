@@ -1,6 +1,7 @@
 extern crate cargo;
 extern crate cargotest;
 extern crate hamcrest;
+extern crate serde_json;
 
 use std::fs::File;
 use std::io::prelude::*;
@@ -91,6 +92,7 @@ fn cargo_test_release() {
 [FINISHED] release [optimized] target(s) in [..]
 [RUNNING] `[..]target[/]release[/]deps[/]foo-[..][EXE]`
 [RUNNING] `[..]target[/]release[/]deps[/]test-[..][EXE]`
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo
 [RUNNING] `rustdoc --test [..]lib.rs[..]`", dir = p.url()))
                        .with_stdout_contains_n("test test ... ok", 2)
@@ -260,6 +262,7 @@ fn cargo_test_failing_test_in_test() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]footest-[..][EXE]
+[SUMMARY] 2 test binaries: [..]
 [ERROR] test failed, to rerun pass '--test footest'", url = p.url()))
                        .with_stdout_contains("running 0 tests")
                        .with_stdout_contains("\
@@ -355,6 +358,7 @@ fn test_with_lib_dep() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]baz-[..][EXE]
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo", p.url()))
                        .with_stdout_contains("test lib_test ... ok")
                        .with_stdout_contains("test bin_test ... ok")
@@ -447,6 +451,7 @@ fn external_test_explicit() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]test-[..][EXE]
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo", p.url()))
                        .with_stdout_contains("test internal_test ... ok")
                        .with_stdout_contains("test external_test ... ok")
@@ -505,6 +510,7 @@ fn external_test_implicit() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]external-[..][EXE]
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo", p.url()))
                        .with_stdout_contains("test internal_test ... ok")
                        .with_stdout_contains("test external_test ... ok")
@@ -619,6 +625,7 @@ fn lib_bin_same_name() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] foo", p.url()))
                        .with_stdout_contains_n("test [..] ... ok", 2)
                        .with_stdout_contains("running 0 tests"));
@@ -657,6 +664,7 @@ fn lib_with_standard_name() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]syntax-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]test-[..][EXE]
+[SUMMARY] 2 test binaries: [..]
 [DOCTEST] syntax", dir = p.url()))
                        .with_stdout_contains("test foo_test ... ok")
                        .with_stdout_contains("test test ... ok")
@@ -989,7 +997,8 @@ fn test_dylib() {
 [COMPILING] foo v0.0.1 ({dir})
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
-[RUNNING] target[/]debug[/]deps[/]test-[..][EXE]", dir = p.url()))
+[RUNNING] target[/]debug[/]deps[/]test-[..][EXE]
+[SUMMARY] 2 test binaries: [..]", dir = p.url()))
                        .with_stdout_contains_n("test foo ... ok", 2));
 
     p.root().move_into_the_past();
@@ -998,7 +1007,8 @@ fn test_dylib() {
                        .with_stderr("\
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
-[RUNNING] target[/]debug[/]deps[/]test-[..][EXE]")
+[RUNNING] target[/]debug[/]deps[/]test-[..][EXE]
+[SUMMARY] 2 test binaries: [..]")
                        .with_stdout_contains_n("test foo ... ok", 2));
 }
 
@@ -1090,7 +1100,42 @@ fn test_no_run() {
 [COMPILING] foo v0.0.1 ({dir})
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 ",
-                       dir = p.url())));
+                       dir = p.url()))
+                       .with_stdout("[..]target[/]debug[/]deps[/]foo-[..][EXE]"));
+}
+
+#[test]
+fn test_no_run_reports_executables() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", "")
+        .build();
+
+    assert_that(p.cargo("test").arg("--no-run"),
+                execs().with_status(0)
+                       .with_stdout_contains("[..]target[/]debug[/]deps[/]foo-[..][EXE]")
+                       .with_stdout_contains("[..]target[/]debug[/]deps[/]a-[..][EXE]"));
+
+    let output = p.cargo("test").arg("--no-run").arg("--message-format").arg("json")
+                  .exec_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut executables = stdout.lines().filter_map(|line| {
+        let message: serde_json::Value = serde_json::from_str(line).unwrap();
+        message["executable"].as_str().map(|s| s.to_string())
+    }).collect::<Vec<_>>();
+    executables.sort();
+    assert_eq!(executables.len(), 2);
+    assert!(executables[0].contains("a-"));
+    assert!(executables[1].contains("foo-"));
+    for exe in executables.iter() {
+        assert_that(p.process(exe), execs().with_status(0));
+    }
 }
 
 #[test]
@@ -1205,7 +1250,8 @@ fn test_run_implicit_test_target() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]mybin-[..][EXE]
 [RUNNING] target[/]debug[/]deps[/]mytest-[..][EXE]
-[RUNNING] target[/]debug[/]examples[/]myexm-[..][EXE]", dir = prj.url()))
+[RUNNING] target[/]debug[/]examples[/]myexm-[..][EXE]
+[SUMMARY] 3 test binaries: [..]", dir = prj.url()))
                        .with_stdout_contains("test test_in_test ... ok"));
 }
 
@@ -1236,7 +1282,8 @@ fn test_run_implicit_bench_target() {
 [COMPILING] foo v0.0.1 ({dir})
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]mybin-[..][EXE]
-[RUNNING] target[/]debug[/]deps[/]mybench-[..][EXE]", dir = prj.url()))
+[RUNNING] target[/]debug[/]deps[/]mybench-[..][EXE]
+[SUMMARY] 2 test binaries: [..]", dir = prj.url()))
                        .with_stdout_contains("test test_in_bench ... ok"));
 }
 
@@ -1353,7 +1400,8 @@ fn selective_testing() {
 [COMPILING] d1 v0.0.1 ({dir}/d1)
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]d1-[..][EXE]
-[RUNNING] target[/]debug[/]deps[/]d1-[..][EXE]", dir = p.url()))
+[RUNNING] target[/]debug[/]deps[/]d1-[..][EXE]
+[SUMMARY] 2 test binaries: [..]", dir = p.url()))
                        .with_stdout_contains_n("running 0 tests", 2));
 
     println!("d2");
@@ -1363,7 +1411,8 @@ fn selective_testing() {
 [COMPILING] d2 v0.0.1 ({dir}/d2)
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [RUNNING] target[/]debug[/]deps[/]d2-[..][EXE]
-[RUNNING] target[/]debug[/]deps[/]d2-[..][EXE]", dir = p.url()))
+[RUNNING] target[/]debug[/]deps[/]d2-[..][EXE]
+[SUMMARY] 2 test binaries: [..]", dir = p.url()))
                        .with_stdout_contains_n("running 0 tests", 2));
 
     println!("whole");
@@ -1990,6 +2039,61 @@ fn no_fail_fast() {
                        .with_stdout_contains_n("test [..] ... ok", 3));
 }
 
+#[test]
+fn test_jobs() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            #[test]
+            fn lib_test() {}
+        "#)
+        .file("tests/a.rs", r#"
+            #[test]
+            fn a_test() {}
+
+            #[test]
+            #[ignore]
+            fn a_ignored() {}
+        "#)
+        .file("tests/b.rs", r#"
+            #[test]
+            fn b_test() {}
+
+            #[test]
+            fn b_fails() { panic!() }
+        "#)
+        .build();
+
+    assert_that(p.cargo("test").arg("--test-jobs").arg("2").arg("--no-fail-fast"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[RUNNING] target[/]debug[/]deps[/]foo-[..][EXE]
+[RUNNING] target[/]debug[/]deps[/]a-[..][EXE]
+[RUNNING] target[/]debug[/]deps[/]b-[..][EXE]
+[SUMMARY] 3 test binaries: 3 passed; 1 failed; 1 ignored")
+                       .with_stdout_contains("test lib_test ... ok")
+                       .with_stdout_contains("test a_test ... ok")
+                       .with_stdout_contains("test b_test ... ok")
+                       .with_stdout_contains("test b_fails ... FAILED"));
+
+    assert_that(p.cargo("test").arg("--no-fail-fast"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[SUMMARY] 3 test binaries: 2 succeeded; 1 failed")
+                       .with_stdout_contains("test b_fails ... FAILED"));
+
+    assert_that(p.cargo("test").arg("--test-jobs").arg("2").arg("--test").arg("a"),
+                execs().with_status(0)
+                       .with_stderr("\
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] target[/]debug[/]deps[/]a-[..][EXE]"));
+}
+
 #[test]
 fn test_multiple_packages() {
     let p = project("foo")
@@ -2167,6 +2271,11 @@ fn only_test_docs() {
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
 [DOCTEST] foo")
                        .with_stdout_contains("test [..] ... ok"));
+
+    assert_that(p.cargo("test").arg("--doc").arg("--test").arg("foo"),
+                execs().with_status(101)
+                       .with_stderr("\
+[ERROR] `--doc` can't be combined with other options selecting targets"));
 }
 
 #[test]
@@ -171,3 +171,63 @@ fn custom_runner() {
 [RUNNING] `nonexistent-runner -r [..][/]target[/]release[/]deps[/]bench-[..][EXE] --param --bench`
 ", url = p.url())));
 }
+
+#[test]
+fn post_link_commands() {
+    let target = rustc_host();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            post-link = ["nonexistent-post-link -x"]
+        "#, target))
+        .build();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[RUNNING] `nonexistent-post-link -x [..][/]target[/]debug[/]deps[/]foo[EXE]`")
+                       .with_stderr_contains("\
+[ERROR] post-link command `nonexistent-post-link -x` failed for `foo`"));
+}
+
+#[cfg(unix)]
+#[test]
+fn post_link_commands_rebuild_when_changed() {
+    let target = rustc_host();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .file(".cargo/config", &format!(r#"
+            [target.{}]
+            post-link = ["true"]
+        "#, target))
+        .build();
+
+    // Only the binary is linked, the rlib is left alone.
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `true [..]foo[EXE]`")
+                       .with_stderr_does_not_contain("[RUNNING] `true [..]libfoo[..]`"));
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stderr_does_not_contain("[RUNNING] `true [..]`"));
+
+    assert_that(p.cargo("build").arg("-v").arg("--config")
+                 .arg(&format!("target.{}.post-link=[\"true -q\"]", target)),
+                execs().with_status(0)
+                       .with_stderr_contains("[RUNNING] `true -q [..]foo[EXE]`"));
+}
@@ -0,0 +1,134 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use cargotest::support::registry::Package;
+use cargotest::support::{project, execs};
+use hamcrest::assert_that;
+
+#[test]
+fn simple() {
+    Package::new("c", "1.0.0").publish();
+    Package::new("b", "1.0.0").dep("c", "1.0").publish();
+    Package::new("a", "1.0.0").dep("b", "1.0").dep("c", "1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+            b = "1.0"
+
+            [dev-dependencies]
+            c = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree"),
+                execs().with_status(0).with_stdout("\
+foo v0.1.0 ([..])
+├── a v1.0.0
+│   ├── b v1.0.0
+│   │   └── c v1.0.0
+│   └── c v1.0.0
+├── b v1.0.0 (*)
+└── c v1.0.0
+"));
+}
+
+#[test]
+fn duplicates() {
+    Package::new("c", "1.0.0").publish();
+    Package::new("c", "2.0.0").publish();
+    Package::new("b", "1.0.0").dep("c", "2.0").publish();
+    Package::new("a", "1.0.0").dep("b", "1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+            c = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("--duplicates"),
+                execs().with_status(0).with_stdout("\
+c v1.0.0
+└── foo v0.1.0 ([..])
+
+c v2.0.0
+└── b v1.0.0
+    └── a v1.0.0
+        └── foo v0.1.0 ([..])
+"));
+}
+
+#[test]
+fn no_duplicates() {
+    Package::new("a", "1.0.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("-d"),
+                execs().with_status(0).with_stdout(""));
+}
+
+#[test]
+fn invert() {
+    Package::new("c", "1.0.0").publish();
+    Package::new("b", "1.0.0").dep("c", "1.0").publish();
+    Package::new("a", "1.0.0").dep("b", "1.0").publish();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            a = "1.0"
+
+            [target.'cfg(unix)'.build-dependencies]
+            c = "1.0"
+
+            [dev-dependencies]
+            c = "1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("tree").arg("-i").arg("c"),
+                execs().with_status(0).with_stdout("\
+c v1.0.0
+├── b v1.0.0 [normal]
+│   └── a v1.0.0 [normal]
+│       └── foo v0.1.0 ([..]) [normal]
+└── foo v0.1.0 ([..]) [dev, build for cfg(unix)]
+"));
+
+    assert_that(p.cargo("tree").arg("--invert").arg("nope"),
+                execs().with_status(101).with_stderr_contains("\
+[ERROR] package id specification `nope` matched no packages"));
+}
@@ -0,0 +1,137 @@
+extern crate cargotest;
+extern crate hamcrest;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use cargotest::support::paths;
+use cargotest::support::registry::Package;
+use cargotest::support::{git, project, execs};
+use hamcrest::assert_that;
+
+fn cached_crate(name: &str) -> PathBuf {
+    let cache = paths::home().join(".cargo/registry/cache");
+    let dir = fs::read_dir(&cache).unwrap().next().unwrap().unwrap().path();
+    dir.join(name)
+}
+
+#[test]
+fn no_lockfile() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("verify"),
+                execs().with_status(101).with_stderr("\
+[ERROR] a Cargo.lock must exist before the cache can be verified, run \
+`cargo generate-lockfile` first
+"));
+}
+
+#[test]
+fn clean_cache() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+    assert_that(p.cargo("verify"),
+                execs().with_status(0).with_stdout("").with_stderr("\
+[..]Verified all locked dependencies in the cache
+"));
+}
+
+#[test]
+fn corrupt_tarball() {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/lib.rs", "")
+        .build();
+
+    Package::new("bar", "0.1.0").publish();
+
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+    File::create(cached_crate("bar-0.1.0.crate")).unwrap()
+        .write_all(b"not a tarball").unwrap();
+
+    assert_that(p.cargo("verify"),
+                execs().with_status(101)
+                       .with_stdout_contains("    remove `[..]bar-0.1.0.crate`")
+                       .with_stderr_contains("\
+[WARNING] the downloaded archive for `bar v0.1.0` does not match its checksum
+"));
+
+    assert_that(p.cargo("verify").arg("--repair"),
+                execs().with_status(0)
+                       .with_stderr_contains("[DOWNLOADING] bar v0.1.0 [..]"));
+    assert_that(p.cargo("verify"), execs().with_status(0));
+}
+
+#[test]
+fn modified_git_checkout() {
+    let bar = git::new("bar", |project| {
+        project.file("Cargo.toml", r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = {{ git = '{}' }}
+        "#, bar.url()))
+        .file("src/lib.rs", "")
+        .build();
+
+    assert_that(p.cargo("fetch"), execs().with_status(0));
+
+    let checkout = git::checkouts().remove(0);
+    let lib = checkout.join("src/lib.rs");
+    let mut perms = fs::metadata(&lib).unwrap().permissions();
+    perms.set_readonly(false);
+    fs::set_permissions(&lib, perms).unwrap();
+    File::create(&lib).unwrap().write_all(b"pub fn modified() {}").unwrap();
+
+    assert_that(p.cargo("verify"),
+                execs().with_status(101)
+                       .with_stderr_contains("\
+[WARNING] the checkout of `[..]bar` at `[..]` does not match revision [..]
+"));
+    assert_that(p.cargo("verify").arg("--repair"), execs().with_status(0));
+    assert_that(p.cargo("verify"), execs().with_status(0));
+}